@@ -1,3 +1,51 @@
+use std::process::Command;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
 fn main() {
     println!("cargo:rerun-if-changed=migrations");
+
+    // build info for `GET /api/version`
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    println!("cargo:rerun-if-env-changed=SHURLY_GIT_SHA");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    // the Docker build has no `.git`, the SHA can be passed as build argument instead
+    let git_sha = std::env::var("SHURLY_GIT_SHA")
+        .ok()
+        .filter(|git_sha| !git_sha.is_empty())
+        .or_else(|| {
+            Command::new("git")
+                .args(["rev-parse", "HEAD"])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .and_then(|output| String::from_utf8(output.stdout).ok())
+                .map(|git_sha| git_sha.trim().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=SHURLY_GIT_SHA={git_sha}");
+
+    // reproducible builds set their own timestamp
+    let build_timestamp = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|timestamp| timestamp.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default()
+        });
+    println!("cargo:rustc-env=SHURLY_BUILD_TIMESTAMP={build_timestamp}");
+
+    let mut features = std::env::vars()
+        .filter_map(|(name, _)| {
+            name.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_lowercase().replace('_', "-"))
+        })
+        .collect::<Vec<_>>();
+    features.sort();
+    println!("cargo:rustc-env=SHURLY_FEATURES={}", features.join(","));
 }
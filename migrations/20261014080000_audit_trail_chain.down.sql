@@ -0,0 +1,6 @@
+DROP INDEX audit_trail_sequence;
+
+ALTER TABLE audit_trail
+    DROP COLUMN hash,
+    DROP COLUMN previous_hash,
+    DROP COLUMN sequence;
@@ -0,0 +1,6 @@
+ALTER TABLE audit_trail
+    ADD COLUMN sequence BIGSERIAL,
+    ADD COLUMN previous_hash VARCHAR,
+    ADD COLUMN hash VARCHAR;
+
+CREATE UNIQUE INDEX audit_trail_sequence ON audit_trail (sequence);
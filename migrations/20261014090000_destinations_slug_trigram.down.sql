@@ -0,0 +1,3 @@
+DROP INDEX destinations_slug_trigram;
+
+DROP EXTENSION IF EXISTS pg_trgm;
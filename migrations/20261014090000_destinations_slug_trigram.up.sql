@@ -0,0 +1,3 @@
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX destinations_slug_trigram ON destinations USING gin (slug gin_trgm_ops);
@@ -0,0 +1,2 @@
+ALTER TABLE destinations
+    ADD COLUMN is_meta_refresh BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,4 @@
+ALTER TABLE destinations
+    DROP COLUMN og_image,
+    DROP COLUMN og_description,
+    DROP COLUMN og_title;
@@ -0,0 +1,4 @@
+ALTER TABLE destinations
+    ADD COLUMN og_title VARCHAR,
+    ADD COLUMN og_description VARCHAR,
+    ADD COLUMN og_image VARCHAR;
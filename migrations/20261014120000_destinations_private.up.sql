@@ -0,0 +1,2 @@
+ALTER TABLE destinations
+    ADD COLUMN is_private BOOLEAN NOT NULL DEFAULT FALSE;
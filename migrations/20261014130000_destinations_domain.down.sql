@@ -0,0 +1,9 @@
+DROP INDEX destinations_slug;
+DROP INDEX destinations_domain_slug;
+
+-- fails when the same slug is used on multiple domains, those need to be cleaned up first
+ALTER TABLE destinations
+    ADD CONSTRAINT single_slug UNIQUE (slug);
+
+ALTER TABLE destinations
+    DROP COLUMN domain;
@@ -0,0 +1,9 @@
+ALTER TABLE destinations
+    ADD COLUMN domain VARCHAR;
+
+ALTER TABLE destinations
+    DROP CONSTRAINT single_slug;
+
+-- slugs are unique per domain, destinations without a domain are served on all domains
+CREATE UNIQUE INDEX destinations_domain_slug ON destinations (domain, slug) WHERE domain IS NOT NULL;
+CREATE UNIQUE INDEX destinations_slug ON destinations (slug) WHERE domain IS NULL;
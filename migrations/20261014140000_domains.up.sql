@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS domains (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id),
+    hostname VARCHAR NOT NULL,
+    fallback_url VARCHAR,
+    not_found_template VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP,
+    CONSTRAINT single_hostname UNIQUE (hostname)
+);
+
+ALTER TYPE audit_trail_entry_type ADD VALUE 'create-domain';
+ALTER TYPE audit_trail_entry_type ADD VALUE 'update-domain';
+ALTER TYPE audit_trail_entry_type ADD VALUE 'delete-domain';
+
+ALTER TABLE audit_trail
+    ADD COLUMN domain_id UUID REFERENCES domains(id);
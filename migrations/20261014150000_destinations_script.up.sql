@@ -0,0 +1,2 @@
+ALTER TABLE destinations
+    ADD COLUMN script VARCHAR;
@@ -0,0 +1,7 @@
+-- daily number of hits per destination, kept when the hits themselves are pruned
+CREATE TABLE IF NOT EXISTS hit_rollups (
+    destination_id UUID NOT NULL REFERENCES destinations(id),
+    day DATE NOT NULL,
+    hits BIGINT NOT NULL,
+    PRIMARY KEY (destination_id, day)
+);
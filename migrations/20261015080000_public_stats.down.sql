@@ -0,0 +1,5 @@
+ALTER TABLE hits
+    DROP COLUMN referrer;
+
+ALTER TABLE destinations
+    DROP COLUMN is_stats_public;
@@ -0,0 +1,6 @@
+ALTER TABLE destinations
+    ADD COLUMN is_stats_public BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- host of the referring page, for the referrers of the stats
+ALTER TABLE hits
+    ADD COLUMN referrer VARCHAR;
@@ -0,0 +1,3 @@
+-- only completed days are rolled up by the job
+DELETE FROM hit_rollups
+WHERE day >= CURRENT_DATE;
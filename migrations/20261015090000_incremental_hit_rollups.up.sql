@@ -0,0 +1,7 @@
+-- the rollups are kept up to date with every saved hit, count the days that are not rolled up yet
+INSERT INTO hit_rollups (destination_id, day, hits)
+SELECT destination_id, created_at::date, COUNT(*)
+FROM hits
+WHERE created_at >= COALESCE((SELECT MAX(day) FROM hit_rollups), '-infinity')
+GROUP BY destination_id, created_at::date
+ON CONFLICT (destination_id, day) DO UPDATE SET hits = GREATEST(hit_rollups.hits, EXCLUDED.hits);
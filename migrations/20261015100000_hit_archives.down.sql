@@ -0,0 +1,2 @@
+DROP TABLE archival_runs;
+DROP TABLE hit_archives;
@@ -0,0 +1,25 @@
+-- hits moved out of `hits`, per destination per month; Postgres compresses the big values
+CREATE TABLE IF NOT EXISTS hit_archives (
+    id UUID PRIMARY KEY,
+    destination_id UUID NOT NULL REFERENCES destinations(id),
+    month DATE NOT NULL,
+    hits BIGINT NOT NULL,
+    data JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX hit_archives_destination_id_month ON hit_archives (destination_id, month);
+
+-- every archival of hits, by the job or triggered by an admin
+CREATE TABLE IF NOT EXISTS archival_runs (
+    id UUID PRIMARY KEY,
+    user_id UUID REFERENCES users(id),
+    months INTEGER NOT NULL,
+    hits BIGINT,
+    archives BIGINT,
+    error VARCHAR,
+    started_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished_at TIMESTAMP
+);
+
+CREATE INDEX archival_runs_started_at ON archival_runs (started_at);
@@ -0,0 +1,5 @@
+DROP TABLE webhook_hits;
+
+ALTER TABLE webhooks
+    DROP COLUMN destination_id,
+    DROP COLUMN is_batched;
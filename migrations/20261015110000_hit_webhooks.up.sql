@@ -0,0 +1,17 @@
+-- webhooks of a single destination receive its hits instead of the changes
+ALTER TABLE webhooks
+    ADD COLUMN destination_id UUID REFERENCES destinations(id),
+    ADD COLUMN is_batched BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX webhooks_destination_id ON webhooks (destination_id)
+    WHERE destination_id IS NOT NULL AND deleted_at IS NULL;
+
+-- hits waiting to be queued as deliveries, right away or every minute when batched
+CREATE TABLE IF NOT EXISTS webhook_hits (
+    id UUID PRIMARY KEY,
+    webhook_id UUID NOT NULL REFERENCES webhooks(id),
+    hit JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX webhook_hits_webhook_id ON webhook_hits (webhook_id);
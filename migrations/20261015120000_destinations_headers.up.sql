@@ -0,0 +1,3 @@
+-- extra headers of the redirect, a JSON object of names and values
+ALTER TABLE destinations
+    ADD COLUMN headers VARCHAR;
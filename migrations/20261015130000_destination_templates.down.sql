@@ -0,0 +1,4 @@
+DROP TABLE destination_templates;
+
+ALTER TABLE destinations
+    DROP COLUMN tags;
@@ -0,0 +1,20 @@
+-- tags to group destinations by
+ALTER TABLE destinations
+    ADD COLUMN tags VARCHAR[] NOT NULL DEFAULT '{}';
+
+-- reusable settings for new destinations, referenced by name
+CREATE TABLE IF NOT EXISTS destination_templates (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id),
+    name VARCHAR NOT NULL,
+    domain VARCHAR,
+    is_permanent BOOLEAN,
+    tags VARCHAR[] NOT NULL DEFAULT '{}',
+    utm_source VARCHAR,
+    utm_medium VARCHAR,
+    utm_campaign VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP,
+    CONSTRAINT single_template_name UNIQUE (name)
+);
@@ -0,0 +1,9 @@
+DROP INDEX notes_deleted_at;
+DROP INDEX destinations_deleted_at;
+
+ALTER TABLE audit_trail
+    ADD CONSTRAINT audit_trail_created_by_fkey FOREIGN KEY (created_by) REFERENCES users(id),
+    ADD CONSTRAINT audit_trail_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id),
+    ADD CONSTRAINT audit_trail_destination_id_fkey
+        FOREIGN KEY (destination_id) REFERENCES destinations(id),
+    ADD CONSTRAINT audit_trail_note_id_fkey FOREIGN KEY (note_id) REFERENCES notes(id);
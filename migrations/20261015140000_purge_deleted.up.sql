@@ -0,0 +1,9 @@
+-- the audit trail keeps the IDs of purged records, its hashes cover those
+ALTER TABLE audit_trail
+    DROP CONSTRAINT audit_trail_created_by_fkey,
+    DROP CONSTRAINT audit_trail_user_id_fkey,
+    DROP CONSTRAINT audit_trail_destination_id_fkey,
+    DROP CONSTRAINT audit_trail_note_id_fkey;
+
+CREATE INDEX destinations_deleted_at ON destinations (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX notes_deleted_at ON notes (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,29 @@
+-- removes the flag entries from the audit trail, breaking the chain when there are any
+DELETE FROM audit_trail
+WHERE type = 'flag-destination';
+
+ALTER TYPE audit_trail_entry_type RENAME TO audit_trail_entry_type_old;
+
+CREATE TYPE audit_trail_entry_type AS ENUM(
+    'create-user',
+    'change-password',
+    'delete-user',
+    'create-destination',
+    'update-destination',
+    'delete-destination',
+    'create-note',
+    'update-note',
+    'delete-note',
+    'create-domain',
+    'update-domain',
+    'delete-domain'
+);
+
+ALTER TABLE audit_trail
+    ALTER COLUMN type TYPE audit_trail_entry_type USING type::text::audit_trail_entry_type;
+
+DROP TYPE audit_trail_entry_type_old;
+
+ALTER TABLE destinations
+    DROP COLUMN flagged_reason,
+    DROP COLUMN flagged_at;
@@ -0,0 +1,5 @@
+ALTER TABLE destinations
+    ADD COLUMN flagged_at TIMESTAMP,
+    ADD COLUMN flagged_reason VARCHAR;
+
+ALTER TYPE audit_trail_entry_type ADD VALUE 'flag-destination';
@@ -0,0 +1,18 @@
+-- reports of abuse by visitors, open until disabled or dismissed by an admin
+CREATE TABLE IF NOT EXISTS abuse_reports (
+    id UUID PRIMARY KEY,
+    destination_id UUID NOT NULL REFERENCES destinations(id),
+    reason VARCHAR NOT NULL,
+    contact VARCHAR,
+    ip_address INET,
+    resolved_by UUID REFERENCES users(id),
+    disabled_at TIMESTAMP,
+    dismissed_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX abuse_reports_open ON abuse_reports (created_at)
+    WHERE disabled_at IS NULL AND dismissed_at IS NULL;
+
+CREATE INDEX abuse_reports_destination_id ON abuse_reports (destination_id);
@@ -0,0 +1,32 @@
+-- removes the reviews from the audit trail, breaking the chain when there are any
+DELETE FROM audit_trail
+WHERE type IN ('approve-destination', 'reject-destination');
+
+ALTER TYPE audit_trail_entry_type RENAME TO audit_trail_entry_type_old;
+
+CREATE TYPE audit_trail_entry_type AS ENUM(
+    'create-user',
+    'change-password',
+    'delete-user',
+    'create-destination',
+    'update-destination',
+    'delete-destination',
+    'create-note',
+    'update-note',
+    'delete-note',
+    'create-domain',
+    'update-domain',
+    'delete-domain',
+    'flag-destination'
+);
+
+ALTER TABLE audit_trail
+    ALTER COLUMN type TYPE audit_trail_entry_type USING type::text::audit_trail_entry_type;
+
+DROP TYPE audit_trail_entry_type_old;
+
+ALTER TABLE destinations
+    DROP COLUMN reviewed_by,
+    DROP COLUMN rejected_at,
+    DROP COLUMN approved_at,
+    DROP COLUMN submitted_at;
@@ -0,0 +1,9 @@
+-- destinations created by managers can wait for the approval of an admin
+ALTER TABLE destinations
+    ADD COLUMN submitted_at TIMESTAMP,
+    ADD COLUMN approved_at TIMESTAMP,
+    ADD COLUMN rejected_at TIMESTAMP,
+    ADD COLUMN reviewed_by UUID REFERENCES users(id);
+
+ALTER TYPE audit_trail_entry_type ADD VALUE 'approve-destination';
+ALTER TYPE audit_trail_entry_type ADD VALUE 'reject-destination';
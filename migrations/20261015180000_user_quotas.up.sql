@@ -0,0 +1,10 @@
+-- quotas of single users, overriding the quotas of their role
+CREATE TABLE IF NOT EXISTS user_quotas (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    max_destinations BIGINT,
+    max_hits_per_month BIGINT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+ALTER TYPE audit_trail_entry_type ADD VALUE 'change-quota';
@@ -0,0 +1,21 @@
+-- counters per user per month, for chargeback and capacity planning
+CREATE TABLE IF NOT EXISTS user_usage (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    month DATE NOT NULL,
+    destinations_created BIGINT NOT NULL DEFAULT 0,
+    redirects BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, month)
+);
+
+-- the history so far, the rollups include the pruned hits
+INSERT INTO user_usage (user_id, month, destinations_created)
+SELECT user_id, date_trunc('month', created_at)::date, COUNT(*)
+FROM destinations
+GROUP BY user_id, date_trunc('month', created_at)::date;
+
+INSERT INTO user_usage (user_id, month, redirects)
+SELECT destinations.user_id, date_trunc('month', hit_rollups.day)::date, SUM(hit_rollups.hits)
+FROM hit_rollups
+INNER JOIN destinations ON destinations.id = hit_rollups.destination_id
+GROUP BY destinations.user_id, date_trunc('month', hit_rollups.day)::date
+ON CONFLICT (user_id, month) DO UPDATE SET redirects = EXCLUDED.redirects;
@@ -0,0 +1,13 @@
+-- slugs reserved for upcoming destinations, only their reserver can use them
+CREATE TABLE IF NOT EXISTS slug_reservations (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    slug TEXT NOT NULL,
+    domain TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- like the destinations, slugs are reserved per domain
+CREATE UNIQUE INDEX slug_reservations_domain_slug ON slug_reservations (domain, slug) WHERE domain IS NOT NULL;
+CREATE UNIQUE INDEX slug_reservations_slug ON slug_reservations (slug) WHERE domain IS NULL;
+CREATE INDEX slug_reservations_user_id ON slug_reservations (user_id);
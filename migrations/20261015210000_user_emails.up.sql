@@ -0,0 +1,4 @@
+-- email addresses of users, for the notifications
+ALTER TABLE users ADD COLUMN email VARCHAR(255);
+
+ALTER TYPE audit_trail_entry_type ADD VALUE 'change-email';
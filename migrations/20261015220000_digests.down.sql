@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS missed_slugs;
+DROP TABLE IF EXISTS digest_subscriptions;
@@ -0,0 +1,19 @@
+-- subscriptions to the weekly digest of the traffic of the destinations of users
+CREATE TABLE IF NOT EXISTS digest_subscriptions (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    last_sent_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- slugs without a destination that visitors tried, for the digests of admins
+CREATE TABLE IF NOT EXISTS missed_slugs (
+    domain TEXT,
+    slug TEXT NOT NULL,
+    hits BIGINT NOT NULL DEFAULT 1,
+    first_seen_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_seen_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- a single counter per slug and domain, all domains share the empty domain
+CREATE UNIQUE INDEX missed_slugs_domain_slug ON missed_slugs ((COALESCE(domain, '')), slug);
+CREATE INDEX missed_slugs_last_seen_at ON missed_slugs (last_seen_at);
@@ -0,0 +1,2 @@
+ALTER TABLE destinations
+    DROP COLUMN source;
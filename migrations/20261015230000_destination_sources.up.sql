@@ -0,0 +1,3 @@
+-- channel the short link is handed out in, like a printed flyer
+ALTER TABLE destinations
+    ADD COLUMN source VARCHAR;
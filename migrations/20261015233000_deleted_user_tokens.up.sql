@@ -0,0 +1,2 @@
+-- attempts to use the tokens of deleted users
+ALTER TYPE audit_trail_entry_type ADD VALUE 'deleted-user-token';
@@ -0,0 +1,3 @@
+-- destinations that never record their hits, like privacy-sensitive internal links
+ALTER TABLE destinations
+    ADD COLUMN is_untracked BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,83 @@
+//! Abuse reports of destinations
+//!
+//! Visitors report the abuse of a short link with `POST /report/<slug>`, these end up in the
+//! queue of `GET /api/abuse-reports`. Admins review the reports: disabling the destination marks
+//! all its open reports as handled, dismissing a report leaves the destination alone. Disabled
+//! destinations respond with a `410 Gone`, like the [flagged](crate::reputation) ones, and are on
+//! the audit trail. Updating the URL enables the destination again.
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Maximum length of the reason of a report, in characters
+pub const MAX_REASON_LENGTH: usize = 2000;
+
+/// Maximum length of the contact details of a reporter, in characters
+pub const MAX_CONTACT_LENGTH: usize = 200;
+
+/// A report of abuse of a destination
+#[derive(Clone, Debug)]
+pub struct AbuseReport {
+    /// Report ID
+    pub id: Uuid,
+
+    /// The reported destination
+    pub destination_id: Uuid,
+
+    /// Why the destination is reported, in the words of the reporter
+    pub reason: String,
+
+    /// How to reach the reporter, like an email address
+    pub contact: Option<String>,
+
+    /// IP address of the reporter
+    pub ip_address: Option<String>,
+
+    /// The admin that disabled the destination or dismissed the report
+    pub resolved_by: Option<Uuid>,
+
+    /// Moment the destination was disabled
+    pub disabled_at: Option<NaiveDateTime>,
+
+    /// Moment the report was dismissed
+    pub dismissed_at: Option<NaiveDateTime>,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+
+    /// Last updated at
+    pub updated_at: NaiveDateTime,
+}
+
+/// Status of a report
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReportStatus {
+    /// Waiting for review
+    Open,
+
+    /// The destination is disabled
+    Disabled,
+
+    /// Reviewed, the destination is left alone
+    Dismissed,
+}
+
+impl AbuseReport {
+    /// Status of the report
+    pub fn status(&self) -> ReportStatus {
+        if self.disabled_at.is_some() {
+            ReportStatus::Disabled
+        } else if self.dismissed_at.is_some() {
+            ReportStatus::Dismissed
+        } else {
+            ReportStatus::Open
+        }
+    }
+
+    /// Reason the destination is disabled for, shown as its `flaggedReason`
+    pub fn flagged_reason(&self) -> String {
+        format!("Reported as abuse: {}", self.reason)
+    }
+}
@@ -0,0 +1,28 @@
+//! Usage accounting of users
+//!
+//! Counters per user per month: the destinations the user created and the redirects served for
+//! the destinations of the user. These are counted as they happen, destinations that are deleted
+//! later on stay counted; groundwork for chargeback and capacity planning. Imported destinations
+//! and hits are not counted.
+
+use chrono::NaiveDate;
+use uuid::Uuid;
+
+/// The counters of a user in a month
+#[derive(Clone, Debug)]
+pub struct UsageCounters {
+    /// The user
+    pub user_id: Uuid,
+
+    /// Username of the user
+    pub username: String,
+
+    /// First day of the month
+    pub month: NaiveDate,
+
+    /// Number of destinations created by the user
+    pub destinations_created: i64,
+
+    /// Number of redirects served for the destinations of the user
+    pub redirects: i64,
+}
@@ -0,0 +1,66 @@
+//! Live activity of the admins, the changes on the audit trail as they happen
+//!
+//! Only the entries registered by this instance are part of its activity, the subscribers get
+//! nothing of the changes made via other instances. Slow subscribers miss the oldest entries
+//! instead of holding up the changes.
+
+use std::sync::Arc;
+
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::webhooks::Event;
+
+/// Number of entries kept for slow subscribers
+const CAPACITY: usize = 256;
+
+/// An entry of the activity
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActivityEntry {
+    /// ID of the entry on the audit trail
+    pub id: Uuid,
+
+    /// Type of the entry, like `create-destination`
+    pub event: String,
+
+    /// The JSON body of the entry, the same as the webhooks get
+    pub payload: String,
+}
+
+/// Broadcast of the activity, clones share the same subscribers
+#[derive(Clone, Debug)]
+pub struct Activity {
+    /// Sender of the entries to all subscribers
+    sender: broadcast::Sender<Arc<ActivityEntry>>,
+}
+
+impl Default for Activity {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(CAPACITY);
+
+        Self { sender }
+    }
+}
+
+impl Activity {
+    /// Publish a change of the audit trail to the current subscribers
+    pub fn publish(&self, event: &Event<'_>) {
+        if self.sender.receiver_count() == 0 {
+            return;
+        }
+
+        let entry = ActivityEntry {
+            id: event.id(),
+            event: event.event().to_string(),
+            payload: event.payload(),
+        };
+
+        // the subscribers can be gone in the meantime, nobody to tell in that case
+        self.sender.send(Arc::new(entry)).ok();
+    }
+
+    /// Subscribe to the entries published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<ActivityEntry>> {
+        self.sender.subscribe()
+    }
+}
@@ -0,0 +1,322 @@
+//! Abuse reports API endpoints
+//!
+//! The public endpoint for visitors to report abuse, and the queue of reports for the admins
+
+use axum::http::HeaderMap;
+use axum::Extension;
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::abuse::AbuseReport;
+use crate::abuse::ReportStatus;
+use crate::abuse::MAX_CONTACT_LENGTH;
+use crate::abuse::MAX_REASON_LENGTH;
+use crate::client_ip::ClientIp;
+use crate::database::AuditEntry;
+use crate::database::CreateAbuseReportValues;
+use crate::database::Database;
+use crate::domains;
+use crate::permissions::Action;
+use crate::root::Settings as RootSettings;
+
+use super::AuditTrail;
+use super::CurrentUser;
+use super::Error;
+use super::Form;
+use super::PathParameters;
+use super::Success;
+
+/// Abuse report response going to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AbuseReportResponse {
+    /// Report ID
+    pub id: Uuid,
+
+    /// The reported destination
+    pub destination_id: Uuid,
+
+    /// Why the destination is reported
+    pub reason: String,
+
+    /// How to reach the reporter
+    pub contact: Option<String>,
+
+    /// IP address of the reporter
+    pub ip_address: Option<String>,
+
+    /// Status of the report
+    pub status: ReportStatus,
+
+    /// The admin that disabled the destination or dismissed the report
+    pub resolved_by: Option<Uuid>,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+
+    /// Last updated at
+    pub updated_at: NaiveDateTime,
+}
+
+impl AbuseReportResponse {
+    /// Create a response from an [`AbuseReport`](AbuseReport)
+    fn from_abuse_report(abuse_report: AbuseReport) -> Self {
+        Self {
+            id: abuse_report.id,
+            status: abuse_report.status(),
+            destination_id: abuse_report.destination_id,
+            reason: abuse_report.reason,
+            contact: abuse_report.contact,
+            ip_address: abuse_report.ip_address,
+            resolved_by: abuse_report.resolved_by,
+            created_at: abuse_report.created_at,
+            updated_at: abuse_report.updated_at,
+        }
+    }
+}
+
+/// Report abuse form
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportAbuseForm {
+    /// Why the destination is reported
+    reason: String,
+
+    /// How to reach the reporter, like an email address
+    contact: Option<String>,
+}
+
+/// Report abuse of the destination of a slug, on the domain of the request
+///
+/// Public, without a token; limited by the rate limit of the root and a fixed limit of reports
+/// per IP address. The response tells nothing about the destination or the report.
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -d '{ "reason": "Phishing for passwords", "contact": "jane@example.com" }' \
+///     http://localhost:7000/report/some-easy-name
+/// ```
+pub async fn report(
+    headers: HeaderMap,
+    ip_address: Option<ClientIp>,
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    PathParameters(slug): PathParameters<String>,
+    Form(form): Form<ReportAbuseForm>,
+) -> Result<Success<&'static str>, Error> {
+    if let Some(ClientIp(ip_address)) = ip_address {
+        let visitor = root_settings.client_ip.visitor(ip_address);
+        if root_settings.rate_limit.check(visitor).is_err()
+            || root_settings.report_limit.check(visitor).is_err()
+        {
+            return Err(Error::too_many_requests("Too many requests"));
+        }
+    }
+
+    let reason = form.reason.trim();
+    if reason.is_empty() {
+        return Err(Error::bad_request("Reason can not be empty"));
+    }
+    if reason.chars().count() > MAX_REASON_LENGTH {
+        return Err(Error::bad_request("Reason is too long")
+            .with_description(format!("Up to {MAX_REASON_LENGTH} characters")));
+    }
+
+    let contact = form
+        .contact
+        .as_deref()
+        .map(str::trim)
+        .filter(|contact| !contact.is_empty());
+    if contact.is_some_and(|contact| contact.chars().count() > MAX_CONTACT_LENGTH) {
+        return Err(Error::bad_request("Contact is too long")
+            .with_description(format!("Up to {MAX_CONTACT_LENGTH} characters")));
+    }
+
+    let domain = domains::from_headers(&headers);
+
+    let destination = database
+        .find_single_destination_by_slug(domain.as_deref(), &slug)
+        .await
+        .map_err(Error::internal_server_error)?
+        .filter(|destination| !destination.is_deleted())
+        .ok_or_else(|| Error::not_found("Destination not found"))?;
+
+    let values = CreateAbuseReportValues {
+        destination: &destination,
+        reason,
+        contact,
+        ip_address: ip_address.as_ref().map(|ip_address| &ip_address.0),
+    };
+
+    database
+        .create_abuse_report(&values)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    tracing::info!(r#"Slug "{slug}" is reported as abuse"#);
+
+    Ok(Success::<&'static str>::no_content())
+}
+
+/// List the open abuse reports, the oldest first
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/abuse-reports
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": [ { "id": "<uuid>", "destinationId": "<uuid>", "status": "open" ... } ] }
+/// ```
+pub async fn list(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+) -> Result<Success<Vec<AbuseReportResponse>>, Error> {
+    current_user.is_allowed(Action::ReviewDestinations)?;
+
+    let abuse_reports = database
+        .find_open_abuse_reports()
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Success::ok(
+        abuse_reports
+            .into_iter()
+            .map(AbuseReportResponse::from_abuse_report)
+            .collect(),
+    ))
+}
+
+/// Get a single abuse report, open or not
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/abuse-reports/<uuid>
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "id": "<uuid>", "destinationId": "<uuid>", "status": "open" ... } }
+/// ```
+pub async fn single(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    PathParameters(abuse_report_id): PathParameters<Uuid>,
+) -> Result<Success<AbuseReportResponse>, Error> {
+    current_user.is_allowed(Action::ReviewDestinations)?;
+
+    fetch_abuse_report(&database, &abuse_report_id)
+        .await
+        .map(|abuse_report| Success::ok(AbuseReportResponse::from_abuse_report(abuse_report)))
+}
+
+/// Disable the destination of an open abuse report, resolving all its open reports
+///
+/// The destination responds with a `410 Gone` until its URL is updated
+///
+/// Request:
+/// ```sh
+/// curl -v -XPOST \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/abuse-reports/<uuid>/disable
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "id": "<uuid>", "destinationId": "<uuid>", "status": "disabled" ... } }
+/// ```
+pub async fn disable(
+    audit_trail: AuditTrail,
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+    PathParameters(abuse_report_id): PathParameters<Uuid>,
+) -> Result<Success<AbuseReportResponse>, Error> {
+    current_user.is_allowed(Action::ReviewDestinations)?;
+
+    let abuse_report = fetch_open_abuse_report(&database, &abuse_report_id).await?;
+
+    let destination = database
+        .disable_reported_destination(&abuse_report, &current_user)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    root_settings
+        .slug_cache
+        .invalidate(&database, &destination.slug)
+        .await;
+
+    audit_trail
+        .register(AuditEntry::FlagDestination(&destination))
+        .await;
+
+    fetch_abuse_report(&database, &abuse_report_id)
+        .await
+        .map(|abuse_report| Success::ok(AbuseReportResponse::from_abuse_report(abuse_report)))
+}
+
+/// Dismiss an open abuse report, leaving its destination alone
+///
+/// Request:
+/// ```sh
+/// curl -v -XPOST \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/abuse-reports/<uuid>/dismiss
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "id": "<uuid>", "destinationId": "<uuid>", "status": "dismissed" ... } }
+/// ```
+pub async fn dismiss(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    PathParameters(abuse_report_id): PathParameters<Uuid>,
+) -> Result<Success<AbuseReportResponse>, Error> {
+    current_user.is_allowed(Action::ReviewDestinations)?;
+
+    let abuse_report = fetch_open_abuse_report(&database, &abuse_report_id).await?;
+
+    database
+        .dismiss_abuse_report(&abuse_report, &current_user)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    fetch_abuse_report(&database, &abuse_report_id)
+        .await
+        .map(|abuse_report| Success::ok(AbuseReportResponse::from_abuse_report(abuse_report)))
+}
+
+/// Fetch abuse report from database
+async fn fetch_abuse_report(
+    database: &Database,
+    abuse_report_id: &Uuid,
+) -> Result<AbuseReport, Error> {
+    database
+        .find_single_abuse_report_by_id(abuse_report_id)
+        .await
+        .map_err(Error::internal_server_error)?
+        .map_or_else(|| Err(Error::not_found("Abuse report not found")), Ok)
+}
+
+/// Fetch abuse report from database, only when it is still open
+async fn fetch_open_abuse_report(
+    database: &Database,
+    abuse_report_id: &Uuid,
+) -> Result<AbuseReport, Error> {
+    let abuse_report = fetch_abuse_report(database, abuse_report_id).await?;
+
+    if abuse_report.status() == ReportStatus::Open {
+        Ok(abuse_report)
+    } else {
+        Err(Error::bad_request("Abuse report is already resolved"))
+    }
+}
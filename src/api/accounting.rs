@@ -0,0 +1,130 @@
+//! Usage accounting API endpoints
+//!
+//! The counters of the users per month, for chargeback and capacity planning
+
+use std::collections::HashMap;
+use std::ops::Deref;
+
+use axum::Extension;
+use chrono::NaiveDate;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::accounting::UsageCounters;
+use crate::database::Database;
+use crate::permissions::Action;
+
+use super::CurrentUser;
+use super::Error;
+use super::PathParameters;
+use super::Success;
+
+/// Usage counters response going to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageCountersResponse {
+    /// The user
+    pub user_id: Uuid,
+
+    /// Username of the user
+    pub username: String,
+
+    /// First day of the month
+    pub month: NaiveDate,
+
+    /// Number of destinations created by the user
+    pub destinations_created: i64,
+
+    /// Number of redirects served for the destinations of the user
+    pub redirects: i64,
+}
+
+impl UsageCountersResponse {
+    /// Create a response from [`UsageCounters`](UsageCounters)
+    fn from_usage_counters(usage_counters: UsageCounters) -> Self {
+        Self {
+            user_id: usage_counters.user_id,
+            username: usage_counters.username,
+            month: usage_counters.month,
+            destinations_created: usage_counters.destinations_created,
+            redirects: usage_counters.redirects,
+        }
+    }
+}
+
+/// List the usage counters of all users, the latest month first
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/usage
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": [ { "userId": "<uuid>", "month": "2026-10-01", "redirects": 4200 ... } ] }
+/// ```
+pub async fn list(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+) -> Result<Success<Vec<UsageCountersResponse>>, Error> {
+    current_user.is_allowed(Action::ManageSystem)?;
+
+    let usage_counters = database
+        .find_usage_counters(None)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Success::ok(
+        usage_counters
+            .into_iter()
+            .map(UsageCountersResponse::from_usage_counters)
+            .collect(),
+    ))
+}
+
+/// List the usage counters of a user or the current user, the latest month first
+///
+/// By passing `me` instead of a user ID, the counters of the current user are returned
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/usage/<uuid>
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": [ { "userId": "<uuid>", "month": "2026-10-01", "redirects": 4200 ... } ] }
+/// ```
+pub async fn single(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    PathParameters(params): PathParameters<HashMap<String, Uuid>>,
+) -> Result<Success<Vec<UsageCountersResponse>>, Error> {
+    let user = if let Some(user_id) = params.get("user") {
+        current_user.is_allowed(Action::ManageSystem)?;
+
+        database
+            .find_single_user_by_id(user_id)
+            .await
+            .map_err(Error::internal_server_error)?
+            .ok_or_else(|| Error::not_found("User not found"))?
+    } else {
+        current_user.deref().clone()
+    };
+
+    let usage_counters = database
+        .find_usage_counters(Some(&user))
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Success::ok(
+        usage_counters
+            .into_iter()
+            .map(UsageCountersResponse::from_usage_counters)
+            .collect(),
+    ))
+}
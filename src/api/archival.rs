@@ -0,0 +1,193 @@
+//! Archival API endpoints
+//!
+//! Trigger an archival of old hits and follow the runs, by the job or by an admin
+
+use axum::Extension;
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::archival;
+use crate::archival::ArchivalRun;
+use crate::archival::ArchivalStatus;
+use crate::database::Database;
+use crate::permissions::Action;
+use crate::root::Settings as RootSettings;
+
+use super::CurrentUser;
+use super::Error;
+use super::Form;
+use super::PathParameters;
+use super::Success;
+
+/// Number of runs in the run log
+const RUN_LOG_SIZE: i64 = 100;
+
+/// Archival run response going to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivalRunResponse {
+    /// Run ID
+    pub id: Uuid,
+
+    /// The admin that triggered the run, `null` for the job
+    pub user_id: Option<Uuid>,
+
+    /// Hits older than this number of months are archived
+    pub months: i32,
+
+    /// Status of the run
+    pub status: ArchivalStatus,
+
+    /// Number of archived hits, when succeeded
+    pub hits: Option<i64>,
+
+    /// Number of archives the hits are moved to, when succeeded
+    pub archives: Option<i64>,
+
+    /// Why the run failed
+    pub error: Option<String>,
+
+    /// Start of the run
+    pub started_at: NaiveDateTime,
+
+    /// End of the run, `null` while running
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+impl ArchivalRunResponse {
+    /// Create a response from an [`ArchivalRun`](ArchivalRun)
+    fn from_run(run: ArchivalRun) -> Self {
+        Self {
+            id: run.id,
+            status: run.status(),
+            user_id: run.user_id,
+            months: run.months,
+            hits: run.hits,
+            archives: run.archives,
+            error: run.error,
+            started_at: run.started_at,
+            finished_at: run.finished_at,
+        }
+    }
+}
+
+/// List the latest 100 archival runs, newest first
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/archival
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": [ { "id": "<uuid>", "months": 6, "status": "succeeded", "hits": 1234 ... } ] }
+/// ```
+pub async fn list(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+) -> Result<Success<Vec<ArchivalRunResponse>>, Error> {
+    current_user.is_allowed(Action::ManageSystem)?;
+
+    let runs = database
+        .find_latest_archival_runs(RUN_LOG_SIZE)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Success::ok(
+        runs.into_iter().map(ArchivalRunResponse::from_run).collect(),
+    ))
+}
+
+/// Get a single archival run, to follow it
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/archival/<uuid>
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "id": "<uuid>", "months": 6, "status": "running" ... } }
+/// ```
+pub async fn single(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    PathParameters(run_id): PathParameters<Uuid>,
+) -> Result<Success<ArchivalRunResponse>, Error> {
+    current_user.is_allowed(Action::ManageSystem)?;
+
+    database
+        .find_single_archival_run_by_id(&run_id)
+        .await
+        .map_err(Error::internal_server_error)?
+        .map_or_else(
+            || Err(Error::not_found("Archival run not found")),
+            |run| Ok(Success::ok(ArchivalRunResponse::from_run(run))),
+        )
+}
+
+/// Trigger archival form
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TriggerArchivalForm {
+    /// Hits older than this number of months are archived, defaults to `HIT_ARCHIVE_MONTHS`
+    months: Option<i32>,
+}
+
+/// Trigger an archival of the old hits based on the [`TriggerArchivalForm`](TriggerArchivalForm)
+/// form
+///
+/// The hits are archived in the background, the run is `running` until done
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     -d '{ "months": 6 }' \
+///     http://localhost:7000/api/archival
+/// ```
+///
+/// Response
+/// ```json
+/// { "data": { "id": "<uuid>", "months": 6, "status": "running" ... } }
+/// ```
+pub async fn trigger(
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+    Form(form): Form<TriggerArchivalForm>,
+) -> Result<Success<ArchivalRunResponse>, Error> {
+    current_user.is_allowed(Action::ManageSystem)?;
+
+    let months = form
+        .months
+        .or_else(|| root_settings.jobs.archive_months())
+        .ok_or_else(|| {
+            Error::bad_request("Missing number of months")
+                .with_description("Give the `months`, `HIT_ARCHIVE_MONTHS` is not set")
+        })?;
+
+    if months <= 0 {
+        return Err(Error::bad_request("Number of months must be positive"));
+    }
+
+    let run = database
+        .create_archival_run(months, Some(&current_user))
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    let background_run = run.clone();
+    tokio::spawn(async move {
+        if let Err(err) = archival::archive(&database, &background_run).await {
+            tracing::error!("Could not finish archival run {}: {err}", background_run.id);
+        }
+    });
+
+    Ok(Success::created(ArchivalRunResponse::from_run(run)))
+}
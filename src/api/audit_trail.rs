@@ -1,4 +1,6 @@
 //! Audit trail service
+//!
+//! Also hosts the endpoint to verify the chain of the audit trail
 
 use std::net::IpAddr;
 
@@ -7,13 +9,23 @@ use axum::extract::FromRequestParts;
 use axum::http::request::Parts;
 use axum::Extension;
 use axum::RequestPartsExt;
-use axum_client_ip::InsecureClientIp;
+use serde::Serialize;
+use uuid::Uuid;
 
+use crate::activity::Activity;
+use crate::audit_trail::verify as verify_chain;
+use crate::audit_trail::Verification;
+use crate::broker::Broker;
+use crate::client_ip::ClientIp;
 use crate::database::AuditEntry;
 use crate::database::Database;
+use crate::permissions::Action;
+use crate::root::Settings as RootSettings;
+use crate::webhooks::Event;
 
 use super::CurrentUser;
 use super::Error;
+use super::Success;
 
 /// Audit trail service
 pub struct AuditTrail {
@@ -25,18 +37,46 @@ pub struct AuditTrail {
 
     /// The IP address associated with the audit trail
     ip_address: Option<IpAddr>,
+
+    /// Publisher of the registered entries
+    broker: Broker,
+
+    /// Live activity, with the registered entries
+    activity: Activity,
 }
 
 impl AuditTrail {
-    /// Register an entry on the audit trail
+    /// Create the audit trail for a user, for requests without a token
+    pub fn new(
+        database: Database,
+        current_user: CurrentUser,
+        ip_address: Option<IpAddr>,
+        root_settings: &RootSettings,
+    ) -> Self {
+        Self {
+            database,
+            current_user,
+            ip_address,
+            broker: root_settings.broker.clone(),
+            activity: root_settings.activity.clone(),
+        }
+    }
+
+    /// Register an entry on the audit trail, and publish it to the broker and the activity
     pub async fn register(&self, entry: AuditEntry<'_>) {
         let result = self
             .database
             .register_audit_trail(&self.current_user, &entry, self.ip_address.as_ref())
             .await;
 
-        if let Err(err) = result {
-            tracing::error!("Could register audit trail entry: {err}");
+        match result {
+            Ok(audit_trail_entry) => {
+                let event = Event::new(&audit_trail_entry, &entry);
+
+                self.broker.publish_audit(&event);
+                self.activity.publish(&event);
+            }
+            Err(err) => tracing::error!("Could register audit trail entry: {err}"),
         }
     }
 }
@@ -56,15 +96,87 @@ where
 
         let current_user = CurrentUser::from_request_parts(parts, state).await?;
 
-        let ip_address = Option::<InsecureClientIp>::from_request_parts(parts, state)
+        let ip_address = Option::<ClientIp>::from_request_parts(parts, state)
             .await
             .map_err(|_| Error::internal_server_error("Missing address"))?
             .map(|i| i.0);
 
-        Ok(AuditTrail {
+        let Extension(root_settings) = parts
+            .extract::<Extension<RootSettings>>()
+            .await
+            .map_err(|_| Error::internal_server_error("Could not get the root settings"))?;
+
+        Ok(AuditTrail::new(
             database,
             current_user,
             ip_address,
-        })
+            &root_settings,
+        ))
+    }
+}
+
+/// Audit trail verification response going to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationResponse {
+    /// Is the complete chain valid?
+    pub is_valid: bool,
+
+    /// Number of entries with a verified hash
+    pub verified_entries: usize,
+
+    /// Number of entries created before the trail was chained
+    pub unchained_entries: usize,
+
+    /// The first entry that breaks the chain, if any
+    pub first_invalid_entry_id: Option<Uuid>,
+
+    /// Hash of the last verified entry
+    pub last_hash: Option<String>,
+}
+
+impl VerificationResponse {
+    /// Create a response from a [`Verification`](Verification)
+    fn from_verification(verification: Verification) -> Self {
+        Self {
+            is_valid: verification.is_valid(),
+            verified_entries: verification.verified_entries,
+            unchained_entries: verification.unchained_entries,
+            first_invalid_entry_id: verification.first_invalid_entry_id,
+            last_hash: verification.last_hash,
+        }
     }
 }
+
+/// Verify the chain of the audit trail
+///
+/// Every entry is hashed together with the hash of the previous entry, any edit after the fact
+/// will break the chain. Keep the `lastHash` somewhere safe to detect the removal of the latest
+/// entries as well.
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/audit-trail/verify
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "isValid": true, "verifiedEntries": 42, "lastHash": "<hash>" ... } }
+/// ```
+pub async fn verify(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+) -> Result<Success<VerificationResponse>, Error> {
+    current_user.is_allowed(Action::ManageSystem)?;
+
+    let entries = database
+        .find_all_audit_trail_entries()
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Success::ok(VerificationResponse::from_verification(
+        verify_chain(&entries),
+    )))
+}
@@ -0,0 +1,157 @@
+//! Authentication metrics API endpoints
+//!
+//! Insight in the outcomes of the authentication, to spot credential stuffing or misbehaving
+//! clients
+
+use axum::Extension;
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::auth_metrics::Statistics;
+use crate::database::Database;
+use crate::permissions::Action;
+use crate::root::Settings as RootSettings;
+use crate::users::DeletedUserToken;
+
+use super::CurrentUser;
+use super::Error;
+use super::Success;
+
+/// Authentication metrics response going to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatisticsResponse {
+    /// Number of issued tokens
+    pub tokens_issued: u64,
+
+    /// Number of logins with an unknown username or a wrong password
+    pub failed_logins: u64,
+
+    /// Number of requests without a token
+    pub missing_tokens: u64,
+
+    /// Number of requests with a token that can not be decoded, or of an unknown user
+    pub invalid_tokens: u64,
+
+    /// Number of requests with the token of a deleted user
+    pub deleted_user_tokens: u64,
+
+    /// Number of requests with an expired token, or of a session that ended
+    pub expired_sessions: u64,
+
+    /// Number of actions denied to the role of the user
+    pub denied_actions: u64,
+}
+
+impl StatisticsResponse {
+    /// Create a response from the [`Statistics`](Statistics)
+    fn from_statistics(statistics: Statistics) -> Self {
+        Self {
+            tokens_issued: statistics.tokens_issued,
+            failed_logins: statistics.failed_logins,
+            missing_tokens: statistics.missing_tokens,
+            invalid_tokens: statistics.invalid_tokens,
+            deleted_user_tokens: statistics.deleted_user_tokens,
+            expired_sessions: statistics.expired_sessions,
+            denied_actions: statistics.denied_actions,
+        }
+    }
+}
+
+/// Outcomes of the authentication, since startup of this instance
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/auth/metrics
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "tokensIssued": 42, "failedLogins": 3 ... } }
+/// ```
+pub async fn statistics(
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+) -> Result<Success<StatisticsResponse>, Error> {
+    current_user.is_allowed(Action::ManageSystem)?;
+
+    Ok(Success::ok(StatisticsResponse::from_statistics(
+        root_settings.auth_metrics.statistics(),
+    )))
+}
+
+/// Attempts to use the token of a deleted user response going to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedUserTokenResponse {
+    /// ID of the deleted user
+    pub user_id: Uuid,
+
+    /// Username of the deleted user
+    pub username: String,
+
+    /// IP address of the client presenting the token, when known
+    pub ip_address: Option<String>,
+
+    /// Number of attempts registered on the audit trail
+    pub attempts: i64,
+
+    /// Moment of the first registered attempt
+    pub first_attempt_at: NaiveDateTime,
+
+    /// Moment of the last registered attempt
+    pub last_attempt_at: NaiveDateTime,
+}
+
+impl DeletedUserTokenResponse {
+    /// Create a response from a [`DeletedUserToken`](DeletedUserToken)
+    fn from_deleted_user_token(deleted_user_token: DeletedUserToken) -> Self {
+        Self {
+            user_id: deleted_user_token.user_id,
+            username: deleted_user_token.username,
+            ip_address: deleted_user_token.ip_address,
+            attempts: deleted_user_token.attempts,
+            first_attempt_at: deleted_user_token.first_attempt_at,
+            last_attempt_at: deleted_user_token.last_attempt_at,
+        }
+    }
+}
+
+/// The tokens of deleted users still being presented, per user and IP address of the client, the
+/// most recent first
+///
+/// Tokens of deleted users are rejected, the attempts are registered on the audit trail. Attempts
+/// from the same IP address are registered once per 10 minutes per instance.
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/auth/deleted-user-tokens
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": [ { "username": "some-username", "ipAddress": "192.0.2.1", "attempts": 3 ... } ] }
+/// ```
+pub async fn deleted_user_tokens(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+) -> Result<Success<Vec<DeletedUserTokenResponse>>, Error> {
+    current_user.is_allowed(Action::ManageSystem)?;
+
+    let deleted_user_tokens = database
+        .find_deleted_user_tokens()
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Success::ok(
+        deleted_user_tokens
+            .into_iter()
+            .map(DeletedUserTokenResponse::from_deleted_user_token)
+            .collect(),
+    ))
+}
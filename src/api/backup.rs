@@ -0,0 +1,82 @@
+//! Backup API endpoints
+//!
+//! The archive is sent as is, without the `data` wrapper, so an export can be imported again
+
+use axum::Extension;
+use axum::Json;
+
+use crate::backup::Archive;
+use crate::backup::Summary;
+use crate::client_ip::ClientIp;
+use crate::database::Database;
+use crate::permissions::Action;
+
+use super::CurrentUser;
+use super::Error;
+use super::Form;
+use super::Success;
+
+/// Export all users, domains, destinations and notes
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/backup > backup.json
+/// ```
+///
+/// Response:
+/// ```json
+/// { "version": 1, "exportedAt": "2026-10-14T16:00:00Z", "users": [ ... ] ... }
+/// ```
+pub async fn export(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+) -> Result<Json<Archive>, Error> {
+    current_user.is_allowed(Action::ManageSystem)?;
+
+    let archive = Archive::export(&database)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Json(archive))
+}
+
+/// Import an archive of the export, into a database without destinations
+///
+/// Big archives may need a bigger `BODY_LIMIT`, or the `import` command
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     -d @backup.json \
+///     http://localhost:7000/api/backup
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "users": 2, "domains": 0, "destinations": 12, "notes": 3 } }
+/// ```
+pub async fn import(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    ip_address: Option<ClientIp>,
+    Form(archive): Form<Archive>,
+) -> Result<Success<Summary>, Error> {
+    current_user.is_allowed(Action::ManageSystem)?;
+
+    archive.check_version().map_err(Error::bad_request)?;
+
+    let summary = archive
+        .import(
+            &database,
+            &current_user,
+            ip_address.as_ref().map(|ip_address| &ip_address.0),
+        )
+        .await
+        .map_err(Error::internal_server_error)?
+        .ok_or_else(|| Error::bad_request("Database is not empty"))?;
+
+    Ok(Success::created(summary))
+}
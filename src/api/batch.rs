@@ -0,0 +1,331 @@
+//! Batch API endpoint
+//!
+//! Runs an ordered list of operations in a single transaction, all or nothing. Provisioning a
+//! campaign, a destination with its notes, is a single call.
+
+use axum::http::HeaderMap;
+use axum::Extension;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::database::AuditEntry;
+use crate::database::BatchDestination;
+use crate::database::BatchOperation;
+use crate::database::BatchOutcome;
+use crate::database::CreateNoteValues;
+use crate::database::Database;
+use crate::permissions::Action;
+use crate::root::Settings as RootSettings;
+
+use super::destinations::check_quota;
+use super::destinations::notify_pending;
+use super::destinations::with_slug_alternatives;
+use super::destinations::CreateDestinationForm;
+use super::destinations::DestinationResponse;
+use super::destinations::NewDestination;
+use super::notes::NoteResponse;
+use super::parse_domain;
+use super::parse_slug;
+use super::reservations::check_slug_reservation;
+use super::AuditTrail;
+use super::CurrentUser;
+use super::Error;
+use super::ErrorCode;
+use super::Form;
+use super::Success;
+
+/// Maximum number of operations of a single batch
+const MAX_OPERATIONS: usize = 100;
+
+/// Batch form
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchForm {
+    /// The operations, run in order
+    operations: Vec<OperationForm>,
+}
+
+/// A single operation of a batch, by its `type`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum OperationForm {
+    /// Create a destination, like `POST /api/destinations`
+    CreateDestination(Box<CreateDestinationForm>),
+
+    /// Create a note for a destination
+    CreateNote(CreateNoteForm),
+}
+
+impl OperationForm {
+    /// The action the user needs to be allowed for the operation
+    fn action(&self) -> Action {
+        match self {
+            Self::CreateDestination(_) => Action::CreateDestinations,
+            Self::CreateNote(_) => Action::ManageNotes,
+        }
+    }
+}
+
+/// Create note form of a batch
+///
+/// The destination is found by its slug, created by an earlier operation or existing
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateNoteForm {
+    /// Slug of the destination
+    slug: String,
+
+    /// Domain of the destination, all domains when not provided
+    domain: Option<String>,
+
+    /// Content for note
+    content: String,
+}
+
+/// Result of a single operation of a batch, by its `type`
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum OperationResponse {
+    /// The created destination
+    CreateDestination {
+        /// The destination
+        destination: Box<DestinationResponse>,
+    },
+
+    /// The created note
+    CreateNote {
+        /// The note
+        note: NoteResponse,
+    },
+}
+
+/// A validated operation, before it runs
+enum Validated {
+    /// A destination to create
+    CreateDestination(Box<NewDestination>),
+
+    /// A note to create, for a destination of the batch or an existing one
+    CreateNote(Target, String),
+}
+
+/// The destination of a note of a batch
+enum Target {
+    /// Created by the operation with the index
+    Created(usize),
+
+    /// An existing destination
+    Existing(Box<crate::destinations::Destination>),
+}
+
+/// Run the operations of the [`BatchForm`](BatchForm), all or nothing
+///
+/// Every operation is validated before any runs, a failing operation is named by its index.
+/// Returns the result of every operation, in order.
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     -d '{ "operations": [
+///         { "type": "createDestination", "slug": "launch", "url": "https://www.example.com/" },
+///         { "type": "createNote", "slug": "launch", "content": "Used on the 26-07 ad campaign" }
+///     ] }' \
+///     http://localhost:7000/api/batch
+/// ```
+///
+/// Response
+/// ```json
+/// { "data": [ { "type": "createDestination", "destination": { ... } }, { "type": "createNote", "note": { ... } } ] }
+/// ```
+pub async fn run(
+    headers: HeaderMap,
+    audit_trail: AuditTrail,
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+    Form(form): Form<BatchForm>,
+) -> Result<Success<Vec<OperationResponse>>, Error> {
+    if form.operations.is_empty() {
+        return Err(Error::bad_request("Batch without operations"));
+    }
+
+    if form.operations.len() > MAX_OPERATIONS {
+        return Err(Error::bad_request(format!(
+            "Batch with more than {MAX_OPERATIONS} operations"
+        )));
+    }
+
+    let mut validated = Vec::with_capacity(form.operations.len());
+    for (index, operation) in form.operations.into_iter().enumerate() {
+        current_user.is_allowed(operation.action())?;
+
+        let operation = validate(
+            &database,
+            &root_settings,
+            &headers,
+            &current_user,
+            &validated,
+            operation,
+        )
+        .await
+        .map_err(|err| err.with_prefix(format!("Operation {index}")))?;
+
+        validated.push(operation);
+    }
+
+    let new_destinations = validated
+        .iter()
+        .filter(|operation| matches!(operation, Validated::CreateDestination(_)))
+        .count();
+    if new_destinations > 0 {
+        check_quota(
+            &database,
+            &root_settings,
+            &current_user,
+            i64::try_from(new_destinations).unwrap_or(i64::MAX),
+        )
+        .await?;
+    }
+
+    let operations = validated
+        .iter()
+        .map(|operation| match operation {
+            Validated::CreateDestination(new_destination) => {
+                BatchOperation::CreateDestination(new_destination.values(&current_user))
+            }
+            Validated::CreateNote(target, content) => BatchOperation::CreateNote(
+                match target {
+                    Target::Created(index) => BatchDestination::Created(*index),
+                    Target::Existing(destination) => BatchDestination::Existing(destination),
+                },
+                CreateNoteValues {
+                    user: &current_user,
+                    content,
+                },
+            ),
+        })
+        .collect::<Vec<_>>();
+
+    let outcomes = database
+        .run_batch(&operations)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    let mut responses = Vec::with_capacity(outcomes.len());
+    for outcome in outcomes {
+        match outcome {
+            BatchOutcome::Destination(destination) => {
+                root_settings
+                    .slug_cache
+                    .invalidate(&database, &destination.slug)
+                    .await;
+
+                audit_trail
+                    .register(AuditEntry::CreateDestination(&destination))
+                    .await;
+
+                notify_pending(&database, &root_settings, &current_user, &destination).await;
+
+                responses.push(OperationResponse::CreateDestination {
+                    destination: Box::new(DestinationResponse::from_destination(destination)),
+                });
+            }
+            BatchOutcome::Note(destination, note) => {
+                audit_trail
+                    .register(AuditEntry::CreateNote(&destination, &note))
+                    .await;
+
+                responses.push(OperationResponse::CreateNote {
+                    note: NoteResponse::from_note(note),
+                });
+            }
+        }
+    }
+
+    Ok(Success::created(responses))
+}
+
+/// Validate an operation, with the operations before it
+async fn validate(
+    database: &Database,
+    root_settings: &RootSettings,
+    headers: &HeaderMap,
+    current_user: &CurrentUser,
+    validated: &[Validated],
+    operation: OperationForm,
+) -> Result<Validated, Error> {
+    match operation {
+        OperationForm::CreateDestination(form) => {
+            let new_destination =
+                NewDestination::from_form(database, root_settings, headers, *form).await?;
+
+            let is_created =
+                find_created(validated, new_destination.domain(), new_destination.slug()).is_some();
+
+            let destination = database
+                .find_single_destination_in_namespace(
+                    new_destination.domain(),
+                    new_destination.slug(),
+                )
+                .await
+                .map_err(Error::internal_server_error)?;
+
+            let error = match destination {
+                Some(destination) if destination.is_deleted() => {
+                    Error::bad_request("Slug already exists and is deleted")
+                        .with_code(ErrorCode::SlugDeleted)
+                }
+                Some(_) => {
+                    Error::bad_request("Slug already exists").with_code(ErrorCode::SlugConflict)
+                }
+                None if is_created => {
+                    Error::bad_request("Slug already exists").with_code(ErrorCode::SlugConflict)
+                }
+                None => {
+                    check_slug_reservation(
+                        database,
+                        current_user,
+                        new_destination.domain(),
+                        new_destination.slug(),
+                    )
+                    .await?;
+
+                    return Ok(Validated::CreateDestination(Box::new(new_destination)));
+                }
+            };
+
+            Err(with_slug_alternatives(database, &new_destination, error).await?)
+        }
+        OperationForm::CreateNote(form) => {
+            let slug = parse_slug(&form.slug)?;
+            let domain = parse_domain(form.domain.as_deref())?;
+
+            if let Some(index) = find_created(validated, domain.as_deref(), &slug) {
+                return Ok(Validated::CreateNote(Target::Created(index), form.content));
+            }
+
+            let destination = database
+                .find_single_destination_in_namespace(domain.as_deref(), &slug)
+                .await
+                .map_err(Error::internal_server_error)?
+                .filter(|destination| !destination.is_deleted())
+                .ok_or_else(|| Error::not_found("Destination not found"))?;
+
+            Ok(Validated::CreateNote(
+                Target::Existing(Box::new(destination)),
+                form.content,
+            ))
+        }
+    }
+}
+
+/// Index of the operation creating the destination of the slug, when created by the batch
+fn find_created(validated: &[Validated], domain: Option<&str>, slug: &str) -> Option<usize> {
+    validated.iter().position(|operation| {
+        matches!(
+            operation,
+            Validated::CreateDestination(new_destination)
+                if new_destination.domain() == domain && new_destination.slug() == slug
+        )
+    })
+}
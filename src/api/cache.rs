@@ -0,0 +1,259 @@
+//! Cache API endpoints
+//!
+//! Insight in and control over the cache of slugs, handy when a redirect looks stale
+
+use axum::http::Uri;
+use axum::Extension;
+use chrono::DateTime;
+use chrono::Utc;
+use serde::Deserialize;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::degraded::Statistics as DegradedStatistics;
+use crate::permissions::Action;
+use crate::root::Settings as RootSettings;
+use crate::slug_cache::Audit;
+use crate::slug_cache::Statistics;
+use crate::slug_cache::DEFAULT_AUDIT_SAMPLE;
+use crate::slug_cache::MAX_AUDIT_SAMPLE;
+
+use super::parse_slug;
+use super::CurrentUser;
+use super::Error;
+use super::Form;
+use super::Success;
+
+/// Cache statistics response going to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatisticsResponse {
+    /// Number of cached slugs, including slugs without a destination
+    pub entries: u64,
+
+    /// Number of lookups since startup
+    pub lookups: u64,
+
+    /// Number of lookups served from the cache
+    pub hits: u64,
+
+    /// Number of lookups that went to the database
+    pub misses: u64,
+
+    /// Part of the lookups served from the cache
+    pub hit_ratio: f64,
+
+    /// Number of entries removed because of the capacity or their expiry
+    pub evictions: u64,
+
+    /// Moment the root got degraded, serving cached redirects only; empty when not degraded
+    pub degraded_since: Option<DateTime<Utc>>,
+
+    /// Number of redirects served while degraded, since startup
+    pub degraded_redirects: u64,
+
+    /// Number of hits waiting to be saved
+    pub queued_hits: usize,
+}
+
+impl StatisticsResponse {
+    /// Create a response from the [`Statistics`](Statistics) and the
+    /// [`DegradedStatistics`](DegradedStatistics)
+    fn from_statistics(
+        statistics: Statistics,
+        degraded: DegradedStatistics,
+        queued_hits: usize,
+    ) -> Self {
+        Self {
+            entries: statistics.entries,
+            lookups: statistics.lookups,
+            hits: statistics.hits,
+            misses: statistics.misses,
+            hit_ratio: statistics.hit_ratio(),
+            evictions: statistics.evictions,
+            degraded_since: degraded.since,
+            degraded_redirects: degraded.redirects,
+            queued_hits,
+        }
+    }
+}
+
+/// Statistics of the cache of slugs, since startup of this instance, and of the degraded mode of
+/// the root
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/cache
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "entries": 42, "hitRatio": 0.9 ... } }
+/// ```
+pub async fn statistics(
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+) -> Result<Success<StatisticsResponse>, Error> {
+    current_user.is_allowed(Action::ManageSystem)?;
+
+    let statistics = root_settings.slug_cache.statistics().await;
+
+    Ok(Success::ok(StatisticsResponse::from_statistics(
+        statistics,
+        root_settings.hits.degraded().statistics(),
+        root_settings.hits.queued(),
+    )))
+}
+
+/// Cache audit response going to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditResponse {
+    /// Number of checked slugs
+    pub checked: usize,
+
+    /// The stale slugs, forgotten on all instances
+    pub stale: Vec<StaleEntryResponse>,
+}
+
+/// Stale slug going to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StaleEntryResponse {
+    /// Domain of the request, if known
+    pub domain: Option<String>,
+
+    /// Slug of the request
+    pub slug: String,
+
+    /// ID of the cached destination, empty when cached without a destination
+    pub cached_destination_id: Option<Uuid>,
+
+    /// ID of the destination in the database, empty without a destination
+    pub destination_id: Option<Uuid>,
+}
+
+impl AuditResponse {
+    /// Create a response from the [`Audit`](Audit)
+    fn from_audit(audit: Audit) -> Self {
+        Self {
+            checked: audit.checked,
+            stale: audit
+                .stale
+                .into_iter()
+                .map(|entry| StaleEntryResponse {
+                    domain: entry.domain,
+                    slug: entry.slug,
+                    cached_destination_id: entry.cached.map(|destination| destination.id),
+                    destination_id: entry.actual.map(|destination| destination.id),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Check a sample of the cached slugs of this instance against the database, the stale slugs
+/// are reported and forgotten on all instances
+///
+/// The `sample` query parameter is the number of slugs to check, 100 by default and at most
+/// 10,000. Stale slugs point at a missed invalidation, like a destination changed outside of
+/// Shurly.
+///
+/// Request:
+/// ```sh
+/// curl -v -XPOST \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     'http://localhost:7000/api/cache/audit?sample=500'
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "checked": 42, "stale": [ { "slug": "some-easy-name", "destinationId": "<uuid>" ... } ] } }
+/// ```
+pub async fn audit(
+    uri: Uri,
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+) -> Result<Success<AuditResponse>, Error> {
+    current_user.is_allowed(Action::ManageSystem)?;
+
+    let mut sample = DEFAULT_AUDIT_SAMPLE;
+    for (key, value) in url::form_urlencoded::parse(uri.query().unwrap_or_default().as_bytes()) {
+        if key == "sample" {
+            sample = value
+                .parse::<usize>()
+                .ok()
+                .filter(|sample| (1..=MAX_AUDIT_SAMPLE).contains(sample))
+                .ok_or_else(|| {
+                    Error::bad_request("Invalid sample").with_description(format!(
+                        "Sample is a number from 1 to {MAX_AUDIT_SAMPLE}"
+                    ))
+                })?;
+        }
+    }
+
+    let audit = root_settings
+        .slug_cache
+        .audit(&database, sample)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Success::ok(AuditResponse::from_audit(audit)))
+}
+
+/// Invalidate slug form
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidateForm {
+    /// Slug to forget, on all domains
+    slug: String,
+}
+
+/// Forget a single slug, on all domains of all instances, based on the
+/// [`InvalidateForm`](InvalidateForm) form
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     -d '{ "slug": "some-easy-name" }' \
+///     http://localhost:7000/api/cache/invalidate
+/// ```
+pub async fn invalidate(
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+    Form(form): Form<InvalidateForm>,
+) -> Result<Success<&'static str>, Error> {
+    current_user.is_allowed(Action::ManageSystem)?;
+
+    let slug = parse_slug(&form.slug)?;
+
+    root_settings.slug_cache.invalidate(&database, &slug).await;
+
+    Ok(Success::<&'static str>::no_content())
+}
+
+/// Forget all slugs, on all instances
+///
+/// Request:
+/// ```sh
+/// curl -v -XDELETE \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/cache
+/// ```
+pub async fn flush(
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+) -> Result<Success<&'static str>, Error> {
+    current_user.is_allowed(Action::ManageSystem)?;
+
+    root_settings.slug_cache.flush(&database).await;
+
+    Ok(Success::<&'static str>::no_content())
+}
@@ -0,0 +1,34 @@
+//! Configuration API endpoints
+//!
+//! Reload a selection of the settings without a restart, see [`reload`](crate::reload)
+
+use axum::Extension;
+
+use crate::permissions::Action;
+use crate::reload;
+use crate::root::Settings as RootSettings;
+
+use super::CurrentUser;
+use super::Error;
+use super::Success;
+
+/// Reload the settings of this instance, like on `SIGHUP`
+///
+/// Request:
+/// ```sh
+/// curl -v -XPOST \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/config/reload
+/// ```
+pub async fn reload(
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+) -> Result<Success<&'static str>, Error> {
+    current_user.is_allowed(Action::ManageSystem)?;
+
+    reload::reload(&root_settings).map_err(|err| {
+        Error::bad_request("Could not reload the settings").with_description(format!("{err:#}"))
+    })?;
+
+    Ok(Success::<&'static str>::no_content())
+}
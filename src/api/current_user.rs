@@ -19,8 +19,18 @@ use serde::Deserialize;
 use serde::Serialize;
 use uuid::Uuid;
 
+use crate::api::AuditTrail;
 use crate::api::Error;
+use crate::api::ErrorCode;
+use crate::auth_metrics::AuthMetrics;
+use crate::auth_metrics::Outcome;
+use crate::client_ip::ClientIp;
+use crate::database::AuditEntry;
 use crate::database::Database;
+use crate::permissions::Action;
+use crate::permissions::Permissions;
+use crate::root::Settings as RootSettings;
+use crate::users::Role;
 use crate::users::User;
 
 /// The keys used for encoding/decoding JWT tokens
@@ -87,13 +97,50 @@ impl Token {
 pub struct CurrentUser {
     /// The actual user
     user: Arc<User>,
+
+    /// The permission matrix to check the actions of the user against
+    permissions: Arc<Permissions>,
+
+    /// Counters of the outcomes of the authentication, for the denied actions
+    auth_metrics: AuthMetrics,
 }
 
 impl CurrentUser {
-    /// Create the current user from a user
-    fn new(user: User) -> Self {
+    /// Create the current user from a user, with the permission matrix and the counters of the
+    /// outcomes of the authentication
+    pub fn new(user: User, permissions: Arc<Permissions>, auth_metrics: AuthMetrics) -> Self {
         Self {
             user: Arc::new(user),
+            permissions,
+            auth_metrics,
+        }
+    }
+
+    /// Check if the user is allowed the action
+    ///
+    /// # Errors
+    ///
+    /// Will return a forbidden `Err` when the role of the user is not allowed the action
+    pub fn is_allowed(&self, action: Action) -> Result<(), Error> {
+        self.permissions
+            .check(self.user.role, action)
+            .inspect_err(|_| self.auth_metrics.record(Outcome::DeniedAction))
+    }
+
+    /// Check if the user is allowed to manage users of the role, only when the role is allowed no
+    /// more than the role of the user
+    ///
+    /// # Errors
+    ///
+    /// Will return a forbidden `Err` when the user is not allowed to manage users of the role
+    pub fn is_allowed_to_manage(&self, role: Role) -> Result<(), Error> {
+        if self.permissions.includes(self.user.role, role) {
+            Ok(())
+        } else {
+            self.auth_metrics.record(Outcome::DeniedAction);
+
+            Err(Error::forbidden("Not allowed to manage this role")
+                .with_code(ErrorCode::NotAllowed))
         }
     }
 }
@@ -133,13 +180,25 @@ where
 
     async fn from_request_parts(parts: &mut Parts, state: &B) -> Result<Self, Self::Rejection> {
         use jsonwebtoken::decode;
+        use jsonwebtoken::errors::ErrorKind;
         use jsonwebtoken::Validation;
 
+        let Extension(root_settings) = parts
+            .extract::<Extension<RootSettings>>()
+            .await
+            .map_err(|_| Error::internal_server_error("Could not get the root settings"))?;
+
+        let auth_metrics = root_settings.auth_metrics.clone();
+
         // Extract the token from the authorization header
         let TypedHeader(Authorization(bearer)) =
             TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state)
                 .await
-                .map_err(|_| Error::forbidden("Missing API token"))?;
+                .map_err(|_| {
+                    auth_metrics.record(Outcome::MissingToken);
+
+                    Error::forbidden("Missing API token").with_code(ErrorCode::MissingToken)
+                })?;
 
         let Extension(jwt_keys) = parts
             .extract::<Extension<JwtKeys>>()
@@ -155,7 +214,15 @@ where
 
         // Decode the user data
         let token_data = decode::<Claims>(bearer.token(), &jwt_keys.decoding, &validation)
-            .map_err(|err| Error::forbidden(format!("Invalid token: {err}")))?;
+            .map_err(|err| {
+                if matches!(err.kind(), ErrorKind::ExpiredSignature) {
+                    auth_metrics.record(Outcome::ExpiredSession);
+                } else {
+                    auth_metrics.record(Outcome::InvalidToken);
+                }
+
+                Error::forbidden(format!("Invalid token: {err}")).with_code(ErrorCode::InvalidToken)
+            })?;
 
         let claims = token_data.claims;
 
@@ -169,12 +236,68 @@ where
         if let Some(user) = user {
             // mechanism to invalidate JWT tokens
             if claims.jti != user.session_id {
-                return Err(Error::forbidden("Token expired"));
+                auth_metrics.record(Outcome::ExpiredSession);
+
+                return Err(Error::forbidden("Token expired").with_code(ErrorCode::TokenExpired));
             }
 
-            Ok(CurrentUser::new(user))
+            Ok(CurrentUser::new(
+                user,
+                root_settings.permissions,
+                auth_metrics,
+            ))
         } else {
+            register_unknown_user(parts, state, &database, &root_settings, &id).await;
+
             Err(Error::forbidden("Could not find user"))
         }
     }
 }
+
+/// Count the token of an unknown user as invalid, unless the user is deleted: then it is counted
+/// on its own and registered on the audit trail, with the IP address of the client
+async fn register_unknown_user<B>(
+    parts: &mut Parts,
+    state: &B,
+    database: &Database,
+    root_settings: &RootSettings,
+    id: &Uuid,
+) where
+    B: Send + Sync,
+{
+    let auth_metrics = &root_settings.auth_metrics;
+
+    let Ok(Some(user)) = database.find_single_deleted_user_by_id(id).await else {
+        auth_metrics.record(Outcome::InvalidToken);
+        return;
+    };
+
+    auth_metrics.record(Outcome::DeletedUserToken);
+
+    let ip_address = Option::<ClientIp>::from_request_parts(parts, state)
+        .await
+        .ok()
+        .flatten()
+        .map(|i| i.0);
+
+    if auth_metrics
+        .should_register_deleted_user_token(user.id, ip_address)
+        .await
+    {
+        tracing::warn!(
+            "Token of deleted user `{}` presented from {}",
+            user.username,
+            ip_address.map_or_else(|| "an unknown address".to_string(), |ip| ip.to_string())
+        );
+
+        let current_user = CurrentUser::new(
+            user.clone(),
+            Arc::clone(&root_settings.permissions),
+            auth_metrics.clone(),
+        );
+
+        AuditTrail::new(database.clone(), current_user, ip_address, root_settings)
+            .register(AuditEntry::DeletedUserToken(&user))
+            .await;
+    }
+}
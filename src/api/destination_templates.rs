@@ -0,0 +1,377 @@
+//! Destination templates API endpoints
+//!
+//! Everything related to the management of the templates for new destinations
+
+use axum::Extension;
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::database::CreateDestinationTemplateValues;
+use crate::database::Database;
+use crate::database::UpdateDestinationTemplateValues;
+use crate::database::UtmValues;
+use crate::destination_templates::DestinationTemplate;
+use crate::permissions::Action;
+
+use super::destinations::parse_domain;
+use super::destinations::parse_tags;
+use super::CurrentUser;
+use super::ETag;
+use super::Error;
+use super::Form;
+use super::IfNoneMatch;
+use super::Patch;
+use super::PathParameters;
+use super::Success;
+
+/// Destination template response going to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DestinationTemplateResponse {
+    /// Template ID
+    pub id: Uuid,
+
+    /// Name to reference the template by
+    pub name: String,
+
+    /// Domain of the destinations, all domains when empty
+    pub domain: Option<String>,
+
+    /// Type of the destinations, temporary when empty
+    pub is_permanent: Option<bool>,
+
+    /// Tags of the destinations
+    pub tags: Vec<String>,
+
+    /// `utm_source` query parameter of the URLs
+    pub utm_source: Option<String>,
+
+    /// `utm_medium` query parameter of the URLs
+    pub utm_medium: Option<String>,
+
+    /// `utm_campaign` query parameter of the URLs
+    pub utm_campaign: Option<String>,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+
+    /// Last updated at
+    pub updated_at: NaiveDateTime,
+}
+
+impl DestinationTemplateResponse {
+    /// Create a response from a [`DestinationTemplate`](DestinationTemplate)
+    fn from_template(template: DestinationTemplate) -> Self {
+        Self {
+            id: template.id,
+            name: template.name,
+            domain: template.domain,
+            is_permanent: template.is_permanent,
+            tags: template.tags,
+            utm_source: template.utm_source,
+            utm_medium: template.utm_medium,
+            utm_campaign: template.utm_campaign,
+            created_at: template.created_at,
+            updated_at: template.updated_at,
+        }
+    }
+}
+
+/// List all destination templates
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/templates
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": [ { "id": "<uuid>", "name": "newsletter" ... } ] }
+/// ```
+///
+/// Tagged with an `ETag`, a `304 Not modified` is returned when matching `If-None-Match`
+pub async fn list(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    if_none_match: IfNoneMatch,
+) -> Result<Success<Vec<DestinationTemplateResponse>>, Error> {
+    current_user.is_allowed(Action::ViewDestinations)?;
+
+    let templates = database
+        .find_all_destination_templates()
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    let etag = ETag::from_updates(
+        templates
+            .iter()
+            .map(|template| (template.id, template.updated_at)),
+    );
+
+    Ok(Success::ok(
+        templates
+            .into_iter()
+            .map(DestinationTemplateResponse::from_template)
+            .collect(),
+    )
+    .with_etag(etag, &if_none_match))
+}
+
+/// Get a single destination template
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/templates/<uuid>
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "id": "<uuid>", "name": "newsletter" ... } }
+/// ```
+///
+/// Tagged with an `ETag`, a `304 Not modified` is returned when matching `If-None-Match`
+pub async fn single(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    if_none_match: IfNoneMatch,
+    PathParameters(template_id): PathParameters<Uuid>,
+) -> Result<Success<DestinationTemplateResponse>, Error> {
+    current_user.is_allowed(Action::ViewDestinations)?;
+
+    let template = fetch_template(&database, &template_id).await?;
+    let etag = ETag::from_updates([(template.id, template.updated_at)]);
+
+    Ok(
+        Success::ok(DestinationTemplateResponse::from_template(template))
+            .with_etag(etag, &if_none_match),
+    )
+}
+
+/// Create destination template form
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateDestinationTemplateForm {
+    /// Name to reference the template by, like `newsletter`
+    name: String,
+
+    /// Domain of the destinations, all domains when not provided
+    domain: Option<String>,
+
+    /// Type of the destinations, temporary when not provided
+    is_permanent: Option<bool>,
+
+    /// Tags of the destinations
+    tags: Option<Vec<String>>,
+
+    /// `utm_source` query parameter of the URLs
+    utm_source: Option<String>,
+
+    /// `utm_medium` query parameter of the URLs
+    utm_medium: Option<String>,
+
+    /// `utm_campaign` query parameter of the URLs
+    utm_campaign: Option<String>,
+}
+
+/// Create a destination template based on the
+/// [`CreateDestinationTemplateForm`](CreateDestinationTemplateForm) form
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     -d '{ "name": "newsletter", "tags": ["marketing"], "utmSource": "newsletter" }' \
+///     http://localhost:7000/api/templates
+/// ```
+///
+/// Response
+/// ```json
+/// { "data": { "id": "<uuid>", "name": "newsletter" ... } }
+/// ```
+pub async fn create(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    Form(form): Form<CreateDestinationTemplateForm>,
+) -> Result<Success<DestinationTemplateResponse>, Error> {
+    current_user.is_allowed(Action::ManageTemplates)?;
+
+    let name = form.name.trim();
+
+    if name.is_empty() {
+        return Err(Error::bad_request("Name can not be empty"));
+    }
+
+    let domain = parse_domain(form.domain.as_deref())?;
+    let tags = parse_tags(form.tags.as_deref().unwrap_or_default())?;
+
+    if let Some(template) = database
+        .find_single_destination_template_by_name(name)
+        .await
+        .map_err(Error::internal_server_error)?
+    {
+        return Err(if template.is_deleted() {
+            Error::bad_request("Template already exists and is deleted")
+        } else {
+            Error::bad_request("Template already exists")
+        });
+    }
+
+    let values = CreateDestinationTemplateValues {
+        user: &current_user,
+        name,
+        domain: domain.as_deref(),
+        is_permanent: form.is_permanent,
+        tags: &tags,
+        utm: UtmValues {
+            source: form.utm_source.as_deref(),
+            medium: form.utm_medium.as_deref(),
+            campaign: form.utm_campaign.as_deref(),
+        },
+    };
+
+    let template = database
+        .create_destination_template(&values)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Success::created(
+        DestinationTemplateResponse::from_template(template),
+    ))
+}
+
+/// Update destination template form
+///
+/// Fields to update a template with, all fields are optional and are not touched when not
+/// provided; `null` removes the value
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct UpdateDestinationTemplateForm {
+    /// New domain of the destinations, an empty string removes the domain
+    domain: Patch<String>,
+
+    /// New type of the destinations
+    is_permanent: Patch<bool>,
+
+    /// New tags of the destinations, replacing all current tags
+    tags: Patch<Vec<String>>,
+
+    /// New `utm_source` query parameter, an empty string removes the parameter
+    utm_source: Patch<String>,
+
+    /// New `utm_medium` query parameter, an empty string removes the parameter
+    utm_medium: Patch<String>,
+
+    /// New `utm_campaign` query parameter, an empty string removes the parameter
+    utm_campaign: Patch<String>,
+}
+
+/// Update a destination template based on the
+/// [`UpdateDestinationTemplateForm`](UpdateDestinationTemplateForm) form
+///
+/// The name of a template can not be changed, existing destinations are not touched
+///
+/// Request:
+/// ```sh
+/// curl -v -XPATCH -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     -d '{ "utmCampaign": "spring" }' \
+///     http://localhost:7000/api/templates/<uuid>
+/// ```
+///
+/// Response
+/// ```json
+/// { "data": { "id": "<uuid>", "name": "newsletter" ... } }
+/// ```
+pub async fn update(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    PathParameters(template_id): PathParameters<Uuid>,
+    Form(form): Form<UpdateDestinationTemplateForm>,
+) -> Result<Success<DestinationTemplateResponse>, Error> {
+    current_user.is_allowed(Action::ManageTemplates)?;
+
+    let template = fetch_template(&database, &template_id).await?;
+
+    let domain = match form.domain.removable() {
+        Some(domain) if !domain.is_empty() => parse_domain(Some(domain))?,
+        Some(_) => Some(String::new()),
+        None => None,
+    };
+
+    let is_permanent = match form.is_permanent {
+        Patch::Missing => None,
+        Patch::Null => Some(None),
+        Patch::Value(is_permanent) => Some(Some(is_permanent)),
+    };
+
+    let tags = match form.tags {
+        Patch::Missing => None,
+        Patch::Null => Some(Vec::new()),
+        Patch::Value(ref tags) => Some(parse_tags(tags)?),
+    };
+
+    let values = UpdateDestinationTemplateValues {
+        domain: domain.as_deref(),
+        is_permanent,
+        tags: tags.as_deref(),
+        utm: UtmValues {
+            source: form.utm_source.removable(),
+            medium: form.utm_medium.removable(),
+            campaign: form.utm_campaign.removable(),
+        },
+    };
+
+    let updated_template = database
+        .update_destination_template(&template, &values)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Success::ok(DestinationTemplateResponse::from_template(
+        updated_template,
+    )))
+}
+
+/// Delete a destination template
+///
+/// Destinations created with the template are not touched
+///
+/// Request:
+/// ```sh
+/// curl -v -XDELETE \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/templates/<uuid>
+/// ```
+pub async fn delete(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    PathParameters(template_id): PathParameters<Uuid>,
+) -> Result<Success<&'static str>, Error> {
+    current_user.is_allowed(Action::ManageTemplates)?;
+
+    let template = fetch_template(&database, &template_id).await?;
+
+    database
+        .delete_destination_template(&template)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Success::<&'static str>::no_content())
+}
+
+/// Fetch destination template from database
+async fn fetch_template(
+    database: &Database,
+    template_id: &Uuid,
+) -> Result<DestinationTemplate, Error> {
+    database
+        .find_single_destination_template_by_id(template_id)
+        .await
+        .map_err(Error::internal_server_error)?
+        .map_or_else(|| Err(Error::not_found("Template not found")), Ok)
+}
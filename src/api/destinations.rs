@@ -2,25 +2,58 @@
 //!
 //! Everything related to the destinations management
 
+use std::collections::BTreeMap;
+
+use axum::http::HeaderMap;
+use axum::http::HeaderName;
+use axum::http::HeaderValue;
 use axum::Extension;
+use chrono::DateTime;
+use chrono::Datelike;
 use chrono::NaiveDateTime;
+use chrono::SubsecRound;
+use chrono::TimeDelta;
+use chrono::Utc;
 use serde::Deserialize;
 use serde::Serialize;
+use serde_json::json;
+use url::Url;
 use uuid::Uuid;
 
+use crate::approval::Approval;
+use crate::approval::ApprovalStatus;
+use crate::database::stored_value;
 use crate::database::AuditEntry;
 use crate::database::CreateDestinationValues;
 use crate::database::Database;
+use crate::database::OpenGraphValues;
 use crate::database::UpdateDestinationValues;
+use crate::destination_templates::DestinationTemplate;
+use crate::destinations;
 use crate::destinations::Destination;
-use crate::users::Role;
+use crate::domains;
+use crate::idn;
+use crate::notifier::Notification;
+use crate::permissions::Action;
+use crate::quotas::Quota;
+use crate::root::Settings as RootSettings;
+use crate::root::RESERVED_SLUGS;
+use crate::slug_policy::SlugPolicy;
+use crate::slug_policy::SlugViolation;
+use crate::users::User;
+use crate::utils::encode_slug;
 
 use super::parse_slug;
 use super::parse_url;
+use super::reservations::check_slug_reservation;
 use super::AuditTrail;
 use super::CurrentUser;
+use super::ETag;
 use super::Error;
+use super::ErrorCode;
 use super::Form;
+use super::IfNoneMatch;
+use super::Patch;
 use super::PathParameters;
 use super::Success;
 
@@ -29,6 +62,7 @@ use super::Success;
 /// Basically filtering which fields are shown to the user
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
+#[allow(clippy::struct_excessive_bools)] // the flags of a destination
 pub struct DestinationResponse {
     /// Destination ID
     pub id: Uuid,
@@ -36,12 +70,60 @@ pub struct DestinationResponse {
     /// Slug used to identify the destination by the root
     pub slug: String,
 
+    /// Domain the slug is used on, all domains when empty
+    pub domain: Option<String>,
+
     /// Url where root will redirect to
     pub url: String,
 
+    /// URL with its hostname in unicode, differs from the URL for internationalized domain names
+    pub unicode_url: String,
+
     /// Type of destination
     pub is_permanent: bool,
 
+    /// Redirect with a meta refresh page, instead of an HTTP redirect
+    pub is_meta_refresh: bool,
+
+    /// Only redirect with a valid signed link
+    pub is_private: bool,
+
+    /// Serve the stats publicly, at `/<slug>+stats`
+    pub is_stats_public: bool,
+
+    /// Never record the hits, like for privacy-sensitive internal links
+    pub is_untracked: bool,
+
+    /// Open Graph title, shown to social media crawlers
+    pub og_title: Option<String>,
+
+    /// Open Graph description, shown to social media crawlers
+    pub og_description: Option<String>,
+
+    /// Open Graph image, shown to social media crawlers
+    pub og_image: Option<String>,
+
+    /// Script deciding the URL to redirect to, based on the request
+    pub script: Option<String>,
+
+    /// Extra headers of the redirect, by name
+    pub headers: BTreeMap<String, String>,
+
+    /// Tags to group destinations by
+    pub tags: Vec<String>,
+
+    /// Channel the short link is handed out in, like `print-flyer`
+    pub source: Option<String>,
+
+    /// Status of the approval by an admin, only approved destinations resolve
+    pub approval_status: ApprovalStatus,
+
+    /// When the URL was flagged as unsafe, disabling the destination
+    pub flagged_at: Option<NaiveDateTime>,
+
+    /// Why the URL was flagged, like `Listed on the blocklist`
+    pub flagged_reason: Option<String>,
+
     /// Creation date
     pub created_at: NaiveDateTime,
 
@@ -53,12 +135,31 @@ impl DestinationResponse {
     /// Create a response from a [`Destination`](Destination)
     ///
     /// Basically filtering which fields are shown to the user
-    fn from_destination(destination: Destination) -> Self {
+    pub fn from_destination(destination: Destination) -> Self {
+        let headers = destination.extra_headers();
+        let approval_status = destination.approval_status();
+
         Self {
             id: destination.id,
             slug: destination.slug,
+            domain: destination.domain,
+            unicode_url: idn::unicode_url(&destination.url),
             url: destination.url,
             is_permanent: destination.is_permanent,
+            is_meta_refresh: destination.is_meta_refresh,
+            is_private: destination.is_private,
+            is_stats_public: destination.is_stats_public,
+            is_untracked: destination.is_untracked,
+            og_title: destination.og_title,
+            og_description: destination.og_description,
+            og_image: destination.og_image,
+            script: destination.script,
+            headers,
+            tags: destination.tags,
+            source: destination.source,
+            approval_status,
+            flagged_at: destination.flagged_at,
+            flagged_reason: destination.flagged_reason,
             created_at: destination.created_at,
             updated_at: destination.updated_at,
         }
@@ -88,20 +189,30 @@ impl DestinationResponse {
 /// ```json
 /// { "data": [ { "id": "<uuid>", "slug": "some-easy-name" ... } ] }
 /// ```
+///
+/// Tagged with an `ETag`, a `304 Not modified` is returned when matching `If-None-Match`
 pub async fn list(
     Extension(database): Extension<Database>,
     current_user: CurrentUser,
+    if_none_match: IfNoneMatch,
 ) -> Result<Success<Vec<DestinationResponse>>, Error> {
-    current_user.role.is_allowed(Role::Manager)?;
+    current_user.is_allowed(Action::ViewDestinations)?;
 
     let destinations = database
         .find_all_destinations()
         .await
         .map_err(Error::internal_server_error)?;
 
-    Ok(Success::ok(DestinationResponse::from_destination_multiple(
-        destinations,
-    )))
+    let etag = ETag::from_updates(
+        destinations
+            .iter()
+            .map(|destination| (destination.id, destination.updated_at)),
+    );
+
+    Ok(
+        Success::ok(DestinationResponse::from_destination_multiple(destinations))
+            .with_etag(etag, &if_none_match),
+    )
 }
 
 /// Get a single destination
@@ -117,40 +228,130 @@ pub async fn list(
 /// ```json
 /// { "data": { "id": "<uuid>", "slug": "some-easy-name" ... } }
 /// ```
+///
+/// Tagged with an `ETag`, a `304 Not modified` is returned when matching `If-None-Match`
 pub async fn single(
     Extension(database): Extension<Database>,
     current_user: CurrentUser,
+    if_none_match: IfNoneMatch,
     PathParameters(destination_id): PathParameters<Uuid>,
 ) -> Result<Success<DestinationResponse>, Error> {
-    current_user.role.is_allowed(Role::Manager)?;
+    current_user.is_allowed(Action::ViewDestinations)?;
 
-    fetch_destination(&database, &destination_id)
-        .await
-        .map(|destination| Success::ok(DestinationResponse::from_destination(destination)))
+    let destination = fetch_destination(&database, &destination_id).await?;
+    let etag = ETag::from_updates([(destination.id, destination.updated_at)]);
+
+    Ok(
+        Success::ok(DestinationResponse::from_destination(destination))
+            .with_etag(etag, &if_none_match),
+    )
 }
 
 /// Create destination form
 ///
 /// Fields to create a destination with
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateDestinationForm {
-    /// Slug to create a destination with
+    /// Slug to create a destination with, derived from the URL when not provided, see
+    /// [`hashed_slugs`](crate::hashed_slugs)
     ///
     /// The slug is normalized:
     /// - Leading and trailing slashes are removed
     /// - Unicode normalization
-    slug: String,
+    slug: Option<String>,
+
+    /// Domain the slug is used on, all domains when not provided
+    ///
+    /// The same slug can be used on multiple domains, a destination on the domain is used over a
+    /// destination for all domains
+    domain: Option<String>,
 
     /// Url to create a destination with
     url: String,
 
     /// Type to create a destination with
     is_permanent: Option<bool>,
+
+    /// Redirect with a meta refresh page, for clients that do not follow HTTP redirects
+    is_meta_refresh: Option<bool>,
+
+    /// Only redirect with a valid signed link, see [`sign`](sign)
+    is_private: Option<bool>,
+
+    /// Serve the stats publicly, at `/<slug>+stats`
+    is_stats_public: Option<bool>,
+
+    /// Never record the hits, like for privacy-sensitive internal links
+    is_untracked: Option<bool>,
+
+    /// Open Graph title, shown to social media crawlers
+    og_title: Option<String>,
+
+    /// Open Graph description, shown to social media crawlers
+    og_description: Option<String>,
+
+    /// Open Graph image URL, shown to social media crawlers
+    og_image: Option<String>,
+
+    /// Script deciding the URL to redirect to, based on the request, see
+    /// [`scripts`](crate::scripts)
+    script: Option<String>,
+
+    /// Extra headers of the redirect, by name, like `X-Robots-Tag: noindex`
+    headers: Option<BTreeMap<String, String>>,
+
+    /// Tags to group destinations by
+    tags: Option<Vec<String>>,
+
+    /// Channel the short link is handed out in, like `print-flyer`, to compare the channels by
+    source: Option<String>,
+
+    /// Name of the template with the defaults of the destination, see
+    /// [`destination_templates`](crate::destination_templates)
+    template: Option<String>,
+}
+
+impl CreateDestinationForm {
+    /// Create the form with just a slug and URL, the rest is the default
+    pub fn new(slug: String, url: String) -> Self {
+        Self {
+            slug: Some(slug),
+            url,
+            ..Self::default()
+        }
+    }
+
+    /// Use the slug on the domain only
+    pub fn with_domain(mut self, domain: Option<String>) -> Self {
+        self.domain = domain;
+        self
+    }
+
+    /// Set the permanent, meta refresh and private flags of the destination
+    pub fn with_flags(
+        mut self,
+        is_permanent: bool,
+        is_meta_refresh: bool,
+        is_private: bool,
+    ) -> Self {
+        self.is_permanent = Some(is_permanent);
+        self.is_meta_refresh = Some(is_meta_refresh);
+        self.is_private = Some(is_private);
+        self
+    }
 }
 
 /// Create a destination based on the [`CreateDestinationForm`](CreateDestinationForm) form
 ///
+/// URLs redirecting back to the slug via Shurly itself are refused
+///
+/// With a `template` the domain, type, tags and UTM parameters of the template are used, unless
+/// provided by the form
+///
+/// Without a slug the slug is derived from the URL, when enabled. Shortening the same URL again
+/// responds with the existing destination and a `200 OK`
+///
 /// Request:
 /// ```sh
 /// curl -v -H 'Content-Type: application/json' \
@@ -164,81 +365,521 @@ pub struct CreateDestinationForm {
 /// { "data": { "id": "<uuid>", "slug": "some-easy-name" ... } }
 /// ```
 pub async fn create(
+    headers: HeaderMap,
     audit_trail: AuditTrail,
     Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
     current_user: CurrentUser,
     Form(form): Form<CreateDestinationForm>,
 ) -> Result<Success<DestinationResponse>, Error> {
-    current_user.role.is_allowed(Role::Manager)?;
+    current_user.is_allowed(Action::CreateDestinations)?;
 
-    let slug = parse_slug(&form.slug)?;
-    let url = parse_url(&form.url)?;
+    let new_destination =
+        NewDestination::from_form(&database, &root_settings, &headers, form).await?;
 
-    if slug.starts_with("api/") {
-        return Err(Error::bad_request("Slug can not start with 'api/'"));
+    let (destination, is_created) = create_new_destination(
+        &audit_trail,
+        &database,
+        &root_settings,
+        &current_user,
+        &new_destination,
+    )
+    .await?;
+
+    let response = DestinationResponse::from_destination(destination);
+
+    Ok(if is_created {
+        Success::created(response)
+    } else {
+        Success::ok(response)
+    })
+}
+
+/// Create or update the destination of the slug, declared by the
+/// [`CreateDestinationForm`](CreateDestinationForm), for infrastructure-as-code tools
+///
+/// The slug of the path is used, with the `domain` of the form as its namespace. The form
+/// declares the whole destination: fields not provided get their default, like on create, and an
+/// existing destination is updated to match. Declaring the current state again changes nothing
+/// and responds with a `200 OK`, a new destination with a `201 Created`
+///
+/// Permanent destinations can only be declared as they are, deleted slugs can not be declared
+/// again
+///
+/// Request:
+/// ```sh
+/// curl -v -XPUT -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     -d '{ "url": "https://www.example.com/", "tags": ["terraform"] }' \
+///     http://localhost:7000/api/destinations/by-slug/some-easy-name
+/// ```
+///
+/// Response
+/// ```json
+/// { "data": { "id": "<uuid>", "slug": "some-easy-name" ... } }
+/// ```
+pub async fn upsert(
+    headers: HeaderMap,
+    audit_trail: AuditTrail,
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+    PathParameters(slug): PathParameters<String>,
+    Form(mut form): Form<CreateDestinationForm>,
+) -> Result<Success<DestinationResponse>, Error> {
+    form.slug = Some(slug);
+
+    let new_destination =
+        NewDestination::from_form(&database, &root_settings, &headers, form).await?;
+
+    let Some(destination) = database
+        .find_single_destination_in_namespace(new_destination.domain(), new_destination.slug())
+        .await
+        .map_err(Error::internal_server_error)?
+    else {
+        current_user.is_allowed(Action::CreateDestinations)?;
+
+        let destination = save_new_destination(
+            &audit_trail,
+            &database,
+            &root_settings,
+            &current_user,
+            &new_destination,
+        )
+        .await?;
+
+        return Ok(Success::created(DestinationResponse::from_destination(
+            destination,
+        )));
+    };
+
+    if destination.is_deleted() {
+        return Err(Error::bad_request("Slug already exists and is deleted")
+            .with_code(ErrorCode::SlugDeleted));
     }
 
-    let destination = database
-        .find_single_destination_by_slug(&slug)
+    current_user.is_allowed(Action::UpdateDestinations)?;
+
+    if new_destination.matches(&destination) {
+        return Ok(Success::ok(DestinationResponse::from_destination(
+            destination,
+        )));
+    }
+
+    if destination.is_permanent {
+        return Err(Error::bad_request("Permanent URLs can not be updated")
+            .with_code(ErrorCode::PermanentImmutable));
+    }
+
+    let updated_destination = database
+        .update_destination(&destination, &new_destination.update_values(&destination))
         .await
         .map_err(Error::internal_server_error)?;
 
-    if let Some(destination) = destination {
-        if destination.is_deleted() {
-            Err(Error::bad_request("Slug already exists and is deleted"))
+    root_settings
+        .slug_cache
+        .invalidate(&database, &destination.slug)
+        .await;
+
+    audit_trail
+        .register(AuditEntry::UpdateDestination(&updated_destination))
+        .await;
+
+    Ok(Success::ok(DestinationResponse::from_destination(
+        updated_destination,
+    )))
+}
+
+/// Create the new destination, when its slug is not taken yet, like `POST /api/destinations`
+///
+/// A taken slug is refused with alternatives for the slug. A slug derived from the same URL is not
+/// taken, its existing destination is returned instead; the `bool` tells whether the destination
+/// is created.
+///
+/// # Errors
+///
+/// Will return `Err` when the slug is taken or the destination is not allowed, like over the quota
+pub async fn create_new_destination(
+    audit_trail: &AuditTrail,
+    database: &Database,
+    root_settings: &RootSettings,
+    current_user: &CurrentUser,
+    new_destination: &NewDestination,
+) -> Result<(Destination, bool), Error> {
+    if let Some(destination) = database
+        .find_single_destination_in_namespace(new_destination.domain(), new_destination.slug())
+        .await
+        .map_err(Error::internal_server_error)?
+    {
+        if new_destination.is_hashed_slug
+            && !destination.is_deleted()
+            && destination.url == new_destination.url.as_str()
+        {
+            return Ok((destination, false));
+        }
+
+        let error = if destination.is_deleted() {
+            Error::bad_request("Slug already exists and is deleted")
+                .with_code(ErrorCode::SlugDeleted)
         } else {
-            Err(Error::bad_request("Slug already exists"))
+            Error::bad_request("Slug already exists").with_code(ErrorCode::SlugConflict)
+        };
+
+        return Err(with_slug_alternatives(database, new_destination, error).await?);
+    }
+
+    let destination = save_new_destination(
+        audit_trail,
+        database,
+        root_settings,
+        current_user,
+        new_destination,
+    )
+    .await?;
+
+    Ok((destination, true))
+}
+
+/// Save the new destination, when the slug is not reserved by another user and the quota of the
+/// user allows it
+async fn save_new_destination(
+    audit_trail: &AuditTrail,
+    database: &Database,
+    root_settings: &RootSettings,
+    current_user: &CurrentUser,
+    new_destination: &NewDestination,
+) -> Result<Destination, Error> {
+    check_slug_reservation(
+        database,
+        current_user,
+        new_destination.domain(),
+        new_destination.slug(),
+    )
+    .await?;
+    check_quota(database, root_settings, current_user, 1).await?;
+
+    let destination = database
+        .create_destination(&new_destination.values(current_user))
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    root_settings
+        .slug_cache
+        .invalidate(database, &destination.slug)
+        .await;
+
+    audit_trail
+        .register(AuditEntry::CreateDestination(&destination))
+        .await;
+
+    notify_pending(database, root_settings, current_user, &destination).await;
+
+    Ok(destination)
+}
+
+/// Notify the reviewers when the new destination awaits approval, except its creator
+///
+/// Notifying never fails the request, a failure to find the reviewers is logged
+pub(super) async fn notify_pending(
+    database: &Database,
+    root_settings: &RootSettings,
+    current_user: &CurrentUser,
+    destination: &Destination,
+) {
+    if destination.approval_status() != ApprovalStatus::Pending {
+        return;
+    }
+
+    tracing::info!(r#"Slug "{}" is awaiting approval"#, destination.slug);
+
+    let users = match database.find_all_users().await {
+        Ok(users) => users,
+        Err(err) => {
+            tracing::warn!("Could not find the reviewers of a pending destination: {err}");
+            return;
         }
-    } else {
-        let values = CreateDestinationValues {
-            user: &current_user,
-            slug: &slug,
-            url: &url,
-            is_permanent: &form.is_permanent.unwrap_or(false),
+    };
+
+    let notification = Notification::PendingDestination {
+        slug: destination.slug.clone(),
+        url: destination.url.clone(),
+        created_by: current_user.username.clone(),
+    };
+
+    for user in users.iter().filter(|user| {
+        user.id != current_user.id
+            && root_settings
+                .permissions
+                .is_allowed(user.role, Action::ReviewDestinations)
+    }) {
+        root_settings.notifier.notify(user, &notification);
+    }
+}
+
+/// Notify the creator of the reviewed destination
+///
+/// Notifying never fails the request, a failure to find the creator is logged
+async fn notify_creator(
+    database: &Database,
+    root_settings: &RootSettings,
+    destination: &Destination,
+    notification: &Notification,
+) {
+    match database.find_single_user_by_id(&destination.user_id).await {
+        Ok(Some(user)) => root_settings.notifier.notify(&user, notification),
+        Ok(None) => {}
+        Err(err) => tracing::warn!("Could not find the creator of a reviewed destination: {err}"),
+    }
+}
+
+/// A new destination of the [`CreateDestinationForm`](CreateDestinationForm), validated
+pub struct NewDestination {
+    /// The parsed slug
+    slug: String,
+
+    /// Is the slug derived from the URL?
+    is_hashed_slug: bool,
+
+    /// The normalized domain
+    domain: Option<String>,
+
+    /// The parsed URL, with the UTM parameters of the template
+    url: Url,
+
+    /// Type of the destination
+    is_permanent: bool,
+
+    /// The extra headers as JSON
+    extra_headers: Option<String>,
+
+    /// The normalized tags, with the tags of the template
+    tags: Vec<String>,
+
+    /// The normalized source
+    source: Option<String>,
+
+    /// Which new destinations need approval
+    approval: Approval,
+
+    /// The rest of the form
+    form: CreateDestinationForm,
+}
+
+impl NewDestination {
+    /// Validate the form, refusing URLs redirecting back to the slug via Shurly itself and
+    /// confusable slugs when configured
+    ///
+    /// The template of the form provides the defaults of the destination
+    ///
+    /// Existing slugs are not checked, the slug could be taken by the time it is created
+    pub async fn from_form(
+        database: &Database,
+        root_settings: &RootSettings,
+        headers: &HeaderMap,
+        form: CreateDestinationForm,
+    ) -> Result<Self, Error> {
+        let slug = form
+            .slug
+            .as_deref()
+            .map(|slug| parse_new_slug(slug, &root_settings.slug_policy))
+            .transpose()?;
+        if let Some(ref slug) = slug {
+            check_confusable_slug(root_settings, slug)?;
+        }
+        let mut url = parse_url(&form.url)?;
+        check_confusable_url(root_settings, &url)?;
+        validate_og_image(form.og_image.as_deref())?;
+        validate_script(root_settings, form.script.as_deref())?;
+        let extra_headers = form.headers.as_ref().map(stored_headers).transpose()?;
+        let mut tags = parse_tags(form.tags.as_deref().unwrap_or_default())?;
+        let source = stored_value(form.source.as_deref().map(parse_source).transpose()?);
+
+        let mut domain = parse_domain(form.domain.as_deref())?;
+        let mut is_permanent = form.is_permanent;
+
+        if let Some(ref name) = form.template {
+            let template = fetch_template_by_name(database, name).await?;
+
+            url = template.tag_url(&url);
+            tags = template.merge_tags(&tags);
+            domain = domain.or(template.domain);
+            is_permanent = is_permanent.or(template.is_permanent);
+        }
+
+        let is_hashed_slug = slug.is_none();
+        let slug = match slug {
+            Some(slug) => slug,
+            None => hashed_slug(database, root_settings, domain.as_deref(), &url).await?,
         };
 
-        let destination = database
-            .create_destination(&values)
-            .await
-            .map_err(Error::internal_server_error)?;
+        check_redirect_loop(
+            database,
+            root_settings,
+            headers,
+            domain.as_deref(),
+            &slug,
+            &url,
+        )
+        .await?;
+        check_url_reputation(root_settings, &url).await?;
+
+        Ok(Self {
+            slug,
+            is_hashed_slug,
+            domain,
+            url,
+            is_permanent: is_permanent.unwrap_or(false),
+            extra_headers,
+            tags,
+            source,
+            approval: root_settings.approval,
+            form,
+        })
+    }
 
-        audit_trail
-            .register(AuditEntry::CreateDestination(&destination))
-            .await;
+    /// The slug of the destination
+    pub fn slug(&self) -> &str {
+        &self.slug
+    }
 
-        Ok(Success::created(DestinationResponse::from_destination(
-            destination,
-        )))
+    /// The domain of the destination, all domains when `None`
+    pub fn domain(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
+
+    /// Values to create the destination with, created by the user
+    pub fn values<'a>(&'a self, user: &'a User) -> CreateDestinationValues<'a> {
+        CreateDestinationValues {
+            user,
+            slug: &self.slug,
+            domain: self.domain.as_deref(),
+            url: &self.url,
+            is_permanent: &self.is_permanent,
+            is_meta_refresh: self.form.is_meta_refresh.as_ref().unwrap_or(&false),
+            is_private: self.form.is_private.as_ref().unwrap_or(&false),
+            is_stats_public: self.form.is_stats_public.as_ref().unwrap_or(&false),
+            is_untracked: self.form.is_untracked.as_ref().unwrap_or(&false),
+            open_graph: OpenGraphValues {
+                title: self.form.og_title.as_deref(),
+                description: self.form.og_description.as_deref(),
+                image: self.form.og_image.as_deref(),
+            },
+            script: self.form.script.as_deref(),
+            headers: self.extra_headers.as_deref(),
+            tags: &self.tags,
+            source: self.source.as_deref(),
+            is_pending: self.approval.is_required_for(user.role),
+        }
+    }
+
+    /// Does the existing destination already match, with nothing to update?
+    pub fn matches(&self, destination: &Destination) -> bool {
+        destination.url == self.url.as_str()
+            && destination.is_permanent == self.is_permanent
+            && destination.is_meta_refresh == self.form.is_meta_refresh.unwrap_or(false)
+            && destination.is_private == self.form.is_private.unwrap_or(false)
+            && destination.is_stats_public == self.form.is_stats_public.unwrap_or(false)
+            && destination.is_untracked == self.form.is_untracked.unwrap_or(false)
+            && destination.og_title == stored_value(self.form.og_title.as_deref())
+            && destination.og_description == stored_value(self.form.og_description.as_deref())
+            && destination.og_image == stored_value(self.form.og_image.as_deref())
+            && destination.script == stored_value(self.form.script.as_deref())
+            && destination.headers == stored_value(self.extra_headers.as_deref())
+            && destination.tags == self.tags
+            && destination.source == self.source
+    }
+
+    /// Values to update the existing destination with, replacing all its fields
+    ///
+    /// The URL is only touched when it changes, a flagged destination stays disabled otherwise
+    pub fn update_values(&self, destination: &Destination) -> UpdateDestinationValues<'_> {
+        UpdateDestinationValues {
+            url: (destination.url != self.url.as_str()).then(|| self.url.clone()),
+            is_permanent: Some(&self.is_permanent),
+            is_meta_refresh: Some(self.form.is_meta_refresh.as_ref().unwrap_or(&false)),
+            is_private: Some(self.form.is_private.as_ref().unwrap_or(&false)),
+            is_stats_public: Some(self.form.is_stats_public.as_ref().unwrap_or(&false)),
+            is_untracked: Some(self.form.is_untracked.as_ref().unwrap_or(&false)),
+            open_graph: OpenGraphValues {
+                title: Some(self.form.og_title.as_deref().unwrap_or_default()),
+                description: Some(self.form.og_description.as_deref().unwrap_or_default()),
+                image: Some(self.form.og_image.as_deref().unwrap_or_default()),
+            },
+            script: Some(self.form.script.as_deref().unwrap_or_default()),
+            headers: Some(self.extra_headers.as_deref().unwrap_or_default()),
+            tags: Some(&self.tags),
+            source: Some(self.source.as_deref().unwrap_or_default()),
+        }
     }
 }
 
 /// Update destination form
 ///
 /// Fields to update a destination with, all fields are optional and are not touched when not
-/// provided
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
+/// provided; `null` removes the value of the Open Graph fields, the script and the headers
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
 pub struct UpdateDestinationForm {
     /// New note to update destination with
-    url: Option<String>,
+    url: Patch<String>,
 
     /// Type to update destination with
     ///
     /// Can only be set to `false` if the destination already has `is_permanent=true`, otherwise
     /// only `true` is valid
-    is_permanent: Option<bool>,
+    is_permanent: Patch<bool>,
+
+    /// Redirect with a meta refresh page, for clients that do not follow HTTP redirects
+    is_meta_refresh: Patch<bool>,
+
+    /// Only redirect with a valid signed link, see [`sign`](sign)
+    is_private: Patch<bool>,
+
+    /// Serve the stats publicly, at `/<slug>+stats`
+    is_stats_public: Patch<bool>,
+
+    /// Never record the hits, like for privacy-sensitive internal links
+    is_untracked: Patch<bool>,
+
+    /// New Open Graph title, an empty string removes the title
+    og_title: Patch<String>,
+
+    /// New Open Graph description, an empty string removes the description
+    og_description: Patch<String>,
+
+    /// New Open Graph image URL, an empty string removes the image
+    og_image: Patch<String>,
+
+    /// New script, an empty string removes the script
+    script: Patch<String>,
+
+    /// New extra headers, replacing all current headers, an empty object removes the headers
+    headers: Patch<BTreeMap<String, String>>,
+
+    /// New tags, replacing all current tags
+    tags: Patch<Vec<String>>,
+
+    /// New source, an empty string removes the source
+    source: Patch<String>,
 }
 
 /// Update a destinations based on the [`UpdateDestinationForm`](UpdateDestinationForm) form
 ///
 /// Only provided values are processed, the other fields of the destination will not be touched
 ///
+/// URLs redirecting back to the slug via Shurly itself are refused
+///
 /// Request:
 /// ```sh
 /// curl -v -XPATCH -H 'Content-Type: application/json' \
 ///     -H 'Authorization: Bearer tokentokentoken' \
 ///     -d '{ "url": "https://www.example.com/", "isPermanent": true }' \
 ///     http://localhost:7000/api/destinations/<uuid>
+///
+/// curl -v -XPATCH -H 'Content-Type: application/merge-patch+json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     -d '{ "ogTitle": null }' \
+///     http://localhost:7000/api/destinations/<uuid>
 /// ```
 ///
 /// Response
@@ -246,29 +887,73 @@ pub struct UpdateDestinationForm {
 /// { "data": { "id": "<uuid>", "slug": "some-easy-name" ... } }
 /// ```
 pub async fn update(
+    headers: HeaderMap,
     audit_trail: AuditTrail,
     Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
     current_user: CurrentUser,
     PathParameters(destination_id): PathParameters<Uuid>,
     Form(form): Form<UpdateDestinationForm>,
 ) -> Result<Success<DestinationResponse>, Error> {
-    current_user.role.is_allowed(Role::Manager)?;
+    current_user.is_allowed(Action::UpdateDestinations)?;
 
     let destination = fetch_destination(&database, &destination_id).await?;
 
     if destination.is_permanent {
-        return Err(Error::bad_request("Permanent URLs can not be updated"));
+        return Err(Error::bad_request("Permanent URLs can not be updated")
+            .with_code(ErrorCode::PermanentImmutable));
     }
 
-    let url = if let Some(ref url) = form.url {
+    let url = if let Some(url) = form.url.required("url")? {
         Some(parse_url(url)?)
     } else {
         None
     };
 
+    if let Some(ref url) = url {
+        check_confusable_url(&root_settings, url)?;
+        check_redirect_loop(
+            &database,
+            &root_settings,
+            &headers,
+            destination.domain.as_deref(),
+            &destination.slug,
+            url,
+        )
+        .await?;
+        check_url_reputation(&root_settings, url).await?;
+    }
+
+    validate_og_image(form.og_image.removable())?;
+    validate_script(&root_settings, form.script.removable())?;
+    let extra_headers = match form.headers {
+        Patch::Missing => None,
+        Patch::Null => Some(String::new()),
+        Patch::Value(ref headers) => Some(stored_headers(headers)?),
+    };
+    let tags = match form.tags {
+        Patch::Missing => None,
+        Patch::Null => Some(Vec::new()),
+        Patch::Value(ref tags) => Some(parse_tags(tags)?),
+    };
+    let source = form.source.removable().map(parse_source).transpose()?;
+
     let values = UpdateDestinationValues {
         url,
-        is_permanent: form.is_permanent.as_ref(),
+        is_permanent: form.is_permanent.required("isPermanent")?,
+        is_meta_refresh: form.is_meta_refresh.required("isMetaRefresh")?,
+        is_private: form.is_private.required("isPrivate")?,
+        is_stats_public: form.is_stats_public.required("isStatsPublic")?,
+        is_untracked: form.is_untracked.required("isUntracked")?,
+        open_graph: OpenGraphValues {
+            title: form.og_title.removable(),
+            description: form.og_description.removable(),
+            image: form.og_image.removable(),
+        },
+        script: form.script.removable(),
+        headers: extra_headers.as_deref(),
+        tags: tags.as_deref(),
+        source,
     };
 
     let updated_destination = database
@@ -276,8 +961,13 @@ pub async fn update(
         .await
         .map_err(Error::internal_server_error)?;
 
+    root_settings
+        .slug_cache
+        .invalidate(&database, &destination.slug)
+        .await;
+
     audit_trail
-        .register(AuditEntry::UpdateDestination(&destination))
+        .register(AuditEntry::UpdateDestination(&updated_destination))
         .await;
 
     Ok(Success::ok(DestinationResponse::from_destination(
@@ -285,7 +975,7 @@ pub async fn update(
     )))
 }
 
-/// Delete a destination
+/// Delete a destination, with its notes
 ///
 /// Permanent destinations can not be deleted
 ///
@@ -298,15 +988,17 @@ pub async fn update(
 pub async fn delete(
     audit_trail: AuditTrail,
     Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
     current_user: CurrentUser,
     PathParameters(destination_id): PathParameters<Uuid>,
 ) -> Result<Success<&'static str>, Error> {
-    current_user.role.is_allowed(Role::Manager)?;
+    current_user.is_allowed(Action::DeleteDestinations)?;
 
     let destination = fetch_destination(&database, &destination_id).await?;
 
     if destination.is_permanent {
-        return Err(Error::bad_request("Permanent URLs can not be deleted"));
+        return Err(Error::bad_request("Permanent URLs can not be deleted")
+            .with_code(ErrorCode::PermanentImmutable));
     }
 
     database
@@ -314,6 +1006,11 @@ pub async fn delete(
         .await
         .map_err(Error::internal_server_error)?;
 
+    root_settings
+        .slug_cache
+        .invalidate(&database, &destination.slug)
+        .await;
+
     audit_trail
         .register(AuditEntry::DeleteDestination(&destination))
         .await;
@@ -321,6 +1018,553 @@ pub async fn delete(
     Ok(Success::<&'static str>::no_content())
 }
 
+/// Approve a pending destination, it resolves right away
+///
+/// Request:
+/// ```sh
+/// curl -v -XPOST \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/destinations/<uuid>/approve
+/// ```
+///
+/// Response
+/// ```json
+/// { "data": { "id": "<uuid>", "approvalStatus": "approved" ... } }
+/// ```
+pub async fn approve(
+    audit_trail: AuditTrail,
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+    PathParameters(destination_id): PathParameters<Uuid>,
+) -> Result<Success<DestinationResponse>, Error> {
+    current_user.is_allowed(Action::ReviewDestinations)?;
+
+    let destination = fetch_pending_destination(&database, &destination_id).await?;
+
+    let approved_destination = database
+        .approve_destination(&destination, &current_user)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    root_settings
+        .slug_cache
+        .invalidate(&database, &approved_destination.slug)
+        .await;
+
+    audit_trail
+        .register(AuditEntry::ApproveDestination(&approved_destination))
+        .await;
+
+    notify_creator(
+        &database,
+        &root_settings,
+        &approved_destination,
+        &Notification::ApprovedDestination {
+            slug: approved_destination.slug.clone(),
+        },
+    )
+    .await;
+
+    Ok(Success::ok(DestinationResponse::from_destination(
+        approved_destination,
+    )))
+}
+
+/// Reject a pending destination, it never resolves
+///
+/// Request:
+/// ```sh
+/// curl -v -XPOST \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/destinations/<uuid>/reject
+/// ```
+///
+/// Response
+/// ```json
+/// { "data": { "id": "<uuid>", "approvalStatus": "rejected" ... } }
+/// ```
+pub async fn reject(
+    audit_trail: AuditTrail,
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+    PathParameters(destination_id): PathParameters<Uuid>,
+) -> Result<Success<DestinationResponse>, Error> {
+    current_user.is_allowed(Action::ReviewDestinations)?;
+
+    let destination = fetch_pending_destination(&database, &destination_id).await?;
+
+    let rejected_destination = database
+        .reject_destination(&destination, &current_user)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    root_settings
+        .slug_cache
+        .invalidate(&database, &rejected_destination.slug)
+        .await;
+
+    audit_trail
+        .register(AuditEntry::RejectDestination(&rejected_destination))
+        .await;
+
+    notify_creator(
+        &database,
+        &root_settings,
+        &rejected_destination,
+        &Notification::RejectedDestination {
+            slug: rejected_destination.slug.clone(),
+        },
+    )
+    .await;
+
+    Ok(Success::ok(DestinationResponse::from_destination(
+        rejected_destination,
+    )))
+}
+
+/// Maximum number of extra headers of a destination
+const MAX_HEADERS: usize = 20;
+
+/// Maximum number of characters of the source of a destination
+const MAX_SOURCE_LENGTH: usize = 64;
+
+/// Headers that are part of the redirect or the connection, these can not be set by a destination
+const FORBIDDEN_HEADERS: [&str; 11] = [
+    "cache-control",
+    "connection",
+    "content-length",
+    "content-type",
+    "keep-alive",
+    "location",
+    "set-cookie",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Default lifetime of a signed link, in seconds
+const DEFAULT_SIGNED_LINK_EXPIRES_IN: i64 = 60 * 60;
+
+/// Maximum lifetime of a signed link, in seconds
+const MAX_SIGNED_LINK_EXPIRES_IN: i64 = 60 * 60 * 24 * 365;
+
+/// Sign destination form
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignDestinationForm {
+    /// Lifetime of the signed link in seconds, defaults to an hour
+    expires_in: Option<i64>,
+}
+
+/// Signed link response going to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedLinkResponse {
+    /// Path and query of the signed link, to be used with the host of Shurly
+    pub path: String,
+
+    /// Moment the signed link expires
+    pub expires_at: NaiveDateTime,
+}
+
+/// Mint a signed link for a private destination
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     -d '{ "expiresIn": 3600 }' \
+///     http://localhost:7000/api/destinations/<uuid>/sign
+/// ```
+///
+/// Response
+/// ```json
+/// { "data": { "path": "/some-easy-name?expires=<timestamp>&signature=<signature>" ... } }
+/// ```
+pub async fn sign(
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+    PathParameters(destination_id): PathParameters<Uuid>,
+    Form(form): Form<SignDestinationForm>,
+) -> Result<Success<SignedLinkResponse>, Error> {
+    current_user.is_allowed(Action::UpdateDestinations)?;
+
+    let destination = fetch_destination(&database, &destination_id).await?;
+
+    if !destination.is_private {
+        return Err(Error::bad_request("Destination is not private"));
+    }
+
+    let expires_at = signed_link_expires_at(form.expires_in)?;
+
+    let query = root_settings
+        .signing_key
+        .sign(&destination.id.to_string(), expires_at);
+
+    Ok(Success::ok(SignedLinkResponse {
+        path: format!("/{}?{query}", encode_slug(&destination.slug)),
+        expires_at: expires_at.naive_utc(),
+    }))
+}
+
+/// Mint a signed link to the stats of a destination, read-only access without an account; the
+/// stats do not have to be public
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     -d '{ "expiresIn": 604800 }' \
+///     http://localhost:7000/api/destinations/<uuid>/stats-link
+/// ```
+///
+/// Response
+/// ```json
+/// { "data": { "path": "/some-easy-name+stats?expires=<timestamp>&signature=<signature>" ... } }
+/// ```
+pub async fn stats_link(
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+    PathParameters(destination_id): PathParameters<Uuid>,
+    Form(form): Form<SignDestinationForm>,
+) -> Result<Success<SignedLinkResponse>, Error> {
+    current_user.is_allowed(Action::ViewDestinations)?;
+
+    let destination = fetch_destination(&database, &destination_id).await?;
+
+    let expires_at = signed_link_expires_at(form.expires_in)?;
+
+    let query = root_settings
+        .signing_key
+        .sign_stats(&destination.id.to_string(), expires_at);
+
+    Ok(Success::ok(SignedLinkResponse {
+        path: format!("/{}+stats?{query}", encode_slug(&destination.slug)),
+        expires_at: expires_at.naive_utc(),
+    }))
+}
+
+/// Moment a signed link expires, an hour from now by default
+fn signed_link_expires_at(expires_in: Option<i64>) -> Result<DateTime<Utc>, Error> {
+    let expires_in = expires_in.unwrap_or(DEFAULT_SIGNED_LINK_EXPIRES_IN);
+
+    if !(1..=MAX_SIGNED_LINK_EXPIRES_IN).contains(&expires_in) {
+        return Err(
+            Error::bad_request("Invalid expiry").with_description(format!(
+                "Signed links expire in 1 to {MAX_SIGNED_LINK_EXPIRES_IN} seconds"
+            )),
+        );
+    }
+
+    Ok((Utc::now() + TimeDelta::seconds(expires_in)).trunc_subsecs(0))
+}
+
+/// Validate the Open Graph image URL, an empty string is allowed to remove the image
+fn validate_og_image(og_image: Option<&str>) -> Result<(), Error> {
+    if let Some(og_image) = og_image.filter(|og_image| !og_image.is_empty()) {
+        parse_url(og_image)?;
+    }
+
+    Ok(())
+}
+
+/// Validate the script, an empty string is allowed to remove the script
+fn validate_script(root_settings: &RootSettings, script: Option<&str>) -> Result<(), Error> {
+    if let Some(script) = script.filter(|script| !script.is_empty()) {
+        root_settings
+            .scripts
+            .validate(script)
+            .map_err(|err| Error::bad_request(format!("Invalid script: {err}")))?;
+    }
+
+    Ok(())
+}
+
+/// Validate the extra headers of the redirect, as JSON to store, an empty string for no headers
+///
+/// Headers that are part of the redirect itself, like `Location`, can not be overridden
+fn stored_headers(headers: &BTreeMap<String, String>) -> Result<String, Error> {
+    if headers.len() > MAX_HEADERS {
+        return Err(Error::bad_request("Too many headers")
+            .with_description(format!("A destination has at most {MAX_HEADERS} headers")));
+    }
+
+    for (name, value) in headers {
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| Error::bad_request(format!("Invalid header name: {name}")))?;
+
+        if FORBIDDEN_HEADERS.contains(&header_name.as_str()) {
+            return Err(Error::bad_request(format!("Header can not be set: {name}")));
+        }
+
+        HeaderValue::from_str(value)
+            .map_err(|_| Error::bad_request(format!("Invalid value of header: {name}")))?;
+    }
+
+    if headers.is_empty() {
+        return Ok(String::new());
+    }
+
+    serde_json::to_string(headers).map_err(Error::internal_server_error)
+}
+
+/// Parse the tags of a destination, trimmed and without duplicates
+pub fn parse_tags(tags: &[String]) -> Result<Vec<String>, Error> {
+    destinations::normalize_tags(tags).ok_or_else(|| Error::bad_request("Tags can not be empty"))
+}
+
+/// Parse the source of a destination, trimmed, an empty string is no source
+fn parse_source(source: &str) -> Result<&str, Error> {
+    let source = source.trim();
+
+    if source.chars().count() > MAX_SOURCE_LENGTH {
+        return Err(
+            Error::bad_request("Source is too long").with_description(format!(
+                "A source has at most {MAX_SOURCE_LENGTH} characters"
+            )),
+        );
+    }
+
+    Ok(source)
+}
+
+/// Parse the slug of a new destination, which can not be a reserved slug and has to follow the
+/// policy of the deployment
+pub fn parse_new_slug(slug: &str, policy: &SlugPolicy) -> Result<String, Error> {
+    let slug = parse_slug(slug)?;
+
+    if slug.starts_with("api/") {
+        return Err(
+            Error::bad_request("Slug can not start with 'api/'").with_code(ErrorCode::SlugReserved)
+        );
+    }
+
+    if RESERVED_SLUGS.contains(&slug.as_str()) {
+        return Err(Error::bad_request("Slug is reserved").with_code(ErrorCode::SlugReserved));
+    }
+
+    match policy.check(&slug) {
+        Ok(()) => Ok(slug),
+        Err(SlugViolation::TooLong(max_length)) => Err(Error::bad_request("Slug is too long")
+            .with_code(ErrorCode::SlugTooLong)
+            .with_description(format!("Slugs have at most {max_length} characters"))),
+        Err(SlugViolation::Character(ch)) => Err(Error::bad_request(format!(
+            r#"Slug can not contain "{ch}""#
+        ))
+        .with_code(ErrorCode::InvalidSlugChar)
+        .with_description(format!("Slugs consist of {}", policy.describe_characters()))),
+        Err(SlugViolation::EmptySegment) => Err(Error::bad_request(
+            "Slug can not have an empty path segment",
+        )
+        .with_code(ErrorCode::InvalidSlug)),
+        Err(SlugViolation::Reserved) => {
+            Err(Error::bad_request("Slug is reserved").with_code(ErrorCode::SlugReserved))
+        }
+    }
+}
+
+/// The slug derived from the URL, the shortest that is free or already has the URL
+async fn hashed_slug(
+    database: &Database,
+    root_settings: &RootSettings,
+    domain: Option<&str>,
+    url: &Url,
+) -> Result<String, Error> {
+    if !root_settings.hashed_slugs.is_enabled() {
+        return Err(Error::bad_request("Slug is required")
+            .with_code(ErrorCode::SlugRequired)
+            .with_description("Slugs are not derived from the URL"));
+    }
+
+    for slug in root_settings.hashed_slugs.candidates(url) {
+        let slug = parse_new_slug(&slug, &root_settings.slug_policy)?;
+
+        match database
+            .find_single_destination_in_namespace(domain, &slug)
+            .await
+            .map_err(Error::internal_server_error)?
+        {
+            Some(destination) if destination.is_deleted() || destination.url != url.as_str() => {}
+            _ => return Ok(slug),
+        }
+    }
+
+    Err(Error::bad_request("No free slug for the URL").with_code(ErrorCode::SlugConflict))
+}
+
+/// Refuse slugs confusable with a Latin slug, when configured
+fn check_confusable_slug(root_settings: &RootSettings, slug: &str) -> Result<(), Error> {
+    if root_settings.confusable_slugs.allows_slug(slug) {
+        Ok(())
+    } else {
+        Err(Error::bad_request("Slug is confusable")
+            .with_code(ErrorCode::SlugConfusable)
+            .with_description(
+                "Slug mixes scripts or looks like Latin letters, like a Cyrillic `а`",
+            ))
+    }
+}
+
+/// Refuse URLs with a hostname confusable with a Latin hostname, when configured
+fn check_confusable_url(root_settings: &RootSettings, url: &Url) -> Result<(), Error> {
+    if root_settings
+        .confusable_urls
+        .allows(url.as_str(), idn::has_confusable_host(url))
+    {
+        Ok(())
+    } else {
+        Err(Error::bad_request("URL has a confusable hostname")
+            .with_code(ErrorCode::UrlConfusable)
+            .with_description(
+                "Hostname mixes scripts or looks like Latin letters, like a Cyrillic `а`",
+            ))
+    }
+}
+
+/// Refuse URLs flagged by the blocklist or Safe Browsing, allowed when they can not be checked
+async fn check_url_reputation(root_settings: &RootSettings, url: &Url) -> Result<(), Error> {
+    match root_settings
+        .url_reputation
+        .flagged_reason(url.as_str())
+        .await
+    {
+        Some(reason) => Err(Error::bad_request("URL is flagged as unsafe")
+            .with_code(ErrorCode::UrlUnsafe)
+            .with_description(&reason)),
+        None => Ok(()),
+    }
+}
+
+/// Check the quota of the user leaves room for the number of new destinations
+///
+/// Too many destinations is a bad request, too many hits this month is too many requests
+pub async fn check_quota(
+    database: &Database,
+    root_settings: &RootSettings,
+    user: &User,
+    new_destinations: i64,
+) -> Result<(), Error> {
+    let user_quota = database
+        .find_user_quota(user)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    let quota = root_settings.quotas.of(user.role, user_quota.as_ref());
+    if quota == Quota::default() {
+        return Ok(());
+    }
+
+    let usage = database
+        .find_usage(user)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    if let Some(max_destinations) = quota.max_destinations {
+        if usage.destinations + new_destinations > max_destinations {
+            return Err(Error::bad_request("Quota of destinations reached")
+                .with_code(ErrorCode::QuotaReached)
+                .with_description(format!("Up to {max_destinations} destinations")));
+        }
+    }
+
+    if let Some(max_hits_per_month) = quota.max_hits_per_month {
+        if usage.hits_this_month >= max_hits_per_month {
+            return Err(Error::too_many_requests("Quota of hits reached")
+                .with_code(ErrorCode::QuotaReached)
+                .with_description(format!("Up to {max_hits_per_month} hits per month")));
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse the optional domain of a destination
+pub fn parse_domain(domain: Option<&str>) -> Result<Option<String>, Error> {
+    domain
+        .map(|domain| {
+            domains::normalize(domain).ok_or_else(|| {
+                Error::bad_request("Invalid domain")
+                    .with_description("Domain should be a hostname, like `sho.rt`")
+            })
+        })
+        .transpose()
+}
+
+/// Add the available alternatives for the taken slug of the destination to the error, as
+/// `details`
+///
+/// ```json
+/// { "error": "Slug already exists", "code": "slug_conflict", "details": { "alternatives": ["launch-2", "launch-2025"] } }
+/// ```
+pub async fn with_slug_alternatives(
+    database: &Database,
+    new_destination: &NewDestination,
+    error: Error,
+) -> Result<Error, Error> {
+    let candidates = destinations::slug_alternatives(new_destination.slug(), Utc::now().year());
+
+    let taken = database
+        .find_taken_slugs_in_namespace(new_destination.domain(), &candidates)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    let alternatives = candidates
+        .into_iter()
+        .filter(|candidate| !taken.contains(candidate))
+        .collect::<Vec<_>>();
+
+    Ok(error.with_details(json!({ "alternatives": alternatives })))
+}
+
+/// Refuse URLs redirecting back to the slug via Shurly itself
+async fn check_redirect_loop(
+    database: &Database,
+    root_settings: &RootSettings,
+    headers: &HeaderMap,
+    domain: Option<&str>,
+    slug: &str,
+    url: &Url,
+) -> Result<(), Error> {
+    let redirect_loop = root_settings
+        .loop_detection
+        .detect(
+            database,
+            &root_settings.slug_cache,
+            headers,
+            domain,
+            slug,
+            url,
+        )
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    if let Some(redirect_loop) = redirect_loop {
+        return Err(Error::bad_request("Redirect loop detected")
+            .with_code(ErrorCode::RedirectLoop)
+            .with_description(redirect_loop.description()));
+    }
+
+    Ok(())
+}
+
+/// Fetch the template of a new destination by its name
+async fn fetch_template_by_name(
+    database: &Database,
+    name: &str,
+) -> Result<DestinationTemplate, Error> {
+    database
+        .find_single_destination_template_by_name(name)
+        .await
+        .map_err(Error::internal_server_error)?
+        .filter(|template| !template.is_deleted())
+        .map_or_else(|| Err(Error::bad_request("Template not found")), Ok)
+}
+
 /// Fetch destination from database
 async fn fetch_destination(
     database: &Database,
@@ -332,3 +1576,17 @@ async fn fetch_destination(
         .map_err(Error::internal_server_error)?
         .map_or_else(|| Err(Error::not_found("Destination not found")), Ok)
 }
+
+/// Fetch destination from database, only when it is awaiting approval
+async fn fetch_pending_destination(
+    database: &Database,
+    destination_id: &Uuid,
+) -> Result<Destination, Error> {
+    let destination = fetch_destination(database, destination_id).await?;
+
+    if destination.approval_status() == ApprovalStatus::Pending {
+        Ok(destination)
+    } else {
+        Err(Error::bad_request("Destination is not pending"))
+    }
+}
@@ -0,0 +1,328 @@
+//! Domains API endpoints
+//!
+//! Everything related to the management of the domains served by Shurly
+
+use axum::Extension;
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::database::AuditEntry;
+use crate::database::CreateDomainValues;
+use crate::database::Database;
+use crate::database::UpdateDomainValues;
+use crate::domains;
+use crate::domains::Domain;
+use crate::permissions::Action;
+
+use super::parse_url;
+use super::AuditTrail;
+use super::CurrentUser;
+use super::ETag;
+use super::Error;
+use super::Form;
+use super::IfNoneMatch;
+use super::Patch;
+use super::PathParameters;
+use super::Success;
+
+/// Domain response going to the user
+///
+/// Basically filtering which fields are shown to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DomainResponse {
+    /// Domain ID
+    pub id: Uuid,
+
+    /// Hostname of the domain
+    pub hostname: String,
+
+    /// Redirect for slugs without a destination on the domain
+    pub fallback_url: Option<String>,
+
+    /// HTML of the 404 page of the domain
+    pub not_found_template: Option<String>,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+
+    /// Last updated at
+    pub updated_at: NaiveDateTime,
+}
+
+impl DomainResponse {
+    /// Create a response from a [`Domain`](Domain)
+    ///
+    /// Basically filtering which fields are shown to the user
+    fn from_domain(domain: Domain) -> Self {
+        Self {
+            id: domain.id,
+            hostname: domain.hostname,
+            fallback_url: domain.fallback_url,
+            not_found_template: domain.not_found_template,
+            created_at: domain.created_at,
+            updated_at: domain.updated_at,
+        }
+    }
+
+    /// Create a response from multiple [`Domain`](Domain)s
+    ///
+    /// Basically filtering which fields are shown to the user
+    fn from_domain_multiple(mut domains: Vec<Domain>) -> Vec<Self> {
+        domains
+            .drain(..)
+            .map(Self::from_domain)
+            .collect::<Vec<Self>>()
+    }
+}
+
+/// List all domains
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/domains
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": [ { "id": "<uuid>", "hostname": "go.acme.com" ... } ] }
+/// ```
+///
+/// Tagged with an `ETag`, a `304 Not modified` is returned when matching `If-None-Match`
+pub async fn list(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    if_none_match: IfNoneMatch,
+) -> Result<Success<Vec<DomainResponse>>, Error> {
+    current_user.is_allowed(Action::ManageDomains)?;
+
+    let domains = database
+        .find_all_domains()
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    let etag = ETag::from_updates(domains.iter().map(|domain| (domain.id, domain.updated_at)));
+
+    Ok(Success::ok(DomainResponse::from_domain_multiple(domains)).with_etag(etag, &if_none_match))
+}
+
+/// Get a single domain
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/domains/<uuid>
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "id": "<uuid>", "hostname": "go.acme.com" ... } }
+/// ```
+///
+/// Tagged with an `ETag`, a `304 Not modified` is returned when matching `If-None-Match`
+pub async fn single(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    if_none_match: IfNoneMatch,
+    PathParameters(domain_id): PathParameters<Uuid>,
+) -> Result<Success<DomainResponse>, Error> {
+    current_user.is_allowed(Action::ManageDomains)?;
+
+    let domain = fetch_domain(&database, &domain_id).await?;
+    let etag = ETag::from_updates([(domain.id, domain.updated_at)]);
+
+    Ok(Success::ok(DomainResponse::from_domain(domain)).with_etag(etag, &if_none_match))
+}
+
+/// Create domain form
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateDomainForm {
+    /// Hostname of the domain, like `go.acme.com`
+    hostname: String,
+
+    /// Optional redirect for slugs without a destination on the domain
+    fallback_url: Option<String>,
+
+    /// Optional HTML of the 404 page of the domain, `{suggestions}` is replaced with suggestions
+    /// of similar slugs
+    not_found_template: Option<String>,
+}
+
+/// Register a domain based on the [`CreateDomainForm`](CreateDomainForm) form
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     -d '{ "hostname": "go.acme.com", "fallbackUrl": "https://www.acme.com/" }' \
+///     http://localhost:7000/api/domains
+/// ```
+///
+/// Response
+/// ```json
+/// { "data": { "id": "<uuid>", "hostname": "go.acme.com" ... } }
+/// ```
+pub async fn create(
+    audit_trail: AuditTrail,
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    Form(form): Form<CreateDomainForm>,
+) -> Result<Success<DomainResponse>, Error> {
+    current_user.is_allowed(Action::ManageDomains)?;
+
+    let hostname = domains::normalize(&form.hostname).ok_or_else(|| {
+        Error::bad_request("Invalid domain")
+            .with_description("Domain should be a hostname, like `sho.rt`")
+    })?;
+
+    let fallback_url = if let Some(ref fallback_url) = form.fallback_url {
+        Some(parse_url(fallback_url)?)
+    } else {
+        None
+    };
+
+    let domain = database
+        .find_single_domain_by_hostname(&hostname)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    if let Some(domain) = domain {
+        if domain.is_deleted() {
+            Err(Error::bad_request("Domain already exists and is deleted"))
+        } else {
+            Err(Error::bad_request("Domain already exists"))
+        }
+    } else {
+        let values = CreateDomainValues {
+            user: &current_user,
+            hostname: &hostname,
+            fallback_url: fallback_url.as_ref(),
+            not_found_template: form.not_found_template.as_deref(),
+        };
+
+        let domain = database
+            .create_domain(&values)
+            .await
+            .map_err(Error::internal_server_error)?;
+
+        audit_trail
+            .register(AuditEntry::CreateDomain(&domain))
+            .await;
+
+        Ok(Success::created(DomainResponse::from_domain(domain)))
+    }
+}
+
+/// Update domain form
+///
+/// Fields to update a domain with, all fields are optional and are not touched when not provided;
+/// `null` removes the value
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct UpdateDomainForm {
+    /// New redirect for slugs without a destination, an empty string removes the redirect
+    fallback_url: Patch<String>,
+
+    /// New HTML of the 404 page, an empty string removes the page
+    not_found_template: Patch<String>,
+}
+
+/// Update a domain based on the [`UpdateDomainForm`](UpdateDomainForm) form
+///
+/// The hostname of a domain can not be changed
+///
+/// Request:
+/// ```sh
+/// curl -v -XPATCH -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     -d '{ "fallbackUrl": "" }' \
+///     http://localhost:7000/api/domains/<uuid>
+/// ```
+///
+/// Response
+/// ```json
+/// { "data": { "id": "<uuid>", "hostname": "go.acme.com" ... } }
+/// ```
+pub async fn update(
+    audit_trail: AuditTrail,
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    PathParameters(domain_id): PathParameters<Uuid>,
+    Form(form): Form<UpdateDomainForm>,
+) -> Result<Success<DomainResponse>, Error> {
+    current_user.is_allowed(Action::ManageDomains)?;
+
+    let domain = fetch_domain(&database, &domain_id).await?;
+
+    if let Some(fallback_url) = form
+        .fallback_url
+        .removable()
+        .filter(|fallback_url| !fallback_url.is_empty())
+    {
+        parse_url(fallback_url)?;
+    }
+
+    let values = UpdateDomainValues {
+        fallback_url: form.fallback_url.removable(),
+        not_found_template: form.not_found_template.removable(),
+    };
+
+    let updated_domain = database
+        .update_domain(&domain, &values)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    audit_trail
+        .register(AuditEntry::UpdateDomain(&updated_domain))
+        .await;
+
+    Ok(Success::ok(DomainResponse::from_domain(updated_domain)))
+}
+
+/// Delete a domain
+///
+/// Destinations of the domain are not touched, those keep working on the domain without its
+/// defaults
+///
+/// Request:
+/// ```sh
+/// curl -v -XDELETE \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/domains/<uuid>
+/// ```
+pub async fn delete(
+    audit_trail: AuditTrail,
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    PathParameters(domain_id): PathParameters<Uuid>,
+) -> Result<Success<&'static str>, Error> {
+    current_user.is_allowed(Action::ManageDomains)?;
+
+    let domain = fetch_domain(&database, &domain_id).await?;
+
+    database
+        .delete_domain(&domain)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    audit_trail
+        .register(AuditEntry::DeleteDomain(&domain))
+        .await;
+
+    Ok(Success::<&'static str>::no_content())
+}
+
+/// Fetch domain from database
+async fn fetch_domain(database: &Database, domain_id: &Uuid) -> Result<Domain, Error> {
+    database
+        .find_single_domain_by_id(domain_id)
+        .await
+        .map_err(Error::internal_server_error)?
+        .map_or_else(|| Err(Error::not_found("Domain not found")), Ok)
+}
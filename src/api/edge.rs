@@ -0,0 +1,75 @@
+//! Edge snapshot endpoint, for edge workers serving redirects at the edge
+//!
+//! Authenticated with the key of the edge in the `X-Shurly-Api-Key` header, like the ingestion of
+//! hits, see [`edge`](crate::edge).
+
+use axum::http::HeaderMap;
+use axum::http::Uri;
+use axum::Extension;
+use chrono::DateTime;
+use chrono::Utc;
+
+use crate::database::Database;
+use crate::edge::Snapshot;
+use crate::root::Settings as RootSettings;
+
+use super::hits::authenticate_edge;
+use super::Error;
+use super::Success;
+
+/// Get a snapshot of all slugs, or the changes since the `version` of a previous snapshot
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'X-Shurly-Api-Key: keykeykey' \
+///     'http://localhost:7000/api/edge/snapshot?since=2026-10-15T12:00:00Z'
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "version": "2026-10-15T13:00:00Z", "isFull": false, "destinations": [ ... ], "removed": [ ... ] } }
+/// ```
+pub async fn snapshot(
+    headers: HeaderMap,
+    uri: Uri,
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+) -> Result<Success<Snapshot>, Error> {
+    let edge = authenticate_edge(&root_settings, &headers)?;
+
+    let since = parse_since(&uri)?;
+
+    let snapshot = Snapshot::create(
+        &database,
+        since,
+        root_settings.jobs.deleted_retention_days(),
+    )
+    .await
+    .map_err(Error::internal_server_error)?;
+
+    tracing::debug!(
+        "Snapshot of {} destination(s) for {edge}, full: {}",
+        snapshot.destinations.len(),
+        snapshot.is_full
+    );
+
+    Ok(Success::ok(snapshot))
+}
+
+/// Parse the `since` query parameter, an RFC 3339 moment
+fn parse_since(uri: &Uri) -> Result<Option<DateTime<Utc>>, Error> {
+    let Some(since) = uri.query().and_then(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "since")
+            .map(|(_, value)| value.into_owned())
+    }) else {
+        return Ok(None);
+    };
+
+    DateTime::parse_from_rfc3339(&since)
+        .map(|since| Some(since.with_timezone(&Utc)))
+        .map_err(|_| {
+            Error::bad_request("Invalid since")
+                .with_description("Expected the version of a previous snapshot")
+        })
+}
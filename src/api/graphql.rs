@@ -0,0 +1,431 @@
+//! GraphQL API endpoint
+//!
+//! Read-only access to the destinations with their notes, creators and hits, and the users, in
+//! a single round trip. Changes go through the other endpoints, to keep a single place where they
+//! are validated and registered on the audit trail.
+//!
+//! ```graphql
+//! {
+//!     destinations {
+//!         slug
+//!         url
+//!         notes { content createdBy { username } }
+//!         hits { total daily(days: 7) { day hits } }
+//!     }
+//! }
+//! ```
+
+use async_graphql::Context;
+use async_graphql::EmptyMutation;
+use async_graphql::EmptySubscription;
+use async_graphql::Enum;
+use async_graphql::Object;
+use async_graphql::Result;
+use axum::Extension;
+use axum::Json;
+use chrono::naive::NaiveDate;
+use chrono::naive::NaiveDateTime;
+use chrono::Duration;
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::approval::ApprovalStatus;
+use crate::database::Database;
+use crate::destinations::DailyHits;
+use crate::destinations::Destination;
+use crate::idn;
+use crate::notes::Note;
+use crate::permissions::Action;
+use crate::users::Role;
+use crate::users::User;
+
+use super::CurrentUser;
+use super::Error;
+use super::Form;
+
+/// Maximum depth of the nested fields of a query
+const MAX_DEPTH: usize = 8;
+
+/// Maximum complexity of a query, every field counts as one
+const MAX_COMPLEXITY: usize = 1000;
+
+/// Schema of the GraphQL API
+pub type Schema = async_graphql::Schema<Query, EmptyMutation, EmptySubscription>;
+
+/// Create the schema, with the limits of queries
+pub fn schema() -> Schema {
+    Schema::build(Query, EmptyMutation, EmptySubscription)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+/// Run a GraphQL query
+///
+/// The response is as described by GraphQL, with `data` and `errors`
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     -d '{ "query": "{ destinations { slug hits { total } } }" }' \
+///     http://localhost:7000/api/graphql
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "destinations": [ { "slug": "some-easy-name", "hits": { "total": 42 } } ] } }
+/// ```
+pub async fn execute(
+    Extension(database): Extension<Database>,
+    Extension(schema): Extension<Schema>,
+    current_user: CurrentUser,
+    Form(request): Form<async_graphql::Request>,
+) -> Result<Json<async_graphql::Response>, Error> {
+    let response = schema
+        .execute(request.data(database).data(current_user))
+        .await;
+
+    Ok(Json(response))
+}
+
+/// Check if the current user is allowed the action
+fn allowed<'a>(ctx: &Context<'a>, action: Action) -> Result<&'a CurrentUser> {
+    let current_user = ctx.data::<CurrentUser>()?;
+    current_user.is_allowed(action)?;
+
+    Ok(current_user)
+}
+
+/// Queries of the GraphQL API
+pub struct Query;
+
+#[Object]
+impl Query {
+    /// All destinations, newest first
+    async fn destinations(&self, ctx: &Context<'_>) -> Result<Vec<DestinationObject>> {
+        allowed(ctx, Action::ViewDestinations)?;
+
+        let destinations = ctx.data::<Database>()?.find_all_destinations().await?;
+
+        Ok(destinations.into_iter().map(DestinationObject).collect())
+    }
+
+    /// A single destination
+    async fn destination(&self, ctx: &Context<'_>, id: Uuid) -> Result<Option<DestinationObject>> {
+        allowed(ctx, Action::ViewDestinations)?;
+
+        let destination = ctx
+            .data::<Database>()?
+            .find_single_destination_by_id(&id)
+            .await?;
+
+        Ok(destination.map(DestinationObject))
+    }
+
+    /// All users, only for admins
+    async fn users(&self, ctx: &Context<'_>) -> Result<Vec<UserObject>> {
+        allowed(ctx, Action::ManageUsers)?;
+
+        let users = ctx.data::<Database>()?.find_all_users().await?;
+
+        Ok(users.into_iter().map(UserObject).collect())
+    }
+
+    /// The current user
+    #[allow(clippy::unused_async)] // resolvers have to be asynchronous
+    async fn me(&self, ctx: &Context<'_>) -> Result<UserObject> {
+        let current_user = ctx.data::<CurrentUser>()?;
+
+        Ok(UserObject(User::clone(current_user)))
+    }
+}
+
+/// Role of a user
+#[derive(Clone, Copy, Debug, Enum, PartialEq, Eq)]
+#[graphql(name = "Role")]
+enum RoleObject {
+    /// Manage users, destinations and notes
+    Admin,
+
+    /// Manage destinations and notes
+    Manager,
+}
+
+/// A user
+struct UserObject(User);
+
+#[Object(name = "User")]
+impl UserObject {
+    /// User ID
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    /// Username
+    async fn username(&self) -> &str {
+        &self.0.username
+    }
+
+    /// Role of the user
+    async fn role(&self) -> RoleObject {
+        match self.0.role {
+            Role::Admin => RoleObject::Admin,
+            Role::Manager => RoleObject::Manager,
+        }
+    }
+}
+
+/// Status of the approval of a destination
+#[derive(Clone, Copy, Debug, Enum, PartialEq, Eq)]
+#[graphql(name = "ApprovalStatus")]
+enum ApprovalStatusObject {
+    /// Awaiting the review of an admin
+    Pending,
+
+    /// Approved, or created without the need for approval
+    Approved,
+
+    /// Rejected by an admin
+    Rejected,
+}
+
+/// A destination
+struct DestinationObject(Destination);
+
+#[Object(name = "Destination")]
+impl DestinationObject {
+    /// Destination ID
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    /// Slug used to identify the destination by the root
+    async fn slug(&self) -> &str {
+        &self.0.slug
+    }
+
+    /// Domain the slug is used on, all domains when empty
+    async fn domain(&self) -> Option<&str> {
+        self.0.domain.as_deref()
+    }
+
+    /// URL the root redirects to
+    async fn url(&self) -> &str {
+        &self.0.url
+    }
+
+    /// URL with its hostname in unicode, differs for internationalized domain names
+    async fn unicode_url(&self) -> String {
+        idn::unicode_url(&self.0.url)
+    }
+
+    /// Is the redirect permanent
+    async fn is_permanent(&self) -> bool {
+        self.0.is_permanent
+    }
+
+    /// Is the redirect a meta refresh page
+    async fn is_meta_refresh(&self) -> bool {
+        self.0.is_meta_refresh
+    }
+
+    /// Does the redirect need a signed link
+    async fn is_private(&self) -> bool {
+        self.0.is_private
+    }
+
+    /// Are the stats served publicly
+    async fn is_stats_public(&self) -> bool {
+        self.0.is_stats_public
+    }
+
+    /// Are the hits never recorded
+    async fn is_untracked(&self) -> bool {
+        self.0.is_untracked
+    }
+
+    /// Open Graph title
+    async fn og_title(&self) -> Option<&str> {
+        self.0.og_title.as_deref()
+    }
+
+    /// Open Graph description
+    async fn og_description(&self) -> Option<&str> {
+        self.0.og_description.as_deref()
+    }
+
+    /// Open Graph image URL
+    async fn og_image(&self) -> Option<&str> {
+        self.0.og_image.as_deref()
+    }
+
+    /// Script deciding the URL to redirect to
+    async fn script(&self) -> Option<&str> {
+        self.0.script.as_deref()
+    }
+
+    /// Extra headers of the redirect, a JSON object of names and values
+    async fn headers(&self) -> Option<&str> {
+        self.0.headers.as_deref()
+    }
+
+    /// Tags to group destinations by
+    async fn tags(&self) -> &[String] {
+        &self.0.tags
+    }
+
+    /// Channel the short link is handed out in, like `print-flyer`
+    async fn source(&self) -> Option<&str> {
+        self.0.source.as_deref()
+    }
+
+    /// Status of the approval by an admin, only approved destinations resolve
+    async fn approval_status(&self) -> ApprovalStatusObject {
+        match self.0.approval_status() {
+            ApprovalStatus::Pending => ApprovalStatusObject::Pending,
+            ApprovalStatus::Approved => ApprovalStatusObject::Approved,
+            ApprovalStatus::Rejected => ApprovalStatusObject::Rejected,
+        }
+    }
+
+    /// When the URL was flagged as unsafe, disabling the destination
+    async fn flagged_at(&self) -> Option<NaiveDateTime> {
+        self.0.flagged_at
+    }
+
+    /// Why the URL was flagged
+    async fn flagged_reason(&self) -> Option<&str> {
+        self.0.flagged_reason.as_deref()
+    }
+
+    /// Creation date
+    async fn created_at(&self) -> NaiveDateTime {
+        self.0.created_at
+    }
+
+    /// Last updated at
+    async fn updated_at(&self) -> NaiveDateTime {
+        self.0.updated_at
+    }
+
+    /// The user that created the destination, unless deleted
+    async fn created_by(&self, ctx: &Context<'_>) -> Result<Option<UserObject>> {
+        let user = ctx
+            .data::<Database>()?
+            .find_single_user_by_id(&self.0.user_id)
+            .await?;
+
+        Ok(user.map(UserObject))
+    }
+
+    /// Notes of the destination, newest first
+    async fn notes(&self, ctx: &Context<'_>) -> Result<Vec<NoteObject>> {
+        let notes = ctx
+            .data::<Database>()?
+            .find_all_notes_by_destination(&self.0)
+            .await?;
+
+        Ok(notes.into_iter().map(NoteObject).collect())
+    }
+
+    /// Number of unique visitors of the hits that are not pruned yet, IPv6 addresses by their
+    /// prefix
+    async fn unique_visitors(&self, ctx: &Context<'_>) -> Result<i64> {
+        Ok(ctx
+            .data::<Database>()?
+            .count_unique_visitors(&self.0)
+            .await?)
+    }
+
+    /// Hits of the destination
+    async fn hits(&self, ctx: &Context<'_>) -> Result<HitsObject> {
+        let daily_hits = ctx.data::<Database>()?.find_daily_hits(&self.0).await?;
+
+        Ok(HitsObject(daily_hits))
+    }
+}
+
+/// A note of a destination
+struct NoteObject(Note);
+
+#[Object(name = "Note")]
+impl NoteObject {
+    /// Note ID
+    async fn id(&self) -> Uuid {
+        self.0.id
+    }
+
+    /// Content of the note
+    async fn content(&self) -> &str {
+        &self.0.content
+    }
+
+    /// Creation date
+    async fn created_at(&self) -> NaiveDateTime {
+        self.0.created_at
+    }
+
+    /// Last updated at
+    async fn updated_at(&self) -> NaiveDateTime {
+        self.0.updated_at
+    }
+
+    /// The user that created the note, unless deleted
+    async fn created_by(&self, ctx: &Context<'_>) -> Result<Option<UserObject>> {
+        let user = ctx
+            .data::<Database>()?
+            .find_single_user_by_id(&self.0.user_id)
+            .await?;
+
+        Ok(user.map(UserObject))
+    }
+}
+
+/// Hits of a destination, pruned hits included
+struct HitsObject(Vec<DailyHits>);
+
+#[Object(name = "Hits")]
+impl HitsObject {
+    /// Number of hits since the destination was created
+    async fn total(&self) -> i64 {
+        self.0.iter().map(|daily_hits| daily_hits.hits).sum()
+    }
+
+    /// Number of hits per day of the last days, 30 by default and including today; days without
+    /// hits are left out
+    async fn daily(&self, #[graphql(default = 30)] days: i64) -> Vec<DailyHitsObject> {
+        let since = Utc::now().date_naive() - Duration::days(days.saturating_sub(1));
+
+        self.0
+            .iter()
+            .filter(|daily_hits| daily_hits.day >= since)
+            .map(|daily_hits| DailyHitsObject {
+                day: daily_hits.day,
+                hits: daily_hits.hits,
+            })
+            .collect()
+    }
+}
+
+/// Number of hits on a single day
+struct DailyHitsObject {
+    /// The day
+    day: NaiveDate,
+
+    /// The number of hits
+    hits: i64,
+}
+
+#[Object(name = "DailyHits")]
+impl DailyHitsObject {
+    /// The day
+    async fn day(&self) -> NaiveDate {
+        self.day
+    }
+
+    /// Number of hits on the day
+    async fn hits(&self) -> i64 {
+        self.hits
+    }
+}
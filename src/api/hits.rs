@@ -0,0 +1,232 @@
+//! Hit ingestion endpoint, for edge workers or CDNs serving cached redirects
+//!
+//! Authenticated with the key of the edge in the `X-Shurly-Api-Key` header, see
+//! [`ingest`](crate::ingest).
+
+use std::net::IpAddr;
+
+use axum::http::HeaderMap;
+use axum::Extension;
+use chrono::DateTime;
+use chrono::SubsecRound;
+use chrono::TimeDelta;
+use chrono::Utc;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::database::Database;
+use crate::destinations::Destination;
+use crate::hit_buffer::Hit;
+use crate::root::referrer_host;
+use crate::root::DoNotTrack;
+use crate::root::Settings as RootSettings;
+
+use super::parse_domain;
+use super::parse_slug;
+use super::Error;
+use super::ErrorCode;
+use super::Form;
+use super::Success;
+
+/// Header with the key of the edge
+const API_KEY_HEADER: &str = "x-shurly-api-key";
+
+/// Maximum number of hits of a single batch
+const MAX_HITS: usize = 1000;
+
+/// Margin for the clocks of the edges, hits later than now plus the margin are refused
+const CLOCK_SKEW: TimeDelta = TimeDelta::minutes(5);
+
+/// Ingest form
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestForm {
+    /// The hits, as served by the edge
+    hits: Vec<ReportedHit>,
+}
+
+/// A hit served by an edge
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportedHit {
+    /// Slug of the destination
+    slug: String,
+
+    /// Domain the slug is served on, all domains when not provided
+    domain: Option<String>,
+
+    /// IP address of the visitor
+    ip_address: Option<IpAddr>,
+
+    /// User agent of the visitor
+    user_agent: Option<String>,
+
+    /// URL of the referring page, only its host is kept
+    referrer: Option<String>,
+
+    /// Did the visitor ask to not be tracked, with `DNT` or `Sec-GPC`?
+    #[serde(default)]
+    do_not_track: bool,
+
+    /// Moment of the hit, now when not provided
+    created_at: Option<DateTime<Utc>>,
+}
+
+/// Ingest response
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestResponse {
+    /// Number of saved hits
+    pub ingested: usize,
+
+    /// Number of hits without a destination, or not tracked
+    pub skipped: usize,
+}
+
+/// Save the hits of the [`IngestForm`](IngestForm), all or nothing
+///
+/// Every hit is validated before any is saved, an invalid hit is named by its index. Hits of
+/// slugs without a destination and of untracked destinations are skipped. The hits count in the
+/// stats and rollups, and go to the webhooks and the message broker, like the hits of the root.
+/// Do Not Track is handled like the root does, with `DO_NOT_TRACK`.
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'X-Shurly-Api-Key: keykeykey' \
+///     -d '{ "hits": [
+///         { "slug": "launch", "ipAddress": "192.0.2.1", "createdAt": "2026-10-15T12:00:00Z" }
+///     ] }' \
+///     http://localhost:7000/api/hits/ingest
+/// ```
+///
+/// Response
+/// ```json
+/// { "data": { "ingested": 1, "skipped": 0 } }
+/// ```
+pub async fn ingest(
+    headers: HeaderMap,
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    Form(form): Form<IngestForm>,
+) -> Result<Success<IngestResponse>, Error> {
+    let edge = authenticate_edge(&root_settings, &headers)?;
+
+    if form.hits.len() > MAX_HITS {
+        return Err(Error::bad_request(format!(
+            "Batch with more than {MAX_HITS} hits"
+        )));
+    }
+
+    let now = Utc::now();
+    let reported_hits = form.hits.len();
+
+    let mut hits = Vec::with_capacity(form.hits.len());
+    let mut destinations = Vec::with_capacity(form.hits.len());
+    for (index, reported) in form.hits.into_iter().enumerate() {
+        let validated = validate(&database, &root_settings, reported, now)
+            .await
+            .map_err(|err| err.with_prefix(format!("Hit {index}")))?;
+
+        if let Some((hit, destination)) = validated {
+            hits.push(hit);
+            destinations.push(destination);
+        }
+    }
+
+    let skipped = reported_hits - hits.len();
+
+    if !hits.is_empty() {
+        database
+            .save_hits(&hits)
+            .await
+            .map_err(Error::internal_server_error)?;
+    }
+
+    for (hit, destination) in hits.iter().zip(&destinations) {
+        root_settings.broker.publish_hit(
+            destination,
+            hit.ip_address.as_ref(),
+            hit.user_agent.as_deref(),
+        );
+    }
+
+    tracing::debug!(
+        "Ingested {} hit(s) of {edge}, skipped {skipped}",
+        hits.len()
+    );
+
+    Ok(Success::ok(IngestResponse {
+        ingested: hits.len(),
+        skipped,
+    }))
+}
+
+/// Name of the edge with the key of the request
+///
+/// Responds with a `404 Not Found` without keys, edges are not enabled
+pub fn authenticate_edge(
+    root_settings: &RootSettings,
+    headers: &HeaderMap,
+) -> Result<String, Error> {
+    if !root_settings.ingest_keys.is_enabled() {
+        return Err(Error::not_found("Edges are not enabled"));
+    }
+
+    let key = headers
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    root_settings
+        .ingest_keys
+        .edge(key)
+        .map(ToString::to_string)
+        .ok_or_else(|| Error::forbidden("Invalid API key").with_code(ErrorCode::InvalidApiKey))
+}
+
+/// Validate a reported hit, `None` when it has no destination or is not tracked
+async fn validate(
+    database: &Database,
+    root_settings: &RootSettings,
+    reported: ReportedHit,
+    now: DateTime<Utc>,
+) -> Result<Option<(Hit, Destination)>, Error> {
+    let slug = parse_slug(&reported.slug)?;
+    let domain = parse_domain(reported.domain.as_deref())?;
+
+    let created_at = reported.created_at.unwrap_or(now);
+    if created_at > now + CLOCK_SKEW {
+        return Err(Error::bad_request("Hit in the future")
+            .with_description("The moment of the hit should be in the past"));
+    }
+
+    // pending and rejected destinations are never served
+    let Some(destination) = root_settings
+        .slug_cache
+        .find(database, domain.as_deref(), &slug)
+        .await
+        .map_err(Error::internal_server_error)?
+        .filter(|destination| destination.is_approved() && !destination.is_untracked)
+    else {
+        return Ok(None);
+    };
+
+    let (ip_address, user_agent) = match reported.do_not_track.then_some(root_settings.do_not_track)
+    {
+        Some(DoNotTrack::Skip) => return Ok(None),
+        Some(DoNotTrack::Anonymize) => (None, None),
+        Some(DoNotTrack::Ignore) | None => (reported.ip_address, reported.user_agent),
+    };
+
+    let hit = Hit {
+        destination_id: destination.id,
+        ip_address: ip_address.map(|ip_address| root_settings.client_ip.visitor(ip_address)),
+        user_agent,
+        referrer: reported.referrer.as_deref().and_then(referrer_host),
+        // the database stores up to microseconds
+        created_at: created_at.naive_utc().trunc_subsecs(6),
+    };
+
+    Ok(Some((hit, destination)))
+}
@@ -0,0 +1,99 @@
+//! Jobs API endpoint
+//!
+//! Insight in the recurring jobs of an instance, handy when the hits look off
+
+use axum::Extension;
+use chrono::DateTime;
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::jobs::Job;
+use crate::jobs::Outcome;
+use crate::jobs::Status;
+use crate::permissions::Action;
+use crate::root::Settings as RootSettings;
+
+use super::CurrentUser;
+use super::Error;
+use super::Success;
+
+/// Job response going to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobResponse {
+    /// Name of the job
+    pub name: &'static str,
+
+    /// Seconds between the runs, without the jitter
+    pub interval: u64,
+
+    /// Number of runs since startup of this instance
+    pub runs: u64,
+
+    /// Start of the last run
+    pub last_run_at: Option<DateTime<Utc>>,
+
+    /// Milliseconds the last run took
+    pub last_duration: Option<u128>,
+
+    /// Outcome of the last run, `succeeded`, `skipped` (another instance leads) or `failed`
+    pub last_outcome: Option<&'static str>,
+
+    /// Description of the result or error of the last run
+    pub last_description: Option<String>,
+
+    /// Start of the next run
+    pub next_run_at: Option<DateTime<Utc>>,
+}
+
+impl JobResponse {
+    /// Create a response from a [`Job`](Job) and its [`Status`](Status)
+    fn from_status(job: Job, status: Status) -> Self {
+        let (last_outcome, last_description) = match status.last_outcome {
+            Some(Outcome::Succeeded(description)) => (Some("succeeded"), Some(description)),
+            Some(Outcome::Skipped) => (Some("skipped"), None),
+            Some(Outcome::Failed(err)) => (Some("failed"), Some(err)),
+            None => (None, None),
+        };
+
+        Self {
+            name: job.name(),
+            interval: job.interval().as_secs(),
+            runs: status.runs,
+            last_run_at: status.last_run_at,
+            last_duration: status.last_duration.map(|duration| duration.as_millis()),
+            last_outcome,
+            last_description,
+            next_run_at: status.next_run_at,
+        }
+    }
+}
+
+/// Status of the recurring jobs, of this instance
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/jobs
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": [ { "name": "rollup-hits", "lastOutcome": "succeeded" ... } ] }
+/// ```
+pub async fn list(
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+) -> Result<Success<Vec<JobResponse>>, Error> {
+    current_user.is_allowed(Action::ManageSystem)?;
+
+    let jobs = root_settings
+        .jobs
+        .statuses()
+        .into_iter()
+        .map(|(job, status)| JobResponse::from_status(job, status))
+        .collect();
+
+    Ok(Success::ok(jobs))
+}
@@ -5,37 +5,89 @@ use axum::routing::get;
 use axum::routing::patch;
 use axum::routing::post;
 use axum::routing::put;
+use axum::Extension;
 use axum::Router;
+use tower_http::compression::CompressionLayer;
 
+pub use abuse_reports::report as report_abuse;
 pub use audit_trail::AuditTrail;
 pub use current_user::CurrentUser;
 pub use current_user::JwtKeys;
+pub use destinations::create_new_destination;
+pub use destinations::parse_domain;
+pub use destinations::parse_new_slug;
+pub use destinations::CreateDestinationForm;
+pub use destinations::NewDestination;
 pub use request::parse_slug;
 pub use request::parse_url;
 pub use request::Form;
+pub use request::IfNoneMatch;
+pub use request::Pagination;
+pub use request::Patch;
 pub use request::PathParameters;
+pub use response::ETag;
 pub use response::Error;
+pub use response::ErrorCode;
 pub use response::Success;
+pub use response::TOTAL_COUNT_HEADER;
 
+mod abuse_reports;
+mod accounting;
+mod archival;
 mod audit_trail;
+mod auth_metrics;
+mod backup;
+mod batch;
+mod cache;
+mod config;
 mod current_user;
+mod destination_templates;
 mod destinations;
+mod domains;
+mod edge;
+mod graphql;
+mod hits;
+mod jobs;
 mod notes;
 mod request;
+mod reservations;
 mod response;
+mod search;
+mod slack;
+mod stats;
+mod stream;
 mod users;
+mod version;
+mod webhooks;
 
 /// Get the Axum router for all API routes
+///
+/// Responses are compressed when the client accepts it, the lists can get big
 pub fn router() -> Router {
+    let abuse_reports = Router::new()
+        .route("/", get(abuse_reports::list))
+        .route("/:report", get(abuse_reports::single))
+        .route("/:report/disable", post(abuse_reports::disable))
+        .route("/:report/dismiss", post(abuse_reports::dismiss));
+
     let users = Router::new()
         .route("/token", post(users::token))
         .route("/", get(users::list))
         .route("/", post(users::create))
         .route("/me/password", put(users::change_password))
         .route("/:user/password", put(users::change_password))
+        .route("/me/email", put(users::change_email))
+        .route("/:user/email", put(users::change_email))
         .route("/me", get(users::single))
         .route("/:user", get(users::single))
-        .route("/:user", delete(users::delete));
+        .route("/:user", delete(users::delete))
+        .route("/me/usage", get(users::usage))
+        .route("/me/digest", get(users::digest))
+        .route("/me/digest", put(users::subscribe_to_digest))
+        .route("/me/digest", delete(users::unsubscribe_from_digest))
+        .route("/:user/usage", get(users::usage))
+        .route("/:user/quota", put(users::update_quota))
+        .route("/:user/quota", delete(users::delete_quota));
 
     let notes = Router::new()
         .route("/", get(notes::list))
@@ -47,12 +99,82 @@ pub fn router() -> Router {
     let destinations = Router::new()
         .route("/", get(destinations::list))
         .route("/", post(destinations::create))
+        .route("/by-slug/*slug", put(destinations::upsert))
         .route("/:destination", get(destinations::single))
         .route("/:destination", patch(destinations::update))
         .route("/:destination", delete(destinations::delete))
+        .route("/:destination/approve", post(destinations::approve))
+        .route("/:destination/reject", post(destinations::reject))
+        .route("/:destination/sign", post(destinations::sign))
+        .route("/:destination/stats-link", post(destinations::stats_link))
+        .route("/:destination/stats/heatmap", get(stats::heatmap))
         .nest("/:destination/notes", notes);
 
+    let domains = Router::new()
+        .route("/", get(domains::list))
+        .route("/", post(domains::create))
+        .route("/:domain", get(domains::single))
+        .route("/:domain", patch(domains::update))
+        .route("/:domain", delete(domains::delete));
+
+    let reservations = Router::new()
+        .route("/", get(reservations::list))
+        .route("/", post(reservations::reserve))
+        .route("/:reservation", delete(reservations::delete));
+
+    let templates = Router::new()
+        .route("/", get(destination_templates::list))
+        .route("/", post(destination_templates::create))
+        .route("/:template", get(destination_templates::single))
+        .route("/:template", patch(destination_templates::update))
+        .route("/:template", delete(destination_templates::delete));
+
+    let webhooks = Router::new()
+        .route("/", get(webhooks::list))
+        .route("/", post(webhooks::create))
+        .route("/:webhook", get(webhooks::single))
+        .route("/:webhook", delete(webhooks::delete))
+        .route("/:webhook/deliveries", get(webhooks::deliveries));
+
     Router::new()
+        .route("/archival", get(archival::list))
+        .route("/archival", post(archival::trigger))
+        .route("/archival/:run", get(archival::single))
+        .route("/audit-trail/verify", get(audit_trail::verify))
+        .route(
+            "/auth/deleted-user-tokens",
+            get(auth_metrics::deleted_user_tokens),
+        )
+        .route("/auth/metrics", get(auth_metrics::statistics))
+        .route("/backup", get(backup::export))
+        .route("/backup", post(backup::import))
+        .route("/batch", post(batch::run))
+        .route("/cache", get(cache::statistics))
+        .route("/cache", delete(cache::flush))
+        .route("/cache/audit", post(cache::audit))
+        .route("/cache/invalidate", post(cache::invalidate))
+        .route("/config/reload", post(config::reload))
+        .route("/edge/snapshot", get(edge::snapshot))
+        .route(
+            "/graphql",
+            post(graphql::execute).layer(Extension(graphql::schema())),
+        )
+        .route("/hits/ingest", post(hits::ingest))
+        .route("/integrations/slack", post(slack::command))
+        .route("/jobs", get(jobs::list))
+        .route("/search", get(search::search))
+        .route("/stats/sources", get(stats::sources))
+        .route("/stream/events", get(stream::events))
+        .route("/usage", get(accounting::list))
+        .route("/usage/me", get(accounting::single))
+        .route("/usage/:user", get(accounting::single))
+        .route("/version", get(version::version))
+        .nest("/abuse-reports", abuse_reports)
         .nest("/users", users)
         .nest("/destinations", destinations)
+        .nest("/domains", domains)
+        .nest("/reservations", reservations)
+        .nest("/templates", templates)
+        .nest("/webhooks", webhooks)
+        .layer(CompressionLayer::new())
 }
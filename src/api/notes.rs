@@ -14,12 +14,16 @@ use crate::database::Database;
 use crate::database::UpdateNoteValues;
 use crate::destinations::Destination;
 use crate::notes::Note;
-use crate::users::Role;
+use crate::permissions::Action;
 
 use super::AuditTrail;
 use super::CurrentUser;
+use super::ETag;
 use super::Error;
 use super::Form;
+use super::IfNoneMatch;
+use super::Pagination;
+use super::Patch;
 use super::PathParameters;
 use super::Success;
 
@@ -46,7 +50,7 @@ impl NoteResponse {
     /// Create a response from a [`Note`](Note)
     ///
     /// Basically filtering which fields are shown to the user
-    fn from_note(note: Note) -> Self {
+    pub fn from_note(note: Note) -> Self {
         Self {
             id: note.id,
             content: note.content,
@@ -63,34 +67,50 @@ impl NoteResponse {
     }
 }
 
-/// List all notes for a destination
+/// List the notes of a destination, newest first
+///
+/// Paginated with the `page` and `limit` query parameters, all notes without them, see
+/// [`Pagination`](Pagination). The total number of notes is in the `X-Total-Count` header
 ///
 /// Request:
 /// ```sh
 /// curl -v -H 'Content-Type: application/json' \
 ///     -H 'Authorization: Bearer tokentokentoken' \
-///     http://localhost:7000/api/destinations/<uuid>/notes
+///     'http://localhost:7000/api/destinations/<uuid>/notes?page=2&limit=50'
 /// ```
 ///
 /// Response:
 /// ```json
 /// { "data": [ { "id": "<uuid>", "content": "Used on the 26-07 ad campaign" ... } ] }
 /// ```
+///
+/// Tagged with an `ETag`, a `304 Not modified` is returned when matching `If-None-Match`
 pub async fn list(
     Extension(database): Extension<Database>,
     current_user: CurrentUser,
+    if_none_match: IfNoneMatch,
+    pagination: Pagination,
     PathParameters(destination_id): PathParameters<Uuid>,
 ) -> Result<Success<Vec<NoteResponse>>, Error> {
-    current_user.role.is_allowed(Role::Manager)?;
+    current_user.is_allowed(Action::ViewDestinations)?;
 
     let destination = fetch_destination(&database, &destination_id).await?;
 
     let notes = database
-        .find_all_notes_by_destination(&destination)
+        .find_notes_by_destination(&destination, pagination.limit(), pagination.offset())
         .await
         .map_err(Error::internal_server_error)?;
 
-    Ok(Success::ok(NoteResponse::from_note_multiple(notes)))
+    let total_count = database
+        .count_notes_by_destination(&destination)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    let etag = ETag::from_updates(notes.iter().map(|note| (note.id, note.updated_at)));
+
+    Ok(Success::ok(NoteResponse::from_note_multiple(notes))
+        .with_total_count(total_count)
+        .with_etag(etag, &if_none_match))
 }
 
 /// Get single note of a destination
@@ -106,18 +126,22 @@ pub async fn list(
 /// ```json
 /// { "data": { "id": "<uuid>", "content": "Used on the 26-07 ad campaign" ... } }
 /// ```
+///
+/// Tagged with an `ETag`, a `304 Not modified` is returned when matching `If-None-Match`
 pub async fn single(
     Extension(database): Extension<Database>,
     current_user: CurrentUser,
+    if_none_match: IfNoneMatch,
     PathParameters((destination_id, note_id)): PathParameters<(Uuid, Uuid)>,
 ) -> Result<Success<NoteResponse>, Error> {
-    current_user.role.is_allowed(Role::Manager)?;
+    current_user.is_allowed(Action::ViewDestinations)?;
 
     let destination = fetch_destination(&database, &destination_id).await?;
 
-    fetch_note(&database, &destination.id, &note_id)
-        .await
-        .map(|note| Success::ok(NoteResponse::from_note(note)))
+    let note = fetch_note(&database, &destination.id, &note_id).await?;
+    let etag = ETag::from_updates([(note.id, note.updated_at)]);
+
+    Ok(Success::ok(NoteResponse::from_note(note)).with_etag(etag, &if_none_match))
 }
 
 /// Create note form
@@ -151,7 +175,7 @@ pub async fn create(
     PathParameters(destination_id): PathParameters<Uuid>,
     Form(form): Form<CreateNoteForm>,
 ) -> Result<Success<NoteResponse>, Error> {
-    current_user.role.is_allowed(Role::Manager)?;
+    current_user.is_allowed(Action::ManageNotes)?;
 
     let destination = fetch_destination(&database, &destination_id).await?;
 
@@ -176,11 +200,11 @@ pub async fn create(
 ///
 /// Fields to update a destination with, all fields are optional and are not touched when not
 /// provided
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
 pub struct UpdateNoteForm {
     /// New content for note
-    content: Option<String>,
+    content: Patch<String>,
 }
 
 /// Update a note based on the [`UpdateNoteForm`](UpdateNoteForm) form
@@ -204,13 +228,13 @@ pub async fn update(
     PathParameters((destination_id, note_id)): PathParameters<(Uuid, Uuid)>,
     Form(form): Form<UpdateNoteForm>,
 ) -> Result<Success<NoteResponse>, Error> {
-    current_user.role.is_allowed(Role::Manager)?;
+    current_user.is_allowed(Action::ManageNotes)?;
 
     let destination = fetch_destination(&database, &destination_id).await?;
     let note = fetch_note(&database, &destination.id, &note_id).await?;
 
     let values = UpdateNoteValues {
-        content: form.content.as_ref(),
+        content: form.content.required("content")?,
     };
 
     let note = database
@@ -241,7 +265,7 @@ pub async fn delete(
     current_user: CurrentUser,
     PathParameters((destination_id, note_id)): PathParameters<(Uuid, Uuid)>,
 ) -> Result<Success<&'static str>, Error> {
-    current_user.role.is_allowed(Role::Manager)?;
+    current_user.is_allowed(Action::ManageNotes)?;
 
     let destination = fetch_destination(&database, &destination_id).await?;
     let note = fetch_note(&database, &destination.id, &note_id).await?;
@@ -1,6 +1,7 @@
 //! API request helpers
 
 use axum::async_trait;
+use axum::extract::rejection::FormRejection;
 use axum::extract::rejection::JsonRejection;
 use axum::extract::rejection::PathRejection;
 use axum::extract::FromRequest;
@@ -8,12 +9,22 @@ use axum::extract::FromRequestParts;
 use axum::extract::Json;
 use axum::extract::Path;
 use axum::extract::Request;
+use axum::http::header::CONTENT_TYPE;
+use axum::http::header::IF_NONE_MATCH;
 use axum::http::request::Parts;
+use axum::http::StatusCode;
+use axum::Form as UrlEncodedForm;
 use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Deserializer;
 use unicode_normalization::UnicodeNormalization;
 use url::Url;
 
+use crate::utils::is_web_url;
+
+use super::ETag;
 use super::Error;
+use super::ErrorCode;
 
 /// Parse and normalize a slug
 ///
@@ -32,11 +43,13 @@ pub fn parse_slug(slug: &str) -> Result<String, Error> {
 
     for ch in slug.chars() {
         if ch == '?' {
-            return Err(Error::bad_request(r#"Slug can not contain "?""#));
+            return Err(Error::bad_request(r#"Slug can not contain "?""#)
+                .with_code(ErrorCode::InvalidSlugChar));
         }
 
         if ch == '#' {
-            return Err(Error::bad_request(r##"Slug can not contain "#""##));
+            return Err(Error::bad_request(r##"Slug can not contain "#""##)
+                .with_code(ErrorCode::InvalidSlugChar));
         }
     }
 
@@ -44,7 +57,7 @@ pub fn parse_slug(slug: &str) -> Result<String, Error> {
     Ok(slug.nfc().collect())
 }
 
-/// Parse and validate a URL
+/// Parse and validate a URL, only `http` and `https` URLs are allowed
 ///
 /// ```rust
 /// let url = "https://www.example.com/";
@@ -54,7 +67,16 @@ pub fn parse_url<I>(url: I) -> Result<Url, Error>
 where
     I: AsRef<str>,
 {
-    Url::parse(url.as_ref()).map_err(Error::bad_request)
+    let url = Url::parse(url.as_ref())
+        .map_err(|err| Error::bad_request(err).with_code(ErrorCode::InvalidUrl))?;
+
+    if !is_web_url(&url) {
+        return Err(Error::bad_request("Invalid URL scheme")
+            .with_code(ErrorCode::InvalidUrl)
+            .with_description("Expected an `http` or `https` URL"));
+    }
+
+    Ok(url)
 }
 
 /// Handle incoming [`Json`](Json) with proper API error handling
@@ -64,23 +86,66 @@ fn handle_json<J>(json: Result<Json<J>, JsonRejection>) -> Result<J, Error> {
     match json {
         Ok(Json(json)) => Ok(json),
         Err(err) => match err {
-            JsonRejection::JsonDataError(err) => {
-                Err(Error::bad_request("Data error").with_description(err))
-            }
+            JsonRejection::JsonDataError(err) => Err(Error::bad_request("Data error")
+                .with_code(ErrorCode::InvalidBody)
+                .with_description(err)),
             JsonRejection::JsonSyntaxError(err) => Err(Error::bad_request("JSON syntax error")
+                .with_code(ErrorCode::InvalidBody)
                 .with_description(std::error::Error::source(&err).expect("A valid source"))),
             JsonRejection::MissingJsonContentType(_err) => Err(Error::bad_request(
                 "Missing `application/json` content type",
-            )),
+            )
+            .with_code(ErrorCode::MissingContentType)),
+            JsonRejection::BytesRejection(err) if err.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+                Err(Error::payload_too_large("Body is too large").with_description(err))
+            }
             JsonRejection::BytesRejection(err) => {
-                Err(Error::bad_request("Invalid characters in JSON").with_description(err))
+                Err(Error::bad_request("Invalid characters in JSON")
+                    .with_code(ErrorCode::InvalidBody)
+                    .with_description(err))
             }
             err => Err(Error::bad_request("Unknown JSON error").with_description(err)),
         },
     }
 }
 
-/// Wrapper around the [`Json`](Json) extractor
+/// Handle incoming URL-encoded forms with proper API error handling
+///
+/// When the form is invalid, a [`Error`](Error) describing the issue will be returned
+fn handle_url_encoded_form<F>(form: Result<UrlEncodedForm<F>, FormRejection>) -> Result<F, Error> {
+    match form {
+        Ok(UrlEncodedForm(form)) => Ok(form),
+        Err(err) => match err {
+            FormRejection::FailedToDeserializeFormBody(err) => {
+                Err(Error::bad_request("Data error")
+                    .with_code(ErrorCode::InvalidBody)
+                    .with_description(err))
+            }
+            FormRejection::BytesRejection(err) if err.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+                Err(Error::payload_too_large("Body is too large").with_description(err))
+            }
+            err => Err(Error::bad_request("Unknown form error").with_description(err)),
+        },
+    }
+}
+
+/// Is the body of the request an `application/x-www-form-urlencoded` form?
+fn is_url_encoded_form(req: &Request) -> bool {
+    req.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|mime| {
+            mime.trim()
+                .eq_ignore_ascii_case("application/x-www-form-urlencoded")
+        })
+}
+
+/// Wrapper around the [`Json`](Json) extractor, or the URL-encoded form extractor with the
+/// `application/x-www-form-urlencoded` content type
+///
+/// URL-encoded forms only have the plain fields, like strings, numbers and booleans; lists and
+/// objects, like the tags or extra headers of a destination, need JSON
 pub struct Form<F>(pub F);
 
 #[async_trait]
@@ -92,6 +157,14 @@ where
     type Rejection = Error;
 
     async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if is_url_encoded_form(&req) {
+            let form = Result::<UrlEncodedForm<F>, FormRejection>::from_request(req, state)
+                .await
+                .map_err(|_| Error::internal_server_error("Could not extract form"))?;
+
+            return handle_url_encoded_form(form).map(Form);
+        }
+
         let json = Result::<Json<F>, JsonRejection>::from_request(req, state)
             .await
             .map_err(|_| Error::internal_server_error("Could not extract form"))?;
@@ -100,6 +173,62 @@ where
     }
 }
 
+/// A field of an update form, telling an omitted field apart from `null`
+///
+/// Updates are JSON Merge Patches (RFC 7396), with `application/json` and
+/// `application/merge-patch+json`: omitted fields are not touched and `null` removes the value.
+/// Forms need `#[serde(default)]` for the omitted fields.
+#[derive(Debug, Default)]
+pub enum Patch<T> {
+    /// The field is omitted, the value is not touched
+    #[default]
+    Missing,
+
+    /// The field is `null`, the value is removed
+    Null,
+
+    /// The new value
+    Value(T),
+}
+
+impl<'de, T> Deserialize<'de> for Patch<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<T>::deserialize(deserializer).map(|value| value.map_or(Self::Null, Self::Value))
+    }
+}
+
+impl<T> Patch<T> {
+    /// The new value of a field that can not be removed, `None` when not touched
+    ///
+    /// Will return an [`Error`](Error) when the field is `null`
+    pub fn required(&self, field: &str) -> Result<Option<&T>, Error> {
+        match self {
+            Self::Missing => Ok(None),
+            Self::Null => Err(Error::bad_request(format!("`{field}` can not be removed"))),
+            Self::Value(value) => Ok(Some(value)),
+        }
+    }
+}
+
+impl Patch<String> {
+    /// The new value of a field that can be removed, `None` when not touched
+    ///
+    /// An empty string removes the value, like `null`
+    pub fn removable(&self) -> Option<&str> {
+        match self {
+            Self::Missing => None,
+            Self::Null => Some(""),
+            Self::Value(value) => Some(value),
+        }
+    }
+}
+
 /// Handle incoming [`Path`](Path) with proper API error handling
 ///
 /// When the path is invalid, a [`Error`](Error) describing the issue will be returned
@@ -138,10 +267,145 @@ where
     }
 }
 
+/// Default number of items of a page
+const DEFAULT_PAGE_LIMIT: i64 = 50;
+
+/// Maximum number of items of a page
+const MAX_PAGE_LIMIT: i64 = 500;
+
+/// Pagination of a list, with the `page` and `limit` query parameters
+///
+/// The first page is `1`, a page has 50 items by default and at most 500. Without either
+/// parameter the whole list is returned.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Pagination {
+    /// The requested page and its number of items, `None` for the whole list
+    page: Option<(i64, i64)>,
+}
+
+impl Pagination {
+    /// Maximum number of items, `None` for the whole list
+    pub fn limit(&self) -> Option<i64> {
+        self.page.map(|(_, limit)| limit)
+    }
+
+    /// Number of items before the page
+    pub fn offset(&self) -> i64 {
+        self.page.map_or(0, |(page, limit)| (page - 1) * limit)
+    }
+
+    /// Parse the `page` and `limit` query parameters
+    fn from_query(query: &str) -> Result<Self, Error> {
+        let mut page = None;
+        let mut limit = None;
+
+        for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "page" => page = Some(parse_positive(&value, "page")?),
+                "limit" => limit = Some(parse_positive(&value, "limit")?.min(MAX_PAGE_LIMIT)),
+                _ => {}
+            }
+        }
+
+        if page.is_none() && limit.is_none() {
+            return Ok(Self::default());
+        }
+
+        Ok(Self {
+            page: Some((page.unwrap_or(1), limit.unwrap_or(DEFAULT_PAGE_LIMIT))),
+        })
+    }
+}
+
+/// Parse a positive whole number of a query parameter
+fn parse_positive(value: &str, name: &str) -> Result<i64, Error> {
+    value
+        .parse::<i64>()
+        .ok()
+        .filter(|value| *value > 0)
+        .ok_or_else(|| {
+            Error::bad_request(format!("Invalid {name}"))
+                .with_description("Expected a positive whole number")
+        })
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Pagination
+where
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .uri
+            .query()
+            .map_or_else(|| Ok(Self::default()), Self::from_query)
+    }
+}
+
+/// The `If-None-Match` header of a request, the entity tags the client already has
+#[derive(Debug, Default)]
+pub struct IfNoneMatch(Option<String>);
+
+impl IfNoneMatch {
+    /// Check if the client already has the [`ETag`](ETag)
+    ///
+    /// Uses the weak comparison, the `W/` prefix is ignored, `*` matches any entity tag
+    pub fn matches(&self, etag: &ETag) -> bool {
+        let Some(if_none_match) = &self.0 else {
+            return false;
+        };
+
+        let etag = etag.as_str().trim_start_matches("W/");
+
+        if_none_match
+            .split(',')
+            .map(str::trim)
+            .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for IfNoneMatch
+where
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(
+            parts
+                .headers
+                .get(IF_NONE_MATCH)
+                .and_then(|value| value.to_str().ok())
+                .map(ToString::to_string),
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_pagination() {
+        let pagination = Pagination::from_query("").unwrap();
+        assert_eq!(None, pagination.limit());
+        assert_eq!(0, pagination.offset());
+
+        let pagination = Pagination::from_query("page=3").unwrap();
+        assert_eq!(Some(50), pagination.limit());
+        assert_eq!(100, pagination.offset());
+
+        let pagination = Pagination::from_query("page=2&limit=1000").unwrap();
+        assert_eq!(Some(500), pagination.limit());
+        assert_eq!(500, pagination.offset());
+
+        assert!(Pagination::from_query("page=0").is_err());
+        assert!(Pagination::from_query("limit=many").is_err());
+    }
+
     #[test]
     fn test_parse_slug() {
         let slug = "/some-slug";
@@ -158,6 +422,50 @@ mod tests {
     fn test_parse_url() {
         let url = "https://www.example.com/";
         assert!(parse_url(url).is_ok());
+
+        assert!(parse_url("javascript:alert(document.cookie)").is_err());
+        assert!(parse_url("data:text/html,<script>alert(1)</script>").is_err());
+        assert!(parse_url("ftp://ftp.example.com/").is_err());
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        let etag = ETag::from_updates([(uuid::Uuid::nil(), chrono::NaiveDateTime::default())]);
+
+        assert!(!IfNoneMatch(None).matches(&etag));
+        assert!(IfNoneMatch(Some("*".into())).matches(&etag));
+        assert!(IfNoneMatch(Some(etag.as_str().into())).matches(&etag));
+        assert!(IfNoneMatch(Some(format!(r#""other", {}"#, etag.as_str()))).matches(&etag));
+
+        // weak comparison
+        let strong = etag.as_str().trim_start_matches("W/").to_string();
+        assert!(IfNoneMatch(Some(strong)).matches(&etag));
+
+        assert!(!IfNoneMatch(Some(r#"W/"other""#.into())).matches(&etag));
+    }
+
+    #[test]
+    fn test_patch() {
+        #[derive(Deserialize)]
+        struct Form {
+            #[serde(default)]
+            title: Patch<String>,
+        }
+
+        let form = serde_json::from_str::<Form>("{}").unwrap();
+        assert_eq!(None, form.title.removable());
+        assert_eq!(None, form.title.required("title").unwrap());
+
+        let form = serde_json::from_str::<Form>(r#"{ "title": null }"#).unwrap();
+        assert_eq!(Some(""), form.title.removable());
+        assert!(form.title.required("title").is_err());
+
+        let form = serde_json::from_str::<Form>(r#"{ "title": "Launch" }"#).unwrap();
+        assert_eq!(Some("Launch"), form.title.removable());
+        assert_eq!(
+            Some(&"Launch".to_string()),
+            form.title.required("title").unwrap()
+        );
     }
 
     #[test]
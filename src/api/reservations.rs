@@ -0,0 +1,244 @@
+//! Slug reservations API endpoints
+//!
+//! Reserve slugs for upcoming destinations, only the reserver can create destinations with them
+
+use std::collections::HashSet;
+
+use axum::Extension;
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::permissions::Action;
+use crate::reservations::SlugReservation;
+use crate::root::Settings as RootSettings;
+use crate::users::User;
+
+use super::destinations::parse_domain;
+use super::destinations::parse_new_slug;
+use super::CurrentUser;
+use super::Error;
+use super::ErrorCode;
+use super::Form;
+use super::PathParameters;
+use super::Success;
+
+/// Maximum number of slugs reserved at once
+const MAX_SLUGS: usize = 100;
+
+/// Slug reservation response going to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlugReservationResponse {
+    /// Reservation ID
+    pub id: Uuid,
+
+    /// The reserved slug
+    pub slug: String,
+
+    /// Domain of the slug, all domains when empty
+    pub domain: Option<String>,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+}
+
+impl SlugReservationResponse {
+    /// Create a response from a [`SlugReservation`](SlugReservation)
+    fn from_reservation(reservation: SlugReservation) -> Self {
+        Self {
+            id: reservation.id,
+            slug: reservation.slug,
+            domain: reservation.domain,
+            created_at: reservation.created_at,
+        }
+    }
+}
+
+/// List the slug reservations of the current user
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/reservations
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": [ { "id": "<uuid>", "slug": "summer-sale" ... } ] }
+/// ```
+pub async fn list(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+) -> Result<Success<Vec<SlugReservationResponse>>, Error> {
+    current_user.is_allowed(Action::CreateDestinations)?;
+
+    let reservations = database
+        .find_all_slug_reservations_by_user(&current_user)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Success::ok(
+        reservations
+            .into_iter()
+            .map(SlugReservationResponse::from_reservation)
+            .collect(),
+    ))
+}
+
+/// Reserve slugs form
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReserveSlugsForm {
+    /// The slugs to reserve, at most 100
+    slugs: Vec<String>,
+
+    /// Domain of the slugs, all domains when not provided
+    domain: Option<String>,
+}
+
+/// Reserve a batch of slugs based on the [`ReserveSlugsForm`](ReserveSlugsForm) form, all or
+/// nothing
+///
+/// Slugs of existing destinations, deleted ones included, and slugs reserved by anybody can not be
+/// reserved
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     -d '{ "slugs": ["summer-sale", "summer-sale-nl"] }' \
+///     http://localhost:7000/api/reservations
+/// ```
+///
+/// Response
+/// ```json
+/// { "data": [ { "id": "<uuid>", "slug": "summer-sale" ... } ] }
+/// ```
+pub async fn reserve(
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+    Form(form): Form<ReserveSlugsForm>,
+) -> Result<Success<Vec<SlugReservationResponse>>, Error> {
+    current_user.is_allowed(Action::CreateDestinations)?;
+
+    if form.slugs.is_empty() {
+        return Err(Error::bad_request("Slugs can not be empty"));
+    }
+
+    if form.slugs.len() > MAX_SLUGS {
+        return Err(Error::bad_request("Too many slugs")
+            .with_description(format!("Up to {MAX_SLUGS} slugs at once")));
+    }
+
+    let domain = parse_domain(form.domain.as_deref())?;
+
+    let mut slugs = Vec::with_capacity(form.slugs.len());
+    let mut seen = HashSet::new();
+    for slug in &form.slugs {
+        let slug = parse_new_slug(slug, &root_settings.slug_policy)?;
+
+        if !seen.insert(slug.clone()) {
+            return Err(Error::bad_request(format!("Duplicate slug: {slug}"))
+                .with_code(ErrorCode::SlugConflict));
+        }
+
+        slugs.push(slug);
+    }
+
+    let taken = database
+        .find_taken_slugs_in_namespace(domain.as_deref(), &slugs)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    if let Some(slug) = taken.first() {
+        return Err(Error::bad_request(format!("Slug already exists: {slug}"))
+            .with_code(ErrorCode::SlugConflict));
+    }
+
+    let reserved = database
+        .find_reserved_slugs_in_namespace(domain.as_deref(), &slugs)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    if let Some(slug) = reserved.first() {
+        return Err(Error::bad_request(format!(
+            "Slug is already reserved: {slug}"
+        )));
+    }
+
+    let reservations = database
+        .create_slug_reservations(&current_user, domain.as_deref(), &slugs)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Success::created(
+        reservations
+            .into_iter()
+            .map(SlugReservationResponse::from_reservation)
+            .collect(),
+    ))
+}
+
+/// Release a slug reservation, of the current user or any user for admins
+///
+/// Request:
+/// ```sh
+/// curl -v -XDELETE \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/reservations/<uuid>
+/// ```
+pub async fn delete(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    PathParameters(reservation_id): PathParameters<Uuid>,
+) -> Result<Success<&'static str>, Error> {
+    current_user.is_allowed(Action::CreateDestinations)?;
+
+    let reservation = database
+        .find_single_slug_reservation_by_id(&reservation_id)
+        .await
+        .map_err(Error::internal_server_error)?
+        .ok_or_else(|| Error::not_found("Reservation not found"))?;
+
+    if reservation.user_id != current_user.id {
+        current_user.is_allowed(Action::ManageSystem)?;
+    }
+
+    database
+        .delete_slug_reservation(&reservation)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Success::<&'static str>::no_content())
+}
+
+/// Check the slug is not reserved by another user, creating the destination releases the
+/// reservation of the user itself
+///
+/// # Errors
+///
+/// Will return a bad request `Err` when another user reserved the slug
+pub async fn check_slug_reservation(
+    database: &Database,
+    user: &User,
+    domain: Option<&str>,
+    slug: &str,
+) -> Result<(), Error> {
+    let reservation = database
+        .find_single_slug_reservation_in_namespace(domain, slug)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    match reservation {
+        Some(reservation) if reservation.user_id != user.id => {
+            Err(Error::bad_request("Slug is reserved by another user")
+                .with_code(ErrorCode::SlugReserved))
+        }
+        _ => Ok(()),
+    }
+}
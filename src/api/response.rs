@@ -1,13 +1,61 @@
 //! API response helpers
 
+use core::fmt;
+
+use axum::http::header::ETAG;
+use axum::http::HeaderName;
+use axum::http::HeaderValue;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use axum::response::Response;
 use axum::Json;
+use chrono::NaiveDateTime;
 use serde::Serialize;
+use serde_json::Value;
+use sha2::Digest;
+use sha2::Sha256;
+use uuid::Uuid;
 
+use crate::permissions::Action;
+use crate::permissions::Permissions;
 use crate::users::Role;
 
+use super::IfNoneMatch;
+
+/// Header with the total number of items of a paginated list
+pub const TOTAL_COUNT_HEADER: HeaderName = HeaderName::from_static("x-total-count");
+
+/// Weak entity tag of a response, from the IDs and last updates of the resources in it
+///
+/// Changes when a resource is updated, added or removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ETag(String);
+
+impl ETag {
+    /// Create the entity tag of the resources, by their ID and last update
+    pub fn from_updates<I>(updates: I) -> Self
+    where
+        I: IntoIterator<Item = (Uuid, NaiveDateTime)>,
+    {
+        let mut hasher = Sha256::new();
+        for (id, updated_at) in updates {
+            hasher.update(format!(
+                "{id}:{}\n",
+                updated_at.and_utc().timestamp_micros()
+            ));
+        }
+
+        let hash = format!("{:x}", hasher.finalize());
+
+        Self(format!("W/\"{}\"", &hash[..32]))
+    }
+
+    /// The entity tag, like `W/"<hash>"`
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
 /// Hold data for a successful API response
 pub struct Success<V>
 where
@@ -18,6 +66,12 @@ where
 
     /// Optional data of the successful response
     data: Option<V>,
+
+    /// Optional entity tag of the data
+    etag: Option<ETag>,
+
+    /// Optional total number of items of a paginated list
+    total_count: Option<i64>,
 }
 
 impl<V> Success<V>
@@ -29,6 +83,8 @@ where
         Self {
             status_code: StatusCode::OK,
             data: Some(data),
+            etag: None,
+            total_count: None,
         }
     }
 
@@ -37,6 +93,8 @@ where
         Self {
             status_code: StatusCode::CREATED,
             data: Some(data),
+            etag: None,
+            total_count: None,
         }
     }
 
@@ -45,6 +103,41 @@ where
         Self {
             status_code: StatusCode::NO_CONTENT,
             data: None,
+            etag: None,
+            total_count: None,
+        }
+    }
+
+    /// Tag the response with the [`ETag`](ETag) of its data
+    ///
+    /// Becomes a `304 Not modified` without data when the client already has it, according to
+    /// the `If-None-Match` header
+    pub fn with_etag(self, etag: ETag, if_none_match: &IfNoneMatch) -> Self {
+        if if_none_match.matches(&etag) {
+            Self {
+                status_code: StatusCode::NOT_MODIFIED,
+                data: None,
+                etag: Some(etag),
+                ..self
+            }
+        } else {
+            Self {
+                etag: Some(etag),
+                ..self
+            }
+        }
+    }
+}
+
+impl<V> Success<Vec<V>>
+where
+    V: Serialize,
+{
+    /// Send the total number of items of the paginated list, in the `X-Total-Count` header
+    pub fn with_total_count(self, total_count: i64) -> Self {
+        Self {
+            total_count: Some(total_count),
+            ..self
         }
     }
 }
@@ -64,10 +157,127 @@ where
     V: Serialize,
 {
     fn into_response(self) -> Response {
-        if let Some(data) = self.data {
+        let mut response = if let Some(data) = self.data {
             (self.status_code, Json(DataWrapper { data })).into_response()
         } else {
             self.status_code.into_response()
+        };
+
+        if let Some(etag) = self.etag {
+            if let Ok(etag) = etag.as_str().parse() {
+                response.headers_mut().insert(ETAG, etag);
+            }
+        }
+
+        if let Some(total_count) = self.total_count {
+            response
+                .headers_mut()
+                .insert(TOTAL_COUNT_HEADER, HeaderValue::from(total_count));
+        }
+
+        response
+    }
+}
+
+/// Stable, machine-readable code of an [`Error`](Error), for clients to branch on without
+/// parsing the message
+///
+/// Errors without a more specific code have the code of their status, like `bad_request`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// `400 Bad request` without a more specific code
+    BadRequest,
+
+    /// `403 Forbidden` without a more specific code
+    Forbidden,
+
+    /// `404 Not found` without a more specific code
+    NotFound,
+
+    /// `413 Payload too large` without a more specific code
+    PayloadTooLarge,
+
+    /// `429 Too many requests` without a more specific code
+    TooManyRequests,
+
+    /// `500 Internal server error` without a more specific code
+    InternalServerError,
+
+    /// The body is no valid JSON or form, or its fields are invalid
+    InvalidBody,
+
+    /// The body has no supported content type
+    MissingContentType,
+
+    /// The slug is already taken
+    SlugConflict,
+
+    /// The slug is already taken by a deleted destination
+    SlugDeleted,
+
+    /// The slug is reserved, by Shurly, the deployment or another user
+    SlugReserved,
+
+    /// The slug has a character that is not allowed
+    InvalidSlugChar,
+
+    /// The slug has an empty path segment
+    InvalidSlug,
+
+    /// The slug has more characters than allowed
+    SlugTooLong,
+
+    /// The slug is confusable with a Latin slug
+    SlugConfusable,
+
+    /// The slug is not provided and not derived from the URL
+    SlugRequired,
+
+    /// The URL can not be parsed
+    InvalidUrl,
+
+    /// The URL is flagged as unsafe
+    UrlUnsafe,
+
+    /// The hostname of the URL is confusable with a Latin hostname
+    UrlConfusable,
+
+    /// The URL redirects back to the slug
+    RedirectLoop,
+
+    /// Permanent destinations can not be changed
+    PermanentImmutable,
+
+    /// The quota of the user is reached
+    QuotaReached,
+
+    /// The user is not allowed to perform the action
+    NotAllowed,
+
+    /// The request has no token
+    MissingToken,
+
+    /// The token is invalid
+    InvalidToken,
+
+    /// The token is expired
+    TokenExpired,
+
+    /// The API key of the edge is invalid
+    InvalidApiKey,
+}
+
+impl ErrorCode {
+    /// The code of errors with the status code, without a more specific code
+    fn from_status_code(status_code: StatusCode) -> Self {
+        match status_code {
+            StatusCode::FORBIDDEN => Self::Forbidden,
+            StatusCode::NOT_FOUND => Self::NotFound,
+            StatusCode::PAYLOAD_TOO_LARGE => Self::PayloadTooLarge,
+            StatusCode::TOO_MANY_REQUESTS => Self::TooManyRequests,
+            StatusCode::INTERNAL_SERVER_ERROR => Self::InternalServerError,
+            _ => Self::BadRequest,
         }
     }
 }
@@ -78,11 +288,17 @@ pub struct Error {
     /// The failed status code
     status_code: StatusCode,
 
+    /// The machine-readable code of the error
+    code: ErrorCode,
+
     /// The error message
     message: String,
 
     /// An optional error description
     description: Option<String>,
+
+    /// Optional machine-readable details, like alternatives for a taken slug
+    details: Option<Value>,
 }
 
 impl Error {
@@ -93,8 +309,10 @@ impl Error {
     {
         Self {
             status_code: StatusCode::BAD_REQUEST,
+            code: ErrorCode::from_status_code(StatusCode::BAD_REQUEST),
             message: message.to_string(),
             description: None,
+            details: None,
         }
     }
 
@@ -105,8 +323,10 @@ impl Error {
     {
         Self {
             status_code: StatusCode::FORBIDDEN,
+            code: ErrorCode::from_status_code(StatusCode::FORBIDDEN),
             message: message.to_string(),
             description: None,
+            details: None,
         }
     }
 
@@ -117,8 +337,38 @@ impl Error {
     {
         Self {
             status_code: StatusCode::NOT_FOUND,
+            code: ErrorCode::from_status_code(StatusCode::NOT_FOUND),
+            message: message.to_string(),
+            description: None,
+            details: None,
+        }
+    }
+
+    /// Create new Error response with `413 Payload too large` status code
+    pub fn payload_too_large<M>(message: M) -> Self
+    where
+        M: ToString,
+    {
+        Self {
+            status_code: StatusCode::PAYLOAD_TOO_LARGE,
+            code: ErrorCode::from_status_code(StatusCode::PAYLOAD_TOO_LARGE),
             message: message.to_string(),
             description: None,
+            details: None,
+        }
+    }
+
+    /// Create new Error response with `429 Too many requests` status code
+    pub fn too_many_requests<M>(message: M) -> Self
+    where
+        M: ToString,
+    {
+        Self {
+            status_code: StatusCode::TOO_MANY_REQUESTS,
+            code: ErrorCode::from_status_code(StatusCode::TOO_MANY_REQUESTS),
+            message: message.to_string(),
+            description: None,
+            details: None,
         }
     }
 
@@ -129,8 +379,10 @@ impl Error {
     {
         Self {
             status_code: StatusCode::INTERNAL_SERVER_ERROR,
+            code: ErrorCode::from_status_code(StatusCode::INTERNAL_SERVER_ERROR),
             message: message.to_string(),
             description: None,
+            details: None,
         }
     }
 
@@ -141,8 +393,49 @@ impl Error {
     {
         Self {
             status_code: self.status_code,
+            code: self.code,
             message: self.message.clone(),
             description: Some(description.to_string()),
+            details: self.details.clone(),
+        }
+    }
+
+    /// Create a version of the error with a prefixed message, like `Operation 2: Slug is reserved`
+    pub fn with_prefix<M>(&self, prefix: M) -> Self
+    where
+        M: ToString,
+    {
+        Self {
+            status_code: self.status_code,
+            code: self.code,
+            message: format!("{}: {}", prefix.to_string(), self.message),
+            description: self.description.clone(),
+            details: self.details.clone(),
+        }
+    }
+
+    /// Create a version of the error with a more specific machine-readable code
+    pub fn with_code(self, code: ErrorCode) -> Self {
+        Self { code, ..self }
+    }
+
+    /// Create a version of the error with machine-readable details, for clients to act upon
+    pub fn with_details(self, details: Value) -> Self {
+        Self {
+            details: Some(details),
+            ..self
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(description) = &self.description {
+            write!(f, "{}: {description}", self.message)
+        } else {
+            write!(f, "{}", self.message)
         }
     }
 }
@@ -156,9 +449,16 @@ where
     /// The error message
     error: D,
 
+    /// The machine-readable code
+    code: ErrorCode,
+
     /// Optional error description
     #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<D>,
+
+    /// Optional machine-readable details
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<Value>,
 }
 
 impl IntoResponse for Error {
@@ -167,29 +467,33 @@ impl IntoResponse for Error {
             self.status_code,
             Json(ErrorWrapper {
                 error: self.message,
+                code: self.code,
                 description: self.description,
+                details: self.details,
             }),
         )
             .into_response()
     }
 }
 
-impl Role {
-    /// Check if the current role matches the target role
+impl Permissions {
+    /// Check if the role is allowed the action
     ///
     /// Will return a forbidden [`Error`](Error) which can be used like this:
     ///
     /// ```rust
-    /// let role = Role::Manager;
-    /// role.is_allowed(Role::Admin)?;
+    /// let permissions = Permissions::default();
+    /// permissions.check(Role::Manager, Action::ManageUsers)?;
     /// ```
-    pub fn is_allowed(self, target_role: Role) -> Result<(), Error> {
-        match self {
-            Role::Admin => Ok(()),
-            Role::Manager => match target_role {
-                Role::Admin => Err(Error::forbidden("Not allowed to acces")),
-                Role::Manager => Ok(()),
-            },
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the role is not allowed the action
+    pub fn check(&self, role: Role, action: Action) -> Result<(), Error> {
+        if self.is_allowed(role, action) {
+            Ok(())
+        } else {
+            Err(Error::forbidden("Not allowed to acces").with_code(ErrorCode::NotAllowed))
         }
     }
 }
@@ -0,0 +1,168 @@
+//! Search API endpoint
+//!
+//! A single search box for the destinations and their notes, see [`search`](crate::search)
+
+use axum::http::Uri;
+use axum::Extension;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::permissions::Action;
+use crate::search::SearchResult;
+use crate::search::MAX_QUERY_LENGTH;
+use crate::search::MIN_QUERY_LENGTH;
+
+use super::CurrentUser;
+use super::ETag;
+use super::Error;
+use super::IfNoneMatch;
+use super::Pagination;
+use super::Success;
+
+/// Number of results without a `limit`
+const DEFAULT_LIMIT: i64 = 20;
+
+/// What matched the search
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchKind {
+    /// The slug or URL of the destination
+    Destination,
+
+    /// A note of the destination
+    Note,
+}
+
+/// Search result going to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResultResponse {
+    /// What matched the search
+    pub kind: SearchKind,
+
+    /// Relevance of the match, from 0 to 1
+    pub score: f32,
+
+    /// ID of the destination
+    pub destination_id: Uuid,
+
+    /// Slug of the destination
+    pub slug: String,
+
+    /// Domain of the destination, all domains when empty
+    pub domain: Option<String>,
+
+    /// URL of the destination
+    pub url: String,
+
+    /// ID of the matching note
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note_id: Option<Uuid>,
+
+    /// Content of the matching note
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note_content: Option<String>,
+}
+
+impl SearchResultResponse {
+    /// Create a response from a [`SearchResult`](SearchResult)
+    fn from_result(result: SearchResult) -> Self {
+        Self {
+            kind: if result.note_id.is_some() {
+                SearchKind::Note
+            } else {
+                SearchKind::Destination
+            },
+            score: result.score,
+            destination_id: result.destination_id,
+            slug: result.slug,
+            domain: result.domain,
+            url: result.url,
+            note_id: result.note_id,
+            note_content: result.note_content,
+        }
+    }
+}
+
+/// Search the slugs and URLs of the destinations and the content of their notes, most relevant
+/// first
+///
+/// The query is the `q` query parameter, of at least 2 characters. Returns 20 results, or a page
+/// with the `page` and `limit` query parameters, see [`Pagination`](Pagination)
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Authorization: Bearer tokentokentoken' \
+///     'http://localhost:7000/api/search?q=launch'
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": [ { "kind": "destination", "score": 1.0, "slug": "launch" ... } ] }
+/// ```
+///
+/// Tagged with an `ETag`, a `304 Not modified` is returned when matching `If-None-Match`
+pub async fn search(
+    uri: Uri,
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    if_none_match: IfNoneMatch,
+    pagination: Pagination,
+) -> Result<Success<Vec<SearchResultResponse>>, Error> {
+    current_user.is_allowed(Action::ViewDestinations)?;
+
+    let query = parse_query(&uri)?;
+
+    let results = database
+        .search(
+            &query,
+            pagination.limit().unwrap_or(DEFAULT_LIMIT),
+            pagination.offset(),
+        )
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    let etag = ETag::from_updates(results.iter().map(|result| {
+        (
+            result.note_id.unwrap_or(result.destination_id),
+            result.updated_at,
+        )
+    }));
+
+    Ok(Success::ok(
+        results
+            .into_iter()
+            .map(SearchResultResponse::from_result)
+            .collect(),
+    )
+    .with_etag(etag, &if_none_match))
+}
+
+/// Parse the `q` query parameter, trimmed
+fn parse_query(uri: &Uri) -> Result<String, Error> {
+    let query = uri
+        .query()
+        .and_then(|query| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .find(|(key, _)| key == "q")
+                .map(|(_, value)| value.trim().to_string())
+        })
+        .unwrap_or_default();
+
+    let length = query.chars().count();
+    if length < MIN_QUERY_LENGTH {
+        return Err(
+            Error::bad_request("Query is too short").with_description(format!(
+                "Search with at least {MIN_QUERY_LENGTH} characters"
+            )),
+        );
+    }
+
+    if length > MAX_QUERY_LENGTH {
+        return Err(Error::bad_request("Query is too long")
+            .with_description(format!("Search with at most {MAX_QUERY_LENGTH} characters")));
+    }
+
+    Ok(query)
+}
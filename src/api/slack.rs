@@ -0,0 +1,228 @@
+//! Slack slash-command endpoint
+//!
+//! Slack posts the commands form encoded, signed with the signing secret of the Slack app. The
+//! replies are only shown to the Slack user running the command.
+
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::http::HeaderMap;
+use axum::Extension;
+use axum::Json;
+use chrono::Days;
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::approval::ApprovalStatus;
+use crate::client_ip::ClientIp;
+use crate::database::Database;
+use crate::permissions::Action;
+use crate::root::Settings as RootSettings;
+use crate::slack::Command;
+use crate::slack::HELP;
+
+use super::destinations::create_new_destination;
+use super::destinations::CreateDestinationForm;
+use super::destinations::NewDestination;
+use super::parse_slug;
+use super::AuditTrail;
+use super::CurrentUser;
+use super::Error;
+
+/// Header with the signature of Slack
+const SIGNATURE_HEADER: &str = "x-slack-signature";
+
+/// Header with the time Slack sent the request
+const TIMESTAMP_HEADER: &str = "x-slack-request-timestamp";
+
+/// Number of days of the recent hits in the stats
+const RECENT_DAYS: u64 = 7;
+
+/// Reply to a slash-command, shown to the Slack user running it
+#[derive(Debug, Serialize)]
+pub struct SlackReply {
+    /// Only shown to the Slack user, `ephemeral`
+    response_type: &'static str,
+
+    /// The text of the reply, in Slack markdown
+    text: String,
+}
+
+impl SlackReply {
+    /// Create a reply with the text
+    fn new<T>(text: T) -> Self
+    where
+        T: ToString,
+    {
+        Self {
+            response_type: "ephemeral",
+            text: text.to_string(),
+        }
+    }
+}
+
+/// Run a slash-command of Slack, like `/shurly create <slug> <url>` and `/shurly stats <slug>`
+///
+/// Only with a valid signature of Slack, for a Slack user mapped to a Shurly user. Failing
+/// commands are replied to with the reason, Slack only shows replies of successful requests.
+///
+/// Response:
+/// ```json
+/// { "response_type": "ephemeral", "text": "Created `launch`, redirecting to https://www.example.com/" }
+/// ```
+pub async fn command(
+    headers: HeaderMap,
+    client_ip: Option<ClientIp>,
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    body: Bytes,
+) -> Result<Json<SlackReply>, Error> {
+    if !root_settings.slack.is_enabled() {
+        return Err(Error::not_found("Slack integration is not enabled"));
+    }
+
+    let timestamp = headers
+        .get(TIMESTAMP_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let signature = headers
+        .get(SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    if !root_settings.slack.verify(timestamp, signature, &body) {
+        return Err(Error::forbidden("Invalid Slack signature"));
+    }
+
+    let mut slack_user_id = String::new();
+    let mut text = String::new();
+    for (key, value) in url::form_urlencoded::parse(&body) {
+        match key.as_ref() {
+            "user_id" => slack_user_id = value.into_owned(),
+            "text" => text = value.into_owned(),
+            _ => {}
+        }
+    }
+
+    let Some(username) = root_settings.slack.username(&slack_user_id) else {
+        return Ok(Json(SlackReply::new(format!(
+            "Your Slack user `{slack_user_id}` is not linked to a Shurly user"
+        ))));
+    };
+
+    let Some(user) = database
+        .find_single_user_by_username(username)
+        .await
+        .map_err(Error::internal_server_error)?
+    else {
+        return Ok(Json(SlackReply::new(format!(
+            "The Shurly user `{username}` does not exist"
+        ))));
+    };
+
+    let current_user = CurrentUser::new(
+        user,
+        Arc::clone(&root_settings.permissions),
+        root_settings.auth_metrics.clone(),
+    );
+    let audit_trail = AuditTrail::new(
+        database.clone(),
+        current_user.clone(),
+        client_ip.map(|client_ip| client_ip.0),
+        &root_settings,
+    );
+
+    let reply = match Command::parse(&text) {
+        Command::Create { slug, url } => {
+            create(
+                &headers,
+                &audit_trail,
+                &database,
+                &root_settings,
+                &current_user,
+                slug,
+                url,
+            )
+            .await
+        }
+        Command::Stats { slug } => stats(&database, &current_user, slug).await,
+        Command::Help => Ok(HELP.to_string()),
+    };
+
+    Ok(Json(SlackReply::new(
+        reply.unwrap_or_else(|err| err.to_string()),
+    )))
+}
+
+/// Create a destination for the slug, like `POST /api/destinations`
+async fn create(
+    headers: &HeaderMap,
+    audit_trail: &AuditTrail,
+    database: &Database,
+    root_settings: &RootSettings,
+    current_user: &CurrentUser,
+    slug: &str,
+    url: &str,
+) -> Result<String, Error> {
+    current_user.is_allowed(Action::CreateDestinations)?;
+
+    let form = CreateDestinationForm::new(slug.to_string(), url.to_string());
+    let new_destination = NewDestination::from_form(database, root_settings, headers, form).await?;
+
+    let (destination, _) = create_new_destination(
+        audit_trail,
+        database,
+        root_settings,
+        current_user,
+        &new_destination,
+    )
+    .await?;
+
+    if destination.approval_status() == ApprovalStatus::Pending {
+        return Ok(format!(
+            "Created `{}`, awaiting approval of an admin",
+            destination.slug
+        ));
+    }
+
+    Ok(format!(
+        "Created `{}`, redirecting to {}",
+        destination.slug, destination.url
+    ))
+}
+
+/// The hits of the destination of the slug
+async fn stats(
+    database: &Database,
+    current_user: &CurrentUser,
+    slug: &str,
+) -> Result<String, Error> {
+    current_user.is_allowed(Action::ViewDestinations)?;
+
+    let slug = parse_slug(slug)?;
+
+    let destination = database
+        .find_single_destination_in_namespace(None, &slug)
+        .await
+        .map_err(Error::internal_server_error)?
+        .filter(|destination| !destination.is_deleted())
+        .ok_or_else(|| Error::not_found("Destination not found"))?;
+
+    let daily_hits = database
+        .find_daily_hits(&destination)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    let since = Utc::now().date_naive() - Days::new(RECENT_DAYS - 1);
+    let total = daily_hits.iter().map(|day| day.hits).sum::<i64>();
+    let recent = daily_hits
+        .iter()
+        .filter(|day| day.day >= since)
+        .map(|day| day.hits)
+        .sum::<i64>();
+
+    Ok(format!(
+        "`{}` has {total} hits, {recent} in the last {RECENT_DAYS} days",
+        destination.slug
+    ))
+}
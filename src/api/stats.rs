@@ -0,0 +1,220 @@
+//! Stats API endpoints
+//!
+//! When the audience of a destination clicks, to plan campaigns by, and how the sources of the
+//! destinations compare
+
+use axum::http::Uri;
+use axum::Extension;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::destinations::Destination;
+use crate::permissions::Action;
+use crate::root::Settings as RootSettings;
+
+use super::CurrentUser;
+use super::Error;
+use super::PathParameters;
+use super::Success;
+
+/// Time zone of the heatmap without a `timezone`
+const DEFAULT_TIMEZONE: &str = "UTC";
+
+/// Maximum number of days of the heatmap
+const MAX_DAYS: i32 = 366;
+
+/// Heatmap of the hits of a destination going to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatmapResponse {
+    /// Channel the short link is handed out in, like `print-flyer`
+    pub source: Option<String>,
+
+    /// Time zone of the hours
+    pub timezone: String,
+
+    /// Number of last days covered, all hits that are not pruned yet when empty
+    pub days: Option<i32>,
+
+    /// Number of hits of all hours
+    pub hits: i64,
+
+    /// Every hour of every day of the week, Monday 00:00 first
+    pub hours: Vec<HeatmapHour>,
+}
+
+/// Hits in an hour of the week
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatmapHour {
+    /// Day of the week, from 1 for Monday to 7 for Sunday
+    pub weekday: i32,
+
+    /// Hour of the day, from 0 to 23
+    pub hour: i32,
+
+    /// Number of hits in the hour
+    pub hits: i64,
+}
+
+/// Hits of the destinations of a source going to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceResponse {
+    /// The source, empty for the destinations without a source
+    pub source: Option<String>,
+
+    /// Number of destinations with the source
+    pub destinations: i64,
+
+    /// Number of hits of the destinations
+    pub hits: i64,
+}
+
+/// Get the hits of a destination per hour of the day and day of the week, to see when its
+/// audience clicks
+///
+/// The hours are in the `timezone` query parameter, like `Europe/Amsterdam`, UTC by default. The
+/// `days` query parameter limits the hits to the last number of days. Only the hits that are not
+/// pruned yet are counted, the daily rollups do not know the hour.
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Authorization: Bearer tokentokentoken' \
+///     'http://localhost:7000/api/destinations/<uuid>/stats/heatmap?timezone=Europe/Amsterdam&days=28'
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "timezone": "Europe/Amsterdam", "days": 28, "hits": 312, "hours": [ { "weekday": 1, "hour": 0, "hits": 0 } ... ] } }
+/// ```
+pub async fn heatmap(
+    uri: Uri,
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+    PathParameters(destination_id): PathParameters<Uuid>,
+) -> Result<Success<HeatmapResponse>, Error> {
+    current_user.is_allowed(Action::ViewDestinations)?;
+
+    let destination = fetch_destination(&database, &destination_id).await?;
+
+    let mut timezone = DEFAULT_TIMEZONE.to_string();
+    let mut days = None;
+    for (key, value) in url::form_urlencoded::parse(uri.query().unwrap_or_default().as_bytes()) {
+        match key.as_ref() {
+            "timezone" => timezone = value.to_string(),
+            "days" => days = Some(parse_days(&value)?),
+            _ => {}
+        }
+    }
+
+    if !database
+        .is_known_timezone(&timezone)
+        .await
+        .map_err(Error::internal_server_error)?
+    {
+        return Err(Error::bad_request("Invalid timezone")
+            .with_description("Use a name of the tz database, like `Europe/Amsterdam`"));
+    }
+
+    let hourly_hits = root_settings
+        .stats_cache
+        .hourly_hits(&database, &destination, &timezone, days)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    let hours = (1..=7)
+        .flat_map(|weekday| (0..24).map(move |hour| (weekday, hour)))
+        .map(|(weekday, hour)| HeatmapHour {
+            weekday,
+            hour,
+            hits: hourly_hits
+                .iter()
+                .find(|hourly| hourly.weekday == weekday && hourly.hour == hour)
+                .map_or(0, |hourly| hourly.hits),
+        })
+        .collect();
+
+    Ok(Success::ok(HeatmapResponse {
+        source: destination.source,
+        timezone,
+        days,
+        hits: hourly_hits.iter().map(|hourly| hourly.hits).sum(),
+        hours,
+    }))
+}
+
+/// Get the hits of the destinations grouped by their source, the most hits first, to compare
+/// channels like printed flyers and email footers
+///
+/// The `days` query parameter limits the hits to the last number of days. Counted from the daily
+/// rollups, pruned hits included.
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Authorization: Bearer tokentokentoken' \
+///     'http://localhost:7000/api/stats/sources?days=28'
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": [ { "source": "print-flyer", "destinations": 4, "hits": 312 }, { "source": null ... } ] }
+/// ```
+pub async fn sources(
+    uri: Uri,
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+) -> Result<Success<Vec<SourceResponse>>, Error> {
+    current_user.is_allowed(Action::ViewDestinations)?;
+
+    let mut days = None;
+    for (key, value) in url::form_urlencoded::parse(uri.query().unwrap_or_default().as_bytes()) {
+        if key == "days" {
+            days = Some(parse_days(&value)?);
+        }
+    }
+
+    let source_hits = root_settings
+        .stats_cache
+        .hits_by_source(&database, days)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Success::ok(
+        source_hits
+            .iter()
+            .map(|source_hits| SourceResponse {
+                source: source_hits.source.clone(),
+                destinations: source_hits.destinations,
+                hits: source_hits.hits,
+            })
+            .collect(),
+    ))
+}
+
+/// Parse the number of last days of the stats
+fn parse_days(value: &str) -> Result<i32, Error> {
+    value
+        .parse::<i32>()
+        .ok()
+        .filter(|days| (1..=MAX_DAYS).contains(days))
+        .ok_or_else(|| {
+            Error::bad_request("Invalid days")
+                .with_description(format!("Days is a number from 1 to {MAX_DAYS}"))
+        })
+}
+
+/// Fetch destination from database
+async fn fetch_destination(
+    database: &Database,
+    destination_id: &Uuid,
+) -> Result<Destination, Error> {
+    database
+        .find_single_destination_by_id(destination_id)
+        .await
+        .map_err(Error::internal_server_error)?
+        .map_or_else(|| Err(Error::not_found("Destination not found")), Ok)
+}
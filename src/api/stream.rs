@@ -0,0 +1,70 @@
+//! Stream API endpoints
+//!
+//! Server-sent events of the live activity, for dashboards that should not poll
+
+use std::convert::Infallible;
+
+use axum::response::sse::Event as SseEvent;
+use axum::response::sse::KeepAlive;
+use axum::response::sse::Sse;
+use axum::Extension;
+use futures_util::stream;
+use futures_util::Stream;
+use futures_util::StreamExt;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::graceful_shutdown;
+use crate::permissions::Action;
+use crate::root::Settings as RootSettings;
+
+use super::CurrentUser;
+use super::Error;
+
+/// Stream the changes on the audit trail as they happen
+///
+/// Every change is an event with the type of the change as its name and the ID of the audit trail
+/// entry as its ID, the data is the same as the webhooks get. Only the changes made via this
+/// instance are part of the stream, the stream ends when the instance shuts down.
+///
+/// Request:
+/// ```sh
+/// curl -v -N -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/stream/events
+/// ```
+///
+/// Response:
+/// ```text
+/// event: create-destination
+/// id: <uuid>
+/// data: {"id":"<uuid>","event":"create-destination","destination":{"slug":"some-slug" ... }}
+/// ```
+pub async fn events(
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+) -> Result<Sse<impl Stream<Item = Result<SseEvent, Infallible>>>, Error> {
+    current_user.is_allowed(Action::ManageSystem)?;
+
+    let receiver = root_settings.activity.subscribe();
+
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(entry) => {
+                    let event = SseEvent::default()
+                        .event(&entry.event)
+                        .id(entry.id.to_string())
+                        .data(&entry.payload);
+
+                    return Some((Ok(event), receiver));
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::debug!("Event stream fell behind, skipped {skipped} event(s)");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+    .take_until(graceful_shutdown::started());
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
@@ -4,17 +4,25 @@ use std::collections::HashMap;
 use std::ops::Deref;
 
 use axum::Extension;
+use chrono::NaiveDateTime;
 use serde::Deserialize;
 use serde::Serialize;
 use uuid::Uuid;
 
+use crate::auth_metrics::Outcome;
 use crate::database::AuditEntry;
 use crate::database::ChangePasswordValues;
 use crate::database::CreateUserValues;
 use crate::database::Database;
+use crate::digests::Digest;
+use crate::notifier::is_valid_address;
+use crate::notifier::Notification;
+use crate::notifier::MAX_ADDRESS_LENGTH;
 use crate::password::generate;
 use crate::password::hash;
 use crate::password::verify;
+use crate::permissions::Action;
+use crate::root::Settings as RootSettings;
 use crate::users::Role;
 use crate::users::User;
 
@@ -42,6 +50,9 @@ pub struct UserResponse {
     /// The role of the user
     pub role: Role,
 
+    /// The email address, for the notifications
+    pub email: Option<String>,
+
     /// The password, if generated
     // Password should only be added when newly generated
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -55,6 +66,7 @@ impl UserResponse {
             id: user.id,
             username: user.username,
             role: user.role,
+            email: user.email,
             password: None,
         }
     }
@@ -101,6 +113,7 @@ pub struct LoginForm {
 pub async fn token(
     Extension(jwt_keys): Extension<JwtKeys>,
     Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
     Form(form): Form<LoginForm>,
 ) -> Result<Success<Token>, Error> {
     let user = database
@@ -112,11 +125,17 @@ pub async fn token(
         if verify(&user.hashed_password, &form.password) {
             let token = generate_token(&jwt_keys, &user)?;
 
+            root_settings.auth_metrics.record(Outcome::TokenIssued);
+
             Ok(Success::ok(token))
         } else {
+            root_settings.auth_metrics.record(Outcome::FailedLogin);
+
             Err(Error::bad_request("Invalid user"))
         }
     } else {
+        root_settings.auth_metrics.record(Outcome::FailedLogin);
+
         Err(Error::bad_request("Invalid user"))
     }
 }
@@ -138,7 +157,7 @@ pub async fn list(
     Extension(database): Extension<Database>,
     current_user: CurrentUser,
 ) -> Result<Success<Vec<UserResponse>>, Error> {
-    current_user.role.is_allowed(Role::Admin)?;
+    current_user.is_allowed(Action::ManageUsers)?;
 
     let users = database
         .find_all_users()
@@ -176,10 +195,9 @@ pub async fn single(
     PathParameters(params): PathParameters<HashMap<String, Uuid>>,
 ) -> Result<Success<UserResponse>, Error> {
     let user = if let Some(user_id) = params.get("user") {
-        current_user.role.is_allowed(Role::Admin)?;
+        current_user.is_allowed(Action::ManageUsers)?;
         fetch_user(&database, user_id).await?
     } else {
-        current_user.role.is_allowed(Role::Manager)?;
         current_user.deref().clone()
     };
 
@@ -199,10 +217,14 @@ pub struct CreateUserForm {
     /// When not provided a new password will be generated and returned in the response, this will
     /// be the only time the password is visible -- make sure to capture it.
     password: Option<String>,
+    /// Optional email address of the new user, for the notifications
+    email: Option<String>,
 }
 
 /// Create a user based on the [`CreateUserForm`](CreateUserForm) form
 ///
+/// With an email address, the new user is invited with an email
+///
 /// Request:
 /// ```sh
 /// curl -v -H 'Content-Type: application/json' \
@@ -218,10 +240,14 @@ pub struct CreateUserForm {
 pub async fn create(
     audit_trail: AuditTrail,
     Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
     current_user: CurrentUser,
     Form(form): Form<CreateUserForm>,
 ) -> Result<Success<UserResponse>, Error> {
-    current_user.role.is_allowed(Role::Admin)?;
+    current_user.is_allowed(Action::ManageUsers)?;
+    current_user.is_allowed_to_manage(form.role)?;
+
+    let email = parse_email(form.email.as_deref())?;
 
     let user = database
         .find_single_user_by_username(&form.username)
@@ -248,6 +274,7 @@ pub async fn create(
             role: form.role,
             username: &form.username,
             hashed_password: &hashed_password,
+            email: email.as_deref(),
         };
 
         let user = database
@@ -257,6 +284,10 @@ pub async fn create(
 
         audit_trail.register(AuditEntry::CreateUser(&user)).await;
 
+        root_settings
+            .notifier
+            .notify(&user, &Notification::Invitation { role: user.role });
+
         let mut response = UserResponse::from_user(user);
 
         // only add the generated password, its the only time the password is known to anybody
@@ -287,7 +318,8 @@ pub struct ChangePasswordForm {
 ///
 /// By passing `me` instead of a user ID, the password of the current user is changed
 ///
-/// Changing your password will invalidate your current access token
+/// Changing your password will invalidate your current access token, the user is notified with an
+/// email
 ///
 /// Request:
 /// ```sh
@@ -305,15 +337,17 @@ pub async fn change_password(
     audit_trail: AuditTrail,
     Extension(jwt_keys): Extension<JwtKeys>,
     Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
     current_user: CurrentUser,
     PathParameters(params): PathParameters<HashMap<String, Uuid>>,
     Form(form): Form<ChangePasswordForm>,
 ) -> Result<Success<Token>, Error> {
     let user = if let Some(user_id) = params.get("user") {
-        current_user.role.is_allowed(Role::Admin)?;
-        fetch_user(&database, user_id).await?
+        current_user.is_allowed(Action::ManageUsers)?;
+        let user = fetch_user(&database, user_id).await?;
+        current_user.is_allowed_to_manage(user.role)?;
+        user
     } else {
-        current_user.role.is_allowed(Role::Manager)?;
         current_user.deref().clone()
     };
 
@@ -338,11 +372,71 @@ pub async fn change_password(
         .register(AuditEntry::ChangePassword(&user))
         .await;
 
+    root_settings
+        .notifier
+        .notify(&updated_user, &Notification::PasswordChanged);
+
     let token = generate_token(&jwt_keys, &updated_user)?;
 
+    root_settings.auth_metrics.record(Outcome::TokenIssued);
+
     Ok(Success::ok(token))
 }
 
+/// Change email form
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeEmailForm {
+    /// The new email address, `null` to remove it
+    email: Option<String>,
+}
+
+/// Change the email address of a user or the current user, used for the notifications
+///
+/// By passing `me` instead of a user ID, the email address of the current user is changed
+///
+/// Request:
+/// ```sh
+/// curl -v -XPUT -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     -d '{ "email": "someone@example.com" }' \
+///     http://localhost:7000/api/users/me/email
+/// ```
+///
+/// Response
+/// ```json
+/// { "data": { "id": "<uuid>", "username": "some-username", "email": "someone@example.com" ... } }
+/// ```
+pub async fn change_email(
+    audit_trail: AuditTrail,
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    PathParameters(params): PathParameters<HashMap<String, Uuid>>,
+    Form(form): Form<ChangeEmailForm>,
+) -> Result<Success<UserResponse>, Error> {
+    let user = if let Some(user_id) = params.get("user") {
+        current_user.is_allowed(Action::ManageUsers)?;
+        let user = fetch_user(&database, user_id).await?;
+        current_user.is_allowed_to_manage(user.role)?;
+        user
+    } else {
+        current_user.deref().clone()
+    };
+
+    let email = parse_email(form.email.as_deref())?;
+
+    let updated_user = database
+        .change_email(&user, email.as_deref())
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    audit_trail
+        .register(AuditEntry::ChangeEmail(&updated_user))
+        .await;
+
+    Ok(Success::ok(UserResponse::from_user(updated_user)))
+}
+
 /// Delete a user
 ///
 /// Request:
@@ -357,9 +451,10 @@ pub async fn delete(
     current_user: CurrentUser,
     PathParameters(user_id): PathParameters<Uuid>,
 ) -> Result<Success<&'static str>, Error> {
-    current_user.role.is_allowed(Role::Admin)?;
+    current_user.is_allowed(Action::ManageUsers)?;
 
     let user = fetch_user(&database, &user_id).await?;
+    current_user.is_allowed_to_manage(user.role)?;
 
     database
         .delete_user(&user)
@@ -371,6 +466,304 @@ pub async fn delete(
     Ok(Success::<&'static str>::no_content())
 }
 
+/// Usage response going to the user, the consumption with the quota
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageResponse {
+    /// Number of destinations that are not deleted
+    pub destinations: i64,
+
+    /// Maximum number of destinations, `null` for no maximum
+    pub max_destinations: Option<i64>,
+
+    /// Number of hits of the destinations since the first day of the month
+    pub hits_this_month: i64,
+
+    /// Maximum number of hits per month, `null` for no maximum
+    pub max_hits_per_month: Option<i64>,
+
+    /// Is the quota set for the user, instead of its role?
+    pub is_user_quota: bool,
+}
+
+/// Get the usage of a user or the current user, with the quota
+///
+/// By passing `me` instead of a user ID, the usage of the current user is returned
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/users/me/usage
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "destinations": 12, "maxDestinations": 100, "hitsThisMonth": 420 ... } }
+/// ```
+pub async fn usage(
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+    PathParameters(params): PathParameters<HashMap<String, Uuid>>,
+) -> Result<Success<UsageResponse>, Error> {
+    let user = if let Some(user_id) = params.get("user") {
+        current_user.is_allowed(Action::ManageUsers)?;
+        fetch_user(&database, user_id).await?
+    } else {
+        current_user.deref().clone()
+    };
+
+    usage_response(&database, &root_settings, &user)
+        .await
+        .map(Success::ok)
+}
+
+/// Quota form, `null` for no maximum
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaForm {
+    /// Maximum number of destinations
+    max_destinations: Option<i64>,
+
+    /// Maximum number of hits per month
+    max_hits_per_month: Option<i64>,
+}
+
+/// Set the quota of a user, overriding the quota of its role
+///
+/// Request:
+/// ```sh
+/// curl -v -XPUT -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     -d '{ "maxDestinations": 100, "maxHitsPerMonth": null }' \
+///     http://localhost:7000/api/users/<uuid>/quota
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "destinations": 12, "maxDestinations": 100, "isUserQuota": true ... } }
+/// ```
+pub async fn update_quota(
+    audit_trail: AuditTrail,
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+    PathParameters(user_id): PathParameters<Uuid>,
+    Form(form): Form<QuotaForm>,
+) -> Result<Success<UsageResponse>, Error> {
+    current_user.is_allowed(Action::ManageUsers)?;
+
+    let user = fetch_user(&database, &user_id).await?;
+    current_user.is_allowed_to_manage(user.role)?;
+
+    if [form.max_destinations, form.max_hits_per_month]
+        .into_iter()
+        .flatten()
+        .any(|max| max < 0)
+    {
+        return Err(
+            Error::bad_request("Invalid quota").with_description("A maximum can not be negative")
+        );
+    }
+
+    database
+        .save_user_quota(&user, form.max_destinations, form.max_hits_per_month)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    audit_trail.register(AuditEntry::ChangeQuota(&user)).await;
+
+    usage_response(&database, &root_settings, &user)
+        .await
+        .map(Success::ok)
+}
+
+/// Remove the quota of a user, the quota of its role applies again
+///
+/// Request:
+/// ```sh
+/// curl -v -XDELETE \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/users/<uuid>/quota
+/// ```
+pub async fn delete_quota(
+    audit_trail: AuditTrail,
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    PathParameters(user_id): PathParameters<Uuid>,
+) -> Result<Success<&'static str>, Error> {
+    current_user.is_allowed(Action::ManageUsers)?;
+
+    let user = fetch_user(&database, &user_id).await?;
+    current_user.is_allowed_to_manage(user.role)?;
+
+    database
+        .delete_user_quota(&user)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    audit_trail.register(AuditEntry::ChangeQuota(&user)).await;
+
+    Ok(Success::<&'static str>::no_content())
+}
+
+/// Digest response going to the user, the subscription with a preview of the digest
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestResponse {
+    /// Is the user subscribed to the weekly digest?
+    pub is_subscribed: bool,
+
+    /// Moment the last digest is sent, `null` when none is sent yet
+    pub last_sent_at: Option<NaiveDateTime>,
+
+    /// The digest, as it would be sent now
+    pub digest: Digest,
+}
+
+/// Get the digest subscription of the current user, with a preview of the digest
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/users/me/digest
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "isSubscribed": true, "digest": { "hits": 42, "previousHits": 36 ... } } }
+/// ```
+pub async fn digest(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+) -> Result<Success<DigestResponse>, Error> {
+    digest_response(&database, &current_user)
+        .await
+        .map(Success::ok)
+}
+
+/// Subscribe the current user to the weekly digest, sent to its email address
+///
+/// Request:
+/// ```sh
+/// curl -v -XPUT \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/users/me/digest
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "isSubscribed": true, "lastSentAt": null, "digest": { ... } } }
+/// ```
+pub async fn subscribe_to_digest(
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+    current_user: CurrentUser,
+) -> Result<Success<DigestResponse>, Error> {
+    if !root_settings.notifier.is_enabled() {
+        return Err(Error::bad_request("Email notifications are disabled")
+            .with_description("Digests are sent by email, configure SMTP_URL to send them"));
+    }
+
+    if current_user.email.is_none() {
+        return Err(Error::bad_request("Missing email address")
+            .with_description("Digests are sent by email, set an email address first"));
+    }
+
+    database
+        .subscribe_to_digest(&current_user)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    digest_response(&database, &current_user)
+        .await
+        .map(Success::ok)
+}
+
+/// Unsubscribe the current user from the weekly digest
+///
+/// Request:
+/// ```sh
+/// curl -v -XDELETE \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/users/me/digest
+/// ```
+pub async fn unsubscribe_from_digest(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+) -> Result<Success<&'static str>, Error> {
+    database
+        .unsubscribe_from_digest(&current_user)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Success::<&'static str>::no_content())
+}
+
+/// The digest subscription of the user, with a preview of the digest
+async fn digest_response(database: &Database, user: &User) -> Result<DigestResponse, Error> {
+    let subscription = database
+        .find_digest_subscription(user)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    let digest = Digest::build(database, user)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(DigestResponse {
+        is_subscribed: subscription.is_some(),
+        last_sent_at: subscription.and_then(|subscription| subscription.last_sent_at),
+        digest,
+    })
+}
+
+/// The usage of the user, with its quota
+async fn usage_response(
+    database: &Database,
+    root_settings: &RootSettings,
+    user: &User,
+) -> Result<UsageResponse, Error> {
+    let user_quota = database
+        .find_user_quota(user)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    let usage = database
+        .find_usage(user)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    let quota = root_settings.quotas.of(user.role, user_quota.as_ref());
+
+    Ok(UsageResponse {
+        destinations: usage.destinations,
+        max_destinations: quota.max_destinations,
+        hits_this_month: usage.hits_this_month,
+        max_hits_per_month: quota.max_hits_per_month,
+        is_user_quota: user_quota.is_some(),
+    })
+}
+
+/// Parse an optional email address, an empty address is no address
+fn parse_email(email: Option<&str>) -> Result<Option<String>, Error> {
+    let Some(email) = email.map(str::trim).filter(|email| !email.is_empty()) else {
+        return Ok(None);
+    };
+
+    if !is_valid_address(email) {
+        return Err(
+            Error::bad_request("Invalid email address").with_description(format!(
+            "Expected an address like someone@example.com, up to {MAX_ADDRESS_LENGTH} characters"
+        )),
+        );
+    }
+
+    Ok(Some(email.to_string()))
+}
+
 /// Fetch a user from database
 async fn fetch_user(database: &Database, user_id: &Uuid) -> Result<User, Error> {
     database
@@ -0,0 +1,75 @@
+//! Version API endpoint
+//!
+//! Which build an instance is running, for the tooling of a fleet of instances
+
+use chrono::DateTime;
+use chrono::Utc;
+use serde::Serialize;
+
+use super::CurrentUser;
+use super::Error;
+use super::Success;
+
+/// Version of the crate
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Git SHA of the build, `unknown` when built without Git
+const GIT_SHA: &str = env!("SHURLY_GIT_SHA");
+
+/// Seconds since the Unix epoch of the build
+const BUILD_TIMESTAMP: &str = env!("SHURLY_BUILD_TIMESTAMP");
+
+/// Enabled Cargo features of the build, comma separated
+const FEATURES: &str = env!("SHURLY_FEATURES");
+
+/// Version response going to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionResponse {
+    /// Version of Shurly
+    pub version: &'static str,
+
+    /// Git SHA of the build
+    pub git_sha: &'static str,
+
+    /// When Shurly was built
+    pub build_timestamp: Option<DateTime<Utc>>,
+
+    /// Enabled Cargo features
+    pub features: Vec<&'static str>,
+}
+
+impl VersionResponse {
+    /// Create the response for this build
+    fn current() -> Self {
+        Self {
+            version: VERSION,
+            git_sha: GIT_SHA,
+            build_timestamp: BUILD_TIMESTAMP
+                .parse::<i64>()
+                .ok()
+                .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0)),
+            features: FEATURES
+                .split(',')
+                .filter(|feature| !feature.is_empty())
+                .collect(),
+        }
+    }
+}
+
+/// Version of this instance of Shurly, for any user
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/version
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "version": "0.3.3", "gitSha": "<sha>", "buildTimestamp": "<timestamp>" ... } }
+/// ```
+pub async fn version(_current_user: CurrentUser) -> Result<Success<VersionResponse>, Error> {
+    Ok(Success::ok(VersionResponse::current()))
+}
@@ -0,0 +1,349 @@
+//! Webhooks API endpoints
+//!
+//! Everything related to the management of the webhooks and their deliveries
+
+use axum::Extension;
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::database::CreateWebhookValues;
+use crate::database::Database;
+use crate::password::generate;
+use crate::permissions::Action;
+use crate::webhooks::DeliveryStatus;
+use crate::webhooks::Webhook;
+use crate::webhooks::WebhookDelivery;
+
+use super::parse_url;
+use super::CurrentUser;
+use super::Error;
+use super::Form;
+use super::PathParameters;
+use super::Success;
+
+/// Number of deliveries in the delivery log
+const DELIVERY_LOG_SIZE: i64 = 100;
+
+/// Webhook response going to the user
+///
+/// Basically filtering which fields are shown to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookResponse {
+    /// Webhook ID
+    pub id: Uuid,
+
+    /// URL the events are posted to
+    pub url: String,
+
+    /// Secret of the signatures, only shown on creation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+
+    /// The destination of which the hits are posted, `null` for the changes
+    pub destination_id: Option<Uuid>,
+
+    /// Are the hits posted every minute, instead of right away
+    pub is_batched: bool,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+
+    /// Last updated at
+    pub updated_at: NaiveDateTime,
+}
+
+impl WebhookResponse {
+    /// Create a response from a [`Webhook`](Webhook), without its secret
+    ///
+    /// Basically filtering which fields are shown to the user
+    fn from_webhook(webhook: Webhook) -> Self {
+        Self {
+            id: webhook.id,
+            url: webhook.url,
+            secret: None,
+            destination_id: webhook.destination_id,
+            is_batched: webhook.is_batched,
+            created_at: webhook.created_at,
+            updated_at: webhook.updated_at,
+        }
+    }
+
+    /// Create a response from multiple [`Webhook`](Webhook)s, without their secrets
+    ///
+    /// Basically filtering which fields are shown to the user
+    fn from_webhook_multiple(mut webhooks: Vec<Webhook>) -> Vec<Self> {
+        webhooks
+            .drain(..)
+            .map(Self::from_webhook)
+            .collect::<Vec<Self>>()
+    }
+}
+
+/// Webhook delivery response going to the user
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDeliveryResponse {
+    /// Delivery ID
+    pub id: Uuid,
+
+    /// Type of the event, like `create-destination`
+    pub event: String,
+
+    /// Status of the delivery
+    pub status: DeliveryStatus,
+
+    /// Number of attempts so far
+    pub attempts: i32,
+
+    /// Status code of the response of the last attempt
+    pub status_code: Option<i32>,
+
+    /// Why the last attempt failed
+    pub error: Option<String>,
+
+    /// Moment of the next attempt, when pending
+    pub next_attempt_at: Option<NaiveDateTime>,
+
+    /// Moment of the successful attempt
+    pub delivered_at: Option<NaiveDateTime>,
+
+    /// The event as posted
+    pub payload: Option<Value>,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+
+    /// Last updated at
+    pub updated_at: NaiveDateTime,
+}
+
+impl WebhookDeliveryResponse {
+    /// Create a response from a [`WebhookDelivery`](WebhookDelivery)
+    fn from_delivery(delivery: WebhookDelivery) -> Self {
+        Self {
+            id: delivery.id,
+            status: delivery.status(),
+            attempts: delivery.attempts,
+            status_code: delivery.status_code,
+            error: delivery.error,
+            next_attempt_at: delivery.next_attempt_at,
+            delivered_at: delivery.delivered_at,
+            payload: serde_json::from_str(&delivery.payload).ok(),
+            event: delivery.event,
+            created_at: delivery.created_at,
+            updated_at: delivery.updated_at,
+        }
+    }
+}
+
+/// List all webhooks
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/webhooks
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": [ { "id": "<uuid>", "url": "https://cms.acme.com/shurly" ... } ] }
+/// ```
+pub async fn list(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+) -> Result<Success<Vec<WebhookResponse>>, Error> {
+    current_user.is_allowed(Action::ManageWebhooks)?;
+
+    let webhooks = database
+        .find_all_webhooks()
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Success::ok(WebhookResponse::from_webhook_multiple(
+        webhooks,
+    )))
+}
+
+/// Get a single webhook
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/webhooks/<uuid>
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": { "id": "<uuid>", "url": "https://cms.acme.com/shurly" ... } }
+/// ```
+pub async fn single(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    PathParameters(webhook_id): PathParameters<Uuid>,
+) -> Result<Success<WebhookResponse>, Error> {
+    current_user.is_allowed(Action::ManageWebhooks)?;
+
+    fetch_webhook(&database, &webhook_id)
+        .await
+        .map(|webhook| Success::ok(WebhookResponse::from_webhook(webhook)))
+}
+
+/// Create webhook form
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWebhookForm {
+    /// URL the events are posted to
+    url: String,
+
+    /// Post the hits of this destination, instead of the changes
+    destination_id: Option<Uuid>,
+
+    /// Post the hits every minute, instead of right away
+    #[serde(default)]
+    is_batched: bool,
+}
+
+/// Register a webhook based on the [`CreateWebhookForm`](CreateWebhookForm) form
+///
+/// The secret of the signatures is generated, it is only part of this response. With a
+/// `destinationId` the webhook receives the hits of the destination, right away or batched
+/// every minute with `isBatched`.
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     -d '{ "url": "https://cms.acme.com/shurly" }' \
+///     http://localhost:7000/api/webhooks
+///
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     -d '{ "url": "https://crm.acme.com/clicks", "destinationId": "<uuid>", "isBatched": true }' \
+///     http://localhost:7000/api/webhooks
+/// ```
+///
+/// Response
+/// ```json
+/// { "data": { "id": "<uuid>", "url": "https://cms.acme.com/shurly", "secret": "<secret>" ... } }
+/// ```
+pub async fn create(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    Form(form): Form<CreateWebhookForm>,
+) -> Result<Success<WebhookResponse>, Error> {
+    current_user.is_allowed(Action::ManageWebhooks)?;
+
+    let url = parse_url(&form.url)?;
+
+    let destination = match form.destination_id {
+        Some(destination_id) => Some(
+            database
+                .find_single_destination_by_id(&destination_id)
+                .await
+                .map_err(Error::internal_server_error)?
+                .ok_or_else(|| Error::bad_request("Destination not found"))?,
+        ),
+        None if form.is_batched => {
+            return Err(Error::bad_request("Only hits can be batched")
+                .with_description("Batched webhooks need a `destinationId`"));
+        }
+        None => None,
+    };
+
+    let secret = generate();
+
+    let values = CreateWebhookValues {
+        user: &current_user,
+        url: &url,
+        secret: &secret,
+        destination: destination.as_ref(),
+        is_batched: form.is_batched,
+    };
+
+    let webhook = database
+        .create_webhook(&values)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    let mut response = WebhookResponse::from_webhook(webhook);
+    response.secret = Some(secret);
+
+    Ok(Success::created(response))
+}
+
+/// Delete a webhook
+///
+/// The pending deliveries of the webhook are given up
+///
+/// Request:
+/// ```sh
+/// curl -v -XDELETE \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/webhooks/<uuid>
+/// ```
+pub async fn delete(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    PathParameters(webhook_id): PathParameters<Uuid>,
+) -> Result<Success<&'static str>, Error> {
+    current_user.is_allowed(Action::ManageWebhooks)?;
+
+    let webhook = fetch_webhook(&database, &webhook_id).await?;
+
+    database
+        .delete_webhook(&webhook)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Success::<&'static str>::no_content())
+}
+
+/// List the latest 100 deliveries of a webhook, newest first
+///
+/// Request:
+/// ```sh
+/// curl -v -H 'Content-Type: application/json' \
+///     -H 'Authorization: Bearer tokentokentoken' \
+///     http://localhost:7000/api/webhooks/<uuid>/deliveries
+/// ```
+///
+/// Response:
+/// ```json
+/// { "data": [ { "id": "<uuid>", "event": "create-destination", "status": "delivered" ... } ] }
+/// ```
+pub async fn deliveries(
+    Extension(database): Extension<Database>,
+    current_user: CurrentUser,
+    PathParameters(webhook_id): PathParameters<Uuid>,
+) -> Result<Success<Vec<WebhookDeliveryResponse>>, Error> {
+    current_user.is_allowed(Action::ManageWebhooks)?;
+
+    let webhook = fetch_webhook(&database, &webhook_id).await?;
+
+    let deliveries = database
+        .find_latest_webhook_deliveries(&webhook, DELIVERY_LOG_SIZE)
+        .await
+        .map_err(Error::internal_server_error)?;
+
+    Ok(Success::ok(
+        deliveries
+            .into_iter()
+            .map(WebhookDeliveryResponse::from_delivery)
+            .collect(),
+    ))
+}
+
+/// Fetch webhook from database
+async fn fetch_webhook(database: &Database, webhook_id: &Uuid) -> Result<Webhook, Error> {
+    database
+        .find_single_webhook_by_id(webhook_id)
+        .await
+        .map_err(Error::internal_server_error)?
+        .map_or_else(|| Err(Error::not_found("Webhook not found")), Ok)
+}
@@ -0,0 +1,73 @@
+//! Approval of new destinations
+//!
+//! With `DESTINATION_APPROVAL=managers` the destinations created by managers start as `pending`,
+//! the root does not resolve them until an admin approves them. Rejected destinations never
+//! resolve, they can only be deleted. Destinations created by admins, and all destinations without
+//! the setting, resolve right away.
+//!
+//! Every approval and rejection is on the audit trail, the webhooks and the event stream notify
+//! others; new pending destinations are part of the `create-destination` events.
+
+use serde::Serialize;
+
+use crate::users::Role;
+use crate::utils::env_var_optional;
+
+/// Which new destinations need the approval of an admin
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Approval {
+    /// None, all destinations resolve right away
+    #[default]
+    None,
+
+    /// The ones created by managers
+    Managers,
+}
+
+impl Approval {
+    /// Setup the approval based on the `DESTINATION_APPROVAL` environment variable
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the value is not `none` or `managers`
+    pub fn from_environment() -> anyhow::Result<Self> {
+        match env_var_optional("DESTINATION_APPROVAL").as_deref() {
+            None | Some("none") => Ok(Self::None),
+            Some("managers") => Ok(Self::Managers),
+            Some(other) => Err(anyhow::anyhow!(
+                "Invalid DESTINATION_APPROVAL: {other}, expected `none` or `managers`"
+            )),
+        }
+    }
+
+    /// Does a new destination of a user with the role need approval?
+    pub fn is_required_for(self, role: Role) -> bool {
+        self == Self::Managers && matches!(role, Role::Manager)
+    }
+}
+
+/// Status of the approval of a destination
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ApprovalStatus {
+    /// Waiting for an admin, not resolved at the root
+    Pending,
+
+    /// Resolved at the root
+    Approved,
+
+    /// Never resolved at the root
+    Rejected,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_required_for() {
+        assert!(!Approval::None.is_required_for(Role::Manager));
+        assert!(Approval::Managers.is_required_for(Role::Manager));
+        assert!(!Approval::Managers.is_required_for(Role::Admin));
+    }
+}
@@ -0,0 +1,149 @@
+//! Archival of old hits into cold storage
+//!
+//! Hits older than a number of months are moved out of the `hits` table into `hit_archives`, a
+//! single row per destination per month with the hits as JSON; Postgres compresses these big
+//! values. Only whole months are archived. The rollups are kept, the stats are not affected.
+//!
+//! With `HIT_ARCHIVE_MONTHS` the `archive-hits` job archives every day, on the leader. Admins can
+//! trigger a run with `POST /api/archival` and follow it with `GET /api/archival/<id>`. Every
+//! run is kept with its outcome.
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::database::Database;
+
+/// A run of the archival, by the job or triggered by an admin
+#[derive(Clone, Debug)]
+pub struct ArchivalRun {
+    /// Run ID
+    pub id: Uuid,
+
+    /// The admin that triggered the run, `None` for the job
+    pub user_id: Option<Uuid>,
+
+    /// Hits older than this number of months are archived
+    pub months: i32,
+
+    /// Number of archived hits, when succeeded
+    pub hits: Option<i64>,
+
+    /// Number of archives the hits are moved to, when succeeded
+    pub archives: Option<i64>,
+
+    /// Why the run failed
+    pub error: Option<String>,
+
+    /// Start of the run
+    pub started_at: NaiveDateTime,
+
+    /// End of the run, `None` while running
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+/// Status of a run
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArchivalStatus {
+    /// Still moving hits
+    Running,
+
+    /// All old hits are archived
+    Succeeded,
+
+    /// Nothing is archived
+    Failed,
+}
+
+impl ArchivalRun {
+    /// Status of the run
+    pub fn status(&self) -> ArchivalStatus {
+        if self.finished_at.is_none() {
+            ArchivalStatus::Running
+        } else if self.error.is_some() {
+            ArchivalStatus::Failed
+        } else {
+            ArchivalStatus::Succeeded
+        }
+    }
+
+    /// Description of the outcome, for the status of the job
+    pub fn describe(&self) -> Result<String, String> {
+        match &self.error {
+            Some(err) => Err(err.clone()),
+            None => Ok(format!(
+                "Archived {} hit(s) into {} archive(s)",
+                self.hits.unwrap_or_default(),
+                self.archives.unwrap_or_default()
+            )),
+        }
+    }
+}
+
+/// Outcome of moving the old hits
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Archived {
+    /// Number of archived hits
+    pub hits: i64,
+
+    /// Number of archives the hits are moved to
+    pub archives: i64,
+}
+
+/// Run the archival right away, like the job does
+pub async fn run(database: &Database, months: i32) -> anyhow::Result<ArchivalRun> {
+    let run = database.create_archival_run(months, None).await?;
+
+    archive(database, &run).await
+}
+
+/// Archive the old hits of a started run, all or nothing
+///
+/// The outcome is kept on the run, only failing to keep it is an error
+pub async fn archive(database: &Database, run: &ArchivalRun) -> anyhow::Result<ArchivalRun> {
+    let outcome = database
+        .archive_hits(run.months)
+        .await
+        .map_err(|err| err.to_string());
+
+    if let Err(err) = &outcome {
+        tracing::warn!("Archival of hits failed: {err}");
+    }
+
+    Ok(database.finish_archival_run(run, outcome).await?)
+}
+
+/// Parse the number of months hits are kept before they are archived, no archival without
+///
+/// # Errors
+///
+/// Will return `Err` when the number of months is not a positive number
+pub fn parse_months(months: Option<&str>) -> anyhow::Result<Option<i32>> {
+    months
+        .map(|months| {
+            months
+                .parse::<i32>()
+                .ok()
+                .filter(|months| *months > 0)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Invalid HIT_ARCHIVE_MONTHS: {months}, expected a positive number"
+                    )
+                })
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_months() {
+        assert_eq!(None, parse_months(None).unwrap());
+        assert_eq!(Some(6), parse_months(Some("6")).unwrap());
+        assert!(parse_months(Some("0")).is_err());
+        assert!(parse_months(Some("half a year")).is_err());
+    }
+}
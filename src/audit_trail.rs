@@ -0,0 +1,227 @@
+//! Audit trail
+//!
+//! Every entry on the audit trail is chained to the entry before it: the hash of an entry covers
+//! its own payload and the hash of the previous entry. Editing, inserting or removing entries in
+//! the middle of the trail will break the chain, which can be detected by [`verify`](verify).
+
+use chrono::naive::NaiveDateTime;
+use sha2::Digest;
+use sha2::Sha256;
+use uuid::Uuid;
+
+/// A single entry on the audit trail, as stored
+#[derive(Clone, Debug)]
+pub struct AuditTrailEntry {
+    /// Entry ID
+    pub id: Uuid,
+
+    /// Position of the entry on the trail
+    #[allow(dead_code)] // used by sqlx
+    pub sequence: i64,
+
+    /// Type of the entry, like `create-destination`
+    pub entry_type: String,
+
+    /// The ID of the user that created the entry
+    pub created_by: Uuid,
+
+    /// Optional user the entry is about
+    pub user_id: Option<Uuid>,
+
+    /// Optional destination the entry is about
+    pub destination_id: Option<Uuid>,
+
+    /// Optional note the entry is about
+    pub note_id: Option<Uuid>,
+
+    /// Optional domain the entry is about
+    pub domain_id: Option<Uuid>,
+
+    /// Optional IP address of the creator
+    pub ip_address: Option<String>,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+
+    /// Hash of the previous entry on the trail
+    pub previous_hash: Option<String>,
+
+    /// Hash of this entry, `None` for entries created before the trail was chained
+    pub hash: Option<String>,
+}
+
+impl AuditTrailEntry {
+    /// Calculate the hash of the entry, based on its payload and the previous hash
+    ///
+    /// The stored hash is ignored, this is used to create and verify the stored hash
+    #[must_use]
+    pub fn calculate_hash(&self) -> String {
+        /// Format an optional value, empty when missing
+        fn optional<T: ToString>(value: Option<&T>) -> String {
+            value.map(ToString::to_string).unwrap_or_default()
+        }
+
+        let mut payload = vec![
+            optional(self.previous_hash.as_ref()),
+            self.id.to_string(),
+            self.entry_type.clone(),
+            self.created_by.to_string(),
+            optional(self.user_id.as_ref()),
+            optional(self.destination_id.as_ref()),
+            optional(self.note_id.as_ref()),
+            optional(self.ip_address.as_ref()),
+            self.created_at.and_utc().timestamp_micros().to_string(),
+        ];
+
+        // only part of the payload when set, keeping the hashes of older entries valid
+        if let Some(domain_id) = self.domain_id {
+            payload.push(domain_id.to_string());
+        }
+
+        let payload = payload.join("\n");
+
+        format!("{:x}", Sha256::digest(payload.as_bytes()))
+    }
+}
+
+/// Outcome of verifying the audit trail
+#[derive(Debug, PartialEq, Eq)]
+pub struct Verification {
+    /// Number of entries with a verified hash
+    pub verified_entries: usize,
+
+    /// Number of entries created before the trail was chained
+    pub unchained_entries: usize,
+
+    /// The first entry that breaks the chain, if any
+    pub first_invalid_entry_id: Option<Uuid>,
+
+    /// Hash of the last verified entry, can be kept elsewhere to detect removal of entries
+    pub last_hash: Option<String>,
+}
+
+impl Verification {
+    /// Is the whole trail valid?
+    pub fn is_valid(&self) -> bool {
+        self.first_invalid_entry_id.is_none()
+    }
+}
+
+/// Verify the chain of the complete audit trail
+///
+/// The entries are expected to be in order of their sequence. Entries without a hash are only
+/// allowed at the start of the trail, those are created before chaining was introduced.
+pub fn verify(entries: &[AuditTrailEntry]) -> Verification {
+    let mut verification = Verification {
+        verified_entries: 0,
+        unchained_entries: 0,
+        first_invalid_entry_id: None,
+        last_hash: None,
+    };
+
+    for entry in entries {
+        let Some(ref hash) = entry.hash else {
+            if verification.verified_entries > 0 {
+                verification.first_invalid_entry_id = Some(entry.id);
+                break;
+            }
+
+            verification.unchained_entries += 1;
+            continue;
+        };
+
+        if entry.previous_hash != verification.last_hash || &entry.calculate_hash() != hash {
+            verification.first_invalid_entry_id = Some(entry.id);
+            break;
+        }
+
+        verification.verified_entries += 1;
+        verification.last_hash = Some(hash.clone());
+    }
+
+    verification
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a chained entry on top of the given previous hash
+    fn chained_entry(sequence: i64, previous_hash: Option<String>) -> AuditTrailEntry {
+        let mut entry = AuditTrailEntry {
+            id: Uuid::new_v4(),
+            sequence,
+            entry_type: "create-destination".to_string(),
+            created_by: Uuid::new_v4(),
+            user_id: None,
+            destination_id: Some(Uuid::new_v4()),
+            note_id: None,
+            domain_id: None,
+            ip_address: Some("127.0.0.1/32".to_string()),
+            created_at: chrono::Utc::now().naive_utc(),
+            previous_hash,
+            hash: None,
+        };
+
+        entry.hash = Some(entry.calculate_hash());
+
+        entry
+    }
+
+    /// Create a chain of a couple of entries
+    fn chain() -> Vec<AuditTrailEntry> {
+        let first = chained_entry(1, None);
+        let second = chained_entry(2, first.hash.clone());
+        let third = chained_entry(3, second.hash.clone());
+
+        vec![first, second, third]
+    }
+
+    #[test]
+    fn test_verify_valid_chain() {
+        let entries = chain();
+
+        let verification = verify(&entries);
+        assert!(verification.is_valid());
+        assert_eq!(3, verification.verified_entries);
+        assert_eq!(entries[2].hash, verification.last_hash);
+    }
+
+    #[test]
+    fn test_verify_edited_entry() {
+        let mut entries = chain();
+        entries[1].destination_id = Some(Uuid::new_v4());
+
+        let verification = verify(&entries);
+        assert_eq!(Some(entries[1].id), verification.first_invalid_entry_id);
+        assert_eq!(1, verification.verified_entries);
+    }
+
+    #[test]
+    fn test_verify_removed_entry() {
+        let mut entries = chain();
+        entries.remove(1);
+
+        let verification = verify(&entries);
+        assert_eq!(Some(entries[1].id), verification.first_invalid_entry_id);
+    }
+
+    #[test]
+    fn test_verify_unchained_entries() {
+        let mut unchained = chained_entry(1, None);
+        unchained.hash = None;
+
+        let mut entries = vec![unchained.clone()];
+        entries.extend(chain());
+
+        let verification = verify(&entries);
+        assert!(verification.is_valid());
+        assert_eq!(1, verification.unchained_entries);
+
+        // unchained entries after the chain started are not allowed
+        entries.push(unchained.clone());
+
+        let verification = verify(&entries);
+        assert_eq!(Some(unchained.id), verification.first_invalid_entry_id);
+    }
+}
@@ -0,0 +1,166 @@
+//! Counters of the outcomes of the authentication of the API
+//!
+//! Handy to alert on credential stuffing or misbehaving clients: a surge of failed logins, or of
+//! requests with an invalid or expired token. The counters are kept per instance, since startup,
+//! and served via the API to the admins.
+
+use std::net::IpAddr;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use moka::future::Cache;
+use uuid::Uuid;
+
+/// Attempts to use the token of a deleted user are registered on the audit trail once per this
+/// interval, per IP address
+const DELETED_USER_TOKEN_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Maximum number of remembered attempts to use the token of a deleted user
+const MAX_DELETED_USER_TOKENS: u64 = 10_000;
+
+/// Outcome of the authentication of a request
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// A token is issued, after a login
+    TokenIssued,
+
+    /// A login with an unknown username or a wrong password
+    FailedLogin,
+
+    /// A request without a token
+    MissingToken,
+
+    /// A request with a token that can not be decoded, or of an unknown user
+    InvalidToken,
+
+    /// A request with the token of a deleted user
+    DeletedUserToken,
+
+    /// A request with a token past its expiration, or of a session that ended, like after a
+    /// change of the password
+    ExpiredSession,
+
+    /// An action denied to the role of the user
+    DeniedAction,
+}
+
+/// Number of outcomes, since startup
+#[derive(Debug, Default)]
+struct Counters {
+    /// Number of issued tokens
+    tokens_issued: AtomicU64,
+
+    /// Number of failed logins
+    failed_logins: AtomicU64,
+
+    /// Number of requests without a token
+    missing_tokens: AtomicU64,
+
+    /// Number of requests with an invalid token
+    invalid_tokens: AtomicU64,
+
+    /// Number of requests with the token of a deleted user
+    deleted_user_tokens: AtomicU64,
+
+    /// Number of requests with an expired token or session
+    expired_sessions: AtomicU64,
+
+    /// Number of actions denied to the role of the user
+    denied_actions: AtomicU64,
+}
+
+/// Statistics of the outcomes, since startup
+#[derive(Debug)]
+pub struct Statistics {
+    /// Number of issued tokens
+    pub tokens_issued: u64,
+
+    /// Number of failed logins
+    pub failed_logins: u64,
+
+    /// Number of requests without a token
+    pub missing_tokens: u64,
+
+    /// Number of requests with an invalid token
+    pub invalid_tokens: u64,
+
+    /// Number of requests with the token of a deleted user
+    pub deleted_user_tokens: u64,
+
+    /// Number of requests with an expired token or session
+    pub expired_sessions: u64,
+
+    /// Number of actions denied to the role of the user
+    pub denied_actions: u64,
+}
+
+/// Counters of the outcomes of the authentication, clones share the same counters
+#[derive(Clone, Debug)]
+pub struct AuthMetrics {
+    /// The shared counters
+    counters: Arc<Counters>,
+
+    /// Deleted users and IP addresses with an attempt registered on the audit trail recently
+    deleted_user_tokens: Cache<(Uuid, Option<IpAddr>), ()>,
+}
+
+impl Default for AuthMetrics {
+    fn default() -> Self {
+        Self {
+            counters: Arc::default(),
+            deleted_user_tokens: Cache::builder()
+                .max_capacity(MAX_DELETED_USER_TOKENS)
+                .time_to_live(DELETED_USER_TOKEN_INTERVAL)
+                .build(),
+        }
+    }
+}
+
+impl AuthMetrics {
+    /// Count an outcome of the authentication
+    pub fn record(&self, outcome: Outcome) {
+        let counter = match outcome {
+            Outcome::TokenIssued => &self.counters.tokens_issued,
+            Outcome::FailedLogin => &self.counters.failed_logins,
+            Outcome::MissingToken => &self.counters.missing_tokens,
+            Outcome::InvalidToken => &self.counters.invalid_tokens,
+            Outcome::DeletedUserToken => &self.counters.deleted_user_tokens,
+            Outcome::ExpiredSession => &self.counters.expired_sessions,
+            Outcome::DeniedAction => &self.counters.denied_actions,
+        };
+
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        tracing::debug!(?outcome, "Authentication outcome");
+    }
+
+    /// Should the attempt to use the token of the deleted user from the IP address be registered
+    /// on the audit trail? Only the first attempt in a while is, a client retrying in a loop does
+    /// not flood the audit trail
+    pub async fn should_register_deleted_user_token(
+        &self,
+        user_id: Uuid,
+        ip_address: Option<IpAddr>,
+    ) -> bool {
+        self.deleted_user_tokens
+            .entry((user_id, ip_address))
+            .or_insert(())
+            .await
+            .is_fresh()
+    }
+
+    /// Statistics of the outcomes, since startup
+    pub fn statistics(&self) -> Statistics {
+        Statistics {
+            tokens_issued: self.counters.tokens_issued.load(Ordering::Relaxed),
+            failed_logins: self.counters.failed_logins.load(Ordering::Relaxed),
+            missing_tokens: self.counters.missing_tokens.load(Ordering::Relaxed),
+            invalid_tokens: self.counters.invalid_tokens.load(Ordering::Relaxed),
+            deleted_user_tokens: self.counters.deleted_user_tokens.load(Ordering::Relaxed),
+            expired_sessions: self.counters.expired_sessions.load(Ordering::Relaxed),
+            denied_actions: self.counters.denied_actions.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,500 @@
+//! Backups of all users, domains, destinations and notes, to migrate or restore an instance
+//!
+//! An archive is a versioned JSON document, exported with `shurly export backup.json` or
+//! `GET /api/backup` and restored with `shurly import backup.json --as admin` or
+//! `POST /api/backup`. Soft-deleted entries are part of the archive, their slugs stay taken.
+//!
+//! ```json
+//! {
+//!     "version": 1,
+//!     "exportedAt": "2026-10-14T16:00:00Z",
+//!     "users": [{ "id": "...", "username": "jane", "hashedPassword": "...", ... }],
+//!     "domains": [],
+//!     "destinations": [{ "id": "...", "userId": "...", "slug": "some-easy-name", ... }],
+//!     "notes": []
+//! }
+//! ```
+//!
+//! The archive contains the hashed passwords of the users, keep it as secret as the database.
+//! Sessions are not part of the archive, users login again after a restore.
+//!
+//! An archive is only imported into a database without destinations, including soft-deleted
+//! ones. Users and domains that already exist, like the initial user, are kept; the destinations
+//! and notes of an existing user are linked to it by username. Every imported entry is
+//! registered on the audit trail, as the acting user.
+
+use std::net::IpAddr;
+use std::path::Path;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use chrono::naive::NaiveDateTime;
+use chrono::DateTime;
+use chrono::Utc;
+use serde::Deserialize;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::database::AuditEntry;
+use crate::database::Database;
+use crate::destinations::Destination;
+use crate::domains::Domain;
+use crate::notes::Note;
+use crate::slug_cache::SlugFoundCache;
+use crate::users::Role;
+use crate::users::User;
+
+/// Version of the archives created by this version of Shurly
+pub const VERSION: u32 = 1;
+
+/// All users, domains, destinations and notes of an instance
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Archive {
+    /// Version of the format of the archive
+    pub version: u32,
+
+    /// Moment of the export
+    pub exported_at: DateTime<Utc>,
+
+    /// All users
+    pub users: Vec<ArchivedUser>,
+
+    /// All domains
+    pub domains: Vec<ArchivedDomain>,
+
+    /// All destinations
+    pub destinations: Vec<ArchivedDestination>,
+
+    /// All notes, of all destinations
+    pub notes: Vec<ArchivedNote>,
+}
+
+/// User of the archive
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedUser {
+    /// User ID
+    pub id: Uuid,
+
+    /// Username
+    pub username: String,
+
+    /// Hashed password, users keep their password
+    pub hashed_password: String,
+
+    /// Role of the user
+    pub role: Role,
+
+    /// Email address, not part of older archives
+    #[serde(default)]
+    pub email: Option<String>,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+
+    /// Last updated at
+    pub updated_at: NaiveDateTime,
+
+    /// Soft-deleted at
+    pub deleted_at: Option<NaiveDateTime>,
+}
+
+impl ArchivedUser {
+    /// Archive a user, without its session
+    fn from_user(user: User) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            hashed_password: user.hashed_password,
+            role: user.role,
+            email: user.email,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+            deleted_at: user.deleted_at,
+        }
+    }
+}
+
+/// Domain of the archive
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedDomain {
+    /// Domain ID
+    pub id: Uuid,
+
+    /// The ID of the user that registered it
+    pub user_id: Uuid,
+
+    /// Hostname of the domain
+    pub hostname: String,
+
+    /// Fallback URL of the domain
+    pub fallback_url: Option<String>,
+
+    /// HTML of the 404 page of the domain
+    pub not_found_template: Option<String>,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+
+    /// Last updated at
+    pub updated_at: NaiveDateTime,
+
+    /// Soft-deleted at
+    pub deleted_at: Option<NaiveDateTime>,
+}
+
+impl ArchivedDomain {
+    /// Archive a domain
+    fn from_domain(domain: Domain) -> Self {
+        Self {
+            id: domain.id,
+            user_id: domain.user_id,
+            hostname: domain.hostname,
+            fallback_url: domain.fallback_url,
+            not_found_template: domain.not_found_template,
+            created_at: domain.created_at,
+            updated_at: domain.updated_at,
+            deleted_at: domain.deleted_at,
+        }
+    }
+}
+
+/// Destination of the archive
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[allow(clippy::struct_excessive_bools)] // the flags of a destination
+pub struct ArchivedDestination {
+    /// Destination ID
+    pub id: Uuid,
+
+    /// The ID of the user that created it
+    pub user_id: Uuid,
+
+    /// Slug of the destination
+    pub slug: String,
+
+    /// Domain the slug is served on, all domains when not set
+    pub domain: Option<String>,
+
+    /// URL the destination redirects to
+    pub url: String,
+
+    /// Redirect permanently
+    pub is_permanent: bool,
+
+    /// Redirect with a meta refresh page
+    pub is_meta_refresh: bool,
+
+    /// Only redirect with a signed link
+    pub is_private: bool,
+
+    /// Serve the stats publicly, not part of older backups
+    #[serde(default)]
+    pub is_stats_public: bool,
+
+    /// Never record the hits, not part of older backups
+    #[serde(default)]
+    pub is_untracked: bool,
+
+    /// Open Graph title
+    pub og_title: Option<String>,
+
+    /// Open Graph description
+    pub og_description: Option<String>,
+
+    /// Open Graph image URL
+    pub og_image: Option<String>,
+
+    /// Script deciding the URL to redirect to
+    pub script: Option<String>,
+
+    /// Extra headers of the redirect, not part of older backups
+    #[serde(default)]
+    pub headers: Option<String>,
+
+    /// Tags of the destination, not part of older backups
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// Channel the short link is handed out in, not part of older backups
+    #[serde(default)]
+    pub source: Option<String>,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+
+    /// Last updated at
+    pub updated_at: NaiveDateTime,
+
+    /// Soft-deleted at
+    pub deleted_at: Option<NaiveDateTime>,
+}
+
+impl ArchivedDestination {
+    /// Archive a destination
+    fn from_destination(destination: Destination) -> Self {
+        Self {
+            id: destination.id,
+            user_id: destination.user_id,
+            slug: destination.slug,
+            domain: destination.domain,
+            url: destination.url,
+            is_permanent: destination.is_permanent,
+            is_meta_refresh: destination.is_meta_refresh,
+            is_private: destination.is_private,
+            is_stats_public: destination.is_stats_public,
+            is_untracked: destination.is_untracked,
+            og_title: destination.og_title,
+            og_description: destination.og_description,
+            og_image: destination.og_image,
+            script: destination.script,
+            headers: destination.headers,
+            tags: destination.tags,
+            source: destination.source,
+            created_at: destination.created_at,
+            updated_at: destination.updated_at,
+            deleted_at: destination.deleted_at,
+        }
+    }
+}
+
+/// Note of the archive
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedNote {
+    /// Note ID
+    pub id: Uuid,
+
+    /// The ID of the user that created it
+    pub user_id: Uuid,
+
+    /// Destination the note belongs to
+    pub destination_id: Uuid,
+
+    /// Content of the note
+    pub content: String,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+
+    /// Last updated at
+    pub updated_at: NaiveDateTime,
+
+    /// Soft-deleted at
+    pub deleted_at: Option<NaiveDateTime>,
+}
+
+impl ArchivedNote {
+    /// Archive a note
+    fn from_note(note: Note) -> Self {
+        Self {
+            id: note.id,
+            user_id: note.user_id,
+            destination_id: note.destination_id,
+            content: note.content,
+            created_at: note.created_at,
+            updated_at: note.updated_at,
+            deleted_at: note.deleted_at,
+        }
+    }
+}
+
+/// Entries created by an import, existing users and domains are not part of it
+#[derive(Debug, Default)]
+pub struct Restored {
+    /// Created users
+    pub users: Vec<User>,
+
+    /// Created domains
+    pub domains: Vec<Domain>,
+
+    /// Created destinations
+    pub destinations: Vec<Destination>,
+
+    /// Created notes
+    pub notes: Vec<Note>,
+}
+
+/// Number of entries created by an import
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Summary {
+    /// Number of created users
+    pub users: usize,
+
+    /// Number of created domains
+    pub domains: usize,
+
+    /// Number of created destinations
+    pub destinations: usize,
+
+    /// Number of created notes
+    pub notes: usize,
+}
+
+impl Archive {
+    /// Export all entries of the database, including the soft-deleted ones
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the entries could not be found
+    pub async fn export(database: &Database) -> Result<Self> {
+        Ok(Self {
+            version: VERSION,
+            exported_at: Utc::now(),
+            users: database
+                .find_all_users_including_deleted()
+                .await?
+                .into_iter()
+                .map(ArchivedUser::from_user)
+                .collect(),
+            domains: database
+                .find_all_domains_including_deleted()
+                .await?
+                .into_iter()
+                .map(ArchivedDomain::from_domain)
+                .collect(),
+            destinations: database
+                .find_all_destinations_including_deleted()
+                .await?
+                .into_iter()
+                .map(ArchivedDestination::from_destination)
+                .collect(),
+            notes: database
+                .find_all_notes_including_deleted()
+                .await?
+                .into_iter()
+                .map(ArchivedNote::from_note)
+                .collect(),
+        })
+    }
+
+    /// Read the archive from a JSON file
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the file can not be read or is invalid
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| anyhow!("Invalid archive: {}, {err}", path.display()))?;
+
+        serde_json::from_str(&content)
+            .map_err(|err| anyhow!("Invalid archive: {}, {err}", path.display()))
+    }
+
+    /// Check if this version of Shurly can import the archive
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the archive is of another version
+    pub fn check_version(&self) -> Result<()> {
+        if self.version == VERSION {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Invalid archive version: {}, expected {VERSION}",
+                self.version
+            ))
+        }
+    }
+
+    /// Import the archive into the database, as the user
+    ///
+    /// Returns `None` when the database is not empty, nothing is imported when it fails; the caller
+    /// checks the permissions of the user
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the archive is of another version or could not be imported
+    pub async fn import(
+        &self,
+        database: &Database,
+        imported_by: &User,
+        ip_address: Option<&IpAddr>,
+    ) -> Result<Option<Summary>> {
+        self.check_version()?;
+
+        if !database
+            .find_all_destinations_including_deleted()
+            .await?
+            .is_empty()
+        {
+            return Ok(None);
+        }
+
+        let restored = database.import_archive(self).await?;
+
+        // running instances could have cached the slugs as missing
+        SlugFoundCache::default().flush(database).await;
+
+        for user in &restored.users {
+            database
+                .register_audit_trail(imported_by, &AuditEntry::CreateUser(user), ip_address)
+                .await?;
+        }
+
+        for domain in &restored.domains {
+            database
+                .register_audit_trail(imported_by, &AuditEntry::CreateDomain(domain), ip_address)
+                .await?;
+        }
+
+        for destination in &restored.destinations {
+            database
+                .register_audit_trail(
+                    imported_by,
+                    &AuditEntry::CreateDestination(destination),
+                    ip_address,
+                )
+                .await?;
+        }
+
+        for note in &restored.notes {
+            let destination = restored
+                .destinations
+                .iter()
+                .find(|destination| destination.id == note.destination_id)
+                .ok_or_else(|| anyhow!("Note without destination: {}", note.id))?;
+
+            database
+                .register_audit_trail(
+                    imported_by,
+                    &AuditEntry::CreateNote(destination, note),
+                    ip_address,
+                )
+                .await?;
+        }
+
+        Ok(Some(Summary {
+            users: restored.users.len(),
+            domains: restored.domains.len(),
+            destinations: restored.destinations.len(),
+            notes: restored.notes.len(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_version() {
+        let mut archive: Archive = serde_json::from_str(
+            r#"{
+                "version": 1,
+                "exportedAt": "2026-10-14T16:00:00Z",
+                "users": [],
+                "domains": [],
+                "destinations": [],
+                "notes": []
+            }"#,
+        )
+        .unwrap();
+        assert!(archive.check_version().is_ok());
+
+        archive.version = 2;
+        assert!(archive.check_version().is_err());
+    }
+}
@@ -0,0 +1,222 @@
+//! Import of the links of Bitly, from its API or a CSV export
+//!
+//! Every link becomes a destination with the same slug and creation date, like `bit.ly/3xyz`
+//! becomes `3xyz`. The custom back-halves of a link in the API become destinations of their own.
+//! Links with a slug that is already taken are reported as conflicts, like the other imports.
+//!
+//! The CSV export needs a header with at least the `link` (or `bitlink`), `long_url` and
+//! `created_at` columns, other columns are ignored.
+//!
+//! ```sh
+//! shurly import-bitly --csv links.csv --as admin
+//! BITLY_ACCESS_TOKEN=tokentokentoken shurly import-bitly --as admin
+//! ```
+
+use std::io::Read;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::import::Csv;
+use crate::import::ExportedLink;
+
+/// Base URL of the API of Bitly
+const API_URL: &str = "https://api-ssl.bitly.com/v4";
+
+/// Number of links per page of the API, the maximum of Bitly
+const PAGE_SIZE: usize = 100;
+
+/// Time to wait for a page of the API
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Read the links of a CSV export of Bitly
+///
+/// # Errors
+///
+/// Will return `Err` when the CSV is invalid or misses one of the needed columns
+pub fn from_csv(reader: impl Read) -> Result<Vec<ExportedLink>> {
+    let csv = Csv::from_reader(reader)?;
+
+    let link = csv.column(&["link", "bitlink"])?;
+    let url = csv.column(&["long_url"])?;
+    let created_at = csv.column(&["created_at"])?;
+
+    let links = csv
+        .records()
+        .iter()
+        .map(|record| ExportedLink {
+            short_link: record[link].to_string(),
+            url: record[url].to_string(),
+            created_at: record[created_at].to_string(),
+            clicks: None,
+        })
+        .collect();
+
+    Ok(links)
+}
+
+/// User of the API of Bitly
+#[derive(Debug, Deserialize)]
+struct ApiUser {
+    /// Group of the links of the user
+    default_group_guid: String,
+}
+
+/// Page of links of the API of Bitly
+#[derive(Debug, Deserialize)]
+struct ApiBitlinks {
+    /// Links of the page
+    links: Vec<ApiBitlink>,
+
+    /// Where to find the next page
+    pagination: Option<ApiPagination>,
+}
+
+/// Link of the API of Bitly
+#[derive(Debug, Deserialize)]
+struct ApiBitlink {
+    /// The short link
+    link: String,
+
+    /// The URL the link redirects to
+    long_url: String,
+
+    /// Creation date of the link
+    created_at: String,
+
+    /// Custom back-halves of the link, like `https://bit.ly/launch`
+    #[serde(default)]
+    custom_bitlinks: Vec<String>,
+}
+
+/// Pagination of the API of Bitly
+#[derive(Debug, Deserialize)]
+struct ApiPagination {
+    /// URL of the next page, empty on the last page
+    #[serde(default)]
+    next: String,
+}
+
+/// Client of the API of Bitly
+pub struct BitlyApi {
+    /// The HTTP client
+    client: reqwest::Client,
+
+    /// Base URL of the API
+    base_url: String,
+
+    /// Access token of the user of Bitly
+    access_token: String,
+}
+
+impl BitlyApi {
+    /// Create a client of the API of Bitly, with the access token of a user
+    pub fn new(access_token: &str) -> Self {
+        Self::with_base_url(API_URL, access_token)
+    }
+
+    /// Create a client of an API like Bitly on another base URL
+    pub fn with_base_url(base_url: &str, access_token: &str) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(concat!("Shurly/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .expect("Valid HTTP client");
+
+        Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            access_token: access_token.to_string(),
+        }
+    }
+
+    /// Fetch all links of the group, the default group of the user when not provided
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the API can not be reached or refuses the access token
+    pub async fn links(&self, group: Option<&str>) -> Result<Vec<ExportedLink>> {
+        let group = if let Some(group) = group {
+            group.to_string()
+        } else {
+            self.get::<ApiUser>(&format!("{}/user", self.base_url))
+                .await?
+                .default_group_guid
+        };
+
+        let mut links = Vec::new();
+        let mut url = format!("{}/groups/{group}/bitlinks?size={PAGE_SIZE}", self.base_url);
+
+        loop {
+            let page = self.get::<ApiBitlinks>(&url).await?;
+
+            for bitlink in page.links {
+                for link in std::iter::once(bitlink.link).chain(bitlink.custom_bitlinks) {
+                    links.push(ExportedLink {
+                        short_link: link,
+                        url: bitlink.long_url.clone(),
+                        created_at: bitlink.created_at.clone(),
+                        clicks: None,
+                    });
+                }
+            }
+
+            match page.pagination.map(|pagination| pagination.next) {
+                // the access token is only sent to the API itself
+                Some(next) if next.starts_with(&self.base_url) => url = next,
+                _ => break,
+            }
+        }
+
+        Ok(links)
+    }
+
+    /// Get a response of the API
+    async fn get<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await
+            .map_err(|err| anyhow!("Could not reach Bitly: {err}"))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(anyhow!("Bitly responded with {status}: {url}"));
+        }
+
+        let body = response
+            .text()
+            .await
+            .map_err(|err| anyhow!("Could not reach Bitly: {err}"))?;
+
+        serde_json::from_str(&body).map_err(|err| anyhow!("Invalid response of Bitly: {err}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_csv() {
+        let csv = "Title,Bitlink,Long URL,Created At\n\
+            Launch,https://bit.ly/launch,https://www.example.com/launch,2026-10-14\n";
+
+        assert_eq!(
+            vec![ExportedLink {
+                short_link: "https://bit.ly/launch".into(),
+                url: "https://www.example.com/launch".into(),
+                created_at: "2026-10-14".into(),
+                clicks: None,
+            }],
+            from_csv(csv.as_bytes()).unwrap()
+        );
+
+        assert!(from_csv("link,created_at\n".as_bytes()).is_err());
+    }
+}
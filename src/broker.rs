@@ -0,0 +1,489 @@
+//! Publishing of the events to a message broker, for consumers that want more than webhooks
+//!
+//! Disabled by default, enabled with the `EVENT_BROKER_URL` environment variable, either
+//! `nats://` or `kafka://` followed by a comma separated list of servers. Support for the brokers
+//! is compiled in with the `nats` and `kafka` features.
+//!
+//! The changes of the audit trail are published to `<prefix>.audit`, the hits of the root to
+//! `<prefix>.hits`, the prefix defaults to `shurly`. NATS gets the type of the event appended to
+//! the subject, like `shurly.audit.create-destination`; Kafka gets it as the key of the record,
+//! on the first partition of the topic.
+//!
+//! Publishing never holds up a request, the events are queued in memory and published in the
+//! background. The events are dropped when the queue is full, like when the broker is
+//! unreachable for too long.
+
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use axum::async_trait;
+use chrono::NaiveDateTime;
+use chrono::SubsecRound;
+use chrono::Utc;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::destinations::Destination;
+use crate::utils::env_var_optional;
+use crate::webhooks::Event;
+
+/// Default prefix of the subjects or topics
+const DEFAULT_PREFIX: &str = "shurly";
+
+/// Number of events waiting to be published, before new events are dropped
+const QUEUE_SIZE: usize = 10_000;
+
+/// Time between the attempts to connect to the broker
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Message broker to publish to
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// NATS servers, like `localhost:4222`
+    Nats(Vec<String>),
+
+    /// Kafka brokers, like `localhost:9092`
+    Kafka(Vec<String>),
+}
+
+impl Target {
+    /// Parse the target from a URL, like `nats://localhost:4222`
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the URL is not a `nats://` or `kafka://` URL with servers, or when
+    /// the support of the broker is not compiled in
+    pub fn parse(url: &str) -> anyhow::Result<Self> {
+        let invalid =
+            || anyhow!("Invalid EVENT_BROKER_URL: {url}, expected `nats://` or `kafka://` servers");
+
+        let (scheme, servers) = url.split_once("://").ok_or_else(invalid)?;
+
+        let servers = servers
+            .split(',')
+            .map(str::trim)
+            .filter(|server| !server.is_empty())
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        if servers.is_empty() {
+            return Err(invalid());
+        }
+
+        let (target, feature) = match scheme {
+            "nats" => (Self::Nats(servers), "nats"),
+            "kafka" => (Self::Kafka(servers), "kafka"),
+            _ => return Err(invalid()),
+        };
+
+        if !target.is_supported() {
+            return Err(anyhow!(
+                "Invalid EVENT_BROKER_URL: {url}, built without the `{feature}` feature"
+            ));
+        }
+
+        Ok(target)
+    }
+
+    /// Is the support of the broker compiled in?
+    fn is_supported(&self) -> bool {
+        match self {
+            Self::Nats(_) => cfg!(feature = "nats"),
+            Self::Kafka(_) => cfg!(feature = "kafka"),
+        }
+    }
+}
+
+/// Stream of events
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stream {
+    /// Changes on the audit trail
+    Audit,
+
+    /// Hits of the root
+    Hits,
+}
+
+impl Stream {
+    /// Name of the stream, appended to the prefix
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Audit => "audit",
+            Self::Hits => "hits",
+        }
+    }
+}
+
+/// An event waiting to be published
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    /// Stream of the event
+    pub stream: Stream,
+
+    /// Type of the event, like `create-destination`
+    pub event: String,
+
+    /// The JSON body of the event
+    pub payload: String,
+}
+
+/// Hit of the root, as published
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HitEvent<'a> {
+    /// Type of the event, always `hit`
+    event: &'static str,
+
+    /// Destination that is hit
+    destination_id: Uuid,
+
+    /// Slug of the destination
+    slug: &'a str,
+
+    /// Domain of the destination, `None` for all domains
+    domain: Option<&'a str>,
+
+    /// IP address of the visitor, when known and tracked
+    ip_address: Option<&'a IpAddr>,
+
+    /// User agent of the visitor, when known and tracked
+    user_agent: Option<&'a str>,
+
+    /// Moment of the hit
+    created_at: NaiveDateTime,
+}
+
+/// Publisher of the events, doing nothing when disabled
+///
+/// Disabled by default, clones share the same queue
+#[derive(Clone, Debug, Default)]
+pub struct Broker {
+    /// Broker to publish to, `None` when publishing is disabled
+    target: Option<Target>,
+
+    /// Prefix of the subjects or topics
+    prefix: String,
+
+    /// Queue of the events
+    sender: Option<mpsc::Sender<Message>>,
+
+    /// Receiving end of the queue, until the publishing is started
+    receiver: Arc<Mutex<Option<mpsc::Receiver<Message>>>>,
+}
+
+impl Broker {
+    /// Create a publisher, publishing to the target when provided
+    pub fn new(target: Option<Target>, prefix: &str) -> Self {
+        let Some(target) = target else {
+            return Self::default();
+        };
+
+        let (sender, receiver) = mpsc::channel(QUEUE_SIZE);
+
+        Self {
+            target: Some(target),
+            prefix: prefix.to_string(),
+            sender: Some(sender),
+            receiver: Arc::new(Mutex::new(Some(receiver))),
+        }
+    }
+
+    /// Setup the publisher based on the `EVENT_BROKER_URL` and `EVENT_BROKER_PREFIX` environment
+    /// variables
+    ///
+    /// Publishing is disabled without an `EVENT_BROKER_URL`, the prefix defaults to `shurly`
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the URL is invalid, or the broker is not supported by this build
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let target = env_var_optional("EVENT_BROKER_URL")
+            .map(|url| Target::parse(&url))
+            .transpose()?;
+
+        let prefix = env_var_optional("EVENT_BROKER_PREFIX");
+
+        Ok(Self::new(
+            target,
+            prefix.as_deref().unwrap_or(DEFAULT_PREFIX),
+        ))
+    }
+
+    /// Publish a change of the audit trail
+    pub fn publish_audit(&self, event: &Event<'_>) {
+        if self.sender.is_none() {
+            return;
+        }
+
+        self.queue(Message {
+            stream: Stream::Audit,
+            event: event.event().to_string(),
+            payload: event.payload(),
+        });
+    }
+
+    /// Publish a hit on the destination, without the details the visitor does not want tracked
+    pub fn publish_hit(
+        &self,
+        destination: &Destination,
+        ip_address: Option<&IpAddr>,
+        user_agent: Option<&str>,
+    ) {
+        if self.sender.is_none() {
+            return;
+        }
+
+        let hit = HitEvent {
+            event: "hit",
+            destination_id: destination.id,
+            slug: &destination.slug,
+            domain: destination.domain.as_deref(),
+            ip_address,
+            user_agent,
+            // the database stores up to microseconds, like the saved hit
+            created_at: Utc::now().naive_utc().trunc_subsecs(6),
+        };
+
+        self.queue(Message {
+            stream: Stream::Hits,
+            event: hit.event.to_string(),
+            payload: serde_json::to_string(&hit).expect("Valid hit"),
+        });
+    }
+
+    /// Queue the message, dropping it when the queue is full
+    fn queue(&self, message: Message) {
+        let Some(ref sender) = self.sender else {
+            return;
+        };
+
+        if let Err(err) = sender.try_send(message) {
+            tracing::warn!("Could not queue event for the broker: {err}");
+        }
+    }
+
+    /// Take the queued messages, for the tests that have no broker
+    #[cfg(test)]
+    pub async fn take_queued(&self) -> Vec<Message> {
+        let mut messages = Vec::new();
+
+        if let Some(ref mut receiver) = *self.receiver.lock().await {
+            while let Ok(message) = receiver.try_recv() {
+                messages.push(message);
+            }
+        }
+
+        messages
+    }
+
+    /// Publish the queued events in the background, until Shurly stops
+    pub fn start(&self) {
+        let Some(ref target) = self.target else {
+            return;
+        };
+
+        let target = target.clone();
+        let prefix = self.prefix.clone();
+        let receiver = Arc::clone(&self.receiver);
+
+        tokio::spawn(async move {
+            let Some(mut receiver) = receiver.lock().await.take() else {
+                return;
+            };
+
+            let mut publisher = loop {
+                match connect(&target).await {
+                    Ok(publisher) => break publisher,
+                    Err(err) => {
+                        tracing::error!("Could not connect to the event broker: {err}");
+                        tokio::time::sleep(RECONNECT_INTERVAL).await;
+                    }
+                }
+            };
+
+            tracing::info!("Publishing events to {target:?}");
+
+            while let Some(message) = receiver.recv().await {
+                let topic = format!("{prefix}.{}", message.stream.name());
+
+                if let Err(err) = publisher.publish(&topic, &message).await {
+                    tracing::error!("Could not publish {} event: {err}", message.event);
+                }
+            }
+        });
+    }
+}
+
+/// Connection to a message broker
+#[async_trait]
+trait Publisher: Send {
+    /// Publish the message on the topic
+    async fn publish(&mut self, topic: &str, message: &Message) -> anyhow::Result<()>;
+}
+
+/// Connect to the broker, when its support is compiled in
+#[allow(clippy::unused_async)] // nothing to await without the `nats` and `kafka` features
+async fn connect(target: &Target) -> anyhow::Result<Box<dyn Publisher>> {
+    match target {
+        #[cfg(feature = "nats")]
+        Target::Nats(servers) => nats::connect(servers).await,
+
+        #[cfg(feature = "kafka")]
+        Target::Kafka(brokers) => kafka::connect(brokers).await,
+
+        // only reachable when a feature is missing, which is refused by the parsing already
+        #[allow(unreachable_patterns)]
+        _ => Err(anyhow!("Unsupported event broker: {target:?}")),
+    }
+}
+
+/// Publishing to NATS
+#[cfg(feature = "nats")]
+mod nats {
+    use axum::async_trait;
+
+    use super::Message;
+    use super::Publisher;
+
+    /// Connection to NATS, reconnecting by itself
+    struct Nats {
+        /// The NATS client
+        client: async_nats::Client,
+    }
+
+    #[async_trait]
+    impl Publisher for Nats {
+        async fn publish(&mut self, topic: &str, message: &Message) -> anyhow::Result<()> {
+            self.client
+                .publish(
+                    format!("{topic}.{}", message.event),
+                    message.payload.clone().into(),
+                )
+                .await?;
+
+            Ok(())
+        }
+    }
+
+    /// Connect to the NATS servers
+    pub async fn connect(servers: &[String]) -> anyhow::Result<Box<dyn Publisher>> {
+        let addresses = servers
+            .iter()
+            .map(|server| server.parse::<async_nats::ServerAddr>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let client = async_nats::connect(addresses).await?;
+
+        Ok(Box::new(Nats { client }))
+    }
+}
+
+/// Publishing to Kafka
+#[cfg(feature = "kafka")]
+mod kafka {
+    use std::collections::BTreeMap;
+    use std::collections::HashMap;
+
+    use axum::async_trait;
+    use chrono::Utc;
+    use rskafka::client::partition::Compression;
+    use rskafka::client::partition::PartitionClient;
+    use rskafka::client::partition::UnknownTopicHandling;
+    use rskafka::client::Client;
+    use rskafka::client::ClientBuilder;
+    use rskafka::record::Record;
+
+    use super::Message;
+    use super::Publisher;
+
+    /// Connection to Kafka, with a client per topic
+    struct Kafka {
+        /// The Kafka client
+        client: Client,
+
+        /// Clients of the first partition of the topics
+        partitions: HashMap<String, PartitionClient>,
+    }
+
+    #[async_trait]
+    impl Publisher for Kafka {
+        async fn publish(&mut self, topic: &str, message: &Message) -> anyhow::Result<()> {
+            if !self.partitions.contains_key(topic) {
+                let partition = self
+                    .client
+                    .partition_client(topic, 0, UnknownTopicHandling::Retry)
+                    .await?;
+
+                self.partitions.insert(topic.to_string(), partition);
+            }
+
+            let record = Record {
+                key: Some(message.event.clone().into_bytes()),
+                value: Some(message.payload.clone().into_bytes()),
+                headers: BTreeMap::new(),
+                timestamp: Utc::now(),
+            };
+
+            self.partitions[topic]
+                .produce(vec![record], Compression::NoCompression)
+                .await?;
+
+            Ok(())
+        }
+    }
+
+    /// Connect to the Kafka brokers
+    pub async fn connect(brokers: &[String]) -> anyhow::Result<Box<dyn Publisher>> {
+        let client = ClientBuilder::new(brokers.to_vec()).build().await?;
+
+        Ok(Box::new(Kafka {
+            client,
+            partitions: HashMap::new(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target() {
+        assert!(Target::parse("localhost:4222").is_err());
+        assert!(Target::parse("amqp://localhost:5672").is_err());
+        assert!(Target::parse("nats://").is_err());
+
+        let nats = Target::parse("nats://localhost:4222, other:4222");
+        if cfg!(feature = "nats") {
+            assert_eq!(
+                Target::Nats(vec!["localhost:4222".into(), "other:4222".into()]),
+                nats.unwrap()
+            );
+        } else {
+            assert!(nats.unwrap_err().to_string().contains("`nats` feature"));
+        }
+
+        let kafka = Target::parse("kafka://localhost:9092");
+        if cfg!(feature = "kafka") {
+            assert_eq!(Target::Kafka(vec!["localhost:9092".into()]), kafka.unwrap());
+        } else {
+            assert!(kafka.unwrap_err().to_string().contains("`kafka` feature"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disabled() {
+        let broker = Broker::default();
+        broker.queue(Message {
+            stream: Stream::Hits,
+            event: "hit".into(),
+            payload: "{}".into(),
+        });
+
+        assert!(broker.take_queued().await.is_empty());
+    }
+}
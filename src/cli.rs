@@ -0,0 +1,702 @@
+//! Command line interface
+//!
+//! Without a command Shurly is served, the other commands manage an instance from the shell
+//! without going through the API. Like the API, every change is registered on the audit trail,
+//! as the user given with `--as`. Destinations are created with the same checks as the API, like
+//! the approval and the quotas.
+//!
+//! Migrations run on startup of `serve`, which can be skipped with `--skip-migrations` when the
+//! migrations are run once per deployment with `migrate` instead.
+//!
+//! ```sh
+//! shurly migrate --dry-run
+//! shurly serve --skip-migrations
+//! shurly user create jane --role admin --as admin
+//! shurly destination create some-easy-name https://www.example.com/ --as jane
+//! shurly destination list
+//! shurly seed seed.json --as admin
+//! shurly export backup.json
+//! shurly export-edge snapshot.json
+//! shurly import backup.json --as admin
+//! shurly import-bitly --csv links.csv --as admin
+//! shurly import-yourls yourls.sql --as admin
+//! shurly import-shlink shlink.sql --as admin
+//! ```
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use axum::http::HeaderMap;
+use chrono::DateTime;
+use chrono::Utc;
+use clap::Parser;
+use clap::Subcommand;
+use uuid::Uuid;
+
+use crate::api::create_new_destination;
+use crate::api::parse_domain;
+use crate::api::AuditTrail;
+use crate::api::CreateDestinationForm;
+use crate::api::CurrentUser;
+use crate::api::NewDestination;
+use crate::approval::ApprovalStatus;
+use crate::backup::Archive;
+use crate::bitly;
+use crate::bitly::BitlyApi;
+use crate::database::AuditEntry;
+use crate::database::CreateUserValues;
+use crate::database::Database;
+use crate::edge::Snapshot;
+use crate::import;
+use crate::import::ExportedLink;
+use crate::password::generate;
+use crate::password::hash;
+use crate::permissions::Action;
+use crate::permissions::Permissions;
+use crate::purge::parse_retention_days;
+use crate::seed::Seed;
+use crate::shlink;
+use crate::users::Role;
+use crate::users::User;
+use crate::utils::env_var_optional;
+use crate::yourls;
+use crate::RootSettings;
+
+/// Shurly, this is a URL shortener with API management
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// What to do, serving Shurly when not provided
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Commands of Shurly
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Serve Shurly, the default
+    Serve {
+        /// Serve without running the pending migrations first
+        #[arg(long)]
+        skip_migrations: bool,
+    },
+
+    /// Run the pending migrations and exit
+    Migrate {
+        /// Only show the pending migrations, without running them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Manage the instance from the shell
+    #[command(flatten)]
+    Manage(ManageCommand),
+}
+
+/// Commands managing the instance
+#[derive(Debug, Subcommand)]
+pub enum ManageCommand {
+    /// Manage users
+    #[command(subcommand)]
+    User(UserCommand),
+
+    /// Manage destinations
+    #[command(subcommand)]
+    Destination(DestinationCommand),
+
+    /// Load users, destinations and notes from a JSON file into an empty database
+    Seed {
+        /// Path to the JSON file with the seed
+        path: PathBuf,
+
+        /// Username of the admin loading the seed
+        #[arg(long = "as", value_name = "USERNAME")]
+        created_by: String,
+    },
+
+    /// Export all users, domains, destinations and notes to a JSON archive
+    Export {
+        /// Path to write the archive to, standard output when not provided
+        path: Option<PathBuf>,
+    },
+
+    /// Export a snapshot of the slugs for edge workers, as compact JSON
+    ExportEdge {
+        /// Path to write the snapshot to, standard output when not provided
+        path: Option<PathBuf>,
+
+        /// Only the changes since the version of a previous snapshot
+        #[arg(long)]
+        since: Option<DateTime<Utc>>,
+    },
+
+    /// Import a JSON archive of `export` into a database without destinations
+    Import {
+        /// Path to the archive
+        path: PathBuf,
+
+        /// Username of the admin importing the archive
+        #[arg(long = "as", value_name = "USERNAME")]
+        imported_by: String,
+    },
+
+    /// Import the links of Bitly as destinations, with their slugs and creation dates
+    ///
+    /// The links are pulled from the API of Bitly with the `BITLY_ACCESS_TOKEN`, unless a CSV
+    /// export is provided
+    ImportBitly {
+        /// Path to a CSV export of Bitly
+        #[arg(long)]
+        csv: Option<PathBuf>,
+
+        /// Group of the links in the API, the default group of the user when not provided
+        #[arg(long, conflicts_with = "csv")]
+        group: Option<String>,
+
+        /// Domain the slugs are served on, all domains when not provided
+        #[arg(long)]
+        domain: Option<String>,
+
+        /// Username of the admin importing the links
+        #[arg(long = "as", value_name = "USERNAME")]
+        imported_by: String,
+    },
+
+    /// Import the links of YOURLS as destinations, with their slugs, creation dates and clicks
+    ImportYourls {
+        /// Path to a SQL dump (`.sql`) or CSV export of YOURLS
+        path: PathBuf,
+
+        /// Domain the slugs are served on, all domains when not provided
+        #[arg(long)]
+        domain: Option<String>,
+
+        /// Username of the admin importing the links
+        #[arg(long = "as", value_name = "USERNAME")]
+        imported_by: String,
+    },
+
+    /// Import the links of Shlink as destinations, with their slugs, creation dates and visits
+    ImportShlink {
+        /// Path to a SQL dump (`.sql`) or CSV export of Shlink
+        path: PathBuf,
+
+        /// Domain the slugs are served on, all domains when not provided
+        #[arg(long)]
+        domain: Option<String>,
+
+        /// Username of the admin importing the links
+        #[arg(long = "as", value_name = "USERNAME")]
+        imported_by: String,
+    },
+}
+
+/// Commands managing users
+#[derive(Debug, Subcommand)]
+pub enum UserCommand {
+    /// Create a user, a password is generated and shown when not provided
+    Create {
+        /// Username of the new user
+        username: String,
+
+        /// Role of the new user
+        #[arg(long, value_enum, default_value_t = Role::Manager)]
+        role: Role,
+
+        /// Password of the new user
+        #[arg(long)]
+        password: Option<String>,
+
+        /// Username of the admin creating the user
+        #[arg(long = "as", value_name = "USERNAME")]
+        created_by: String,
+    },
+}
+
+/// Commands managing destinations
+#[derive(Debug, Subcommand)]
+pub enum DestinationCommand {
+    /// Create a destination
+    Create {
+        /// Slug of the new destination
+        slug: String,
+
+        /// URL the new destination redirects to
+        url: String,
+
+        /// Domain the slug is served on, all domains when not provided
+        #[arg(long)]
+        domain: Option<String>,
+
+        /// Redirect permanently, this can not be undone
+        #[arg(long)]
+        permanent: bool,
+
+        /// Redirect with a meta refresh page
+        #[arg(long)]
+        meta_refresh: bool,
+
+        /// Only redirect with a signed link
+        #[arg(long)]
+        private: bool,
+
+        /// Username of the user creating the destination
+        #[arg(long = "as", value_name = "USERNAME")]
+        created_by: String,
+    },
+
+    /// List all destinations, tab separated: ID, domain (`*` for all domains), slug and URL
+    List,
+}
+
+/// Run the pending migrations, or only show them with a dry run
+///
+/// # Errors
+///
+/// Will return `Err` when the migrations could not be found or run
+pub async fn migrate(database: &Database, dry_run: bool) -> Result<()> {
+    let pending_migrations = database.find_pending_migrations().await?;
+
+    if pending_migrations.is_empty() {
+        println!("No pending migrations");
+
+        return Ok(());
+    }
+
+    for (version, description) in &pending_migrations {
+        println!("{version}\t{description}");
+    }
+
+    if dry_run {
+        println!("{} pending migration(s)", pending_migrations.len());
+    } else {
+        database.migrate().await?;
+
+        println!("Ran {} migration(s)", pending_migrations.len());
+    }
+
+    Ok(())
+}
+
+/// Run a command managing the instance
+///
+/// # Errors
+///
+/// Will return `Err` when the command fails, like an invalid slug or an unknown user
+pub async fn run(
+    database: &Database,
+    root_settings: &RootSettings,
+    command: ManageCommand,
+) -> Result<()> {
+    match command {
+        ManageCommand::User(UserCommand::Create {
+            username,
+            role,
+            password,
+            created_by,
+        }) => {
+            let created_by = find_acting_user(database, &created_by, Action::ManageUsers).await?;
+
+            if !Permissions::from_environment()?.includes(created_by.role, role) {
+                return Err(anyhow!("Not allowed to manage this role"));
+            }
+
+            create_user(database, &created_by, &username, role, password).await
+        }
+        ManageCommand::Destination(DestinationCommand::Create {
+            slug,
+            url,
+            domain,
+            permanent,
+            meta_refresh,
+            private,
+            created_by,
+        }) => {
+            let created_by =
+                find_acting_user(database, &created_by, Action::CreateDestinations).await?;
+
+            let form = CreateDestinationForm::new(slug, url)
+                .with_domain(domain)
+                .with_flags(permanent, meta_refresh, private);
+
+            create_destination(database, root_settings, created_by, form).await
+        }
+        ManageCommand::Destination(DestinationCommand::List) => {
+            for destination in database.find_all_destinations().await? {
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    destination.id,
+                    destination.domain.as_deref().unwrap_or("*"),
+                    destination.slug,
+                    destination.url,
+                );
+            }
+
+            Ok(())
+        }
+        ManageCommand::Seed { path, created_by } => seed(database, &created_by, &path).await,
+        ManageCommand::Export { path } => export_archive(database, path.as_deref()).await,
+        ManageCommand::ExportEdge { path, since } => {
+            export_edge(database, path.as_deref(), since).await
+        }
+        ManageCommand::Import { path, imported_by } => {
+            import_archive(database, &imported_by, &path).await
+        }
+        ManageCommand::ImportBitly {
+            csv,
+            group,
+            domain,
+            imported_by,
+        } => import_bitly(database, &imported_by, csv, group, domain).await,
+        ManageCommand::ImportYourls {
+            path,
+            domain,
+            imported_by,
+        } => {
+            let links = read_export(&path, yourls::from_sql, yourls::from_csv)?;
+
+            import_links(database, &imported_by, &links, domain).await
+        }
+        ManageCommand::ImportShlink {
+            path,
+            domain,
+            imported_by,
+        } => {
+            let links = read_export(&path, shlink::from_sql, shlink::from_csv)?;
+
+            import_links(database, &imported_by, &links, domain).await
+        }
+    }
+}
+
+/// Find the user performing the command, allowed the action
+async fn find_acting_user(database: &Database, username: &str, action: Action) -> Result<User> {
+    let user = database
+        .find_single_user_by_username(username)
+        .await?
+        .filter(|user| !user.is_deleted())
+        .ok_or_else(|| anyhow!("User not found: {username}"))?;
+
+    Permissions::from_environment()?.check(user.role, action)?;
+
+    Ok(user)
+}
+
+/// Create a user, generating a password when not provided
+async fn create_user(
+    database: &Database,
+    created_by: &User,
+    username: &str,
+    role: Role,
+    password: Option<String>,
+) -> Result<()> {
+    if let Some(user) = database.find_single_user_by_username(username).await? {
+        if user.is_deleted() {
+            return Err(anyhow!("User already exists and is deleted"));
+        }
+
+        return Err(anyhow!("User already exists"));
+    }
+
+    let (is_generated, password) = if let Some(password) = password {
+        (false, password)
+    } else {
+        (true, generate())
+    };
+
+    let values = CreateUserValues {
+        session_id: &Uuid::new_v4(),
+        role,
+        username,
+        hashed_password: &hash(&password),
+        email: None,
+    };
+
+    let user = database.create_user(&values).await?;
+
+    database
+        .register_audit_trail(created_by, &AuditEntry::CreateUser(&user), None)
+        .await?;
+
+    println!("Created user {} ({})", user.username, user.id);
+
+    // only show the generated password, its the only time the password is known to anybody
+    if is_generated {
+        println!("Password: {password}");
+    }
+
+    Ok(())
+}
+
+/// Load a seed into an empty database
+async fn seed(database: &Database, created_by: &str, path: &Path) -> Result<()> {
+    let created_by = find_acting_user(database, created_by, Action::ManageSystem).await?;
+
+    let seed = Seed::from_file(path)?;
+
+    let summary = seed
+        .load(database, &created_by)
+        .await?
+        .ok_or_else(|| anyhow!("Database is not empty"))?;
+
+    println!(
+        "Seeded {} users, {} destinations and {} notes",
+        summary.users, summary.destinations, summary.notes
+    );
+
+    Ok(())
+}
+
+/// Export all entries to an archive
+async fn export_archive(database: &Database, path: Option<&Path>) -> Result<()> {
+    let archive = serde_json::to_string_pretty(&Archive::export(database).await?)?;
+
+    if let Some(path) = path {
+        std::fs::write(path, archive)
+            .map_err(|err| anyhow!("Could not write archive: {}, {err}", path.display()))?;
+    } else {
+        println!("{archive}");
+    }
+
+    Ok(())
+}
+
+/// Export a snapshot of the slugs for edge workers, or the changes since a previous snapshot
+async fn export_edge(
+    database: &Database,
+    path: Option<&Path>,
+    since: Option<DateTime<Utc>>,
+) -> Result<()> {
+    let deleted_retention_days =
+        parse_retention_days(env_var_optional("DELETED_RETENTION_DAYS").as_deref())?;
+    let snapshot =
+        serde_json::to_string(&Snapshot::create(database, since, deleted_retention_days).await?)?;
+
+    if let Some(path) = path {
+        std::fs::write(path, snapshot)
+            .map_err(|err| anyhow!("Could not write snapshot: {}, {err}", path.display()))?;
+    } else {
+        println!("{snapshot}");
+    }
+
+    Ok(())
+}
+
+/// Import an archive of `export` into a database without destinations
+async fn import_archive(database: &Database, imported_by: &str, path: &Path) -> Result<()> {
+    let imported_by = find_acting_user(database, imported_by, Action::ManageSystem).await?;
+
+    let archive = Archive::from_file(path)?;
+
+    let summary = archive
+        .import(database, &imported_by, None)
+        .await?
+        .ok_or_else(|| anyhow!("Database is not empty"))?;
+
+    println!(
+        "Imported {} users, {} domains, {} destinations and {} notes",
+        summary.users, summary.domains, summary.destinations, summary.notes
+    );
+
+    Ok(())
+}
+
+/// Import the links of Bitly, from the CSV export or the API, and report the conflicts
+async fn import_bitly(
+    database: &Database,
+    imported_by: &str,
+    csv: Option<PathBuf>,
+    group: Option<String>,
+    domain: Option<String>,
+) -> Result<()> {
+    let links = if let Some(path) = csv {
+        let file = std::fs::File::open(&path)
+            .map_err(|err| anyhow!("Invalid CSV: {}, {err}", path.display()))?;
+
+        bitly::from_csv(file)?
+    } else {
+        let access_token = env_var_optional("BITLY_ACCESS_TOKEN")
+            .ok_or_else(|| anyhow!("Missing BITLY_ACCESS_TOKEN, or a CSV export with --csv"))?;
+        let api_url = env_var_optional("BITLY_API_URL");
+
+        let api = api_url.map_or_else(
+            || BitlyApi::new(&access_token),
+            |api_url| BitlyApi::with_base_url(&api_url, &access_token),
+        );
+
+        api.links(group.as_deref()).await?
+    };
+
+    import_links(database, imported_by, &links, domain).await
+}
+
+/// Read the links of an export, a SQL dump for `.sql` files and a CSV export otherwise
+fn read_export(
+    path: &Path,
+    from_sql: fn(&str) -> Result<Vec<ExportedLink>>,
+    from_csv: fn(std::fs::File) -> Result<Vec<ExportedLink>>,
+) -> Result<Vec<ExportedLink>> {
+    if path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("sql"))
+    {
+        let sql = std::fs::read_to_string(path)
+            .map_err(|err| anyhow!("Invalid SQL: {}, {err}", path.display()))?;
+
+        from_sql(&sql)
+    } else {
+        let file = std::fs::File::open(path)
+            .map_err(|err| anyhow!("Invalid CSV: {}, {err}", path.display()))?;
+
+        from_csv(file)
+    }
+}
+
+/// Import the links on the domain as the admin, and report the conflicts
+async fn import_links(
+    database: &Database,
+    imported_by: &str,
+    links: &[ExportedLink],
+    domain: Option<String>,
+) -> Result<()> {
+    let imported_by = find_acting_user(database, imported_by, Action::ManageSystem).await?;
+    let domain = parse_domain(domain.as_deref())?;
+
+    let report = import::import(database, links, &imported_by, domain.as_deref()).await?;
+
+    for conflict in &report.conflicts {
+        println!("Conflict {}: {}", conflict.short_link, conflict.reason);
+    }
+
+    println!(
+        "Imported {} destinations, {} conflicts",
+        report.destinations.len(),
+        report.conflicts.len()
+    );
+
+    Ok(())
+}
+
+/// Create a destination like `POST /api/destinations`, with the same checks and approval
+async fn create_destination(
+    database: &Database,
+    root_settings: &RootSettings,
+    created_by: User,
+    form: CreateDestinationForm,
+) -> Result<()> {
+    let current_user = CurrentUser::new(
+        created_by,
+        Arc::clone(&root_settings.permissions),
+        root_settings.auth_metrics.clone(),
+    );
+    let audit_trail = AuditTrail::new(database.clone(), current_user.clone(), None, root_settings);
+
+    // without a request, only the configured hostnames are followed by the loop detection
+    let new_destination =
+        NewDestination::from_form(database, root_settings, &HeaderMap::new(), form).await?;
+
+    let (destination, _) = create_new_destination(
+        &audit_trail,
+        database,
+        root_settings,
+        &current_user,
+        &new_destination,
+    )
+    .await?;
+
+    if destination.approval_status() == ApprovalStatus::Pending {
+        println!(
+            "Created destination {} ({}), awaiting approval",
+            destination.slug, destination.id
+        );
+    } else {
+        println!(
+            "Created destination {} ({})",
+            destination.slug, destination.id
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn test_cli() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from(["shurly"]).unwrap();
+        assert!(cli.command.is_none());
+
+        let cli = Cli::try_parse_from(["shurly", "serve"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Serve {
+                skip_migrations: false
+            })
+        ));
+
+        let cli = Cli::try_parse_from(["shurly", "serve", "--skip-migrations"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Serve {
+                skip_migrations: true
+            })
+        ));
+
+        let cli = Cli::try_parse_from(["shurly", "migrate", "--dry-run"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Migrate { dry_run: true })
+        ));
+
+        let cli = Cli::try_parse_from([
+            "shurly",
+            "destination",
+            "create",
+            "some-slug",
+            "https://www.example.com/",
+            "--permanent",
+            "--as",
+            "admin",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Manage(ManageCommand::Destination(
+                DestinationCommand::Create {
+                    permanent: true,
+                    private: false,
+                    ..
+                }
+            )))
+        ));
+
+        let cli = Cli::try_parse_from(["shurly", "export"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Manage(ManageCommand::Export { path: None }))
+        ));
+
+        let cli = Cli::try_parse_from(["shurly", "export-edge", "--since", "2026-10-15T12:00:00Z"])
+            .unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Manage(ManageCommand::ExportEdge {
+                path: None,
+                since: Some(_)
+            }))
+        ));
+
+        // the acting user is required
+        assert!(Cli::try_parse_from(["shurly", "user", "create", "jane"]).is_err());
+        assert!(Cli::try_parse_from(["shurly", "import", "backup.json"]).is_err());
+    }
+}
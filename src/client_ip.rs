@@ -0,0 +1,556 @@
+//! IP address of the client, for the hits, the audit trail and the rate limit
+//!
+//! By default any header that could hold the IP address is used, or the address of the
+//! connection. Any client can send these headers, behind a load balancer the source and the
+//! trusted proxies should be configured:
+//!
+//! - `x-forwarded-for`, the rightmost address of `X-Forwarded-For` that is not a trusted proxy
+//! - `cf-connecting-ip`, the `CF-Connecting-IP` header of Cloudflare
+//! - `connect-info`, the address of the connection, without a proxy in front of Shurly
+//!
+//! The headers are only used when the connection comes from a trusted proxy. Without trusted
+//! proxies, every connection is assumed to come from the proxy in front of Shurly. Connections
+//! over a Unix domain socket come from a proxy on the same machine, and are always trusted.
+//!
+//! A single visitor rotates through the IPv6 addresses of its network, the hits and the rate
+//! limit only keep the prefix of an IPv6 address (`IPV6_VISITOR_PREFIX`, `/64` by default).
+
+use std::fmt;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+
+use anyhow::anyhow;
+use axum::async_trait;
+use axum::extract::ConnectInfo;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::http::Extensions;
+use axum::http::HeaderMap;
+use axum::http::StatusCode;
+use axum_client_ip::InsecureClientIp;
+
+use crate::root::Settings as RootSettings;
+use crate::utils::env_var_optional;
+
+/// Header with the addresses of the client and the proxies, comma separated
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Header with the address of the client, set by Cloudflare
+const CF_CONNECTING_IP: &str = "cf-connecting-ip";
+
+/// Prefix of IPv6 addresses of a single visitor, the usual size of a subnet
+const DEFAULT_IPV6_VISITOR_PREFIX: u8 = 64;
+
+/// IP address of the client, based on the [`ClientIpSettings`](ClientIpSettings)
+#[derive(Clone, Copy, Debug)]
+pub struct ClientIp(pub IpAddr);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let settings = parts
+            .extensions
+            .get::<RootSettings>()
+            .map(|settings| settings.client_ip.clone())
+            .unwrap_or_default();
+
+        settings
+            .find(&parts.headers, &parts.extensions)
+            .map(Self)
+            .ok_or((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Could not find client IP",
+            ))
+    }
+}
+
+/// Where to find the IP address of the client
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Source {
+    /// Any header that could hold the address, or the address of the connection
+    #[default]
+    Any,
+
+    /// The rightmost address of `X-Forwarded-For` that is not a trusted proxy
+    XForwardedFor,
+
+    /// The `CF-Connecting-IP` header of Cloudflare
+    CfConnectingIp,
+
+    /// The address of the connection
+    ConnectInfo,
+}
+
+/// Range of IP addresses, like `10.0.0.0/8`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Cidr {
+    /// First address of the range
+    address: IpAddr,
+
+    /// Number of leading bits of the address that have to match
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Parse a range, a single address without a prefix
+    fn parse(cidr: &str) -> anyhow::Result<Self> {
+        let invalid = || anyhow!("Invalid TRUSTED_PROXIES: {cidr}, expected `address[/prefix]`");
+
+        let (address, prefix) = match cidr.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (cidr, None),
+        };
+
+        let address = address
+            .parse::<IpAddr>()
+            .map_err(|_| invalid())?
+            .to_canonical();
+
+        let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix
+            .map(|prefix| prefix.parse::<u8>().map_err(|_| invalid()))
+            .transpose()?
+            .unwrap_or(max_prefix);
+
+        if prefix > max_prefix {
+            return Err(invalid());
+        }
+
+        Ok(Self { address, prefix })
+    }
+
+    /// Is the address part of this range
+    fn contains(&self, address: IpAddr) -> bool {
+        match (self.address, address.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+
+                u32::from(network) & mask == u32::from(address) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+
+                u128::from(network) & mask == u128::from(address) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix)
+    }
+}
+
+/// How to find the IP address of the client
+#[derive(Clone, Debug)]
+pub struct ClientIpSettings {
+    /// Where to find the address
+    source: Source,
+
+    /// Proxies allowed to send the address of the client
+    trusted_proxies: Vec<Cidr>,
+
+    /// Number of leading bits of an IPv6 address that make up a single visitor
+    ipv6_visitor_prefix: u8,
+}
+
+impl Default for ClientIpSettings {
+    fn default() -> Self {
+        Self {
+            source: Source::default(),
+            trusted_proxies: Vec::new(),
+            ipv6_visitor_prefix: DEFAULT_IPV6_VISITOR_PREFIX,
+        }
+    }
+}
+
+impl ClientIpSettings {
+    /// Setup the settings based on the `CLIENT_IP_SOURCE`, `TRUSTED_PROXIES` (comma separated)
+    /// and `IPV6_VISITOR_PREFIX` environment variables
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the source is unknown, a trusted proxy or the prefix is invalid
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let settings = Self::parse(
+            env_var_optional("CLIENT_IP_SOURCE").as_deref(),
+            env_var_optional("TRUSTED_PROXIES").as_deref(),
+        )?;
+
+        match env_var_optional("IPV6_VISITOR_PREFIX") {
+            Some(prefix) => match prefix.trim_start_matches('/').parse::<u8>() {
+                Ok(prefix) if prefix <= 128 => Ok(settings.with_ipv6_visitor_prefix(prefix)),
+                _ => Err(anyhow!(
+                    "Invalid IPV6_VISITOR_PREFIX: {prefix}, expected a number up to 128"
+                )),
+            },
+            None => Ok(settings),
+        }
+    }
+
+    /// Parse the settings, any header without a source
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the source is unknown or a trusted proxy is invalid
+    pub fn parse(source: Option<&str>, trusted_proxies: Option<&str>) -> anyhow::Result<Self> {
+        let source = match source {
+            None | Some("any") => Source::Any,
+            Some("x-forwarded-for") => Source::XForwardedFor,
+            Some("cf-connecting-ip") => Source::CfConnectingIp,
+            Some("connect-info") => Source::ConnectInfo,
+            Some(other) => {
+                return Err(anyhow!(
+                    "Invalid CLIENT_IP_SOURCE: {other}, expected `any`, `x-forwarded-for`, \
+                    `cf-connecting-ip` or `connect-info`"
+                ))
+            }
+        };
+
+        let trusted_proxies = trusted_proxies
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|cidr| !cidr.is_empty())
+            .map(Cidr::parse)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            source,
+            trusted_proxies,
+            ..Self::default()
+        })
+    }
+
+    /// Keep the leading bits of IPv6 addresses of visitors, `128` for the full address
+    #[must_use]
+    pub fn with_ipv6_visitor_prefix(mut self, prefix: u8) -> Self {
+        self.ipv6_visitor_prefix = prefix.min(128);
+        self
+    }
+
+    /// The address of the visitor with the address of a client, IPv6 addresses are cut to their
+    /// prefix; IPv4 addresses are kept as they are
+    pub fn visitor(&self, ip_address: IpAddr) -> IpAddr {
+        match ip_address.to_canonical() {
+            IpAddr::V6(address) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.ipv6_visitor_prefix))
+                    .unwrap_or(0);
+
+                IpAddr::V6((u128::from(address) & mask).into())
+            }
+            address @ IpAddr::V4(_) => address,
+        }
+    }
+
+    /// Find the IP address of the client of a request
+    fn find(&self, headers: &HeaderMap, extensions: &Extensions) -> Option<IpAddr> {
+        let peer = extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(address)| address.ip().to_canonical());
+
+        match self.source {
+            Source::Any => InsecureClientIp::from(headers, extensions)
+                .ok()
+                .map(|ip_address| ip_address.0),
+            Source::XForwardedFor if self.is_trusted_peer(peer) => {
+                self.find_forwarded_for(headers).or(peer)
+            }
+            Source::CfConnectingIp if self.is_trusted_peer(peer) => headers
+                .get(CF_CONNECTING_IP)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<IpAddr>().ok())
+                .map(|ip_address| ip_address.to_canonical())
+                .or(peer),
+            // the connection itself, or not from a trusted proxy
+            Source::ConnectInfo | Source::XForwardedFor | Source::CfConnectingIp => peer,
+        }
+    }
+
+    /// Is the connection from a trusted proxy, connections without address are
+    fn is_trusted_peer(&self, peer: Option<IpAddr>) -> bool {
+        peer.is_none_or(|peer| self.trusted_proxies.is_empty() || self.is_trusted_proxy(peer))
+    }
+
+    /// Is the address one of the trusted proxies
+    fn is_trusted_proxy(&self, ip_address: IpAddr) -> bool {
+        self.trusted_proxies
+            .iter()
+            .any(|cidr| cidr.contains(ip_address))
+    }
+
+    /// Find the rightmost address of `X-Forwarded-For` that is not a trusted proxy, the
+    /// rightmost address without trusted proxies
+    ///
+    /// An invalid address stops the search, everything to its left can not be trusted
+    fn find_forwarded_for(&self, headers: &HeaderMap) -> Option<IpAddr> {
+        let entries = headers
+            .get_all(X_FORWARDED_FOR)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect::<Vec<_>>();
+
+        let mut leftmost_proxy = None;
+        for entry in entries.into_iter().rev() {
+            let ip_address = entry.parse::<IpAddr>().ok()?.to_canonical();
+
+            if !self.is_trusted_proxy(ip_address) {
+                return Some(ip_address);
+            }
+
+            leftmost_proxy = Some(ip_address);
+        }
+
+        // every address is a trusted proxy, the first one is closest to the client
+        leftmost_proxy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Find the address for the headers and the address of the connection
+    fn find(
+        settings: &ClientIpSettings,
+        headers: &[(&'static str, &'static str)],
+        peer: Option<&str>,
+    ) -> Option<String> {
+        let mut header_map = HeaderMap::new();
+        for (name, value) in headers {
+            header_map.append(*name, value.parse().unwrap());
+        }
+
+        let mut extensions = Extensions::new();
+        if let Some(peer) = peer {
+            extensions.insert(ConnectInfo(SocketAddr::new(peer.parse().unwrap(), 1234)));
+        }
+
+        settings
+            .find(&header_map, &extensions)
+            .map(|ip_address| ip_address.to_string())
+    }
+
+    #[test]
+    fn test_cidr() {
+        let cidr = Cidr::parse("10.0.0.0/8").unwrap();
+        assert_eq!("10.0.0.0/8", cidr.to_string());
+        assert!(cidr.contains("10.1.2.3".parse().unwrap()));
+        assert!(cidr.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!cidr.contains("11.0.0.1".parse().unwrap()));
+        assert!(!cidr.contains("::1".parse().unwrap()));
+
+        let cidr = Cidr::parse("192.168.1.1").unwrap();
+        assert!(cidr.contains("192.168.1.1".parse().unwrap()));
+        assert!(!cidr.contains("192.168.1.2".parse().unwrap()));
+
+        let cidr = Cidr::parse("fd00::/8").unwrap();
+        assert!(cidr.contains("fd12::1".parse().unwrap()));
+        assert!(!cidr.contains("fe80::1".parse().unwrap()));
+
+        assert!(Cidr::parse("0.0.0.0/0")
+            .unwrap()
+            .contains("1.2.3.4".parse().unwrap()));
+
+        assert!(Cidr::parse("10.0.0.0/33").is_err());
+        assert!(Cidr::parse("10.0.0.0/eight").is_err());
+        assert!(Cidr::parse("localhost").is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(ClientIpSettings::parse(None, None).is_ok());
+        assert!(ClientIpSettings::parse(Some("x-forwarded-for"), Some("10.0.0.0/8, ::1")).is_ok());
+        assert!(ClientIpSettings::parse(Some("x-real-ip"), None).is_err());
+        assert!(ClientIpSettings::parse(None, Some("10.0.0.0/99")).is_err());
+    }
+
+    #[test]
+    fn test_visitor() {
+        let settings = ClientIpSettings::default();
+        assert_eq!(
+            "2001:db8:1:2::",
+            settings
+                .visitor("2001:db8:1:2:aaaa:bbbb:cccc:dddd".parse().unwrap())
+                .to_string()
+        );
+        assert_eq!(
+            "1.2.3.4",
+            settings.visitor("1.2.3.4".parse().unwrap()).to_string()
+        );
+        assert_eq!(
+            "1.2.3.4",
+            settings
+                .visitor("::ffff:1.2.3.4".parse().unwrap())
+                .to_string()
+        );
+
+        let settings = settings.with_ipv6_visitor_prefix(128);
+        assert_eq!(
+            "2001:db8::1",
+            settings.visitor("2001:db8::1".parse().unwrap()).to_string()
+        );
+
+        let settings = settings.with_ipv6_visitor_prefix(0);
+        assert_eq!(
+            "::",
+            settings.visitor("2001:db8::1".parse().unwrap()).to_string()
+        );
+    }
+
+    #[test]
+    fn test_find_any() {
+        let settings = ClientIpSettings::default();
+
+        assert_eq!(
+            Some("1.1.1.1".to_string()),
+            find(
+                &settings,
+                &[("x-forwarded-for", "1.1.1.1, 10.0.0.1")],
+                Some("10.0.0.2")
+            )
+        );
+        assert_eq!(
+            Some("10.0.0.2".to_string()),
+            find(&settings, &[], Some("10.0.0.2"))
+        );
+        assert_eq!(None, find(&settings, &[], None));
+    }
+
+    #[test]
+    fn test_find_x_forwarded_for() {
+        let settings =
+            ClientIpSettings::parse(Some("x-forwarded-for"), Some("10.0.0.0/8")).unwrap();
+
+        // the client can prepend anything
+        assert_eq!(
+            Some("2.2.2.2".to_string()),
+            find(
+                &settings,
+                &[("x-forwarded-for", "1.1.1.1, 2.2.2.2, 10.0.0.1")],
+                Some("10.0.0.2")
+            )
+        );
+
+        // multiple headers are a single list
+        assert_eq!(
+            Some("2.2.2.2".to_string()),
+            find(
+                &settings,
+                &[
+                    ("x-forwarded-for", "1.1.1.1, 2.2.2.2"),
+                    ("x-forwarded-for", "10.0.0.1")
+                ],
+                Some("10.0.0.2")
+            )
+        );
+
+        // not from a trusted proxy
+        assert_eq!(
+            Some("3.3.3.3".to_string()),
+            find(
+                &settings,
+                &[("x-forwarded-for", "1.1.1.1")],
+                Some("3.3.3.3")
+            )
+        );
+
+        // without the header
+        assert_eq!(
+            Some("10.0.0.2".to_string()),
+            find(&settings, &[], Some("10.0.0.2"))
+        );
+
+        // an invalid address can not be trusted
+        assert_eq!(
+            Some("10.0.0.2".to_string()),
+            find(
+                &settings,
+                &[("x-forwarded-for", "1.1.1.1, garbage, 10.0.0.1")],
+                Some("10.0.0.2")
+            )
+        );
+
+        // only proxies
+        assert_eq!(
+            Some("10.0.0.3".to_string()),
+            find(
+                &settings,
+                &[("x-forwarded-for", "10.0.0.3, 10.0.0.1")],
+                Some("10.0.0.2")
+            )
+        );
+
+        // over a Unix domain socket
+        assert_eq!(
+            Some("2.2.2.2".to_string()),
+            find(&settings, &[("x-forwarded-for", "1.1.1.1, 2.2.2.2")], None)
+        );
+
+        // without trusted proxies, the rightmost address
+        let settings = ClientIpSettings::parse(Some("x-forwarded-for"), None).unwrap();
+        assert_eq!(
+            Some("2.2.2.2".to_string()),
+            find(
+                &settings,
+                &[("x-forwarded-for", "1.1.1.1, 2.2.2.2")],
+                Some("10.0.0.2")
+            )
+        );
+    }
+
+    #[test]
+    fn test_find_cf_connecting_ip() {
+        let settings =
+            ClientIpSettings::parse(Some("cf-connecting-ip"), Some("173.245.48.0/20")).unwrap();
+
+        assert_eq!(
+            Some("1.1.1.1".to_string()),
+            find(
+                &settings,
+                &[("cf-connecting-ip", "1.1.1.1")],
+                Some("173.245.48.1")
+            )
+        );
+        assert_eq!(
+            Some("3.3.3.3".to_string()),
+            find(
+                &settings,
+                &[("cf-connecting-ip", "1.1.1.1")],
+                Some("3.3.3.3")
+            )
+        );
+    }
+
+    #[test]
+    fn test_find_connect_info() {
+        let settings = ClientIpSettings::parse(Some("connect-info"), None).unwrap();
+
+        assert_eq!(
+            Some("3.3.3.3".to_string()),
+            find(
+                &settings,
+                &[("x-forwarded-for", "1.1.1.1")],
+                Some("::ffff:3.3.3.3")
+            )
+        );
+        assert_eq!(
+            None,
+            find(&settings, &[("x-forwarded-for", "1.1.1.1")], None)
+        );
+    }
+}
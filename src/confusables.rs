@@ -0,0 +1,163 @@
+//! Detection of confusable slugs and hostnames
+//!
+//! A slug like `pаypal` with a Cyrillic `а` looks just like its Latin counterpart, a welcome
+//! tool for phishing. New slugs mixing the scripts that share lookalike letters (Latin, Greek,
+//! Cyrillic, Armenian and Cherokee), or written in one of them with lookalikes only, are
+//! confusable. Other scripts, digits and punctuation are left alone. The same goes for every
+//! label of the [hostname](crate::idn) of a URL.
+//!
+//! With `SLUG_CONFUSABLES` and `URL_CONFUSABLES` confusable slugs and URLs are allowed
+//! (default), flagged in the logs or rejected.
+
+use crate::utils::env_var_optional;
+
+/// Letters of other scripts looking just like a Latin letter
+const LATIN_LOOKALIKES: &str = "аеорсухіјѕһԁԛԝӏАВЕКМНОРСТХЅІЈοικνρυΑΒΕΖΗΙΚΜΝΟΡΤΥΧօսհո";
+
+/// Scripts sharing lookalike letters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Script {
+    /// Latin, including its accented letters
+    Latin,
+
+    /// Greek and Coptic
+    Greek,
+
+    /// Cyrillic, including its extensions
+    Cyrillic,
+
+    /// Armenian
+    Armenian,
+
+    /// Cherokee
+    Cherokee,
+}
+
+impl Script {
+    /// The script of the character, `None` for other scripts, digits and punctuation
+    fn of(ch: char) -> Option<Self> {
+        match ch {
+            // the multiplication and division signs among the accented letters
+            '\u{00d7}' | '\u{00f7}' => None,
+            'a'..='z' | 'A'..='Z' | '\u{00c0}'..='\u{024f}' | '\u{1e00}'..='\u{1eff}' => {
+                Some(Self::Latin)
+            }
+            '\u{0370}'..='\u{03ff}' | '\u{1f00}'..='\u{1fff}' => Some(Self::Greek),
+            '\u{0400}'..='\u{052f}' | '\u{1c80}'..='\u{1c8f}' | '\u{a640}'..='\u{a69f}' => {
+                Some(Self::Cyrillic)
+            }
+            '\u{0530}'..='\u{058f}' => Some(Self::Armenian),
+            '\u{13a0}'..='\u{13ff}' => Some(Self::Cherokee),
+            _ => None,
+        }
+    }
+}
+
+/// Is the text, like a slug, confusable with Latin text?
+///
+/// Either it mixes scripts, or it consists of lookalikes of Latin letters only
+pub fn is_confusable(text: &str) -> bool {
+    let mut scripts: Vec<Script> = Vec::new();
+    let mut has_lookalikes_only = true;
+
+    for ch in text.chars() {
+        let Some(script) = Script::of(ch) else {
+            continue;
+        };
+
+        if !scripts.contains(&script) {
+            scripts.push(script);
+        }
+
+        if script != Script::Latin && !LATIN_LOOKALIKES.contains(ch) {
+            has_lookalikes_only = false;
+        }
+    }
+
+    match scripts.as_slice() {
+        [] | [Script::Latin] => false,
+        [_] => has_lookalikes_only,
+        _ => true,
+    }
+}
+
+/// How to handle confusable slugs or URLs of new destinations
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Confusables {
+    /// Create them like any other slug
+    #[default]
+    Allow,
+
+    /// Create them, with a warning in the logs
+    Flag,
+
+    /// Refuse to create them
+    Reject,
+}
+
+impl Confusables {
+    /// Setup the handling based on the environment variable, like `SLUG_CONFUSABLES`
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the value is not `allow`, `flag` or `reject`
+    pub fn from_environment(variable: &'static str) -> anyhow::Result<Self> {
+        match env_var_optional(variable).as_deref() {
+            None | Some("allow") => Ok(Self::Allow),
+            Some("flag") => Ok(Self::Flag),
+            Some("reject") => Ok(Self::Reject),
+            Some(other) => Err(anyhow::anyhow!(
+                "Invalid {variable}: {other}, expected `allow`, `flag` or `reject`"
+            )),
+        }
+    }
+
+    /// Is the value, known to be confusable or not, allowed? Confusable values are flagged in
+    /// the logs along the way
+    pub fn allows(self, value: &str, is_confusable: bool) -> bool {
+        if self == Self::Allow || !is_confusable {
+            return true;
+        }
+
+        tracing::warn!(r#""{value}" is confusable with Latin text"#);
+
+        self == Self::Flag
+    }
+
+    /// Is the new slug allowed?
+    pub fn allows_slug(self, slug: &str) -> bool {
+        self.allows(slug, is_confusable(slug))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_confusable() {
+        assert!(!is_confusable("paypal"));
+        assert!(!is_confusable("café-2025"));
+        assert!(!is_confusable("привет"));
+        assert!(!is_confusable("ελληνικά"));
+        assert!(!is_confusable("東京-tokyo"));
+        assert!(!is_confusable("🦀"));
+
+        // a Cyrillic `а` in a Latin word
+        assert!(is_confusable("p\u{0430}ypal"));
+
+        // Cyrillic lookalikes only
+        assert!(is_confusable("\u{0441}\u{043e}\u{0440}"));
+
+        // a Latin letter in a Cyrillic word
+        assert!(is_confusable("пpивет"));
+    }
+
+    #[test]
+    fn test_allows() {
+        assert!(Confusables::Allow.allows_slug("p\u{0430}ypal"));
+        assert!(Confusables::Flag.allows_slug("p\u{0430}ypal"));
+        assert!(!Confusables::Reject.allows_slug("p\u{0430}ypal"));
+        assert!(Confusables::Reject.allows_slug("paypal"));
+    }
+}
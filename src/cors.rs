@@ -0,0 +1,165 @@
+//! Cross-origin requests to the API, for browser based dashboards on other origins
+//!
+//! Disabled by default, enabled with the `CORS_ALLOWED_ORIGINS` environment variable. The API
+//! uses the `Authorization` header, not cookies, so credentials are never allowed.
+
+use anyhow::anyhow;
+use axum::http::header::AUTHORIZATION;
+use axum::http::header::CONTENT_TYPE;
+use axum::http::header::ETAG;
+use axum::http::header::IF_NONE_MATCH;
+use axum::http::HeaderName;
+use axum::http::HeaderValue;
+use axum::http::Method;
+use tower_http::cors::AllowOrigin;
+use tower_http::cors::CorsLayer;
+use url::Url;
+
+use crate::api::TOTAL_COUNT_HEADER;
+use crate::telemetry::X_REQUEST_ID;
+use crate::utils::env_var_optional;
+
+/// Headers allowed by default, enough for the API
+const DEFAULT_ALLOWED_HEADERS: [HeaderName; 3] = [AUTHORIZATION, CONTENT_TYPE, IF_NONE_MATCH];
+
+/// Methods allowed by default, all methods of the API
+const DEFAULT_ALLOWED_METHODS: [Method; 5] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::PATCH,
+    Method::DELETE,
+];
+
+/// Setup the CORS layer based on the `CORS_ALLOWED_ORIGINS`, `CORS_ALLOWED_HEADERS` and
+/// `CORS_ALLOWED_METHODS` environment variables, all comma separated lists
+///
+/// Returns `None` when no origins are allowed, `*` allows all origins
+///
+/// # Errors
+///
+/// Will return `Err` when an origin, header or method is invalid
+pub fn layer() -> anyhow::Result<Option<CorsLayer>> {
+    let Some(origins) = env_var_optional("CORS_ALLOWED_ORIGINS") else {
+        return Ok(None);
+    };
+
+    parse(
+        &origins,
+        env_var_optional("CORS_ALLOWED_HEADERS").as_deref(),
+        env_var_optional("CORS_ALLOWED_METHODS").as_deref(),
+    )
+    .map(Some)
+}
+
+/// Parse the CORS layer, with the default headers and methods when not provided
+pub fn parse(
+    origins: &str,
+    headers: Option<&str>,
+    methods: Option<&str>,
+) -> anyhow::Result<CorsLayer> {
+    let layer = CorsLayer::new()
+        .allow_origin(parse_origins(origins)?)
+        .allow_headers(
+            headers
+                .map(parse_headers)
+                .transpose()?
+                .unwrap_or_else(|| DEFAULT_ALLOWED_HEADERS.to_vec()),
+        )
+        .allow_methods(
+            methods
+                .map(parse_methods)
+                .transpose()?
+                .unwrap_or_else(|| DEFAULT_ALLOWED_METHODS.to_vec()),
+        )
+        .expose_headers([X_REQUEST_ID, ETAG, TOTAL_COUNT_HEADER]);
+
+    Ok(layer)
+}
+
+/// Split a comma separated list, without empty entries
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+}
+
+/// Parse the allowed origins, like `https://dashboard.example.com`, or `*` for all origins
+fn parse_origins(origins: &str) -> anyhow::Result<AllowOrigin> {
+    if origins.trim() == "*" {
+        return Ok(AllowOrigin::any());
+    }
+
+    let origins = split_list(origins)
+        .map(|origin| {
+            let invalid = || {
+                anyhow!("Invalid CORS_ALLOWED_ORIGINS: {origin}, expected `scheme://host[:port]`")
+            };
+
+            let url = Url::parse(origin).map_err(|_| invalid())?;
+
+            if !url.has_host() || url.path() != "/" || url.query().is_some() {
+                return Err(invalid());
+            }
+
+            HeaderValue::from_str(&url.origin().ascii_serialization()).map_err(|_| invalid())
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(AllowOrigin::list(origins))
+}
+
+/// Parse the allowed headers, like `Authorization`
+fn parse_headers(headers: &str) -> anyhow::Result<Vec<HeaderName>> {
+    split_list(headers)
+        .map(|header| {
+            header
+                .parse::<HeaderName>()
+                .map_err(|_| anyhow!("Invalid CORS_ALLOWED_HEADERS: {header}"))
+        })
+        .collect()
+}
+
+/// Parse the allowed methods, like `GET`
+fn parse_methods(methods: &str) -> anyhow::Result<Vec<Method>> {
+    split_list(methods)
+        .map(|method| {
+            Method::from_bytes(method.to_uppercase().as_bytes())
+                .map_err(|_| anyhow!("Invalid CORS_ALLOWED_METHODS: {method}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_origins() {
+        assert!(parse_origins("*").is_ok());
+        assert!(parse_origins("https://dashboard.example.com").is_ok());
+        assert!(parse_origins("https://dashboard.example.com, http://localhost:3000").is_ok());
+
+        assert!(parse_origins("dashboard.example.com").is_err());
+        assert!(parse_origins("https://dashboard.example.com/path").is_err());
+        assert!(parse_origins("https://dashboard.example.com/?q=1").is_err());
+    }
+
+    #[test]
+    fn test_parse_headers() {
+        assert_eq!(
+            vec![AUTHORIZATION, X_REQUEST_ID],
+            parse_headers("Authorization, X-Request-Id").unwrap()
+        );
+        assert!(parse_headers("Invalid Header").is_err());
+    }
+
+    #[test]
+    fn test_parse_methods() {
+        assert_eq!(
+            vec![Method::GET, Method::DELETE],
+            parse_methods("get, DELETE").unwrap()
+        );
+        assert!(parse_methods("GET,NOT A METHOD").is_err());
+    }
+}
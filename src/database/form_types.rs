@@ -1,9 +1,12 @@
 //! Form types
 
+use std::net::IpAddr;
+
 use url::Url;
 use uuid::Uuid;
 
 use crate::destinations::Destination;
+use crate::domains::Domain;
 use crate::notes::Note;
 use crate::users::Role;
 use crate::users::User;
@@ -21,6 +24,9 @@ pub struct CreateUserValues<'a> {
 
     /// The hashed password
     pub hashed_password: &'a str,
+
+    /// The email address, for the notifications
+    pub email: Option<&'a str>,
 }
 
 /// Values to change a password of a user
@@ -40,11 +46,44 @@ pub struct CreateDestinationValues<'a> {
     /// The slug of the destination
     pub slug: &'a str,
 
+    /// The domain of the destination, all domains when not set
+    pub domain: Option<&'a str>,
+
     /// The URL the destination redirects to
     pub url: &'a Url,
 
     /// Make the destination as permanent
     pub is_permanent: &'a bool,
+
+    /// Redirect with a meta refresh page
+    pub is_meta_refresh: &'a bool,
+
+    /// Only redirect with a valid signed link
+    pub is_private: &'a bool,
+
+    /// Serve the stats publicly
+    pub is_stats_public: &'a bool,
+
+    /// Never record the hits
+    pub is_untracked: &'a bool,
+
+    /// Open Graph metadata
+    pub open_graph: OpenGraphValues<'a>,
+
+    /// Script deciding the URL to redirect to, already validated
+    pub script: Option<&'a str>,
+
+    /// Extra headers of the redirect as a JSON object, already validated
+    pub headers: Option<&'a str>,
+
+    /// Tags of the destination, already normalized
+    pub tags: &'a [String],
+
+    /// Channel the short link is handed out in, already normalized
+    pub source: Option<&'a str>,
+
+    /// Wait for the approval of an admin before resolving
+    pub is_pending: bool,
 }
 
 /// Values to update an Destination
@@ -57,6 +96,170 @@ pub struct UpdateDestinationValues<'a> {
     /// Can only be set to `false` if the destination already has `is_permanent=true`, otherwise
     /// only `true` is valid
     pub is_permanent: Option<&'a bool>,
+
+    /// Redirect with a meta refresh page
+    pub is_meta_refresh: Option<&'a bool>,
+
+    /// Only redirect with a valid signed link
+    pub is_private: Option<&'a bool>,
+
+    /// Serve the stats publicly
+    pub is_stats_public: Option<&'a bool>,
+
+    /// Never record the hits
+    pub is_untracked: Option<&'a bool>,
+
+    /// Open Graph metadata to update, fields are not touched when not provided
+    pub open_graph: OpenGraphValues<'a>,
+
+    /// Script to update, already validated, an empty string removes the script
+    pub script: Option<&'a str>,
+
+    /// Extra headers to update as a JSON object, already validated, an empty string removes the
+    /// headers
+    pub headers: Option<&'a str>,
+
+    /// New tags, already normalized, replacing the current tags
+    pub tags: Option<&'a [String]>,
+
+    /// New source, already normalized, an empty string removes the source
+    pub source: Option<&'a str>,
+}
+
+/// Open Graph metadata of a Destination
+///
+/// An empty string means no value, on update it removes the value
+pub struct OpenGraphValues<'a> {
+    /// Title of the destination
+    pub title: Option<&'a str>,
+
+    /// Description of the destination
+    pub description: Option<&'a str>,
+
+    /// Image URL of the destination, already validated
+    pub image: Option<&'a str>,
+}
+
+/// The optional value to store, an empty string is no value
+pub fn stored_value(value: Option<&str>) -> Option<String> {
+    value
+        .filter(|value| !value.is_empty())
+        .map(ToString::to_string)
+}
+
+/// The optional value to store on update, keeping the current value when not provided
+#[must_use]
+pub fn updated_value(value: Option<&str>, current: Option<&String>) -> Option<String> {
+    value.map_or_else(|| current.cloned(), |value| stored_value(Some(value)))
+}
+
+/// Values to create a Domain
+pub struct CreateDomainValues<'a> {
+    /// The user registering the domain
+    pub user: &'a User,
+
+    /// The normalized hostname of the domain
+    pub hostname: &'a str,
+
+    /// Fallback URL for slugs without a destination
+    pub fallback_url: Option<&'a Url>,
+
+    /// HTML of the 404 page of the domain
+    pub not_found_template: Option<&'a str>,
+}
+
+/// Values to update a Domain
+///
+/// An empty value removes the default, the default is not touched when not provided
+pub struct UpdateDomainValues<'a> {
+    /// New (optional) fallback URL for slugs without a destination
+    pub fallback_url: Option<&'a str>,
+
+    /// New (optional) HTML of the 404 page of the domain
+    pub not_found_template: Option<&'a str>,
+}
+
+/// Values to create a Destination template
+pub struct CreateDestinationTemplateValues<'a> {
+    /// The user creating the template
+    pub user: &'a User,
+
+    /// Name to reference the template by
+    pub name: &'a str,
+
+    /// Domain of the destinations, already normalized
+    pub domain: Option<&'a str>,
+
+    /// Type of the destinations
+    pub is_permanent: Option<bool>,
+
+    /// Tags of the destinations, already normalized
+    pub tags: &'a [String],
+
+    /// UTM parameters of the URLs
+    pub utm: UtmValues<'a>,
+}
+
+/// Values to update a Destination template
+///
+/// Values are not touched when not provided, an empty string removes the value
+pub struct UpdateDestinationTemplateValues<'a> {
+    /// New domain of the destinations, already normalized
+    pub domain: Option<&'a str>,
+
+    /// New type of the destinations, `Some(None)` removes the type
+    pub is_permanent: Option<Option<bool>>,
+
+    /// New tags of the destinations, already normalized
+    pub tags: Option<&'a [String]>,
+
+    /// New UTM parameters of the URLs
+    pub utm: UtmValues<'a>,
+}
+
+/// UTM parameters of the URLs of a Destination template
+pub struct UtmValues<'a> {
+    /// `utm_source` query parameter
+    pub source: Option<&'a str>,
+
+    /// `utm_medium` query parameter
+    pub medium: Option<&'a str>,
+
+    /// `utm_campaign` query parameter
+    pub campaign: Option<&'a str>,
+}
+
+/// Values to create a Webhook
+pub struct CreateWebhookValues<'a> {
+    /// The user registering the webhook
+    pub user: &'a User,
+
+    /// URL the events are posted to
+    pub url: &'a Url,
+
+    /// Secret of the signatures
+    pub secret: &'a str,
+
+    /// The destination of which the hits are posted, `None` for the changes
+    pub destination: Option<&'a Destination>,
+
+    /// Post the hits every minute, instead of right away
+    pub is_batched: bool,
+}
+
+/// Values to create an Abuse report
+pub struct CreateAbuseReportValues<'a> {
+    /// The reported destination
+    pub destination: &'a Destination,
+
+    /// Why the destination is reported
+    pub reason: &'a str,
+
+    /// How to reach the reporter
+    pub contact: Option<&'a str>,
+
+    /// IP address of the reporter
+    pub ip_address: Option<&'a IpAddr>,
 }
 
 /// Values to create an Note
@@ -76,6 +279,33 @@ pub struct UpdateNoteValues<'a> {
     pub content: Option<&'a String>,
 }
 
+/// An operation of a batch, already validated
+pub enum BatchOperation<'a> {
+    /// Create a destination
+    CreateDestination(CreateDestinationValues<'a>),
+
+    /// Create a note for a destination
+    CreateNote(BatchDestination<'a>, CreateNoteValues<'a>),
+}
+
+/// The destination of an operation of a batch
+pub enum BatchDestination<'a> {
+    /// An existing destination
+    Existing(&'a Destination),
+
+    /// The destination created by an earlier operation of the batch, by its index
+    Created(usize),
+}
+
+/// The outcome of an operation of a batch
+pub enum BatchOutcome {
+    /// The created destination
+    Destination(Destination),
+
+    /// The created note, with its destination
+    Note(Destination, Note),
+}
+
 /// Possible audit trail entry types
 pub enum AuditEntry<'a> {
     /// User is created
@@ -87,6 +317,15 @@ pub enum AuditEntry<'a> {
     /// User is deleted
     DeleteUser(&'a User),
 
+    /// Quota of the user is changed
+    ChangeQuota(&'a User),
+
+    /// Email address of the user is changed
+    ChangeEmail(&'a User),
+
+    /// Token of the user is presented after the user is deleted
+    DeletedUserToken(&'a User),
+
     /// Destination is created
     CreateDestination(&'a Destination),
 
@@ -96,6 +335,15 @@ pub enum AuditEntry<'a> {
     /// Destination is deleted
     DeleteDestination(&'a Destination),
 
+    /// Destination is disabled, its URL is flagged as unsafe
+    FlagDestination(&'a Destination),
+
+    /// Pending destination is approved by an admin
+    ApproveDestination(&'a Destination),
+
+    /// Pending destination is rejected by an admin
+    RejectDestination(&'a Destination),
+
     /// Note is created
     CreateNote(&'a Destination, &'a Note),
 
@@ -104,4 +352,13 @@ pub enum AuditEntry<'a> {
 
     /// Note is deleted
     DeleteNote(&'a Destination, &'a Note),
+
+    /// Domain is created
+    CreateDomain(&'a Domain),
+
+    /// Domain is updated
+    UpdateDomain(&'a Domain),
+
+    /// Domain is deleted
+    DeleteDomain(&'a Domain),
 }
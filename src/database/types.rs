@@ -2,8 +2,10 @@
 
 use chrono::NaiveDateTime;
 use sqlx::migrate::Migrator;
+use sqlx::types::ipnetwork::IpNetwork;
 use uuid::Uuid;
 
+use crate::audit_trail::AuditTrailEntry;
 use crate::users::Role;
 use crate::users::User;
 
@@ -56,6 +58,15 @@ pub enum AuditEntryType {
     /// User is deleted
     DeleteUser,
 
+    /// Quota of the user is changed
+    ChangeQuota,
+
+    /// Email address of the user is changed
+    ChangeEmail,
+
+    /// Token of the user is presented after the user is deleted
+    DeletedUserToken,
+
     /// Destination is created
     CreateDestination,
 
@@ -65,6 +76,15 @@ pub enum AuditEntryType {
     /// Destination is deleted
     DeleteDestination,
 
+    /// Destination is disabled, its URL is flagged as unsafe
+    FlagDestination,
+
+    /// Pending destination is approved
+    ApproveDestination,
+
+    /// Pending destination is rejected
+    RejectDestination,
+
     /// Note is deleted
     CreateNote,
 
@@ -73,6 +93,15 @@ pub enum AuditEntryType {
 
     /// Note is deleted
     DeleteNote,
+
+    /// Domain is created
+    CreateDomain,
+
+    /// Domain is updated
+    UpdateDomain,
+
+    /// Domain is deleted
+    DeleteDomain,
 }
 
 impl AuditEntryType {
@@ -82,18 +111,124 @@ impl AuditEntryType {
             AuditEntry::CreateUser(_) => Self::CreateUser,
             AuditEntry::ChangePassword(_) => Self::ChangePassword,
             AuditEntry::DeleteUser(_) => Self::DeleteUser,
+            AuditEntry::ChangeQuota(_) => Self::ChangeQuota,
+            AuditEntry::ChangeEmail(_) => Self::ChangeEmail,
+            AuditEntry::DeletedUserToken(_) => Self::DeletedUserToken,
 
             AuditEntry::CreateDestination(_) => Self::CreateDestination,
             AuditEntry::UpdateDestination(_) => Self::UpdateDestination,
             AuditEntry::DeleteDestination(_) => Self::DeleteDestination,
+            AuditEntry::FlagDestination(_) => Self::FlagDestination,
+            AuditEntry::ApproveDestination(_) => Self::ApproveDestination,
+            AuditEntry::RejectDestination(_) => Self::RejectDestination,
 
             AuditEntry::CreateNote(_, _) => Self::CreateNote,
             AuditEntry::UpdateNote(_, _) => Self::UpdateNote,
             AuditEntry::DeleteNote(_, _) => Self::DeleteNote,
+
+            AuditEntry::CreateDomain(_) => Self::CreateDomain,
+            AuditEntry::UpdateDomain(_) => Self::UpdateDomain,
+            AuditEntry::DeleteDomain(_) => Self::DeleteDomain,
+        }
+    }
+
+    /// Name of the audit entry type, the same as it is stored
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::CreateUser => "create-user",
+            Self::ChangePassword => "change-password",
+            Self::DeleteUser => "delete-user",
+            Self::ChangeQuota => "change-quota",
+            Self::ChangeEmail => "change-email",
+            Self::DeletedUserToken => "deleted-user-token",
+
+            Self::CreateDestination => "create-destination",
+            Self::UpdateDestination => "update-destination",
+            Self::DeleteDestination => "delete-destination",
+            Self::FlagDestination => "flag-destination",
+            Self::ApproveDestination => "approve-destination",
+            Self::RejectDestination => "reject-destination",
+
+            Self::CreateNote => "create-note",
+            Self::UpdateNote => "update-note",
+            Self::DeleteNote => "delete-note",
+
+            Self::CreateDomain => "create-domain",
+            Self::UpdateDomain => "update-domain",
+            Self::DeleteDomain => "delete-domain",
         }
     }
 }
 
+/// `SQLx` version of an audit trail entry
+pub struct SqlxAuditTrailEntry {
+    /// Entry ID
+    pub id: Uuid,
+
+    /// Position on the trail
+    pub sequence: i64,
+
+    /// Type of entry
+    pub entry_type: AuditEntryType,
+
+    /// Creator of the entry
+    pub created_by: Uuid,
+
+    /// Optional user
+    pub user_id: Option<Uuid>,
+
+    /// Optional destination
+    pub destination_id: Option<Uuid>,
+
+    /// Optional note
+    pub note_id: Option<Uuid>,
+
+    /// Optional domain
+    pub domain_id: Option<Uuid>,
+
+    /// Optional IP address
+    pub ip_address: Option<IpNetwork>,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+
+    /// Hash of the previous entry
+    pub previous_hash: Option<String>,
+
+    /// Hash of the entry
+    pub hash: Option<String>,
+}
+
+impl AuditTrailEntry {
+    /// Create audit trail entry from `SQLx` version
+    pub fn from_sqlx_audit_trail_entry(entry: SqlxAuditTrailEntry) -> Self {
+        Self {
+            id: entry.id,
+            sequence: entry.sequence,
+            entry_type: entry.entry_type.name().to_string(),
+            created_by: entry.created_by,
+            user_id: entry.user_id,
+            destination_id: entry.destination_id,
+            note_id: entry.note_id,
+            domain_id: entry.domain_id,
+            ip_address: entry.ip_address.as_ref().map(ToString::to_string),
+            created_at: entry.created_at,
+            previous_hash: entry.previous_hash,
+            hash: entry.hash,
+        }
+    }
+
+    /// Create multiple audit trail entries from `SQLx` version
+    pub fn from_sqlx_audit_trail_entry_multiple(
+        mut entries: Vec<SqlxAuditTrailEntry>,
+    ) -> Vec<Self> {
+        entries
+            .drain(..)
+            .map(Self::from_sqlx_audit_trail_entry)
+            .collect::<Vec<Self>>()
+    }
+}
+
 /// `SQLx` version of user
 pub struct SqlxUser {
     /// User ID
@@ -111,6 +246,9 @@ pub struct SqlxUser {
     /// User role
     pub role: UserRoleType,
 
+    /// Email address
+    pub email: Option<String>,
+
     /// Creation date
     pub created_at: NaiveDateTime,
 
@@ -123,6 +261,7 @@ pub struct SqlxUser {
 
 impl User {
     /// Create user from `SQLx` version
+    #[must_use]
     pub fn from_sqlx_user(user: SqlxUser) -> Self {
         Self {
             id: user.id,
@@ -130,6 +269,7 @@ impl User {
             username: user.username,
             hashed_password: user.hashed_password,
             role: user.role.to_role(),
+            email: user.email,
             created_at: user.created_at,
             updated_at: user.updated_at,
             deleted_at: user.deleted_at,
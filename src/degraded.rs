@@ -0,0 +1,88 @@
+//! Degraded mode of the root, while the database is unreachable
+//!
+//! Redirects keep being served for the slugs in the cache of slugs, their hits are queued in the
+//! hit buffer and saved once the database is back. Slugs that are not cached, and everything
+//! else, still need the database.
+//!
+//! The root enters degraded mode when a hit can not be saved, and leaves it when the queued hits
+//! are saved. Hits are not saved right away while degraded, visitors do not wait for a database
+//! that is down.
+
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use chrono::DateTime;
+use chrono::Utc;
+
+/// State of the degraded mode
+#[derive(Debug, Default)]
+struct State {
+    /// Moment degraded mode was entered, `None` when not degraded
+    since: Mutex<Option<DateTime<Utc>>>,
+
+    /// Number of redirects served while degraded, since startup
+    redirects: AtomicU64,
+}
+
+/// Statistics of the degraded mode
+#[derive(Debug)]
+pub struct Statistics {
+    /// Moment degraded mode was entered, `None` when not degraded
+    pub since: Option<DateTime<Utc>>,
+
+    /// Number of redirects served while degraded, since startup
+    pub redirects: u64,
+}
+
+/// Degraded mode of the root, clones share the same mode
+#[derive(Clone, Debug, Default)]
+pub struct Degraded {
+    /// The shared state
+    state: Arc<State>,
+}
+
+impl Degraded {
+    /// Is the root degraded?
+    pub fn is_degraded(&self) -> bool {
+        self.state
+            .since
+            .lock()
+            .expect("Valid degraded lock")
+            .is_some()
+    }
+
+    /// Enter degraded mode, because of the error
+    pub fn enter(&self, err: &impl std::fmt::Display) {
+        let mut since = self.state.since.lock().expect("Valid degraded lock");
+
+        if since.is_none() {
+            tracing::warn!("Database is unreachable, serving cached redirects only: {err}");
+
+            *since = Some(Utc::now());
+        }
+    }
+
+    /// Leave degraded mode, the database is reachable again
+    pub fn leave(&self) {
+        let mut since = self.state.since.lock().expect("Valid degraded lock");
+
+        if let Some(entered_at) = since.take() {
+            tracing::info!("Database is reachable again, degraded since {entered_at}");
+        }
+    }
+
+    /// Count a redirect served while degraded
+    pub fn served(&self) {
+        self.state.redirects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Statistics of the degraded mode
+    pub fn statistics(&self) -> Statistics {
+        Statistics {
+            since: *self.state.since.lock().expect("Valid degraded lock"),
+            redirects: self.state.redirects.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//! Destination templates
+//!
+//! Reusable settings for new destinations, for teams to create consistent links without repeating
+//! the settings. A template is referenced by its name when a destination is created; settings of
+//! the destination itself go over the ones of the template:
+//!
+//! - `domain`, the domain of the destination
+//! - `isPermanent`, the type of the destination
+//! - `tags`, added to the tags of the destination
+//! - `utmSource`, `utmMedium` and `utmCampaign`, added to the query of the URL, unless the URL
+//!   already has them
+//!
+//! Destinations do not keep a reference to their template, changing a template does not change
+//! existing destinations.
+
+use chrono::naive::NaiveDateTime;
+use url::Url;
+use uuid::Uuid;
+
+/// A template for new destinations
+#[derive(Clone, Debug)]
+pub struct DestinationTemplate {
+    /// Template ID
+    pub id: Uuid,
+
+    /// The ID of the user that created it
+    #[allow(dead_code)] // used by sqlx
+    pub user_id: Uuid,
+
+    /// Name to reference the template by
+    pub name: String,
+
+    /// Domain of the destinations, all domains when not set
+    pub domain: Option<String>,
+
+    /// Type of the destinations, temporary when not set
+    pub is_permanent: Option<bool>,
+
+    /// Tags of the destinations
+    pub tags: Vec<String>,
+
+    /// `utm_source` query parameter of the URLs
+    pub utm_source: Option<String>,
+
+    /// `utm_medium` query parameter of the URLs
+    pub utm_medium: Option<String>,
+
+    /// `utm_campaign` query parameter of the URLs
+    pub utm_campaign: Option<String>,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+
+    /// Last updated at
+    pub updated_at: NaiveDateTime,
+
+    /// Soft-deleted at
+    pub deleted_at: Option<NaiveDateTime>,
+}
+
+impl DestinationTemplate {
+    /// Is the template soft-deleted?
+    #[must_use]
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    /// The URL with the UTM parameters of the template, parameters already in the URL are kept
+    #[must_use]
+    pub fn tag_url(&self, url: &Url) -> Url {
+        let parameters = [
+            ("utm_source", self.utm_source.as_deref()),
+            ("utm_medium", self.utm_medium.as_deref()),
+            ("utm_campaign", self.utm_campaign.as_deref()),
+        ];
+
+        let mut tagged_url = url.clone();
+
+        for (name, value) in parameters {
+            let Some(value) = value else {
+                continue;
+            };
+
+            if !url.query_pairs().any(|(key, _)| key == name) {
+                tagged_url.query_pairs_mut().append_pair(name, value);
+            }
+        }
+
+        tagged_url
+    }
+
+    /// The tags of the template followed by the tags of the destination, without duplicates
+    #[must_use]
+    pub fn merge_tags(&self, tags: &[String]) -> Vec<String> {
+        let mut merged_tags = self.tags.clone();
+
+        for tag in tags {
+            if !merged_tags.contains(tag) {
+                merged_tags.push(tag.clone());
+            }
+        }
+
+        merged_tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn template() -> DestinationTemplate {
+        let now = Utc::now().naive_utc();
+
+        DestinationTemplate {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            name: "newsletter".to_string(),
+            domain: None,
+            is_permanent: None,
+            tags: vec!["marketing".to_string()],
+            utm_source: Some("newsletter".to_string()),
+            utm_medium: Some("email".to_string()),
+            utm_campaign: None,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn test_tag_url() {
+        let template = template();
+
+        assert_eq!(
+            "https://www.example.com/?utm_source=newsletter&utm_medium=email",
+            template
+                .tag_url(&Url::parse("https://www.example.com/").unwrap())
+                .as_str()
+        );
+        assert_eq!(
+            "https://www.example.com/?utm_source=blog&utm_medium=email",
+            template
+                .tag_url(&Url::parse("https://www.example.com/?utm_source=blog").unwrap())
+                .as_str()
+        );
+    }
+
+    #[test]
+    fn test_merge_tags() {
+        let template = template();
+
+        assert_eq!(
+            vec!["marketing".to_string(), "launch".to_string()],
+            template.merge_tags(&["launch".to_string(), "marketing".to_string()])
+        );
+    }
+}
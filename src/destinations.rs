@@ -1,10 +1,19 @@
 //! Destinations
 
+use std::collections::BTreeMap;
+
+use chrono::naive::NaiveDate;
 use chrono::naive::NaiveDateTime;
 use uuid::Uuid;
 
+use crate::approval::ApprovalStatus;
+
+/// Number of numbered alternatives for a taken slug
+const SLUG_ALTERNATIVES: u32 = 3;
+
 /// Destination in all its glory
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)] // the flags of a destination
 pub struct Destination {
     /// Destination ID
     pub id: Uuid,
@@ -16,12 +25,48 @@ pub struct Destination {
     /// External identifier for the root
     pub slug: String,
 
+    /// Domain the slug is served on, all domains when not set
+    pub domain: Option<String>,
+
     /// Location where the destination goes
     pub url: String,
 
     /// Type of destination
     pub is_permanent: bool,
 
+    /// Redirect with a HTML page with a meta refresh, instead of an HTTP redirect
+    pub is_meta_refresh: bool,
+
+    /// Only redirect with a valid signed link
+    pub is_private: bool,
+
+    /// Serve the stats publicly, at `/<slug>+stats`
+    pub is_stats_public: bool,
+
+    /// Never record the hits, like for privacy-sensitive internal links
+    pub is_untracked: bool,
+
+    /// Open Graph title, shown when the short link is shared
+    pub og_title: Option<String>,
+
+    /// Open Graph description, shown when the short link is shared
+    pub og_description: Option<String>,
+
+    /// Open Graph image URL, shown when the short link is shared
+    pub og_image: Option<String>,
+
+    /// Script deciding the URL to redirect to, based on the request
+    pub script: Option<String>,
+
+    /// Extra headers of the redirect, a JSON object of names and values
+    pub headers: Option<String>,
+
+    /// Tags to group destinations by
+    pub tags: Vec<String>,
+
+    /// Channel the short link is handed out in, like `print-flyer`
+    pub source: Option<String>,
+
     /// Creation date
     pub created_at: NaiveDateTime,
 
@@ -30,11 +75,191 @@ pub struct Destination {
 
     /// Soft-deleted at
     pub deleted_at: Option<NaiveDateTime>,
+
+    /// Disabled at, when the [reputation](crate::reputation) of its URL flagged it as unsafe
+    pub flagged_at: Option<NaiveDateTime>,
+
+    /// Why the URL is flagged as unsafe, like `Listed on the blocklist`
+    pub flagged_reason: Option<String>,
+
+    /// Submitted for [approval](crate::approval) at, `None` when created without
+    pub submitted_at: Option<NaiveDateTime>,
+
+    /// Approved by an admin at
+    pub approved_at: Option<NaiveDateTime>,
+
+    /// Rejected by an admin at
+    pub rejected_at: Option<NaiveDateTime>,
+
+    /// The admin that approved or rejected the destination
+    pub reviewed_by: Option<Uuid>,
 }
 
 impl Destination {
     /// Is the destination soft-deleted?
+    #[must_use]
     pub fn is_deleted(&self) -> bool {
         self.deleted_at.is_some()
     }
+
+    /// Is the destination disabled, with its URL flagged as unsafe?
+    #[must_use]
+    pub fn is_flagged(&self) -> bool {
+        self.flagged_at.is_some()
+    }
+
+    /// Status of the approval of the destination, approved when created without
+    #[must_use]
+    pub fn approval_status(&self) -> ApprovalStatus {
+        if self.rejected_at.is_some() {
+            ApprovalStatus::Rejected
+        } else if self.submitted_at.is_some() && self.approved_at.is_none() {
+            ApprovalStatus::Pending
+        } else {
+            ApprovalStatus::Approved
+        }
+    }
+
+    /// Does the destination resolve at the root, once approved?
+    #[must_use]
+    pub fn is_approved(&self) -> bool {
+        self.approval_status() == ApprovalStatus::Approved
+    }
+
+    /// Does the destination have any Open Graph metadata?
+    #[must_use]
+    pub fn has_open_graph(&self) -> bool {
+        self.og_title.is_some() || self.og_description.is_some() || self.og_image.is_some()
+    }
+
+    /// The extra headers of the redirect, by name, empty when none are set
+    #[must_use]
+    pub fn extra_headers(&self) -> BTreeMap<String, String> {
+        self.headers
+            .as_deref()
+            .and_then(|headers| serde_json::from_str(headers).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Normalize the tags of a destination, trimmed and without duplicates
+///
+/// Returns `None` when a tag is empty
+pub fn normalize_tags(tags: &[String]) -> Option<Vec<String>> {
+    let mut normalized_tags: Vec<String> = Vec::with_capacity(tags.len());
+
+    for tag in tags {
+        let tag = tag.trim();
+
+        if tag.is_empty() {
+            return None;
+        }
+
+        if !normalized_tags
+            .iter()
+            .any(|normalized_tag| normalized_tag == tag)
+        {
+            normalized_tags.push(tag.to_string());
+        }
+    }
+
+    Some(normalized_tags)
+}
+
+/// Candidate alternatives for a taken slug, like `launch-2` and `launch-2025` for `launch`
+///
+/// A slug already ending with a number counts up from there, `launch-2` gives `launch-3`
+#[must_use]
+pub fn slug_alternatives(slug: &str, year: i32) -> Vec<String> {
+    let (base, number) = slug
+        .rsplit_once('-')
+        .filter(|(base, number)| !base.is_empty() && !number.starts_with('0'))
+        .and_then(|(base, number)| Some((base, number.parse::<u16>().ok()?)))
+        .unwrap_or((slug, 1));
+
+    let mut alternatives = (1..=SLUG_ALTERNATIVES)
+        .map(|offset| format!("{base}-{}", u32::from(number) + offset))
+        .collect::<Vec<_>>();
+
+    if !slug.ends_with(&format!("-{year}")) {
+        alternatives.push(format!("{slug}-{year}"));
+    }
+
+    alternatives
+}
+
+/// Number of hits of a destination on a single day
+#[derive(Clone, Debug)]
+pub struct DailyHits {
+    /// The day
+    pub day: NaiveDate,
+
+    /// Number of hits on the day
+    pub hits: i64,
+}
+
+/// Number of hits of a destination from a referrer
+#[derive(Clone, Debug)]
+pub struct ReferrerHits {
+    /// Host of the referring page
+    pub referrer: String,
+
+    /// Number of hits from the referrer
+    pub hits: i64,
+}
+
+/// Number of hits of the destinations of a source
+#[derive(Clone, Debug)]
+pub struct SourceHits {
+    /// The source, `None` for the destinations without a source
+    pub source: Option<String>,
+
+    /// Number of destinations with the source
+    pub destinations: i64,
+
+    /// Number of hits of the destinations
+    pub hits: i64,
+}
+
+/// Number of hits of a destination in an hour of a weekday
+#[derive(Clone, Debug)]
+pub struct HourlyHits {
+    /// Day of the week, from 1 for Monday to 7 for Sunday
+    pub weekday: i32,
+
+    /// Hour of the day, from 0 to 23
+    pub hour: i32,
+
+    /// Number of hits in the hour
+    pub hits: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slug_alternatives() {
+        assert_eq!(
+            vec!["launch-2", "launch-3", "launch-4", "launch-2026"],
+            slug_alternatives("launch", 2026)
+        );
+        assert_eq!(
+            vec!["launch-3", "launch-4", "launch-5", "launch-2-2026"],
+            slug_alternatives("launch-2", 2026)
+        );
+        assert_eq!(
+            vec!["launch-2027", "launch-2028", "launch-2029"],
+            slug_alternatives("launch-2026", 2026)
+        );
+        assert_eq!(
+            vec![
+                "agent-007-2",
+                "agent-007-3",
+                "agent-007-4",
+                "agent-007-2026"
+            ],
+            slug_alternatives("agent-007", 2026)
+        );
+    }
 }
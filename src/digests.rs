@@ -0,0 +1,271 @@
+//! Weekly digests of the traffic of the destinations of users
+//!
+//! Users with an email address subscribe via `PUT /api/users/me/digest`. The `send-digests` job
+//! sends the digest to every subscriber that did not get one in the last week, via the
+//! [notifier](crate::notifier); the job is enabled with the notifier. The digest covers the last
+//! seven full days, compared with the seven days before, based on the rollups of the hits:
+//!
+//! - the hits of all destinations of the user, with the trend
+//! - the destinations with the most hits, with their trends
+//! - for admins, the new slugs without a destination that visitors tried, most tried first
+//!
+//! Slugs without a destination are only recorded while the notifier is enabled, they are
+//! forgotten after 30 days without visits.
+
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::notifier::Notification;
+use crate::notifier::Notifier;
+use crate::users::Role;
+use crate::users::User;
+
+/// Number of destinations with the most hits in a digest
+const TOP_DESTINATIONS: i64 = 5;
+
+/// Number of new missed slugs in a digest
+const NEW_MISSED_SLUGS: i64 = 10;
+
+/// Number of days missed slugs without visits are kept
+pub const MISSED_SLUG_RETENTION_DAYS: i32 = 30;
+
+/// Maximum length of a recorded missed slug, longer ones are not worth a digest
+pub const MAX_MISSED_SLUG_LENGTH: usize = 255;
+
+/// A subscription to the weekly digest
+#[derive(Clone, Debug)]
+pub struct DigestSubscription {
+    /// The subscribed user
+    pub user_id: Uuid,
+
+    /// Moment the last digest is sent, `None` before the first one
+    pub last_sent_at: Option<NaiveDateTime>,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+}
+
+/// Hits of a destination in the digest
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestDestination {
+    /// Slug of the destination
+    pub slug: String,
+
+    /// Domain of the destination, all domains when empty
+    pub domain: Option<String>,
+
+    /// Hits in the last seven days
+    pub hits: i64,
+
+    /// Hits in the seven days before
+    pub previous_hits: i64,
+}
+
+/// A slug without a destination, that visitors tried
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MissedSlug {
+    /// The missed slug
+    pub slug: String,
+
+    /// Domain it is tried on, all domains when empty
+    pub domain: Option<String>,
+
+    /// Number of tries
+    pub hits: i64,
+
+    /// First try
+    pub first_seen_at: NaiveDateTime,
+}
+
+/// The digest of a user
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Digest {
+    /// Hits of all destinations in the last seven days
+    pub hits: i64,
+
+    /// Hits of all destinations in the seven days before
+    pub previous_hits: i64,
+
+    /// The destinations with the most hits in the last seven days
+    pub top_destinations: Vec<DigestDestination>,
+
+    /// New missed slugs of the last seven days, only for admins
+    pub missed_slugs: Vec<MissedSlug>,
+}
+
+impl Digest {
+    /// Build the digest of the user
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the hits could not be counted
+    pub async fn build(database: &Database, user: &User) -> anyhow::Result<Self> {
+        let (hits, previous_hits) = database.count_digest_hits(user).await?;
+
+        let top_destinations = database
+            .find_top_digest_destinations(user, TOP_DESTINATIONS)
+            .await?;
+
+        let missed_slugs = if matches!(user.role, Role::Admin) {
+            database.find_new_missed_slugs(NEW_MISSED_SLUGS).await?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            hits,
+            previous_hits,
+            top_destinations,
+            missed_slugs,
+        })
+    }
+
+    /// The digest as plain text, for the email
+    pub fn render(&self) -> String {
+        let mut lines = vec![format!(
+            "Your destinations got {} hit(s) in the last seven days ({}).",
+            self.hits,
+            trend(self.hits, self.previous_hits)
+        )];
+
+        if !self.top_destinations.is_empty() {
+            lines.push(String::new());
+            lines.push("Most visited:".to_string());
+
+            for destination in &self.top_destinations {
+                lines.push(format!(
+                    "- {}: {} hit(s) ({})",
+                    display_slug(destination.domain.as_deref(), &destination.slug),
+                    destination.hits,
+                    trend(destination.hits, destination.previous_hits)
+                ));
+            }
+        }
+
+        if !self.missed_slugs.is_empty() {
+            lines.push(String::new());
+            lines.push("New slugs without a destination:".to_string());
+
+            for missed_slug in &self.missed_slugs {
+                lines.push(format!(
+                    "- {}: tried {} time(s)",
+                    display_slug(missed_slug.domain.as_deref(), &missed_slug.slug),
+                    missed_slug.hits
+                ));
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Outcome of sending the digests
+#[derive(Clone, Debug, Default)]
+pub struct Sent {
+    /// Number of sent digests
+    pub digests: usize,
+
+    /// Number of forgotten missed slugs
+    pub missed_slugs: u64,
+}
+
+impl Sent {
+    /// Description of the sending, for the status of the job
+    pub fn describe(&self) -> String {
+        format!(
+            "Sent {} digest(s), forgot {} missed slug(s)",
+            self.digests, self.missed_slugs
+        )
+    }
+}
+
+/// Send the digests that are due, and forget the missed slugs without recent visits
+///
+/// # Errors
+///
+/// Will return `Err` when the digests could not be built or marked as sent
+pub async fn run(database: &Database, notifier: &Notifier) -> anyhow::Result<Sent> {
+    let mut sent = Sent::default();
+
+    for user in database.find_users_with_due_digests().await? {
+        let digest = Digest::build(database, &user).await?;
+
+        notifier.notify(
+            &user,
+            &Notification::Digest {
+                content: digest.render(),
+            },
+        );
+
+        database.mark_digest_sent(&user).await?;
+
+        sent.digests += 1;
+    }
+
+    sent.missed_slugs = database
+        .prune_missed_slugs(MISSED_SLUG_RETENTION_DAYS)
+        .await?;
+
+    Ok(sent)
+}
+
+/// The slug as visitors see it, with its domain when it has one
+fn display_slug(domain: Option<&str>, slug: &str) -> String {
+    match domain {
+        Some(domain) => format!("{domain}/{slug}"),
+        None => format!("/{slug}"),
+    }
+}
+
+/// The trend of the hits compared to the previous hits, like `+25%`
+fn trend(hits: i64, previous_hits: i64) -> String {
+    if previous_hits == 0 {
+        return if hits == 0 { "no change" } else { "new" }.to_string();
+    }
+
+    #[allow(clippy::cast_precision_loss)] // a percentage, precise enough
+    let change = (hits - previous_hits) as f64 / previous_hits as f64 * 100.0;
+
+    format!("{change:+.0}%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trend() {
+        assert_eq!("no change", trend(0, 0));
+        assert_eq!("new", trend(12, 0));
+        assert_eq!("+25%", trend(5, 4));
+        assert_eq!("-50%", trend(2, 4));
+        assert_eq!("+0%", trend(4, 4));
+        assert_eq!("-100%", trend(0, 4));
+    }
+
+    #[test]
+    fn test_render() {
+        let digest = Digest {
+            hits: 6,
+            previous_hits: 4,
+            top_destinations: vec![DigestDestination {
+                slug: "summer".to_string(),
+                domain: Some("example.com".to_string()),
+                hits: 6,
+                previous_hits: 4,
+            }],
+            missed_slugs: vec![],
+        };
+
+        assert_eq!(
+            "Your destinations got 6 hit(s) in the last seven days (+50%).\n\nMost visited:\n\
+            - example.com/summer: 6 hit(s) (+50%)",
+            digest.render()
+        );
+    }
+}
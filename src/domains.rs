@@ -0,0 +1,97 @@
+//! Domains
+//!
+//! One instance can serve multiple hostnames, each with its own namespace of slugs.
+//! Destinations without a domain are served on all domains, unless a domain has its own
+//! destination with the same slug.
+//!
+//! Domains can be registered, with their own defaults for slugs without a destination.
+
+use axum::http::header::HOST;
+use axum::http::uri::Authority;
+use axum::http::HeaderMap;
+use chrono::naive::NaiveDateTime;
+use uuid::Uuid;
+
+/// A registered domain, with its defaults
+#[derive(Clone, Debug)]
+pub struct Domain {
+    /// Domain ID
+    pub id: Uuid,
+
+    /// The ID of the user that registered it
+    #[allow(dead_code)] // used by sqlx
+    pub user_id: Uuid,
+
+    /// Hostname of the domain, normalized
+    pub hostname: String,
+
+    /// Redirect slugs without a destination to this URL, instead of the 404 page
+    pub fallback_url: Option<String>,
+
+    /// HTML of the 404 page of the domain, instead of the global 404 page
+    pub not_found_template: Option<String>,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+
+    /// Last updated at
+    pub updated_at: NaiveDateTime,
+
+    /// Soft-deleted at
+    pub deleted_at: Option<NaiveDateTime>,
+}
+
+impl Domain {
+    /// Is the domain soft-deleted?
+    #[must_use]
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+}
+
+/// The domain of the request, based on the `Host` header
+///
+/// The port is not part of the domain
+pub fn from_headers(headers: &HeaderMap) -> Option<String> {
+    let host = headers.get(HOST)?.to_str().ok()?;
+
+    let authority = host.parse::<Authority>().ok()?;
+
+    Some(authority.host().to_lowercase())
+}
+
+/// Normalize a domain, like one provided by a user
+///
+/// Returns `None` when the domain is not a valid hostname
+pub fn normalize(domain: &str) -> Option<String> {
+    let host = url::Host::parse(domain.trim()).ok()?;
+
+    Some(host.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(None, from_headers(&headers));
+
+        headers.insert(HOST, HeaderValue::from_static("Go.Acme.com:7000"));
+        assert_eq!(Some("go.acme.com".to_string()), from_headers(&headers));
+
+        headers.insert(HOST, HeaderValue::from_static("[::1]:7000"));
+        assert_eq!(Some("[::1]".to_string()), from_headers(&headers));
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(Some("go.acme.com".to_string()), normalize("GO.acme.com"));
+        assert_eq!(Some("xn--bcher-kva.ch".to_string()), normalize("bücher.ch"));
+        assert_eq!(None, normalize("go.acme.com/path"));
+        assert_eq!(None, normalize(""));
+    }
+}
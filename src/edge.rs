@@ -0,0 +1,194 @@
+//! Snapshots of the slugs for edge workers, like Cloudflare Workers, serving redirects at the edge
+//!
+//! A snapshot maps every slug to its URL, exported with `shurly export-edge snapshot.json` or
+//! `GET /api/edge/snapshot` with the key of the edge, see [`ingest`](crate::ingest). Shurly stays
+//! the source of truth: slugs not in the snapshot are left to Shurly, and the edges report their
+//! hits back with `POST /api/hits/ingest`.
+//!
+//! ```json
+//! {
+//!     "version": "2026-10-15T12:00:00Z",
+//!     "isFull": true,
+//!     "destinations": [{ "slug": "launch", "url": "https://www.example.com/", "isPermanent": false }],
+//!     "removed": []
+//! }
+//! ```
+//!
+//! Passing the `version` of the last snapshot as `since` gets the changes only: the changed
+//! destinations, and the removed slugs the edge should forward to Shurly again. Changes can be
+//! sent more than once. When the changes are no longer known, because the deleted destinations
+//! are purged since, a full snapshot is sent instead.
+//!
+//! Only destinations that are a plain redirect are in the snapshot: private destinations,
+//! meta refreshes, scripts and Open Graph metadata need Shurly itself.
+
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+
+use anyhow::Result;
+use chrono::DateTime;
+use chrono::TimeDelta;
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::database::Database;
+use crate::destinations::Destination;
+
+/// Overlap of the changes with the previous snapshot, for the changes that were saved while it
+/// was created
+const OVERLAP: TimeDelta = TimeDelta::minutes(1);
+
+/// The slugs of an instance, or the changes since a previous snapshot
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    /// Version of the snapshot, pass it as `since` for the next changes
+    pub version: DateTime<Utc>,
+
+    /// Is this a full snapshot, replacing the previous?
+    pub is_full: bool,
+
+    /// The destinations served at the edge, changed ones only when not full
+    pub destinations: Vec<EdgeDestination>,
+
+    /// Slugs no longer served at the edge, always empty when full
+    pub removed: Vec<RemovedSlug>,
+}
+
+/// A destination served at the edge
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EdgeDestination {
+    /// Slug of the destination
+    pub slug: String,
+
+    /// Domain the slug is served on, all domains when not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+
+    /// Location to redirect to
+    pub url: String,
+
+    /// Redirect permanently
+    pub is_permanent: bool,
+
+    /// Extra headers of the redirect
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
+
+/// A slug no longer served at the edge
+#[derive(Debug, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemovedSlug {
+    /// The slug
+    pub slug: String,
+
+    /// Domain the slug was served on, all domains when not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+}
+
+impl Snapshot {
+    /// Create a snapshot of all slugs, or of the changes since the version of a previous
+    /// snapshot
+    ///
+    /// A full snapshot is created when the previous is older than the retention of deleted
+    /// destinations, in days
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the destinations could not be found
+    pub async fn create(
+        database: &Database,
+        since: Option<DateTime<Utc>>,
+        deleted_retention_days: Option<i32>,
+    ) -> Result<Self> {
+        let version = Utc::now();
+
+        let since = since.filter(|since| {
+            deleted_retention_days
+                .is_none_or(|days| *since > version - TimeDelta::days(i64::from(days)) + OVERLAP)
+        });
+
+        let Some(since) = since else {
+            let destinations = database
+                .find_all_destinations()
+                .await?
+                .into_iter()
+                .filter(is_served_at_edge)
+                .map(EdgeDestination::from_destination)
+                .collect();
+
+            return Ok(Self {
+                version,
+                is_full: true,
+                destinations,
+                removed: Vec::new(),
+            });
+        };
+
+        let (served, removed): (Vec<_>, Vec<_>) = database
+            .find_changed_destinations((since - OVERLAP).naive_utc())
+            .await?
+            .into_iter()
+            .partition(is_served_at_edge);
+
+        let destinations = served
+            .into_iter()
+            .map(EdgeDestination::from_destination)
+            .collect::<Vec<_>>();
+
+        // a slug can be deleted and taken again, the served destination wins
+        let served_slugs = destinations
+            .iter()
+            .map(|destination| (destination.domain.as_deref(), destination.slug.as_str()))
+            .collect::<HashSet<_>>();
+
+        let mut removed_slugs = HashSet::new();
+        let removed = removed
+            .into_iter()
+            .filter(|destination| {
+                !served_slugs.contains(&(destination.domain.as_deref(), destination.slug.as_str()))
+            })
+            .map(|destination| RemovedSlug {
+                slug: destination.slug,
+                domain: destination.domain,
+            })
+            .filter(|removed| removed_slugs.insert((removed.domain.clone(), removed.slug.clone())))
+            .collect();
+
+        Ok(Self {
+            version,
+            is_full: false,
+            destinations,
+            removed,
+        })
+    }
+}
+
+impl EdgeDestination {
+    /// The destination, as served at the edge
+    fn from_destination(destination: Destination) -> Self {
+        let headers = destination.extra_headers();
+
+        Self {
+            slug: destination.slug,
+            domain: destination.domain,
+            url: destination.url,
+            is_permanent: destination.is_permanent,
+            headers,
+        }
+    }
+}
+
+/// Can the edge serve the destination, as a plain redirect?
+fn is_served_at_edge(destination: &Destination) -> bool {
+    !destination.is_deleted()
+        && !destination.is_flagged()
+        && destination.is_approved()
+        && !destination.is_private
+        && !destination.is_meta_refresh
+        && destination.script.is_none()
+        && !destination.has_open_graph()
+}
@@ -1,6 +1,12 @@
 //! Graceful shutdown
 
+use std::sync::LazyLock;
+
 use tokio::signal;
+use tokio::sync::watch;
+
+/// Is the graceful shutdown started? For the responses that never end by themselves
+static STARTED: LazyLock<watch::Sender<bool>> = LazyLock::new(|| watch::channel(false).0);
 
 /// Handler for graceful shutdown
 ///
@@ -27,4 +33,14 @@ pub async fn handler() {
     }
 
     tracing::info!("Terminate signal received, starting graceful shutdown");
+
+    STARTED.send_replace(true);
+}
+
+/// Wait for the graceful shutdown to start
+///
+/// Long-lived responses, like event streams, should end by then to let the shutdown complete
+pub async fn started() {
+    // the sender is never dropped, waiting can not fail
+    STARTED.subscribe().wait_for(|started| *started).await.ok();
 }
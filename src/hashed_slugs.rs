@@ -0,0 +1,108 @@
+//! Slugs derived from the URL of new destinations
+//!
+//! With `HASHED_SLUG_LENGTH`, destinations created without a slug get a slug derived from the
+//! SHA-256 hash of their URL, like `k3x9a2b`. Shortening the same URL again, on the same domain,
+//! gives the same destination back, without looking up the URL first. When the slug is taken by
+//! another URL, or by a deleted destination, the next character of the hash is added.
+//!
+//! The slugs consist of digits and lowercase letters, without the `i`, `l`, `o` and `u` that are
+//! easily confused.
+
+use sha2::Digest;
+use sha2::Sha256;
+use url::Url;
+
+use crate::utils::env_var_optional;
+
+/// Characters of the slugs, one for every 5 bits
+const ALPHABET: &[u8; 32] = b"0123456789abcdefghjkmnpqrstvwxyz";
+
+/// Minimum length of the slugs, shorter ones collide too often
+const MIN_LENGTH: usize = 4;
+
+/// Maximum length of the slugs, a character per byte of the hash
+const MAX_LENGTH: usize = 32;
+
+/// Slugs derived from the URL, disabled by default
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HashedSlugs {
+    /// Length of the slugs, disabled when not set
+    length: Option<usize>,
+}
+
+impl HashedSlugs {
+    /// Derive slugs of the length
+    pub fn new(length: usize) -> Self {
+        Self {
+            length: Some(length),
+        }
+    }
+
+    /// Setup the slugs based on the `HASHED_SLUG_LENGTH` environment variable
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the length is not a number from 4 to 32
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let length = env_var_optional("HASHED_SLUG_LENGTH")
+            .map(|value| match value.parse::<usize>() {
+                Ok(length) if (MIN_LENGTH..=MAX_LENGTH).contains(&length) => Ok(length),
+                _ => Err(anyhow::anyhow!(
+                    "Invalid HASHED_SLUG_LENGTH: {value}, expected {MIN_LENGTH} to {MAX_LENGTH}"
+                )),
+            })
+            .transpose()?;
+
+        Ok(Self { length })
+    }
+
+    /// Are slugs derived from the URL?
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.length.is_some()
+    }
+
+    /// The slugs of the URL, from the shortest to the longest, none when disabled
+    pub fn candidates(&self, url: &Url) -> impl Iterator<Item = String> {
+        let hash = Sha256::digest(url.as_str().as_bytes())
+            .iter()
+            .map(|byte| char::from(ALPHABET[usize::from(byte & 31)]))
+            .collect::<String>();
+
+        let lengths = self.length.map_or(0..0, |length| length..MAX_LENGTH + 1);
+
+        lengths.map(move |length| hash[..length].to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates() {
+        let url = Url::parse("https://www.example.com/").unwrap();
+
+        assert_eq!(0, HashedSlugs::default().candidates(&url).count());
+
+        let candidates = HashedSlugs::new(7).candidates(&url).collect::<Vec<_>>();
+        assert_eq!(26, candidates.len());
+        assert_eq!(7, candidates[0].len());
+        assert!(candidates[1].starts_with(&candidates[0]));
+        assert!(candidates[0]
+            .chars()
+            .all(|ch| ch.is_ascii_digit() || ch.is_ascii_lowercase()));
+
+        // the same URL, the same slugs
+        assert_eq!(
+            candidates,
+            HashedSlugs::new(7).candidates(&url).collect::<Vec<_>>()
+        );
+
+        let other = Url::parse("https://www.example.com/other").unwrap();
+        assert_ne!(
+            candidates[0],
+            HashedSlugs::new(7).candidates(&other).next().unwrap()
+        );
+    }
+}
@@ -0,0 +1,76 @@
+//! Health and readiness of Shurly
+//!
+//! Meant for the probes of Kubernetes and the health checks of load balancers, both are handled
+//! before the root so they never end up as a slug lookup.
+//!
+//! - `/healthz`, Shurly is alive and handles requests
+//! - `/readyz`, Shurly can serve redirects: the database is reachable, all migrations of this
+//!   version of Shurly are applied, the root is not degraded and the hit queue is not full
+
+use axum::http::header::CACHE_CONTROL;
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Extension;
+
+use crate::database::Database;
+use crate::RootSettings;
+
+/// Probes should always see the current state, never a cached one
+const HEALTH_CACHE_CONTROL: &str = "no-store";
+
+/// Is Shurly alive
+///
+/// Request:
+/// ```sh
+/// curl -v http://localhost:7000/healthz
+/// ```
+pub async fn healthz() -> impl IntoResponse {
+    response(StatusCode::OK, "OK")
+}
+
+/// Is Shurly ready to serve redirects
+///
+/// Responds with a `503 Service Unavailable` when the database is unreachable or migrations are
+/// missing, like when another instance is still running them. Also when the root is degraded or
+/// the hit queue is full, see [`hit_buffer`](crate::hit_buffer), so traffic moves to healthy
+/// instances
+///
+/// Request:
+/// ```sh
+/// curl -v http://localhost:7000/readyz
+/// ```
+pub async fn readyz(
+    Extension(database): Extension<Database>,
+    Extension(root_settings): Extension<RootSettings>,
+) -> impl IntoResponse {
+    if root_settings.hits.is_saturated() {
+        return response(StatusCode::SERVICE_UNAVAILABLE, "Hit queue is full");
+    }
+
+    if root_settings.hits.degraded().is_degraded() {
+        return response(StatusCode::SERVICE_UNAVAILABLE, "Database is degraded");
+    }
+
+    match database.has_current_migrations().await {
+        Ok(true) => response(StatusCode::OK, "OK"),
+        Ok(false) => response(StatusCode::SERVICE_UNAVAILABLE, "Migrations are missing"),
+        Err(err) => {
+            tracing::error!("Database is not ready: {err}");
+
+            response(StatusCode::SERVICE_UNAVAILABLE, "Database is unreachable")
+        }
+    }
+}
+
+/// Plain text response of a probe
+fn response(status_code: StatusCode, body: &'static str) -> impl IntoResponse {
+    (
+        status_code,
+        [
+            (CONTENT_TYPE, "text/plain; charset=utf-8"),
+            (CACHE_CONTROL, HEALTH_CACHE_CONTROL),
+        ],
+        body,
+    )
+}
@@ -0,0 +1,242 @@
+//! Write-behind buffer of the hits of the root
+//!
+//! By default every hit is saved as part of its request. On busy instances the hits can be
+//! buffered in memory instead, and saved in bulk every `HIT_FLUSH_INTERVAL` milliseconds or every
+//! `HIT_FLUSH_SIZE` hits, whichever comes first. The buffer is saved on a graceful shutdown, the
+//! hits of at most a single flush are lost when an instance crashes.
+//!
+//! Hits that can not be saved, because the database is unreachable, are queued and saved later on;
+//! the root is degraded meanwhile, see [`degraded`](crate::degraded). At most
+//! [`MAX_QUEUED_HITS`] hits are queued, later hits are lost until the database is back.
+
+use std::mem;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+use chrono::SubsecRound;
+use chrono::Utc;
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::degraded::Degraded;
+use crate::destinations::Destination;
+use crate::utils::env_var_optional;
+use crate::utils::parse_positive;
+
+/// Default number of buffered hits before they are saved
+const DEFAULT_FLUSH_SIZE: usize = 1000;
+
+/// Maximum number of hits waiting to be saved, while the database is unreachable
+pub const MAX_QUEUED_HITS: usize = 100_000;
+
+/// Time between the attempts to save the queued hits, without buffering
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A hit waiting to be saved
+#[derive(Clone, Debug)]
+pub struct Hit {
+    /// Destination that is hit
+    pub destination_id: Uuid,
+
+    /// IP address of the visitor, when known and tracked
+    pub ip_address: Option<IpAddr>,
+
+    /// User agent of the visitor, when known and tracked
+    pub user_agent: Option<String>,
+
+    /// Host of the referring page, when known
+    pub referrer: Option<String>,
+
+    /// Moment of the hit
+    pub created_at: NaiveDateTime,
+}
+
+/// When to save the buffered hits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Flush {
+    /// Time between the saves
+    pub interval: Duration,
+
+    /// Number of hits that are saved right away
+    pub size: usize,
+}
+
+/// Buffer of the hits, saving every hit right away when disabled
+///
+/// Disabled by default, clones share the same buffer
+#[derive(Clone, Debug, Default)]
+pub struct HitBuffer {
+    /// When to save the buffered hits, `None` when buffering is disabled
+    flush: Option<Flush>,
+
+    /// The buffered hits
+    hits: Arc<Mutex<Vec<Hit>>>,
+
+    /// Wakes up the flushing when the buffer is full
+    is_full: Arc<Notify>,
+
+    /// Degraded mode of the root, entered when hits can not be saved
+    degraded: Degraded,
+}
+
+impl HitBuffer {
+    /// Create a buffer, saving the hits as configured
+    pub fn new(flush: Option<Flush>) -> Self {
+        Self {
+            flush,
+            ..Self::default()
+        }
+    }
+
+    /// Setup the buffer based on the `HIT_FLUSH_INTERVAL` and `HIT_FLUSH_SIZE` environment
+    /// variables
+    ///
+    /// Buffering is disabled without a `HIT_FLUSH_INTERVAL`, the size defaults to 1000 hits
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the interval or size are not positive numbers
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let Some(interval) = env_var_optional("HIT_FLUSH_INTERVAL") else {
+            return Ok(Self::default());
+        };
+
+        let interval = Duration::from_millis(parse_positive("HIT_FLUSH_INTERVAL", &interval)?);
+
+        let size = env_var_optional("HIT_FLUSH_SIZE")
+            .map(|size| parse_positive("HIT_FLUSH_SIZE", &size))
+            .transpose()?
+            .map_or(DEFAULT_FLUSH_SIZE, |size| {
+                usize::try_from(size).unwrap_or(usize::MAX)
+            });
+
+        Ok(Self::new(Some(Flush { interval, size })))
+    }
+
+    /// Save a hit on the destination, or buffer it when buffering is enabled
+    ///
+    /// A hit that can not be saved right away is queued and the root is degraded, while degraded
+    /// every hit is queued
+    pub async fn save(
+        &self,
+        database: &Database,
+        destination: &Destination,
+        ip_address: Option<&IpAddr>,
+        user_agent: Option<&String>,
+        referrer: Option<&str>,
+    ) {
+        let hit = Hit {
+            destination_id: destination.id,
+            ip_address: ip_address.copied(),
+            user_agent: user_agent.cloned(),
+            referrer: referrer.map(ToString::to_string),
+            // the database stores up to microseconds
+            created_at: Utc::now().naive_utc().trunc_subsecs(6),
+        };
+
+        if self.flush.is_none() && !self.degraded.is_degraded() {
+            let Err(err) = database
+                .save_hit(destination, ip_address, user_agent, referrer)
+                .await
+            else {
+                return;
+            };
+
+            self.degraded.enter(&err);
+        }
+
+        let buffered = self.queue(vec![hit]);
+
+        if self.flush.is_some_and(|flush| buffered >= flush.size) {
+            self.is_full.notify_one();
+        }
+    }
+
+    /// Save the buffered and queued hits
+    ///
+    /// The hits are queued again when they can not be saved, saving them leaves degraded mode
+    pub async fn flush(&self, database: &Database) {
+        let hits = mem::take(&mut *self.hits.lock().expect("Valid hit buffer lock"));
+
+        if hits.is_empty() {
+            return;
+        }
+
+        if let Err(err) = database.save_hits(&hits).await {
+            tracing::error!("Could not save {} buffered hit(s): {err}", hits.len());
+
+            self.degraded.enter(&err);
+            self.queue(hits);
+        } else {
+            tracing::debug!("Saved {} buffered hit(s)", hits.len());
+
+            self.degraded.leave();
+        }
+    }
+
+    /// Save the buffered and queued hits in the background, until Shurly stops
+    ///
+    /// Without buffering, only the hits queued while degraded are saved
+    pub fn start(&self, database: &Database) {
+        let interval = self.flush.map_or(RETRY_INTERVAL, |flush| flush.interval);
+
+        let buffer = self.clone();
+        let database = database.clone();
+
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    () = tokio::time::sleep(interval) => {},
+                    () = buffer.is_full.notified() => {},
+                }
+
+                buffer.flush(&database).await;
+            }
+        });
+    }
+
+    /// Number of hits waiting to be saved, buffered or queued
+    pub fn queued(&self) -> usize {
+        self.hits.lock().expect("Valid hit buffer lock").len()
+    }
+
+    /// Is the queue full, later hits are lost
+    pub fn is_saturated(&self) -> bool {
+        self.queued() >= MAX_QUEUED_HITS
+    }
+
+    /// Degraded mode of the root
+    pub fn degraded(&self) -> &Degraded {
+        &self.degraded
+    }
+
+    /// Queue the hits, up to the maximum, the number of hits waiting
+    fn queue(&self, mut queued: Vec<Hit>) -> usize {
+        let mut hits = self.hits.lock().expect("Valid hit buffer lock");
+
+        let room = MAX_QUEUED_HITS.saturating_sub(hits.len());
+        if queued.len() > room {
+            tracing::error!("Hit queue is full, lost {} hit(s)", queued.len() - room);
+
+            queued.truncate(room);
+        }
+
+        hits.append(&mut queued);
+
+        hits.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default() {
+        assert_eq!(None, HitBuffer::default().flush);
+    }
+}
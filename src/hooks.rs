@@ -0,0 +1,125 @@
+//! Hooks in the redirect pipeline of the root
+//!
+//! Embedders of Shurly can add custom logic to the root without forking it, like injecting
+//! headers or allow/deny decisions. Hooks are registered on the settings of the root, before
+//! creating the router:
+//!
+//! ```rust
+//! let mut root_settings = shurly::setup_dependencies(&database).await?;
+//! root_settings.hooks = shurly::RedirectHooks::default().with(DenyBots);
+//!
+//! let limits = shurly::Limits::default();
+//! let app = shurly::create_router(database, root_settings, limits, None, shurly::Routes::All);
+//! ```
+//!
+//! Hooks run in the order they are registered, the first hook responding wins.
+
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use axum::http::HeaderMap;
+use axum::http::Method;
+use axum::http::Uri;
+use axum::response::Response;
+
+use crate::destinations::Destination;
+
+/// The request of the root, as seen by the hooks
+pub struct HookRequest<'a> {
+    /// Method of the request
+    pub method: &'a Method,
+
+    /// Headers of the request
+    pub headers: &'a HeaderMap,
+
+    /// URI of the request
+    pub uri: &'a Uri,
+
+    /// IP address of the visitor, when known
+    pub ip_address: Option<IpAddr>,
+
+    /// The decoded slug
+    pub slug: &'a str,
+
+    /// Domain of the request, from the `Host` header
+    pub domain: Option<&'a str>,
+}
+
+/// Custom logic in the redirect pipeline of the root
+///
+/// All hooks do nothing by default, implement the ones that are needed
+pub trait RedirectHook: Send + Sync {
+    /// Before the destination of the slug is looked up
+    ///
+    /// Return a response to send that instead, without a lookup
+    fn before_lookup(&self, _request: &HookRequest<'_>) -> Option<Response> {
+        None
+    }
+
+    /// After the destination of the slug is found, before the hit is recorded
+    ///
+    /// Return a response to send that instead, without recording a hit
+    fn after_resolve(
+        &self,
+        _request: &HookRequest<'_>,
+        _destination: &Destination,
+    ) -> Option<Response> {
+        None
+    }
+
+    /// Before redirecting to the destination, like to add headers to the redirect
+    fn before_redirect(
+        &self,
+        _request: &HookRequest<'_>,
+        _destination: &Destination,
+        _response: &mut Response,
+    ) {
+    }
+}
+
+/// The registered hooks, none by default
+#[derive(Clone, Default)]
+pub struct RedirectHooks {
+    /// The hooks, in order of registration
+    hooks: Vec<Arc<dyn RedirectHook>>,
+}
+
+impl RedirectHooks {
+    /// Register a hook, after the already registered hooks
+    #[must_use]
+    pub fn with(mut self, hook: impl RedirectHook + 'static) -> Self {
+        self.hooks.push(Arc::new(hook));
+
+        self
+    }
+
+    /// Run the hooks before the lookup, the first response wins
+    pub(crate) fn before_lookup(&self, request: &HookRequest<'_>) -> Option<Response> {
+        self.hooks
+            .iter()
+            .find_map(|hook| hook.before_lookup(request))
+    }
+
+    /// Run the hooks after the destination is found, the first response wins
+    pub(crate) fn after_resolve(
+        &self,
+        request: &HookRequest<'_>,
+        destination: &Destination,
+    ) -> Option<Response> {
+        self.hooks
+            .iter()
+            .find_map(|hook| hook.after_resolve(request, destination))
+    }
+
+    /// Run all hooks before redirecting
+    pub(crate) fn before_redirect(
+        &self,
+        request: &HookRequest<'_>,
+        destination: &Destination,
+        response: &mut Response,
+    ) {
+        for hook in &self.hooks {
+            hook.before_redirect(request, destination, response);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Internationalized domain names in the URLs of destinations
+//!
+//! URLs are normalized when parsed, their hostname is stored in punycode (`xn--...`), the form
+//! clients send along. The unicode form is shown next to it, so deceptive hostnames stand out.
+//! Hostnames with a [confusable](crate::confusables) label are handled with `URL_CONFUSABLES`.
+
+use url::Position;
+use url::Url;
+
+use crate::confusables;
+
+/// Prefix of the labels of a hostname in punycode
+const PUNYCODE_PREFIX: &str = "xn--";
+
+/// The URL with its hostname in unicode, as is when not an internationalized domain name
+pub fn unicode_url(url: &str) -> String {
+    let Ok(parsed_url) = Url::parse(url) else {
+        return url.to_string();
+    };
+
+    let Some(host) = parsed_url.host_str().filter(|host| is_punycode(host)) else {
+        return url.to_string();
+    };
+
+    let (unicode_host, _) = idna::domain_to_unicode(host);
+
+    format!(
+        "{}{unicode_host}{}",
+        &parsed_url[..Position::BeforeHost],
+        &parsed_url[Position::AfterHost..]
+    )
+}
+
+/// Does the hostname of the URL have a label confusable with Latin text?
+pub fn has_confusable_host(url: &Url) -> bool {
+    let Some(host) = url.host_str().filter(|host| is_punycode(host)) else {
+        return false;
+    };
+
+    let (unicode_host, _) = idna::domain_to_unicode(host);
+
+    unicode_host.split('.').any(confusables::is_confusable)
+}
+
+/// Does the hostname have a label in punycode?
+fn is_punycode(host: &str) -> bool {
+    host.split('.')
+        .any(|label| label.starts_with(PUNYCODE_PREFIX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unicode_url() {
+        assert_eq!(
+            "https://www.example.com/?q=1",
+            unicode_url("https://www.example.com/?q=1")
+        );
+        assert_eq!(
+            "https://münchen.de:8080/path?q=1",
+            unicode_url(
+                Url::parse("https://MÜNCHEN.de:8080/path?q=1")
+                    .unwrap()
+                    .as_str()
+            )
+        );
+    }
+
+    #[test]
+    fn test_has_confusable_host() {
+        let url = |url: &str| Url::parse(url).unwrap();
+
+        assert!(!has_confusable_host(&url("https://www.example.com/")));
+        assert!(!has_confusable_host(&url("https://münchen.de/")));
+        assert!(!has_confusable_host(&url("https://пример.рф/")));
+
+        // a Cyrillic `а` in a Latin label
+        assert!(has_confusable_host(&url("https://p\u{0430}ypal.com/")));
+
+        // Cyrillic lookalikes only
+        assert!(has_confusable_host(&url(
+            "https://\u{0441}\u{043e}\u{0440}.com/"
+        )));
+    }
+}
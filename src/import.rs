@@ -0,0 +1,667 @@
+//! Import of the links of other URL shorteners, like Bitly, YOURLS and Shlink
+//!
+//! Every link becomes a destination with the same slug and creation date. Links with a slug that
+//! is already taken are not imported, those are reported as conflicts together with the links
+//! that are invalid, like a link without a slug.
+//!
+//! The click counts of the links are kept as the hits of the day the link was created, or of
+//! yesterday for links created today. The hits themselves are not imported.
+
+use std::io::Read;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use chrono::DateTime;
+use chrono::Days;
+use chrono::NaiveDate;
+use chrono::NaiveDateTime;
+use chrono::Utc;
+
+use crate::api::parse_new_slug;
+use crate::api::parse_url;
+use crate::database::AuditEntry;
+use crate::database::CreateDestinationValues;
+use crate::database::Database;
+use crate::database::OpenGraphValues;
+use crate::destinations::Destination;
+use crate::slug_cache::SlugFoundCache;
+use crate::slug_policy::SlugPolicy;
+use crate::users::User;
+
+/// A link of another URL shortener, as found in the export, validated when imported
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExportedLink {
+    /// The short link or its slug, like `https://bit.ly/3xyz` or `3xyz`
+    pub short_link: String,
+
+    /// The URL the link redirects to
+    pub url: String,
+
+    /// Creation date of the link, like `2026-10-14T16:00:00+0000`
+    pub created_at: String,
+
+    /// Number of clicks on the link, when known
+    pub clicks: Option<String>,
+}
+
+/// A link that is not imported, and why
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    /// The short link or its slug
+    pub short_link: String,
+
+    /// Why the link is not imported, like `Slug already exists`
+    pub reason: String,
+}
+
+/// Outcome of an import
+#[derive(Debug, Default)]
+pub struct Report {
+    /// The imported destinations
+    pub destinations: Vec<Destination>,
+
+    /// The links that are not imported
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Import the links as destinations on the domain, all domains when not provided
+///
+/// Every imported destination is registered on the audit trail, as the importing user
+///
+/// # Errors
+///
+/// Will return `Err` when the database fails, the links imported so far are kept
+pub async fn import(
+    database: &Database,
+    links: &[ExportedLink],
+    imported_by: &User,
+    domain: Option<&str>,
+) -> Result<Report> {
+    let mut report = Report::default();
+
+    for link in links {
+        match import_link(database, link, imported_by, domain).await? {
+            Ok(destination) => report.destinations.push(destination),
+            Err(reason) => report.conflicts.push(Conflict {
+                short_link: link.short_link.clone(),
+                reason,
+            }),
+        }
+    }
+
+    if !report.destinations.is_empty() {
+        // running instances could have cached the slugs as missing
+        SlugFoundCache::default().flush(database).await;
+    }
+
+    Ok(report)
+}
+
+/// Import a single link, with the reason when it is not imported
+async fn import_link(
+    database: &Database,
+    link: &ExportedLink,
+    imported_by: &User,
+    domain: Option<&str>,
+) -> Result<Result<Destination, String>> {
+    let Some(slug) = slug_of_link(&link.short_link) else {
+        return Ok(Err("Link without a slug".to_string()));
+    };
+
+    // imported links keep their slugs, whatever the policy for new slugs
+    let slug = match parse_new_slug(&slug, &SlugPolicy::default()) {
+        Ok(slug) => slug,
+        Err(err) => return Ok(Err(err.to_string())),
+    };
+
+    let url = match parse_url(&link.url) {
+        Ok(url) => url,
+        Err(err) => return Ok(Err(err.to_string())),
+    };
+
+    let Some(created_at) = parse_created_at(&link.created_at) else {
+        return Ok(Err(format!("Invalid creation date: {}", link.created_at)));
+    };
+
+    let clicks = match link.clicks.as_deref().map(str::trim) {
+        None | Some("") => 0,
+        Some(clicks) => match clicks.parse::<i64>() {
+            Ok(clicks) if clicks >= 0 => clicks,
+            _ => return Ok(Err(format!("Invalid number of clicks: {clicks}"))),
+        },
+    };
+
+    if let Some(destination) = database
+        .find_single_destination_in_namespace(domain, &slug)
+        .await?
+    {
+        return Ok(Err(if destination.is_deleted() {
+            "Slug already exists and is deleted".to_string()
+        } else {
+            "Slug already exists".to_string()
+        }));
+    }
+
+    let values = CreateDestinationValues {
+        user: imported_by,
+        slug: &slug,
+        domain,
+        url: &url,
+        is_permanent: &false,
+        is_meta_refresh: &false,
+        is_private: &false,
+        is_stats_public: &false,
+        is_untracked: &false,
+        open_graph: OpenGraphValues {
+            title: None,
+            description: None,
+            image: None,
+        },
+        script: None,
+        headers: None,
+        tags: &[],
+        source: None,
+        is_pending: false,
+    };
+
+    let destination = database.import_destination(&values, &created_at).await?;
+
+    if clicks > 0 {
+        database
+            .import_hits(&destination, &day_of_clicks(&created_at), clicks)
+            .await?;
+    }
+
+    database
+        .register_audit_trail(
+            imported_by,
+            &AuditEntry::CreateDestination(&destination),
+            None,
+        )
+        .await?;
+
+    Ok(Ok(destination))
+}
+
+/// The day to keep the clicks on, the day of creation but never today
+///
+/// Today is still counted by the rollups of the hits, that would overwrite the clicks
+fn day_of_clicks(created_at: &NaiveDateTime) -> NaiveDate {
+    let yesterday = Utc::now()
+        .date_naive()
+        .checked_sub_days(Days::new(1))
+        .expect("Valid yesterday");
+
+    created_at.date().min(yesterday)
+}
+
+/// The slug of a short link, the path without its leading slash, or the slug itself
+fn slug_of_link(link: &str) -> Option<String> {
+    let link = link.trim();
+
+    let slug = match link.split_once("://") {
+        Some((_, link)) => link.split_once('/').map(|(_, slug)| slug)?,
+        None => link.split_once('/').map_or(link, |(_, slug)| slug),
+    };
+
+    Some(slug.trim_end_matches('/'))
+        .filter(|slug| !slug.is_empty())
+        .map(ToString::to_string)
+}
+
+/// Parse the creation date of a link, in the format of the API of Bitly, RFC 3339, like a
+/// database or just a date
+fn parse_created_at(created_at: &str) -> Option<NaiveDateTime> {
+    let created_at = created_at.trim();
+
+    DateTime::parse_from_str(created_at, "%Y-%m-%dT%H:%M:%S%z")
+        .or_else(|_| DateTime::parse_from_rfc3339(created_at))
+        .or_else(|_| DateTime::parse_from_str(created_at, "%Y-%m-%d %H:%M:%S%#z"))
+        .map(|created_at| created_at.naive_utc())
+        .ok()
+        .or_else(|| NaiveDateTime::parse_from_str(created_at, "%Y-%m-%d %H:%M:%S").ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(created_at, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+}
+
+/// A CSV export, with the columns found by the names of their headers
+pub struct Csv {
+    /// Headers in lowercase, without spaces and underscores
+    headers: Vec<String>,
+
+    /// The records
+    records: Vec<csv::StringRecord>,
+}
+
+impl Csv {
+    /// Read a CSV export with a header
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the CSV is invalid
+    pub fn from_reader(reader: impl Read) -> Result<Self> {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(reader);
+
+        let headers = reader
+            .headers()
+            .map_err(|err| anyhow!("Invalid CSV: {err}"))?
+            .iter()
+            .map(normalize_name)
+            .collect();
+
+        let records = reader
+            .records()
+            .collect::<Result<_, _>>()
+            .map_err(|err| anyhow!("Invalid CSV: {err}"))?;
+
+        Ok(Self { headers, records })
+    }
+
+    /// The index of the first column with one of the names, like `long_url` for `Long URL`
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when none of the columns is found
+    pub fn column(&self, names: &[&str]) -> Result<usize> {
+        self.optional_column(names)
+            .ok_or_else(|| anyhow!("Invalid CSV: missing the `{}` column", names[0]))
+    }
+
+    /// The index of the first column with one of the names, when found
+    pub fn optional_column(&self, names: &[&str]) -> Option<usize> {
+        let names = names
+            .iter()
+            .map(|name| normalize_name(name))
+            .collect::<Vec<_>>();
+
+        names
+            .iter()
+            .find_map(|name| self.headers.iter().position(|header| header == name))
+    }
+
+    /// The records, with a value for every column
+    pub fn records(&self) -> &[csv::StringRecord] {
+        &self.records
+    }
+}
+
+/// Name of a column without its case, spaces and underscores, like `longurl` for `Long URL`
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '_'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The rows inserted into a table by a SQL dump
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Table {
+    /// Names of the columns, when named by the `INSERT` statements
+    pub columns: Vec<String>,
+
+    /// The values of the rows, `None` for `NULL`
+    pub rows: Vec<Vec<Option<String>>>,
+}
+
+impl Table {
+    /// The index of the named column, or the given index when the columns are not named
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the columns are named, but not like this one
+    pub fn column(&self, name: &str, index: usize) -> Result<usize> {
+        if self.columns.is_empty() {
+            return Ok(index);
+        }
+
+        self.columns
+            .iter()
+            .position(|column| column.eq_ignore_ascii_case(name))
+            .ok_or_else(|| anyhow!("Invalid SQL: missing the `{name}` column"))
+    }
+
+    /// The value of a column of a row, `None` for `NULL` or a missing value
+    pub fn value(row: &[Option<String>], index: usize) -> Option<&str> {
+        row.get(index).and_then(Option::as_deref)
+    }
+}
+
+/// Find the rows inserted into the table by the `INSERT` statements of a SQL dump
+///
+/// Dumps of `MySQL` and `PostgreSQL` with `INSERT` statements are supported, other statements
+/// (like `COPY`) are ignored. The name of the table matches with and without its schema or
+/// prefix, like `yourls_url` for `url`.
+///
+/// # Errors
+///
+/// Will return `Err` when an `INSERT` statement of the table is invalid
+pub fn inserts(sql: &str, table: &str) -> Result<Table> {
+    let mut result = Table::default();
+
+    for statement in statements(sql) {
+        let mut parser = Parser::new(&statement);
+        if !parser.keyword("INSERT") || !parser.keyword("INTO") {
+            continue;
+        }
+
+        let name = parser.identifier().unwrap_or_default();
+        let name = name.rsplit('.').next().unwrap_or_default();
+        if !name.eq_ignore_ascii_case(table) && !name.to_lowercase().ends_with(&format!("_{table}"))
+        {
+            continue;
+        }
+
+        let invalid = || anyhow!("Invalid SQL: unexpected INSERT of {name}");
+
+        let mut columns = Vec::new();
+        if parser.symbol('(') {
+            loop {
+                columns.push(parser.identifier().ok_or_else(invalid)?);
+
+                if parser.symbol(')') {
+                    break;
+                }
+                if !parser.symbol(',') {
+                    return Err(invalid());
+                }
+            }
+        }
+
+        if !result.columns.is_empty() && columns != result.columns {
+            return Err(anyhow!("Invalid SQL: different columns of {name}"));
+        }
+        result.columns = columns;
+
+        if !parser.keyword("VALUES") {
+            return Err(invalid());
+        }
+
+        loop {
+            if !parser.symbol('(') {
+                return Err(invalid());
+            }
+
+            let mut row = Vec::new();
+            loop {
+                row.push(parser.value().ok_or_else(invalid)?);
+
+                if parser.symbol(')') {
+                    break;
+                }
+                if !parser.symbol(',') {
+                    return Err(invalid());
+                }
+            }
+            result.rows.push(row);
+
+            if !parser.symbol(',') {
+                break;
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Split a SQL dump in its statements, without the comments
+fn statements(sql: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut statement = String::new();
+    let mut chars = sql.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' | '`' => {
+                statement.push(c);
+                while let Some(next) = chars.next() {
+                    statement.push(next);
+                    if next == '\\' && c == '\'' {
+                        statement.extend(chars.next());
+                    } else if next == c {
+                        break;
+                    }
+                }
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            }
+            '#' => {
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for next in chars.by_ref() {
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+            }
+            ';' => statements.push(std::mem::take(&mut statement)),
+            _ => statement.push(c),
+        }
+    }
+
+    statements.push(statement);
+    statements.retain(|statement| !statement.trim().is_empty());
+
+    statements
+}
+
+/// Parser of a single SQL statement
+struct Parser<'a> {
+    /// The rest of the statement
+    rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    /// Parse a statement
+    fn new(statement: &'a str) -> Self {
+        Self { rest: statement }
+    }
+
+    /// Skip the whitespace
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    /// Consume the keyword, when next
+    fn keyword(&mut self, keyword: &str) -> bool {
+        self.skip_whitespace();
+
+        let is_next = self
+            .rest
+            .get(..keyword.len())
+            .is_some_and(|next| next.eq_ignore_ascii_case(keyword))
+            && !self.rest[keyword.len()..].starts_with(|c: char| c.is_alphanumeric() || c == '_');
+
+        if is_next {
+            self.rest = &self.rest[keyword.len()..];
+        }
+
+        is_next
+    }
+
+    /// Consume the symbol, when next
+    fn symbol(&mut self, symbol: char) -> bool {
+        self.skip_whitespace();
+
+        self.rest
+            .strip_prefix(symbol)
+            .map(|rest| self.rest = rest)
+            .is_some()
+    }
+
+    /// Consume an identifier, quoted or not, with its schema like `public.short_urls`
+    fn identifier(&mut self) -> Option<String> {
+        self.skip_whitespace();
+
+        let mut identifier = String::new();
+        loop {
+            if let Some(quote) = self.rest.chars().next().filter(|c| matches!(c, '`' | '"')) {
+                let end = self.rest[1..].find(quote)? + 1;
+                identifier.push_str(&self.rest[1..end]);
+                self.rest = &self.rest[end + 1..];
+            } else {
+                let end = self
+                    .rest
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                    .unwrap_or(self.rest.len());
+                if end == 0 {
+                    return None;
+                }
+                identifier.push_str(&self.rest[..end]);
+                self.rest = &self.rest[end..];
+            }
+
+            if !self.rest.starts_with('.') {
+                return Some(identifier);
+            }
+            identifier.push('.');
+            self.rest = &self.rest[1..];
+        }
+    }
+
+    /// Consume a value, a string, a number or `NULL`
+    #[allow(clippy::option_option)] // `NULL` is a value as well
+    fn value(&mut self) -> Option<Option<String>> {
+        self.skip_whitespace();
+
+        if self.keyword("NULL") {
+            return Some(None);
+        }
+
+        // like `E'...'` of PostgreSQL
+        if self.rest.starts_with(['E', 'e']) && self.rest[1..].starts_with('\'') {
+            self.rest = &self.rest[1..];
+        }
+
+        if let Some(rest) = self.rest.strip_prefix('\'') {
+            let mut value = String::new();
+            let mut chars = rest.char_indices();
+
+            while let Some((index, c)) = chars.next() {
+                match c {
+                    '\\' => match chars.next()?.1 {
+                        'n' => value.push('\n'),
+                        'r' => value.push('\r'),
+                        't' => value.push('\t'),
+                        '0' => value.push('\0'),
+                        escaped => value.push(escaped),
+                    },
+                    '\'' if rest[index + 1..].starts_with('\'') => {
+                        value.push('\'');
+                        chars.next();
+                    }
+                    '\'' => {
+                        self.rest = &rest[index + 1..];
+                        return Some(Some(value));
+                    }
+                    _ => value.push(c),
+                }
+            }
+
+            return None;
+        }
+
+        let end = self
+            .rest
+            .find(|c: char| c == ',' || c == ')' || c.is_whitespace())
+            .unwrap_or(self.rest.len());
+        if end == 0 {
+            return None;
+        }
+
+        let value = self.rest[..end].to_string();
+        self.rest = &self.rest[end..];
+
+        Some(Some(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slug_of_link() {
+        assert_eq!(Some("3xyz".into()), slug_of_link("https://bit.ly/3xyz"));
+        assert_eq!(Some("launch".into()), slug_of_link("bit.ly/launch/"));
+        assert_eq!(Some("launch".into()), slug_of_link("launch"));
+        assert_eq!(None, slug_of_link("https://bit.ly/"));
+        assert_eq!(None, slug_of_link("https://bit.ly"));
+        assert_eq!(None, slug_of_link(""));
+    }
+
+    #[test]
+    fn test_parse_created_at() {
+        let expected = NaiveDate::from_ymd_opt(2026, 10, 14)
+            .unwrap()
+            .and_hms_opt(16, 0, 0);
+
+        assert_eq!(expected, parse_created_at("2026-10-14T16:00:00+0000"));
+        assert_eq!(expected, parse_created_at("2026-10-14T18:00:00+02:00"));
+        assert_eq!(expected, parse_created_at("2026-10-14 18:00:00+02"));
+        assert_eq!(expected, parse_created_at("2026-10-14 16:00:00"));
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2026, 10, 14)
+                .unwrap()
+                .and_hms_opt(0, 0, 0),
+            parse_created_at("2026-10-14")
+        );
+        assert_eq!(None, parse_created_at("yesterday"));
+    }
+
+    #[test]
+    fn test_inserts() {
+        let sql = "-- a comment; with a semicolon\n\
+            CREATE TABLE `yourls_url` (`keyword` varchar(100));\n\
+            /* another; comment */\n\
+            INSERT INTO `yourls_url` (`keyword`, `url`, `clicks`) VALUES \
+                ('ozh','https://ozh.org/?a=1;b=2',12),\
+                ('it''s', 'https://www.example.com/\\'quoted\\'', NULL);\n\
+            INSERT INTO yourls_options VALUES ('version', '1.9');\n\
+            INSERT INTO \"public\".\"yourls_url\" (\"keyword\", \"url\", \"clicks\") VALUES (E'line\\n', 'https://www.example.com/', 0)";
+
+        assert_eq!(
+            Table {
+                columns: vec!["keyword".into(), "url".into(), "clicks".into()],
+                rows: vec![
+                    vec![
+                        Some("ozh".into()),
+                        Some("https://ozh.org/?a=1;b=2".into()),
+                        Some("12".into())
+                    ],
+                    vec![
+                        Some("it's".into()),
+                        Some("https://www.example.com/'quoted'".into()),
+                        None
+                    ],
+                    vec![
+                        Some("line\n".into()),
+                        Some("https://www.example.com/".into()),
+                        Some("0".into())
+                    ],
+                ],
+            },
+            inserts(sql, "url").unwrap()
+        );
+
+        assert_eq!(Table::default(), inserts(sql, "visits").unwrap());
+        assert!(inserts("INSERT INTO url VALUES ('unterminated", "url").is_err());
+    }
+}
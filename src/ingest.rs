@@ -0,0 +1,162 @@
+//! Ingestion of hits reported by edge workers or CDNs
+//!
+//! Edges that serve cached redirects themselves report those hits in batches via
+//! `POST /api/hits/ingest`, so they end up in the stats, rollups, webhooks and events like the hits
+//! of the root. Disabled by default, enabled with `HIT_INGEST_KEYS`, a comma separated list of
+//! `<name>=<key>` pairs like `cloudflare=...,fastly=...`. Every edge sends its key in the
+//! `X-Shurly-Api-Key` header, the name shows up in the logs. The same keys get the
+//! [snapshots](crate::edge) of the slugs.
+
+use anyhow::anyhow;
+
+use crate::utils::env_var_optional;
+
+/// Minimum number of characters of a key, shorter keys are guessable
+pub const MIN_KEY_LENGTH: usize = 32;
+
+/// A key of an edge
+#[derive(Clone)]
+struct IngestKey {
+    /// Name of the edge, for the logs
+    name: String,
+
+    /// The secret key
+    key: String,
+}
+
+/// The keys of the edges that report hits
+#[derive(Clone, Default)]
+pub struct IngestKeys {
+    /// All keys, ingestion is disabled without
+    keys: Vec<IngestKey>,
+}
+
+impl IngestKeys {
+    /// Create the keys from `(name, key)` pairs
+    pub fn new(keys: &[(&str, &str)]) -> Self {
+        Self {
+            keys: keys
+                .iter()
+                .map(|(name, key)| IngestKey {
+                    name: (*name).to_string(),
+                    key: (*key).to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Setup the keys based on the `HIT_INGEST_KEYS` environment variable
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when a pair is not valid, or a key is too short
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let Some(keys) = env_var_optional("HIT_INGEST_KEYS") else {
+            return Ok(Self::default());
+        };
+
+        Ok(Self {
+            keys: parse_keys(&keys)?,
+        })
+    }
+
+    /// Is the ingestion enabled?
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Name of the edge with the key, `None` when the key is unknown
+    ///
+    /// Every key is compared, in constant time
+    pub fn edge(&self, key: &str) -> Option<&str> {
+        self.keys.iter().fold(None, |edge, ingest_key| {
+            if constant_time_eq(ingest_key.key.as_bytes(), key.as_bytes()) {
+                Some(ingest_key.name.as_str())
+            } else {
+                edge
+            }
+        })
+    }
+}
+
+impl std::fmt::Debug for IngestKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IngestKeys")
+            .field(
+                "edges",
+                &self
+                    .keys
+                    .iter()
+                    .map(|ingest_key| &ingest_key.name)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// Parse a comma separated list of `<name>=<key>` pairs
+fn parse_keys(keys: &str) -> anyhow::Result<Vec<IngestKey>> {
+    keys.split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, key) = pair
+                .split_once('=')
+                .map(|(name, key)| (name.trim(), key.trim()))
+                .filter(|(name, key)| !name.is_empty() && !key.is_empty())
+                .ok_or_else(|| anyhow!("Invalid HIT_INGEST_KEYS, expected `<name>=<key>` pairs"))?;
+
+            if key.chars().count() < MIN_KEY_LENGTH {
+                return Err(anyhow!(
+                    "Invalid HIT_INGEST_KEYS: the key of {name} is too short, expected at least \
+                    {MIN_KEY_LENGTH} characters"
+                ));
+            }
+
+            Ok(IngestKey {
+                name: name.to_string(),
+                key: key.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Compare the bytes without stopping at the first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keys() {
+        let keys = parse_keys(&format!(
+            " cdn={}, worker = {} ,",
+            "a".repeat(32),
+            "b".repeat(40)
+        ))
+        .unwrap();
+        assert_eq!(2, keys.len());
+        assert_eq!("worker", keys[1].name);
+        assert_eq!("b".repeat(40), keys[1].key);
+
+        assert!(parse_keys("cdn").is_err());
+        assert!(parse_keys(&format!("={}", "a".repeat(32))).is_err());
+        assert!(parse_keys("cdn=tooshort").is_err());
+    }
+
+    #[test]
+    fn test_edge() {
+        let first = "a".repeat(32);
+        let second = "b".repeat(32);
+        let keys = IngestKeys::new(&[("cdn", &first), ("worker", &second)]);
+
+        assert!(keys.is_enabled());
+        assert_eq!(Some("worker"), keys.edge(&second));
+        assert_eq!(None, keys.edge("b"));
+        assert_eq!(None, keys.edge(""));
+        assert!(!IngestKeys::default().is_enabled());
+    }
+}
@@ -0,0 +1,578 @@
+//! Recurring jobs of Shurly, run in the background of the leading instance
+//!
+//! - `health-check`, checks the database every minute and logs when it is not ready
+//! - `rollup-hits`, counts the hits per destination of yesterday and today again, every hour; saved
+//!   hits are counted right away, this catches up on the hits that were not
+//! - `prune-hits`, deletes hits older than `HIT_RETENTION_DAYS`, every hour; disabled by default,
+//!   only days that are rolled up are pruned
+//! - `batch-hit-webhooks`, queues the batched hits of the [webhooks](crate::webhooks) of
+//!   destinations, every minute
+//! - `archive-hits`, moves hits older than `HIT_ARCHIVE_MONTHS` into the
+//!   [archives](crate::archival), every day; disabled by default
+//! - `purge-deleted`, deletes destinations, notes and users soft-deleted longer than
+//!   `DELETED_RETENTION_DAYS` ago for good, every hour; disabled by default, see
+//!   [`purge`](crate::purge)
+//! - `check-url-reputation`, checks the URLs of the destinations against the blocklist and Safe
+//!   Browsing, disabling the flagged ones and [notifying](crate::notifier) their creators, every
+//!   hour; disabled by default, see [`reputation`](crate::reputation)
+//! - `send-digests`, sends the weekly [digests](crate::digests) that are due, every hour; enabled
+//!   with the notifier
+//!
+//! Every instance schedules the jobs, only the [leader](crate::leader) runs them; the others skip
+//! their runs. Every run is delayed with a random jitter, up to a tenth of the interval.
+//!
+//! The status of the jobs of an instance is available via `GET /api/jobs`.
+
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use chrono::DateTime;
+use chrono::Utc;
+use rand_core::OsRng;
+use rand_core::RngCore;
+
+use crate::archival;
+use crate::archival::parse_months;
+use crate::database::Database;
+use crate::digests;
+use crate::leader::Leader;
+use crate::notifier::Notifier;
+use crate::purge;
+use crate::purge::parse_retention_days;
+use crate::reputation;
+use crate::reputation::UrlReputation;
+use crate::stats_cache::StatsCache;
+use crate::utils::env_var_optional;
+
+/// Interval of the health check
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Interval of the rollup of hits
+const ROLLUP_HITS_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Interval of the pruning of hits
+const PRUNE_HITS_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Interval of the batches of hits of webhooks
+const BATCH_HIT_WEBHOOKS_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Interval of the archival of hits
+const ARCHIVE_HITS_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Interval of the purge of deleted records
+const PURGE_DELETED_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Interval of the check of the reputation of URLs
+const CHECK_URL_REPUTATION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Interval of the sending of digests
+const SEND_DIGESTS_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Part of the interval used as maximum jitter
+const JITTER_DIVISOR: u32 = 10;
+
+/// A recurring job
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Job {
+    /// Check if the database is ready
+    HealthCheck,
+
+    /// Count the hits per destination per day
+    RollupHits,
+
+    /// Delete the hits older than the number of days
+    PruneHits {
+        /// Number of days hits are kept
+        retention_days: i32,
+    },
+
+    /// Queue the batched hits of the webhooks of destinations
+    BatchHitWebhooks,
+
+    /// Move the hits older than the number of months into the archives
+    ArchiveHits {
+        /// Number of months hits are kept before they are archived
+        months: i32,
+    },
+
+    /// Delete the records soft-deleted longer than the number of days ago
+    PurgeDeleted {
+        /// Number of days deleted records are kept
+        retention_days: i32,
+    },
+
+    /// Check the URLs of the destinations, disabling the flagged ones
+    CheckUrlReputation,
+
+    /// Send the weekly digests that are due
+    SendDigests,
+}
+
+impl Job {
+    /// Name of the job, in the logs and the status
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::HealthCheck => "health-check",
+            Self::RollupHits => "rollup-hits",
+            Self::PruneHits { .. } => "prune-hits",
+            Self::BatchHitWebhooks => "batch-hit-webhooks",
+            Self::ArchiveHits { .. } => "archive-hits",
+            Self::PurgeDeleted { .. } => "purge-deleted",
+            Self::CheckUrlReputation => "check-url-reputation",
+            Self::SendDigests => "send-digests",
+        }
+    }
+
+    /// Time between the runs of the job, without the jitter
+    pub fn interval(self) -> Duration {
+        match self {
+            Self::HealthCheck => HEALTH_CHECK_INTERVAL,
+            Self::RollupHits => ROLLUP_HITS_INTERVAL,
+            Self::PruneHits { .. } => PRUNE_HITS_INTERVAL,
+            Self::BatchHitWebhooks => BATCH_HIT_WEBHOOKS_INTERVAL,
+            Self::ArchiveHits { .. } => ARCHIVE_HITS_INTERVAL,
+            Self::PurgeDeleted { .. } => PURGE_DELETED_INTERVAL,
+            Self::CheckUrlReputation => CHECK_URL_REPUTATION_INTERVAL,
+            Self::SendDigests => SEND_DIGESTS_INTERVAL,
+        }
+    }
+
+    /// Do the work of the job, a description of the result
+    async fn work(
+        self,
+        database: &Database,
+        url_reputation: &UrlReputation,
+        notifier: &Notifier,
+        stats_cache: &StatsCache,
+    ) -> Result<String, String> {
+        match self {
+            Self::HealthCheck => match database.has_current_migrations().await {
+                Ok(true) => Ok("Database is ready".to_string()),
+                Ok(false) => Err("Migrations are missing".to_string()),
+                Err(err) => Err(format!("Database is unreachable: {err}")),
+            },
+            Self::RollupHits => database
+                .rollup_hits()
+                .await
+                .inspect(|_| stats_cache.invalidate())
+                .map(|days| format!("Counted {days} day(s) of destinations"))
+                .map_err(|err| err.to_string()),
+            Self::PruneHits { retention_days } => database
+                .prune_hits(retention_days)
+                .await
+                .inspect(|_| stats_cache.invalidate())
+                .map(|hits| format!("Deleted {hits} hit(s)"))
+                .map_err(|err| err.to_string()),
+            Self::BatchHitWebhooks => database
+                .queue_webhook_hits(true)
+                .await
+                .map(|deliveries| format!("Queued {deliveries} delivery(ies) of hits"))
+                .map_err(|err| err.to_string()),
+            Self::ArchiveHits { months } => archival::run(database, months)
+                .await
+                .inspect(|_| stats_cache.invalidate())
+                .map_err(|err| err.to_string())
+                .and_then(|run| run.describe()),
+            Self::PurgeDeleted { retention_days } => purge::run(database, retention_days)
+                .await
+                .map(|purged| purged.describe())
+                .map_err(|err| err.to_string()),
+            Self::CheckUrlReputation => reputation::run(database, url_reputation, notifier)
+                .await
+                .map(|checked| checked.describe())
+                .map_err(|err| err.to_string()),
+            Self::SendDigests => digests::run(database, notifier)
+                .await
+                .map(|sent| sent.describe())
+                .map_err(|err| err.to_string()),
+        }
+    }
+}
+
+/// Outcome of a run of a job
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The job did its work, with a description of the result
+    Succeeded(String),
+
+    /// The job runs on the leading instance, this instance does not lead
+    Skipped,
+
+    /// The job failed, with a description of the error
+    Failed(String),
+}
+
+/// Status of a job on this instance
+#[derive(Clone, Debug, Default)]
+pub struct Status {
+    /// Number of runs since startup, including skipped runs
+    pub runs: u64,
+
+    /// Start of the last run
+    pub last_run_at: Option<DateTime<Utc>>,
+
+    /// Duration of the last run
+    pub last_duration: Option<Duration>,
+
+    /// Outcome of the last run
+    pub last_outcome: Option<Outcome>,
+
+    /// Start of the next run
+    pub next_run_at: Option<DateTime<Utc>>,
+}
+
+/// A job with its status
+struct Entry {
+    /// The job
+    job: Job,
+
+    /// Status of the job, updated by every run
+    status: Mutex<Status>,
+}
+
+impl Entry {
+    /// Run the job once, skipped when this instance does not lead
+    async fn run(
+        &self,
+        database: &Database,
+        leader: &Leader,
+        url_reputation: &UrlReputation,
+        notifier: &Notifier,
+        stats_cache: &StatsCache,
+    ) -> Outcome {
+        let started_at = Utc::now();
+        let start = Instant::now();
+
+        let outcome = if leader.is_leader() {
+            match self
+                .job
+                .work(database, url_reputation, notifier, stats_cache)
+                .await
+            {
+                Ok(description) => Outcome::Succeeded(description),
+                Err(err) => Outcome::Failed(err),
+            }
+        } else {
+            Outcome::Skipped
+        };
+
+        match outcome {
+            Outcome::Succeeded(ref description) => {
+                tracing::debug!("Job {} succeeded: {description}", self.job.name());
+            }
+            Outcome::Skipped => tracing::debug!("Job {} runs elsewhere", self.job.name()),
+            Outcome::Failed(ref err) => tracing::warn!("Job {} failed: {err}", self.job.name()),
+        }
+
+        let mut status = self.status.lock().expect("Valid lock");
+        status.runs += 1;
+        status.last_run_at = Some(started_at);
+        status.last_duration = Some(start.elapsed());
+        status.last_outcome = Some(outcome.clone());
+
+        outcome
+    }
+
+    /// Wait for the next run, recording when it starts
+    async fn wait(&self, delay: Duration) {
+        self.status.lock().expect("Valid lock").next_run_at = chrono::Duration::from_std(delay)
+            .ok()
+            .map(|delay| Utc::now() + delay);
+
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// The recurring jobs of this instance
+#[derive(Clone)]
+pub struct Jobs {
+    /// All enabled jobs, with their status
+    entries: Vec<Arc<Entry>>,
+
+    /// Leadership of this instance, only the leader runs the jobs
+    leader: Leader,
+
+    /// Checks of the reputation of URLs, for `check-url-reputation`
+    url_reputation: UrlReputation,
+
+    /// Sender of the notifications, for `check-url-reputation` and `send-digests`
+    notifier: Notifier,
+
+    /// Cache of the stats, forgotten by `rollup-hits`, `prune-hits` and `archive-hits`
+    stats_cache: StatsCache,
+}
+
+impl Jobs {
+    /// Setup the jobs based on the `HIT_RETENTION_DAYS`, `HIT_ARCHIVE_MONTHS` and
+    /// `DELETED_RETENTION_DAYS` environment variables
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the retentions or the months are not a positive number
+    pub fn from_environment() -> anyhow::Result<Self> {
+        Ok(Self::new(
+            parse_retention(env_var_optional("HIT_RETENTION_DAYS").as_deref())?,
+            parse_months(env_var_optional("HIT_ARCHIVE_MONTHS").as_deref())?,
+            parse_retention_days(env_var_optional("DELETED_RETENTION_DAYS").as_deref())?,
+        ))
+    }
+
+    /// Setup the jobs, pruning hits with a retention, archiving hits after a number of months and
+    /// purging deleted records with a retention
+    pub fn new(
+        retention_days: Option<i32>,
+        archive_months: Option<i32>,
+        deleted_retention_days: Option<i32>,
+    ) -> Self {
+        let mut jobs = vec![Job::HealthCheck, Job::RollupHits, Job::BatchHitWebhooks];
+        if let Some(retention_days) = retention_days {
+            jobs.push(Job::PruneHits { retention_days });
+        }
+        if let Some(months) = archive_months {
+            jobs.push(Job::ArchiveHits { months });
+        }
+        if let Some(retention_days) = deleted_retention_days {
+            jobs.push(Job::PurgeDeleted { retention_days });
+        }
+
+        Self {
+            entries: jobs
+                .into_iter()
+                .map(|job| {
+                    Arc::new(Entry {
+                        job,
+                        status: Mutex::new(Status::default()),
+                    })
+                })
+                .collect(),
+            leader: Leader::default(),
+            url_reputation: UrlReputation::default(),
+            notifier: Notifier::default(),
+            stats_cache: StatsCache::default(),
+        }
+    }
+
+    /// Check the reputation of the URLs of the destinations, when any checks are enabled
+    #[must_use]
+    pub fn with_url_reputation(mut self, url_reputation: UrlReputation) -> Self {
+        if url_reputation.is_enabled() {
+            self.entries.push(Arc::new(Entry {
+                job: Job::CheckUrlReputation,
+                status: Mutex::new(Status::default()),
+            }));
+        }
+
+        Self {
+            url_reputation,
+            ..self
+        }
+    }
+
+    /// Notify the creators of the destinations disabled by `check-url-reputation`, and send the
+    /// digests when notifying is enabled
+    #[must_use]
+    pub fn with_notifier(mut self, notifier: Notifier) -> Self {
+        if notifier.is_enabled() {
+            self.entries.push(Arc::new(Entry {
+                job: Job::SendDigests,
+                status: Mutex::new(Status::default()),
+            }));
+        }
+
+        Self { notifier, ..self }
+    }
+
+    /// Forget the cached stats of this instance after the rollups of hits changed
+    #[must_use]
+    pub fn with_stats_cache(self, stats_cache: StatsCache) -> Self {
+        Self {
+            stats_cache,
+            ..self
+        }
+    }
+
+    /// Run the jobs and the leader election in the background, until Shurly stops
+    ///
+    /// The first run is after the jitter only, the next runs after the interval and the jitter
+    pub fn start(&self, database: &Database) {
+        self.leader.start(database);
+
+        for entry in &self.entries {
+            let entry = Arc::clone(entry);
+            let database = database.clone();
+            let leader = self.leader.clone();
+            let url_reputation = self.url_reputation.clone();
+            let notifier = self.notifier.clone();
+            let stats_cache = self.stats_cache.clone();
+
+            tokio::spawn(async move {
+                entry.wait(jitter(entry.job.interval())).await;
+
+                loop {
+                    entry
+                        .run(&database, &leader, &url_reputation, &notifier, &stats_cache)
+                        .await;
+
+                    entry
+                        .wait(entry.job.interval() + jitter(entry.job.interval()))
+                        .await;
+                }
+            });
+        }
+    }
+
+    /// Run all jobs once, right away, after holding the election
+    pub async fn run_all(&self, database: &Database) -> Vec<(Job, Outcome)> {
+        self.leader.elect(database).await;
+
+        let mut outcomes = Vec::with_capacity(self.entries.len());
+
+        for entry in &self.entries {
+            outcomes.push((
+                entry.job,
+                entry
+                    .run(
+                        database,
+                        &self.leader,
+                        &self.url_reputation,
+                        &self.notifier,
+                        &self.stats_cache,
+                    )
+                    .await,
+            ));
+        }
+
+        outcomes
+    }
+
+    /// Number of months hits are kept before the job archives them, `None` without archival
+    pub fn archive_months(&self) -> Option<i32> {
+        self.entries.iter().find_map(|entry| match entry.job {
+            Job::ArchiveHits { months } => Some(months),
+            _ => None,
+        })
+    }
+
+    /// Number of days deleted records are kept before the job purges them, `None` without purge
+    pub fn deleted_retention_days(&self) -> Option<i32> {
+        self.entries.iter().find_map(|entry| match entry.job {
+            Job::PurgeDeleted { retention_days } => Some(retention_days),
+            _ => None,
+        })
+    }
+
+    /// Is this instance the leader, running the jobs
+    pub fn is_leader(&self) -> bool {
+        self.leader.is_leader()
+    }
+
+    /// Stop leading, another instance can run the jobs
+    pub async fn resign(&self) {
+        self.leader.resign().await;
+    }
+
+    /// The jobs with their status
+    pub fn statuses(&self) -> Vec<(Job, Status)> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let status = entry.status.lock().expect("Valid lock").clone();
+
+                (entry.job, status)
+            })
+            .collect()
+    }
+}
+
+/// Parse the retention of hits in days, no pruning without a retention
+fn parse_retention(days: Option<&str>) -> anyhow::Result<Option<i32>> {
+    days.map(|days| {
+        days.parse::<i32>()
+            .ok()
+            .filter(|days| *days > 0)
+            .ok_or_else(|| {
+                anyhow::anyhow!("Invalid HIT_RETENTION_DAYS: {days}, expected a positive number")
+            })
+    })
+    .transpose()
+}
+
+/// Random delay, up to a tenth of the interval
+fn jitter(interval: Duration) -> Duration {
+    let maximum = interval / JITTER_DIVISOR;
+
+    let millis = u64::try_from(maximum.as_millis()).unwrap_or(u64::MAX);
+    if millis == 0 {
+        return Duration::ZERO;
+    }
+
+    Duration::from_millis(OsRng.next_u64() % millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retention() {
+        assert_eq!(None, parse_retention(None).unwrap());
+        assert_eq!(Some(30), parse_retention(Some("30")).unwrap());
+        assert!(parse_retention(Some("0")).is_err());
+        assert!(parse_retention(Some("-1")).is_err());
+        assert!(parse_retention(Some("forever")).is_err());
+    }
+
+    #[test]
+    fn test_jitter() {
+        for _ in 0..100 {
+            assert!(jitter(Duration::from_secs(60)) < Duration::from_secs(6));
+        }
+
+        assert_eq!(Duration::ZERO, jitter(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_jobs() {
+        let jobs = Jobs::new(None, None, None);
+        assert_eq!(
+            vec!["health-check", "rollup-hits", "batch-hit-webhooks"],
+            jobs.statuses()
+                .iter()
+                .map(|(job, _)| job.name())
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(None, jobs.archive_months());
+
+        let jobs = Jobs::new(Some(30), None, None);
+        assert_eq!(
+            Some(&Job::PruneHits { retention_days: 30 }),
+            jobs.statuses().iter().map(|(job, _)| job).next_back()
+        );
+
+        let jobs = Jobs::new(None, Some(6), None);
+        assert_eq!(
+            Some(&Job::ArchiveHits { months: 6 }),
+            jobs.statuses().iter().map(|(job, _)| job).next_back()
+        );
+        assert_eq!(Some(6), jobs.archive_months());
+
+        let jobs = Jobs::new(None, None, Some(90));
+        assert_eq!(
+            Some(&Job::PurgeDeleted { retention_days: 90 }),
+            jobs.statuses().iter().map(|(job, _)| job).next_back()
+        );
+
+        let jobs = Jobs::new(None, None, None).with_url_reputation(UrlReputation::default());
+        assert_eq!(3, jobs.statuses().len());
+
+        let jobs = Jobs::new(None, None, None)
+            .with_url_reputation(UrlReputation::default().with_blocklist("blocklist.txt"));
+        assert_eq!(
+            Some(&Job::CheckUrlReputation),
+            jobs.statuses().iter().map(|(job, _)| job).next_back()
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Leader election between the instances of Shurly, for the background jobs
+//!
+//! A single instance leads, it holds a Postgres advisory lock on a connection of its own. The
+//! other instances try to take over the lock every 15 seconds. When the leader dies, Postgres
+//! releases the lock with its connection and one of the others takes over. A leader losing its
+//! connection, like when the database restarts, stops leading until it gets the lock again. Once
+//! the database is closed, the leader resigns right away.
+
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+
+use crate::database::Database;
+use crate::database::LeaderLock;
+
+/// Time between the elections
+const ELECTION_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Key of the advisory lock of the leader, unique within the database
+const LEADER_LOCK: i64 = 0x5348_5552_4c59_0001;
+
+/// Leadership of this instance
+#[derive(Clone, Default)]
+pub struct Leader {
+    /// Is this instance the leader, as of the last election
+    is_leader: Arc<AtomicBool>,
+
+    /// The lock, while this instance leads
+    lock: Arc<Mutex<Option<LeaderLock>>>,
+}
+
+impl Leader {
+    /// Is this instance the leader, as of the last election
+    pub fn is_leader(&self) -> bool {
+        self.is_leader.load(Ordering::Relaxed)
+    }
+
+    /// Hold the election, keeping the leadership or trying to take it over
+    ///
+    /// Returns if this instance is the leader
+    pub async fn elect(&self, database: &Database) -> bool {
+        let mut lock = self.lock.lock().await;
+
+        let is_leader = match lock.as_mut() {
+            Some(lock) => lock.is_held().await,
+            None => false,
+        };
+
+        if !is_leader {
+            if lock.take().is_some() {
+                tracing::warn!("Lost the leadership of the background jobs");
+            }
+
+            match database.try_lead(LEADER_LOCK).await {
+                Ok(Some(new_lock)) => {
+                    tracing::info!("Leading the background jobs");
+
+                    *lock = Some(new_lock);
+                }
+                Ok(None) => {}
+                Err(err) => tracing::warn!("Could not hold the election: {err}"),
+            }
+        }
+
+        let is_leader = lock.is_some();
+        self.is_leader.store(is_leader, Ordering::Relaxed);
+
+        is_leader
+    }
+
+    /// Step down, another instance can lead
+    pub async fn resign(&self) {
+        self.is_leader.store(false, Ordering::Relaxed);
+
+        if let Some(lock) = self.lock.lock().await.take() {
+            if let Err(err) = lock.release().await {
+                tracing::warn!("Could not release the leadership: {err}");
+            }
+        }
+    }
+
+    /// Hold the elections in the background, until the database is closed
+    pub fn start(&self, database: &Database) {
+        let leader = self.clone();
+        let database = database.clone();
+
+        tokio::spawn(async move {
+            loop {
+                leader.elect(&database).await;
+
+                tokio::select! {
+                    () = tokio::time::sleep(ELECTION_INTERVAL) => {},
+                    () = database.closed() => break,
+                }
+            }
+
+            // the lock is held on a connection of its own, apart from the closed pool
+            leader.resign().await;
+        });
+    }
+}
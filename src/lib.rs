@@ -0,0 +1,388 @@
+#![forbid(unsafe_code)]
+// deny instead of forbid, the derives of `clap` allow lints on the generated code
+#![deny(clippy::missing_docs_in_private_items)]
+#![warn(clippy::pedantic)]
+// easier to use when using the functions as callback of foreign functions
+#![allow(clippy::needless_pass_by_value)]
+// types are used on their and are easier to read with a complete name
+#![allow(clippy::module_name_repetitions)]
+#![doc = include_str!("../README.md")]
+
+use anyhow::Context;
+use anyhow::Result;
+use axum::extract::DefaultBodyLimit;
+use axum::routing::get;
+use axum::routing::post;
+use axum::Extension;
+use axum::Router;
+use tower_http::cors::CorsLayer;
+use tower_http::request_id::MakeRequestUuid;
+use tower_http::request_id::PropagateRequestIdLayer;
+use tower_http::request_id::SetRequestIdLayer;
+use tower_http::timeout::TimeoutLayer;
+use tower_http::trace::TraceLayer;
+use tracing_subscriber::prelude::*;
+
+use crate::api::report_abuse;
+use crate::api::router;
+use crate::api::JwtKeys;
+use crate::cli::Cli;
+use crate::cli::Command;
+use crate::listener::Address;
+use crate::telemetry::LogFormat;
+use crate::tls::Tls;
+use crate::users::ensure_initial_user;
+use crate::utils::env_var_or_else;
+
+mod abuse;
+mod accounting;
+mod activity;
+mod api;
+mod approval;
+mod archival;
+mod audit_trail;
+mod auth_metrics;
+mod backup;
+mod bitly;
+mod broker;
+pub mod cli;
+mod client_ip;
+mod config;
+mod confusables;
+mod cors;
+// every storage interaction can only fail with a connection error, the possible panics are the
+// unreachable ones of the query macros of `sqlx`
+#[allow(clippy::missing_errors_doc, clippy::missing_panics_doc)]
+pub mod database;
+mod degraded;
+mod destination_templates;
+mod destinations;
+mod digests;
+mod domains;
+mod edge;
+mod graceful_shutdown;
+mod hashed_slugs;
+mod health;
+mod hit_buffer;
+mod hooks;
+mod idn;
+mod import;
+mod ingest;
+mod jobs;
+mod leader;
+mod limits;
+mod listener;
+mod notes;
+mod notifier;
+mod password;
+mod permissions;
+mod purge;
+mod quotas;
+mod rate_limit;
+mod redirect_loops;
+mod reload;
+mod reputation;
+mod reservations;
+mod root;
+mod scripts;
+mod search;
+mod seed;
+mod shlink;
+mod signing;
+mod slack;
+mod slug_cache;
+mod slug_policy;
+mod stats_cache;
+mod telemetry;
+mod templates;
+#[cfg(test)]
+mod tests;
+mod tls;
+mod users;
+mod utils;
+mod webhooks;
+mod yourls;
+
+pub use audit_trail::AuditTrailEntry;
+pub use database::Database;
+pub use destinations::Destination;
+pub use domains::Domain;
+pub use hooks::HookRequest;
+pub use hooks::RedirectHook;
+pub use hooks::RedirectHooks;
+pub use limits::Limits;
+pub use notes::Note;
+pub use notifier::Email;
+pub use notifier::Notifier;
+pub use notifier::Transport;
+pub use root::Settings as RootSettings;
+pub use users::Role;
+pub use users::User;
+
+/// Default `RUST_LOG` value
+const DEFAULT_RUST_LOG: &str = "shurly=debug,tower_http=debug";
+
+/// Run Shurly with the command of the command line, serving Shurly without a command
+///
+/// # Errors
+///
+/// Will return `Err` when the command fails, like when Shurly could not be served
+pub async fn run(cli: Cli) -> Result<()> {
+    setup_environment()?;
+    setup_tracing()?;
+
+    match cli.command {
+        None => serve(false).await,
+        Some(Command::Serve { skip_migrations }) => serve(skip_migrations).await,
+        Some(Command::Migrate { dry_run }) => {
+            let database = Database::from_environment().await?;
+
+            cli::migrate(&database, dry_run).await
+        }
+        Some(Command::Manage(command)) => {
+            let database = Database::from_environment().await?;
+            database
+                .migrate()
+                .await
+                .context("Migrations could not run")?;
+            let root_settings = RootSettings::from_environment()?;
+
+            cli::run(&database, &root_settings, command).await
+        }
+    }
+}
+
+/// Serve Shurly, until a terminate signal is received
+///
+/// The pending migrations are run first, unless skipped
+async fn serve(skip_migrations: bool) -> Result<()> {
+    let database = Database::from_environment().await?;
+    if !skip_migrations {
+        database
+            .migrate()
+            .await
+            .context("Migrations could not run")?;
+    }
+
+    let root_settings = setup_dependencies(&database).await?;
+    let limits = Limits::from_environment()?;
+    let cors = cors::layer()?;
+
+    // the buffered hits are saved once the in-flight requests are handled, then the leadership is
+    // released right away instead of when the database notices the connection is gone
+    let hits = root_settings.hits.clone();
+    let jobs = root_settings.jobs.clone();
+    let shutdown_database = database.clone();
+
+    let tls = Tls::from_environment()?;
+
+    let address = Address::from_environment()?;
+
+    if let Some(api_address) = Address::api_from_environment()? {
+        let api_tls = tls.for_api()?;
+
+        tracing::info!("Listening on {} for the API", api_address);
+        let api = create_router(
+            database.clone(),
+            root_settings.clone(),
+            limits,
+            cors,
+            Routes::Api,
+        );
+
+        tracing::info!("Listening on {} for the root", address);
+        let root = create_router(database, root_settings, limits, None, Routes::Root);
+
+        tokio::try_join!(
+            serve_on(address, tls, root),
+            serve_on(api_address, api_tls, api),
+        )?;
+    } else {
+        tracing::info!("Listening on {}", address);
+        let app = create_router(database, root_settings, limits, cors, Routes::All);
+
+        serve_on(address, tls, app).await?;
+    }
+
+    hits.flush(&shutdown_database).await;
+    jobs.resign().await;
+    shutdown_database.close().await;
+
+    telemetry::shutdown();
+
+    Ok(())
+}
+
+/// Serve the app on the address, until a terminate signal is received
+async fn serve_on(address: Address, tls: Tls, app: Router) -> Result<()> {
+    match address {
+        Address::Tcp(address) => tls.serve(address, app).await,
+        Address::Unix(path) => {
+            if tls != Tls::Disabled {
+                return Err(anyhow::anyhow!(
+                    "Invalid TLS: not available on a Unix domain socket"
+                ));
+            }
+
+            listener::serve_unix(&path, app).await
+        }
+    }
+}
+
+/// Create and setup the app with its dependencies
+///
+/// # Errors
+///
+/// Will return `Err` if any of its dependencies fail to load:
+/// - Initial user setup
+/// - Root settings, like custom templates
+pub async fn setup_app(database: Database) -> Result<Router> {
+    let root_settings = setup_dependencies(&database).await?;
+    let limits = Limits::from_environment()?;
+    let cors = cors::layer()?;
+
+    Ok(create_router(
+        database,
+        root_settings,
+        limits,
+        cors,
+        Routes::All,
+    ))
+}
+
+/// Setup the dependencies of the app, the initial user and the settings of the root
+///
+/// Use with [`create_router`] to serve only some of the routes, or with other limits
+///
+/// # Errors
+///
+/// Will return `Err` if the initial user setup or the root settings fail
+pub async fn setup_dependencies(database: &Database) -> Result<RootSettings> {
+    ensure_initial_user(database).await?;
+    seed::load_from_environment(database).await?;
+
+    let root_settings = RootSettings::from_environment()?;
+    root_settings.templates.watch();
+    reload::listen(&root_settings);
+    root_settings.jobs.start(database);
+    root_settings.hits.start(database);
+    root_settings.webhooks.start(database);
+    root_settings.broker.start();
+    root_settings.notifier.start();
+    root_settings.slug_cache.listen(database).await?;
+    root_settings
+        .slug_cache
+        .warm_up(database, root_settings.loop_detection.hostnames())
+        .await;
+
+    Ok(root_settings)
+}
+
+/// Routes served by a router
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Routes {
+    /// The API and the root, on a single address
+    All,
+
+    /// Only the root, without the API
+    Root,
+
+    /// Only the API
+    Api,
+}
+
+/// Create the router for Shurly, the health checks are part of all routes
+///
+/// The CORS layer only applies to the API
+///
+/// Other axum apps can mount Shurly by nesting this router
+pub fn create_router(
+    database: Database,
+    root_settings: RootSettings,
+    limits: Limits,
+    cors: Option<CorsLayer>,
+    routes: Routes,
+) -> Router {
+    let mut app = Router::new()
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz));
+
+    if routes != Routes::Root {
+        let mut api = router().layer(DefaultBodyLimit::max(limits.body_limit));
+        if let Some(cors) = cors {
+            api = api.layer(cors);
+        }
+
+        app = app.nest("/api", api).layer(Extension(setup_jwt_keys()));
+    }
+
+    if routes != Routes::Api {
+        app = app
+            .route("/robots.txt", get(root::robots_txt))
+            .route("/favicon.ico", get(root::favicon))
+            // only posts are reports, slugs starting with `report/` are served as usual
+            .route("/report/*slug", post(report_abuse).fallback(root::root))
+            .fallback(root::root);
+    }
+
+    app.layer(TimeoutLayer::new(limits.request_timeout))
+        .layer(TraceLayer::new_for_http().make_span_with(telemetry::make_span))
+        .layer(PropagateRequestIdLayer::new(telemetry::X_REQUEST_ID))
+        .layer(SetRequestIdLayer::new(
+            telemetry::X_REQUEST_ID,
+            MakeRequestUuid,
+        ))
+        .layer(Extension(database))
+        .layer(Extension(root_settings))
+}
+
+/// Setup the environment (variables) in which Shurly runs, including the configuration file
+///
+/// # Errors
+///
+/// Will return `Err` when the configuration file can not be loaded
+fn setup_environment() -> Result<()> {
+    dotenvy::dotenv().ok();
+
+    config::load()
+}
+
+/// Setup the tracing subscriber for logging, and the export of traces when configured
+///
+/// # Errors
+///
+/// Will return `Err` when the export of traces could not be setup
+fn setup_tracing() -> Result<()> {
+    use tracing_subscriber::fmt;
+    use tracing_subscriber::registry;
+    use tracing_subscriber::EnvFilter;
+
+    let (text, json) = match LogFormat::from_environment()? {
+        LogFormat::Text => (Some(fmt::layer()), None),
+        LogFormat::Json => (None, Some(fmt::layer().json().with_span_list(false))),
+    };
+
+    registry()
+        .with(EnvFilter::new(env_var_or_else("RUST_LOG", || {
+            DEFAULT_RUST_LOG.into()
+        })))
+        .with(text)
+        .with(json)
+        .with(telemetry::layer()?)
+        .init();
+
+    Ok(())
+}
+
+/// Setup the JWT keys for encoding/decoding
+fn setup_jwt_keys() -> JwtKeys {
+    use crate::password::generate;
+
+    let jwt_secret = env_var_or_else("JWT_SECRET", || {
+        let jwt_secret = generate();
+        tracing::info!("`JWT_SECRET` is not set, generating temporary one: {jwt_secret}");
+        jwt_secret
+    });
+
+    JwtKeys::new(jwt_secret.as_bytes())
+}
@@ -0,0 +1,112 @@
+//! Limits of requests, so a slow client or a big payload can not hold on to the resources
+//!
+//! Every request has to be handled within the timeout, otherwise `408 Request Timeout` is
+//! returned. Bodies of API requests are limited in size, bigger bodies get
+//! `413 Payload Too Large`.
+//!
+//! The root can have a deadline for its lookups in the database with `DATABASE_DEADLINE`
+//! (milliseconds), a slow database gets visitors a fast `503 Service Unavailable` instead of a
+//! pile of waiting requests.
+
+use std::future::Future;
+use std::time::Duration;
+
+use crate::utils::env_var_optional;
+use crate::utils::parse_positive;
+
+/// Default number of seconds to handle a request in
+const DEFAULT_REQUEST_TIMEOUT: u64 = 30;
+
+/// Default maximum size of the body of API requests, in bytes
+const DEFAULT_BODY_LIMIT: u64 = 64 * 1024;
+
+/// Limits of the requests
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// Time to handle a request in
+    pub request_timeout: Duration,
+
+    /// Maximum size of the body of API requests, in bytes
+    pub body_limit: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_secs(DEFAULT_REQUEST_TIMEOUT),
+            body_limit: usize::try_from(DEFAULT_BODY_LIMIT).expect("Body limit fits in usize"),
+        }
+    }
+}
+
+impl Limits {
+    /// Setup the limits based on the `REQUEST_TIMEOUT` (seconds) and `BODY_LIMIT` (bytes)
+    /// environment variables
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when a limit is not a positive whole number
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let defaults = Self::default();
+
+        let request_timeout = env_var_optional("REQUEST_TIMEOUT")
+            .map(|seconds| parse_positive("REQUEST_TIMEOUT", &seconds))
+            .transpose()?
+            .map_or(defaults.request_timeout, Duration::from_secs);
+
+        let body_limit = env_var_optional("BODY_LIMIT")
+            .map(|bytes| {
+                parse_positive("BODY_LIMIT", &bytes).and_then(|bytes| {
+                    usize::try_from(bytes)
+                        .map_err(|_| anyhow::anyhow!("Invalid BODY_LIMIT: {bytes}, too big"))
+                })
+            })
+            .transpose()?
+            .unwrap_or(defaults.body_limit);
+
+        Ok(Self {
+            request_timeout,
+            body_limit,
+        })
+    }
+}
+
+/// Deadline for the work of the root in the database, no deadline by default
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DatabaseDeadline {
+    /// Time for the lookups of a single request
+    deadline: Option<Duration>,
+}
+
+impl DatabaseDeadline {
+    /// Setup the deadline based on the `DATABASE_DEADLINE` (milliseconds) environment variable
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the deadline is not a positive whole number
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let deadline = env_var_optional("DATABASE_DEADLINE")
+            .map(|milliseconds| parse_positive("DATABASE_DEADLINE", &milliseconds))
+            .transpose()?
+            .map(Duration::from_millis);
+
+        Ok(Self { deadline })
+    }
+
+    /// Use the deadline
+    #[must_use]
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Run the future within the deadline, `None` when the deadline passed first
+    ///
+    /// The future is dropped when the deadline passes, cancelling its queries
+    pub async fn run<F: Future>(&self, future: F) -> Option<F::Output> {
+        match self.deadline {
+            Some(deadline) => tokio::time::timeout(deadline, future).await.ok(),
+            None => Some(future.await),
+        }
+    }
+}
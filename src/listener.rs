@@ -0,0 +1,220 @@
+//! Address Shurly binds to, a TCP address or a Unix domain socket
+//!
+//! A Unix domain socket is handy when a proxy like nginx or Caddy on the same machine is in front
+//! of Shurly, with `ADDRESS=unix:/run/shurly.sock`. The proxy should send the `X-Forwarded-For`
+//! header, there is no IP address of the client on a Unix domain socket.
+
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use axum::Router;
+
+use crate::utils::env_var_optional;
+use crate::utils::env_var_or_else;
+
+/// Default address Shurly binds to
+const DEFAULT_ADDRESS: &str = "0.0.0.0:7000";
+
+/// Prefix of an address of a Unix domain socket
+const UNIX_PREFIX: &str = "unix:";
+
+/// Address to bind to
+#[derive(Debug, PartialEq, Eq)]
+pub enum Address {
+    /// TCP address, like `0.0.0.0:7000`
+    Tcp(SocketAddr),
+
+    /// Path of a Unix domain socket, like `unix:/run/shurly.sock`
+    Unix(PathBuf),
+}
+
+impl Address {
+    /// Setup the address based on the `ADDRESS` and `PORT` environment variables
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the address or port is invalid
+    pub fn from_environment() -> anyhow::Result<Self> {
+        Self::parse(
+            &env_var_or_else("ADDRESS", || String::from(DEFAULT_ADDRESS)),
+            env_var_optional("PORT").as_deref(),
+        )
+    }
+
+    /// Setup the address of the API based on the `API_ADDRESS` environment variable, TCP or a
+    /// Unix domain socket
+    ///
+    /// Returns `None` when the API is served on the address of the root
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the address is invalid
+    pub fn api_from_environment() -> anyhow::Result<Option<Self>> {
+        env_var_optional("API_ADDRESS")
+            .map(|address| Self::parse(&address, None))
+            .transpose()
+    }
+
+    /// Parse the address, with an optional override of just the port
+    fn parse(address: &str, port: Option<&str>) -> anyhow::Result<Self> {
+        if let Some(path) = address.strip_prefix(UNIX_PREFIX) {
+            if path.is_empty() {
+                return Err(anyhow!(
+                    "Invalid ADDRESS: {address}, expected a path after `{UNIX_PREFIX}`"
+                ));
+            }
+
+            if port.is_some() {
+                return Err(anyhow!(
+                    "Invalid PORT: a Unix domain socket has no port, remove `PORT`"
+                ));
+            }
+
+            return Ok(Self::Unix(PathBuf::from(path)));
+        }
+
+        let mut socket_address = address.parse::<SocketAddr>().map_err(|_| {
+            anyhow!("Invalid ADDRESS: {address}, expected `ip:port` or `unix:path`")
+        })?;
+
+        if let Some(port) = port {
+            let port = port
+                .parse::<u16>()
+                .map_err(|_| anyhow!("Invalid PORT: {port}, expected a port number"))?;
+
+            socket_address.set_port(port);
+        }
+
+        Ok(Self::Tcp(socket_address))
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(address) => write!(f, "{address}"),
+            Self::Unix(path) => write!(f, "{UNIX_PREFIX}{}", path.display()),
+        }
+    }
+}
+
+/// Serve the app on a Unix domain socket, until a terminate signal is received
+///
+/// A socket left behind by a previous run is replaced, the socket is removed after shutting down
+///
+/// # Errors
+///
+/// Will return `Err` when the socket can not be bound to
+#[cfg(unix)]
+pub async fn serve_unix(path: &Path, app: Router) -> anyhow::Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    use hyper_util::rt::TokioExecutor;
+    use hyper_util::rt::TokioIo;
+    use hyper_util::server::conn::auto::Builder;
+    use hyper_util::server::graceful::GracefulShutdown;
+    use hyper_util::service::TowerToHyperService;
+    use tokio::net::UnixListener;
+
+    use crate::graceful_shutdown;
+
+    // only replace sockets, never a regular file given by mistake
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if metadata.file_type().is_socket() {
+            std::fs::remove_file(path)?;
+        }
+    }
+
+    let listener = UnixListener::bind(path)
+        .map_err(|err| anyhow!("Invalid ADDRESS: {}, {err}", path.display()))?;
+
+    let builder = Builder::new(TokioExecutor::new());
+    let graceful = GracefulShutdown::new();
+
+    let shutdown = graceful_shutdown::handler();
+    tokio::pin!(shutdown);
+
+    loop {
+        tokio::select! {
+            result = listener.accept() => {
+                let stream = match result {
+                    Ok((stream, _)) => stream,
+                    Err(err) => {
+                        tracing::error!("Accepting connection failed: {err}");
+                        continue;
+                    }
+                };
+
+                let connection = builder
+                    .serve_connection_with_upgrades(
+                        TokioIo::new(stream),
+                        TowerToHyperService::new(app.clone()),
+                    )
+                    .into_owned();
+                let connection = graceful.watch(connection);
+
+                tokio::spawn(async move {
+                    if let Err(err) = connection.await {
+                        tracing::debug!("Connection failed: {err}");
+                    }
+                });
+            }
+            () = &mut shutdown => break,
+        }
+    }
+
+    // stop accepting connections, and wait for the current connections to finish
+    drop(listener);
+    graceful.shutdown().await;
+
+    std::fs::remove_file(path).ok();
+
+    Ok(())
+}
+
+/// Serving on a Unix domain socket is not available on this platform
+///
+/// # Errors
+///
+/// Will always return `Err`
+#[cfg(not(unix))]
+pub async fn serve_unix(path: &Path, _app: Router) -> anyhow::Result<()> {
+    Err(anyhow!(
+        "Invalid ADDRESS: {}, Unix domain sockets are not available on this platform",
+        path.display()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Address::Tcp("0.0.0.0:7000".parse().unwrap()),
+            Address::parse(DEFAULT_ADDRESS, None).unwrap()
+        );
+        assert_eq!(
+            Address::Tcp("127.0.0.1:8000".parse().unwrap()),
+            Address::parse("127.0.0.1:7000", Some("8000")).unwrap()
+        );
+        assert_eq!(
+            Address::Unix(PathBuf::from("/run/shurly.sock")),
+            Address::parse("unix:/run/shurly.sock", None).unwrap()
+        );
+
+        assert!(Address::parse("unix:", None).is_err());
+        assert!(Address::parse("unix:/run/shurly.sock", Some("8000")).is_err());
+        assert!(Address::parse("localhost", None).is_err());
+        assert!(Address::parse(DEFAULT_ADDRESS, Some("port")).is_err());
+
+        assert_eq!(
+            "unix:/run/shurly.sock",
+            Address::Unix(PathBuf::from("/run/shurly.sock")).to_string()
+        );
+    }
+}
@@ -25,6 +25,12 @@ pub fn hash(password: &str) -> String {
     hashed_password.to_string()
 }
 
+/// Is the hash an Argon2 hash of a password, in the PHC string format?
+pub fn is_valid_hash(hashed_password: &str) -> bool {
+    PasswordHash::new(hashed_password)
+        .is_ok_and(|parsed_hash| argon2::Algorithm::try_from(parsed_hash.algorithm).is_ok())
+}
+
 /// Verify a given password against a given hash
 pub fn verify(hashed_password: &str, password: &str) -> bool {
     let parsed_hash = PasswordHash::new(hashed_password).expect("Valid parsed hash");
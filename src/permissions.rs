@@ -0,0 +1,213 @@
+//! Permissions of the roles
+//!
+//! Every role is allowed a set of actions, the permission matrix. By default admins are allowed
+//! every action and managers can view, create, update and delete destinations and manage their
+//! notes. With `ADMIN_PERMISSIONS` and `MANAGER_PERMISSIONS`, comma-separated lists of actions,
+//! deployments decide for themselves; like letting managers manage users, but not delete
+//! destinations.
+//!
+//! Users can only create, change the password of or delete users whose role is allowed no more
+//! than their own role, a manager can not create an admin.
+
+use std::collections::HashSet;
+
+use crate::users::Role;
+use crate::utils::env_var_optional;
+
+/// An action a role can be allowed to perform
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// View destinations with their notes, hits and templates
+    ViewDestinations,
+
+    /// Create destinations
+    CreateDestinations,
+
+    /// Update destinations and sign their links
+    UpdateDestinations,
+
+    /// Delete destinations
+    DeleteDestinations,
+
+    /// Approve and reject destinations, review abuse reports
+    ReviewDestinations,
+
+    /// Create, update and delete notes
+    ManageNotes,
+
+    /// Create, update and delete destination templates
+    ManageTemplates,
+
+    /// View, create and delete users, change their passwords
+    ManageUsers,
+
+    /// Manage domains
+    ManageDomains,
+
+    /// Manage webhooks
+    ManageWebhooks,
+
+    /// Jobs, archival, backups, caches, the audit trail and the event stream
+    ManageSystem,
+}
+
+impl Action {
+    /// All actions
+    pub const ALL: [Self; 11] = [
+        Self::ViewDestinations,
+        Self::CreateDestinations,
+        Self::UpdateDestinations,
+        Self::DeleteDestinations,
+        Self::ReviewDestinations,
+        Self::ManageNotes,
+        Self::ManageTemplates,
+        Self::ManageUsers,
+        Self::ManageDomains,
+        Self::ManageWebhooks,
+        Self::ManageSystem,
+    ];
+
+    /// Name of the action, as used in the configuration
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ViewDestinations => "view-destinations",
+            Self::CreateDestinations => "create-destinations",
+            Self::UpdateDestinations => "update-destinations",
+            Self::DeleteDestinations => "delete-destinations",
+            Self::ReviewDestinations => "review-destinations",
+            Self::ManageNotes => "manage-notes",
+            Self::ManageTemplates => "manage-templates",
+            Self::ManageUsers => "manage-users",
+            Self::ManageDomains => "manage-domains",
+            Self::ManageWebhooks => "manage-webhooks",
+            Self::ManageSystem => "manage-system",
+        }
+    }
+
+    /// The action of the name, `None` for unknown names
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// The permission matrix, the allowed actions of every role
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Permissions {
+    /// Allowed actions of admins
+    admin: HashSet<Action>,
+
+    /// Allowed actions of managers
+    manager: HashSet<Action>,
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Self {
+            admin: Action::ALL.into_iter().collect(),
+            manager: [
+                Action::ViewDestinations,
+                Action::CreateDestinations,
+                Action::UpdateDestinations,
+                Action::DeleteDestinations,
+                Action::ManageNotes,
+            ]
+            .into_iter()
+            .collect(),
+        }
+    }
+}
+
+impl Permissions {
+    /// Setup the matrix based on the `ADMIN_PERMISSIONS` and `MANAGER_PERMISSIONS` environment
+    /// variables, roles without a variable keep their default actions
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when an action is unknown
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let mut permissions = Self::default();
+
+        if let Some(actions) = env_var_optional("ADMIN_PERMISSIONS") {
+            permissions.admin = parse_actions("ADMIN_PERMISSIONS", &actions)?;
+        }
+
+        if let Some(actions) = env_var_optional("MANAGER_PERMISSIONS") {
+            permissions.manager = parse_actions("MANAGER_PERMISSIONS", &actions)?;
+        }
+
+        Ok(permissions)
+    }
+
+    /// Allow the role exactly the actions
+    #[must_use]
+    pub fn with_role(mut self, role: Role, actions: &[Action]) -> Self {
+        *self.actions_mut(role) = actions.iter().copied().collect();
+        self
+    }
+
+    /// The allowed actions of the role
+    pub fn actions(&self, role: Role) -> &HashSet<Action> {
+        match role {
+            Role::Admin => &self.admin,
+            Role::Manager => &self.manager,
+        }
+    }
+
+    /// Is the role allowed the action?
+    pub fn is_allowed(&self, role: Role, action: Action) -> bool {
+        self.actions(role).contains(&action)
+    }
+
+    /// Is the role allowed every action of the other role?
+    pub fn includes(&self, role: Role, other: Role) -> bool {
+        self.actions(other).is_subset(self.actions(role))
+    }
+
+    /// The allowed actions of the role, to change
+    fn actions_mut(&mut self, role: Role) -> &mut HashSet<Action> {
+        match role {
+            Role::Admin => &mut self.admin,
+            Role::Manager => &mut self.manager,
+        }
+    }
+}
+
+/// Parse a comma-separated list of actions
+fn parse_actions(var_name: &str, actions: &str) -> anyhow::Result<HashSet<Action>> {
+    actions
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            Action::from_name(name)
+                .ok_or_else(|| anyhow::anyhow!("Invalid {var_name}: unknown action {name}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_actions() {
+        let actions =
+            parse_actions("MANAGER_PERMISSIONS", "view-destinations, manage-users,").unwrap();
+        assert_eq!(
+            HashSet::from([Action::ViewDestinations, Action::ManageUsers]),
+            actions
+        );
+
+        assert!(parse_actions("MANAGER_PERMISSIONS", "delete-everything").is_err());
+    }
+
+    #[test]
+    fn test_includes() {
+        let permissions = Permissions::default();
+        assert!(permissions.includes(Role::Admin, Role::Manager));
+        assert!(!permissions.includes(Role::Manager, Role::Admin));
+
+        let permissions = permissions.with_role(Role::Admin, &[Action::ManageUsers]);
+        assert!(!permissions.includes(Role::Admin, Role::Manager));
+    }
+}
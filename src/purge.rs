@@ -0,0 +1,83 @@
+//! Purge of long soft-deleted records
+//!
+//! Deleted destinations, notes and users are soft-deleted: the slugs of deleted destinations are
+//! not reused and the records stay around for the backups. With `DELETED_RETENTION_DAYS` the
+//! `purge-deleted` job deletes them for good once they are deleted longer ago, every hour, on
+//! the leader. Their slugs can be used again.
+//!
+//! A purged destination takes its notes, hits, rollups, archives and webhooks along. Users are
+//! only purged once nothing refers to them anymore. The audit trail keeps the IDs of the purged
+//! records, its chain stays intact.
+
+use crate::database::Database;
+use crate::slug_cache;
+
+/// Outcome of a purge
+#[derive(Clone, Debug, Default)]
+pub struct Purged {
+    /// Slugs of the purged destinations
+    pub slugs: Vec<String>,
+
+    /// Number of purged notes, without the notes of the purged destinations
+    pub notes: u64,
+
+    /// Number of purged users
+    pub users: u64,
+}
+
+impl Purged {
+    /// Description of the purge, for the status of the job
+    pub fn describe(&self) -> String {
+        format!(
+            "Purged {} destination(s), {} note(s) and {} user(s)",
+            self.slugs.len(),
+            self.notes,
+            self.users
+        )
+    }
+}
+
+/// Purge the records deleted longer than the number of days ago, all or nothing
+///
+/// The purged slugs are forgotten by the caches of all instances
+pub async fn run(database: &Database, retention_days: i32) -> anyhow::Result<Purged> {
+    let purged = database.purge_deleted(retention_days).await?;
+
+    for slug in &purged.slugs {
+        slug_cache::invalidate_everywhere(database, slug).await;
+    }
+
+    Ok(purged)
+}
+
+/// Parse the number of days deleted records are kept, no purge without
+///
+/// # Errors
+///
+/// Will return `Err` when the number of days is not a positive number
+pub fn parse_retention_days(days: Option<&str>) -> anyhow::Result<Option<i32>> {
+    days.map(|days| {
+        days.parse::<i32>()
+            .ok()
+            .filter(|days| *days > 0)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Invalid DELETED_RETENTION_DAYS: {days}, expected a positive number"
+                )
+            })
+    })
+    .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retention_days() {
+        assert_eq!(None, parse_retention_days(None).unwrap());
+        assert_eq!(Some(90), parse_retention_days(Some("90")).unwrap());
+        assert!(parse_retention_days(Some("0")).is_err());
+        assert!(parse_retention_days(Some("forever")).is_err());
+    }
+}
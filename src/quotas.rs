@@ -0,0 +1,126 @@
+//! Quotas of users
+//!
+//! Every role can have a maximum number of destinations and hits per month, set with
+//! `ADMIN_MAX_DESTINATIONS`, `ADMIN_MAX_HITS_PER_MONTH`, `MANAGER_MAX_DESTINATIONS` and
+//! `MANAGER_MAX_HITS_PER_MONTH`; without a variable there is no maximum. The quota of a single
+//! user, set by an admin, overrides the quota of its role.
+//!
+//! Creating destinations checks the quota: too many destinations get a `400 Bad Request`, too many
+//! hits this month a `429 Too Many Requests`. Deleted destinations do not count, the hits are
+//! counted since the first day of the month.
+
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use crate::users::Role;
+use crate::utils::env_var_optional;
+
+/// Limits of a user, `None` for no maximum
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quota {
+    /// Maximum number of destinations
+    pub max_destinations: Option<i64>,
+
+    /// Maximum number of hits of the destinations per month
+    pub max_hits_per_month: Option<i64>,
+}
+
+/// The quotas of the roles
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quotas {
+    /// Quota of admins
+    admin: Quota,
+
+    /// Quota of managers
+    manager: Quota,
+}
+
+impl Quotas {
+    /// Setup the quotas based on the `ADMIN_MAX_DESTINATIONS`, `ADMIN_MAX_HITS_PER_MONTH`,
+    /// `MANAGER_MAX_DESTINATIONS` and `MANAGER_MAX_HITS_PER_MONTH` environment variables
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when a maximum is not a positive whole number
+    pub fn from_environment() -> anyhow::Result<Self> {
+        Ok(Self {
+            admin: Quota {
+                max_destinations: parse_max("ADMIN_MAX_DESTINATIONS")?,
+                max_hits_per_month: parse_max("ADMIN_MAX_HITS_PER_MONTH")?,
+            },
+            manager: Quota {
+                max_destinations: parse_max("MANAGER_MAX_DESTINATIONS")?,
+                max_hits_per_month: parse_max("MANAGER_MAX_HITS_PER_MONTH")?,
+            },
+        })
+    }
+
+    /// Use the quota for the role
+    #[must_use]
+    pub fn with_role(mut self, role: Role, quota: Quota) -> Self {
+        match role {
+            Role::Admin => self.admin = quota,
+            Role::Manager => self.manager = quota,
+        }
+
+        self
+    }
+
+    /// The quota of a user with the role, overridden by the quota of the user itself
+    pub fn of(&self, role: Role, user_quota: Option<&UserQuota>) -> Quota {
+        if let Some(user_quota) = user_quota {
+            return Quota {
+                max_destinations: user_quota.max_destinations,
+                max_hits_per_month: user_quota.max_hits_per_month,
+            };
+        }
+
+        match role {
+            Role::Admin => self.admin,
+            Role::Manager => self.manager,
+        }
+    }
+}
+
+/// The quota of a single user, set by an admin
+#[derive(Clone, Debug)]
+pub struct UserQuota {
+    /// The user
+    pub user_id: Uuid,
+
+    /// Maximum number of destinations
+    pub max_destinations: Option<i64>,
+
+    /// Maximum number of hits of the destinations per month
+    pub max_hits_per_month: Option<i64>,
+
+    /// Creation date
+    #[allow(dead_code)] // used by sqlx
+    pub created_at: NaiveDateTime,
+
+    /// Last updated at
+    #[allow(dead_code)] // used by sqlx
+    pub updated_at: NaiveDateTime,
+}
+
+/// The current consumption of a user
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// Number of destinations that are not deleted
+    pub destinations: i64,
+
+    /// Number of hits of the destinations since the first day of the month
+    pub hits_this_month: i64,
+}
+
+/// Parse the maximum of the environment variable, `None` without the variable
+fn parse_max(var_name: &'static str) -> anyhow::Result<Option<i64>> {
+    env_var_optional(var_name)
+        .map(|value| match value.parse::<i64>() {
+            Ok(max) if max > 0 => Ok(max),
+            _ => Err(anyhow::anyhow!(
+                "Invalid {var_name}: {value}, expected a positive whole number"
+            )),
+        })
+        .transpose()
+}
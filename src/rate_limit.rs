@@ -0,0 +1,288 @@
+//! Rate limiting of the root
+//!
+//! Every IP address gets a bucket of tokens, a request takes a token and the bucket refills at
+//! a steady rate. An empty bucket means the request is refused, until the bucket has refilled
+//! enough for a single request. This keeps scrapers from hammering the lookups in the database,
+//! while allowing short bursts of regular visitors.
+//!
+//! Up to [`MAX_BUCKETS`] IP addresses are tracked, beyond that the least recently seen IP address
+//! is forgotten. Its bucket has refilled the longest, forgetting it is the same as refilling it.
+
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::RwLock;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::utils::env_var_optional;
+use crate::utils::parse_positive_fraction;
+
+/// Maximum number of tracked IP addresses
+const MAX_BUCKETS: usize = 10_000;
+
+/// Abuse reports per second of an IP address, a report every minute, regardless of `RATE_LIMIT`
+pub const REPORT_RATE: f64 = 1.0 / 60.0;
+
+/// Burst of abuse reports of an IP address
+pub const REPORT_BURST: f64 = 5.0;
+
+/// Limits of the buckets
+#[derive(Clone, Copy, Debug)]
+struct Limits {
+    /// Tokens added to a bucket per second
+    rate: f64,
+
+    /// Maximum number of tokens in a bucket
+    burst: f64,
+}
+
+/// Bucket of tokens of a single IP address
+#[derive(Debug)]
+struct Bucket {
+    /// Tokens left, as of the last update
+    tokens: f64,
+
+    /// Last time the tokens were updated
+    updated_at: Instant,
+}
+
+impl Bucket {
+    /// The tokens in the bucket at the given time, refilled since the last update
+    fn tokens_at(&self, limits: Limits, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+
+        (self.tokens + elapsed * limits.rate).min(limits.burst)
+    }
+}
+
+/// Buckets of the IP addresses, in the order they were last updated
+#[derive(Debug, Default)]
+struct Buckets {
+    /// Bucket per IP address
+    by_ip_address: HashMap<IpAddr, Bucket>,
+
+    /// IP addresses by the last update of their bucket, the least recently updated first
+    by_updated_at: BTreeSet<(Instant, IpAddr)>,
+}
+
+impl Buckets {
+    /// The bucket of the IP address, a full bucket when the IP address is not tracked yet
+    ///
+    /// The least recently updated bucket is forgotten when [`MAX_BUCKETS`] are tracked already
+    fn take(&mut self, ip_address: IpAddr, limits: Limits, now: Instant) -> Bucket {
+        if let Some(bucket) = self.by_ip_address.remove(&ip_address) {
+            self.by_updated_at.remove(&(bucket.updated_at, ip_address));
+
+            return bucket;
+        }
+
+        while self.by_ip_address.len() >= MAX_BUCKETS {
+            let Some((_, oldest)) = self.by_updated_at.pop_first() else {
+                break;
+            };
+
+            self.by_ip_address.remove(&oldest);
+        }
+
+        Bucket {
+            tokens: limits.burst,
+            updated_at: now,
+        }
+    }
+
+    /// Keep track of the updated bucket of the IP address
+    fn put(&mut self, ip_address: IpAddr, bucket: Bucket) {
+        self.by_updated_at.insert((bucket.updated_at, ip_address));
+        self.by_ip_address.insert(ip_address, bucket);
+    }
+
+    /// Number of tracked IP addresses
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.by_ip_address.len()
+    }
+}
+
+/// Per IP address rate limit of the root
+///
+/// Disabled by default, clones share the same limits and buckets
+#[derive(Clone, Debug, Default)]
+pub struct RateLimit {
+    /// Limits of the buckets, `None` when rate limiting is disabled
+    limits: Arc<RwLock<Option<Limits>>>,
+
+    /// Buckets per IP address
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimit {
+    /// Create a rate limit of `rate` requests per second, with bursts of up to `burst` requests
+    pub fn new(rate: f64, burst: f64) -> Self {
+        Self {
+            limits: Arc::new(RwLock::new(Some(Limits {
+                rate,
+                burst: burst.max(1.0),
+            }))),
+            buckets: Arc::default(),
+        }
+    }
+
+    /// Setup the rate limit based on the `RATE_LIMIT` and `RATE_LIMIT_BURST` environment
+    /// variables
+    ///
+    /// Rate limiting is disabled without a `RATE_LIMIT`, the burst defaults to the rate
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the rate or burst are not positive numbers
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let Some(rate) = env_var_optional("RATE_LIMIT") else {
+            return Ok(Self::default());
+        };
+
+        let rate = parse_positive_fraction("RATE_LIMIT", &rate)?;
+
+        let burst = env_var_optional("RATE_LIMIT_BURST")
+            .map(|burst| parse_positive_fraction("RATE_LIMIT_BURST", &burst))
+            .transpose()?
+            .unwrap_or(rate);
+
+        Ok(Self::new(rate, burst))
+    }
+
+    /// Take a token for a request of the IP address
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` with the time until a request is allowed again, when the bucket of the
+    /// IP address is empty
+    pub fn check(&self, ip_address: IpAddr) -> Result<(), Duration> {
+        self.check_at(ip_address, Instant::now())
+    }
+
+    /// Use the limits of the reloaded rate limit, the buckets are kept
+    pub fn reload(&self, reloaded: &Self) {
+        let limits = *reloaded.limits.read().expect("Valid rate limit lock");
+
+        *self.limits.write().expect("Valid rate limit lock") = limits;
+    }
+
+    /// Take a token for a request of the IP address, at the given time
+    fn check_at(&self, ip_address: IpAddr, now: Instant) -> Result<(), Duration> {
+        let Some(limits) = *self.limits.read().expect("Valid rate limit lock") else {
+            return Ok(());
+        };
+
+        let mut buckets = self.buckets.lock().expect("Valid rate limit lock");
+
+        let mut bucket = buckets.take(ip_address, limits, now);
+
+        bucket.tokens = bucket.tokens_at(limits, now);
+        bucket.updated_at = now;
+
+        let result = if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / limits.rate))
+        };
+
+        buckets.put(ip_address, bucket);
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn test_disabled() {
+        let rate_limit = RateLimit::default();
+        let ip_address = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+        for _ in 0..100 {
+            assert_eq!(Ok(()), rate_limit.check(ip_address));
+        }
+    }
+
+    #[test]
+    fn test_burst_and_refill() {
+        let rate_limit = RateLimit::new(2.0, 3.0);
+        let ip_address = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let other_ip_address = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert_eq!(Ok(()), rate_limit.check_at(ip_address, now));
+        }
+
+        assert_eq!(
+            Err(Duration::from_millis(500)),
+            rate_limit.check_at(ip_address, now)
+        );
+
+        // other IP addresses have their own bucket
+        assert_eq!(Ok(()), rate_limit.check_at(other_ip_address, now));
+
+        // refilled a single token
+        let later = now + Duration::from_millis(500);
+        assert_eq!(Ok(()), rate_limit.check_at(ip_address, later));
+        assert!(rate_limit.check_at(ip_address, later).is_err());
+    }
+
+    #[test]
+    fn test_reload() {
+        let rate_limit = RateLimit::new(1.0, 1.0);
+        let ip_address = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let now = Instant::now();
+
+        assert_eq!(Ok(()), rate_limit.check_at(ip_address, now));
+        assert!(rate_limit.check_at(ip_address, now).is_err());
+
+        rate_limit.reload(&RateLimit::default());
+        assert_eq!(Ok(()), rate_limit.check_at(ip_address, now));
+
+        // the bucket is kept, still empty
+        rate_limit.reload(&RateLimit::new(1.0, 1.0));
+        assert!(rate_limit.check_at(ip_address, now).is_err());
+    }
+
+    #[test]
+    fn test_max_buckets() {
+        let rate_limit = RateLimit::new(0.001, 1.0);
+        let ip_address = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let now = Instant::now();
+
+        assert_eq!(Ok(()), rate_limit.check_at(ip_address, now));
+        assert!(rate_limit.check_at(ip_address, now).is_err());
+
+        let max_buckets = u32::try_from(MAX_BUCKETS).unwrap();
+        for i in 0..max_buckets {
+            let other_ip_address = IpAddr::V4(Ipv4Addr::from(0x0a00_0000 + i));
+            let later = now + Duration::from_millis(u64::from(i) + 1);
+
+            assert_eq!(Ok(()), rate_limit.check_at(other_ip_address, later));
+        }
+
+        assert_eq!(
+            MAX_BUCKETS,
+            rate_limit
+                .buckets
+                .lock()
+                .expect("Valid rate limit lock")
+                .len()
+        );
+
+        // the least recently seen IP address is forgotten, with a full bucket again
+        let later = now + Duration::from_millis(u64::from(max_buckets) + 1);
+        assert_eq!(Ok(()), rate_limit.check_at(ip_address, later));
+    }
+}
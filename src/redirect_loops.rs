@@ -0,0 +1,194 @@
+//! Redirect loop detection
+//!
+//! A destination can point back at Shurly itself, directly or via a chain of other
+//! destinations. When that chain ends up at the slug it started with, clients would bounce
+//! forever. These loops are refused when a destination is created or updated, and broken when
+//! they show up anyway (like with a new hostname).
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use axum::http::header::HOST;
+use axum::http::HeaderMap;
+use percent_encoding::percent_decode_str;
+use url::Url;
+
+use crate::database::Database;
+use crate::database::Error;
+use crate::slug_cache::SlugFoundCache;
+use crate::utils::env_var_optional;
+use crate::utils::env_var_or_else;
+
+/// Default number of destinations followed before giving up
+const DEFAULT_MAX_DEPTH: &str = "10";
+
+/// A detected loop
+#[derive(Debug, PartialEq, Eq)]
+pub enum RedirectLoop {
+    /// The chain ends up at the slug it started with
+    Loop,
+
+    /// The chain is longer than the maximum depth
+    TooDeep,
+}
+
+impl RedirectLoop {
+    /// Description of the loop, safe to show to users
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Loop => "URL redirects back to this slug",
+            Self::TooDeep => "URL redirects via too many other slugs",
+        }
+    }
+}
+
+/// Settings of the redirect loop detection
+#[derive(Clone)]
+pub struct LoopDetection {
+    /// Hostnames Shurly is served on, next to the `Host` of the request
+    hostnames: Vec<String>,
+
+    /// Maximum number of destinations followed
+    max_depth: usize,
+}
+
+impl LoopDetection {
+    /// Setup the loop detection based on the `HOSTNAMES` and `REDIRECT_MAX_DEPTH` environment
+    /// variables
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the maximum depth is not a valid number
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let hostnames = env_var_optional("HOSTNAMES")
+            .map(|hostnames| {
+                hostnames
+                    .split(',')
+                    .map(|hostname| hostname.trim().to_lowercase())
+                    .filter(|hostname| !hostname.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let max_depth = env_var_or_else("REDIRECT_MAX_DEPTH", || DEFAULT_MAX_DEPTH.to_string())
+            .parse::<usize>()?;
+
+        Ok(Self {
+            hostnames,
+            max_depth,
+        })
+    }
+
+    /// Hostnames Shurly is served on, as configured
+    pub fn hostnames(&self) -> &[String] {
+        &self.hostnames
+    }
+
+    /// Detect if the URL of the slug (on the domain, or all domains) loops back to the slug, via
+    /// Shurly itself
+    ///
+    /// Only URLs pointing at one of the hostnames of Shurly (or the `Host` of the request) are
+    /// followed, by looking up their destinations in the cache of slugs. No requests are made.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when a destination of the chain could not be looked up
+    pub async fn detect(
+        &self,
+        database: &Database,
+        slug_cache: &SlugFoundCache,
+        headers: &HeaderMap,
+        domain: Option<&str>,
+        slug: &str,
+        url: &Url,
+    ) -> Result<Option<RedirectLoop>, Arc<Error>> {
+        let hostnames = self.hostnames_for_request(headers);
+
+        let mut visited = HashSet::new();
+        let mut url = url.clone();
+
+        for _ in 0..self.max_depth {
+            let Some((next_domain, next_slug)) = own_slug(&hostnames, &url) else {
+                return Ok(None);
+            };
+
+            let is_start = next_slug == slug && domain.is_none_or(|domain| domain == next_domain);
+
+            if is_start || !visited.insert((next_domain.clone(), next_slug.clone())) {
+                return Ok(Some(RedirectLoop::Loop));
+            }
+
+            let destination = slug_cache
+                .follow(database, Some(&next_domain), &next_slug)
+                .await?;
+
+            let Some(destination) = destination.filter(|d| !d.is_deleted()) else {
+                return Ok(None);
+            };
+
+            let Ok(next_url) = Url::parse(&destination.url) else {
+                return Ok(None);
+            };
+
+            url = next_url;
+        }
+
+        if own_slug(&hostnames, &url).is_some() {
+            Ok(Some(RedirectLoop::TooDeep))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// All hostnames of Shurly, including the `Host` of the request
+    fn hostnames_for_request(&self, headers: &HeaderMap) -> Vec<String> {
+        let mut hostnames = self.hostnames.clone();
+
+        if let Some(host) = headers.get(HOST).and_then(|host| host.to_str().ok()) {
+            hostnames.push(host.to_lowercase());
+        }
+
+        hostnames
+    }
+}
+
+/// The domain and slug of the URL, when it points at one of the hostnames of Shurly
+fn own_slug(hostnames: &[String], url: &Url) -> Option<(String, String)> {
+    let host = url.host_str()?.to_lowercase();
+
+    let authority = match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.clone(),
+    };
+
+    if !hostnames.contains(&authority) {
+        return None;
+    }
+
+    let slug = url.path().trim_matches('/');
+
+    percent_decode_str(slug)
+        .decode_utf8()
+        .ok()
+        .map(|slug| (host, slug.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_own_slug() {
+        let hostnames = vec!["sho.rt".to_string(), "localhost:7000".to_string()];
+
+        let own = |url: &str| own_slug(&hostnames, &Url::parse(url).unwrap());
+
+        let slug = |domain: &str, slug: &str| Some((domain.to_string(), slug.to_string()));
+
+        assert_eq!(slug("sho.rt", "abc"), own("https://sho.rt/abc"));
+        assert_eq!(slug("sho.rt", "a b"), own("https://SHO.RT/a%20b/"));
+        assert_eq!(slug("localhost", "abc"), own("http://localhost:7000/abc"));
+        assert_eq!(None, own("http://localhost/abc"));
+        assert_eq!(None, own("https://www.example.com/abc"));
+    }
+}
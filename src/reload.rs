@@ -0,0 +1,69 @@
+//! Reloading of settings, without a restart
+//!
+//! A selection of the settings is reloaded on `SIGHUP` and via `/api/config/reload`:
+//!
+//! - `HOMEPAGE_URL`
+//! - `RATE_LIMIT` and `RATE_LIMIT_BURST`
+//! - `URL_BLOCKLIST_FILE`, `SAFE_BROWSING_API_KEY` and `SAFE_BROWSING_URL`
+//! - `SLUG_MAX_LENGTH`, `SLUG_CHARACTERS` and `RESERVED_SLUGS`
+//!
+//! The environment of a running process does not change, the configuration file of
+//! `SHURLY_CONFIG` is read again; environment variables still take precedence. Nothing is
+//! reloaded when any of the settings is invalid. The cache of slugs, the buffered hits and the
+//! buckets of the rate limit are kept.
+
+use crate::config;
+use crate::rate_limit::RateLimit;
+use crate::reputation::UrlReputation;
+use crate::root::Homepage;
+use crate::root::Settings;
+use crate::slug_policy::SlugPolicy;
+
+/// Reload the settings
+///
+/// # Errors
+///
+/// Will return `Err` when the configuration file or any of the settings is invalid, the current
+/// settings are kept
+pub fn reload(settings: &Settings) -> anyhow::Result<()> {
+    config::load()?;
+
+    let homepage = Homepage::from_environment()?;
+    let rate_limit = RateLimit::from_environment()?;
+    let url_reputation = UrlReputation::from_environment()?;
+    let slug_policy = SlugPolicy::from_environment()?;
+
+    settings.homepage.reload(&homepage);
+    settings.rate_limit.reload(&rate_limit);
+    settings.url_reputation.reload(&url_reputation);
+    settings.slug_policy.reload(&slug_policy);
+
+    tracing::info!("Settings reloaded");
+
+    Ok(())
+}
+
+/// Reload the settings on every `SIGHUP`, in the background
+///
+/// Does nothing on platforms without signals
+pub fn listen(settings: &Settings) {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::signal;
+        use tokio::signal::unix::SignalKind;
+
+        let mut hangup = signal(SignalKind::hangup()).expect("Valid hangup handler");
+        let settings = settings.clone();
+
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                if let Err(err) = reload(&settings) {
+                    tracing::error!("Could not reload the settings: {err:#}");
+                }
+            }
+        });
+    }
+
+    #[cfg(not(unix))]
+    let _ = settings;
+}
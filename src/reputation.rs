@@ -0,0 +1,472 @@
+//! Reputation of the URLs of destinations
+//!
+//! URLs can be checked against a local blocklist feed (`URL_BLOCKLIST_FILE`) and the Safe
+//! Browsing API of Google (`SAFE_BROWSING_API_KEY`). New and updated URLs that are flagged are
+//! refused. The `check-url-reputation` job checks the existing destinations every hour, on the
+//! leader, and disables the flagged ones; every disabled destination is on the audit trail.
+//! Updating the URL of a disabled destination enables it again.
+//!
+//! The blocklist has an entry per line: a hostname flags the URLs of the host and its subdomains,
+//! a URL flags the URLs starting with it. Empty lines and lines starting with `#` are skipped.
+//! The file is read for every check, an updated feed is picked up right away.
+//!
+//! When the Safe Browsing API can not be reached, new URLs are not refused; the check fails open.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use serde::Deserialize;
+use serde_json::json;
+use url::Url;
+
+use crate::database::AuditEntry;
+use crate::database::Database;
+use crate::notifier::Notification;
+use crate::notifier::Notifier;
+use crate::slug_cache;
+use crate::utils::env_var_optional;
+use crate::utils::env_var_or_else;
+
+/// Default endpoint of the Safe Browsing API
+const DEFAULT_SAFE_BROWSING_URL: &str = "https://safebrowsing.googleapis.com/v4/threatMatches:find";
+
+/// Maximum number of URLs per request to the Safe Browsing API
+const SAFE_BROWSING_BATCH_SIZE: usize = 500;
+
+/// Timeout of a request to the Safe Browsing API
+const SAFE_BROWSING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Reason of the URLs flagged by the blocklist
+const BLOCKLIST_REASON: &str = "Listed on the blocklist";
+
+/// The checks of the URLs
+#[derive(Clone, Debug, Default)]
+struct Checks {
+    /// Path of the blocklist feed
+    blocklist: Option<PathBuf>,
+
+    /// The Safe Browsing API
+    safe_browsing: Option<SafeBrowsing>,
+}
+
+/// Checks of the reputation of URLs, disabled by default
+///
+/// Clones share the same checks
+#[derive(Clone, Debug, Default)]
+pub struct UrlReputation {
+    /// The checks, replaced when reloaded
+    checks: Arc<RwLock<Checks>>,
+}
+
+impl UrlReputation {
+    /// Setup the checks based on the `URL_BLOCKLIST_FILE`, `SAFE_BROWSING_API_KEY` and
+    /// `SAFE_BROWSING_URL` environment variables
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the blocklist can not be read
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let mut url_reputation = Self::default();
+
+        if let Some(path) = env_var_optional("URL_BLOCKLIST_FILE") {
+            url_reputation = url_reputation.with_blocklist(path);
+
+            // a typo is better found on startup than on the first check
+            url_reputation.checks().blocklist()?;
+        }
+
+        if let Some(api_key) = env_var_optional("SAFE_BROWSING_API_KEY") {
+            let url = env_var_or_else("SAFE_BROWSING_URL", || {
+                DEFAULT_SAFE_BROWSING_URL.to_string()
+            });
+
+            url_reputation = url_reputation.with_safe_browsing(&url, &api_key);
+        }
+
+        Ok(url_reputation)
+    }
+
+    /// Check against the blocklist feed at the path
+    #[must_use]
+    pub fn with_blocklist<P>(self, path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.checks
+            .write()
+            .expect("Valid reputation lock")
+            .blocklist = Some(path.into());
+        self
+    }
+
+    /// Check against the Safe Browsing API at the URL, with the API key
+    #[must_use]
+    pub fn with_safe_browsing(self, url: &str, api_key: &str) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(SAFE_BROWSING_TIMEOUT)
+            .user_agent(concat!("Shurly/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .expect("Valid HTTP client");
+
+        self.checks
+            .write()
+            .expect("Valid reputation lock")
+            .safe_browsing = Some(SafeBrowsing {
+            client,
+            url: url.to_string(),
+            api_key: api_key.to_string(),
+        });
+        self
+    }
+
+    /// Use the checks of the reloaded reputation
+    pub fn reload(&self, reloaded: &Self) {
+        let checks = reloaded.checks();
+
+        *self.checks.write().expect("Valid reputation lock") = checks;
+    }
+
+    /// Are any URLs checked?
+    pub fn is_enabled(&self) -> bool {
+        let checks = self.checks();
+
+        checks.blocklist.is_some() || checks.safe_browsing.is_some()
+    }
+
+    /// Check the URLs, the flagged URLs with the reason
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the blocklist can not be read or the Safe Browsing API can not be
+    /// reached
+    pub async fn check(&self, urls: &[&str]) -> anyhow::Result<HashMap<String, String>> {
+        let checks = self.checks();
+        let mut flagged = HashMap::new();
+
+        if let Some(blocklist) = checks.blocklist()? {
+            for url in urls {
+                if blocklist.contains(url) {
+                    flagged.insert((*url).to_string(), BLOCKLIST_REASON.to_string());
+                }
+            }
+        }
+
+        if let Some(ref safe_browsing) = checks.safe_browsing {
+            let unflagged = urls
+                .iter()
+                .filter(|url| !flagged.contains_key(**url))
+                .copied()
+                .collect::<Vec<_>>();
+
+            for batch in unflagged.chunks(SAFE_BROWSING_BATCH_SIZE) {
+                flagged.extend(safe_browsing.find_threats(batch).await?);
+            }
+        }
+
+        Ok(flagged)
+    }
+
+    /// Why the new URL is flagged, `None` when it is not or it could not be checked
+    pub async fn flagged_reason(&self, url: &str) -> Option<String> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        match self.check(&[url]).await {
+            Ok(mut flagged) => flagged.remove(url),
+            Err(err) => {
+                tracing::warn!("Could not check the reputation of {url}: {err}");
+                None
+            }
+        }
+    }
+
+    /// The current checks
+    fn checks(&self) -> Checks {
+        self.checks.read().expect("Valid reputation lock").clone()
+    }
+}
+
+impl Checks {
+    /// The current blocklist, `None` without a blocklist
+    fn blocklist(&self) -> anyhow::Result<Option<Blocklist>> {
+        self.blocklist
+            .as_ref()
+            .map(|path| {
+                std::fs::read_to_string(path)
+                    .map(|content| Blocklist::parse(&content))
+                    .map_err(|err| anyhow!("Could not read {}: {err}", path.display()))
+            })
+            .transpose()
+    }
+}
+
+/// Outcome of a check of the existing destinations
+#[derive(Clone, Debug, Default)]
+pub struct Checked {
+    /// Number of checked destinations
+    pub checked: usize,
+
+    /// Slugs of the destinations disabled by the check
+    pub flagged: Vec<String>,
+}
+
+impl Checked {
+    /// Description of the check, for the status of the job
+    pub fn describe(&self) -> String {
+        format!(
+            "Checked {} destination(s), flagged {}",
+            self.checked,
+            self.flagged.len()
+        )
+    }
+}
+
+/// Check the URLs of the destinations that are not disabled yet, disabling the flagged ones
+///
+/// The disabled slugs are forgotten by the caches of all instances, the creators of the
+/// destinations are notified
+///
+/// # Errors
+///
+/// Will return `Err` when the destinations could not be checked or disabled
+pub async fn run(
+    database: &Database,
+    url_reputation: &UrlReputation,
+    notifier: &Notifier,
+) -> anyhow::Result<Checked> {
+    let destinations = database
+        .find_all_destinations()
+        .await?
+        .into_iter()
+        .filter(|destination| !destination.is_flagged())
+        .collect::<Vec<_>>();
+
+    let urls = destinations
+        .iter()
+        .map(|destination| destination.url.as_str())
+        .collect::<Vec<_>>();
+
+    let flagged = url_reputation.check(&urls).await?;
+
+    let mut checked = Checked {
+        checked: destinations.len(),
+        flagged: Vec::new(),
+    };
+
+    for destination in &destinations {
+        let Some(reason) = flagged.get(&destination.url) else {
+            continue;
+        };
+
+        let flagged_destination = database.flag_destination(destination, reason).await?;
+
+        database
+            .register_system_audit_trail(&AuditEntry::FlagDestination(&flagged_destination))
+            .await?;
+
+        slug_cache::invalidate_everywhere(database, &destination.slug).await;
+
+        tracing::warn!(
+            r#"Slug "{}" is disabled, its URL is flagged: {reason}"#,
+            destination.slug
+        );
+
+        if let Some(user) = database
+            .find_single_user_by_id(&destination.user_id)
+            .await?
+        {
+            notifier.notify(
+                &user,
+                &Notification::FlaggedDestination {
+                    slug: destination.slug.clone(),
+                    url: destination.url.clone(),
+                    reason: reason.clone(),
+                },
+            );
+        }
+
+        checked.flagged.push(flagged_destination.slug);
+    }
+
+    Ok(checked)
+}
+
+/// Entries of the blocklist feed
+#[derive(Debug, Default)]
+struct Blocklist {
+    /// Hostnames, flagging the host and its subdomains
+    hostnames: Vec<String>,
+
+    /// URLs, flagging the URLs starting with them
+    urls: Vec<String>,
+}
+
+impl Blocklist {
+    /// Parse the content of the feed, an entry per line
+    fn parse(content: &str) -> Self {
+        let mut blocklist = Self::default();
+
+        for entry in content.lines().map(str::trim) {
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+
+            if entry.contains("://") {
+                if let Ok(url) = Url::parse(entry) {
+                    blocklist.urls.push(url.to_string());
+                }
+            } else if let Ok(hostname) = idna::domain_to_ascii(entry.trim_end_matches('.')) {
+                blocklist.hostnames.push(hostname);
+            }
+        }
+
+        blocklist
+    }
+
+    /// Is the URL on the blocklist?
+    fn contains(&self, url: &str) -> bool {
+        let Ok(url) = Url::parse(url) else {
+            return false;
+        };
+
+        let is_listed_host = url.host_str().is_some_and(|host| {
+            self.hostnames.iter().any(|hostname| {
+                host == hostname
+                    || host
+                        .strip_suffix(hostname.as_str())
+                        .is_some_and(|subdomain| subdomain.ends_with('.'))
+            })
+        });
+
+        is_listed_host
+            || self
+                .urls
+                .iter()
+                .any(|listed| url.as_str().starts_with(listed))
+    }
+}
+
+/// Client of the Safe Browsing API
+#[derive(Clone, Debug)]
+struct SafeBrowsing {
+    /// The HTTP client
+    client: reqwest::Client,
+
+    /// Endpoint to find the threats
+    url: String,
+
+    /// Key of the API
+    api_key: String,
+}
+
+/// Response of the Safe Browsing API, without any matches for safe URLs
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ThreatMatches {
+    /// The matching threats
+    #[serde(default)]
+    matches: Vec<ThreatMatch>,
+}
+
+/// A threat matching a URL
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ThreatMatch {
+    /// Type of the threat, like `MALWARE`
+    threat_type: String,
+
+    /// The matching URL
+    threat: ThreatEntry,
+}
+
+/// The URL of a threat
+#[derive(Debug, Deserialize)]
+struct ThreatEntry {
+    /// The URL
+    url: String,
+}
+
+impl SafeBrowsing {
+    /// Find the threats of the URLs, the threatened URLs with the reason
+    async fn find_threats(&self, urls: &[&str]) -> anyhow::Result<HashMap<String, String>> {
+        let body = json!({
+            "client": {
+                "clientId": "shurly",
+                "clientVersion": env!("CARGO_PKG_VERSION"),
+            },
+            "threatInfo": {
+                "threatTypes": [
+                    "MALWARE",
+                    "SOCIAL_ENGINEERING",
+                    "UNWANTED_SOFTWARE",
+                    "POTENTIALLY_HARMFUL_APPLICATION",
+                ],
+                "platformTypes": ["ANY_PLATFORM"],
+                "threatEntryTypes": ["URL"],
+                "threatEntries": urls.iter().map(|url| json!({ "url": url })).collect::<Vec<_>>(),
+            },
+        });
+
+        let response = self
+            .client
+            .post(&self.url)
+            .query(&[("key", &self.api_key)])
+            .header("content-type", "application/json")
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|err| anyhow!("Could not reach Safe Browsing: {err}"))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(anyhow!("Safe Browsing responded with {status}"));
+        }
+
+        let body = response
+            .text()
+            .await
+            .map_err(|err| anyhow!("Could not reach Safe Browsing: {err}"))?;
+
+        let threat_matches = serde_json::from_str::<ThreatMatches>(&body)
+            .map_err(|err| anyhow!("Invalid response of Safe Browsing: {err}"))?;
+
+        Ok(threat_matches
+            .matches
+            .into_iter()
+            .map(|threat_match| {
+                (
+                    threat_match.threat.url,
+                    format!("Flagged by Safe Browsing as {}", threat_match.threat_type),
+                )
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocklist() {
+        let blocklist = Blocklist::parse(
+            "# phishing\n\
+            \n\
+            evil.example.com\n\
+            https://www.example.org/downloads/\n",
+        );
+
+        assert!(blocklist.contains("https://evil.example.com/login"));
+        assert!(blocklist.contains("http://www.evil.example.com/"));
+        assert!(!blocklist.contains("https://notevil.example.com/"));
+        assert!(!blocklist.contains("https://example.com/"));
+
+        assert!(blocklist.contains("https://www.example.org/downloads/setup.exe"));
+        assert!(!blocklist.contains("https://www.example.org/"));
+    }
+}
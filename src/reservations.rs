@@ -0,0 +1,28 @@
+//! Reservations of slugs
+//!
+//! On a shared instance, the names of upcoming campaigns can be squatted before their URL is
+//! known. A user can reserve a batch of slugs without a URL; only that user can create
+//! destinations with the slugs, which releases the reservation. Reservations are per domain, like
+//! the slugs of destinations, and stay until they are used or released.
+
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+/// A slug reserved by a user
+#[derive(Clone, Debug)]
+pub struct SlugReservation {
+    /// Reservation ID
+    pub id: Uuid,
+
+    /// The ID of the user that reserved the slug
+    pub user_id: Uuid,
+
+    /// The reserved slug
+    pub slug: String,
+
+    /// Domain of the slug, all domains when not set
+    pub domain: Option<String>,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+}
@@ -2,96 +2,1144 @@
 //!
 //! The most important part of Shurly, the actual redirect logic
 
+use std::sync::Arc;
+use std::sync::RwLock;
+
+use axum::http::header::HeaderName;
+use axum::http::header::CACHE_CONTROL;
+use axum::http::header::CONTENT_TYPE;
+use axum::http::header::REFERER;
+use axum::http::header::RETRY_AFTER;
+use axum::http::HeaderMap;
+use axum::http::HeaderValue;
+use axum::http::Method;
 use axum::http::StatusCode;
 use axum::http::Uri;
 use axum::response::Html;
+use axum::response::IntoResponse;
 use axum::response::Redirect;
+use axum::response::Response;
 use axum::Extension;
-use axum_client_ip::InsecureClientIp;
 use axum_extra::headers::UserAgent;
 use axum_extra::TypedHeader;
+use chrono::Utc;
 use percent_encoding::percent_decode_str;
+use tracing::Span;
+use url::Url;
 
+use crate::activity::Activity;
+use crate::approval::Approval;
+use crate::auth_metrics::AuthMetrics;
+use crate::broker::Broker;
+use crate::client_ip::ClientIp;
+use crate::client_ip::ClientIpSettings;
+use crate::confusables::Confusables;
 use crate::database::Database;
+use crate::destinations::Destination;
+use crate::digests::MAX_MISSED_SLUG_LENGTH;
+use crate::domains;
+use crate::hashed_slugs::HashedSlugs;
+use crate::hit_buffer::HitBuffer;
+use crate::hooks::HookRequest;
+use crate::hooks::RedirectHooks;
+use crate::ingest::IngestKeys;
+use crate::jobs::Jobs;
+use crate::limits::DatabaseDeadline;
+use crate::notifier::Notifier;
+use crate::permissions::Permissions;
+use crate::quotas::Quotas;
+use crate::rate_limit::RateLimit;
+use crate::rate_limit::REPORT_BURST;
+use crate::rate_limit::REPORT_RATE;
+use crate::redirect_loops::LoopDetection;
+use crate::reputation::UrlReputation;
+use crate::scripts::Scripts;
+use crate::signing::SignatureError;
+use crate::signing::SigningKey;
+use crate::slack::Slack;
+use crate::slug_cache::SlugFoundCache;
+use crate::slug_policy::SlugPolicy;
+use crate::stats_cache::StatsCache;
+use crate::templates::Templates;
+use crate::utils::encode_slug;
+use crate::utils::env_var_optional;
+use crate::utils::env_var_or_else;
+use crate::webhooks::Webhooks;
+
+/// Settings of the root, configured on startup
+#[derive(Clone)]
+pub struct Settings {
+    /// Templates for the served pages
+    pub templates: Templates,
+
+    /// What to show when the empty slug has no destination
+    pub homepage: Homepage,
+
+    /// The `Cache-Control` headers sent with redirects
+    pub cache_control: CacheControl,
+
+    /// Suggestions of similar slugs on the 404 page
+    pub suggestions: Suggestions,
+
+    /// How to handle visitors that do not want to be tracked
+    pub do_not_track: DoNotTrack,
+
+    /// Detection of destinations redirecting back to themselves
+    pub loop_detection: LoopDetection,
+
+    /// Policy for the slugs of new destinations
+    pub slug_policy: SlugPolicy,
+
+    /// Slugs derived from the URL of new destinations without a slug
+    pub hashed_slugs: HashedSlugs,
+
+    /// How to handle new slugs confusable with a Latin slug
+    pub confusable_slugs: Confusables,
+
+    /// How to handle new URLs with a hostname confusable with a Latin hostname
+    pub confusable_urls: Confusables,
+
+    /// Checks of the reputation of new URLs, against a blocklist or Safe Browsing
+    pub url_reputation: UrlReputation,
+
+    /// Which new destinations need the approval of an admin
+    pub approval: Approval,
+
+    /// The actions every role is allowed to perform via the API
+    pub permissions: Arc<Permissions>,
+
+    /// Maximum number of destinations and hits per month of every role
+    pub quotas: Quotas,
+
+    /// Key for the signed links of private destinations
+    pub signing_key: SigningKey,
+
+    /// Per IP address rate limit of the root
+    pub rate_limit: RateLimit,
+
+    /// Per IP address rate limit of the abuse reports, always enabled
+    pub report_limit: RateLimit,
+
+    /// Cache of the destinations of slugs
+    pub slug_cache: SlugFoundCache,
+
+    /// Cache of the stats of destinations, forgotten by the jobs changing the rollups
+    pub stats_cache: StatsCache,
+
+    /// Deadline for the work in the database of a single request
+    pub database_deadline: DatabaseDeadline,
+
+    /// How to find the IP address of visitors, for the hits and the rate limit
+    pub client_ip: ClientIpSettings,
+
+    /// Custom logic in the redirect pipeline, registered by embedders of Shurly
+    pub hooks: RedirectHooks,
+
+    /// Engine for the scripts of destinations
+    pub scripts: Scripts,
+
+    /// Recurring jobs, like the rollup of hits
+    pub jobs: Jobs,
+
+    /// Buffer of the hits, saved right away by default
+    pub hits: HitBuffer,
+
+    /// Sender of the deliveries of the webhooks
+    pub webhooks: Webhooks,
+
+    /// Publisher of the events to a message broker
+    pub broker: Broker,
+
+    /// Sender of the email notifications of the users
+    pub notifier: Notifier,
+
+    /// Live activity of the admins, for the event stream of the API
+    pub activity: Activity,
+
+    /// Slash-commands of Slack, disabled by default
+    pub slack: Slack,
+
+    /// Keys of the edges reporting hits, disabled by default
+    pub ingest_keys: IngestKeys,
+
+    /// Counters of the outcomes of the authentication of the API
+    pub auth_metrics: AuthMetrics,
+}
+
+impl Settings {
+    /// Setup the settings of the root based on the environment
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when any of the settings is invalid
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let url_reputation = UrlReputation::from_environment()?;
+        let notifier = Notifier::from_environment()?;
+        let stats_cache = StatsCache::from_environment()?;
+
+        Ok(Self {
+            templates: Templates::from_environment()?,
+            homepage: Homepage::from_environment()?,
+            cache_control: CacheControl::from_environment()?,
+            suggestions: Suggestions::from_environment()?,
+            do_not_track: DoNotTrack::from_environment()?,
+            loop_detection: LoopDetection::from_environment()?,
+            slug_policy: SlugPolicy::from_environment()?,
+            hashed_slugs: HashedSlugs::from_environment()?,
+            confusable_slugs: Confusables::from_environment("SLUG_CONFUSABLES")?,
+            confusable_urls: Confusables::from_environment("URL_CONFUSABLES")?,
+            url_reputation: url_reputation.clone(),
+            approval: Approval::from_environment()?,
+            permissions: Arc::new(Permissions::from_environment()?),
+            quotas: Quotas::from_environment()?,
+            signing_key: SigningKey::from_environment(),
+            rate_limit: RateLimit::from_environment()?,
+            report_limit: RateLimit::new(REPORT_RATE, REPORT_BURST),
+            slug_cache: SlugFoundCache::from_environment()?,
+            stats_cache: stats_cache.clone(),
+            database_deadline: DatabaseDeadline::from_environment()?,
+            client_ip: ClientIpSettings::from_environment()?,
+            hooks: RedirectHooks::default(),
+            scripts: Scripts::default(),
+            jobs: Jobs::from_environment()?
+                .with_url_reputation(url_reputation)
+                .with_notifier(notifier.clone())
+                .with_stats_cache(stats_cache),
+            hits: HitBuffer::from_environment()?,
+            webhooks: Webhooks::default(),
+            broker: Broker::from_environment()?,
+            notifier,
+            activity: Activity::default(),
+            slack: Slack::from_environment()?,
+            ingest_keys: IngestKeys::from_environment()?,
+            auth_metrics: AuthMetrics::default(),
+        })
+    }
+}
+
+/// What to show when the empty slug has no destination
+///
+/// Without configuration, the regular 404 page is shown. Clones share the same homepage
+#[derive(Clone, Default)]
+pub struct Homepage {
+    /// Redirect to this URL, like a marketing site
+    url: Arc<RwLock<Option<Url>>>,
+}
+
+impl Homepage {
+    /// Setup the homepage based on the `HOMEPAGE_URL` environment variable
+    ///
+    /// A homepage template can be configured with the templates, the URL takes precedence
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the URL is not valid
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let url = env_var_optional("HOMEPAGE_URL")
+            .map(|url| Url::parse(&url))
+            .transpose()?;
+
+        Ok(Self {
+            url: Arc::new(RwLock::new(url)),
+        })
+    }
+
+    /// Use the URL of the reloaded homepage
+    pub fn reload(&self, reloaded: &Self) {
+        let url = reloaded.url();
+
+        *self.url.write().expect("Valid homepage lock") = url;
+    }
+
+    /// The URL to redirect to, if any
+    fn url(&self) -> Option<Url> {
+        self.url.read().expect("Valid homepage lock").clone()
+    }
+}
+
+/// Default `Cache-Control` for permanent redirects, these never change
+const DEFAULT_CACHE_CONTROL_PERMANENT: &str = "public, max-age=31536000, immutable";
+
+/// Default `Cache-Control` for temporary redirects, these can change at any time
+const DEFAULT_CACHE_CONTROL_TEMPORARY: &str = "no-store";
+
+/// `Cache-Control` for private destinations, regardless of the type of redirect
+const PRIVATE_CACHE_CONTROL: &str = "private, no-store";
+
+/// The `Cache-Control` headers sent with redirects
+#[derive(Clone)]
+pub struct CacheControl {
+    /// For permanent redirects
+    permanent: HeaderValue,
+
+    /// For temporary redirects
+    temporary: HeaderValue,
+}
+
+impl Default for CacheControl {
+    fn default() -> Self {
+        Self {
+            permanent: HeaderValue::from_static(DEFAULT_CACHE_CONTROL_PERMANENT),
+            temporary: HeaderValue::from_static(DEFAULT_CACHE_CONTROL_TEMPORARY),
+        }
+    }
+}
+
+impl CacheControl {
+    /// Setup the `Cache-Control` headers based on the `CACHE_CONTROL_PERMANENT` and
+    /// `CACHE_CONTROL_TEMPORARY` environment variables
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when a value is not a valid header value
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let mut cache_control = Self::default();
+
+        if let Some(permanent) = env_var_optional("CACHE_CONTROL_PERMANENT") {
+            cache_control.permanent = HeaderValue::from_str(&permanent)?;
+        }
+
+        if let Some(temporary) = env_var_optional("CACHE_CONTROL_TEMPORARY") {
+            cache_control.temporary = HeaderValue::from_str(&temporary)?;
+        }
+
+        Ok(cache_control)
+    }
+
+    /// Permanent redirect with the matching `Cache-Control` header
+    fn permanent(&self, url: &str) -> Response {
+        (
+            [(CACHE_CONTROL, self.permanent.clone())],
+            Redirect::permanent(url),
+        )
+            .into_response()
+    }
+
+    /// Temporary redirect with the matching `Cache-Control` header
+    fn temporary(&self, url: &str) -> Response {
+        (
+            [(CACHE_CONTROL, self.temporary.clone())],
+            Redirect::temporary(url),
+        )
+            .into_response()
+    }
+
+    /// Meta refresh page with the `Cache-Control` header matching the type of redirect
+    fn meta_refresh(&self, is_permanent: bool, html: Html<String>) -> Response {
+        let cache_control = if is_permanent {
+            self.permanent.clone()
+        } else {
+            self.temporary.clone()
+        };
+
+        ([(CACHE_CONTROL, cache_control)], html).into_response()
+    }
+
+    /// Redirect to the destination, based on its type
+    ///
+    /// Private destinations are never cached, their signed links expire; neither are destinations
+    /// with a script, their URL depends on the request
+    fn redirect(&self, templates: &Templates, destination: &Destination) -> Response {
+        let mut response = if destination.is_meta_refresh {
+            self.meta_refresh(
+                destination.is_permanent,
+                templates.render_meta_refresh(destination),
+            )
+        } else if destination.is_permanent {
+            self.permanent(&destination.url)
+        } else {
+            self.temporary(&destination.url)
+        };
+
+        if destination.is_private || destination.script.is_some() {
+            response.headers_mut().insert(
+                CACHE_CONTROL,
+                HeaderValue::from_static(PRIVATE_CACHE_CONTROL),
+            );
+        }
 
-/// Template for 404 page
-const NOT_FOUND: &str = include_str!("pages/404.html");
+        response
+    }
+}
 
-/// Template for error page
+/// How to handle visitors that do not want to be tracked
 ///
-/// Has a placeholder to inject a current error message
-const ERROR: &str = include_str!("pages/500.html");
+/// Visitors signal this with the `DNT: 1` (Do Not Track) or `Sec-GPC: 1` (Global Privacy
+/// Control) header
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DoNotTrack {
+    /// Record the hit, without the IP address and user agent
+    #[default]
+    Anonymize,
+
+    /// Do not record the hit at all
+    Skip,
+
+    /// Record the hit as usual
+    Ignore,
+}
+
+impl DoNotTrack {
+    /// Setup the handling based on the `DO_NOT_TRACK` environment variable
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the value is not `anonymize`, `skip` or `ignore`
+    pub fn from_environment() -> anyhow::Result<Self> {
+        match env_var_optional("DO_NOT_TRACK").as_deref() {
+            None | Some("anonymize") => Ok(Self::Anonymize),
+            Some("skip") => Ok(Self::Skip),
+            Some("ignore") => Ok(Self::Ignore),
+            Some(other) => Err(anyhow::anyhow!(
+                "Invalid DO_NOT_TRACK: {other}, expected `anonymize`, `skip` or `ignore`"
+            )),
+        }
+    }
+
+    /// The handling for this request, `None` when the visitor did not ask to not be tracked
+    fn for_request(self, headers: &HeaderMap) -> Option<Self> {
+        let is_requested = ["dnt", "sec-gpc"]
+            .iter()
+            .any(|name| headers.get(*name).is_some_and(|value| value == "1"));
+
+        is_requested.then_some(self)
+    }
+}
+
+/// User agents of social media crawlers, these unfurl shared links
+///
+/// Matched case-insensitive on part of the user agent
+const SOCIAL_MEDIA_CRAWLERS: &[&str] = &[
+    "slackbot",
+    "twitterbot",
+    "facebookexternalhit",
+    "facebookcatalog",
+    "linkedinbot",
+    "discordbot",
+    "whatsapp",
+    "telegrambot",
+    "skypeuripreview",
+    "mastodon",
+    "redditbot",
+    "pinterest",
+    "embedly",
+];
+
+/// Is the user agent a known social media crawler?
+fn is_social_media_crawler(user_agent: &UserAgent) -> bool {
+    let user_agent = user_agent.as_str().to_lowercase();
+
+    SOCIAL_MEDIA_CRAWLERS
+        .iter()
+        .any(|crawler| user_agent.contains(crawler))
+}
+
+/// Header with the suggested slugs of a 404, comma separated and percent-encoded
+static SUGGESTIONS_HEADER: HeaderName = HeaderName::from_static("x-shurly-suggestions");
+
+/// Default number of suggestions shown on the 404 page
+const DEFAULT_SUGGESTIONS_LIMIT: &str = "3";
+
+/// Suggestions of similar slugs when a slug is not found
+#[derive(Clone)]
+pub struct Suggestions {
+    /// Maximum number of suggestions, `0` disables the suggestions
+    limit: i64,
+}
+
+impl Default for Suggestions {
+    fn default() -> Self {
+        Self {
+            limit: DEFAULT_SUGGESTIONS_LIMIT
+                .parse()
+                .expect("Valid default limit"),
+        }
+    }
+}
+
+impl Suggestions {
+    /// Setup the suggestions based on the `NOT_FOUND_SUGGESTIONS` environment variable
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the limit is not a valid number
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let limit = env_var_or_else("NOT_FOUND_SUGGESTIONS", || {
+            DEFAULT_SUGGESTIONS_LIMIT.to_string()
+        })
+        .parse::<u8>()?;
+
+        Ok(Self {
+            limit: i64::from(limit),
+        })
+    }
+
+    /// The 404 page with suggestions of similar slugs
+    ///
+    /// Suggestions are a nicety, failing to find them results in a 404 without suggestions
+    async fn not_found(
+        &self,
+        database: &Database,
+        templates: &Templates,
+        domain: Option<&str>,
+        domain_template: Option<&str>,
+        slug: &str,
+    ) -> Response {
+        let suggestions = if self.limit > 0 {
+            database
+                .find_similar_destination_slugs(domain, slug, self.limit)
+                .await
+                .unwrap_or_else(|err| {
+                    tracing::error!("Could not find suggestions: {err}");
+
+                    Vec::new()
+                })
+        } else {
+            Vec::new()
+        };
+
+        let html = templates.render_not_found(domain_template, &suggestions);
+
+        if suggestions.is_empty() {
+            return (StatusCode::NOT_FOUND, html).into_response();
+        }
+
+        let header = suggestions
+            .iter()
+            .map(|slug| encode_slug(slug))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        match HeaderValue::from_str(&header) {
+            Ok(header) => (
+                StatusCode::NOT_FOUND,
+                [(SUGGESTIONS_HEADER.clone(), header)],
+                html,
+            )
+                .into_response(),
+            Err(_) => (StatusCode::NOT_FOUND, html).into_response(),
+        }
+    }
+}
+
+/// Suffix of a slug for the public stats page of its destination
+const STATS_SUFFIX: &str = "+stats";
+
+/// Number of referrers on the public stats page
+const STATS_REFERRERS: i64 = 10;
 
 /// The root!
 ///
 /// All wildcard requests end up in this function.
 ///
 /// A lookup in database will be done looking for the right slug, based on the path
+///
+/// `HEAD` requests get the same response, without recording a hit: those are uptime checkers
+/// and link validators, not visitors
+///
+/// Adding a `+` to the slug (or the `?preview` query parameter) shows a preview of the
+/// destination instead of redirecting, no hit is recorded for a preview
+///
+/// Adding `+stats` to the slug shows the stats of the destination, only when its stats are public
+/// or with a signed stats link
+///
+/// Unknown slugs get a 404 with suggestions of similar slugs, in the page and in the
+/// `X-Shurly-Suggestions` header
+///
+/// Destinations with `is_meta_refresh` are served as a HTML page with a meta refresh and a link,
+/// for clients that do not follow HTTP redirects
+///
+/// Social media crawlers get a HTML page with the Open Graph metadata of the destination (when it
+/// has any) instead of a redirect, so shared short links unfurl nicely. These are not recorded as
+/// hits, crawlers are not visitors
+///
+/// Private destinations only redirect with a valid signed link, otherwise a `403 Forbidden`
+///
+/// Destinations redirecting back to themselves via Shurly are not redirected, but get a
+/// `508 Loop Detected`
+///
+/// Visitors sending `DNT: 1` or `Sec-GPC: 1` get their hit anonymized or skipped, depending on
+/// the settings
+///
+/// Destinations with a script redirect to the URL returned by the script, see
+/// [`scripts`](crate::scripts)
+///
+/// The registered hooks run before the lookup, after the destination is found and before the
+/// redirect, see [`RedirectHook`](crate::hooks::RedirectHook)
+///
+/// Requests taking longer than the database deadline get a `503 Service Unavailable`, see
+/// [`DatabaseDeadline`]
 pub async fn root(
-    ip_address: Option<InsecureClientIp>,
+    method: Method,
+    headers: HeaderMap,
+    ip_address: Option<ClientIp>,
     user_agent: Option<TypedHeader<UserAgent>>,
     Extension(database): Extension<Database>,
+    Extension(settings): Extension<Settings>,
+    uri: Uri,
+) -> Result<Response, (StatusCode, Html<String>)> {
+    let database_deadline = settings.database_deadline;
+    let templates = settings.templates.clone();
+
+    // the resolving is a big future, boxed instead of on the stack
+    let response = database_deadline
+        .run(Box::pin(resolve(
+            method, headers, ip_address, user_agent, database, settings, uri,
+        )))
+        .await;
+
+    response.unwrap_or_else(|| {
+        tracing::warn!("Database deadline passed, the database is too slow");
+
+        Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            templates.render_error("Service unavailable, try again later"),
+        ))
+    })
+}
+
+/// Resolve the slug of the request, see [`root`]
+async fn resolve(
+    method: Method,
+    headers: HeaderMap,
+    ip_address: Option<ClientIp>,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    database: Database,
+    settings: Settings,
     uri: Uri,
-) -> Result<Redirect, (StatusCode, Html<String>)> {
+) -> Result<Response, (StatusCode, Html<String>)> {
+    let templates = &settings.templates;
+
+    if let Some(response) = rate_limited(&settings, ip_address.as_ref()) {
+        return Ok(response);
+    }
+
     let slug = uri.path().trim_matches('/');
-    let slug = url_decode_slug(slug)?;
+    let slug = url_decode_slug(templates, slug)?;
 
     tracing::debug!("Looking for slug: /{slug}");
+    Span::current().record("slug", slug.as_str());
 
-    let destination = database
-        .find_single_destination_by_slug(&slug)
+    let mut is_preview = is_preview_requested(&uri);
+
+    let domain = domains::from_headers(&headers);
+
+    let hook_request = HookRequest {
+        method: &method,
+        headers: &headers,
+        uri: &uri,
+        ip_address: ip_address.as_ref().map(|ip_address| ip_address.0),
+        slug: &slug,
+        domain: domain.as_deref(),
+    };
+
+    if let Some(response) = settings.hooks.before_lookup(&hook_request) {
+        return Ok(response);
+    }
+
+    let mut destination = settings
+        .slug_cache
+        .find(&database, domain.as_deref(), &slug)
+        .await
+        .map_err(|err| internal_error(templates, err))?;
+
+    // slugs can end with `+stats` themselves, only show the stats when such a slug does not exist
+    if destination.is_none() {
+        if let Some(response) = stats(&settings, &database, domain.as_deref(), &slug, &uri).await? {
+            return Ok(response);
+        }
+    }
+
+    // slugs can end with a `+` themselves, only preview when such a slug does not exist
+    if destination.is_none() {
+        if let Some(preview_slug) = slug.strip_suffix('+') {
+            destination = settings
+                .slug_cache
+                .find(&database, domain.as_deref(), preview_slug)
+                .await
+                .map_err(|err| internal_error(templates, err))?;
+
+            is_preview = true;
+        }
+    }
+
+    // pending and rejected destinations do not exist for visitors
+    let Some(destination) = destination.filter(Destination::is_approved) else {
+        return not_found(&settings, &database, &method, domain.as_deref(), &slug).await;
+    };
+
+    Span::current().record("destination_id", destination.id.to_string());
+
+    if let Some(response) = settings.hooks.after_resolve(&hook_request, &destination) {
+        return Ok(response);
+    }
+
+    let is_open_graph = destination.has_open_graph()
+        && user_agent
+            .as_ref()
+            .is_some_and(|user_agent| is_social_media_crawler(&user_agent.0));
+
+    let signature = if destination.is_private && !destination.is_deleted() {
+        settings
+            .signing_key
+            .verify(&destination.id.to_string(), uri.query())
+    } else {
+        Ok(())
+    };
+
+    if method != Method::HEAD && !is_preview && !is_open_graph && signature.is_ok() {
+        record_hit(
+            &settings,
+            &database,
+            &headers,
+            ip_address,
+            user_agent,
+            &destination,
+        )
+        .await;
+    }
+
+    if destination.is_deleted() {
+        tracing::debug!(r#"Slug "{slug}" no longer exists"#);
+
+        Err((
+            StatusCode::GONE,
+            templates.render_error("Page not longer exists"),
+        ))
+    } else if destination.is_flagged() {
+        tracing::debug!(r#"Slug "{slug}" is disabled, its URL is flagged"#);
+
+        Err((StatusCode::GONE, templates.render_error("Page is disabled")))
+    } else if let Err(err) = signature {
+        tracing::debug!(r#"Slug "{slug}" is private: {err:?}"#);
+
+        Err((
+            StatusCode::FORBIDDEN,
+            templates.render_error(err.description()),
+        ))
+    } else if is_open_graph {
+        tracing::debug!(r#"Slug "{slug}" unfurled for a social media crawler"#);
+
+        Ok(templates.render_open_graph(&destination).into_response())
+    } else if is_preview {
+        tracing::debug!(r#"Slug "{}" previewed"#, destination.slug);
+
+        Ok(templates.render_preview(&destination).into_response())
+    } else {
+        break_redirect_loop(&settings, &database, &headers, &destination).await?;
+
+        let destination = run_script(&settings, &hook_request, destination);
+
+        tracing::debug!(r#"Slug "{slug}" redirecting to: {}"#, destination.url);
+
+        Ok(redirect(&settings, &hook_request, &destination))
+    }
+}
+
+/// The redirect to the destination, with its extra headers, after the hooks ran
+///
+/// Redirects served while the root is degraded are counted, see [`degraded`](crate::degraded)
+fn redirect(settings: &Settings, request: &HookRequest<'_>, destination: &Destination) -> Response {
+    let degraded = settings.hits.degraded();
+    if degraded.is_degraded() {
+        degraded.served();
+        Span::current().record("degraded", true);
+    }
+
+    let mut response = settings
+        .cache_control
+        .redirect(&settings.templates, destination);
+    add_extra_headers(destination, &mut response);
+    settings
+        .hooks
+        .before_redirect(request, destination, &mut response);
+
+    response
+}
+
+/// The public stats page of the destination of a slug ending with `+stats`, the hits per day and
+/// the top referrers
+///
+/// `None` when the slug is not for stats, or the stats of its destination are not public and the
+/// link is not signed; a link with an invalid or expired signature gets a `403 Forbidden`
+async fn stats(
+    settings: &Settings,
+    database: &Database,
+    domain: Option<&str>,
+    slug: &str,
+    uri: &Uri,
+) -> Result<Option<Response>, (StatusCode, Html<String>)> {
+    let templates = &settings.templates;
+
+    let Some(slug) = slug.strip_suffix(STATS_SUFFIX) else {
+        return Ok(None);
+    };
+
+    let Some(destination) = settings
+        .slug_cache
+        .find(database, domain, slug)
         .await
-        .map_err(internal_error)?;
+        .map_err(|err| internal_error(templates, err))?
+        .filter(|destination| !destination.is_deleted() && destination.is_approved())
+    else {
+        return Ok(None);
+    };
+
+    if !destination.is_stats_public {
+        match settings
+            .signing_key
+            .verify_stats(&destination.id.to_string(), uri.query())
+        {
+            Ok(()) => {}
+            Err(SignatureError::Missing) => return Ok(None),
+            Err(err) => {
+                tracing::debug!(r#"Slug "{slug}" stats not shown: {err:?}"#);
 
-    if let Some(destination) = destination {
-        database
-            .save_hit(
+                return Err((
+                    StatusCode::FORBIDDEN,
+                    templates.render_error(err.description()),
+                ));
+            }
+        }
+    }
+
+    tracing::debug!(r#"Slug "{slug}" stats shown"#);
+
+    let daily_hits = settings
+        .stats_cache
+        .daily_hits(database, &destination)
+        .await
+        .map_err(|err| internal_error(templates, err))?;
+
+    let referrers = settings
+        .stats_cache
+        .top_referrers(database, &destination, STATS_REFERRERS)
+        .await
+        .map_err(|err| internal_error(templates, err))?;
+
+    Ok(Some(
+        templates
+            .render_stats(
                 &destination,
-                ip_address.map(|i| i.0).as_ref(),
-                user_agent.map(|i| i.0.to_string()).as_ref(),
+                &daily_hits,
+                &referrers,
+                Utc::now().date_naive(),
             )
+            .into_response(),
+    ))
+}
+
+/// Record a hit on the destination, respecting the Do Not Track settings
+///
+/// Untracked destinations never record their hits, nor publish them to the broker
+///
+/// Hits that can not be saved are queued, the redirect is served regardless
+async fn record_hit(
+    settings: &Settings,
+    database: &Database,
+    headers: &HeaderMap,
+    ip_address: Option<ClientIp>,
+    user_agent: Option<TypedHeader<UserAgent>>,
+    destination: &Destination,
+) {
+    if destination.is_untracked {
+        return;
+    }
+
+    let (ip_address, user_agent) = match settings.do_not_track.for_request(headers) {
+        Some(DoNotTrack::Skip) => return,
+        Some(DoNotTrack::Anonymize) => (None, None),
+        Some(DoNotTrack::Ignore) | None => (ip_address, user_agent),
+    };
+
+    let ip_address = ip_address.map(|i| settings.client_ip.visitor(i.0));
+    let user_agent = user_agent.map(|i| i.0.to_string());
+    let referrer = referrer(headers);
+
+    settings
+        .hits
+        .save(
+            database,
+            destination,
+            ip_address.as_ref(),
+            user_agent.as_ref(),
+            referrer.as_deref(),
+        )
+        .await;
+
+    settings
+        .broker
+        .publish_hit(destination, ip_address.as_ref(), user_agent.as_deref());
+}
+
+/// Host of the referring page, of the `Referer` header
+fn referrer(headers: &HeaderMap) -> Option<String> {
+    referrer_host(headers.get(REFERER)?.to_str().ok()?)
+}
+
+/// Host of the URL of a referring page, without `www.`
+///
+/// Only the host is kept, the rest of the URL could be personal
+pub fn referrer_host(referer: &str) -> Option<String> {
+    Url::parse(referer)
+        .ok()?
+        .host_str()
+        .map(|host| host.trim_start_matches("www.").to_lowercase())
+}
+
+/// Refuse the request when the IP address is over its rate limit, with a `429 Too Many Requests`
+///
+/// IPv6 addresses share the rate limit of their prefix, requests without a known IP address are
+/// not limited
+fn rate_limited(settings: &Settings, ip_address: Option<&ClientIp>) -> Option<Response> {
+    let visitor = settings.client_ip.visitor(ip_address?.0);
+
+    let retry_after = settings.rate_limit.check(visitor).err()?;
+
+    tracing::debug!("Rate limited: {visitor}");
+
+    // whole seconds, rounded up
+    let retry_after = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+
+    Some(
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(RETRY_AFTER, retry_after.to_string())],
+            settings.templates.render_error("Too many requests"),
+        )
+            .into_response(),
+    )
+}
+
+/// Slug without a destination, the fallback of the domain, the homepage for the empty slug or a
+/// 404
+///
+/// With notifications enabled, the 404s are recorded for the digests of the admins
+async fn not_found(
+    settings: &Settings,
+    database: &Database,
+    method: &Method,
+    domain: Option<&str>,
+    slug: &str,
+) -> Result<Response, (StatusCode, Html<String>)> {
+    let templates = &settings.templates;
+
+    let registered_domain = match domain {
+        Some(domain) => database
+            .find_single_domain_by_hostname(domain)
             .await
-            .map_err(internal_error)?;
+            .map_err(|err| internal_error(templates, err))?
+            .filter(|registered_domain| !registered_domain.is_deleted()),
+        None => None,
+    };
 
-        if destination.is_deleted() {
-            tracing::debug!(r#"Slug "{slug}" no longer exists"#);
+    if let Some(url) = registered_domain
+        .as_ref()
+        .and_then(|registered_domain| registered_domain.fallback_url.as_ref())
+    {
+        tracing::debug!(r#"Slug "{slug}" not found, domain fallback redirecting to: {url}"#);
 
-            Err((
-                StatusCode::GONE,
-                render_error_template("Page not longer exists"),
-            ))
-        } else {
-            tracing::debug!(r#"Slug "{slug}" redirecting to: {}"#, destination.url);
+        return Ok(settings.cache_control.temporary(url));
+    }
+
+    let domain_template = registered_domain
+        .as_ref()
+        .and_then(|registered_domain| registered_domain.not_found_template.as_deref());
 
-            if destination.is_permanent {
-                Ok(Redirect::permanent(&destination.url))
-            } else {
-                Ok(Redirect::temporary(&destination.url))
+    if !slug.is_empty() {
+        tracing::debug!(r#"Slug "{slug}" not found"#);
+
+        if settings.notifier.is_enabled()
+            && method != Method::HEAD
+            && slug.len() <= MAX_MISSED_SLUG_LENGTH
+        {
+            let hostname = registered_domain
+                .as_ref()
+                .map(|registered_domain| registered_domain.hostname.as_str());
+
+            if let Err(err) = database.record_missed_slug(hostname, slug).await {
+                tracing::warn!("Could not record missed slug: {err}");
             }
         }
+
+        return Ok(settings
+            .suggestions
+            .not_found(database, templates, domain, domain_template, slug)
+            .await);
+    }
+
+    if let Some(url) = settings.homepage.url() {
+        tracing::debug!("Homepage redirecting to: {url}");
+
+        Ok(settings.cache_control.temporary(url.as_str()))
+    } else if let Some(html) = templates.render_homepage() {
+        Ok(html.into_response())
     } else {
-        tracing::debug!(r#"Slug "{slug}" not found"#);
+        Err((
+            StatusCode::NOT_FOUND,
+            templates.render_not_found(domain_template, &[]),
+        ))
+    }
+}
+
+/// Slugs served by Shurly itself, these never end up in the root
+pub const RESERVED_SLUGS: &[&str] = &["robots.txt", "favicon.ico", "healthz", "readyz"];
+
+/// Built-in favicon, served from the binary
+const FAVICON: &[u8] = include_bytes!("pages/favicon.ico");
+
+/// `Cache-Control` of the favicon, it only changes with a new version of Shurly
+const FAVICON_CACHE_CONTROL: &str = "public, max-age=86400";
+
+/// Serve the `robots.txt`
+///
+/// By default all crawling of short links is disallowed, the contents can be replaced with a
+/// template. Handled before the root, so it never ends up as a slug lookup.
+pub async fn robots_txt(Extension(settings): Extension<Settings>) -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, "text/plain; charset=utf-8")],
+        settings.templates.render_robots_txt(),
+    )
+}
+
+/// Serve the built-in favicon
+///
+/// Handled before the root, so browsers asking for it do not end up as a slug lookup
+pub async fn favicon() -> impl IntoResponse {
+    (
+        [
+            (CONTENT_TYPE, "image/x-icon"),
+            (CACHE_CONTROL, FAVICON_CACHE_CONTROL),
+        ],
+        FAVICON,
+    )
+}
+
+/// Break the redirect loop of a destination, if it has one
+///
+/// The redirect is served when the loop can not be detected, like while the database is
+/// unreachable
+async fn break_redirect_loop(
+    settings: &Settings,
+    database: &Database,
+    headers: &HeaderMap,
+    destination: &Destination,
+) -> Result<(), (StatusCode, Html<String>)> {
+    let templates = &settings.templates;
+
+    let Ok(url) = Url::parse(&destination.url) else {
+        return Ok(());
+    };
+
+    let redirect_loop = settings
+        .loop_detection
+        .detect(
+            database,
+            &settings.slug_cache,
+            headers,
+            destination.domain.as_deref(),
+            &destination.slug,
+            &url,
+        )
+        .await;
 
-        Err((StatusCode::NOT_FOUND, render_not_found_template()))
+    let redirect_loop = match redirect_loop {
+        Ok(redirect_loop) => redirect_loop,
+        Err(err) => {
+            tracing::error!(
+                r#"Slug "{}" not checked for a redirect loop: {err}"#,
+                destination.slug
+            );
+
+            None
+        }
+    };
+
+    if let Some(redirect_loop) = redirect_loop {
+        tracing::warn!(
+            r#"Slug "{}" has a redirect loop: {redirect_loop:?}"#,
+            destination.slug
+        );
+
+        return Err((
+            StatusCode::LOOP_DETECTED,
+            templates.render_error(redirect_loop.description()),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Add the extra headers of the destination to its redirect
+///
+/// The headers are validated when stored, invalid ones are skipped anyway
+fn add_extra_headers(destination: &Destination, response: &mut Response) {
+    for (name, value) in destination.extra_headers() {
+        let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) else {
+            tracing::warn!(
+                r#"Slug "{}" has an invalid header: {name}"#,
+                destination.slug
+            );
+            continue;
+        };
+
+        response.headers_mut().insert(name, value);
     }
 }
 
+/// Run the script of the destination, the destination with the URL decided by the script
+///
+/// A failing script keeps the URL of the destination, the link keeps working
+fn run_script(
+    settings: &Settings,
+    request: &HookRequest<'_>,
+    mut destination: Destination,
+) -> Destination {
+    let Some(ref script) = destination.script else {
+        return destination;
+    };
+
+    match settings.scripts.run(script, &destination.url, request) {
+        Ok(Some(url)) => destination.url = url.to_string(),
+        Ok(None) => {}
+        Err(err) => tracing::warn!(r#"Slug "{}" has a failing script: {err}"#, destination.slug),
+    }
+
+    destination
+}
+
+/// Is a preview requested with the `?preview` query parameter?
+///
+/// Any value is accepted, except for `0` and `false`
+fn is_preview_requested(uri: &Uri) -> bool {
+    uri.query().is_some_and(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .any(|(key, value)| key == "preview" && value != "0" && value != "false")
+    })
+}
+
 /// Utility function for mapping any error into a `500 Internal Server Error`
 /// response.
-fn internal_error<E>(err: E) -> (StatusCode, Html<String>)
+fn internal_error<E>(templates: &Templates, err: E) -> (StatusCode, Html<String>)
 where
     E: std::error::Error,
 {
     (
         StatusCode::INTERNAL_SERVER_ERROR,
-        render_error_template(&err.to_string()),
+        templates.render_error(&err.to_string()),
     )
 }
 
 /// URL decode slug
 ///
 /// Uses percentage encoding for the decoding, might error in case of invalid UTF-8
-fn url_decode_slug(slug: &str) -> Result<String, (StatusCode, Html<String>)> {
+fn url_decode_slug(
+    templates: &Templates,
+    slug: &str,
+) -> Result<String, (StatusCode, Html<String>)> {
     let decoded = percent_decode_str(slug);
 
     decoded
@@ -100,38 +1148,72 @@ fn url_decode_slug(slug: &str) -> Result<String, (StatusCode, Html<String>)> {
         .map_err(|_| {
             (
                 StatusCode::BAD_REQUEST,
-                render_error_template("URL contains invalid UTF-8 characters"),
+                templates.render_error("URL contains invalid UTF-8 characters"),
             )
         })
 }
 
-/// Create a HTML version of not found template
-fn render_not_found_template() -> Html<String> {
-    Html(NOT_FOUND.to_string())
-}
-
-/// Very, very simple template renderer
-///
-/// Only replaces the `{error}` in the template with the given string
-///
-/// Make sure to not use user provided error messages, those are _NOT_ safe
-fn render_error_template(error: &str) -> Html<String> {
-    Html(ERROR.replace("{error}", error))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_url_decode_slug_space() {
-        let slug = url_decode_slug("%20").unwrap();
+        let slug = url_decode_slug(&Templates::default(), "%20").unwrap();
         assert_eq!(" ".to_string(), slug);
     }
 
+    #[test]
+    fn test_do_not_track_for_request() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(None, DoNotTrack::Skip.for_request(&headers));
+
+        headers.insert("dnt", HeaderValue::from_static("0"));
+        assert_eq!(None, DoNotTrack::Skip.for_request(&headers));
+
+        headers.insert("dnt", HeaderValue::from_static("1"));
+        assert_eq!(
+            Some(DoNotTrack::Skip),
+            DoNotTrack::Skip.for_request(&headers)
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert("sec-gpc", HeaderValue::from_static("1"));
+        assert_eq!(
+            Some(DoNotTrack::Anonymize),
+            DoNotTrack::Anonymize.for_request(&headers)
+        );
+    }
+
+    #[test]
+    fn test_is_social_media_crawler() {
+        assert!(is_social_media_crawler(&UserAgent::from_static(
+            "Slackbot-LinkExpanding 1.0 (+https://api.slack.com/robots)"
+        )));
+        assert!(is_social_media_crawler(&UserAgent::from_static(
+            "Twitterbot/1.0"
+        )));
+        assert!(!is_social_media_crawler(&UserAgent::from_static(
+            "Mozilla/5.0 (X11; Linux x86_64; rv:131.0) Gecko/20100101 Firefox/131.0"
+        )));
+    }
+
+    #[test]
+    fn test_is_preview_requested() {
+        assert!(is_preview_requested(&Uri::from_static("/slug?preview")));
+        assert!(is_preview_requested(&Uri::from_static(
+            "/slug?a=b&preview=1"
+        )));
+        assert!(!is_preview_requested(&Uri::from_static("/slug")));
+        assert!(!is_preview_requested(&Uri::from_static("/slug?preview=0")));
+        assert!(!is_preview_requested(&Uri::from_static(
+            "/slug?preview=false"
+        )));
+    }
+
     #[test]
     fn test_url_decode_slug_invalid() {
-        let error = url_decode_slug("%c0").unwrap_err();
+        let error = url_decode_slug(&Templates::default(), "%c0").unwrap_err();
         assert_eq!(StatusCode::BAD_REQUEST, error.0);
     }
 }
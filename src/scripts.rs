@@ -0,0 +1,244 @@
+//! Scripts of destinations, deciding the URL to redirect to based on the request
+//!
+//! Scripts are written in [Rhai](https://rhai.rs/), a small scripting language. A script gets the
+//! URL of the destination as `url` and the request as `request`, and returns the URL to redirect
+//! to. Returning nothing redirects to the URL of the destination.
+//!
+//! ```rhai
+//! if request.headers["accept-language"]?.starts_with("nl") ?? false {
+//!     "https://www.example.com/nl/"
+//! } else {
+//!     url
+//! }
+//! ```
+//!
+//! The `request` has the `method`, `path`, `query`, `slug`, `domain`, `ip` and `headers` (with
+//! lowercase names) of the request. Missing values are `()`.
+//!
+//! Scripts are sandboxed: without access to files or modules, and limited in the number of
+//! operations and the size of values. A failing script redirects to the URL of the destination,
+//! links keep working.
+
+use std::sync::Arc;
+
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::Dynamic;
+use rhai::Engine;
+use rhai::Map;
+use rhai::Scope;
+use url::Url;
+
+use crate::hooks::HookRequest;
+use crate::utils::is_web_url;
+
+/// Maximum number of operations of a script, stops endless loops
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// Maximum size of strings, arrays and maps in a script
+const MAX_SIZE: usize = 64 * 1024;
+
+/// Maximum depth of expressions in a script, at global level and in functions
+const MAX_EXPR_DEPTH: usize = 64;
+
+/// Maximum depth of function calls in a script
+const MAX_CALL_LEVELS: usize = 16;
+
+/// The engine running the scripts of destinations
+#[derive(Clone)]
+pub struct Scripts {
+    /// The actual engine, with the limits of the sandbox
+    engine: Arc<Engine>,
+}
+
+impl Default for Scripts {
+    fn default() -> Self {
+        let mut engine = Engine::new();
+
+        engine
+            .set_module_resolver(DummyModuleResolver::new())
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_string_size(MAX_SIZE)
+            .set_max_array_size(MAX_SIZE)
+            .set_max_map_size(MAX_SIZE)
+            .set_max_expr_depths(MAX_EXPR_DEPTH, MAX_EXPR_DEPTH)
+            .set_max_call_levels(MAX_CALL_LEVELS)
+            .disable_symbol("eval")
+            .on_print(|text| tracing::debug!("Script printed: {text}"))
+            .on_debug(|text, _, _| tracing::debug!("Script debugged: {text}"));
+
+        Self {
+            engine: Arc::new(engine),
+        }
+    }
+}
+
+impl Scripts {
+    /// Validate the script, without running it
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` with the description of the syntax error
+    pub fn validate(&self, script: &str) -> Result<(), String> {
+        self.engine
+            .compile(script)
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+
+    /// Run the script, the URL to redirect to
+    ///
+    /// Returns `None` when the script returns nothing
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` with the description when the script fails or returns something else than
+    /// a valid `http` or `https` URL
+    pub fn run(
+        &self,
+        script: &str,
+        url: &str,
+        request: &HookRequest<'_>,
+    ) -> Result<Option<Url>, String> {
+        let mut scope = Scope::new();
+        scope.push_constant("url", url.to_string());
+        scope.push_constant("request", request_map(request));
+
+        let result = self
+            .engine
+            .eval_with_scope::<Dynamic>(&mut scope, script)
+            .map_err(|err| err.to_string())?;
+
+        if result.is_unit() {
+            return Ok(None);
+        }
+
+        let type_name = result.type_name();
+        let url = result
+            .into_string()
+            .map_err(|_| format!("Expected a URL, got: {type_name}"))?;
+
+        let url = Url::parse(&url).map_err(|err| format!("Invalid URL: {url}, {err}"))?;
+        if !is_web_url(&url) {
+            return Err(format!(
+                "Invalid URL: {url}, expected an `http` or `https` URL"
+            ));
+        }
+
+        Ok(Some(url))
+    }
+}
+
+/// The request as seen by scripts
+fn request_map(request: &HookRequest<'_>) -> Map {
+    let headers = request
+        .headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let value = value.to_str().ok()?;
+
+            Some((name.as_str().into(), Dynamic::from(value.to_string())))
+        })
+        .collect::<Map>();
+
+    let mut map = Map::new();
+    map.insert("method".into(), request.method.to_string().into());
+    map.insert("path".into(), request.uri.path().to_string().into());
+    map.insert("query".into(), optional(request.uri.query()));
+    map.insert("slug".into(), request.slug.to_string().into());
+    map.insert("domain".into(), optional(request.domain));
+    map.insert(
+        "ip".into(),
+        optional(request.ip_address.map(|ip| ip.to_string()).as_deref()),
+    );
+    map.insert("headers".into(), headers.into());
+
+    map
+}
+
+/// Optional values are `()` in scripts
+fn optional(value: Option<&str>) -> Dynamic {
+    value.map_or(Dynamic::UNIT, |value| value.to_string().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderMap;
+    use axum::http::Method;
+    use axum::http::Uri;
+
+    use super::*;
+
+    fn run(script: &str, headers: &HeaderMap) -> Result<Option<Url>, String> {
+        let uri = Uri::from_static("/some-slug?campaign=summer");
+        let request = HookRequest {
+            method: &Method::GET,
+            headers,
+            uri: &uri,
+            ip_address: Some("1.1.1.1".parse().unwrap()),
+            slug: "some-slug",
+            domain: None,
+        };
+
+        Scripts::default().run(script, "https://www.example.com/", &request)
+    }
+
+    #[test]
+    fn test_run() {
+        let headers = HeaderMap::new();
+
+        assert_eq!(None, run("", &headers).unwrap());
+        assert_eq!(
+            Some(Url::parse("https://www.example.com/").unwrap()),
+            run("url", &headers).unwrap()
+        );
+        assert_eq!(
+            Some(
+                Url::parse("https://www.example.com/some-slug?campaign=summer&ip=1.1.1.1").unwrap()
+            ),
+            run(
+                "`${url}${request.slug}?${request.query}&ip=${request.ip}`",
+                &headers
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            None,
+            run("if request.domain == () { return; }", &headers).unwrap()
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert("accept-language", "nl-NL".parse().unwrap());
+        assert_eq!(
+            Some(Url::parse("https://www.example.com/nl/").unwrap()),
+            run(
+                r#"if request.headers["accept-language"]?.starts_with("nl") ?? false {
+                    "https://www.example.com/nl/"
+                } else {
+                    url
+                }"#,
+                &headers
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_run_invalid() {
+        let headers = HeaderMap::new();
+
+        assert!(run("42", &headers).is_err());
+        assert!(run(r#""not a URL""#, &headers).is_err());
+        assert!(run(r#""javascript:alert(1)""#, &headers).is_err());
+        assert!(run("loop {}", &headers).is_err());
+        assert!(run(r#"import "some-module" as m;"#, &headers).is_err());
+        assert!(run(r#"eval("url")"#, &headers).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let scripts = Scripts::default();
+
+        assert!(scripts.validate("url").is_ok());
+        assert!(scripts.validate("if {").is_err());
+    }
+}
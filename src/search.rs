@@ -0,0 +1,64 @@
+//! Search across the destinations and their notes, for a single search box
+//!
+//! A query matches the slug or URL of a destination, or the content of its notes, case
+//! insensitive. Matches are ranked by their relevance: an exact slug first, then similar slugs,
+//! then URLs and then notes, based on the trigram similarity of `pg_trgm`. Deleted destinations
+//! and notes are never found.
+
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+/// Minimum number of characters of a query
+pub const MIN_QUERY_LENGTH: usize = 2;
+
+/// Maximum number of characters of a query
+pub const MAX_QUERY_LENGTH: usize = 255;
+
+/// A destination matching a query, or one of its notes
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    /// The destination
+    pub destination_id: Uuid,
+
+    /// Slug of the destination
+    pub slug: String,
+
+    /// Domain of the destination, all domains when empty
+    pub domain: Option<String>,
+
+    /// URL of the destination
+    pub url: String,
+
+    /// The matching note, `None` when the destination itself matches
+    pub note_id: Option<Uuid>,
+
+    /// Content of the matching note
+    pub note_content: Option<String>,
+
+    /// Relevance of the match, from 0 to 1
+    pub score: f32,
+
+    /// Last update of the destination or note
+    pub updated_at: NaiveDateTime,
+}
+
+/// The query as a case insensitive `LIKE` pattern, matching it anywhere
+pub fn like_pattern(query: &str) -> String {
+    let escaped = query
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+
+    format!("%{escaped}%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_like_pattern() {
+        assert_eq!("%launch%", like_pattern("launch"));
+        assert_eq!("%100\\%\\_off\\\\%", like_pattern("100%_off\\"));
+    }
+}
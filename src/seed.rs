@@ -0,0 +1,302 @@
+//! Seed data for local development and demos, loaded into an empty database
+//!
+//! With `SHURLY_SEED=path/to/seed.json` the seed is loaded on startup, or with
+//! `shurly seed path/to/seed.json --as admin` from the shell. The database is empty without any
+//! destinations, otherwise the seed is skipped. Every entry is registered on the audit trail, as
+//! the acting user.
+//!
+//! ```json
+//! {
+//!     "users": [
+//!         { "username": "jane", "password": "verysecret", "role": "manager" }
+//!     ],
+//!     "destinations": [
+//!         {
+//!             "slug": "some-easy-name",
+//!             "url": "https://www.example.com/",
+//!             "isPermanent": false,
+//!             "notes": ["Used on the 26-07 ad campaign"]
+//!         }
+//!     ]
+//! }
+//! ```
+
+use std::path::Path;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::api::parse_domain;
+use crate::api::parse_new_slug;
+use crate::api::parse_url;
+use crate::database::AuditEntry;
+use crate::database::CreateDestinationValues;
+use crate::database::CreateNoteValues;
+use crate::database::CreateUserValues;
+use crate::database::Database;
+use crate::database::OpenGraphValues;
+use crate::password::hash;
+use crate::slug_cache::SlugFoundCache;
+use crate::slug_policy::SlugPolicy;
+use crate::users::Role;
+use crate::users::User;
+use crate::utils::env_var_optional;
+
+/// Users, destinations and notes to load
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Seed {
+    /// Users to create
+    #[serde(default)]
+    users: Vec<SeedUser>,
+
+    /// Destinations to create, with their notes
+    #[serde(default)]
+    destinations: Vec<SeedDestination>,
+}
+
+/// User of the seed
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SeedUser {
+    /// Username of the user
+    username: String,
+
+    /// Password of the user, known to everybody with the seed
+    password: String,
+
+    /// Role of the user
+    #[serde(default = "default_role")]
+    role: Role,
+}
+
+/// Destination of the seed
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+#[allow(clippy::struct_excessive_bools)] // the flags of a destination
+struct SeedDestination {
+    /// Slug of the destination
+    slug: String,
+
+    /// URL the destination redirects to
+    url: String,
+
+    /// Domain the slug is served on, all domains when not provided
+    domain: Option<String>,
+
+    /// Redirect permanently
+    #[serde(default)]
+    is_permanent: bool,
+
+    /// Redirect with a meta refresh page
+    #[serde(default)]
+    is_meta_refresh: bool,
+
+    /// Only redirect with a signed link
+    #[serde(default)]
+    is_private: bool,
+
+    /// Serve the stats publicly
+    #[serde(default)]
+    is_stats_public: bool,
+
+    /// Never record the hits
+    #[serde(default)]
+    is_untracked: bool,
+
+    /// Contents of the notes of the destination
+    #[serde(default)]
+    notes: Vec<String>,
+}
+
+/// Users get the least privileges by default
+fn default_role() -> Role {
+    Role::Manager
+}
+
+/// Number of entries created by the seed
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    /// Number of created users
+    pub users: usize,
+
+    /// Number of created destinations
+    pub destinations: usize,
+
+    /// Number of created notes
+    pub notes: usize,
+}
+
+impl Seed {
+    /// Read the seed from a JSON file
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the file can not be read or is invalid
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| anyhow!("Invalid seed: {}, {err}", path.display()))?;
+
+        serde_json::from_str(&content)
+            .map_err(|err| anyhow!("Invalid seed: {}, {err}", path.display()))
+    }
+
+    /// Load the seed into the database, as the user
+    ///
+    /// Returns `None` when the database is not empty; the caller checks the permissions of the user
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when an entry is invalid or could not be created, the entries before it
+    /// are kept
+    pub async fn load(&self, database: &Database, created_by: &User) -> Result<Option<Summary>> {
+        if !database.find_all_destinations().await?.is_empty() {
+            return Ok(None);
+        }
+
+        let mut summary = Summary::default();
+
+        for user in &self.users {
+            let values = CreateUserValues {
+                session_id: &Uuid::new_v4(),
+                role: user.role,
+                username: &user.username,
+                hashed_password: &hash(&user.password),
+                email: None,
+            };
+
+            let user = database.create_user(&values).await?;
+
+            database
+                .register_audit_trail(created_by, &AuditEntry::CreateUser(&user), None)
+                .await?;
+
+            summary.users += 1;
+        }
+
+        let slug_policy = SlugPolicy::from_environment()?;
+        for destination in &self.destinations {
+            let slug = parse_new_slug(&destination.slug, &slug_policy)?;
+            let url = parse_url(&destination.url)?;
+            let domain = parse_domain(destination.domain.as_deref())?;
+
+            let values = CreateDestinationValues {
+                user: created_by,
+                slug: &slug,
+                domain: domain.as_deref(),
+                url: &url,
+                is_permanent: &destination.is_permanent,
+                is_meta_refresh: &destination.is_meta_refresh,
+                is_private: &destination.is_private,
+                is_stats_public: &destination.is_stats_public,
+                is_untracked: &destination.is_untracked,
+                open_graph: OpenGraphValues {
+                    title: None,
+                    description: None,
+                    image: None,
+                },
+                script: None,
+                headers: None,
+                tags: &[],
+                source: None,
+                is_pending: false,
+            };
+
+            let created_destination = database.create_destination(&values).await?;
+
+            // running instances could have cached the slug as missing
+            SlugFoundCache::default()
+                .invalidate(database, &created_destination.slug)
+                .await;
+
+            database
+                .register_audit_trail(
+                    created_by,
+                    &AuditEntry::CreateDestination(&created_destination),
+                    None,
+                )
+                .await?;
+
+            summary.destinations += 1;
+
+            for content in &destination.notes {
+                let values = CreateNoteValues {
+                    user: created_by,
+                    content,
+                };
+
+                let note = database.create_note(&created_destination, &values).await?;
+
+                database
+                    .register_audit_trail(
+                        created_by,
+                        &AuditEntry::CreateNote(&created_destination, &note),
+                        None,
+                    )
+                    .await?;
+
+                summary.notes += 1;
+            }
+        }
+
+        Ok(Some(summary))
+    }
+}
+
+/// Load the seed of the `SHURLY_SEED` environment variable on startup, as the initial user
+///
+/// # Errors
+///
+/// Will return `Err` when the seed is invalid or could not be loaded
+pub async fn load_from_environment(database: &Database) -> Result<()> {
+    let Some(path) = env_var_optional("SHURLY_SEED") else {
+        return Ok(());
+    };
+
+    let seed = Seed::from_file(Path::new(&path))?;
+
+    // the initial user is the only user of an empty database
+    let created_by = database
+        .find_any_single_user()
+        .await?
+        .ok_or_else(|| anyhow!("Seeding needs the initial user"))?;
+
+    if let Some(summary) = seed.load(database, &created_by).await? {
+        tracing::info!(
+            "Seeded {} users, {} destinations and {} notes from {path}",
+            summary.users,
+            summary.destinations,
+            summary.notes,
+        );
+    } else {
+        tracing::info!("Database is not empty, skipping the seed of {path}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed() {
+        let seed: Seed = serde_json::from_str(
+            r#"{
+                "users": [{ "username": "jane", "password": "verysecret" }],
+                "destinations": [
+                    { "slug": "some-slug", "url": "https://www.example.com/", "notes": ["Hi"] }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert!(matches!(seed.users[0].role, Role::Manager));
+        assert!(!seed.destinations[0].is_permanent);
+        assert_eq!(vec!["Hi"], seed.destinations[0].notes);
+
+        assert!(serde_json::from_str::<Seed>("{}").is_ok());
+        assert!(serde_json::from_str::<Seed>(r#"{ "aliases": [] }"#).is_err());
+    }
+}
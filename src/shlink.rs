@@ -0,0 +1,184 @@
+//! Import of the links of Shlink, from a SQL dump or a CSV export
+//!
+//! Every short code becomes a destination with the same slug and creation date, the visits are
+//! kept as hits. Links with a slug that is already taken are reported as conflicts, like the
+//! other imports.
+//!
+//! The SQL dump needs `INSERT` statements with the names of the columns, like
+//! `pg_dump --column-inserts` or `mysqldump --complete-insert`, of the `short_urls` table. The
+//! visits are counted from the `short_url_visits_counts` or `visits` tables, when dumped as well.
+//! The CSV export of the web client needs at least the `shortUrl` (or `shortCode`), `longUrl`
+//! and `createdAt` columns, `visits` is optional and other columns are ignored.
+//!
+//! ```sh
+//! shurly import-shlink shlink.sql --as admin
+//! shurly import-shlink short_urls.csv --as admin
+//! ```
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use anyhow::anyhow;
+use anyhow::Result;
+
+use crate::import::inserts;
+use crate::import::Csv;
+use crate::import::ExportedLink;
+use crate::import::Table;
+
+/// Read the links of a SQL dump of Shlink
+///
+/// # Errors
+///
+/// Will return `Err` when the SQL is invalid or misses one of the needed columns
+pub fn from_sql(sql: &str) -> Result<Vec<ExportedLink>> {
+    let table = named_inserts(sql, "short_urls")?;
+
+    let id = table.column("id", 0)?;
+    let short_code = table.column("short_code", 0)?;
+    let original_url = table.column("original_url", 0)?;
+    let date_created = table.column("date_created", 0)?;
+
+    let visits = visits(sql)?;
+
+    let links = table
+        .rows
+        .iter()
+        .map(|row| ExportedLink {
+            short_link: Table::value(row, short_code)
+                .unwrap_or_default()
+                .to_string(),
+            url: Table::value(row, original_url)
+                .unwrap_or_default()
+                .to_string(),
+            created_at: Table::value(row, date_created)
+                .unwrap_or_default()
+                .to_string(),
+            clicks: Table::value(row, id)
+                .and_then(|id| visits.get(id))
+                .map(ToString::to_string),
+        })
+        .collect();
+
+    Ok(links)
+}
+
+/// Number of visits per short URL, from the counts when dumped or else the visits themselves
+fn visits(sql: &str) -> Result<HashMap<String, u64>> {
+    let mut visits = HashMap::new();
+
+    let counts = named_inserts(sql, "short_url_visits_counts")?;
+    if counts.rows.is_empty() {
+        let table = named_inserts(sql, "visits")?;
+        let short_url_id = table.column("short_url_id", 0)?;
+
+        for row in &table.rows {
+            if let Some(short_url_id) = Table::value(row, short_url_id) {
+                *visits.entry(short_url_id.to_string()).or_default() += 1;
+            }
+        }
+    } else {
+        let short_url_id = counts.column("short_url_id", 0)?;
+        let count = counts.column("count", 0)?;
+
+        for row in &counts.rows {
+            let count = Table::value(row, count)
+                .and_then(|count| count.parse::<u64>().ok())
+                .ok_or_else(|| anyhow!("Invalid SQL: invalid visits count"))?;
+
+            if let Some(short_url_id) = Table::value(row, short_url_id) {
+                *visits.entry(short_url_id.to_string()).or_default() += count;
+            }
+        }
+    }
+
+    Ok(visits)
+}
+
+/// The rows inserted into the table, with the names of the columns
+///
+/// The order of the columns of Shlink differs per version and database
+fn named_inserts(sql: &str, table: &str) -> Result<Table> {
+    let inserts = inserts(sql, table)?;
+
+    if !inserts.rows.is_empty() && inserts.columns.is_empty() {
+        return Err(anyhow!(
+            "Invalid SQL: INSERT of {table} without the names of the columns"
+        ));
+    }
+
+    Ok(inserts)
+}
+
+/// Read the links of a CSV export of Shlink
+///
+/// # Errors
+///
+/// Will return `Err` when the CSV is invalid or misses one of the needed columns
+pub fn from_csv(reader: impl Read) -> Result<Vec<ExportedLink>> {
+    let csv = Csv::from_reader(reader)?;
+
+    let link = csv.column(&["short_url", "short_code"])?;
+    let url = csv.column(&["long_url", "original_url"])?;
+    let created_at = csv.column(&["created_at", "date_created"])?;
+    let visits = csv.optional_column(&["visits", "visits_count"]);
+
+    let links = csv
+        .records()
+        .iter()
+        .map(|record| ExportedLink {
+            short_link: record[link].to_string(),
+            url: record[url].to_string(),
+            created_at: record[created_at].to_string(),
+            clicks: visits.map(|visits| record[visits].to_string()),
+        })
+        .collect();
+
+    Ok(links)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_sql() {
+        let sql = "INSERT INTO public.short_urls (id, original_url, short_code, date_created) \
+                VALUES (1, 'https://www.example.com/', 'abc', '2020-01-02 03:04:05'), \
+                (2, 'https://www.example.com/other', 'def', '2020-01-03 03:04:05');\n\
+            INSERT INTO public.visits (id, short_url_id) VALUES (1, 1), (2, 1), (3, 1);";
+
+        let links = from_sql(sql).unwrap();
+        assert_eq!(2, links.len());
+        assert_eq!("abc", links[0].short_link);
+        assert_eq!(Some("3".into()), links[0].clicks);
+        assert_eq!(None, links[1].clicks);
+
+        let sql = format!(
+            "{sql}\nINSERT INTO short_url_visits_counts (short_url_id, slot_id, count) \
+                VALUES (1, 1, 40), (1, 2, 2), (2, 1, 7);"
+        );
+
+        let links = from_sql(&sql).unwrap();
+        assert_eq!(Some("42".into()), links[0].clicks);
+        assert_eq!(Some("7".into()), links[1].clicks);
+
+        assert!(from_sql("INSERT INTO short_urls VALUES (1, 'abc');").is_err());
+    }
+
+    #[test]
+    fn test_from_csv() {
+        let csv = "createdAt,shortUrl,longUrl,title,tags,visits\n\
+            2020-01-02T03:04:05+00:00,https://s.test/abc,https://www.example.com/,,,12\n";
+
+        assert_eq!(
+            vec![ExportedLink {
+                short_link: "https://s.test/abc".into(),
+                url: "https://www.example.com/".into(),
+                created_at: "2020-01-02T03:04:05+00:00".into(),
+                clicks: Some("12".into()),
+            }],
+            from_csv(csv.as_bytes()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,259 @@
+//! Signed links for private destinations
+//!
+//! Private destinations only redirect with a valid signature and expiry in the query, like
+//! `/slug?expires=1700000000&signature=...`. The signature is a HMAC-SHA256 of the destination ID
+//! and the expiry, so a signed link can not be reused for another destination (even with the same
+//! slug on another domain) or extended.
+//!
+//! Stats links work the same, like `/slug+stats?expires=1700000000&signature=...`, and give
+//! read-only access to the stats of a single destination. Their signatures are scoped to the
+//! stats, a stats link does not open a private destination or the other way around.
+
+use chrono::DateTime;
+use chrono::Utc;
+use hmac::Hmac;
+use hmac::Mac;
+use sha2::Sha256;
+
+use crate::utils::env_var_or_else;
+
+/// HMAC-SHA256, used for the signatures
+type HmacSha256 = Hmac<Sha256>;
+
+/// Query parameter with the expiry, as a UNIX timestamp
+const EXPIRES_PARAMETER: &str = "expires";
+
+/// Query parameter with the signature, hex encoded
+const SIGNATURE_PARAMETER: &str = "signature";
+
+/// Prefix of the signed destination ID of stats links
+const STATS_SCOPE: &str = "stats:";
+
+/// Why a signed link is rejected
+#[derive(Debug, PartialEq, Eq)]
+pub enum SignatureError {
+    /// The link has no signature or expiry
+    Missing,
+
+    /// The signature does not match the destination and expiry
+    Invalid,
+
+    /// The link is no longer valid
+    Expired,
+}
+
+impl SignatureError {
+    /// Description of the rejection, safe to show to users
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Missing => "This link is private",
+            Self::Invalid => "This link has an invalid signature",
+            Self::Expired => "This link has expired",
+        }
+    }
+}
+
+/// Key for signing links of private destinations
+#[derive(Clone)]
+pub struct SigningKey {
+    /// The secret of the HMAC
+    secret: Vec<u8>,
+}
+
+impl SigningKey {
+    /// Create a signing key with the given secret
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            secret: secret.to_vec(),
+        }
+    }
+
+    /// Setup the signing key based on the `SIGNING_SECRET` environment variable
+    ///
+    /// A temporary secret is generated when not set, signed links will not survive a restart
+    pub fn from_environment() -> Self {
+        use crate::password::generate;
+
+        let secret = env_var_or_else("SIGNING_SECRET", || {
+            let secret = generate();
+            tracing::info!("`SIGNING_SECRET` is not set, generating temporary one: {secret}");
+            secret
+        });
+
+        Self::new(secret.as_bytes())
+    }
+
+    /// Sign the destination ID, valid until the expiry
+    ///
+    /// Returns the query string to add to the link
+    pub fn sign(&self, destination_id: &str, expires_at: DateTime<Utc>) -> String {
+        let expires = expires_at.timestamp();
+        let signature = self.signature(destination_id, expires);
+
+        format!("{EXPIRES_PARAMETER}={expires}&{SIGNATURE_PARAMETER}={signature}")
+    }
+
+    /// Verify the signature of the destination ID in the query string
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the signature is missing, invalid or expired
+    pub fn verify(&self, destination_id: &str, query: Option<&str>) -> Result<(), SignatureError> {
+        let mut expires = None;
+        let mut signature = None;
+
+        for (key, value) in url::form_urlencoded::parse(query.unwrap_or_default().as_bytes()) {
+            match &*key {
+                EXPIRES_PARAMETER => expires = Some(value),
+                SIGNATURE_PARAMETER => signature = Some(value),
+                _ => {}
+            }
+        }
+
+        let (Some(expires), Some(signature)) = (expires, signature) else {
+            return Err(SignatureError::Missing);
+        };
+
+        let expires = expires
+            .parse::<i64>()
+            .map_err(|_| SignatureError::Invalid)?;
+
+        let signature = decode_hex(&signature).ok_or(SignatureError::Invalid)?;
+
+        self.mac(destination_id, expires)
+            .verify_slice(&signature)
+            .map_err(|_| SignatureError::Invalid)?;
+
+        if expires < Utc::now().timestamp() {
+            return Err(SignatureError::Expired);
+        }
+
+        Ok(())
+    }
+
+    /// Sign the stats of the destination ID, valid until the expiry
+    ///
+    /// Returns the query string to add to the stats link
+    pub fn sign_stats(&self, destination_id: &str, expires_at: DateTime<Utc>) -> String {
+        self.sign(&format!("{STATS_SCOPE}{destination_id}"), expires_at)
+    }
+
+    /// Verify the signature of the stats of the destination ID in the query string
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the signature is missing, invalid or expired
+    pub fn verify_stats(
+        &self,
+        destination_id: &str,
+        query: Option<&str>,
+    ) -> Result<(), SignatureError> {
+        self.verify(&format!("{STATS_SCOPE}{destination_id}"), query)
+    }
+
+    /// The hex encoded signature of the destination ID and expiry
+    fn signature(&self, destination_id: &str, expires: i64) -> String {
+        format!(
+            "{:x}",
+            self.mac(destination_id, expires).finalize().into_bytes()
+        )
+    }
+
+    /// The HMAC of the destination ID and expiry
+    fn mac(&self, destination_id: &str, expires: i64) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts any key");
+
+        mac.update(destination_id.as_bytes());
+        mac.update(b"\n");
+        mac.update(expires.to_string().as_bytes());
+
+        mac
+    }
+}
+
+/// Decode a hex string, `None` when it is not valid hex
+pub fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if value.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let key = SigningKey::new(b"verysecret");
+
+        let query = key.sign("private", Utc::now() + TimeDelta::hours(1));
+        assert_eq!(Ok(()), key.verify("private", Some(&query)));
+
+        // other destination
+        assert_eq!(
+            Err(SignatureError::Invalid),
+            key.verify("other", Some(&query))
+        );
+
+        // other key
+        assert_eq!(
+            Err(SignatureError::Invalid),
+            SigningKey::new(b"othersecret").verify("private", Some(&query))
+        );
+
+        // extended expiry
+        let extended = query.replace("expires=", "expires=1");
+        assert_eq!(
+            Err(SignatureError::Invalid),
+            key.verify("private", Some(&extended))
+        );
+
+        assert_eq!(Err(SignatureError::Missing), key.verify("private", None));
+    }
+
+    #[test]
+    fn test_sign_and_verify_stats() {
+        let key = SigningKey::new(b"verysecret");
+
+        let query = key.sign_stats("private", Utc::now() + TimeDelta::hours(1));
+        assert_eq!(Ok(()), key.verify_stats("private", Some(&query)));
+
+        // stats links do not open the destination, nor the other way around
+        assert_eq!(
+            Err(SignatureError::Invalid),
+            key.verify("private", Some(&query))
+        );
+
+        let query = key.sign("private", Utc::now() + TimeDelta::hours(1));
+        assert_eq!(
+            Err(SignatureError::Invalid),
+            key.verify_stats("private", Some(&query))
+        );
+    }
+
+    #[test]
+    fn test_verify_expired() {
+        let key = SigningKey::new(b"verysecret");
+
+        let query = key.sign("private", Utc::now() - TimeDelta::minutes(1));
+        assert_eq!(
+            Err(SignatureError::Expired),
+            key.verify("private", Some(&query))
+        );
+    }
+
+    #[test]
+    fn test_decode_hex() {
+        assert_eq!(Some(vec![0x00, 0xff, 0x1a]), decode_hex("00ff1a"));
+        assert_eq!(None, decode_hex("0"));
+        assert_eq!(None, decode_hex("zz"));
+        assert_eq!(None, decode_hex("é0"));
+    }
+}
@@ -0,0 +1,235 @@
+//! Slack slash-command integration, `/shurly create <slug> <url>` and `/shurly stats <slug>`
+//!
+//! Disabled by default, enabled with the `SLACK_SIGNING_SECRET` of the Slack app. Requests are
+//! only accepted with a valid signature of Slack, sent in the last five minutes. Slack users are
+//! mapped to Shurly users with `SLACK_USERS`, unmapped users can not run any commands.
+
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use chrono::Utc;
+use hmac::Hmac;
+use hmac::Mac;
+use sha2::Sha256;
+
+use crate::signing::decode_hex;
+use crate::utils::env_var_optional;
+
+/// HMAC-SHA256, used for the signatures of Slack
+type HmacSha256 = Hmac<Sha256>;
+
+/// Version of the signatures of Slack
+const SIGNATURE_VERSION: &str = "v0";
+
+/// Maximum age of a request in seconds, older requests could be replayed
+const MAX_AGE: i64 = 60 * 5;
+
+/// Settings of the Slack integration
+#[derive(Clone, Debug, Default)]
+pub struct Slack {
+    /// Signing secret of the Slack app, disabled without
+    signing_secret: Option<Vec<u8>>,
+
+    /// Shurly usernames by the ID of the Slack user
+    users: HashMap<String, String>,
+}
+
+impl Slack {
+    /// Create the Slack integration with a signing secret and the users, by their Slack ID
+    pub fn new(signing_secret: &[u8], users: HashMap<String, String>) -> Self {
+        Self {
+            signing_secret: Some(signing_secret.to_vec()),
+            users,
+        }
+    }
+
+    /// Setup the Slack integration based on the `SLACK_SIGNING_SECRET` and `SLACK_USERS`
+    /// environment variables
+    ///
+    /// The users are a comma separated list of the ID of the Slack user and the Shurly username,
+    /// like `U012AB3CD=admin,U045EF6GH=marketing`
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when a user is not valid
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let Some(signing_secret) = env_var_optional("SLACK_SIGNING_SECRET") else {
+            return Ok(Self::default());
+        };
+
+        let users = env_var_optional("SLACK_USERS")
+            .as_deref()
+            .map(parse_users)
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(Self::new(signing_secret.as_bytes(), users))
+    }
+
+    /// Is the integration enabled?
+    pub fn is_enabled(&self) -> bool {
+        self.signing_secret.is_some()
+    }
+
+    /// The Shurly username of the Slack user
+    pub fn username(&self, slack_user_id: &str) -> Option<&str> {
+        self.users.get(slack_user_id).map(String::as_str)
+    }
+
+    /// Verify the `X-Slack-Signature` of the body, sent at the `X-Slack-Request-Timestamp`
+    pub fn verify(&self, timestamp: &str, signature: &str, body: &[u8]) -> bool {
+        let Some(signing_secret) = &self.signing_secret else {
+            return false;
+        };
+
+        let Ok(sent_at) = timestamp.parse::<i64>() else {
+            return false;
+        };
+
+        if (Utc::now().timestamp() - sent_at).abs() > MAX_AGE {
+            return false;
+        }
+
+        let Some(signature) = signature
+            .strip_prefix(SIGNATURE_VERSION)
+            .and_then(|signature| signature.strip_prefix('='))
+            .and_then(decode_hex)
+        else {
+            return false;
+        };
+
+        let mut mac = HmacSha256::new_from_slice(signing_secret).expect("HMAC accepts any key");
+        mac.update(format!("{SIGNATURE_VERSION}:{timestamp}:").as_bytes());
+        mac.update(body);
+
+        mac.verify_slice(&signature).is_ok()
+    }
+}
+
+/// Parse the comma separated `<slack user ID>=<username>` pairs
+fn parse_users(users: &str) -> anyhow::Result<HashMap<String, String>> {
+    users
+        .split(',')
+        .map(str::trim)
+        .filter(|user| !user.is_empty())
+        .map(|user| {
+            user.split_once('=')
+                .map(|(slack_user_id, username)| {
+                    (
+                        slack_user_id.trim().to_string(),
+                        username.trim().to_string(),
+                    )
+                })
+                .filter(|(slack_user_id, username)| {
+                    !slack_user_id.is_empty() && !username.is_empty()
+                })
+                .ok_or_else(|| {
+                    anyhow!("Invalid Slack user, expected `<slack user ID>=<username>`: {user}")
+                })
+        })
+        .collect()
+}
+
+/// A slash-command, from the text after `/shurly`
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command<'a> {
+    /// Create a destination
+    Create {
+        /// Slug of the destination
+        slug: &'a str,
+
+        /// URL of the destination
+        url: &'a str,
+    },
+
+    /// Show the hits of a destination
+    Stats {
+        /// Slug of the destination
+        slug: &'a str,
+    },
+
+    /// Explain the commands, also for unknown commands
+    Help,
+}
+
+impl<'a> Command<'a> {
+    /// Parse the text of the slash-command
+    pub fn parse(text: &'a str) -> Self {
+        let parts = text.split_whitespace().collect::<Vec<_>>();
+
+        match parts.as_slice() {
+            ["create", slug, url] => Self::Create { slug, url },
+            ["stats", slug] => Self::Stats { slug },
+            _ => Self::Help,
+        }
+    }
+}
+
+/// Explanation of the commands
+pub const HELP: &str = "Usage:\n\
+    • `/shurly create <slug> <url>` creates a destination\n\
+    • `/shurly stats <slug>` shows the hits of a destination";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(secret: &[u8], timestamp: &str, body: &[u8]) -> String {
+        let mut mac = HmacSha256::new_from_slice(secret).unwrap();
+        mac.update(format!("v0:{timestamp}:").as_bytes());
+        mac.update(body);
+
+        format!("v0={:x}", mac.finalize().into_bytes())
+    }
+
+    #[test]
+    fn test_verify() {
+        let slack = Slack::new(b"secret", HashMap::new());
+        let body = b"command=%2Fshurly&text=stats+abc";
+
+        let timestamp = Utc::now().timestamp().to_string();
+        let signature = sign(b"secret", &timestamp, body);
+        assert!(slack.verify(&timestamp, &signature, body));
+
+        // another body, secret or version
+        assert!(!slack.verify(&timestamp, &signature, b"text=stats+def"));
+        assert!(!slack.verify(&timestamp, &sign(b"other", &timestamp, body), body));
+        assert!(!slack.verify(&timestamp, &signature.replace("v0=", "v1="), body));
+
+        // too old to be trusted
+        let timestamp = (Utc::now().timestamp() - 600).to_string();
+        let signature = sign(b"secret", &timestamp, body);
+        assert!(!slack.verify(&timestamp, &signature, body));
+
+        // disabled
+        assert!(!Slack::default().verify(&timestamp, &signature, body));
+    }
+
+    #[test]
+    fn test_parse_users() {
+        let users = parse_users("U012AB3CD=admin, U045EF6GH = marketing,").unwrap();
+        assert_eq!(2, users.len());
+        assert_eq!("admin", users["U012AB3CD"]);
+        assert_eq!("marketing", users["U045EF6GH"]);
+
+        assert!(parse_users("U012AB3CD").is_err());
+        assert!(parse_users("U012AB3CD=").is_err());
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            Command::Create {
+                slug: "launch",
+                url: "https://www.example.com/"
+            },
+            Command::parse(" create launch  https://www.example.com/")
+        );
+        assert_eq!(
+            Command::Stats { slug: "launch" },
+            Command::parse("stats launch")
+        );
+        assert_eq!(Command::Help, Command::parse(""));
+        assert_eq!(Command::Help, Command::parse("delete launch"));
+    }
+}
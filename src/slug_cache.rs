@@ -0,0 +1,623 @@
+//! Cache of the destinations of slugs
+//!
+//! Every request on the root looks up a destination by slug, the cache keeps the database out of
+//! the hot path. Slugs without a destination are cached as well, but only for a short while:
+//! random 404 scanning should not end up in the database for every request, while a new
+//! destination should not take long to show up on other instances.
+//!
+//! Concurrent lookups of the same slug are coalesced into a single query to the database, so a
+//! stampede on a cold or expired slug results in a single query.
+//!
+//! Multiple instances of Shurly can share a database, invalidations are sent to all instances
+//! with `NOTIFY` of Postgres. When the connection of the listener is lost, invalidations may be
+//! missed and the complete cache is flushed.
+//!
+//! The capacity of the cache and the lifetime of the cached destinations can be configured, by
+//! default destinations are kept until the capacity is reached or they change.
+//!
+//! On startup the cache is warmed up with the most hit destinations of the last week, so a
+//! restart during peak traffic does not send every request to the database at once.
+//!
+//! An audit checks a sample of the cached slugs against the database, and forgets the stale
+//! slugs on all instances; a stale slug points at a missed invalidation.
+
+use std::collections::BTreeSet;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use moka::future::Cache;
+use moka::Expiry;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::sync::watch;
+use tracing::Span;
+
+use crate::database::Database;
+use crate::database::Error;
+use crate::database::SlugCacheListener;
+use crate::destinations::Destination;
+use crate::utils::env_var_optional;
+use crate::utils::parse_positive;
+
+/// Default maximum number of cached slugs
+pub const DEFAULT_CACHE_MAX_CAPACITY: u64 = 10_000;
+
+/// Default number of destinations to warm up the cache with
+pub const DEFAULT_CACHE_WARM_UP: u64 = 100;
+
+/// Number of days of hits to find the destinations to warm up the cache with
+const WARM_UP_DAYS: i32 = 7;
+
+/// How long slugs without a destination are cached
+const MISSING_TIME_TO_LIVE: Duration = Duration::from_secs(10);
+
+/// Longest configurable lifetime of cached destinations, a year
+const MAX_TIME: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// How long to wait before listening again, after the listener failed to reconnect
+const LISTEN_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Default number of cached slugs checked by an audit
+pub const DEFAULT_AUDIT_SAMPLE: usize = 100;
+
+/// Maximum number of cached slugs checked by an audit
+pub const MAX_AUDIT_SAMPLE: usize = 10_000;
+
+/// Invalidation of the cache, as sent to all instances
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum Invalidation {
+    /// Forget the slug on all domains
+    Slug {
+        /// The slug to forget
+        slug: String,
+    },
+
+    /// Forget all slugs
+    Flush,
+}
+
+/// Key of a cached slug, as served on the domain
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+struct SlugKey {
+    /// Domain of the request, if known
+    domain: Option<String>,
+
+    /// Slug of the request
+    slug: String,
+}
+
+/// Expiry of the cached slugs, only slugs without a destination expire
+struct MissingExpiry;
+
+impl Expiry<SlugKey, Option<Destination>> for MissingExpiry {
+    fn expire_after_create(
+        &self,
+        _key: &SlugKey,
+        destination: &Option<Destination>,
+        _created_at: Instant,
+    ) -> Option<Duration> {
+        destination.is_none().then_some(MISSING_TIME_TO_LIVE)
+    }
+}
+
+/// Counters of the cache usage, since startup
+#[derive(Debug, Default)]
+struct Counters {
+    /// Number of lookups
+    lookups: AtomicU64,
+
+    /// Number of lookups that went to the database
+    misses: AtomicU64,
+
+    /// Number of entries removed because of the capacity or their expiry
+    evictions: AtomicU64,
+}
+
+/// Statistics of the cache, since startup
+#[derive(Debug)]
+pub struct Statistics {
+    /// Number of cached slugs, including slugs without a destination
+    pub entries: u64,
+
+    /// Number of lookups
+    pub lookups: u64,
+
+    /// Number of lookups served from the cache
+    pub hits: u64,
+
+    /// Number of lookups that went to the database
+    pub misses: u64,
+
+    /// Number of entries removed because of the capacity or their expiry
+    pub evictions: u64,
+}
+
+/// A cached slug that differs from the database
+#[derive(Debug)]
+pub struct StaleEntry {
+    /// Domain of the request, if known
+    pub domain: Option<String>,
+
+    /// Slug of the request
+    pub slug: String,
+
+    /// The cached destination, `None` when cached without a destination
+    pub cached: Option<Destination>,
+
+    /// The destination in the database, `None` without a destination
+    pub actual: Option<Destination>,
+}
+
+/// Outcome of an audit of the cache
+#[derive(Debug, Default)]
+pub struct Audit {
+    /// Number of checked slugs
+    pub checked: usize,
+
+    /// The stale slugs, forgotten on all instances
+    pub stale: Vec<StaleEntry>,
+}
+
+impl Statistics {
+    /// Part of the lookups served from the cache, `0` without lookups
+    #[allow(clippy::cast_precision_loss)] // a ratio does not need to be precise
+    pub fn hit_ratio(&self) -> f64 {
+        if self.lookups == 0 {
+            return 0.0;
+        }
+
+        self.hits as f64 / self.lookups as f64
+    }
+}
+
+/// Cache of the destinations of slugs, `None` when the slug has no destination
+///
+/// Clones share the same cache
+#[derive(Clone)]
+pub struct SlugFoundCache {
+    /// The cached lookups
+    cache: Cache<SlugKey, Option<Destination>>,
+
+    /// Counters of the cache usage
+    counters: Arc<Counters>,
+
+    /// Dropped together with the last clone, to stop listening for invalidations
+    dropped: Arc<watch::Sender<()>>,
+
+    /// Number of destinations to warm up the cache with, `0` to skip the warm-up
+    warm_up: u64,
+}
+
+impl Default for SlugFoundCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_MAX_CAPACITY, None, None)
+    }
+}
+
+impl SlugFoundCache {
+    /// Create a cache with room for the given number of slugs
+    ///
+    /// Cached slugs expire after `time_to_live` since they were cached, or after `time_to_idle`
+    /// since they were last used, if any. Slugs without a destination always expire quickly.
+    pub fn new(
+        max_capacity: u64,
+        time_to_live: Option<Duration>,
+        time_to_idle: Option<Duration>,
+    ) -> Self {
+        let counters = Arc::new(Counters::default());
+
+        let evictions = Arc::clone(&counters);
+        let mut builder = Cache::builder()
+            .max_capacity(max_capacity)
+            .expire_after(MissingExpiry);
+
+        if let Some(time_to_live) = time_to_live {
+            builder = builder.time_to_live(time_to_live);
+        }
+
+        if let Some(time_to_idle) = time_to_idle {
+            builder = builder.time_to_idle(time_to_idle);
+        }
+
+        let cache = builder
+            .support_invalidation_closures()
+            .eviction_listener(move |_, _, cause| {
+                if cause.was_evicted() {
+                    evictions.evictions.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+            .build();
+
+        Self {
+            cache,
+            counters,
+            dropped: Arc::new(watch::Sender::new(())),
+            warm_up: DEFAULT_CACHE_WARM_UP,
+        }
+    }
+
+    /// Setup the cache based on the `CACHE_MAX_CAPACITY`, `CACHE_TIME_TO_LIVE`,
+    /// `CACHE_TIME_TO_IDLE` and `CACHE_WARM_UP` environment variables
+    ///
+    /// The times are in seconds, without a time cached destinations do not expire
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the capacity or times are not positive whole numbers, the times
+    /// are longer than a year or the warm-up is not a whole number
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let max_capacity = env_var_optional("CACHE_MAX_CAPACITY")
+            .map(|max_capacity| parse_positive("CACHE_MAX_CAPACITY", &max_capacity))
+            .transpose()?
+            .unwrap_or(DEFAULT_CACHE_MAX_CAPACITY);
+
+        let time_to_live = env_var_optional("CACHE_TIME_TO_LIVE")
+            .map(|seconds| parse_time("CACHE_TIME_TO_LIVE", &seconds))
+            .transpose()?;
+
+        let time_to_idle = env_var_optional("CACHE_TIME_TO_IDLE")
+            .map(|seconds| parse_time("CACHE_TIME_TO_IDLE", &seconds))
+            .transpose()?;
+
+        let warm_up = env_var_optional("CACHE_WARM_UP")
+            .map(|warm_up| {
+                warm_up.parse::<u64>().map_err(|_| {
+                    anyhow::anyhow!("Invalid CACHE_WARM_UP: {warm_up}, expected a whole number")
+                })
+            })
+            .transpose()?
+            .unwrap_or(DEFAULT_CACHE_WARM_UP);
+
+        Ok(Self {
+            warm_up,
+            ..Self::new(max_capacity, time_to_live, time_to_idle)
+        })
+    }
+
+    /// Find the destination of the slug as served on the domain, from cache when possible
+    ///
+    /// See [`find_single_destination_by_slug`](Database::find_single_destination_by_slug)
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the destination could not be looked up, errors are not cached
+    pub async fn find(
+        &self,
+        database: &Database,
+        domain: Option<&str>,
+        slug: &str,
+    ) -> Result<Option<Destination>, Arc<Error>> {
+        let (destination, is_miss) = self.lookup(database, domain, slug).await;
+
+        // lookups waiting on the query of another lookup are hits as well
+        Span::current().record("cache", if is_miss { "miss" } else { "hit" });
+
+        destination
+    }
+
+    /// Find the destination of a slug another destination redirects to, from cache when possible
+    ///
+    /// Like [`find`](Self::find), without recording the cache on the span of the request; that
+    /// is about the slug of the request
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the destination could not be looked up, errors are not cached
+    pub async fn follow(
+        &self,
+        database: &Database,
+        domain: Option<&str>,
+        slug: &str,
+    ) -> Result<Option<Destination>, Arc<Error>> {
+        self.lookup(database, domain, slug).await.0
+    }
+
+    /// Find the destination of the slug, and whether the database was queried for it
+    async fn lookup(
+        &self,
+        database: &Database,
+        domain: Option<&str>,
+        slug: &str,
+    ) -> (Result<Option<Destination>, Arc<Error>>, bool) {
+        let key = SlugKey {
+            domain: domain.map(ToString::to_string),
+            slug: slug.to_string(),
+        };
+
+        self.counters.lookups.fetch_add(1, Ordering::Relaxed);
+
+        let mut is_miss = false;
+
+        let destination = self
+            .cache
+            .try_get_with(key, async {
+                is_miss = true;
+                self.counters.misses.fetch_add(1, Ordering::Relaxed);
+
+                database.find_single_destination_by_slug(domain, slug).await
+            })
+            .await;
+
+        (destination, is_miss)
+    }
+
+    /// Forget the slug on all domains of all instances, after its destination is created or
+    /// changed
+    ///
+    /// A destination for all domains affects the slug on every domain
+    pub async fn invalidate(&self, database: &Database, slug: &str) {
+        self.apply_everywhere(
+            database,
+            Invalidation::Slug {
+                slug: slug.to_string(),
+            },
+        )
+        .await;
+    }
+
+    /// Forget all slugs, on all instances
+    pub async fn flush(&self, database: &Database) {
+        self.apply_everywhere(database, Invalidation::Flush).await;
+    }
+
+    /// Warm up the cache with the most hit destinations of the last week
+    ///
+    /// Destinations of a domain are cached for their domain, destinations for all domains are
+    /// cached for requests without a `Host`, the registered domains and the given hostnames.
+    /// Warming up is a nicety, failing to warm up results in a cold cache.
+    pub async fn warm_up(&self, database: &Database, hostnames: &[String]) {
+        if self.warm_up == 0 {
+            return;
+        }
+
+        let limit = i64::try_from(self.warm_up).unwrap_or(i64::MAX);
+
+        let (destinations, domains) = match tokio::try_join!(
+            database.find_most_hit_destinations(WARM_UP_DAYS, limit),
+            database.find_all_domains(),
+        ) {
+            Ok(found) => found,
+            Err(err) => {
+                tracing::error!("Could not warm up the cache: {err}");
+
+                return;
+            }
+        };
+
+        let mut all_domains = vec![None];
+        all_domains.extend(hostnames.iter().cloned().map(Some));
+        all_domains.extend(domains.into_iter().map(|domain| Some(domain.hostname)));
+        all_domains.sort();
+        all_domains.dedup();
+
+        for destination in destinations {
+            let domains = match destination.domain {
+                Some(ref domain) => vec![Some(domain.clone())],
+                None => all_domains.clone(),
+            };
+
+            for domain in domains {
+                // a destination of the domain itself could be preferred over this one
+                match database
+                    .find_single_destination_by_slug(domain.as_deref(), &destination.slug)
+                    .await
+                {
+                    Ok(found) => {
+                        let key = SlugKey {
+                            domain,
+                            slug: destination.slug.clone(),
+                        };
+
+                        self.cache.insert(key, found).await;
+                    }
+                    Err(err) => {
+                        tracing::error!("Could not warm up the cache: {err}");
+
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Listen for invalidations of other instances, in the background
+    ///
+    /// Stops listening when the last clone of the cache is dropped
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the listener could not be setup
+    pub async fn listen(&self, database: &Database) -> Result<(), Error> {
+        let mut listener = database.listen_slug_cache().await?;
+
+        let cache = self.cache.clone();
+        let mut dropped = self.dropped.subscribe();
+
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    // only errors when the cache is dropped
+                    Err(_) = dropped.changed() => break,
+                    () = receive(&cache, &mut listener) => {},
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Apply the invalidation right away and notify all instances
+    ///
+    /// The invalidation is not lost when other instances can not be notified, those will have
+    /// stale entries until the destinations change again
+    async fn apply_everywhere(&self, database: &Database, invalidation: Invalidation) {
+        apply(&self.cache, &invalidation);
+
+        notify(database, &invalidation).await;
+    }
+
+    /// Check a sample of the cached slugs against the database, the stale slugs are forgotten
+    /// on all instances
+    ///
+    /// A slug is stale when its cached destination differs from the destination in the database,
+    /// or only one of them has a destination. The sample is in no particular order. A slug that
+    /// changes while it is checked can be reported as stale, forgetting it does no harm.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when a destination could not be looked up
+    pub async fn audit(&self, database: &Database, sample: usize) -> Result<Audit, Error> {
+        let entries = self.cache.iter().take(sample).collect::<Vec<_>>();
+
+        let mut audit = Audit::default();
+        for (key, cached) in entries {
+            let actual = database
+                .find_single_destination_by_slug(key.domain.as_deref(), &key.slug)
+                .await?;
+
+            audit.checked += 1;
+
+            if cached != actual {
+                audit.stale.push(StaleEntry {
+                    domain: key.domain.clone(),
+                    slug: key.slug.clone(),
+                    cached,
+                    actual,
+                });
+            }
+        }
+
+        let stale_slugs = audit
+            .stale
+            .iter()
+            .map(|entry| entry.slug.as_str())
+            .collect::<BTreeSet<_>>();
+        for slug in stale_slugs {
+            tracing::warn!(r#"Slug "{slug}" is stale in the cache, forgetting it"#);
+
+            self.invalidate(database, slug).await;
+        }
+
+        Ok(audit)
+    }
+
+    /// Statistics of the cache, since startup
+    pub async fn statistics(&self) -> Statistics {
+        // the entry count is only accurate after the pending maintenance
+        self.cache.run_pending_tasks().await;
+
+        let lookups = self.counters.lookups.load(Ordering::Relaxed);
+        let misses = self.counters.misses.load(Ordering::Relaxed);
+
+        Statistics {
+            entries: self.cache.entry_count(),
+            lookups,
+            hits: lookups.saturating_sub(misses),
+            misses,
+            evictions: self.counters.evictions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Receive and apply a single invalidation from the listener
+async fn receive(cache: &Cache<SlugKey, Option<Destination>>, listener: &mut SlugCacheListener) {
+    match listener.recv().await {
+        Ok(Some(payload)) => match serde_json::from_str::<Invalidation>(&payload) {
+            Ok(invalidation) => apply(cache, &invalidation),
+            Err(err) => tracing::error!("Invalid cache invalidation: {err}"),
+        },
+        Ok(None) => {
+            tracing::warn!("Lost connection of cache invalidations, flushing the cache");
+
+            cache.invalidate_all();
+        }
+        Err(err) => {
+            tracing::error!("Could not listen for cache invalidations: {err}");
+
+            tokio::time::sleep(LISTEN_RETRY_INTERVAL).await;
+        }
+    }
+}
+
+/// Parse a time in seconds of an environment variable, up to a year
+fn parse_time(var_name: &str, value: &str) -> anyhow::Result<Duration> {
+    let time = Duration::from_secs(parse_positive(var_name, value)?);
+
+    if time > MAX_TIME {
+        return Err(anyhow::anyhow!(
+            "Invalid {var_name}: {value}, expected at most a year"
+        ));
+    }
+
+    Ok(time)
+}
+
+/// Forget the slug on all instances, without the cache at hand, like in a job
+///
+/// Every instance applies the invalidation with its listener, this instance as well
+pub async fn invalidate_everywhere(database: &Database, slug: &str) {
+    notify(
+        database,
+        &Invalidation::Slug {
+            slug: slug.to_string(),
+        },
+    )
+    .await;
+}
+
+/// Notify all instances of the invalidation
+async fn notify(database: &Database, invalidation: &Invalidation) {
+    let payload = serde_json::to_string(invalidation).expect("Valid invalidation");
+
+    if let Err(err) = database.notify_slug_cache(&payload).await {
+        tracing::error!("Could not notify other instances of cache invalidation: {err}");
+    }
+}
+
+/// Apply the invalidation on the cache of this instance
+fn apply(cache: &Cache<SlugKey, Option<Destination>>, invalidation: &Invalidation) {
+    match invalidation {
+        Invalidation::Slug { slug } => {
+            let slug = slug.clone();
+
+            cache
+                .invalidate_entries_if(move |key, _| key.slug == slug)
+                .expect("Invalidation closures are supported");
+        }
+        Invalidation::Flush => cache.invalidate_all(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalidation_payload() {
+        let invalidation = Invalidation::Slug {
+            slug: "some/slug".to_string(),
+        };
+
+        let payload = serde_json::to_string(&invalidation).unwrap();
+        assert_eq!(r#"{"type":"slug","slug":"some/slug"}"#, payload);
+        assert_eq!(invalidation, serde_json::from_str(&payload).unwrap());
+
+        let payload = serde_json::to_string(&Invalidation::Flush).unwrap();
+        assert_eq!(r#"{"type":"flush"}"#, payload);
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(
+            Duration::from_secs(3600),
+            parse_time("CACHE_TIME_TO_LIVE", "3600").unwrap()
+        );
+        assert!(parse_time("CACHE_TIME_TO_LIVE", "31536000").is_ok());
+        assert!(parse_time("CACHE_TIME_TO_LIVE", "31536001").is_err());
+        assert!(parse_time("CACHE_TIME_TO_IDLE", "0").is_err());
+    }
+}
@@ -0,0 +1,372 @@
+//! Policy for the slugs of new destinations
+//!
+//! By default any slug goes, as long as it has no `?` or `#`. Deployments can restrict new slugs
+//! with `SLUG_MAX_LENGTH`, the maximum number of characters, and `SLUG_CHARACTERS`, a
+//! comma-separated list of the allowed classes of characters:
+//!
+//! - `lowercase`, the letters `a` to `z`
+//! - `uppercase`, the letters `A` to `Z`
+//! - `digits`, the digits `0` to `9`
+//! - `punctuation`, the `-`, `_`, `.` and `~` that need no encoding in a URL
+//! - `slashes`, a `/` between path segments, like `campaign/summer`
+//! - `unicode`, letters and digits of other scripts, like `é` or `ß`
+//! - `emoji`, emoji and their modifiers, like `🦙`
+//!
+//! Next to the slugs Shurly serves itself, like `robots.txt`, more slugs can be reserved with
+//! `RESERVED_SLUGS`, a comma-separated list like `admin,login`.
+//!
+//! Existing destinations are not touched by the policy, their slugs keep working.
+
+use std::sync::Arc;
+use std::sync::RwLock;
+
+use crate::utils::env_var_optional;
+
+/// A class of characters allowed in slugs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CharacterClass {
+    /// The letters `a` to `z`
+    Lowercase,
+
+    /// The letters `A` to `Z`
+    Uppercase,
+
+    /// The digits `0` to `9`
+    Digits,
+
+    /// `-`, `_`, `.` and `~`
+    Punctuation,
+
+    /// `/` between path segments
+    Slashes,
+
+    /// Letters and digits of other scripts
+    Unicode,
+
+    /// Emoji and their modifiers
+    Emoji,
+}
+
+impl CharacterClass {
+    /// All classes
+    const ALL: [Self; 7] = [
+        Self::Lowercase,
+        Self::Uppercase,
+        Self::Digits,
+        Self::Punctuation,
+        Self::Slashes,
+        Self::Unicode,
+        Self::Emoji,
+    ];
+
+    /// Name of the class, as used in the configuration
+    fn name(self) -> &'static str {
+        match self {
+            Self::Lowercase => "lowercase",
+            Self::Uppercase => "uppercase",
+            Self::Digits => "digits",
+            Self::Punctuation => "punctuation",
+            Self::Slashes => "slashes",
+            Self::Unicode => "unicode",
+            Self::Emoji => "emoji",
+        }
+    }
+
+    /// The class of a character, `None` when it is in none of the classes
+    fn of(ch: char) -> Option<Self> {
+        match ch {
+            'a'..='z' => Some(Self::Lowercase),
+            'A'..='Z' => Some(Self::Uppercase),
+            '0'..='9' => Some(Self::Digits),
+            '-' | '_' | '.' | '~' => Some(Self::Punctuation),
+            '/' => Some(Self::Slashes),
+            ch if is_emoji(ch) => Some(Self::Emoji),
+            ch if !ch.is_ascii() && ch.is_alphanumeric() => Some(Self::Unicode),
+            _ => None,
+        }
+    }
+}
+
+/// Is the character an emoji, or part of one, like a skin tone or a joiner?
+fn is_emoji(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{200d}'
+            | '\u{20e3}'
+            | '\u{2190}'..='\u{21ff}'
+            | '\u{2300}'..='\u{23ff}'
+            | '\u{2600}'..='\u{27bf}'
+            | '\u{2b00}'..='\u{2bff}'
+            | '\u{fe0e}'..='\u{fe0f}'
+            | '\u{1f000}'..='\u{1faff}'
+            | '\u{e0020}'..='\u{e007f}'
+    )
+}
+
+/// Why a slug is not allowed by the policy
+#[derive(Debug, PartialEq, Eq)]
+pub enum SlugViolation {
+    /// The slug has more characters than allowed
+    TooLong(usize),
+
+    /// The slug has a character of a class that is not allowed
+    Character(char),
+
+    /// The slug has an empty path segment, like `campaign//summer`
+    EmptySegment,
+
+    /// The slug is reserved by the deployment
+    Reserved,
+}
+
+/// The rules of the policy
+#[derive(Clone, Debug, Default)]
+struct Rules {
+    /// Maximum number of characters, no maximum when not set
+    max_length: Option<usize>,
+
+    /// Allowed classes of characters, any character when not set
+    characters: Option<Vec<CharacterClass>>,
+
+    /// Slugs that can not be used
+    reserved: Vec<String>,
+}
+
+/// The policy for the slugs of new destinations
+///
+/// Clones share the same policy
+#[derive(Clone, Debug, Default)]
+pub struct SlugPolicy {
+    /// The rules, replaced when reloaded
+    rules: Arc<RwLock<Rules>>,
+}
+
+impl SlugPolicy {
+    /// Setup the policy based on the `SLUG_MAX_LENGTH`, `SLUG_CHARACTERS` and `RESERVED_SLUGS`
+    /// environment variables
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the maximum length is not a positive number or a class is unknown
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let max_length = env_var_optional("SLUG_MAX_LENGTH")
+            .map(|value| match value.parse::<usize>() {
+                Ok(max_length) if max_length > 0 => Ok(max_length),
+                _ => Err(anyhow::anyhow!(
+                    "Invalid SLUG_MAX_LENGTH: {value}, expected a positive whole number"
+                )),
+            })
+            .transpose()?;
+
+        let characters = env_var_optional("SLUG_CHARACTERS")
+            .map(|value| parse_classes(&value))
+            .transpose()?;
+
+        let reserved = env_var_optional("RESERVED_SLUGS")
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(|slug| slug.trim().trim_matches('/').to_string())
+                    .filter(|slug| !slug.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            rules: Arc::new(RwLock::new(Rules {
+                max_length,
+                characters,
+                reserved,
+            })),
+        })
+    }
+
+    /// Allow at most the number of characters
+    #[must_use]
+    pub fn with_max_length(self, max_length: usize) -> Self {
+        self.rules
+            .write()
+            .expect("Valid slug policy lock")
+            .max_length = Some(max_length);
+        self
+    }
+
+    /// Allow only the classes of characters
+    #[must_use]
+    pub fn with_characters(self, characters: &[CharacterClass]) -> Self {
+        self.rules
+            .write()
+            .expect("Valid slug policy lock")
+            .characters = Some(characters.to_vec());
+        self
+    }
+
+    /// Reserve the slugs
+    #[must_use]
+    pub fn with_reserved(self, reserved: &[&str]) -> Self {
+        self.rules.write().expect("Valid slug policy lock").reserved =
+            reserved.iter().map(ToString::to_string).collect();
+        self
+    }
+
+    /// Use the rules of the reloaded policy
+    pub fn reload(&self, reloaded: &Self) {
+        let rules = reloaded.rules();
+
+        *self.rules.write().expect("Valid slug policy lock") = rules;
+    }
+
+    /// Check the slug, already trimmed of slashes and normalized
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` with the first violation of the policy
+    pub fn check(&self, slug: &str) -> Result<(), SlugViolation> {
+        let rules = self.rules();
+
+        if rules.reserved.iter().any(|reserved| reserved == slug) {
+            return Err(SlugViolation::Reserved);
+        }
+
+        if let Some(max_length) = rules.max_length {
+            if slug.chars().count() > max_length {
+                return Err(SlugViolation::TooLong(max_length));
+            }
+        }
+
+        let Some(characters) = &rules.characters else {
+            return Ok(());
+        };
+
+        if let Some(ch) = slug
+            .chars()
+            .find(|ch| CharacterClass::of(*ch).is_none_or(|class| !characters.contains(&class)))
+        {
+            return Err(SlugViolation::Character(ch));
+        }
+
+        if slug.contains("//") {
+            return Err(SlugViolation::EmptySegment);
+        }
+
+        Ok(())
+    }
+
+    /// Description of the allowed characters, like `lowercase, digits and punctuation`
+    pub fn describe_characters(&self) -> String {
+        let rules = self.rules();
+        let names = rules
+            .characters
+            .as_deref()
+            .unwrap_or(&CharacterClass::ALL)
+            .iter()
+            .map(|class| class.name())
+            .collect::<Vec<_>>();
+
+        match names.split_last() {
+            Some((last, [])) => (*last).to_string(),
+            Some((last, rest)) => format!("{} and {last}", rest.join(", ")),
+            None => String::new(),
+        }
+    }
+
+    /// The current rules
+    fn rules(&self) -> Rules {
+        self.rules.read().expect("Valid slug policy lock").clone()
+    }
+}
+
+/// Parse a comma-separated list of classes
+fn parse_classes(value: &str) -> anyhow::Result<Vec<CharacterClass>> {
+    let classes = value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            CharacterClass::ALL
+                .into_iter()
+                .find(|class| class.name() == name)
+                .ok_or_else(|| anyhow::anyhow!("Invalid SLUG_CHARACTERS: unknown class {name}"))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    if classes.is_empty() {
+        return Err(anyhow::anyhow!("Invalid SLUG_CHARACTERS: no classes"));
+    }
+
+    Ok(classes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        assert_eq!(Ok(()), SlugPolicy::default().check("Any thing 🦙!"));
+
+        let policy = SlugPolicy::default().with_max_length(12).with_characters(&[
+            CharacterClass::Lowercase,
+            CharacterClass::Digits,
+            CharacterClass::Punctuation,
+        ]);
+        assert_eq!(Ok(()), policy.check("summer-2025"));
+        assert_eq!(
+            Err(SlugViolation::TooLong(12)),
+            policy.check("summer-sale-2025")
+        );
+        assert_eq!(Err(SlugViolation::Character('S')), policy.check("Summer"));
+        assert_eq!(Err(SlugViolation::Character('/')), policy.check("a/b"));
+        assert_eq!(Err(SlugViolation::Character('é')), policy.check("café"));
+
+        let policy = SlugPolicy::default().with_characters(&[
+            CharacterClass::Lowercase,
+            CharacterClass::Slashes,
+            CharacterClass::Unicode,
+            CharacterClass::Emoji,
+        ]);
+        assert_eq!(Ok(()), policy.check("café/🦙"));
+        assert_eq!(Ok(()), policy.check("👍🏽"));
+        assert_eq!(Err(SlugViolation::EmptySegment), policy.check("a//b"));
+        assert_eq!(Err(SlugViolation::Character(' ')), policy.check("a b"));
+
+        let policy = SlugPolicy::default().with_reserved(&["admin", "login"]);
+        assert_eq!(Err(SlugViolation::Reserved), policy.check("admin"));
+        assert_eq!(Ok(()), policy.check("admin/users"));
+    }
+
+    #[test]
+    fn test_reload() {
+        let policy = SlugPolicy::default();
+        let shared = policy.clone();
+
+        policy.reload(&SlugPolicy::default().with_max_length(4));
+        assert_eq!(Err(SlugViolation::TooLong(4)), shared.check("summer"));
+    }
+
+    #[test]
+    fn test_parse_classes() {
+        assert_eq!(
+            vec![CharacterClass::Lowercase, CharacterClass::Emoji],
+            parse_classes("lowercase, emoji,").unwrap()
+        );
+        assert!(parse_classes("everything").is_err());
+        assert!(parse_classes(",").is_err());
+    }
+
+    #[test]
+    fn test_describe_characters() {
+        let policy = SlugPolicy::default().with_characters(&[
+            CharacterClass::Lowercase,
+            CharacterClass::Digits,
+            CharacterClass::Punctuation,
+        ]);
+        assert_eq!(
+            "lowercase, digits and punctuation",
+            policy.describe_characters()
+        );
+
+        let policy = policy.with_characters(&[CharacterClass::Emoji]);
+        assert_eq!("emoji", policy.describe_characters());
+    }
+}
@@ -0,0 +1,235 @@
+//! Cache of the stats of destinations
+//!
+//! Dashboards ask for the same stats over and over, while the aggregate queries behind them are
+//! among the most expensive of Shurly. The daily hits and top referrers of the public stats
+//! pages, the heatmaps and the hits per source are cached for a short while, a minute by
+//! default; concurrent requests for the same stats are coalesced into a single query.
+//!
+//! The jobs that change the rollups of hits, `rollup-hits`, `prune-hits` and `archive-hits`, forget
+//! all cached stats of the instance running them. Other instances forget their stats when they
+//! expire, stats can be a little behind until then.
+
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::Duration;
+
+use moka::future::Cache;
+use uuid::Uuid;
+
+use crate::database::Database;
+use crate::database::Error;
+use crate::destinations::DailyHits;
+use crate::destinations::Destination;
+use crate::destinations::HourlyHits;
+use crate::destinations::ReferrerHits;
+use crate::destinations::SourceHits;
+use crate::utils::env_var_optional;
+
+/// Default lifetime of the cached stats
+const DEFAULT_TIME_TO_LIVE: Duration = Duration::from_secs(60);
+
+/// Longest lifetime of the cached stats, stale stats for longer than an hour are of no use
+const MAX_TIME_TO_LIVE: Duration = Duration::from_secs(60 * 60);
+
+/// Number of cached stats of every kind
+const MAX_CAPACITY: u64 = 10_000;
+
+/// Cached stats, shared by all requests of this instance
+#[derive(Clone)]
+pub struct StatsCache {
+    /// Daily hits by destination
+    daily_hits: Cache<Uuid, Arc<Vec<DailyHits>>>,
+
+    /// Top referrers by destination and limit
+    top_referrers: Cache<(Uuid, i64), Arc<Vec<ReferrerHits>>>,
+
+    /// Hourly hits by destination, timezone and number of days
+    hourly_hits: Cache<(Uuid, String, Option<i32>), Arc<Vec<HourlyHits>>>,
+
+    /// Hits per source by number of days
+    hits_by_source: Cache<Option<i32>, Arc<Vec<SourceHits>>>,
+
+    /// Without a time to live, every request queries the database
+    is_enabled: bool,
+}
+
+impl Default for StatsCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TIME_TO_LIVE)
+    }
+}
+
+impl StatsCache {
+    /// Create a cache keeping the stats for the given time, nothing is kept without a time
+    pub fn new(time_to_live: Duration) -> Self {
+        Self {
+            daily_hits: build(time_to_live),
+            top_referrers: build(time_to_live),
+            hourly_hits: build(time_to_live),
+            hits_by_source: build(time_to_live),
+            is_enabled: !time_to_live.is_zero(),
+        }
+    }
+
+    /// Setup the cache based on the `STATS_CACHE_TIME_TO_LIVE` environment variable
+    ///
+    /// The time is in seconds, `0` disables the cache
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the time is not a whole number or longer than an hour
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let time_to_live = env_var_optional("STATS_CACHE_TIME_TO_LIVE")
+            .map(|seconds| parse_time_to_live(&seconds))
+            .transpose()?
+            .unwrap_or(DEFAULT_TIME_TO_LIVE);
+
+        Ok(Self::new(time_to_live))
+    }
+
+    /// Daily hits of the destination, see [`find_daily_hits`](Database::find_daily_hits)
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the hits could not be found, errors are not cached
+    pub async fn daily_hits(
+        &self,
+        database: &Database,
+        destination: &Destination,
+    ) -> Result<Arc<Vec<DailyHits>>, Arc<Error>> {
+        self.cached(
+            &self.daily_hits,
+            destination.id,
+            database.find_daily_hits(destination),
+        )
+        .await
+    }
+
+    /// Top referrers of the destination, see
+    /// [`find_top_referrers`](Database::find_top_referrers)
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the referrers could not be found, errors are not cached
+    pub async fn top_referrers(
+        &self,
+        database: &Database,
+        destination: &Destination,
+        limit: i64,
+    ) -> Result<Arc<Vec<ReferrerHits>>, Arc<Error>> {
+        self.cached(
+            &self.top_referrers,
+            (destination.id, limit),
+            database.find_top_referrers(destination, limit),
+        )
+        .await
+    }
+
+    /// Hourly hits of the destination, see [`find_hourly_hits`](Database::find_hourly_hits)
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the hits could not be found, errors are not cached
+    pub async fn hourly_hits(
+        &self,
+        database: &Database,
+        destination: &Destination,
+        timezone: &str,
+        days: Option<i32>,
+    ) -> Result<Arc<Vec<HourlyHits>>, Arc<Error>> {
+        self.cached(
+            &self.hourly_hits,
+            (destination.id, timezone.to_string(), days),
+            database.find_hourly_hits(destination, timezone, days),
+        )
+        .await
+    }
+
+    /// Hits per source, see [`find_hits_by_source`](Database::find_hits_by_source)
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the hits could not be found, errors are not cached
+    pub async fn hits_by_source(
+        &self,
+        database: &Database,
+        days: Option<i32>,
+    ) -> Result<Arc<Vec<SourceHits>>, Arc<Error>> {
+        self.cached(
+            &self.hits_by_source,
+            days,
+            database.find_hits_by_source(days),
+        )
+        .await
+    }
+
+    /// Forget all cached stats, after the rollups of hits changed
+    pub fn invalidate(&self) {
+        self.daily_hits.invalidate_all();
+        self.top_referrers.invalidate_all();
+        self.hourly_hits.invalidate_all();
+        self.hits_by_source.invalidate_all();
+    }
+
+    /// The cached stats of the key, found and cached when missing
+    ///
+    /// Concurrent requests for the same missing stats wait for a single query
+    async fn cached<K, V>(
+        &self,
+        cache: &Cache<K, Arc<V>>,
+        key: K,
+        find: impl Future<Output = Result<V, Error>>,
+    ) -> Result<Arc<V>, Arc<Error>>
+    where
+        K: Hash + Eq + Send + Sync + 'static,
+        V: Send + Sync + 'static,
+    {
+        if !self.is_enabled {
+            return find.await.map(Arc::new).map_err(Arc::new);
+        }
+
+        cache
+            .try_get_with(key, async { find.await.map(Arc::new) })
+            .await
+    }
+}
+
+/// Build a cache of a single kind of stats
+fn build<K, V>(time_to_live: Duration) -> Cache<K, V>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    Cache::builder()
+        .max_capacity(MAX_CAPACITY)
+        .time_to_live(time_to_live)
+        .build()
+}
+
+/// Parse the lifetime of the cached stats in seconds, up to an hour
+fn parse_time_to_live(value: &str) -> anyhow::Result<Duration> {
+    match value.parse::<u64>().map(Duration::from_secs) {
+        Ok(time_to_live) if time_to_live <= MAX_TIME_TO_LIVE => Ok(time_to_live),
+        _ => Err(anyhow::anyhow!(
+            "Invalid STATS_CACHE_TIME_TO_LIVE: {value}, expected a whole number of at most an hour"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_time_to_live() {
+        assert_eq!(Duration::ZERO, parse_time_to_live("0").unwrap());
+        assert_eq!(
+            Duration::from_secs(3600),
+            parse_time_to_live("3600").unwrap()
+        );
+        assert!(parse_time_to_live("3601").is_err());
+        assert!(parse_time_to_live("-1").is_err());
+        assert!(parse_time_to_live("1.5").is_err());
+    }
+}
@@ -0,0 +1,188 @@
+//! Log output and export of traces with OpenTelemetry
+//!
+//! Logs are human readable text by default, or structured JSON for log aggregation platforms
+//! with `LOG_FORMAT=json`. Every request gets an ID, from the `X-Request-Id` header when sent or
+//! generated otherwise, which is part of the fields of the logs of the request.
+//!
+//! The export of traces is disabled by default, traces are exported via OTLP (gRPC) when the
+//! `OTEL_EXPORTER_OTLP_ENDPOINT` environment variable is set, like `http://localhost:4317`. The
+//! other `OTEL_EXPORTER_OTLP_*` environment variables of OpenTelemetry are respected as well.
+//!
+//! Every request gets a span, continuing the trace of the `traceparent` header when sent. Spans
+//! of the root get the slug, the ID of the destination, if the cache was hit and if the root is
+//! degraded as attributes.
+
+use axum::body::Body;
+use axum::http::HeaderMap;
+use axum::http::HeaderName;
+use axum::http::Request;
+use opentelemetry::global;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::runtime;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing::field::Empty;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::utils::env_var_optional;
+
+/// Header with the ID of the request, generated when not sent
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Name of the service in the exported traces
+const SERVICE_NAME: &str = "shurly";
+
+/// Format of the log output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human readable text
+    #[default]
+    Text,
+
+    /// Structured JSON, a single object per line
+    Json,
+}
+
+impl LogFormat {
+    /// Setup the log format based on the `LOG_FORMAT` environment variable, `text` or `json`
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the format is unknown
+    pub fn from_environment() -> anyhow::Result<Self> {
+        Self::parse(env_var_optional("LOG_FORMAT").as_deref())
+    }
+
+    /// Parse the log format, the default without a format
+    fn parse(format: Option<&str>) -> anyhow::Result<Self> {
+        match format {
+            None | Some("text") => Ok(Self::Text),
+            Some("json") => Ok(Self::Json),
+            Some(format) => Err(anyhow::anyhow!(
+                "Invalid LOG_FORMAT: {format}, expected `text` or `json`"
+            )),
+        }
+    }
+}
+
+/// Setup the layer exporting the spans, based on the `OTEL_EXPORTER_OTLP_ENDPOINT` environment
+/// variable
+///
+/// Returns `None` when the export is disabled
+///
+/// # Errors
+///
+/// Will return `Err` when the exporter could not be setup
+pub fn layer<S>() -> anyhow::Result<Option<OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>>>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    let Some(endpoint) = env_var_optional("OTEL_EXPORTER_OTLP_ENDPOINT") else {
+        return Ok(None);
+    };
+
+    // explicitly, the endpoint could be set in the configuration file only
+    let exporter = SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new("service.name", SERVICE_NAME)]))
+        .build();
+
+    let tracer = provider.tracer(SERVICE_NAME);
+
+    global::set_tracer_provider(provider);
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    Ok(Some(tracing_opentelemetry::layer().with_tracer(tracer)))
+}
+
+/// Export the spans that are not exported yet, before shutting down
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+}
+
+/// Create the span of a request, continuing the trace of the request when available
+///
+/// The `slug`, `destination_id`, `cache` and `degraded` are recorded by the root
+pub fn make_span(request: &Request<Body>) -> Span {
+    let request_id = request
+        .headers()
+        .get(X_REQUEST_ID)
+        .and_then(|value| value.to_str().ok());
+
+    let span = tracing::info_span!(
+        "request",
+        request_id,
+        method = %request.method(),
+        uri = %request.uri(),
+        version = ?request.version(),
+        slug = Empty,
+        destination_id = Empty,
+        cache = Empty,
+        degraded = Empty,
+    );
+
+    let context = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(request.headers()))
+    });
+    span.set_parent(context);
+
+    span
+}
+
+/// Extract the trace context from the headers of a request
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(HeaderName::as_str).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_format() {
+        assert_eq!(LogFormat::Text, LogFormat::parse(None).unwrap());
+        assert_eq!(LogFormat::Text, LogFormat::parse(Some("text")).unwrap());
+        assert_eq!(LogFormat::Json, LogFormat::parse(Some("json")).unwrap());
+        assert!(LogFormat::parse(Some("xml")).is_err());
+    }
+
+    #[test]
+    fn test_header_extractor() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01"
+                .parse()
+                .unwrap(),
+        );
+
+        let extractor = HeaderExtractor(&headers);
+        assert_eq!(
+            Some("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01"),
+            extractor.get("traceparent")
+        );
+        assert_eq!(None, extractor.get("tracestate"));
+        assert_eq!(vec!["traceparent"], extractor.keys());
+    }
+}
@@ -0,0 +1,685 @@
+//! Templates for the pages served by the root
+//!
+//! The built-in templates can be overridden with files on disk, configured with the
+//! `NOT_FOUND_TEMPLATE`, `ERROR_TEMPLATE`, `PREVIEW_TEMPLATE`, `META_REFRESH_TEMPLATE`,
+//! `OPEN_GRAPH_TEMPLATE`, `STATS_TEMPLATE` and `ROBOTS_TXT_TEMPLATE` environment variables. A homepage, shown when the empty slug has no
+//! destination, can be added with `HOMEPAGE_TEMPLATE`. Templates on disk are watched and reloaded
+//! when they change, no restart needed.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::RwLock;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use anyhow::Result;
+use axum::response::Html;
+use chrono::Days;
+use chrono::NaiveDate;
+use url::Url;
+
+use crate::destinations::DailyHits;
+use crate::destinations::Destination;
+use crate::destinations::ReferrerHits;
+use crate::utils::encode_slug;
+use crate::utils::env_var_optional;
+use crate::utils::is_web_url;
+
+/// Built-in template for 404 page
+///
+/// Has a placeholder to inject `{suggestions}` of similar slugs
+const NOT_FOUND: &str = include_str!("pages/404.html");
+
+/// Built-in template for error page
+///
+/// Has a placeholder to inject a current error message
+const ERROR: &str = include_str!("pages/500.html");
+
+/// Built-in template for the preview page of a destination
+///
+/// Has placeholders for the `{slug}`, `{url}`, `{type}` and `{created_at}` of the destination
+const PREVIEW: &str = include_str!("pages/preview.html");
+
+/// Built-in template for the meta refresh redirect of a destination
+///
+/// Has a placeholder for the `{url}` of the destination
+const META_REFRESH: &str = include_str!("pages/meta-refresh.html");
+
+/// Built-in template for the Open Graph metadata of a destination, for social media crawlers
+///
+/// Has placeholders for the `{url}`, `{title}`, `{description}` and `{image}` of the destination
+const OPEN_GRAPH: &str = include_str!("pages/open-graph.html");
+
+/// Built-in template for the public stats page of a destination
+///
+/// Has placeholders for the `{slug}`, `{total}` and `{recent}` hits and `{created_at}` of the
+/// destination, and the `{sparkline}` and `{referrers}` HTML
+const STATS: &str = include_str!("pages/stats.html");
+
+/// Number of days of the sparkline and the recent hits of the stats page
+const STATS_DAYS: u64 = 30;
+
+/// Built-in `robots.txt`, disallows crawling of all short links
+const ROBOTS_TXT: &str = include_str!("pages/robots.txt");
+
+/// How often the templates on disk are checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A single template, built-in or loaded from disk
+struct Template {
+    /// Path of the template on disk, if overridden
+    path: Option<PathBuf>,
+
+    /// Current state of the template
+    state: RwLock<TemplateState>,
+}
+
+/// The loaded content of a template
+struct TemplateState {
+    /// The content of the template
+    content: Arc<str>,
+
+    /// Last modification time of the loaded file on disk
+    modified: Option<SystemTime>,
+}
+
+impl Template {
+    /// Create a built-in template
+    fn builtin(content: &'static str) -> Self {
+        Self {
+            path: None,
+            state: RwLock::new(TemplateState {
+                content: content.into(),
+                modified: None,
+            }),
+        }
+    }
+
+    /// Create a template from a file on disk
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the file can not be read
+    fn from_path(path: PathBuf) -> Result<Self> {
+        let state = Self::load(&path)?;
+
+        Ok(Self {
+            path: Some(path),
+            state: RwLock::new(state),
+        })
+    }
+
+    /// Load the template state from disk
+    fn load(path: &PathBuf) -> Result<TemplateState> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read template: {}", path.display()))?;
+
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+
+        Ok(TemplateState {
+            content: content.into(),
+            modified,
+        })
+    }
+
+    /// Current content of the template
+    fn content(&self) -> Arc<str> {
+        self.state
+            .read()
+            .expect("Valid template lock")
+            .content
+            .clone()
+    }
+
+    /// Reload the template when the file on disk has changed
+    ///
+    /// A template that fails to reload keeps its current content
+    fn reload_if_modified(&self) {
+        let Some(ref path) = self.path else {
+            return;
+        };
+
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+
+        if modified == self.state.read().expect("Valid template lock").modified {
+            return;
+        }
+
+        match Self::load(path) {
+            Ok(state) => {
+                tracing::info!("Reloaded template: {}", path.display());
+
+                *self.state.write().expect("Valid template lock") = state;
+            }
+            Err(err) => {
+                tracing::error!("{err:#}, keeping the current template");
+
+                // only report the failure once for this modification
+                self.state.write().expect("Valid template lock").modified = modified;
+            }
+        }
+    }
+}
+
+/// All templates used by the root
+#[derive(Clone)]
+pub struct Templates {
+    /// Template for the 404 page
+    not_found: Arc<Template>,
+
+    /// Template for the error page
+    error: Arc<Template>,
+
+    /// Template for the preview page
+    preview: Arc<Template>,
+
+    /// Template for the meta refresh redirect
+    meta_refresh: Arc<Template>,
+
+    /// Template for the Open Graph page
+    open_graph: Arc<Template>,
+
+    /// Template for the public stats page
+    stats: Arc<Template>,
+
+    /// Template for the `robots.txt`
+    robots_txt: Arc<Template>,
+
+    /// Optional template for the homepage
+    homepage: Option<Arc<Template>>,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self {
+            not_found: Arc::new(Template::builtin(NOT_FOUND)),
+            error: Arc::new(Template::builtin(ERROR)),
+            preview: Arc::new(Template::builtin(PREVIEW)),
+            meta_refresh: Arc::new(Template::builtin(META_REFRESH)),
+            open_graph: Arc::new(Template::builtin(OPEN_GRAPH)),
+            stats: Arc::new(Template::builtin(STATS)),
+            robots_txt: Arc::new(Template::builtin(ROBOTS_TXT)),
+            homepage: None,
+        }
+    }
+}
+
+impl Templates {
+    /// Setup the templates based on the environment
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when a configured template can not be read
+    pub fn from_environment() -> Result<Self> {
+        let mut templates = Self::default();
+
+        if let Some(path) = env_path("NOT_FOUND_TEMPLATE") {
+            templates.not_found = Arc::new(Template::from_path(path)?);
+        }
+
+        if let Some(path) = env_path("ERROR_TEMPLATE") {
+            templates.error = Arc::new(Template::from_path(path)?);
+        }
+
+        if let Some(path) = env_path("PREVIEW_TEMPLATE") {
+            templates.preview = Arc::new(Template::from_path(path)?);
+        }
+
+        if let Some(path) = env_path("META_REFRESH_TEMPLATE") {
+            templates.meta_refresh = Arc::new(Template::from_path(path)?);
+        }
+
+        if let Some(path) = env_path("OPEN_GRAPH_TEMPLATE") {
+            templates.open_graph = Arc::new(Template::from_path(path)?);
+        }
+
+        if let Some(path) = env_path("STATS_TEMPLATE") {
+            templates.stats = Arc::new(Template::from_path(path)?);
+        }
+
+        if let Some(path) = env_path("ROBOTS_TXT_TEMPLATE") {
+            templates.robots_txt = Arc::new(Template::from_path(path)?);
+        }
+
+        if let Some(path) = env_path("HOMEPAGE_TEMPLATE") {
+            templates.homepage = Some(Arc::new(Template::from_path(path)?));
+        }
+
+        Ok(templates)
+    }
+
+    /// Watch the templates on disk for changes in the background
+    ///
+    /// Does nothing when only built-in templates are used
+    pub fn watch(&self) {
+        if self.not_found.path.is_none()
+            && self.error.path.is_none()
+            && self.preview.path.is_none()
+            && self.meta_refresh.path.is_none()
+            && self.open_graph.path.is_none()
+            && self.stats.path.is_none()
+            && self.robots_txt.path.is_none()
+            && self.homepage.is_none()
+        {
+            return;
+        }
+
+        let templates = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+
+            loop {
+                interval.tick().await;
+
+                templates.not_found.reload_if_modified();
+                templates.error.reload_if_modified();
+                templates.preview.reload_if_modified();
+                templates.meta_refresh.reload_if_modified();
+                templates.open_graph.reload_if_modified();
+                templates.stats.reload_if_modified();
+                templates.robots_txt.reload_if_modified();
+
+                if let Some(ref homepage) = templates.homepage {
+                    homepage.reload_if_modified();
+                }
+            }
+        });
+    }
+
+    /// Create a HTML version of not found template, or the 404 template of a domain when given
+    ///
+    /// The `{suggestions}` placeholder is replaced with links to the given slugs, if any
+    pub fn render_not_found(
+        &self,
+        domain_template: Option<&str>,
+        suggestions: &[String],
+    ) -> Html<String> {
+        let suggestions = if suggestions.is_empty() {
+            String::new()
+        } else {
+            let links = suggestions
+                .iter()
+                .map(|slug| {
+                    format!(
+                        r#"<a href="/{}">/{}</a>"#,
+                        escape_html(&encode_slug(slug)),
+                        escape_html(slug)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            format!(r#"<p class="suggestions">Did you mean {links}?</p>"#)
+        };
+
+        let template = domain_template.map_or_else(|| self.not_found.content(), Arc::from);
+
+        Html(template.replace("{suggestions}", &suggestions))
+    }
+
+    /// Create a HTML version of the homepage template, if there is one
+    pub fn render_homepage(&self) -> Option<Html<String>> {
+        self.homepage
+            .as_ref()
+            .map(|homepage| Html(homepage.content().to_string()))
+    }
+
+    /// Create a HTML version of the preview template for a destination
+    ///
+    /// All values of the destination are escaped, other URLs than web URLs are refused
+    pub fn render_preview(&self, destination: &Destination) -> Html<String> {
+        let redirect_type = match (destination.is_permanent, destination.is_meta_refresh) {
+            (true, false) => "Permanent (308)",
+            (false, false) => "Temporary (307)",
+            (true, true) => "Permanent (meta refresh)",
+            (false, true) => "Temporary (meta refresh)",
+        };
+
+        Html(fill(
+            &self.preview.content(),
+            &[
+                ("slug", &destination.slug),
+                ("url", web_url(&destination.url)),
+                ("type", redirect_type),
+                (
+                    "created_at",
+                    &destination
+                        .created_at
+                        .format("%Y-%m-%d %H:%M UTC")
+                        .to_string(),
+                ),
+            ],
+        ))
+    }
+
+    /// Create a HTML version of the meta refresh template for a destination
+    ///
+    /// The URL of the destination is escaped, other URLs than web URLs are refused
+    pub fn render_meta_refresh(&self, destination: &Destination) -> Html<String> {
+        Html(fill(
+            &self.meta_refresh.content(),
+            &[("url", web_url(&destination.url))],
+        ))
+    }
+
+    /// Create a HTML version of the Open Graph template for a destination
+    ///
+    /// Without a title, the URL of the destination is used. All values are escaped, other URLs than
+    /// web URLs are refused
+    pub fn render_open_graph(&self, destination: &Destination) -> Html<String> {
+        Html(fill(
+            &self.open_graph.content(),
+            &[
+                ("url", web_url(&destination.url)),
+                (
+                    "title",
+                    destination.og_title.as_ref().unwrap_or(&destination.url),
+                ),
+                (
+                    "description",
+                    destination.og_description.as_deref().unwrap_or_default(),
+                ),
+                ("image", destination.og_image.as_deref().unwrap_or_default()),
+            ],
+        ))
+    }
+
+    /// Create a HTML version of the public stats template for a destination, up to today
+    ///
+    /// The sparkline shows the hits of the last 30 days. All values are escaped
+    pub fn render_stats(
+        &self,
+        destination: &Destination,
+        daily_hits: &[DailyHits],
+        referrers: &[ReferrerHits],
+        today: NaiveDate,
+    ) -> Html<String> {
+        let since = today - Days::new(STATS_DAYS - 1);
+        let recent_hits = since
+            .iter_days()
+            .take_while(|day| *day <= today)
+            .map(|day| {
+                daily_hits
+                    .iter()
+                    .find(|daily_hits| daily_hits.day == day)
+                    .map_or(0, |daily_hits| daily_hits.hits)
+            })
+            .collect::<Vec<_>>();
+
+        let total = daily_hits
+            .iter()
+            .map(|daily_hits| daily_hits.hits)
+            .sum::<i64>();
+        let recent = recent_hits.iter().sum::<i64>();
+
+        let referrers = if referrers.is_empty() {
+            "None yet".to_string()
+        } else {
+            let items = referrers
+                .iter()
+                .map(|referrer| {
+                    format!(
+                        "<li>{} ({})</li>",
+                        escape_html(&referrer.referrer),
+                        referrer.hits
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            format!(r#"<ul class="referrers">{items}</ul>"#)
+        };
+
+        let html = fill(
+            &self.stats.content(),
+            &[
+                ("slug", &destination.slug),
+                ("total", &total.to_string()),
+                ("recent", &recent.to_string()),
+                (
+                    "created_at",
+                    &destination.created_at.format("%Y-%m-%d").to_string(),
+                ),
+            ],
+        );
+
+        Html(
+            html.replace("{sparkline}", &sparkline(&recent_hits))
+                .replace("{referrers}", &referrers),
+        )
+    }
+
+    /// The current `robots.txt`, as plain text
+    pub fn render_robots_txt(&self) -> String {
+        self.robots_txt.content().to_string()
+    }
+
+    /// Very, very simple template renderer
+    ///
+    /// Only replaces the `{error}` in the template with the given string
+    ///
+    /// Make sure to not use user provided error messages, those are _NOT_ safe
+    pub fn render_error(&self, error: &str) -> Html<String> {
+        Html(self.error.content().replace("{error}", error))
+    }
+}
+
+/// The URL to put in a page, `about:blank` when it is not a web URL
+///
+/// URLs are validated when stored, this keeps `javascript:` URLs out of the pages regardless
+fn web_url(url: &str) -> &str {
+    match Url::parse(url) {
+        Ok(parsed) if is_web_url(&parsed) => url,
+        _ => "about:blank",
+    }
+}
+
+/// Fill the `{placeholders}` of a template with escaped values, in a single pass
+///
+/// Unknown placeholders are left as is
+fn fill(template: &str, values: &[(&str, &str)]) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let value = rest.find('}').and_then(|end| {
+            values
+                .iter()
+                .find(|(name, _)| *name == &rest[1..end])
+                .map(|(_, value)| (end, value))
+        });
+
+        if let Some((end, value)) = value {
+            output.push_str(&escape_html(value));
+            rest = &rest[end + 1..];
+        } else {
+            output.push('{');
+            rest = &rest[1..];
+        }
+    }
+
+    output.push_str(rest);
+
+    output
+}
+
+/// A SVG sparkline of the hits per day, scaled to the day with the most hits
+fn sparkline(hits: &[i64]) -> String {
+    const WIDTH: usize = 300;
+    const HEIGHT: i64 = 60;
+
+    let max = hits.iter().copied().max().unwrap_or_default().max(1);
+    let step = WIDTH / hits.len().saturating_sub(1).max(1);
+
+    let points = hits
+        .iter()
+        .enumerate()
+        .map(|(index, hits)| format!("{},{}", index * step, HEIGHT - hits * HEIGHT / max))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    format!(
+        r#"<svg class="sparkline" viewBox="0 0 {WIDTH} {HEIGHT}" preserveAspectRatio="none" role="img" aria-label="Hits per day"><polyline points="{points}"/></svg>"#
+    )
+}
+
+/// Escape a value to be safely used in HTML, including attributes
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for ch in value.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            ch => escaped.push(ch),
+        }
+    }
+
+    escaped
+}
+
+/// Get a path from an ENV var, only when it is set and not empty
+fn env_path(var_name: &'static str) -> Option<PathBuf> {
+    env_var_optional(var_name).map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_builtin_templates() {
+        let templates = Templates::default();
+
+        assert!(templates
+            .render_not_found(None, &[])
+            .0
+            .contains("Page not found"));
+        assert!(!templates
+            .render_not_found(None, &[])
+            .0
+            .contains("{suggestions}"));
+        assert!(templates.render_error("Oops").0.contains("Oops"));
+        assert!(templates.render_homepage().is_none());
+        assert!(templates.render_robots_txt().contains("Disallow: /"));
+    }
+
+    #[test]
+    fn test_web_url() {
+        assert_eq!(
+            "https://www.example.com/",
+            web_url("https://www.example.com/")
+        );
+        assert_eq!("about:blank", web_url("javascript:alert(document.cookie)"));
+        assert_eq!(
+            "about:blank",
+            web_url("data:text/html,<script>alert(1)</script>")
+        );
+    }
+
+    #[test]
+    fn test_not_found_suggestions() {
+        let templates = Templates::default();
+
+        let html = templates
+            .render_not_found(None, &["hello world".to_string(), "<b>".to_string()])
+            .0;
+
+        assert!(html.contains(r#"<a href="/hello%20world">/hello world</a>"#));
+        assert!(html.contains(r#"<a href="/%3Cb%3E">/&lt;b&gt;</a>"#));
+
+        let html = templates
+            .render_not_found(Some("<h1>Acme</h1>{suggestions}"), &["docs".to_string()])
+            .0;
+
+        assert!(html.starts_with("<h1>Acme</h1>"));
+        assert!(html.contains(r#"<a href="/docs">/docs</a>"#));
+    }
+
+    #[test]
+    fn test_homepage_template() {
+        let path = std::env::temp_dir().join(format!("shurly-{}.html", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "<h1>Welcome</h1>").unwrap();
+
+        let templates = Templates {
+            homepage: Some(Arc::new(Template::from_path(path.clone()).unwrap())),
+            ..Templates::default()
+        };
+
+        assert_eq!(
+            Some("<h1>Welcome</h1>".to_string()),
+            templates.render_homepage().map(|html| html.0)
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_reload_template() {
+        let path = std::env::temp_dir().join(format!("shurly-{}.html", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "<p>{error}</p>").unwrap();
+
+        let template = Template::from_path(path.clone()).unwrap();
+        assert_eq!("<p>{error}</p>", &*template.content());
+
+        // unchanged file is not reloaded
+        template.reload_if_modified();
+        assert_eq!("<p>{error}</p>", &*template.content());
+
+        let mut file = File::create(&path).unwrap();
+        file.write_all(b"<h1>{error}</h1>").unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+
+        template.reload_if_modified();
+        assert_eq!("<h1>{error}</h1>", &*template.content());
+
+        // removed file keeps the last known content
+        std::fs::remove_file(&path).unwrap();
+
+        template.reload_if_modified();
+        assert_eq!("<h1>{error}</h1>", &*template.content());
+    }
+
+    #[test]
+    fn test_fill() {
+        assert_eq!(
+            "<a href=\"https://example.com/?a=1&amp;b=&lt;2&gt;\">{unknown}</a>",
+            fill(
+                "<a href=\"{url}\">{unknown}</a>",
+                &[("url", "https://example.com/?a=1&b=<2>")]
+            )
+        );
+
+        // values are not filled again
+        assert_eq!("{b} b", fill("{a} {b}", &[("a", "{b}"), ("b", "b")]));
+
+        assert_eq!("{ unbalanced", fill("{ unbalanced", &[]));
+    }
+
+    #[test]
+    fn test_sparkline() {
+        let html = sparkline(&[0, 2, 1]);
+
+        assert!(html.contains(r#"points="0,60 150,0 300,30""#));
+        assert!(sparkline(&[0]).contains(r#"points="0,60""#));
+    }
+
+    #[test]
+    fn test_missing_template() {
+        let path = std::env::temp_dir().join(format!("shurly-{}.html", uuid::Uuid::new_v4()));
+
+        assert!(Template::from_path(path).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use axum::http::StatusCode;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_abuse_reports(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "phishing",
+        "https://www.example.com/login",
+    )
+    .await;
+    helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "report/2025",
+        "https://www.example.com/report",
+    )
+    .await;
+
+    // slugs starting with `report/` are served as usual
+    let (status_code, location, _) = helper::root(&mut app, "report/2025").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(Some("https://www.example.com/report".to_string()), location);
+
+    let (status_code, message) =
+        helper::maybe_report_abuse(&mut app, "unknown", r#"{ "reason": "Spam" }"#).await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+    assert_eq!(Some("Destination not found".to_string()), message);
+
+    let (status_code, message) =
+        helper::maybe_report_abuse(&mut app, "phishing", r#"{ "reason": " " }"#).await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Reason can not be empty".to_string()), message);
+
+    let (status_code, _) = helper::maybe_report_abuse(
+        &mut app,
+        "phishing",
+        r#"{ "reason": "Phishing for passwords", "contact": "jane@example.com" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    let (status_code, _) =
+        helper::maybe_report_abuse(&mut app, "report/2025", r#"{ "reason": "Spam" }"#).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    // only admins review the reports
+    helper::maybe_create_user_with_password(
+        &mut app,
+        &access_token,
+        "manager",
+        "manager",
+        Some("verysecret"),
+    )
+    .await;
+    let manager_access_token = helper::login_as(&mut app, "manager", "verysecret").await;
+    let (status_code, _) = helper::list_abuse_reports(&mut app, &manager_access_token).await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    let (status_code, abuse_reports) = helper::list_abuse_reports(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let abuse_reports = abuse_reports.unwrap();
+    assert_eq!(2, abuse_reports.as_array().unwrap().len());
+    assert_eq!("Phishing for passwords", abuse_reports[0]["reason"]);
+    assert_eq!("jane@example.com", abuse_reports[0]["contact"]);
+    assert_eq!("open", abuse_reports[0]["status"]);
+
+    // dismissed, the destination is left alone
+    let spam_id = abuse_reports[1]["id"].as_str().unwrap();
+    let (status_code, abuse_report, _) =
+        helper::maybe_resolve_abuse_report(&mut app, &access_token, spam_id, "dismiss").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!("dismissed", abuse_report.unwrap()["status"]);
+
+    let (status_code, _, message) =
+        helper::maybe_resolve_abuse_report(&mut app, &access_token, spam_id, "disable").await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(
+        Some("Abuse report is already resolved".to_string()),
+        message
+    );
+
+    let (status_code, _, _) = helper::root(&mut app, "report/2025").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+
+    // disabled, resolving all open reports of the destination
+    helper::maybe_report_abuse(&mut app, "phishing", r#"{ "reason": "Fake login" }"#).await;
+
+    let phishing_id = abuse_reports[0]["id"].as_str().unwrap();
+    let (status_code, abuse_report, _) =
+        helper::maybe_resolve_abuse_report(&mut app, &access_token, phishing_id, "disable").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!("disabled", abuse_report.unwrap()["status"]);
+
+    let (_, abuse_reports) = helper::list_abuse_reports(&mut app, &access_token).await;
+    assert_eq!(0, abuse_reports.unwrap().as_array().unwrap().len());
+
+    let (status_code, _, _) = helper::root(&mut app, "phishing").await;
+    assert_eq!(StatusCode::GONE, status_code);
+
+    let (_, verification) = helper::verify_audit_trail(&mut app, &access_token).await;
+    assert!(verification.unwrap().is_valid);
+}
+
+#[sqlx::test]
+async fn test_abuse_reports_limited(pool: sqlx::PgPool) {
+    // without the rate limit of the root
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "phishing",
+        "https://www.example.com/login",
+    )
+    .await;
+
+    let reporter = [("x-real-ip", "10.0.0.1")];
+
+    for _ in 0..5 {
+        let (status_code, _) = helper::maybe_report_abuse_with_headers(
+            &mut app,
+            "phishing",
+            r#"{ "reason": "Spam" }"#,
+            &reporter,
+        )
+        .await;
+        assert_eq!(StatusCode::NO_CONTENT, status_code);
+    }
+
+    let (status_code, message) = helper::maybe_report_abuse_with_headers(
+        &mut app,
+        "phishing",
+        r#"{ "reason": "Spam" }"#,
+        &reporter,
+    )
+    .await;
+    assert_eq!(StatusCode::TOO_MANY_REQUESTS, status_code);
+    assert_eq!(Some("Too many requests".to_string()), message);
+
+    // other IP addresses have their own limit
+    let (status_code, _) = helper::maybe_report_abuse_with_headers(
+        &mut app,
+        "phishing",
+        r#"{ "reason": "Spam" }"#,
+        &[("x-real-ip", "10.0.0.2")],
+    )
+    .await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    let (_, abuse_reports) = helper::list_abuse_reports(&mut app, &access_token).await;
+    assert_eq!(6, abuse_reports.unwrap().as_array().unwrap().len());
+}
@@ -0,0 +1,69 @@
+use axum::http::StatusCode;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_accounting(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    helper::maybe_create_user_with_password(
+        &mut app,
+        &access_token,
+        "manager",
+        "manager",
+        Some("verysecret"),
+    )
+    .await;
+    let manager_access_token = helper::login_as(&mut app, "manager", "verysecret").await;
+
+    helper::maybe_create_destination(&mut app, &access_token, "admin", "https://www.example.com/")
+        .await;
+
+    let (_, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &manager_access_token,
+        "launch",
+        "https://www.example.com/launch",
+    )
+    .await;
+    helper::maybe_create_destination(
+        &mut app,
+        &manager_access_token,
+        "giveaway",
+        "https://www.example.com/giveaway",
+    )
+    .await;
+
+    for _ in 0..3 {
+        helper::root(&mut app, "launch").await;
+    }
+    helper::root(&mut app, "admin").await;
+
+    // deleted destinations stay counted
+    helper::myabe_delete_destination(&mut app, &manager_access_token, &destination.unwrap().id)
+        .await;
+
+    let (status_code, usage_counters) =
+        helper::list_usage_counters(&mut app, &manager_access_token, Some("me")).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let usage_counters = usage_counters.unwrap();
+    assert_eq!(1, usage_counters.as_array().unwrap().len());
+    assert_eq!("manager", usage_counters[0]["username"]);
+    assert_eq!(2, usage_counters[0]["destinationsCreated"]);
+    assert_eq!(3, usage_counters[0]["redirects"]);
+
+    // only admins see the counters of everybody
+    let (status_code, _) = helper::list_usage_counters(&mut app, &manager_access_token, None).await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    let (status_code, usage_counters) =
+        helper::list_usage_counters(&mut app, &access_token, None).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let usage_counters = usage_counters.unwrap();
+    assert_eq!(2, usage_counters.as_array().unwrap().len());
+    assert_eq!("admin", usage_counters[0]["username"]);
+    assert_eq!(1, usage_counters[0]["destinationsCreated"]);
+    assert_eq!(1, usage_counters[0]["redirects"]);
+}
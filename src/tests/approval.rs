@@ -0,0 +1,107 @@
+use axum::http::StatusCode;
+
+use crate::approval::Approval;
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_approval(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app_with_root_settings(pool, |settings| {
+        settings.approval = Approval::Managers;
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    helper::maybe_create_user_with_password(
+        &mut app,
+        &access_token,
+        "manager",
+        "manager",
+        Some("verysecret"),
+    )
+    .await;
+    let manager_access_token = helper::login_as(&mut app, "manager", "verysecret").await;
+
+    // destinations of managers await approval
+    let (status_code, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &manager_access_token,
+        "launch",
+        "https://www.example.com/launch",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let launch_id = destination.unwrap().id;
+
+    let (status_code, _, _) = helper::root(&mut app, "launch").await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+
+    let (status_code, response) = helper::graphql(
+        &mut app,
+        &access_token,
+        &format!(r#"{{ destination(id: "{launch_id}") {{ approvalStatus }} }}"#),
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(
+        "PENDING",
+        response.unwrap()["data"]["destination"]["approvalStatus"]
+    );
+
+    // only admins review destinations
+    let (status_code, _, _) =
+        helper::maybe_review_destination(&mut app, &manager_access_token, &launch_id, "approve")
+            .await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    let (status_code, destination, _) =
+        helper::maybe_review_destination(&mut app, &access_token, &launch_id, "approve").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!("approved", destination.unwrap()["approvalStatus"]);
+
+    let (status_code, location, _) = helper::root(&mut app, "launch").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(Some("https://www.example.com/launch".to_string()), location);
+
+    let (status_code, _, message) =
+        helper::maybe_review_destination(&mut app, &access_token, &launch_id, "reject").await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Destination is not pending".to_string()), message);
+
+    // rejected destinations never resolve
+    let (_, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &manager_access_token,
+        "giveaway",
+        "https://www.example.com/giveaway",
+    )
+    .await;
+    let giveaway_id = destination.unwrap().id;
+
+    let (status_code, destination, _) =
+        helper::maybe_review_destination(&mut app, &access_token, &giveaway_id, "reject").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!("rejected", destination.unwrap()["approvalStatus"]);
+
+    let (status_code, _, _) =
+        helper::maybe_review_destination(&mut app, &access_token, &giveaway_id, "approve").await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+
+    let (status_code, _, _) = helper::root(&mut app, "giveaway").await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+
+    // destinations of admins resolve right away
+    helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "admin",
+        "https://www.example.com/admin",
+    )
+    .await;
+
+    let (status_code, _, _) = helper::root(&mut app, "admin").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+
+    let (_, verification) = helper::verify_audit_trail(&mut app, &access_token).await;
+    assert!(verification.unwrap().is_valid);
+}
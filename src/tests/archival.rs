@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use axum::http::StatusCode;
+
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::jobs::Job;
+use crate::jobs::Jobs;
+use crate::jobs::Outcome;
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_archival(pool: sqlx::PgPool) {
+    let jobs = Jobs::new(None, Some(2), None);
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |settings| {
+        settings.jobs = jobs.clone();
+    })
+    .await;
+
+    let (status_code, _, _) = helper::maybe_trigger_archival(&mut app, "", Some(2)).await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    let access_token = helper::login(&mut app).await;
+
+    helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+
+    for _ in 0..3 {
+        helper::root(&mut app, "some-slug").await;
+    }
+
+    // two hits of a couple of months ago
+    sqlx::query(
+        r"
+        UPDATE hits
+        SET created_at = CURRENT_TIMESTAMP - interval '120 days'
+        WHERE id IN (SELECT id FROM hits LIMIT 2)
+        ",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let (status_code, _, message) =
+        helper::maybe_trigger_archival(&mut app, &access_token, Some(0)).await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(
+        Some("Number of months must be positive".to_string()),
+        message
+    );
+
+    // the months of `HIT_ARCHIVE_MONTHS` by default
+    let (status_code, run, _) =
+        helper::maybe_trigger_archival(&mut app, &access_token, None).await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let mut run = run.unwrap();
+    assert_eq!(2, run["months"]);
+    let id = run["id"].as_str().unwrap().to_string();
+
+    // archived in the background
+    for _ in 0..50 {
+        if run["status"] != "running" {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        run = helper::archival_runs(&mut app, &access_token, Some(&id))
+            .await
+            .1
+            .unwrap();
+    }
+    assert_eq!("succeeded", run["status"]);
+    assert_eq!(2, run["hits"]);
+    assert_eq!(1, run["archives"]);
+    assert!(run["userId"].is_string());
+
+    // the archived hits are gone, their counts are kept
+    assert_eq!(1, helper::count_hits(&pool).await);
+    let archived = sqlx::query_as::<_, (i64, i64)>(
+        "SELECT hits, jsonb_array_length(data)::BIGINT FROM hit_archives",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!((2, 2), archived);
+
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool.clone())).await;
+    let destination = database
+        .find_single_destination_by_slug(None, "some-slug")
+        .await
+        .unwrap()
+        .unwrap();
+    let daily_hits = database.find_daily_hits(&destination).await.unwrap();
+    assert_eq!(3, daily_hits.iter().map(|day| day.hits).sum::<i64>());
+
+    // the job archives as well, nothing is left to archive
+    let outcomes = jobs.run_all(&database).await;
+    assert_eq!(
+        Some(&(
+            Job::ArchiveHits { months: 2 },
+            Outcome::Succeeded("Archived 0 hit(s) into 0 archive(s)".to_string())
+        )),
+        outcomes.last()
+    );
+
+    let (status_code, runs) = helper::archival_runs(&mut app, &access_token, None).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let runs = runs.unwrap();
+    assert_eq!(2, runs.as_array().unwrap().len());
+    assert!(runs[0]["userId"].is_null());
+    assert_eq!(id, runs[1]["id"]);
+
+    let (status_code, _) = helper::archival_runs(
+        &mut app,
+        &access_token,
+        Some("00000000-0000-0000-0000-000000000000"),
+    )
+    .await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+}
+
+#[sqlx::test]
+async fn test_archival_without_months(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    // without `HIT_ARCHIVE_MONTHS` the months are needed
+    let (status_code, _, message) =
+        helper::maybe_trigger_archival(&mut app, &access_token, None).await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Missing number of months".to_string()), message);
+
+    let (status_code, run, _) =
+        helper::maybe_trigger_archival(&mut app, &access_token, Some(6)).await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    assert_eq!(6, run.unwrap()["months"]);
+}
@@ -0,0 +1,56 @@
+use axum::http::StatusCode;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_audit_trail_verify(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    // setup
+    let url = "https://www.example.com/";
+
+    // empty trail is valid
+    let (status_code, verification) = helper::verify_audit_trail(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let verification = verification.unwrap();
+    assert!(verification.is_valid);
+    assert_eq!(0, verification.verified_entries);
+
+    // create some entries
+    let (status_code, destination, _) =
+        helper::maybe_create_destination(&mut app, &access_token, "first", url).await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let destination = destination.unwrap();
+
+    let (status_code, _, _) =
+        helper::maybe_create_destination(&mut app, &access_token, "second", url).await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, _) =
+        helper::myabe_delete_destination(&mut app, &access_token, &destination.id).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    // all entries are chained
+    let (status_code, verification) = helper::verify_audit_trail(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let verification = verification.unwrap();
+    assert!(verification.is_valid);
+    assert_eq!(3, verification.verified_entries);
+
+    // tamper with the trail after the fact
+    let tampered_entry_id = sqlx::query_scalar::<_, uuid::Uuid>(
+        "UPDATE audit_trail SET type = 'update-destination' WHERE sequence = (SELECT MAX(sequence) FROM audit_trail) RETURNING id",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+
+    let (status_code, verification) = helper::verify_audit_trail(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let verification = verification.unwrap();
+    assert!(!verification.is_valid);
+    assert_eq!(2, verification.verified_entries);
+    assert_eq!(Some(tampered_entry_id), verification.first_invalid_entry_id);
+}
@@ -0,0 +1,60 @@
+use axum::http::StatusCode;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_auth_metrics(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, metrics) = helper::auth_metrics(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let metrics = metrics.unwrap();
+    assert_eq!(1, metrics["tokensIssued"]);
+    assert_eq!(0, metrics["failedLogins"]);
+    assert_eq!(0, metrics["deniedActions"]);
+
+    let status_code = helper::maybe_login_as(&mut app, "admin", "wrong-password").await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    let status_code = helper::maybe_login_as(&mut app, "unknown", "verysecret").await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+
+    let (status_code, _) = helper::auth_metrics(&mut app, "").await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+    let (status_code, _) = helper::auth_metrics(&mut app, "Bearer not-a-token").await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    helper::maybe_create_user_with_password(
+        &mut app,
+        &access_token,
+        "manager",
+        "manager",
+        Some("verysecret"),
+    )
+    .await;
+    let manager_access_token = helper::login_as(&mut app, "manager", "verysecret").await;
+
+    // managers are not allowed to see the metrics
+    let (status_code, _) = helper::auth_metrics(&mut app, &manager_access_token).await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    // the session of the token ends with the change of the password
+    let (status_code, _, _) =
+        helper::maybe_change_password(&mut app, &manager_access_token, "verysecret", "new-secret")
+            .await;
+    assert_eq!(StatusCode::OK, status_code);
+    let (status_code, _) = helper::auth_metrics(&mut app, &manager_access_token).await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    let (status_code, metrics) = helper::auth_metrics(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let metrics = metrics.unwrap();
+    // logins of the admin and the manager, and the change of the password
+    assert_eq!(3, metrics["tokensIssued"]);
+    assert_eq!(2, metrics["failedLogins"]);
+    assert_eq!(1, metrics["missingTokens"]);
+    assert_eq!(1, metrics["invalidTokens"]);
+    assert_eq!(1, metrics["expiredSessions"]);
+    assert_eq!(1, metrics["deniedActions"]);
+}
@@ -0,0 +1,160 @@
+use std::path::Path;
+
+use axum::http::StatusCode;
+use clap::Parser;
+
+use crate::backup::Archive;
+use crate::cli;
+use crate::cli::Cli;
+use crate::cli::Command;
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::tests::helper;
+use crate::RootSettings;
+
+async fn run(database: &Database, args: &[&str]) -> anyhow::Result<()> {
+    let cli = Cli::try_parse_from(["shurly"].iter().chain(args)).unwrap();
+
+    let Some(Command::Manage(command)) = cli.command else {
+        panic!("Not a command managing the instance");
+    };
+
+    let root_settings = RootSettings::from_environment().unwrap();
+
+    cli::run(database, &root_settings, command).await
+}
+
+/// Forget everything but the initial user, like a fresh instance
+async fn wipe(pool: &sqlx::PgPool) {
+    for query in [
+        "DELETE FROM audit_trail",
+        "DELETE FROM hit_rollups",
+        "DELETE FROM hits",
+        "DELETE FROM notes",
+        "DELETE FROM destinations",
+        "DELETE FROM domains",
+        "DELETE FROM users WHERE username <> 'admin'",
+    ] {
+        sqlx::query(query).execute(pool).await.unwrap();
+    }
+}
+
+#[sqlx::test]
+async fn test_backup(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let (status_code, _) = helper::export_backup(&mut app, "").await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    let access_token = helper::login(&mut app).await;
+
+    helper::maybe_create_user(&mut app, &access_token, "jane", "manager").await;
+    helper::maybe_create_domain(&mut app, &access_token, r#"{ "hostname": "go.acme.com" }"#).await;
+
+    let (_, destination, _) =
+        helper::maybe_create_destination(&mut app, &access_token, "kept", "https://www.acme.com/")
+            .await;
+    let destination = destination.unwrap();
+    helper::maybe_create_note(&mut app, &access_token, &destination.id, "Some note").await;
+
+    let (_, deleted, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "deleted",
+        "https://www.acme.com/",
+    )
+    .await;
+    helper::myabe_delete_destination(&mut app, &access_token, &deleted.unwrap().id).await;
+
+    let (status_code, archive) = helper::export_backup(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let mut archive = archive.unwrap();
+    assert_eq!(1, archive["version"]);
+    assert_eq!(2, archive["users"].as_array().unwrap().len());
+    assert_eq!(1, archive["domains"].as_array().unwrap().len());
+    assert_eq!(2, archive["destinations"].as_array().unwrap().len());
+    assert_eq!(1, archive["notes"].as_array().unwrap().len());
+    assert!(archive["users"][0]["hashedPassword"].is_string());
+
+    let (status_code, _, message) =
+        helper::maybe_import_backup(&mut app, &access_token, &archive).await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Database is not empty".to_string()), message);
+
+    wipe(&pool).await;
+
+    let (status_code, _, _) = helper::root(&mut app, "kept").await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+
+    let (status_code, summary, _) =
+        helper::maybe_import_backup(&mut app, &access_token, &archive).await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let summary = summary.unwrap();
+    // the initial user already exists
+    assert_eq!(1, summary["users"]);
+    assert_eq!(1, summary["domains"]);
+    assert_eq!(2, summary["destinations"]);
+    assert_eq!(1, summary["notes"]);
+
+    let (status_code, location, _) = helper::root(&mut app, "kept").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(Some("https://www.acme.com/".to_string()), location);
+
+    let (status_code, _, _) = helper::root(&mut app, "deleted").await;
+    assert_eq!(StatusCode::GONE, status_code);
+
+    let (_, notes) = helper::list_notes(&mut app, &access_token, &destination.id).await;
+    assert_eq!("Some note", notes.unwrap()[0].content);
+
+    let (_, users) = helper::list_users(&mut app, &access_token).await;
+    assert!(users.unwrap().iter().any(|user| user.username == "jane"));
+
+    // the import is on the audit trail, just like changes via the API
+    let (_, verification) = helper::verify_audit_trail(&mut app, &access_token).await;
+    let verification = verification.unwrap();
+    assert!(verification.is_valid);
+    assert_eq!(5, verification.verified_entries);
+
+    archive["version"] = 2.into();
+    let (status_code, _, message) =
+        helper::maybe_import_backup(&mut app, &access_token, &archive).await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(
+        Some("Invalid archive version: 2, expected 1".to_string()),
+        message
+    );
+}
+
+#[sqlx::test]
+async fn test_backup_cli(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool.clone())).await;
+
+    let access_token = helper::login(&mut app).await;
+    helper::maybe_create_destination(&mut app, &access_token, "kept", "https://www.acme.com/")
+        .await;
+
+    let path = std::env::temp_dir().join(format!("shurly-{}.json", uuid::Uuid::new_v4()));
+    let path = path.to_str().unwrap();
+
+    run(&database, &["export", path]).await.unwrap();
+
+    let archive = Archive::from_file(Path::new(path)).unwrap();
+    assert_eq!(1, archive.destinations.len());
+
+    let err = run(&database, &["import", path, "--as", "admin"])
+        .await
+        .unwrap_err();
+    assert_eq!("Database is not empty", err.to_string());
+
+    wipe(&pool).await;
+
+    run(&database, &["import", path, "--as", "admin"])
+        .await
+        .unwrap();
+
+    std::fs::remove_file(path).unwrap();
+
+    let (status_code, _, _) = helper::root(&mut app, "kept").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+}
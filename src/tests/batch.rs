@@ -0,0 +1,90 @@
+use axum::http::StatusCode;
+use serde_json::json;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_batch(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "existing",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, results, _) = helper::maybe_run_batch(
+        &mut app,
+        &access_token,
+        json!({ "operations": [
+            { "type": "createDestination", "slug": "launch", "url": "https://www.example.com/launch" },
+            { "type": "createNote", "slug": "launch", "content": "Launch campaign" },
+            { "type": "createNote", "slug": "existing", "content": "Also used for the launch" },
+        ] }),
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let results = results.unwrap();
+    assert_eq!("createDestination", results[0]["type"]);
+    assert_eq!("launch", results[0]["destination"]["slug"]);
+    assert_eq!("createNote", results[1]["type"]);
+    assert_eq!("Launch campaign", results[1]["note"]["content"]);
+    assert_eq!("Also used for the launch", results[2]["note"]["content"]);
+
+    let destination_id = results[0]["destination"]["id"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let (_, notes) = helper::list_notes(&mut app, &access_token, &destination_id).await;
+    assert_eq!(1, notes.unwrap().len());
+
+    let (status_code, location, _) = helper::root(&mut app, "launch").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(Some("https://www.example.com/launch".to_string()), location);
+}
+
+#[sqlx::test]
+async fn test_batch_is_all_or_nothing(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, error) = helper::maybe_run_batch(
+        &mut app,
+        &access_token,
+        json!({ "operations": [
+            { "type": "createDestination", "slug": "launch", "url": "https://www.example.com/" },
+            { "type": "createNote", "slug": "unknown", "content": "Launch campaign" },
+        ] }),
+    )
+    .await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+    assert_eq!("Operation 1: Destination not found", error.unwrap());
+
+    // the destination of the first operation is rolled back
+    let (status_code, _, _) = helper::root(&mut app, "launch").await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+
+    let (status_code, _, error) = helper::maybe_run_batch(
+        &mut app,
+        &access_token,
+        json!({ "operations": [
+            { "type": "createDestination", "slug": "launch", "url": "https://www.example.com/" },
+            { "type": "createDestination", "slug": "launch", "url": "https://www.example.com/" },
+        ] }),
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("Operation 1: Slug already exists", error.unwrap());
+
+    let (status_code, _, error) =
+        helper::maybe_run_batch(&mut app, &access_token, json!({ "operations": [] })).await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("Batch without operations", error.unwrap());
+}
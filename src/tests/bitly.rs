@@ -0,0 +1,171 @@
+use axum::extract::RawQuery;
+use axum::http::HeaderMap;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::Json;
+use axum::Router;
+use chrono::NaiveDate;
+use serde_json::json;
+use serde_json::Value;
+
+use crate::bitly;
+use crate::bitly::BitlyApi;
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::import;
+use crate::tests::helper;
+
+/// Serve an API like Bitly with two pages of links, returns its base URL
+fn serve_api() -> String {
+    async fn user(headers: HeaderMap) -> Result<Json<Value>, StatusCode> {
+        if headers["authorization"] != "Bearer tokentokentoken" {
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        Ok(Json(json!({ "default_group_guid": "some-group" })))
+    }
+
+    async fn bitlinks(
+        headers: HeaderMap,
+        RawQuery(query): RawQuery,
+    ) -> Result<Json<Value>, StatusCode> {
+        if headers["authorization"] != "Bearer tokentokentoken" {
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        let host = headers["host"].to_str().unwrap();
+        let is_first_page = !query.unwrap_or_default().contains("page=2");
+
+        Ok(Json(if is_first_page {
+            json!({
+                "links": [{
+                    "link": "https://bit.ly/3xyz",
+                    "long_url": "https://www.example.com/launch",
+                    "created_at": "2020-01-02T03:04:05+0000",
+                    "custom_bitlinks": ["https://bit.ly/launch"],
+                }],
+                "pagination": {
+                    "next": format!("http://{host}/v4/groups/some-group/bitlinks?size=100&page=2"),
+                },
+            })
+        } else {
+            json!({
+                "links": [{
+                    "link": "https://bit.ly/taken",
+                    "long_url": "https://www.example.com/taken",
+                    "created_at": "2020-01-02T03:04:05+0000",
+                }],
+                "pagination": { "next": "" },
+            })
+        }))
+    }
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let app = Router::new()
+        .route("/v4/user", get(user))
+        .route("/v4/groups/some-group/bitlinks", get(bitlinks));
+
+    tokio::spawn(axum_server::from_tcp(listener).serve(app.into_make_service()));
+
+    format!("http://{address}/v4")
+}
+
+#[sqlx::test]
+async fn test_import_from_api(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool)).await;
+    let admin = database
+        .find_single_user_by_username("admin")
+        .await
+        .unwrap()
+        .unwrap();
+
+    let access_token = helper::login(&mut app).await;
+    helper::maybe_create_destination(&mut app, &access_token, "taken", "https://www.example.com/")
+        .await;
+
+    let base_url = serve_api();
+
+    let err = BitlyApi::with_base_url(&base_url, "wrong")
+        .links(None)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().starts_with("Bitly responded with 403"));
+
+    let links = BitlyApi::with_base_url(&base_url, "tokentokentoken")
+        .links(None)
+        .await
+        .unwrap();
+    assert_eq!(3, links.len());
+
+    let report = import::import(&database, &links, &admin, None)
+        .await
+        .unwrap();
+
+    let slugs = report
+        .destinations
+        .iter()
+        .map(|destination| destination.slug.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["3xyz", "launch"], slugs);
+
+    let created_at = NaiveDate::from_ymd_opt(2020, 1, 2)
+        .unwrap()
+        .and_hms_opt(3, 4, 5)
+        .unwrap();
+    assert_eq!(created_at, report.destinations[0].created_at);
+    assert_eq!("https://www.example.com/launch", report.destinations[1].url);
+
+    assert_eq!(1, report.conflicts.len());
+    assert_eq!("https://bit.ly/taken", report.conflicts[0].short_link);
+    assert_eq!("Slug already exists", report.conflicts[0].reason);
+
+    let (status_code, location, _) = helper::root(&mut app, "launch").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!("https://www.example.com/launch", location.unwrap());
+}
+
+#[sqlx::test]
+async fn test_import_from_csv(pool: sqlx::PgPool) {
+    let _app = helper::setup_test_app(pool.clone()).await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool)).await;
+    let admin = database
+        .find_single_user_by_username("admin")
+        .await
+        .unwrap()
+        .unwrap();
+
+    let csv = "Title,Bitlink,Long URL,Created At\n\
+        Launch,https://bit.ly/launch,https://www.example.com/launch,2020-01-02\n\
+        Again,https://bit.ly/launch,https://www.example.com/again,2020-01-03\n\
+        Nothing,https://bit.ly/,https://www.example.com/,2020-01-04\n\
+        Someday,https://bit.ly/someday,https://www.example.com/someday,someday\n";
+
+    let links = bitly::from_csv(csv.as_bytes()).unwrap();
+    let report = import::import(&database, &links, &admin, Some("go.acme.com"))
+        .await
+        .unwrap();
+
+    assert_eq!(1, report.destinations.len());
+    assert_eq!(
+        Some("go.acme.com"),
+        report.destinations[0].domain.as_deref()
+    );
+
+    let reasons = report
+        .conflicts
+        .iter()
+        .map(|conflict| conflict.reason.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            "Slug already exists",
+            "Link without a slug",
+            "Invalid creation date: someday"
+        ],
+        reasons
+    );
+}
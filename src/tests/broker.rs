@@ -0,0 +1,54 @@
+use axum::http::Method;
+use axum::http::StatusCode;
+use serde_json::Value;
+
+use crate::broker::Broker;
+use crate::broker::Stream;
+use crate::broker::Target;
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_broker(pool: sqlx::PgPool) {
+    // never started, the events stay in the queue
+    let broker = Broker::new(Some(Target::Nats(vec!["localhost:4222".into()])), "shurly");
+
+    let mut app = helper::setup_test_app_with_root_settings(pool, |settings| {
+        settings.broker = broker.clone();
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, _, _) = helper::root_with_headers(
+        &mut app,
+        Method::GET,
+        "some-slug",
+        &[("User-Agent", "Some Browser")],
+    )
+    .await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+
+    let messages = broker.take_queued().await;
+    assert_eq!(2, messages.len());
+
+    assert_eq!(Stream::Audit, messages[0].stream);
+    assert_eq!("create-destination", messages[0].event);
+    let event = serde_json::from_str::<Value>(&messages[0].payload).unwrap();
+    assert_eq!("some-slug", event["destination"]["slug"]);
+
+    assert_eq!(Stream::Hits, messages[1].stream);
+    assert_eq!("hit", messages[1].event);
+    let hit = serde_json::from_str::<Value>(&messages[1].payload).unwrap();
+    assert_eq!("some-slug", hit["slug"]);
+    assert_eq!("Some Browser", hit["userAgent"]);
+    assert_eq!(event["destination"]["id"], hit["destinationId"]);
+}
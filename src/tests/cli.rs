@@ -0,0 +1,209 @@
+use axum::http::StatusCode;
+use clap::Parser;
+
+use crate::approval::Approval;
+use crate::cli;
+use crate::cli::Cli;
+use crate::cli::Command;
+use crate::confusables::Confusables;
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::quotas::Quota;
+use crate::quotas::Quotas;
+use crate::tests::helper;
+use crate::users::Role;
+use crate::RootSettings;
+
+async fn run(database: &Database, args: &[&str]) -> anyhow::Result<()> {
+    let root_settings = RootSettings::from_environment().unwrap();
+
+    run_with_root_settings(database, &root_settings, args).await
+}
+
+async fn run_with_root_settings(
+    database: &Database,
+    root_settings: &RootSettings,
+    args: &[&str],
+) -> anyhow::Result<()> {
+    let cli = Cli::try_parse_from(["shurly"].iter().chain(args)).unwrap();
+
+    let Some(Command::Manage(command)) = cli.command else {
+        panic!("Not a command managing the instance");
+    };
+
+    cli::run(database, root_settings, command).await
+}
+
+#[sqlx::test]
+async fn test_cli(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool)).await;
+
+    run(
+        &database,
+        &[
+            "user",
+            "create",
+            "jane",
+            "--password",
+            "secret",
+            "--as",
+            "admin",
+        ],
+    )
+    .await
+    .unwrap();
+
+    let err = run(&database, &["user", "create", "jane", "--as", "admin"])
+        .await
+        .unwrap_err();
+    assert_eq!("User already exists", err.to_string());
+
+    // managers can not create users
+    let err = run(&database, &["user", "create", "john", "--as", "jane"])
+        .await
+        .unwrap_err();
+    assert_eq!("Not allowed to acces", err.to_string());
+
+    let err = run(&database, &["user", "create", "john", "--as", "nobody"])
+        .await
+        .unwrap_err();
+    assert_eq!("User not found: nobody", err.to_string());
+
+    run(
+        &database,
+        &[
+            "destination",
+            "create",
+            "from-the-shell",
+            "https://www.example.com/",
+            "--as",
+            "jane",
+        ],
+    )
+    .await
+    .unwrap();
+
+    let (status_code, location, _) = helper::root(&mut app, "from-the-shell").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(Some("https://www.example.com/".to_string()), location);
+
+    let err = run(
+        &database,
+        &[
+            "destination",
+            "create",
+            "from-the-shell",
+            "https://www.example.com/",
+            "--as",
+            "jane",
+        ],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!("Slug already exists", err.to_string());
+
+    let err = run(
+        &database,
+        &[
+            "destination",
+            "create",
+            "robots.txt",
+            "https://www.example.com/",
+            "--as",
+            "jane",
+        ],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!("Slug is reserved", err.to_string());
+
+    run(&database, &["destination", "list"]).await.unwrap();
+
+    let access_token = helper::login(&mut app).await;
+
+    let (_, users) = helper::list_users(&mut app, &access_token).await;
+    assert!(users.unwrap().iter().any(|user| user.username == "jane"));
+
+    // changes are on the audit trail, just like changes via the API
+    let (_, verification) = helper::verify_audit_trail(&mut app, &access_token).await;
+    let verification = verification.unwrap();
+    assert!(verification.is_valid);
+    assert_eq!(2, verification.verified_entries);
+}
+
+#[sqlx::test]
+async fn test_cli_destination_checks(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool)).await;
+
+    let mut root_settings = RootSettings::from_environment().unwrap();
+    root_settings.approval = Approval::Managers;
+    root_settings.confusable_slugs = Confusables::Reject;
+    root_settings.quotas = Quotas::default().with_role(
+        Role::Manager,
+        Quota {
+            max_destinations: Some(1),
+            max_hits_per_month: None,
+        },
+    );
+
+    run(&database, &["user", "create", "jane", "--as", "admin"])
+        .await
+        .unwrap();
+
+    // a Cyrillic `а` in a Latin word
+    let err = run_with_root_settings(
+        &database,
+        &root_settings,
+        &[
+            "destination",
+            "create",
+            "p\u{0430}ypal",
+            "https://www.example.com/",
+            "--as",
+            "jane",
+        ],
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().starts_with("Slug is confusable"));
+
+    // destinations of managers await approval, like via the API
+    run_with_root_settings(
+        &database,
+        &root_settings,
+        &[
+            "destination",
+            "create",
+            "launch",
+            "https://www.example.com/launch",
+            "--as",
+            "jane",
+        ],
+    )
+    .await
+    .unwrap();
+
+    let (status_code, _, _) = helper::root(&mut app, "launch").await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+
+    let err = run_with_root_settings(
+        &database,
+        &root_settings,
+        &[
+            "destination",
+            "create",
+            "second",
+            "https://www.example.com/second",
+            "--as",
+            "jane",
+        ],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(
+        "Quota of destinations reached: Up to 1 destinations",
+        err.to_string()
+    );
+}
@@ -0,0 +1,131 @@
+use axum::http::Method;
+use axum::http::StatusCode;
+
+use crate::client_ip::ClientIpSettings;
+use crate::tests::helper;
+
+async fn last_hit_ip_address(pool: &sqlx::PgPool) -> Option<String> {
+    sqlx::query_scalar::<_, Option<String>>(
+        "SELECT host(ip_address) FROM hits ORDER BY created_at DESC LIMIT 1",
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+#[sqlx::test]
+async fn test_client_ip(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    // by default the leftmost address, anybody can send it
+    let (status_code, _, _) = helper::root_with_headers(
+        &mut app,
+        Method::GET,
+        "some-slug",
+        &[("x-forwarded-for", "1.1.1.1, 2.2.2.2, 10.0.0.1")],
+    )
+    .await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(
+        Some("1.1.1.1".to_string()),
+        last_hit_ip_address(&pool).await
+    );
+}
+
+#[sqlx::test]
+async fn test_client_ip_trusted_proxies(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |settings| {
+        settings.client_ip =
+            ClientIpSettings::parse(Some("x-forwarded-for"), Some("10.0.0.0/8")).unwrap();
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    // the address added by the trusted proxy, not the one sent by the client
+    let (status_code, _, _) = helper::root_with_headers(
+        &mut app,
+        Method::GET,
+        "some-slug",
+        &[("x-forwarded-for", "1.1.1.1, 2.2.2.2, 10.0.0.1")],
+    )
+    .await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(
+        Some("2.2.2.2".to_string()),
+        last_hit_ip_address(&pool).await
+    );
+}
+
+#[sqlx::test]
+async fn test_client_ip_ipv6_visitor(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (_, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+    let destination_id = destination.unwrap().id;
+
+    // the rotating addresses of a single network are a single visitor
+    for ip_address in ["2001:db8:1:2::aaaa", "2001:db8:1:2::bbbb", "1.1.1.1"] {
+        helper::root_with_headers(
+            &mut app,
+            Method::GET,
+            "some-slug",
+            &[("x-forwarded-for", ip_address)],
+        )
+        .await;
+    }
+
+    let (status_code, response) = helper::graphql(
+        &mut app,
+        &access_token,
+        &format!(r#"{{ destination(id: "{destination_id}") {{ uniqueVisitors }} }}"#),
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(
+        2,
+        response.unwrap()["data"]["destination"]["uniqueVisitors"]
+    );
+
+    let ip_addresses = sqlx::query_scalar::<_, Option<String>>(
+        "SELECT DISTINCT host(ip_address) FROM hits ORDER BY 1",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        vec![
+            Some("1.1.1.1".to_string()),
+            Some("2001:db8:1:2::".to_string())
+        ],
+        ip_addresses
+    );
+}
@@ -0,0 +1,57 @@
+use axum::body::Body;
+use axum::http::header::ACCEPT_ENCODING;
+use axum::http::header::AUTHORIZATION;
+use axum::http::header::CONTENT_ENCODING;
+use axum::http::Method;
+use axum::http::Request;
+use axum::http::StatusCode;
+use tower::Service;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_compression(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let request = Request::builder()
+        .uri("/api/destinations")
+        .header(AUTHORIZATION, &access_token)
+        .header(ACCEPT_ENCODING, "gzip")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(StatusCode::OK, response.status());
+    assert_eq!("gzip", response.headers().get(CONTENT_ENCODING).unwrap());
+
+    // only when accepted
+    let request = Request::builder()
+        .uri("/api/destinations")
+        .header(AUTHORIZATION, &access_token)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(StatusCode::OK, response.status());
+    assert_eq!(None, response.headers().get(CONTENT_ENCODING));
+
+    // redirects are never compressed
+    let (status_code, headers, _) = helper::root_with_headers(
+        &mut app,
+        Method::GET,
+        "some-slug",
+        &[("accept-encoding", "gzip")],
+    )
+    .await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(None, headers.get(CONTENT_ENCODING));
+}
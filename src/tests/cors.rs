@@ -0,0 +1,83 @@
+use axum::body::Body;
+use axum::http::header::ACCESS_CONTROL_ALLOW_METHODS;
+use axum::http::header::ACCESS_CONTROL_ALLOW_ORIGIN;
+use axum::http::header::ACCESS_CONTROL_REQUEST_HEADERS;
+use axum::http::header::ACCESS_CONTROL_REQUEST_METHOD;
+use axum::http::header::ORIGIN;
+use axum::http::Method;
+use axum::http::Request;
+use axum::http::StatusCode;
+use tower::Service;
+
+use crate::cors;
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_cors(pool: sqlx::PgPool) {
+    let cors = cors::parse("https://dashboard.example.com", None, None).unwrap();
+    let mut app = helper::setup_test_app_with_cors(pool, cors).await;
+
+    // preflight of the dashboard
+    let request = Request::builder()
+        .method(Method::OPTIONS)
+        .uri("/api/destinations")
+        .header(ORIGIN, "https://dashboard.example.com")
+        .header(ACCESS_CONTROL_REQUEST_METHOD, "POST")
+        .header(ACCESS_CONTROL_REQUEST_HEADERS, "authorization,content-type")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(StatusCode::OK, response.status());
+    assert_eq!(
+        "https://dashboard.example.com",
+        response.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap()
+    );
+    assert!(response
+        .headers()
+        .get(ACCESS_CONTROL_ALLOW_METHODS)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .contains("POST"));
+
+    // other origins are not allowed
+    let request = Request::builder()
+        .method(Method::OPTIONS)
+        .uri("/api/destinations")
+        .header(ORIGIN, "https://evil.example.com")
+        .header(ACCESS_CONTROL_REQUEST_METHOD, "POST")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(None, response.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN));
+
+    // the root is not part of CORS
+    let (_, headers, _) = helper::root_with_headers(
+        &mut app,
+        Method::GET,
+        "some-slug",
+        &[("origin", "https://dashboard.example.com")],
+    )
+    .await;
+    assert_eq!(None, headers.get(ACCESS_CONTROL_ALLOW_ORIGIN));
+}
+
+#[sqlx::test]
+async fn test_cors_disabled(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _) = helper::list_destinations(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+
+    let request = Request::builder()
+        .method(Method::OPTIONS)
+        .uri("/api/destinations")
+        .header(ORIGIN, "https://dashboard.example.com")
+        .header(ACCESS_CONTROL_REQUEST_METHOD, "POST")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(None, response.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN));
+}
@@ -0,0 +1,63 @@
+use axum::http::StatusCode;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_deleted_user_tokens(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, user, _) = helper::maybe_create_user_with_password(
+        &mut app,
+        &access_token,
+        "manager",
+        "manager",
+        Some("verysecret"),
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let user = user.unwrap();
+    let manager_access_token = helper::login_as(&mut app, "manager", "verysecret").await;
+
+    let (status_code, tokens) = helper::deleted_user_tokens(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert!(tokens.unwrap().is_empty());
+
+    let (status_code, _) = helper::maybe_delete_user(&mut app, &access_token, &user.id).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    // the token of the deleted user keeps being presented
+    for _ in 0..3 {
+        let (status_code, _) = helper::list_users(&mut app, &manager_access_token).await;
+        assert_eq!(StatusCode::FORBIDDEN, status_code);
+    }
+
+    // registered once on the audit trail, retries from the same IP address are skipped for a while
+    let (status_code, tokens) = helper::deleted_user_tokens(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let tokens = tokens.unwrap();
+    assert_eq!(1, tokens.len());
+    assert_eq!(user.id.to_string(), tokens[0]["userId"]);
+    assert_eq!("manager", tokens[0]["username"]);
+    assert_eq!(1, tokens[0]["attempts"]);
+
+    let (status_code, metrics) = helper::auth_metrics(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let metrics = metrics.unwrap();
+    assert_eq!(3, metrics["deletedUserTokens"]);
+    assert_eq!(0, metrics["invalidTokens"]);
+
+    // managers are not allowed to see the attempts
+    helper::maybe_create_user_with_password(
+        &mut app,
+        &access_token,
+        "other-manager",
+        "manager",
+        Some("verysecret"),
+    )
+    .await;
+    let other_access_token = helper::login_as(&mut app, "other-manager", "verysecret").await;
+    let (status_code, _) = helper::deleted_user_tokens(&mut app, &other_access_token).await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+}
@@ -1,5 +1,11 @@
 use axum::http::StatusCode;
+use chrono::Datelike;
+use chrono::Utc;
+use serde_json::json;
 
+use crate::confusables::Confusables;
+use crate::slug_policy::CharacterClass;
+use crate::slug_policy::SlugPolicy;
 use crate::tests::helper;
 
 #[sqlx::test]
@@ -120,3 +126,157 @@ async fn test_destination_create_api_prefix(pool: sqlx::PgPool) {
     assert_eq!(StatusCode::BAD_REQUEST, status_code);
     assert!(destination.is_none());
 }
+
+#[sqlx::test]
+async fn test_destination_create_reserved_slug(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, destination, error) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "robots.txt",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert!(destination.is_none());
+    assert_eq!(Some("Slug is reserved".to_string()), error);
+}
+
+#[sqlx::test]
+async fn test_destination_create_slug_alternatives(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let url = "https://www.example.com/";
+    helper::maybe_create_destination(&mut app, &access_token, "launch", url).await;
+    helper::maybe_create_destination(&mut app, &access_token, "launch-3", url).await;
+
+    // the taken alternatives are left out
+    let (status_code, _, error) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "launch", "url": "https://www.example.com/" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    let error = error.unwrap();
+    assert_eq!("Slug already exists", error.error);
+    assert_eq!(
+        Some(json!({ "alternatives": [
+            "launch-2",
+            "launch-4",
+            format!("launch-{}", Utc::now().year()),
+        ] })),
+        error.details
+    );
+}
+
+#[sqlx::test]
+async fn test_destination_create_web_url(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    for url in [
+        "javascript:alert(document.cookie)",
+        "data:text/html,<script>alert(document.cookie)</script>",
+    ] {
+        let (status_code, destination, error) =
+            helper::maybe_create_destination(&mut app, &access_token, "click-me", url).await;
+        assert_eq!(StatusCode::BAD_REQUEST, status_code);
+        assert!(destination.is_none());
+        assert_eq!(Some("Invalid URL scheme".to_string()), error);
+    }
+}
+
+#[sqlx::test]
+async fn test_destination_create_confusable_slug(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app_with_root_settings(pool, |settings| {
+        settings.confusable_slugs = Confusables::Reject;
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    // a Cyrillic `а` in a Latin word
+    let (status_code, destination, error) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "p\u{0430}ypal",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert!(destination.is_none());
+    assert_eq!(Some("Slug is confusable".to_string()), error);
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "привет",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+}
+
+#[sqlx::test]
+async fn test_destination_create_slug_policy(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app_with_root_settings(pool, |settings| {
+        settings.slug_policy = SlugPolicy::default().with_max_length(16).with_characters(&[
+            CharacterClass::Lowercase,
+            CharacterClass::Digits,
+            CharacterClass::Punctuation,
+            CharacterClass::Slashes,
+        ]);
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "/2025/summer-sale/",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, _, error) = helper::maybe_create_destination_on_host(
+        &mut app,
+        &access_token,
+        "localhost",
+        "Summer",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    let error = error.unwrap();
+    assert_eq!(r#"Slug can not contain "S""#, error.error);
+    assert_eq!(
+        Some("Slugs consist of lowercase, digits, punctuation and slashes".to_string()),
+        error.description
+    );
+
+    for (slug, message) in [
+        ("a-very-long-summer-sale", "Slug is too long"),
+        ("🦙", r#"Slug can not contain "🦙""#),
+        ("2025//summer", "Slug can not have an empty path segment"),
+    ] {
+        let (status_code, _, error) = helper::maybe_create_destination(
+            &mut app,
+            &access_token,
+            slug,
+            "https://www.example.com/",
+        )
+        .await;
+        assert_eq!(StatusCode::BAD_REQUEST, status_code);
+        assert_eq!(Some(message.to_string()), error);
+    }
+}
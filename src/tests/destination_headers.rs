@@ -0,0 +1,102 @@
+use axum::http::Method;
+use axum::http::StatusCode;
+use serde_json::json;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_destination_headers(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, destination, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{
+            "slug": "hidden",
+            "url": "https://www.example.com/",
+            "headers": { "X-Robots-Tag": "noindex", "X-Campaign": "launch" }
+        }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let destination_id = destination.unwrap().id;
+
+    let (status_code, headers, _) =
+        helper::root_with_headers(&mut app, Method::GET, "hidden", &[]).await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!("https://www.example.com/", headers.get("location").unwrap());
+    assert_eq!("noindex", headers.get("x-robots-tag").unwrap());
+    assert_eq!("launch", headers.get("x-campaign").unwrap());
+
+    // the headers are replaced as a whole
+    let (status_code, destination, _) = helper::maybe_patch_destination(
+        &mut app,
+        &access_token,
+        &destination_id,
+        r#"{ "headers": { "x-robots-tag": "noindex, nofollow" } }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(
+        json!({ "x-robots-tag": "noindex, nofollow" }),
+        destination.unwrap()["headers"]
+    );
+
+    let (_, headers, _) = helper::root_with_headers(&mut app, Method::GET, "hidden", &[]).await;
+    assert_eq!("noindex, nofollow", headers.get("x-robots-tag").unwrap());
+    assert!(headers.get("x-campaign").is_none());
+
+    // `null` removes the headers
+    let (status_code, destination, _) = helper::maybe_patch_destination(
+        &mut app,
+        &access_token,
+        &destination_id,
+        r#"{ "headers": null }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(json!({}), destination.unwrap()["headers"]);
+
+    let (_, headers, _) = helper::root_with_headers(&mut app, Method::GET, "hidden", &[]).await;
+    assert!(headers.get("x-robots-tag").is_none());
+}
+
+#[sqlx::test]
+async fn test_destination_headers_invalid(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, error) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "moved", "url": "https://www.example.com/", "headers": { "Location": "https://www.example.org/" } }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("Header can not be set: Location", error.unwrap().error);
+
+    let (status_code, _, error) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "moved", "url": "https://www.example.com/", "headers": { "X Space": "yes" } }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("Invalid header name: X Space", error.unwrap().error);
+
+    let (status_code, _, error) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "moved", "url": "https://www.example.com/", "headers": { "X-Line": "a\nb" } }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("Invalid value of header: X-Line", error.unwrap().error);
+}
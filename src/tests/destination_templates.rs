@@ -0,0 +1,170 @@
+use axum::http::StatusCode;
+use serde_json::json;
+
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_destination_templates(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, template, _) = helper::maybe_create_template(
+        &mut app,
+        &access_token,
+        r#"{
+            "name": "newsletter",
+            "domain": "Go.Acme.com",
+            "isPermanent": true,
+            "tags": ["marketing", " marketing "],
+            "utmSource": "newsletter",
+            "utmMedium": "email"
+        }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let template = template.unwrap();
+    assert_eq!("go.acme.com", template["domain"]);
+    assert_eq!(json!(["marketing"]), template["tags"]);
+    let template_id = template["id"].as_str().unwrap().to_string();
+
+    let (status_code, _, message) =
+        helper::maybe_create_template(&mut app, &access_token, r#"{ "name": "newsletter" }"#).await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Template already exists".to_string()), message);
+
+    // the settings of the destination go over the ones of the template
+    let (status_code, _, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{
+            "slug": "spring",
+            "url": "https://www.example.com/?utm_medium=social",
+            "template": "newsletter",
+            "isPermanent": false,
+            "tags": ["spring"]
+        }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool)).await;
+    let destination = database
+        .find_single_destination_in_namespace(Some("go.acme.com"), "spring")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        "https://www.example.com/?utm_medium=social&utm_source=newsletter",
+        destination.url
+    );
+    assert_eq!(Some("go.acme.com".to_string()), destination.domain);
+    assert!(!destination.is_permanent);
+    assert_eq!(vec!["marketing", "spring"], destination.tags);
+
+    // existing destinations are not touched by changes of the template
+    let (status_code, template) = helper::maybe_update_template(
+        &mut app,
+        &access_token,
+        &template_id,
+        r#"{ "domain": null, "isPermanent": null, "tags": null, "utmCampaign": "summer" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    let template = template.unwrap();
+    assert!(template["domain"].is_null());
+    assert!(template["isPermanent"].is_null());
+    assert_eq!(json!([]), template["tags"]);
+    assert_eq!("newsletter", template["utmSource"]);
+    assert_eq!("summer", template["utmCampaign"]);
+
+    let (status_code, destination, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "summer", "url": "https://www.example.com/", "template": "newsletter" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    assert_eq!(
+        "https://www.example.com/?utm_source=newsletter&utm_medium=email&utm_campaign=summer",
+        destination.unwrap().url
+    );
+
+    let (status_code, templates) = helper::list_templates(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(1, templates.unwrap().as_array().unwrap().len());
+
+    let status_code = helper::maybe_delete_template(&mut app, &access_token, &template_id).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    let (status_code, _, message) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "autumn", "url": "https://www.example.com/", "template": "newsletter" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("Template not found", message.unwrap().error);
+}
+
+#[sqlx::test]
+async fn test_destination_templates_managers(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    helper::maybe_create_user_with_password(
+        &mut app,
+        &access_token,
+        "manager",
+        "manager",
+        Some("verysecret"),
+    )
+    .await;
+    let manager_access_token = helper::login_as(&mut app, "manager", "verysecret").await;
+
+    // managers use the templates, admins manage them
+    let (status_code, _, _) = helper::maybe_create_template(
+        &mut app,
+        &manager_access_token,
+        r#"{ "name": "newsletter" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    helper::maybe_create_template(
+        &mut app,
+        &access_token,
+        r#"{ "name": "newsletter", "tags": ["marketing"] }"#,
+    )
+    .await;
+
+    let (status_code, templates) = helper::list_templates(&mut app, &manager_access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!("newsletter", templates.unwrap()[0]["name"]);
+
+    let (status_code, destination, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &manager_access_token,
+        r#"{ "slug": "tagged", "url": "https://www.example.com/", "template": "newsletter" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    // the tags are replaced on update
+    let (status_code, destination, _) = helper::maybe_patch_destination(
+        &mut app,
+        &manager_access_token,
+        &destination.unwrap().id,
+        r#"{ "tags": ["launch", "marketing"] }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(json!(["launch", "marketing"]), destination.unwrap()["tags"]);
+}
@@ -1,5 +1,6 @@
 use axum::http::StatusCode;
 
+use crate::confusables::Confusables;
 use crate::tests::helper;
 
 #[sqlx::test]
@@ -40,3 +41,50 @@ async fn test_destination_update(pool: sqlx::PgPool) {
     assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
     assert_eq!(Some(url_two.to_string()), location);
 }
+
+#[sqlx::test]
+async fn test_destination_update_internationalized_url(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app_with_root_settings(pool, |settings| {
+        settings.confusable_urls = Confusables::Reject;
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (_, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "munich",
+        "https://www.example.com/",
+    )
+    .await;
+    let destination_id = destination.unwrap().id;
+
+    // stored in punycode, shown in unicode as well
+    let (status_code, destination, _) = helper::maybe_patch_destination(
+        &mut app,
+        &access_token,
+        &destination_id,
+        r#"{ "url": "https://MÜNCHEN.de/path" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    let destination = destination.unwrap();
+    assert_eq!("https://xn--mnchen-3ya.de/path", destination["url"]);
+    assert_eq!("https://münchen.de/path", destination["unicodeUrl"]);
+
+    let (status_code, location, _) = helper::root(&mut app, "munich").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(Some("https://xn--mnchen-3ya.de/path".to_string()), location);
+
+    // a Cyrillic `а` in a Latin hostname
+    let (status_code, _, error) = helper::maybe_patch_destination(
+        &mut app,
+        &access_token,
+        &destination_id,
+        "{ \"url\": \"https://p\u{0430}ypal.com/\" }",
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("URL has a confusable hostname".to_string()), error);
+}
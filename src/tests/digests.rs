@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use axum::http::Method;
+use axum::http::StatusCode;
+use serde_json::Value;
+
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::jobs::Jobs;
+use crate::notifier::Notifier;
+use crate::notifier::Smtp;
+use crate::tests::helper;
+
+async fn insert_rollup(pool: &sqlx::PgPool, slug: &str, days_ago: i32, hits: i64) {
+    sqlx::query(
+        r"
+        INSERT INTO hit_rollups (destination_id, day, hits)
+        SELECT id, CURRENT_DATE - $2, $3
+        FROM destinations
+        WHERE slug = $1
+        ",
+    )
+    .bind(slug)
+    .bind(days_ago)
+    .bind(hits)
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+#[sqlx::test]
+async fn test_digests(pool: sqlx::PgPool) {
+    // notifying is disabled in the environment of the tests
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |_| {}).await;
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, message) =
+        helper::maybe_digest(&mut app, &access_token, Method::PUT).await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(
+        Some("Email notifications are disabled".to_string()),
+        message
+    );
+
+    // never started, the emails stay in the queue
+    let notifier = Notifier::new(
+        Arc::new(Smtp::parse("smtp://localhost:2525").unwrap()),
+        "shurly@example.com",
+    );
+
+    let jobs = Jobs::new(None, None, None).with_notifier(notifier.clone());
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |settings| {
+        settings.notifier = notifier.clone();
+    })
+    .await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool.clone())).await;
+
+    let (status_code, _, message) =
+        helper::maybe_digest(&mut app, &access_token, Method::PUT).await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Missing email address".to_string()), message);
+
+    let (status_code, _, _) = helper::maybe_change_email(
+        &mut app,
+        &access_token,
+        "me",
+        r#"{ "email": "admin@example.com" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, digest, _) = helper::maybe_digest(&mut app, &access_token, Method::PUT).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let digest = digest.unwrap();
+    assert_eq!(true, digest["isSubscribed"]);
+    assert_eq!(Value::Null, digest["lastSentAt"]);
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "summer",
+        "https://www.example.com/summer",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    insert_rollup(&pool, "summer", 3, 6).await;
+    insert_rollup(&pool, "summer", 10, 4).await;
+
+    // the hits of today are in the next digest
+    helper::root(&mut app, "summer").await;
+
+    for _ in 0..2 {
+        let (status_code, _, _) = helper::root(&mut app, "sumer").await;
+        assert_eq!(StatusCode::NOT_FOUND, status_code);
+    }
+
+    let (status_code, digest, _) = helper::maybe_digest(&mut app, &access_token, Method::GET).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let digest = digest.unwrap()["digest"].clone();
+    assert_eq!(6, digest["hits"]);
+    assert_eq!(4, digest["previousHits"]);
+    assert_eq!("summer", digest["topDestinations"][0]["slug"]);
+    assert_eq!("sumer", digest["missedSlugs"][0]["slug"]);
+    assert_eq!(2, digest["missedSlugs"][0]["hits"]);
+
+    jobs.run_all(&database).await;
+
+    let emails = notifier.take_queued().await;
+    assert_eq!(1, emails.len());
+    assert_eq!("admin@example.com", emails[0].to);
+    assert_eq!("Your weekly digest of Shurly", emails[0].subject);
+    assert!(emails[0].body.contains("- /summer: 6 hit(s) (+50%)"));
+    assert!(emails[0].body.contains("- /sumer: tried 2 time(s)"));
+
+    // a digest a week
+    jobs.run_all(&database).await;
+    assert!(notifier.take_queued().await.is_empty());
+
+    let (status_code, _, _) = helper::maybe_digest(&mut app, &access_token, Method::DELETE).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    let (status_code, digest, _) = helper::maybe_digest(&mut app, &access_token, Method::GET).await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(false, digest.unwrap()["isSubscribed"]);
+}
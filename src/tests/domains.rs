@@ -0,0 +1,186 @@
+use axum::http::Method;
+use axum::http::StatusCode;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_domains(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    // setup
+    for raw_body in [
+        r#"{ "slug": "docs", "url": "https://www.example.com/" }"#,
+        r#"{ "slug": "docs", "domain": "Go.Acme.com", "url": "https://acme.example.com/" }"#,
+        r#"{ "slug": "docs", "domain": "go.other.com", "url": "https://other.example.com/" }"#,
+    ] {
+        let (status_code, _, _) =
+            helper::maybe_create_destination_with_raw_body(&mut app, &access_token, raw_body, true)
+                .await;
+        assert_eq!(StatusCode::CREATED, status_code);
+    }
+
+    // destination of the domain, regardless of port
+    let (status_code, headers, _) = helper::root_with_headers(
+        &mut app,
+        Method::GET,
+        "docs",
+        &[("host", "go.acme.com:7000")],
+    )
+    .await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(
+        "https://acme.example.com/",
+        headers.get("location").unwrap()
+    );
+
+    let (_, headers, _) =
+        helper::root_with_headers(&mut app, Method::GET, "docs", &[("host", "go.other.com")]).await;
+    assert_eq!(
+        "https://other.example.com/",
+        headers.get("location").unwrap()
+    );
+
+    // fallback to the destination for all domains
+    let (_, headers, _) =
+        helper::root_with_headers(&mut app, Method::GET, "docs", &[("host", "sho.rt")]).await;
+    assert_eq!("https://www.example.com/", headers.get("location").unwrap());
+
+    // not on any domain
+    let (status_code, _, _) =
+        helper::root_with_headers(&mut app, Method::GET, "other", &[("host", "go.acme.com")]).await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+}
+
+#[sqlx::test]
+async fn test_domains_duplicate_slug(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "docs", "domain": "go.acme.com", "url": "https://www.example.com/" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, _, error) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "docs", "domain": "GO.ACME.COM", "url": "https://www.example.com/" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("Slug already exists", error.unwrap().error);
+
+    let (status_code, _, error) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "docs", "domain": "not a domain", "url": "https://www.example.com/" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("Invalid domain", error.unwrap().error);
+}
+
+#[sqlx::test]
+async fn test_domain_management(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    // register
+    let (status_code, domain, _) = helper::maybe_create_domain(
+        &mut app,
+        &access_token,
+        r#"{ "hostname": "Go.Acme.com", "fallbackUrl": "https://www.acme.com/" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let domain = domain.unwrap();
+    assert_eq!("go.acme.com", domain.hostname);
+
+    let (status_code, _, error) =
+        helper::maybe_create_domain(&mut app, &access_token, r#"{ "hostname": "go.acme.com" }"#)
+            .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Domain already exists".to_string()), error);
+
+    let (status_code, _, error) =
+        helper::maybe_create_domain(&mut app, &access_token, r#"{ "hostname": "not a domain" }"#)
+            .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Invalid domain".to_string()), error);
+
+    let (status_code, domains) = helper::list_domains(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert!(domains.unwrap().iter().any(|d| d.id == domain.id));
+
+    // fallback for slugs without a destination on the domain
+    let (status_code, headers, _) =
+        helper::root_with_headers(&mut app, Method::GET, "missing", &[("host", "go.acme.com")])
+            .await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!("https://www.acme.com/", headers.get("location").unwrap());
+
+    // other domains keep the regular 404 page
+    let (status_code, _, _) =
+        helper::root_with_headers(&mut app, Method::GET, "missing", &[("host", "sho.rt")]).await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+
+    // 404 page of the domain
+    let (status_code, _) = helper::maybe_update_domain(
+        &mut app,
+        &access_token,
+        &domain.id,
+        r#"{ "fallbackUrl": "", "notFoundTemplate": "<h1>Not at Acme</h1>" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+
+    let (status_code, _, body) =
+        helper::root_with_headers(&mut app, Method::GET, "missing", &[("host", "go.acme.com")])
+            .await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+    assert_eq!("<h1>Not at Acme</h1>", body);
+
+    let (status_code, error) = helper::maybe_update_domain(
+        &mut app,
+        &access_token,
+        &domain.id,
+        r#"{ "fallbackUrl": "not a url" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert!(error.is_some());
+
+    // delete
+    let status_code = helper::maybe_delete_domain(&mut app, &access_token, &domain.id).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    let (_, _, body) =
+        helper::root_with_headers(&mut app, Method::GET, "missing", &[("host", "go.acme.com")])
+            .await;
+    assert!(body.contains("Page not found"));
+
+    let (status_code, _, error) =
+        helper::maybe_create_domain(&mut app, &access_token, r#"{ "hostname": "go.acme.com" }"#)
+            .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(
+        Some("Domain already exists and is deleted".to_string()),
+        error
+    );
+
+    // domain changes are on the audit trail
+    let (_, verification) = helper::verify_audit_trail(&mut app, &access_token).await;
+    let verification = verification.unwrap();
+    assert!(verification.is_valid);
+    assert_eq!(3, verification.verified_entries);
+}
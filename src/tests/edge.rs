@@ -0,0 +1,118 @@
+use axum::http::StatusCode;
+use serde_json::Value;
+
+use crate::ingest::IngestKeys;
+use crate::tests::helper;
+
+const API_KEY: &str = "edge-key-edge-key-edge-key-edge-key";
+
+#[sqlx::test]
+async fn test_edge_snapshot(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |_| {}).await;
+
+    let (status_code, _, message) = helper::maybe_edge_snapshot(&mut app, API_KEY, None).await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+    assert_eq!(Some("Edges are not enabled".to_string()), message);
+
+    let mut app = helper::setup_test_app_with_root_settings(pool, |settings| {
+        settings.ingest_keys = IngestKeys::new(&[("cdn", API_KEY)]);
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, message) =
+        helper::maybe_edge_snapshot(&mut app, &access_token, None).await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+    assert_eq!(Some("Invalid API key".to_string()), message);
+
+    let (status_code, launch, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "launch",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let launch = launch.unwrap();
+
+    let (status_code, gone, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "gone",
+        "https://www.example.com/gone",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let gone = gone.unwrap();
+
+    // private destinations and meta refreshes need Shurly itself
+    let (status_code, _, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "private", "url": "https://www.example.com/", "isPrivate": true }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, _, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "meta-refresh", "url": "https://www.example.com/", "isMetaRefresh": true }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, snapshot, _) = helper::maybe_edge_snapshot(&mut app, API_KEY, None).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let snapshot = snapshot.unwrap();
+    assert_eq!(Value::Bool(true), snapshot["isFull"]);
+    assert_eq!(Some(&vec![]), snapshot["removed"].as_array());
+    assert_eq!(vec!["gone", "launch"], slugs(&snapshot["destinations"]));
+    let version = snapshot["version"].as_str().unwrap().to_string();
+
+    let (status_code, _, message) =
+        helper::maybe_edge_snapshot(&mut app, API_KEY, Some("yesterday")).await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Invalid since".to_string()), message);
+
+    let (status_code, _) = helper::maybe_update_destination(
+        &mut app,
+        &access_token,
+        &launch.id,
+        "https://www.example.com/launched",
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+
+    let (status_code, _) =
+        helper::myabe_delete_destination(&mut app, &access_token, &gone.id).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    let (status_code, snapshot, _) =
+        helper::maybe_edge_snapshot(&mut app, API_KEY, Some(&version)).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let snapshot = snapshot.unwrap();
+    assert_eq!(Value::Bool(false), snapshot["isFull"]);
+
+    let destinations = snapshot["destinations"].as_array().unwrap();
+    assert_eq!(1, destinations.len());
+    assert_eq!("launch", destinations[0]["slug"]);
+    assert_eq!("https://www.example.com/launched", destinations[0]["url"]);
+
+    // the edge forwards removed slugs to Shurly again
+    assert!(slugs(&snapshot["removed"]).contains(&"gone".to_string()));
+}
+
+fn slugs(values: &Value) -> Vec<String> {
+    let mut slugs = values
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|value| value["slug"].as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    slugs.sort();
+    slugs
+}
@@ -0,0 +1,86 @@
+use axum::http::Method;
+use axum::http::StatusCode;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_error_codes(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, destination, _) = helper::maybe_create_destination_with_is_permanent(
+        &mut app,
+        &access_token,
+        "launch",
+        "https://www.example.com/",
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let destination = destination.unwrap();
+
+    for (body, code) in [
+        (
+            r#"{ "slug": "launch", "url": "https://www.example.com/" }"#,
+            "slug_conflict",
+        ),
+        (
+            r#"{ "slug": "launch?", "url": "https://www.example.com/" }"#,
+            "invalid_slug_char",
+        ),
+        (
+            r#"{ "slug": "api/launch", "url": "https://www.example.com/" }"#,
+            "slug_reserved",
+        ),
+        (r#"{ "slug": "other", "url": "not a url" }"#, "invalid_url"),
+        (r#"{ "slug": "other" }"#, "invalid_body"),
+        (r#"{ "slug": "other", "#, "invalid_body"),
+    ] {
+        let (status_code, _, error) =
+            helper::maybe_create_destination_with_raw_body(&mut app, &access_token, body, true)
+                .await;
+        assert_eq!(StatusCode::BAD_REQUEST, status_code, "{body}");
+        assert_eq!(code, error.unwrap().code, "{body}");
+    }
+
+    let (status_code, _, error) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "other", "url": "https://www.example.com/" }"#,
+        false,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("missing_content_type", error.unwrap().code);
+
+    let (status_code, error) = helper::api_error(
+        &mut app,
+        Method::DELETE,
+        &format!("/api/destinations/{}", destination.id),
+        Some(&access_token),
+        "",
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    let error = error.unwrap();
+    assert_eq!("Permanent URLs can not be deleted", error.error);
+    assert_eq!("permanent_immutable", error.code);
+
+    let (status_code, error) =
+        helper::api_error(&mut app, Method::GET, "/api/destinations", None, "").await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+    assert_eq!("missing_token", error.unwrap().code);
+
+    // errors without a more specific code have the code of their status
+    let (status_code, error) = helper::api_error(
+        &mut app,
+        Method::GET,
+        &format!("/api/destinations/{}", uuid::Uuid::new_v4()),
+        Some(&access_token),
+        "",
+    )
+    .await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+    assert_eq!("not_found", error.unwrap().code);
+}
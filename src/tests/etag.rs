@@ -0,0 +1,84 @@
+use axum::http::StatusCode;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_etag(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "abc",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let destination_id = destination.unwrap().id;
+    let uri = format!("/api/destinations/{destination_id}");
+
+    let (status_code, etag, _) = helper::conditional_get(&mut app, &access_token, &uri, None).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let etag = etag.unwrap();
+    assert!(etag.starts_with("W/\""));
+
+    // nothing changed, no body
+    let (status_code, same_etag, body) =
+        helper::conditional_get(&mut app, &access_token, &uri, Some(&etag)).await;
+    assert_eq!(StatusCode::NOT_MODIFIED, status_code);
+    assert_eq!(Some(&etag), same_etag.as_ref());
+    assert!(body.is_empty());
+
+    let (status_code, list_etag, _) =
+        helper::conditional_get(&mut app, &access_token, "/api/destinations", None).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let list_etag = list_etag.unwrap();
+
+    let (status_code, _, _) = helper::conditional_get(
+        &mut app,
+        &access_token,
+        "/api/destinations",
+        Some(&list_etag),
+    )
+    .await;
+    assert_eq!(StatusCode::NOT_MODIFIED, status_code);
+
+    // an update changes the tag of the destination and the list
+    let (status_code, _) = helper::maybe_update_destination(
+        &mut app,
+        &access_token,
+        &destination_id,
+        "https://www.example.com/updated",
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+
+    let (status_code, new_etag, _) =
+        helper::conditional_get(&mut app, &access_token, &uri, Some(&etag)).await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_ne!(Some(etag), new_etag);
+
+    let (status_code, _, _) = helper::conditional_get(
+        &mut app,
+        &access_token,
+        "/api/destinations",
+        Some(&list_etag),
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+
+    // a new note changes the tag of the list of notes
+    let notes_uri = format!("/api/destinations/{destination_id}/notes");
+    let (_, notes_etag, _) =
+        helper::conditional_get(&mut app, &access_token, &notes_uri, None).await;
+
+    let (status_code, _, _) =
+        helper::maybe_create_note(&mut app, &access_token, &destination_id, "Launch").await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, _, _) =
+        helper::conditional_get(&mut app, &access_token, &notes_uri, notes_etag.as_deref()).await;
+    assert_eq!(StatusCode::OK, status_code);
+}
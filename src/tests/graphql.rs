@@ -0,0 +1,143 @@
+use axum::http::StatusCode;
+use serde_json::json;
+
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::jobs::Jobs;
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_graphql(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let (status_code, _) = helper::graphql(&mut app, "", "{ me { username } }").await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let destination = destination.unwrap();
+
+    helper::maybe_create_note(&mut app, &access_token, &destination.id, "Some note").await;
+
+    for _ in 0..3 {
+        helper::root(&mut app, "some-slug").await;
+    }
+
+    let (status_code, response) = helper::graphql(
+        &mut app,
+        &access_token,
+        r"{
+            destinations {
+                slug
+                isPermanent
+                createdBy { username role }
+                notes { content createdBy { username } }
+                hits { total daily { hits } }
+            }
+        }",
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(
+        json!({
+            "data": {
+                "destinations": [{
+                    "slug": "some-slug",
+                    "isPermanent": false,
+                    "createdBy": { "username": "admin", "role": "ADMIN" },
+                    "notes": [{ "content": "Some note", "createdBy": { "username": "admin" } }],
+                    "hits": { "total": 3, "daily": [{ "hits": 3 }] },
+                }]
+            }
+        }),
+        response.unwrap()
+    );
+
+    // a hit of a couple of days ago, the rollup does not count today twice
+    sqlx::query(
+        r"
+        INSERT INTO hit_rollups (destination_id, day, hits)
+        SELECT id, CURRENT_DATE - 2, 1 FROM destinations
+        ",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool.clone())).await;
+    Jobs::new(None, None, None).run_all(&database).await;
+
+    let (status_code, response) = helper::graphql(
+        &mut app,
+        &access_token,
+        &format!(
+            r#"{{
+                destination(id: "{}") {{
+                    hits {{ total today: daily(days: 1) {{ hits }} week: daily(days: 7) {{ hits }} }}
+                }}
+            }}"#,
+            destination.id
+        ),
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(
+        json!({
+            "total": 4,
+            "today": [{ "hits": 3 }],
+            "week": [{ "hits": 1 }, { "hits": 3 }],
+        }),
+        response.unwrap()["data"]["destination"]["hits"]
+    );
+}
+
+#[sqlx::test]
+async fn test_graphql_manager(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_user_with_password(
+        &mut app,
+        &access_token,
+        "jane",
+        "manager",
+        Some("alsoverysecret"),
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (_, response) = helper::graphql(&mut app, &access_token, "{ users { username } }").await;
+    assert_eq!(
+        2,
+        response.unwrap()["data"]["users"].as_array().unwrap().len()
+    );
+
+    let access_token = helper::login_as(&mut app, "jane", "alsoverysecret").await;
+
+    let (status_code, response) =
+        helper::graphql(&mut app, &access_token, "{ me { username role } }").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(
+        json!({ "data": { "me": { "username": "jane", "role": "MANAGER" } } }),
+        response.unwrap()
+    );
+
+    // only admins can list the users
+    let (status_code, response) =
+        helper::graphql(&mut app, &access_token, "{ users { username } }").await;
+    assert_eq!(StatusCode::OK, status_code);
+    let response = response.unwrap();
+    assert_eq!(
+        "Not allowed to acces",
+        response["errors"][0]["message"].as_str().unwrap()
+    );
+}
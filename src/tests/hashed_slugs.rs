@@ -0,0 +1,92 @@
+use axum::http::StatusCode;
+
+use crate::hashed_slugs::HashedSlugs;
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_hashed_slugs(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |_| {}).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, error) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "url": "https://www.example.com/" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("Slug is required", error.unwrap().error);
+
+    let mut app = helper::setup_test_app_with_root_settings(pool, |settings| {
+        settings.hashed_slugs = HashedSlugs::new(7);
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, destination, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "url": "https://www.example.com/" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let destination = destination.unwrap();
+    assert_eq!(7, destination.slug.len());
+
+    let (status_code, location, _) = helper::root(&mut app, &destination.slug).await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(Some("https://www.example.com/".to_string()), location);
+
+    // the same URL, the same destination
+    let (status_code, same, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "url": "https://www.example.com/" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(destination.id, same.unwrap().id);
+
+    let (status_code, other, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "url": "https://www.example.com/other" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    assert_ne!(destination.slug, other.unwrap().slug);
+
+    // a provided slug is used as is
+    let (status_code, provided, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "launch",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    assert_eq!("launch", provided.unwrap().slug);
+
+    // the slug of a deleted destination is not taken again
+    let (status_code, _) =
+        helper::myabe_delete_destination(&mut app, &access_token, &destination.id).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    let (status_code, recreated, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "url": "https://www.example.com/" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let recreated = recreated.unwrap();
+    assert_eq!(8, recreated.slug.len());
+    assert!(recreated.slug.starts_with(&destination.slug));
+}
@@ -0,0 +1,150 @@
+use std::time::Duration;
+
+use axum::http::header::CACHE_CONTROL;
+use axum::http::Method;
+use axum::http::StatusCode;
+
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::hit_buffer::Flush;
+use crate::hit_buffer::HitBuffer;
+use crate::hit_buffer::MAX_QUEUED_HITS;
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_health(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let (status_code, headers, body) =
+        helper::root_with_method(&mut app, Method::GET, "healthz").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!("no-store", headers.get(CACHE_CONTROL).unwrap());
+    assert_eq!("OK", body);
+
+    let (status_code, _, body) = helper::root_with_method(&mut app, Method::GET, "readyz").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!("OK", body);
+
+    // like a newer version of Shurly added a migration
+    sqlx::query(
+        r"
+        DELETE FROM _sqlx_migrations
+        WHERE version = (SELECT MAX(version) FROM _sqlx_migrations)
+        ",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let (status_code, _, body) = helper::root_with_method(&mut app, Method::GET, "readyz").await;
+    assert_eq!(StatusCode::SERVICE_UNAVAILABLE, status_code);
+    assert_eq!("Migrations are missing", body);
+
+    // still alive
+    let (status_code, _, _) = helper::root_with_method(&mut app, Method::GET, "healthz").await;
+    assert_eq!(StatusCode::OK, status_code);
+}
+
+#[sqlx::test]
+async fn test_health_degraded(pool: sqlx::PgPool) {
+    let hits = HitBuffer::default();
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |settings| {
+        settings.hits = hits.clone();
+    })
+    .await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool.clone())).await;
+
+    let access_token = helper::login(&mut app).await;
+    helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+
+    // like an unreachable database, for the hits
+    sqlx::query("ALTER TABLE hits RENAME TO unreachable_hits")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    helper::root(&mut app, "some-slug").await;
+
+    let (status_code, _, body) = helper::root_with_method(&mut app, Method::GET, "readyz").await;
+    assert_eq!(StatusCode::SERVICE_UNAVAILABLE, status_code);
+    assert_eq!("Database is degraded", body);
+
+    sqlx::query("ALTER TABLE unreachable_hits RENAME TO hits")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // like the retry in the background
+    hits.flush(&database).await;
+
+    let (status_code, _, body) = helper::root_with_method(&mut app, Method::GET, "readyz").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!("OK", body);
+}
+
+#[sqlx::test]
+async fn test_health_saturated(pool: sqlx::PgPool) {
+    // never saved on its own
+    let hits = HitBuffer::new(Some(Flush {
+        interval: Duration::from_secs(60 * 60),
+        size: usize::MAX,
+    }));
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |settings| {
+        settings.hits = hits.clone();
+    })
+    .await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool.clone())).await;
+
+    let access_token = helper::login(&mut app).await;
+    helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+    let destination = database
+        .find_single_destination_by_slug(None, "some-slug")
+        .await
+        .unwrap()
+        .unwrap();
+
+    for _ in 0..MAX_QUEUED_HITS {
+        hits.save(&database, &destination, None, None, None).await;
+    }
+
+    let (status_code, _, body) = helper::root_with_method(&mut app, Method::GET, "readyz").await;
+    assert_eq!(StatusCode::SERVICE_UNAVAILABLE, status_code);
+    assert_eq!("Hit queue is full", body);
+
+    hits.flush(&database).await;
+
+    let (status_code, _, body) = helper::root_with_method(&mut app, Method::GET, "readyz").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!("OK", body);
+}
+
+#[sqlx::test]
+async fn test_health_reserved_slugs(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    for slug in ["healthz", "readyz"] {
+        let (status_code, _, error) = helper::maybe_create_destination(
+            &mut app,
+            &access_token,
+            slug,
+            "https://www.example.com/",
+        )
+        .await;
+        assert_eq!(StatusCode::BAD_REQUEST, status_code);
+        assert_eq!(Some("Slug is reserved".to_string()), error);
+    }
+}
@@ -0,0 +1,104 @@
+use axum::http::StatusCode;
+use chrono::Datelike;
+use chrono::NaiveDateTime;
+use chrono::NaiveTime;
+use chrono::TimeDelta;
+use chrono::Utc;
+use serde_json::Value;
+
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::hit_buffer::Hit;
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_heatmap(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool)).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "launch",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let destination = destination.unwrap();
+
+    let today = Utc::now().date_naive();
+    let recent = (today - TimeDelta::days(1)).and_time(NaiveTime::from_hms_opt(9, 30, 0).unwrap());
+    let old = (today - TimeDelta::days(100)).and_time(NaiveTime::from_hms_opt(23, 30, 0).unwrap());
+
+    let hit = |created_at: NaiveDateTime| Hit {
+        destination_id: destination.id,
+        ip_address: None,
+        user_agent: None,
+        referrer: None,
+        created_at,
+    };
+    database
+        .save_hits(&[hit(recent), hit(recent), hit(old)])
+        .await
+        .unwrap();
+
+    let weekday = |moment: NaiveDateTime| moment.weekday().number_from_monday();
+
+    let (status_code, heatmap, _) =
+        helper::destination_heatmap(&mut app, &access_token, &destination.id, "").await;
+    assert_eq!(StatusCode::OK, status_code);
+    let heatmap = heatmap.unwrap();
+    assert_eq!("UTC", heatmap["timezone"]);
+    assert_eq!(3, heatmap["hits"]);
+    assert_eq!(168, heatmap["hours"].as_array().unwrap().len());
+    assert_eq!(2, hits(&heatmap, weekday(recent), 9));
+    assert_eq!(1, hits(&heatmap, weekday(old), 23));
+
+    // 9 hours ahead, without daylight saving time
+    let (status_code, heatmap, _) = helper::destination_heatmap(
+        &mut app,
+        &access_token,
+        &destination.id,
+        "timezone=Asia/Tokyo",
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    let heatmap = heatmap.unwrap();
+    assert_eq!(2, hits(&heatmap, weekday(recent), 18));
+    assert_eq!(1, hits(&heatmap, weekday(old + TimeDelta::days(1)), 8));
+
+    let (status_code, heatmap, _) =
+        helper::destination_heatmap(&mut app, &access_token, &destination.id, "days=30").await;
+    assert_eq!(StatusCode::OK, status_code);
+    let heatmap = heatmap.unwrap();
+    assert_eq!(30, heatmap["days"]);
+    assert_eq!(2, heatmap["hits"]);
+
+    let (status_code, _, message) = helper::destination_heatmap(
+        &mut app,
+        &access_token,
+        &destination.id,
+        "timezone=Mars/Olympus",
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Invalid timezone".to_string()), message);
+
+    let (status_code, _, message) =
+        helper::destination_heatmap(&mut app, &access_token, &destination.id, "days=0").await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Invalid days".to_string()), message);
+}
+
+fn hits(heatmap: &Value, weekday: u32, hour: u32) -> i64 {
+    heatmap["hours"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|cell| cell["weekday"] == weekday && cell["hour"] == hour)
+        .unwrap()["hits"]
+        .as_i64()
+        .unwrap()
+}
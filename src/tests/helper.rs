@@ -2,7 +2,11 @@ use axum::body::Body;
 use axum::body::Bytes;
 use axum::http::header::AUTHORIZATION;
 use axum::http::header::CONTENT_TYPE;
+use axum::http::header::ETAG;
+use axum::http::header::HOST;
+use axum::http::header::IF_NONE_MATCH;
 use axum::http::header::LOCATION;
+use axum::http::HeaderMap;
 use axum::http::Method;
 use axum::http::Request;
 use axum::http::StatusCode;
@@ -11,10 +15,17 @@ use http_body_util::BodyExt;
 use serde_json::Map;
 use serde_json::Value;
 use tower::Service;
+use tower_http::cors::CorsLayer;
 use uuid::Uuid;
 
+use crate::create_router;
+use crate::database::Database;
 use crate::database::DatabaseConfig;
+use crate::limits::Limits;
+use crate::root::Settings as RootSettings;
 use crate::setup_app;
+use crate::users::ensure_initial_user;
+use crate::Routes;
 
 /// Test helper version of User struct
 #[derive(Debug)]
@@ -35,6 +46,13 @@ pub struct Destination {
     pub url: String,
 }
 
+/// Test helper version of Domain struct
+#[derive(Debug)]
+pub struct Domain {
+    pub id: Uuid,
+    pub hostname: String,
+}
+
 /// Test helper version of Note struct
 #[derive(Debug, PartialEq, Eq)]
 pub struct Note {
@@ -42,50 +60,158 @@ pub struct Note {
     pub content: String,
 }
 
+/// Test helper version of the audit trail verification
+#[derive(Debug)]
+pub struct AuditTrailVerification {
+    pub is_valid: bool,
+    pub verified_entries: u64,
+    pub first_invalid_entry_id: Option<Uuid>,
+}
+
 /// Error response
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq)]
+#[allow(clippy::struct_field_names)] // the fields of the error response
 pub struct Error {
     pub error: String,
+    pub code: String,
     pub description: Option<String>,
+    pub details: Option<Value>,
 }
 
 /// Setup the Shurly app
 ///
+/// Setup the Shurly app, with cross-origin requests to the API
+pub async fn setup_test_app_with_cors(pool: sqlx::PgPool, cors: CorsLayer) -> Router {
+    setup_test_environment();
+
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool)).await;
+    ensure_initial_user(&database).await.unwrap();
+
+    let root_settings = RootSettings::from_environment().unwrap();
+
+    create_router(
+        database,
+        root_settings,
+        Limits::default(),
+        Some(cors),
+        Routes::All,
+    )
+}
+
 /// Inject some environment variables to match our tests
 pub async fn setup_test_app(pool: sqlx::PgPool) -> Router {
-    std::env::set_var("INITIAL_USERNAME", "admin");
-    std::env::set_var("INITIAL_PASSWORD", "verysecret");
-    std::env::set_var("JWT_SECRET", "verysecret");
+    setup_test_environment();
 
-    setup_app(DatabaseConfig::ExistingConnection(pool))
+    setup_app(Database::from_config(DatabaseConfig::ExistingConnection(pool)).await)
         .await
         .unwrap()
 }
 
-pub async fn root(app: &mut Router, slug: &str) -> (StatusCode, Option<String>, String) {
-    let request = Request::builder()
-        .method(Method::GET)
-        .uri(format!("/{slug}"))
-        .body(Body::empty())
-        .unwrap();
+/// Setup the Shurly app, with adjusted settings of the root
+///
+/// Settings not covered by the environment of the tests, like the rate limit
+pub async fn setup_test_app_with_root_settings(
+    pool: sqlx::PgPool,
+    configure: impl FnOnce(&mut RootSettings),
+) -> Router {
+    setup_test_environment();
+
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool)).await;
+    ensure_initial_user(&database).await.unwrap();
+
+    let mut root_settings = RootSettings::from_environment().unwrap();
+    configure(&mut root_settings);
+
+    create_router(
+        database,
+        root_settings,
+        Limits::default(),
+        None,
+        Routes::All,
+    )
+}
 
-    let response = app.call(request).await.unwrap();
+/// Setup the Shurly app, with only some of the routes, like on separate addresses
+pub async fn setup_test_app_with_routes(pool: sqlx::PgPool, routes: Routes) -> Router {
+    setup_test_environment();
 
-    let status_code = response.status();
-    let headers = response.headers();
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool)).await;
+    ensure_initial_user(&database).await.unwrap();
+
+    let root_settings = RootSettings::from_environment().unwrap();
+
+    create_router(database, root_settings, Limits::default(), None, routes)
+}
+
+/// Inject some environment variables to match our tests
+fn setup_test_environment() {
+    std::env::set_var("INITIAL_USERNAME", "admin");
+    std::env::set_var("INITIAL_PASSWORD", "verysecret");
+    std::env::set_var("JWT_SECRET", "verysecret");
+    std::env::set_var("STATS_CACHE_TIME_TO_LIVE", "0");
+}
+
+pub async fn root(app: &mut Router, slug: &str) -> (StatusCode, Option<String>, String) {
+    let (status_code, headers, body) = root_with_method(app, Method::GET, slug).await;
 
     let location = headers.get(LOCATION);
     let location = location.map(|header| header.to_str().unwrap().to_string());
 
+    (status_code, location, body)
+}
+
+pub async fn root_with_method(
+    app: &mut Router,
+    method: Method,
+    slug: &str,
+) -> (StatusCode, HeaderMap, String) {
+    root_with_headers(app, method, slug, &[]).await
+}
+
+pub async fn root_with_headers(
+    app: &mut Router,
+    method: Method,
+    slug: &str,
+    headers: &[(&str, &str)],
+) -> (StatusCode, HeaderMap, String) {
+    let mut request = Request::builder().method(method).uri(format!("/{slug}"));
+
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+
+    let request = request.body(Body::empty()).unwrap();
+
+    let response = app.call(request).await.unwrap();
+
+    let status_code = response.status();
+    let headers = response.headers().clone();
+
     let body = response.into_body().collect().await.unwrap().to_bytes();
     let body = String::from_utf8_lossy(&body[..]).to_string();
 
-    (status_code, location, body)
+    (status_code, headers, body)
 }
 
-pub async fn login_with_password(app: &mut Router, password: &str) -> String {
+pub async fn count_hits(pool: &sqlx::PgPool) -> i64 {
+    sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM hits")
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+pub async fn count_anonymous_hits(pool: &sqlx::PgPool) -> i64 {
+    sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM hits WHERE ip_address IS NULL AND user_agent IS NULL",
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+pub async fn login_as(app: &mut Router, username: &str, password: &str) -> String {
     let mut payload = Map::new();
-    payload.insert("username".to_string(), Value::String("admin".to_string()));
+    payload.insert("username".to_string(), Value::String(username.to_string()));
     payload.insert("password".to_string(), Value::String(password.to_string()));
 
     let request = Request::builder()
@@ -105,6 +231,10 @@ pub async fn login_with_password(app: &mut Router, password: &str) -> String {
     get_access_token(&body)
 }
 
+pub async fn login_with_password(app: &mut Router, password: &str) -> String {
+    login_as(app, "admin", password).await
+}
+
 pub async fn login(app: &mut Router) -> String {
     login_with_password(app, "verysecret").await
 }
@@ -150,6 +280,37 @@ pub async fn maybe_change_password(
     )
 }
 
+pub async fn conditional_get(
+    app: &mut Router,
+    access_token: &str,
+    uri: &str,
+    if_none_match: Option<&str>,
+) -> (StatusCode, Option<String>, Bytes) {
+    let mut request = Request::builder()
+        .method(Method::GET)
+        .uri(uri)
+        .header(AUTHORIZATION, access_token);
+
+    if let Some(if_none_match) = if_none_match {
+        request = request.header(IF_NONE_MATCH, if_none_match);
+    }
+
+    let response = app
+        .call(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status_code = response.status();
+
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .map(|etag| etag.to_str().unwrap().to_string());
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (status_code, etag, body)
+}
+
 pub async fn single_destination(
     app: &mut Router,
     access_token: &str,
@@ -252,6 +413,46 @@ pub async fn maybe_create_destination(
     maybe_create_destination_with_is_permanent(app, access_token, slug, url, false).await
 }
 
+pub async fn maybe_create_destination_on_host(
+    app: &mut Router,
+    access_token: &str,
+    host: &str,
+    slug: &str,
+    url: &str,
+) -> (StatusCode, Option<Destination>, Option<Error>) {
+    let mut payload = Map::new();
+    payload.insert("slug".to_string(), Value::String(slug.to_string()));
+    payload.insert("url".to_string(), Value::String(url.to_string()));
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/destinations")
+        .header(HOST, host)
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::CREATED {
+            Some(get_destination(&body))
+        } else {
+            None
+        },
+        if status_code == StatusCode::BAD_REQUEST {
+            Some(get_error(&body))
+        } else {
+            None
+        },
+    )
+}
+
 pub async fn maybe_create_destination_with_raw_body(
     app: &mut Router,
     access_token: &str,
@@ -278,7 +479,7 @@ pub async fn maybe_create_destination_with_raw_body(
 
     (
         status_code,
-        if status_code == StatusCode::CREATED {
+        if matches!(status_code, StatusCode::CREATED | StatusCode::OK) {
             Some(get_destination(&body))
         } else {
             None
@@ -324,6 +525,40 @@ pub async fn maybe_update_destination(
     )
 }
 
+pub async fn maybe_patch_destination(
+    app: &mut Router,
+    access_token: &str,
+    destination_id: &Uuid,
+    body: &'static str,
+) -> (StatusCode, Option<Value>, Option<String>) {
+    let request = Request::builder()
+        .method(Method::PATCH)
+        .uri(format!("/api/destinations/{destination_id}"))
+        .header(CONTENT_TYPE, "application/merge-patch+json")
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(body))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code == StatusCode::BAD_REQUEST {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
 pub async fn myabe_delete_destination(
     app: &mut Router,
     access_token: &str,
@@ -351,6 +586,77 @@ pub async fn myabe_delete_destination(
     )
 }
 
+pub async fn maybe_sign_destination(
+    app: &mut Router,
+    access_token: &str,
+    destination_id: &Uuid,
+    expires_in: Option<i64>,
+) -> (StatusCode, Option<String>, Option<String>) {
+    maybe_mint_link(
+        app,
+        access_token,
+        &format!("/api/destinations/{destination_id}/sign"),
+        expires_in,
+    )
+    .await
+}
+
+pub async fn maybe_mint_stats_link(
+    app: &mut Router,
+    access_token: &str,
+    destination_id: &Uuid,
+    expires_in: Option<i64>,
+) -> (StatusCode, Option<String>, Option<String>) {
+    maybe_mint_link(
+        app,
+        access_token,
+        &format!("/api/destinations/{destination_id}/stats-link"),
+        expires_in,
+    )
+    .await
+}
+
+async fn maybe_mint_link(
+    app: &mut Router,
+    access_token: &str,
+    uri: &str,
+    expires_in: Option<i64>,
+) -> (StatusCode, Option<String>, Option<String>) {
+    let mut payload = Map::new();
+    if let Some(expires_in) = expires_in {
+        payload.insert("expiresIn".to_string(), Value::from(expires_in));
+    }
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(uri)
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]["path"]
+                .as_str()
+                .map(ToString::to_string)
+        } else {
+            None
+        },
+        if status_code == StatusCode::BAD_REQUEST {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
 pub async fn maybe_create_note(
     app: &mut Router,
     access_token: &str,
@@ -712,122 +1018,1689 @@ pub async fn maybe_create_user(
     maybe_create_user_with_password(app, access_token, username, role, None).await
 }
 
-fn value_to_user(user: &Map<String, Value>) -> User {
-    User {
-        id: user["id"].as_str().map(Uuid::parse_str).unwrap().unwrap(),
-        username: user["username"].as_str().map(ToString::to_string).unwrap(),
-        role: user["role"].as_str().map(ToString::to_string).unwrap(),
-        password: user
-            .get("password")
-            .and_then(Value::as_str)
-            .map(ToString::to_string),
-    }
-}
+pub async fn list_domains(
+    app: &mut Router,
+    access_token: &str,
+) -> (StatusCode, Option<Vec<Domain>>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/domains")
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
 
-fn get_user(body: &Bytes) -> User {
-    serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]
-        .as_object()
-        .map(value_to_user)
-        .unwrap()
-}
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
 
-fn get_users(body: &Bytes) -> Vec<User> {
-    serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]
-        .as_array()
-        .unwrap()
-        .iter()
-        .map(|f| f.as_object().unwrap())
-        .map(value_to_user)
-        .collect()
-}
+    let body = response.into_body().collect().await.unwrap().to_bytes();
 
-fn value_to_destination(destination: &Map<String, Value>) -> Destination {
-    Destination {
-        id: destination["id"]
-            .as_str()
-            .map(Uuid::parse_str)
-            .unwrap()
-            .unwrap(),
-        slug: destination["slug"]
-            .as_str()
-            .map(ToString::to_string)
-            .unwrap(),
-        url: destination["url"]
-            .as_str()
-            .map(ToString::to_string)
-            .unwrap(),
-    }
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(get_domains(&body))
+        } else {
+            None
+        },
+    )
 }
 
-fn get_destination(body: &Bytes) -> Destination {
+pub async fn maybe_create_domain(
+    app: &mut Router,
+    access_token: &str,
+    body: &'static str,
+) -> (StatusCode, Option<Domain>, Option<String>) {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/domains")
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(body))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::CREATED {
+            Some(get_domain(&body))
+        } else {
+            None
+        },
+        if status_code == StatusCode::BAD_REQUEST {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_update_domain(
+    app: &mut Router,
+    access_token: &str,
+    id: &Uuid,
+    body: &'static str,
+) -> (StatusCode, Option<String>) {
+    let request = Request::builder()
+        .method(Method::PATCH)
+        .uri(format!("/api/domains/{id}"))
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(body))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::BAD_REQUEST {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_delete_domain(app: &mut Router, access_token: &str, id: &Uuid) -> StatusCode {
+    let request = Request::builder()
+        .method(Method::DELETE)
+        .uri(format!("/api/domains/{id}"))
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+
+    response.status()
+}
+
+pub async fn list_templates(app: &mut Router, access_token: &str) -> (StatusCode, Option<Value>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/templates")
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_create_template(
+    app: &mut Router,
+    access_token: &str,
+    body: &'static str,
+) -> (StatusCode, Option<Value>, Option<String>) {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/templates")
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(body))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::CREATED {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code == StatusCode::BAD_REQUEST {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_update_template(
+    app: &mut Router,
+    access_token: &str,
+    id: &str,
+    body: &'static str,
+) -> (StatusCode, Option<Value>) {
+    let request = Request::builder()
+        .method(Method::PATCH)
+        .uri(format!("/api/templates/{id}"))
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(body))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_delete_template(app: &mut Router, access_token: &str, id: &str) -> StatusCode {
+    let request = Request::builder()
+        .method(Method::DELETE)
+        .uri(format!("/api/templates/{id}"))
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+
+    response.status()
+}
+
+pub async fn cache_statistics(app: &mut Router, access_token: &str) -> (StatusCode, Option<Value>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/cache")
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn invalidate_cache(
+    app: &mut Router,
+    access_token: &str,
+    body: &'static str,
+) -> StatusCode {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/cache/invalidate")
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(body))
+        .unwrap();
+
+    app.call(request).await.unwrap().status()
+}
+
+pub async fn flush_cache(app: &mut Router, access_token: &str) -> StatusCode {
+    let request = Request::builder()
+        .method(Method::DELETE)
+        .uri("/api/cache")
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    app.call(request).await.unwrap().status()
+}
+
+pub async fn reload_config(app: &mut Router, access_token: &str) -> StatusCode {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/config/reload")
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    app.call(request).await.unwrap().status()
+}
+
+pub async fn version(app: &mut Router, access_token: &str) -> (StatusCode, Option<Value>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/version")
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn jobs(app: &mut Router, access_token: &str) -> (StatusCode, Option<Value>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/jobs")
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_trigger_archival(
+    app: &mut Router,
+    access_token: &str,
+    months: Option<i32>,
+) -> (StatusCode, Option<Value>, Option<String>) {
+    let mut payload = Map::new();
+    if let Some(months) = months {
+        payload.insert("months".to_string(), Value::from(months));
+    }
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/archival")
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::CREATED {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code == StatusCode::BAD_REQUEST {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn archival_runs(
+    app: &mut Router,
+    access_token: &str,
+    id: Option<&str>,
+) -> (StatusCode, Option<Value>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(id.map_or_else(|| "/api/archival".to_string(), |id| format!("/api/archival/{id}")))
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn export_backup(app: &mut Router, access_token: &str) -> (StatusCode, Option<Value>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/backup")
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap())
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_import_backup(
+    app: &mut Router,
+    access_token: &str,
+    archive: &Value,
+) -> (StatusCode, Option<Value>, Option<String>) {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/backup")
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(serde_json::to_vec(archive).unwrap()))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::CREATED {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code == StatusCode::BAD_REQUEST {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn list_webhooks(app: &mut Router, access_token: &str) -> (StatusCode, Option<Value>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/webhooks")
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_create_webhook(
+    app: &mut Router,
+    access_token: &str,
+    url: &str,
+) -> (StatusCode, Option<Value>, Option<String>) {
+    let mut payload = Map::new();
+    payload.insert("url".to_string(), Value::String(url.to_string()));
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/webhooks")
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::CREATED {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code == StatusCode::BAD_REQUEST {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_create_destination_webhook(
+    app: &mut Router,
+    access_token: &str,
+    url: &str,
+    destination_id: Option<&str>,
+    is_batched: bool,
+) -> (StatusCode, Option<Value>, Option<String>) {
+    let mut payload = Map::new();
+    payload.insert("url".to_string(), Value::String(url.to_string()));
+    if let Some(destination_id) = destination_id {
+        payload.insert(
+            "destinationId".to_string(),
+            Value::String(destination_id.to_string()),
+        );
+    }
+    payload.insert("isBatched".to_string(), Value::Bool(is_batched));
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/webhooks")
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::CREATED {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code == StatusCode::BAD_REQUEST {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_delete_webhook(app: &mut Router, access_token: &str, id: &str) -> StatusCode {
+    let request = Request::builder()
+        .method(Method::DELETE)
+        .uri(format!("/api/webhooks/{id}"))
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+
+    response.status()
+}
+
+pub async fn maybe_run_batch(
+    app: &mut Router,
+    access_token: &str,
+    body: Value,
+) -> (StatusCode, Option<Value>, Option<String>) {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/batch")
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(serde_json::to_vec(&body).unwrap()))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::CREATED {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code.is_client_error() {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn slack_command(
+    app: &mut Router,
+    secret: &[u8],
+    slack_user_id: &str,
+    text: &str,
+) -> (StatusCode, Option<String>) {
+    use hmac::Mac;
+
+    let body = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("command", "/shurly")
+        .append_pair("user_id", slack_user_id)
+        .append_pair("text", text)
+        .finish();
+
+    let timestamp = chrono::Utc::now().timestamp().to_string();
+    let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(secret).unwrap();
+    mac.update(format!("v0:{timestamp}:{body}").as_bytes());
+    let signature = format!("v0={:x}", mac.finalize().into_bytes());
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/integrations/slack")
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .header("x-slack-request-timestamp", timestamp)
+        .header("x-slack-signature", signature)
+        .body(Body::from(body))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            serde_json::from_slice::<Value>(&body[..]).unwrap()["text"]
+                .as_str()
+                .map(ToString::to_string)
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn webhook_deliveries(
+    app: &mut Router,
+    access_token: &str,
+    id: &str,
+) -> (StatusCode, Option<Value>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(format!("/api/webhooks/{id}/deliveries"))
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn graphql(
+    app: &mut Router,
+    access_token: &str,
+    query: &str,
+) -> (StatusCode, Option<Value>) {
+    let mut payload = Map::new();
+    payload.insert("query".to_string(), Value::String(query.to_string()));
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/graphql")
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap())
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn verify_audit_trail(
+    app: &mut Router,
+    access_token: &str,
+) -> (StatusCode, Option<AuditTrailVerification>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/audit-trail/verify")
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(get_audit_trail_verification(&body))
+        } else {
+            None
+        },
+    )
+}
+
+/// Open the stream of events, the body never ends by itself
+pub async fn stream_events(app: &mut Router, access_token: &str) -> (StatusCode, HeaderMap, Body) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/stream/events")
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+    let headers = response.headers().clone();
+
+    (status_code, headers, response.into_body())
+}
+
+pub async fn maybe_report_abuse(
+    app: &mut Router,
+    slug: &str,
+    body: &'static str,
+) -> (StatusCode, Option<String>) {
+    maybe_report_abuse_with_headers(app, slug, body, &[]).await
+}
+
+pub async fn maybe_report_abuse_with_headers(
+    app: &mut Router,
+    slug: &str,
+    body: &'static str,
+    headers: &[(&str, &str)],
+) -> (StatusCode, Option<String>) {
+    let mut request = Request::builder()
+        .method(Method::POST)
+        .uri(format!("/report/{slug}"))
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref());
+
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+
+    let request = request.body(Body::from(body)).unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code.is_client_error() {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn list_abuse_reports(
+    app: &mut Router,
+    access_token: &str,
+) -> (StatusCode, Option<Value>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/abuse-reports")
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_resolve_abuse_report(
+    app: &mut Router,
+    access_token: &str,
+    id: &str,
+    action: &str,
+) -> (StatusCode, Option<Value>, Option<String>) {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(format!("/api/abuse-reports/{id}/{action}"))
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code.is_client_error() {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_review_destination(
+    app: &mut Router,
+    access_token: &str,
+    id: &Uuid,
+    action: &str,
+) -> (StatusCode, Option<Value>, Option<String>) {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(format!("/api/destinations/{id}/{action}"))
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code.is_client_error() {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn get_usage(app: &mut Router, access_token: &str, user: &str) -> (StatusCode, Option<Value>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(format!("/api/users/{user}/usage"))
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_update_quota(
+    app: &mut Router,
+    access_token: &str,
+    id: &Uuid,
+    body: &str,
+) -> (StatusCode, Option<Value>, Option<String>) {
+    let request = Request::builder()
+        .method(Method::PUT)
+        .uri(format!("/api/users/{id}/quota"))
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code.is_client_error() {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn list_usage_counters(
+    app: &mut Router,
+    access_token: &str,
+    user: Option<&str>,
+) -> (StatusCode, Option<Value>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(user.map_or_else(|| "/api/usage".to_string(), |user| format!("/api/usage/{user}")))
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_create_user_with_body(
+    app: &mut Router,
+    access_token: &str,
+    body: &str,
+) -> (StatusCode, Option<Value>, Option<String>) {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/users")
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::CREATED {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code.is_client_error() {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_change_email(
+    app: &mut Router,
+    access_token: &str,
+    user: &str,
+    body: &str,
+) -> (StatusCode, Option<Value>, Option<String>) {
+    let request = Request::builder()
+        .method(Method::PUT)
+        .uri(format!("/api/users/{user}/email"))
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code.is_client_error() {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
+fn value_to_user(user: &Map<String, Value>) -> User {
+    User {
+        id: user["id"].as_str().map(Uuid::parse_str).unwrap().unwrap(),
+        username: user["username"].as_str().map(ToString::to_string).unwrap(),
+        role: user["role"].as_str().map(ToString::to_string).unwrap(),
+        password: user
+            .get("password")
+            .and_then(Value::as_str)
+            .map(ToString::to_string),
+    }
+}
+
+fn get_user(body: &Bytes) -> User {
+    serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]
+        .as_object()
+        .map(value_to_user)
+        .unwrap()
+}
+
+fn get_users(body: &Bytes) -> Vec<User> {
+    serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f.as_object().unwrap())
+        .map(value_to_user)
+        .collect()
+}
+
+fn value_to_destination(destination: &Map<String, Value>) -> Destination {
+    Destination {
+        id: destination["id"]
+            .as_str()
+            .map(Uuid::parse_str)
+            .unwrap()
+            .unwrap(),
+        slug: destination["slug"]
+            .as_str()
+            .map(ToString::to_string)
+            .unwrap(),
+        url: destination["url"]
+            .as_str()
+            .map(ToString::to_string)
+            .unwrap(),
+    }
+}
+
+fn get_destination(body: &Bytes) -> Destination {
+    serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]
+        .as_object()
+        .map(value_to_destination)
+        .unwrap()
+}
+
+fn get_destinations(body: &Bytes) -> Vec<Destination> {
+    serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f.as_object().unwrap())
+        .map(value_to_destination)
+        .collect()
+}
+
+fn value_to_domain(domain: &Map<String, Value>) -> Domain {
+    Domain {
+        id: domain["id"].as_str().map(Uuid::parse_str).unwrap().unwrap(),
+        hostname: domain["hostname"]
+            .as_str()
+            .map(ToString::to_string)
+            .unwrap(),
+    }
+}
+
+fn get_domain(body: &Bytes) -> Domain {
     serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]
         .as_object()
-        .map(value_to_destination)
+        .map(value_to_domain)
+        .unwrap()
+}
+
+fn get_domains(body: &Bytes) -> Vec<Domain> {
+    serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f.as_object().unwrap())
+        .map(value_to_domain)
+        .collect()
+}
+
+fn value_to_note(note: &Map<String, Value>) -> Note {
+    Note {
+        id: note["id"].as_str().map(Uuid::parse_str).unwrap().unwrap(),
+        content: note["content"].as_str().map(ToString::to_string).unwrap(),
+    }
+}
+
+fn get_note(body: &Bytes) -> Note {
+    serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]
+        .as_object()
+        .map(value_to_note)
+        .unwrap()
+}
+
+fn get_notes(body: &Bytes) -> Vec<Note> {
+    serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f.as_object().unwrap())
+        .map(value_to_note)
+        .collect()
+}
+
+fn get_audit_trail_verification(body: &Bytes) -> AuditTrailVerification {
+    let verification = &serde_json::from_slice::<Value>(&body[..]).unwrap()["data"];
+
+    AuditTrailVerification {
+        is_valid: verification["isValid"].as_bool().unwrap(),
+        verified_entries: verification["verifiedEntries"].as_u64().unwrap(),
+        first_invalid_entry_id: verification["firstInvalidEntryId"]
+            .as_str()
+            .map(Uuid::parse_str)
+            .transpose()
+            .unwrap(),
+    }
+}
+
+fn value_to_error(error: &Map<String, Value>) -> Error {
+    Error {
+        error: error["error"].as_str().map(ToString::to_string).unwrap(),
+        code: error["code"].as_str().map(ToString::to_string).unwrap(),
+        description: error
+            .get("description")
+            .and_then(Value::as_str)
+            .map(ToString::to_string),
+        details: error.get("details").cloned(),
+    }
+}
+
+fn get_error(body: &Bytes) -> Error {
+    serde_json::from_slice::<Value>(&body[..])
+        .unwrap()
+        .as_object()
+        .map(value_to_error)
+        .unwrap()
+}
+
+fn get_error_message(body: &Bytes) -> String {
+    serde_json::from_slice::<Value>(&body[..]).unwrap()["error"]
+        .as_str()
+        .map(ToString::to_string)
+        .unwrap()
+}
+
+fn get_access_token(body: &Bytes) -> String {
+    serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]["access_token"]
+        .as_str()
+        .map(|access_token| format!("Bearer {access_token}"))
         .unwrap()
 }
 
-fn get_destinations(body: &Bytes) -> Vec<Destination> {
-    serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]
-        .as_array()
-        .unwrap()
-        .iter()
-        .map(|f| f.as_object().unwrap())
-        .map(value_to_destination)
-        .collect()
+pub async fn list_reservations(app: &mut Router, access_token: &str) -> (StatusCode, Option<Value>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/reservations")
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_reserve_slugs(
+    app: &mut Router,
+    access_token: &str,
+    body: &'static str,
+) -> (StatusCode, Option<Value>, Option<String>) {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/reservations")
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(body))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::CREATED {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code == StatusCode::BAD_REQUEST {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_release_reservation(
+    app: &mut Router,
+    access_token: &str,
+    id: &str,
+) -> StatusCode {
+    let request = Request::builder()
+        .method(Method::DELETE)
+        .uri(format!("/api/reservations/{id}"))
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+
+    response.status()
+}
+
+pub async fn maybe_digest(
+    app: &mut Router,
+    access_token: &str,
+    method: Method,
+) -> (StatusCode, Option<Value>, Option<String>) {
+    let request = Request::builder()
+        .method(method)
+        .uri("/api/users/me/digest")
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code.is_client_error() {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_ingest_hits(
+    app: &mut Router,
+    api_key: Option<&str>,
+    body: &str,
+) -> (StatusCode, Option<Value>, Option<String>) {
+    let mut request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/hits/ingest")
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref());
+    if let Some(api_key) = api_key {
+        request = request.header("X-Shurly-Api-Key", api_key);
+    }
+    let request = request.body(Body::from(body.to_string())).unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code.is_client_error() {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_edge_snapshot(
+    app: &mut Router,
+    api_key: &str,
+    since: Option<&str>,
+) -> (StatusCode, Option<Value>, Option<String>) {
+    let uri = match since {
+        Some(since) => format!(
+            "/api/edge/snapshot?{}",
+            url::form_urlencoded::Serializer::new(String::new())
+                .append_pair("since", since)
+                .finish()
+        ),
+        None => "/api/edge/snapshot".to_string(),
+    };
+
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(uri)
+        .header("X-Shurly-Api-Key", api_key)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code.is_client_error() {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn maybe_create_destination_with_url_encoded_form(
+    app: &mut Router,
+    access_token: &str,
+    body: &str,
+) -> (StatusCode, Option<Destination>, Option<Error>) {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/destinations")
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::CREATED {
+            Some(get_destination(&body))
+        } else {
+            None
+        },
+        if status_code == StatusCode::BAD_REQUEST {
+            Some(get_error(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn api_error(
+    app: &mut Router,
+    method: Method,
+    uri: &str,
+    access_token: Option<&str>,
+    body: &'static str,
+) -> (StatusCode, Option<Error>) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref());
+    if let Some(access_token) = access_token {
+        request = request.header(AUTHORIZATION, access_token);
+    }
+    let request = request.body(Body::from(body)).unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code.is_client_error() {
+            Some(get_error(&body))
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn list_notes_page(
+    app: &mut Router,
+    access_token: &str,
+    destination_id: &Uuid,
+    query: &str,
+) -> (StatusCode, Option<Vec<Note>>, Option<String>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(format!("/api/destinations/{destination_id}/notes?{query}"))
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let total_count = response
+        .headers()
+        .get("x-total-count")
+        .map(|value| value.to_str().unwrap().to_string());
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(get_notes(&body))
+        } else {
+            None
+        },
+        total_count,
+    )
 }
 
-fn value_to_note(note: &Map<String, Value>) -> Note {
-    Note {
-        id: note["id"].as_str().map(Uuid::parse_str).unwrap().unwrap(),
-        content: note["content"].as_str().map(ToString::to_string).unwrap(),
-    }
+pub async fn search(
+    app: &mut Router,
+    access_token: &str,
+    query: &str,
+) -> (StatusCode, Option<Vec<Value>>, Option<String>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(format!(
+            "/api/search?{}",
+            url::form_urlencoded::Serializer::new(String::new())
+                .append_pair("q", query)
+                .finish()
+        ))
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]
+                .as_array()
+                .cloned()
+        } else {
+            None
+        },
+        if status_code.is_client_error() {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
 }
 
-fn get_note(body: &Bytes) -> Note {
-    serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]
-        .as_object()
-        .map(value_to_note)
-        .unwrap()
+pub async fn maybe_upsert_destination(
+    app: &mut Router,
+    access_token: &str,
+    slug: &str,
+    body: &str,
+) -> (StatusCode, Option<Value>, Option<Error>) {
+    let request = Request::builder()
+        .method(Method::PUT)
+        .uri(format!("/api/destinations/by-slug/{slug}"))
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(AUTHORIZATION, access_token)
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if matches!(status_code, StatusCode::CREATED | StatusCode::OK) {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code.is_client_error() {
+            Some(get_error(&body))
+        } else {
+            None
+        },
+    )
 }
 
-fn get_notes(body: &Bytes) -> Vec<Note> {
-    serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]
-        .as_array()
-        .unwrap()
-        .iter()
-        .map(|f| f.as_object().unwrap())
-        .map(value_to_note)
-        .collect()
+pub async fn destination_heatmap(
+    app: &mut Router,
+    access_token: &str,
+    destination_id: &Uuid,
+    query: &str,
+) -> (StatusCode, Option<Value>, Option<String>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(format!(
+            "/api/destinations/{destination_id}/stats/heatmap?{query}"
+        ))
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code.is_client_error() {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
 }
 
-fn value_to_error(error: &Map<String, Value>) -> Error {
-    Error {
-        error: error["error"].as_str().map(ToString::to_string).unwrap(),
-        description: error
-            .get("description")
-            .and_then(Value::as_str)
-            .map(ToString::to_string),
-    }
+pub async fn stats_sources(
+    app: &mut Router,
+    access_token: &str,
+    query: &str,
+) -> (StatusCode, Option<Vec<Value>>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri(format!("/api/stats/sources?{query}"))
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]
+                .as_array()
+                .cloned()
+        } else {
+            None
+        },
+    )
 }
 
-fn get_error(body: &Bytes) -> Error {
-    serde_json::from_slice::<Value>(&body[..])
-        .unwrap()
-        .as_object()
-        .map(value_to_error)
-        .unwrap()
+pub async fn audit_cache(
+    app: &mut Router,
+    access_token: &str,
+    query: &str,
+) -> (StatusCode, Option<Value>, Option<String>) {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(format!("/api/cache/audit?{query}"))
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+        if status_code.is_client_error() {
+            Some(get_error_message(&body))
+        } else {
+            None
+        },
+    )
 }
 
-fn get_error_message(body: &Bytes) -> String {
-    serde_json::from_slice::<Value>(&body[..]).unwrap()["error"]
-        .as_str()
-        .map(ToString::to_string)
-        .unwrap()
+pub async fn maybe_login_as(app: &mut Router, username: &str, password: &str) -> StatusCode {
+    let mut payload = Map::new();
+    payload.insert("username".to_string(), Value::String(username.to_string()));
+    payload.insert("password".to_string(), Value::String(password.to_string()));
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/users/token")
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .body(Body::from(serde_json::to_vec(&payload).unwrap()))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+
+    response.status()
 }
 
-fn get_access_token(body: &Bytes) -> String {
-    serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]["access_token"]
-        .as_str()
-        .map(|access_token| format!("Bearer {access_token}"))
-        .unwrap()
+pub async fn auth_metrics(app: &mut Router, access_token: &str) -> (StatusCode, Option<Value>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/auth/metrics")
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(serde_json::from_slice::<Value>(&body[..]).unwrap()["data"].clone())
+        } else {
+            None
+        },
+    )
+}
+
+pub async fn deleted_user_tokens(
+    app: &mut Router,
+    access_token: &str,
+) -> (StatusCode, Option<Vec<Value>>) {
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/api/auth/deleted-user-tokens")
+        .header(AUTHORIZATION, access_token)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    let status_code = response.status();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+
+    (
+        status_code,
+        if status_code == StatusCode::OK {
+            Some(
+                serde_json::from_slice::<Value>(&body[..]).unwrap()["data"]
+                    .as_array()
+                    .unwrap()
+                    .clone(),
+            )
+        } else {
+            None
+        },
+    )
 }
@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use axum::http::StatusCode;
+
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::hit_buffer::Flush;
+use crate::hit_buffer::HitBuffer;
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_hit_buffer(pool: sqlx::PgPool) {
+    let hits = HitBuffer::new(Some(Flush {
+        interval: Duration::from_secs(60 * 60),
+        size: 3,
+    }));
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |settings| {
+        settings.hits = hits.clone();
+    })
+    .await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool.clone())).await;
+    hits.start(&database);
+
+    let access_token = helper::login(&mut app).await;
+    helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+
+    for _ in 0..2 {
+        let (status_code, _, _) = helper::root(&mut app, "some-slug").await;
+        assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    }
+
+    // buffered, not saved yet
+    assert_eq!(0, helper::count_hits(&pool).await);
+
+    // a full buffer is saved right away
+    helper::root(&mut app, "some-slug").await;
+    for _ in 0..50 {
+        if helper::count_hits(&pool).await == 3 {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(3, helper::count_hits(&pool).await);
+
+    // like on a graceful shutdown
+    helper::root(&mut app, "some-slug").await;
+    hits.flush(&database).await;
+    assert_eq!(4, helper::count_hits(&pool).await);
+
+    // counted in the rollup of today right away
+    let destination = database
+        .find_single_destination_by_slug(None, "some-slug")
+        .await
+        .unwrap()
+        .unwrap();
+    let daily_hits = database.find_daily_hits(&destination).await.unwrap();
+    assert_eq!(1, daily_hits.len());
+    assert_eq!(4, daily_hits[0].hits);
+}
+
+#[sqlx::test]
+async fn test_hit_buffer_degraded(pool: sqlx::PgPool) {
+    let hits = HitBuffer::default();
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |settings| {
+        settings.hits = hits.clone();
+    })
+    .await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool.clone())).await;
+
+    let access_token = helper::login(&mut app).await;
+    helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+
+    // cached by the first redirect
+    let (status_code, _, _) = helper::root(&mut app, "some-slug").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(1, helper::count_hits(&pool).await);
+
+    // like an unreachable database, for the hits
+    sqlx::query("ALTER TABLE hits RENAME TO unreachable_hits")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    for _ in 0..2 {
+        let (status_code, location, _) = helper::root(&mut app, "some-slug").await;
+        assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+        assert_eq!(Some("https://www.example.com/".to_string()), location);
+    }
+
+    let (_, statistics) = helper::cache_statistics(&mut app, &access_token).await;
+    let statistics = statistics.unwrap();
+    assert!(statistics["degradedSince"].is_string());
+    assert_eq!(2, statistics["degradedRedirects"]);
+    assert_eq!(2, statistics["queuedHits"]);
+
+    sqlx::query("ALTER TABLE unreachable_hits RENAME TO hits")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // like the retry in the background
+    hits.flush(&database).await;
+    assert_eq!(3, helper::count_hits(&pool).await);
+
+    let (_, statistics) = helper::cache_statistics(&mut app, &access_token).await;
+    let statistics = statistics.unwrap();
+    assert!(statistics["degradedSince"].is_null());
+    assert_eq!(2, statistics["degradedRedirects"]);
+    assert_eq!(0, statistics["queuedHits"]);
+}
@@ -0,0 +1,88 @@
+use axum::http::HeaderValue;
+use axum::http::Method;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+
+use crate::destinations::Destination;
+use crate::hooks::HookRequest;
+use crate::hooks::RedirectHook;
+use crate::hooks::RedirectHooks;
+use crate::tests::helper;
+
+/// Deny the `blocked` slug before its lookup
+struct DenySlug;
+
+impl RedirectHook for DenySlug {
+    fn before_lookup(&self, request: &HookRequest<'_>) -> Option<Response> {
+        (request.slug == "blocked").then(|| StatusCode::FORBIDDEN.into_response())
+    }
+}
+
+/// Only allow destinations to `example.com` for visitors with the `X-Allowed` header
+struct RequireHeader;
+
+impl RedirectHook for RequireHeader {
+    fn after_resolve(
+        &self,
+        request: &HookRequest<'_>,
+        destination: &Destination,
+    ) -> Option<Response> {
+        (destination.url.contains("example.com") && !request.headers.contains_key("x-allowed"))
+            .then(|| StatusCode::UNAUTHORIZED.into_response())
+    }
+}
+
+/// Add the slug as header to the redirects
+struct InjectHeader;
+
+impl RedirectHook for InjectHeader {
+    fn before_redirect(
+        &self,
+        request: &HookRequest<'_>,
+        _destination: &Destination,
+        response: &mut Response,
+    ) {
+        response
+            .headers_mut()
+            .insert("x-slug", HeaderValue::from_str(request.slug).unwrap());
+    }
+}
+
+#[sqlx::test]
+async fn test_hooks(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |settings| {
+        settings.hooks = RedirectHooks::default()
+            .with(DenySlug)
+            .with(RequireHeader)
+            .with(InjectHeader);
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    for slug in ["blocked", "some-slug"] {
+        let (status_code, _, _) = helper::maybe_create_destination(
+            &mut app,
+            &access_token,
+            slug,
+            "https://www.example.com/",
+        )
+        .await;
+        assert_eq!(StatusCode::CREATED, status_code);
+    }
+
+    let (status_code, _, _) = helper::root(&mut app, "blocked").await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    // denied after the lookup, without recording a hit
+    let (status_code, _, _) = helper::root(&mut app, "some-slug").await;
+    assert_eq!(StatusCode::UNAUTHORIZED, status_code);
+    assert_eq!(0, helper::count_hits(&pool).await);
+
+    let (status_code, headers, _) =
+        helper::root_with_headers(&mut app, Method::GET, "some-slug", &[("x-allowed", "1")]).await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!("some-slug", headers.get("x-slug").unwrap());
+    assert_eq!(1, helper::count_hits(&pool).await);
+}
@@ -0,0 +1,93 @@
+use axum::http::StatusCode;
+use chrono::Days;
+use chrono::NaiveDate;
+use chrono::Utc;
+
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::import;
+use crate::shlink;
+use crate::tests::helper;
+use crate::yourls;
+
+#[sqlx::test]
+async fn test_import_yourls_with_clicks(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool)).await;
+    let admin = database
+        .find_single_user_by_username("admin")
+        .await
+        .unwrap()
+        .unwrap();
+
+    let sql = "INSERT INTO `yourls_url` VALUES \
+        ('ozh','https://ozh.org/','Ozh','2020-01-02 03:04:05','127.0.0.1',12),\
+        ('none','https://www.example.com/none','None','2020-01-03 03:04:05','127.0.0.1',0),\
+        ('wrong','https://www.example.com/wrong','Wrong','2020-01-04 03:04:05','127.0.0.1',-1);";
+
+    let links = yourls::from_sql(sql).unwrap();
+    let report = import::import(&database, &links, &admin, None)
+        .await
+        .unwrap();
+
+    assert_eq!(2, report.destinations.len());
+    assert_eq!(1, report.conflicts.len());
+    assert_eq!("Invalid number of clicks: -1", report.conflicts[0].reason);
+
+    let daily_hits = database
+        .find_daily_hits(&report.destinations[0])
+        .await
+        .unwrap();
+    assert_eq!(1, daily_hits.len());
+    assert_eq!(
+        NaiveDate::from_ymd_opt(2020, 1, 2).unwrap(),
+        daily_hits[0].day
+    );
+    assert_eq!(12, daily_hits[0].hits);
+
+    let daily_hits = database
+        .find_daily_hits(&report.destinations[1])
+        .await
+        .unwrap();
+    assert!(daily_hits.is_empty());
+
+    let (status_code, location, _) = helper::root(&mut app, "ozh").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!("https://ozh.org/", location.unwrap());
+}
+
+#[sqlx::test]
+async fn test_import_shlink_created_today(pool: sqlx::PgPool) {
+    let _app = helper::setup_test_app(pool.clone()).await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool)).await;
+    let admin = database
+        .find_single_user_by_username("admin")
+        .await
+        .unwrap()
+        .unwrap();
+
+    let today = Utc::now().date_naive();
+    let csv = format!(
+        "createdAt,shortUrl,longUrl,title,tags,visits\n\
+        {today},https://s.test/abc,https://www.example.com/,,,7\n"
+    );
+
+    let links = shlink::from_csv(csv.as_bytes()).unwrap();
+    let report = import::import(&database, &links, &admin, None)
+        .await
+        .unwrap();
+
+    assert_eq!(1, report.destinations.len());
+
+    // today is left to the rollups of the hits
+    let daily_hits = database
+        .find_daily_hits(&report.destinations[0])
+        .await
+        .unwrap();
+    assert_eq!(1, daily_hits.len());
+    assert_eq!(
+        today.checked_sub_days(Days::new(1)).unwrap(),
+        daily_hits[0].day
+    );
+    assert_eq!(7, daily_hits[0].hits);
+}
@@ -0,0 +1,94 @@
+use axum::http::StatusCode;
+
+use crate::ingest::IngestKeys;
+use crate::tests::helper;
+
+const API_KEY: &str = "edge-key-edge-key-edge-key-edge-key";
+
+#[sqlx::test]
+async fn test_ingest(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |_| {}).await;
+
+    let (status_code, _, _) =
+        helper::maybe_ingest_hits(&mut app, Some(API_KEY), r#"{ "hits": [] }"#).await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |settings| {
+        settings.ingest_keys = IngestKeys::new(&[("cdn", API_KEY)]);
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    // the tokens of users are no API keys
+    let (status_code, _, message) =
+        helper::maybe_ingest_hits(&mut app, Some(&access_token), r#"{ "hits": [] }"#).await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+    assert_eq!(Some("Invalid API key".to_string()), message);
+
+    let (status_code, _, _) = helper::maybe_ingest_hits(&mut app, None, r#"{ "hits": [] }"#).await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "launch",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, _, message) = helper::maybe_ingest_hits(
+        &mut app,
+        Some(API_KEY),
+        r#"{ "hits": [
+            { "slug": "launch" },
+            { "slug": "launch", "createdAt": "2999-01-01T00:00:00Z" }
+        ] }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Hit 1: Hit in the future".to_string()), message);
+
+    let (status_code, ingested, _) = helper::maybe_ingest_hits(
+        &mut app,
+        Some(API_KEY),
+        r#"{ "hits": [
+            { "slug": "/launch", "ipAddress": "192.0.2.1", "userAgent": "Mozilla/5.0",
+                "referrer": "https://www.news.example.com/article?id=1" },
+            { "slug": "launch", "ipAddress": "192.0.2.2", "userAgent": "Mozilla/5.0",
+                "doNotTrack": true },
+            { "slug": "launch", "createdAt": "2026-01-01T12:00:00.123456789Z" },
+            { "slug": "unknown" }
+        ] }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    let ingested = ingested.unwrap();
+    assert_eq!(3, ingested["ingested"]);
+    assert_eq!(1, ingested["skipped"]);
+
+    let hits = sqlx::query_as::<_, (Option<String>, Option<String>, Option<String>)>(
+        "SELECT host(ip_address), user_agent, referrer FROM hits ORDER BY created_at DESC",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(3, hits.len());
+    assert!(hits.contains(&(
+        Some("192.0.2.1".to_string()),
+        Some("Mozilla/5.0".to_string()),
+        Some("news.example.com".to_string())
+    )));
+    // anonymized, like the root does by default
+    assert_eq!(2, hits.iter().filter(|hit| hit.0.is_none()).count());
+
+    // counted in the rollups right away, on the day of the hit
+    let rollups = sqlx::query_as::<_, (i64,)>(
+        "SELECT hits::BIGINT FROM hit_rollups WHERE day = '2026-01-01'",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(vec![(1,)], rollups);
+}
@@ -0,0 +1,84 @@
+use axum::http::StatusCode;
+
+use crate::database::CreateUserValues;
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::password::hash;
+use crate::tests::helper;
+use crate::users::InitialUsers;
+use crate::users::Role;
+
+#[sqlx::test]
+async fn test_initial_users(pool: sqlx::PgPool) {
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool.clone())).await;
+
+    let initial_users = format!(
+        r#"{{
+            "users": [
+                {{ "username": "ops", "role": "admin", "hashedPassword": "{}" }},
+                {{ "username": "editor", "hashedPassword": "{}", "email": "editor@example.com" }}
+            ]
+        }}"#,
+        hash("verysecret"),
+        hash("editorsecret"),
+    );
+
+    let path = std::env::temp_dir().join(format!("shurly-{}.json", uuid::Uuid::new_v4()));
+    std::fs::write(&path, initial_users).unwrap();
+
+    let initial_users = InitialUsers::from_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(Some(2), initial_users.create(&database).await.unwrap());
+
+    // only on the first run
+    assert_eq!(None, initial_users.create(&database).await.unwrap());
+
+    // the database has users, the single initial user is skipped
+    let mut app = helper::setup_test_app(pool).await;
+
+    let status_code = helper::maybe_login_as(&mut app, "admin", "verysecret").await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+
+    let access_token = helper::login_as(&mut app, "ops", "verysecret").await;
+    let (status_code, users) = helper::list_users(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let users = users.unwrap();
+    assert_eq!(2, users.len());
+    assert!(users.iter().any(|user| user.username == "editor"));
+
+    // managers by default
+    let editor_access_token = helper::login_as(&mut app, "editor", "editorsecret").await;
+    let (status_code, _) = helper::list_users(&mut app, &editor_access_token).await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+}
+
+#[sqlx::test]
+async fn test_initial_users_all_or_nothing(pool: sqlx::PgPool) {
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool.clone())).await;
+
+    let hashed_password = hash("verysecret");
+    let session_id = uuid::Uuid::new_v4();
+    let user = |username| CreateUserValues {
+        session_id: &session_id,
+        role: Role::Admin,
+        username,
+        hashed_password: &hashed_password,
+        email: None,
+    };
+
+    // the second user fails, the first one is not kept either
+    assert!(database
+        .create_initial_users(1, &[user("ops"), user("ops")])
+        .await
+        .is_err());
+    assert!(database.find_any_single_user().await.unwrap().is_none());
+
+    assert_eq!(
+        Some(2),
+        database
+            .create_initial_users(1, &[user("ops"), user("editor")])
+            .await
+            .unwrap()
+    );
+}
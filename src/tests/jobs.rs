@@ -0,0 +1,199 @@
+use std::time::Duration;
+
+use axum::http::StatusCode;
+
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::jobs::Job;
+use crate::jobs::Jobs;
+use crate::jobs::Outcome;
+use crate::tests::helper;
+
+async fn backdate_hits(pool: &sqlx::PgPool, count: i64, days: i32) {
+    sqlx::query(
+        r"
+        UPDATE hits
+        SET created_at = CURRENT_TIMESTAMP - make_interval(days => $1)
+        WHERE id IN (
+            SELECT id FROM hits WHERE created_at > CURRENT_TIMESTAMP - interval '1 day' LIMIT $2
+        )
+        ",
+    )
+    .bind(days)
+    .bind(count)
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+async fn count_rollups(pool: &sqlx::PgPool) -> (i64, i64) {
+    sqlx::query_as::<_, (i64, i64)>(
+        "SELECT COUNT(*), COALESCE(SUM(hits), 0)::BIGINT FROM hit_rollups",
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+#[sqlx::test]
+async fn test_jobs(pool: sqlx::PgPool) {
+    let jobs = Jobs::new(Some(30), None, None);
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |settings| {
+        settings.jobs = jobs.clone();
+    })
+    .await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool.clone())).await;
+
+    let (status_code, _) = helper::jobs(&mut app, "").await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    for _ in 0..4 {
+        helper::root(&mut app, "some-slug").await;
+    }
+
+    // the hits are counted right away
+    assert_eq!((1, 4), count_rollups(&pool).await);
+
+    // a hit of long ago, and one of yesterday that was not counted, like from an older instance
+    backdate_hits(&pool, 1, 40).await;
+    sqlx::query(
+        r"
+        INSERT INTO hits (id, destination_id, created_at)
+        SELECT gen_random_uuid(), id, CURRENT_DATE - interval '12 hours'
+        FROM destinations
+        ",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let outcomes = jobs.run_all(&database).await;
+    assert_eq!(
+        vec![
+            (
+                Job::HealthCheck,
+                Outcome::Succeeded("Database is ready".to_string())
+            ),
+            (
+                Job::RollupHits,
+                Outcome::Succeeded("Counted 2 day(s) of destinations".to_string())
+            ),
+            (
+                Job::BatchHitWebhooks,
+                Outcome::Succeeded("Queued 0 delivery(ies) of hits".to_string())
+            ),
+            (
+                Job::PruneHits { retention_days: 30 },
+                Outcome::Succeeded("Deleted 1 hit(s)".to_string())
+            ),
+        ],
+        outcomes
+    );
+
+    // the pruned hit is still counted, the missed hit is caught up on
+    assert_eq!(4, helper::count_hits(&pool).await);
+    assert_eq!((2, 5), count_rollups(&pool).await);
+
+    // running again counts the days again, without counting them twice
+    jobs.run_all(&database).await;
+    assert_eq!((2, 5), count_rollups(&pool).await);
+
+    let (status_code, statuses) = helper::jobs(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let statuses = statuses.unwrap();
+    assert_eq!(4, statuses.as_array().unwrap().len());
+    assert_eq!("rollup-hits", statuses[1]["name"]);
+    assert_eq!(2, statuses[1]["runs"]);
+    assert_eq!("succeeded", statuses[1]["lastOutcome"]);
+    assert_eq!("Deleted 0 hit(s)", statuses[3]["lastDescription"]);
+}
+
+#[sqlx::test]
+async fn test_jobs_leader(pool: sqlx::PgPool) {
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool.clone())).await;
+
+    // two instances on the same database
+    let leader = Jobs::new(None, None, None);
+    let follower = Jobs::new(None, None, None);
+
+    let outcomes = leader.run_all(&database).await;
+    assert!(leader.is_leader());
+    assert!(matches!(outcomes[0].1, Outcome::Succeeded(_)));
+
+    let outcomes = follower.run_all(&database).await;
+    assert!(!follower.is_leader());
+    assert_eq!(Outcome::Skipped, outcomes[0].1);
+    assert_eq!(Outcome::Skipped, outcomes[1].1);
+
+    leader.resign().await;
+    follower.run_all(&database).await;
+    assert!(follower.is_leader());
+
+    // the connection of the leader is lost, like when the instance dies
+    sqlx::query(
+        r"
+        SELECT pg_terminate_backend(pid, 5000)
+        FROM pg_locks
+        WHERE locktype = 'advisory'
+            AND database = (SELECT oid FROM pg_database WHERE datname = current_database())
+        ",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let outcomes = leader.run_all(&database).await;
+    assert!(leader.is_leader());
+    assert!(matches!(outcomes[0].1, Outcome::Succeeded(_)));
+
+    follower.run_all(&database).await;
+    assert!(!follower.is_leader());
+}
+
+#[sqlx::test]
+async fn test_jobs_leader_shutdown(pool: sqlx::PgPool) {
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool.clone())).await;
+
+    // an instance with a connection pool of its own, shutting down like `serve` does
+    let instance_pool = sqlx::PgPool::connect_with((*pool.connect_options()).clone())
+        .await
+        .unwrap();
+    let instance_database =
+        Database::from_config(DatabaseConfig::ExistingConnection(instance_pool)).await;
+
+    let leader = Jobs::new(None, None, None);
+    let follower = Jobs::new(None, None, None);
+
+    leader.start(&instance_database);
+    while !leader.is_leader() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    follower.run_all(&database).await;
+    assert!(!follower.is_leader());
+
+    // the lock is released once the pool is closed, not when the server notices
+    instance_database.close().await;
+
+    for _ in 0..100 {
+        follower.run_all(&database).await;
+        if follower.is_leader() {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(follower.is_leader());
+    assert!(!leader.is_leader());
+}
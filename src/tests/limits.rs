@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use axum::http::StatusCode;
+
+use crate::limits::DatabaseDeadline;
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_limits_body(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let url = format!("https://www.example.com/?q={}", "a".repeat(128 * 1024));
+
+    let (status_code, destination, error) =
+        helper::maybe_create_destination(&mut app, &access_token, "some-slug", &url).await;
+    assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, status_code);
+    assert!(destination.is_none());
+    assert!(error.is_none());
+
+    // just below the limit
+    let url = format!("https://www.example.com/?q={}", "a".repeat(32 * 1024));
+
+    let (status_code, _, _) =
+        helper::maybe_create_destination(&mut app, &access_token, "some-slug", &url).await;
+    assert_eq!(StatusCode::CREATED, status_code);
+}
+
+#[sqlx::test]
+async fn test_limits_database_deadline(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |settings| {
+        settings.database_deadline =
+            DatabaseDeadline::default().with_deadline(Duration::from_millis(200));
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    helper::maybe_create_destination(&mut app, &access_token, "slow", "https://www.example.com/")
+        .await;
+
+    // a slow database, the lookup waits for the lock
+    let mut transaction = pool.begin().await.unwrap();
+    sqlx::query("LOCK TABLE destinations IN ACCESS EXCLUSIVE MODE")
+        .execute(&mut *transaction)
+        .await
+        .unwrap();
+
+    let (status_code, location, body) = helper::root(&mut app, "slow").await;
+    assert_eq!(StatusCode::SERVICE_UNAVAILABLE, status_code);
+    assert!(location.is_none());
+    assert!(body.contains("Service unavailable"));
+
+    transaction.rollback().await.unwrap();
+
+    let (status_code, location, _) = helper::root(&mut app, "slow").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(Some("https://www.example.com/".to_string()), location);
+}
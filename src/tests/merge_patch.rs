@@ -0,0 +1,58 @@
+use axum::http::StatusCode;
+use serde_json::Value;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_merge_patch_destination(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "abc",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let destination_id = destination.unwrap().id;
+
+    let (status_code, destination, _) = helper::maybe_patch_destination(
+        &mut app,
+        &access_token,
+        &destination_id,
+        r#"{ "ogTitle": "Launch", "ogDescription": "The launch" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    let destination = destination.unwrap();
+    assert_eq!("Launch", destination["ogTitle"]);
+    assert_eq!("The launch", destination["ogDescription"]);
+
+    // omitted fields are not touched, `null` removes the value
+    let (status_code, destination, _) = helper::maybe_patch_destination(
+        &mut app,
+        &access_token,
+        &destination_id,
+        r#"{ "ogTitle": null }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    let destination = destination.unwrap();
+    assert_eq!(Value::Null, destination["ogTitle"]);
+    assert_eq!("The launch", destination["ogDescription"]);
+    assert_eq!("https://www.example.com/", destination["url"]);
+
+    // the URL can not be removed
+    let (status_code, _, error) = helper::maybe_patch_destination(
+        &mut app,
+        &access_token,
+        &destination_id,
+        r#"{ "url": null }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("`url` can not be removed", error.unwrap());
+}
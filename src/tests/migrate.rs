@@ -0,0 +1,27 @@
+use crate::cli;
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+
+#[sqlx::test(migrations = false)]
+async fn test_migrate(pool: sqlx::PgPool) {
+    let database =
+        Database::from_config_without_migrations(DatabaseConfig::ExistingConnection(pool)).await;
+
+    let pending_migrations = database.find_pending_migrations().await.unwrap();
+    assert!(!pending_migrations.is_empty());
+    assert!(!database.has_current_migrations().await.unwrap());
+
+    // a dry run leaves the migrations pending
+    cli::migrate(&database, true).await.unwrap();
+    assert_eq!(
+        pending_migrations,
+        database.find_pending_migrations().await.unwrap()
+    );
+
+    cli::migrate(&database, false).await.unwrap();
+    assert!(database.find_pending_migrations().await.unwrap().is_empty());
+    assert!(database.has_current_migrations().await.unwrap());
+
+    // nothing left to run
+    cli::migrate(&database, false).await.unwrap();
+}
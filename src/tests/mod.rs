@@ -1,13 +1,74 @@
+mod abuse_reports;
+mod accounting;
+mod approval;
+mod archival;
+mod audit_trail;
+mod auth_metrics;
+mod backup;
+mod batch;
+mod bitly;
+mod broker;
 mod change_password;
+mod cli;
+mod client_ip;
+mod compression;
+mod cors;
+mod deleted_user_tokens;
 mod destination;
 mod destination_create;
 mod destination_delete_is_permanent;
+mod destination_headers;
+mod destination_templates;
 mod destination_update;
 mod destination_update_is_permanent;
+mod digests;
+mod domains;
+mod edge;
 mod emoji;
+mod error_codes;
+mod etag;
+mod graphql;
+mod hashed_slugs;
+mod health;
+mod heatmap;
 mod helper;
+mod hit_buffer;
+mod hooks;
+mod import;
+mod ingest;
+mod initial_users;
 mod invalid_json;
+mod jobs;
+mod limits;
 mod login;
+mod merge_patch;
+mod migrate;
 mod notes;
+mod notifications;
+mod permissions;
+mod preview;
+mod private;
+mod purge;
+mod quotas;
+mod public_stats;
+mod rate_limit;
+mod redirect_loops;
+mod reload;
+mod reservations;
 mod root;
+mod routes;
+mod scripts;
+mod search;
+mod seed;
+mod slack;
+mod slug_cache;
+mod sources;
+mod stats_cache;
+mod stream;
+mod untracked;
+mod upsert;
+mod url_encoded_form;
+mod url_reputation;
 mod users;
+mod version;
+mod webhooks;
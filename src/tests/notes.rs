@@ -118,3 +118,112 @@ async fn test_note_invalid_id(pool: sqlx::PgPool) {
     assert_eq!(StatusCode::BAD_REQUEST, status_code);
     assert_eq!(Some("Invalid path parameter".to_string()), error);
 }
+
+#[sqlx::test]
+async fn test_notes_deleted_with_destination(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (_, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+    let destination = destination.unwrap();
+
+    let (_, note, _) =
+        helper::maybe_create_note(&mut app, &access_token, &destination.id, "Deleted before").await;
+    helper::maybe_create_note(&mut app, &access_token, &destination.id, "Deleted along").await;
+
+    helper::myabe_delete_note(&mut app, &access_token, &destination.id, &note.unwrap().id).await;
+
+    let (status_code, _) =
+        helper::myabe_delete_destination(&mut app, &access_token, &destination.id).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    // notes deleted along have the deletion date of the destination
+    let notes = sqlx::query_as::<_, (String, bool)>(
+        r"
+        SELECT notes.content, notes.deleted_at = destinations.deleted_at
+        FROM notes
+        INNER JOIN destinations ON destinations.id = notes.destination_id
+        ORDER BY notes.created_at ASC
+        ",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        vec![
+            ("Deleted before".to_string(), false),
+            ("Deleted along".to_string(), true)
+        ],
+        notes
+    );
+}
+
+#[sqlx::test]
+async fn test_notes_pagination(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (_, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "abc",
+        "https://www.example.com/",
+    )
+    .await;
+    let destination = destination.unwrap();
+
+    for index in 1..=5 {
+        let (status_code, _, _) = helper::maybe_create_note(
+            &mut app,
+            &access_token,
+            &destination.id,
+            &format!("Note {index}"),
+        )
+        .await;
+        assert_eq!(StatusCode::CREATED, status_code);
+    }
+
+    // all notes without pagination
+    let (status_code, notes, total_count) =
+        helper::list_notes_page(&mut app, &access_token, &destination.id, "").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(5, notes.unwrap().len());
+    assert_eq!(Some("5".to_string()), total_count);
+
+    let (status_code, notes, total_count) =
+        helper::list_notes_page(&mut app, &access_token, &destination.id, "limit=2").await;
+    assert_eq!(StatusCode::OK, status_code);
+    let notes = notes.unwrap();
+    assert_eq!(
+        vec!["Note 5", "Note 4"],
+        notes
+            .iter()
+            .map(|note| note.content.as_str())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(Some("5".to_string()), total_count);
+
+    let (status_code, notes, _) =
+        helper::list_notes_page(&mut app, &access_token, &destination.id, "page=3&limit=2").await;
+    assert_eq!(StatusCode::OK, status_code);
+    let notes = notes.unwrap();
+    assert_eq!(1, notes.len());
+    assert_eq!("Note 1", notes[0].content);
+
+    let (status_code, notes, _) =
+        helper::list_notes_page(&mut app, &access_token, &destination.id, "page=4&limit=2").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert!(notes.unwrap().is_empty());
+
+    let (status_code, _, _) =
+        helper::list_notes_page(&mut app, &access_token, &destination.id, "page=0").await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+}
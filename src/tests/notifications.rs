@@ -0,0 +1,254 @@
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use serde_json::Value;
+
+use crate::approval::Approval;
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::jobs::Jobs;
+use crate::notifier::Notifier;
+use crate::notifier::Smtp;
+use crate::reputation::UrlReputation;
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_notifications(pool: sqlx::PgPool) {
+    // never started, the emails stay in the queue
+    let notifier = Notifier::new(
+        Arc::new(Smtp::parse("smtp://localhost:2525").unwrap()),
+        "Shurly <shurly@example.com>",
+    );
+
+    let mut app = helper::setup_test_app_with_root_settings(pool, |settings| {
+        settings.notifier = notifier.clone();
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, message) = helper::maybe_create_user_with_body(
+        &mut app,
+        &access_token,
+        r#"{ "username": "manager", "role": "manager", "email": "not an email" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Invalid email address".to_string()), message);
+
+    let (status_code, user, _) = helper::maybe_create_user_with_body(
+        &mut app,
+        &access_token,
+        r#"{ "username": "manager", "role": "manager", "password": "verysecret",
+            "email": " manager@example.com " }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let user = user.unwrap();
+    assert_eq!("manager@example.com", user["email"]);
+
+    let emails = notifier.take_queued().await;
+    assert_eq!(1, emails.len());
+    assert_eq!("Shurly <shurly@example.com>", emails[0].from);
+    assert_eq!("manager@example.com", emails[0].to);
+    assert_eq!("You are invited to Shurly", emails[0].subject);
+    assert!(emails[0].body.contains("as a manager"));
+
+    let manager_access_token = helper::login_as(&mut app, "manager", "verysecret").await;
+
+    let (status_code, _, _) =
+        helper::maybe_change_password(&mut app, &manager_access_token, "verysecret", "secret")
+            .await;
+    assert_eq!(StatusCode::OK, status_code);
+
+    let emails = notifier.take_queued().await;
+    assert_eq!(1, emails.len());
+    assert_eq!("Your password is changed", emails[0].subject);
+
+    // the admin has no email address, nothing to notify
+    let (status_code, _, _) =
+        helper::maybe_change_password(&mut app, &access_token, "verysecret", "verysecret").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert!(notifier.take_queued().await.is_empty());
+
+    // only admins change the email address of others
+    let manager_access_token = helper::login_as(&mut app, "manager", "secret").await;
+    let access_token = helper::login(&mut app).await;
+    let admin = helper::current_user(&mut app, &access_token)
+        .await
+        .1
+        .unwrap();
+
+    let (status_code, _, _) = helper::maybe_change_email(
+        &mut app,
+        &manager_access_token,
+        &admin.id.to_string(),
+        r#"{ "email": "admin@example.com" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    let (status_code, user, _) = helper::maybe_change_email(
+        &mut app,
+        &manager_access_token,
+        "me",
+        r#"{ "email": null }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(Value::Null, user.unwrap()["email"]);
+
+    let (status_code, user, _) = helper::maybe_change_email(
+        &mut app,
+        &access_token,
+        "me",
+        r#"{ "email": "admin@example.com" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!("admin@example.com", user.unwrap()["email"]);
+
+    let (_, verification) = helper::verify_audit_trail(&mut app, &access_token).await;
+    assert!(verification.unwrap().is_valid);
+}
+
+#[sqlx::test]
+async fn test_notifications_flagged_destination(pool: sqlx::PgPool) {
+    let notifier = Notifier::new(
+        Arc::new(Smtp::parse("smtp://localhost:2525").unwrap()),
+        "shurly@example.com",
+    );
+
+    let path = std::env::temp_dir().join(format!("shurly-{}.txt", uuid::Uuid::new_v4()));
+    std::fs::write(&path, "evil.example.com\n").unwrap();
+
+    let url_reputation = UrlReputation::default().with_blocklist(&path);
+    let jobs = Jobs::new(None, None, None)
+        .with_url_reputation(url_reputation)
+        .with_notifier(notifier.clone());
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |settings| {
+        settings.notifier = notifier.clone();
+    })
+    .await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool)).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_change_email(
+        &mut app,
+        &access_token,
+        "me",
+        r#"{ "email": "admin@example.com" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "login",
+        "https://www.evil.example.com/login",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    jobs.run_all(&database).await;
+
+    let emails = notifier.take_queued().await;
+    assert_eq!(1, emails.len());
+    assert_eq!("admin@example.com", emails[0].to);
+    assert_eq!("Destination login is disabled", emails[0].subject);
+    assert!(emails[0]
+        .body
+        .contains("https://www.evil.example.com/login"));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[sqlx::test]
+async fn test_notifications_approval(pool: sqlx::PgPool) {
+    let notifier = Notifier::new(
+        Arc::new(Smtp::parse("smtp://localhost:2525").unwrap()),
+        "shurly@example.com",
+    );
+
+    let mut app = helper::setup_test_app_with_root_settings(pool, |settings| {
+        settings.notifier = notifier.clone();
+        settings.approval = Approval::Managers;
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_change_email(
+        &mut app,
+        &access_token,
+        "me",
+        r#"{ "email": "admin@example.com" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+
+    let (status_code, _, _) = helper::maybe_create_user_with_body(
+        &mut app,
+        &access_token,
+        r#"{ "username": "manager", "role": "manager", "password": "verysecret",
+            "email": "manager@example.com" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    notifier.take_queued().await;
+
+    let manager_access_token = helper::login_as(&mut app, "manager", "verysecret").await;
+
+    // the admins review the destinations of managers
+    let (_, launch, _) = helper::maybe_create_destination(
+        &mut app,
+        &manager_access_token,
+        "launch",
+        "https://www.example.com/launch",
+    )
+    .await;
+    let launch_id = launch.unwrap().id;
+
+    let emails = notifier.take_queued().await;
+    assert_eq!(1, emails.len());
+    assert_eq!("admin@example.com", emails[0].to);
+    assert_eq!("Destination launch awaits your approval", emails[0].subject);
+    assert!(emails[0].body.contains("https://www.example.com/launch"));
+    assert!(emails[0].body.contains("of manager"));
+
+    // the creator hears back
+    let (status_code, _, _) =
+        helper::maybe_review_destination(&mut app, &access_token, &launch_id, "approve").await;
+    assert_eq!(StatusCode::OK, status_code);
+
+    let emails = notifier.take_queued().await;
+    assert_eq!(1, emails.len());
+    assert_eq!("manager@example.com", emails[0].to);
+    assert_eq!("Destination launch is approved", emails[0].subject);
+
+    let (_, giveaway, _) = helper::maybe_create_destination(
+        &mut app,
+        &manager_access_token,
+        "giveaway",
+        "https://www.example.com/giveaway",
+    )
+    .await;
+    let giveaway_id = giveaway.unwrap().id;
+    notifier.take_queued().await;
+
+    let (status_code, _, _) =
+        helper::maybe_review_destination(&mut app, &access_token, &giveaway_id, "reject").await;
+    assert_eq!(StatusCode::OK, status_code);
+
+    let emails = notifier.take_queued().await;
+    assert_eq!(1, emails.len());
+    assert_eq!("manager@example.com", emails[0].to);
+    assert_eq!("Destination giveaway is rejected", emails[0].subject);
+
+    // destinations of admins need no approval, nothing to notify
+    helper::maybe_create_destination(&mut app, &access_token, "news", "https://www.example.com/")
+        .await;
+    assert!(notifier.take_queued().await.is_empty());
+}
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+
+use crate::permissions::Action;
+use crate::permissions::Permissions;
+use crate::tests::helper;
+use crate::users::Role;
+
+#[sqlx::test]
+async fn test_permissions(pool: sqlx::PgPool) {
+    // managers manage users, but do not delete destinations
+    let permissions = Permissions::default().with_role(
+        Role::Manager,
+        &[
+            Action::ViewDestinations,
+            Action::CreateDestinations,
+            Action::UpdateDestinations,
+            Action::ManageNotes,
+            Action::ManageUsers,
+        ],
+    );
+    let mut app = helper::setup_test_app_with_root_settings(pool, |settings| {
+        settings.permissions = Arc::new(permissions);
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    helper::maybe_create_user_with_password(
+        &mut app,
+        &access_token,
+        "manager",
+        "manager",
+        Some("verysecret"),
+    )
+    .await;
+    let manager_access_token = helper::login_as(&mut app, "manager", "verysecret").await;
+
+    let (status_code, users) = helper::list_users(&mut app, &manager_access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(2, users.unwrap().len());
+
+    let (status_code, user, _) =
+        helper::maybe_create_user(&mut app, &manager_access_token, "john", "manager").await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, _) =
+        helper::maybe_delete_user(&mut app, &manager_access_token, &user.unwrap().id).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    // admins are allowed more than managers
+    let (status_code, _, _) =
+        helper::maybe_create_user(&mut app, &manager_access_token, "jane", "admin").await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    let (status_code, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &manager_access_token,
+        "launch",
+        "https://www.example.com/launch",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let destination_id = destination.unwrap().id;
+
+    let (status_code, _) =
+        helper::myabe_delete_destination(&mut app, &manager_access_token, &destination_id).await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    let (status_code, _) =
+        helper::myabe_delete_destination(&mut app, &access_token, &destination_id).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+}
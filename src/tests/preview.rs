@@ -0,0 +1,76 @@
+use axum::http::StatusCode;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_preview(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    // setup
+    let slug = "preview-me";
+    let url = "https://www.example.com/?a=1&b=2";
+
+    let (status_code, _, _) =
+        helper::maybe_create_destination(&mut app, &access_token, slug, url).await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    // preview with `+` suffix, values are escaped
+    let (status_code, location, body) = helper::root(&mut app, "preview-me+").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(None, location);
+    assert!(body.contains("https://www.example.com/?a=1&amp;b=2"));
+    assert!(body.contains("Temporary (307)"));
+
+    // preview with query parameter
+    let (status_code, location, body) = helper::root(&mut app, "preview-me?preview=1").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(None, location);
+    assert!(body.contains("/preview-me"));
+
+    // previews are not hits
+    assert_eq!(0, helper::count_hits(&pool).await);
+
+    // slugs ending with `+` take precedence over a preview
+    let (status_code, _, _) =
+        helper::maybe_create_destination(&mut app, &access_token, "preview-me+", url).await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, location, _) = helper::root(&mut app, "preview-me+").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(Some(url.to_string()), location);
+
+    // no preview for unknown slugs
+    let (status_code, _, _) = helper::root(&mut app, "unknown+").await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+}
+
+#[sqlx::test]
+async fn test_preview_web_url(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "click-me",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    // stored before URLs were validated
+    sqlx::query("UPDATE destinations SET url = $1 WHERE slug = $2")
+        .bind("javascript:alert(document.cookie)")
+        .bind("click-me")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let (status_code, _, body) = helper::root(&mut app, "click-me+").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert!(!body.contains("javascript:"));
+    assert!(body.contains("about:blank"));
+}
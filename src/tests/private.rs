@@ -0,0 +1,83 @@
+use axum::http::header::CACHE_CONTROL;
+use axum::http::Method;
+use axum::http::StatusCode;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_private(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    // setup
+    let (status_code, destination, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "private", "url": "https://www.example.com/", "isPrivate": true }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let destination = destination.unwrap();
+
+    // without signature
+    let (status_code, location, body) = helper::root(&mut app, "private").await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+    assert_eq!(None, location);
+    assert!(body.contains("This link is private"));
+
+    // with invalid signature
+    let (status_code, _, body) =
+        helper::root(&mut app, "private?expires=9999999999&signature=abcd").await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+    assert!(body.contains("This link has an invalid signature"));
+
+    // no preview without signature either
+    let (status_code, _, _) = helper::root(&mut app, "private+").await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    assert_eq!(0, helper::count_hits(&pool).await);
+
+    // with signature
+    let (status_code, path, _) =
+        helper::maybe_sign_destination(&mut app, &access_token, &destination.id, None).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let path = path.unwrap();
+
+    let (status_code, headers, _) =
+        helper::root_with_method(&mut app, Method::GET, path.trim_start_matches('/')).await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!("https://www.example.com/", headers.get("location").unwrap());
+    assert_eq!("private, no-store", headers.get(CACHE_CONTROL).unwrap());
+
+    assert_eq!(1, helper::count_hits(&pool).await);
+
+    // invalid expiry
+    let (status_code, _, error) =
+        helper::maybe_sign_destination(&mut app, &access_token, &destination.id, Some(0)).await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Invalid expiry".to_string()), error);
+}
+
+#[sqlx::test]
+async fn test_private_sign_public_destination(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "public",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, _, error) =
+        helper::maybe_sign_destination(&mut app, &access_token, &destination.unwrap().id, None)
+            .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Destination is not private".to_string()), error);
+}
@@ -0,0 +1,115 @@
+use axum::http::Method;
+use axum::http::StatusCode;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_public_stats(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "launch", "url": "https://www.example.com/", "isStatsPublic": true }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "hidden",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    for referer in [
+        "https://www.news.test/article",
+        "https://news.test/",
+        "not a URL",
+    ] {
+        let (status_code, _, _) =
+            helper::root_with_headers(&mut app, Method::GET, "launch", &[("referer", referer)])
+                .await;
+        assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    }
+
+    let (status_code, _, body) = helper::root(&mut app, "launch+stats").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert!(body.contains("Stats of /launch"));
+    assert!(body.contains("<dd>3</dd>"));
+    assert!(body.contains("<li>news.test (2)</li>"));
+    assert!(body.contains("<svg"));
+
+    // the stats page is not a hit
+    let (_, _, body) = helper::root(&mut app, "launch+stats").await;
+    assert!(body.contains("<dd>3</dd>"));
+
+    // only public stats
+    let (status_code, _, _) = helper::root(&mut app, "hidden+stats").await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+
+    let (status_code, _, _) = helper::maybe_patch_destination(
+        &mut app,
+        &access_token,
+        &destination.unwrap().id,
+        r#"{ "isStatsPublic": true }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+
+    let (status_code, _, body) = helper::root(&mut app, "hidden+stats").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert!(body.contains("None yet"));
+}
+
+#[sqlx::test]
+async fn test_public_stats_link(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (_, destination, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "hidden", "url": "https://www.example.com/", "isPrivate": true }"#,
+        true,
+    )
+    .await;
+    let destination_id = destination.unwrap().id;
+
+    let (status_code, path, _) =
+        helper::maybe_mint_stats_link(&mut app, &access_token, &destination_id, None).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let path = path.unwrap();
+    assert!(path.starts_with("/hidden+stats?expires="));
+
+    // the stats without an account
+    let (status_code, _, body) = helper::root(&mut app, path.trim_start_matches('/')).await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert!(body.contains("Stats of /hidden"));
+
+    let (status_code, _, _) = helper::root(&mut app, "hidden+stats").await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+
+    let tampered = path.replace("signature=", "signature=00");
+    let (status_code, _, _) = helper::root(&mut app, tampered.trim_start_matches('/')).await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    // the stats link does not open the private destination
+    let (status_code, _, _) = helper::root(
+        &mut app,
+        &path.trim_start_matches('/').replace("+stats", ""),
+    )
+    .await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    let (status_code, _, error) =
+        helper::maybe_mint_stats_link(&mut app, &access_token, &destination_id, Some(0)).await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Invalid expiry".to_string()), error);
+}
@@ -0,0 +1,110 @@
+use axum::http::StatusCode;
+
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::jobs::Job;
+use crate::jobs::Jobs;
+use crate::jobs::Outcome;
+use crate::tests::helper;
+
+async fn backdate_deleted(pool: &sqlx::PgPool, days: i32) {
+    for table in ["destinations", "notes", "users"] {
+        sqlx::query(&format!(
+            "UPDATE {table} SET deleted_at = CURRENT_TIMESTAMP - make_interval(days => $1) WHERE deleted_at IS NOT NULL"
+        ))
+        .bind(days)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+}
+
+#[sqlx::test]
+async fn test_purge_deleted(pool: sqlx::PgPool) {
+    let jobs = Jobs::new(None, None, Some(30));
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |settings| {
+        settings.jobs = jobs.clone();
+    })
+    .await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool.clone())).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (_, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+    let destination = destination.unwrap();
+    helper::maybe_create_note(&mut app, &access_token, &destination.id, "Launch").await;
+    helper::root(&mut app, "some-slug").await;
+
+    let (_, user, _) = helper::maybe_create_user_with_password(
+        &mut app,
+        &access_token,
+        "leaver",
+        "manager",
+        Some("verysecret"),
+    )
+    .await;
+    helper::maybe_delete_user(&mut app, &access_token, &user.unwrap().id).await;
+
+    let (status_code, _) =
+        helper::myabe_delete_destination(&mut app, &access_token, &destination.id).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    let (status_code, _, message) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.org/",
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(
+        Some("Slug already exists and is deleted".to_string()),
+        message
+    );
+
+    // deleted recently, kept
+    backdate_deleted(&pool, 10).await;
+    let outcomes = jobs.run_all(&database).await;
+    assert_eq!(
+        Some(&(
+            Job::PurgeDeleted { retention_days: 30 },
+            Outcome::Succeeded("Purged 0 destination(s), 0 note(s) and 0 user(s)".to_string())
+        )),
+        outcomes.last()
+    );
+
+    // deleted long ago, purged along with the notes and hits of the destination
+    backdate_deleted(&pool, 40).await;
+    let outcomes = jobs.run_all(&database).await;
+    assert_eq!(
+        Some(&(
+            Job::PurgeDeleted { retention_days: 30 },
+            Outcome::Succeeded("Purged 1 destination(s), 0 note(s) and 1 user(s)".to_string())
+        )),
+        outcomes.last()
+    );
+    assert_eq!(0, helper::count_hits(&pool).await);
+
+    // the slug can be used again
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.org/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, _, _) = helper::root(&mut app, "some-slug").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+
+    // the audit trail keeps the IDs of the purged records
+    let (_, verification) = helper::verify_audit_trail(&mut app, &access_token).await;
+    assert!(verification.unwrap().is_valid);
+}
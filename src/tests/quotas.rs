@@ -0,0 +1,117 @@
+use axum::http::StatusCode;
+use serde_json::Value;
+
+use crate::quotas::Quota;
+use crate::quotas::Quotas;
+use crate::tests::helper;
+use crate::users::Role;
+
+#[sqlx::test]
+async fn test_quotas(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app_with_root_settings(pool, |settings| {
+        settings.quotas = Quotas::default().with_role(
+            Role::Manager,
+            Quota {
+                max_destinations: Some(2),
+                max_hits_per_month: None,
+            },
+        );
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (_, manager, _) = helper::maybe_create_user_with_password(
+        &mut app,
+        &access_token,
+        "manager",
+        "manager",
+        Some("verysecret"),
+    )
+    .await;
+    let manager_id = manager.unwrap().id;
+    let manager_access_token = helper::login_as(&mut app, "manager", "verysecret").await;
+
+    for slug in ["first", "second"] {
+        let (status_code, _, _) = helper::maybe_create_destination(
+            &mut app,
+            &manager_access_token,
+            slug,
+            "https://www.example.com/",
+        )
+        .await;
+        assert_eq!(StatusCode::CREATED, status_code);
+    }
+
+    let (status_code, _, message) = helper::maybe_create_destination(
+        &mut app,
+        &manager_access_token,
+        "third",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Quota of destinations reached".to_string()), message);
+
+    let (status_code, usage) = helper::get_usage(&mut app, &manager_access_token, "me").await;
+    assert_eq!(StatusCode::OK, status_code);
+    let usage = usage.unwrap();
+    assert_eq!(2, usage["destinations"]);
+    assert_eq!(2, usage["maxDestinations"]);
+    assert_eq!(false, usage["isUserQuota"]);
+
+    // admins have no quota
+    let (status_code, usage) = helper::get_usage(&mut app, &access_token, "me").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(Value::Null, usage.unwrap()["maxDestinations"]);
+
+    // only admins set the quota of a user
+    let (status_code, _, _) = helper::maybe_update_quota(
+        &mut app,
+        &manager_access_token,
+        &manager_id,
+        r#"{ "maxDestinations": 10 }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    let (status_code, usage, _) = helper::maybe_update_quota(
+        &mut app,
+        &access_token,
+        &manager_id,
+        r#"{ "maxDestinations": 10, "maxHitsPerMonth": 2 }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    let usage = usage.unwrap();
+    assert_eq!(10, usage["maxDestinations"]);
+    assert_eq!(true, usage["isUserQuota"]);
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &manager_access_token,
+        "third",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    // the hits of this month
+    helper::root(&mut app, "first").await;
+    helper::root(&mut app, "second").await;
+
+    let (_, usage) = helper::get_usage(&mut app, &access_token, &manager_id.to_string()).await;
+    assert_eq!(2, usage.unwrap()["hitsThisMonth"]);
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &manager_access_token,
+        "fourth",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::TOO_MANY_REQUESTS, status_code);
+
+    let (_, verification) = helper::verify_audit_trail(&mut app, &access_token).await;
+    assert!(verification.unwrap().is_valid);
+}
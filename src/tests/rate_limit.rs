@@ -0,0 +1,57 @@
+use axum::http::header::RETRY_AFTER;
+use axum::http::Method;
+use axum::http::StatusCode;
+
+use crate::rate_limit::RateLimit;
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_rate_limit(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |settings| {
+        settings.rate_limit = RateLimit::new(0.1, 2.0);
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    // setup
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "limited",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let scraper = [("x-real-ip", "10.0.0.1")];
+
+    for _ in 0..2 {
+        let (status_code, _, _) =
+            helper::root_with_headers(&mut app, Method::GET, "limited", &scraper).await;
+        assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    }
+
+    // bucket is empty, a token is added every 10 seconds
+    let (status_code, headers, body) =
+        helper::root_with_headers(&mut app, Method::GET, "limited", &scraper).await;
+    assert_eq!(StatusCode::TOO_MANY_REQUESTS, status_code);
+    assert_eq!("10", headers.get(RETRY_AFTER).unwrap());
+    assert!(body.contains("Too many requests"));
+
+    assert_eq!(2, helper::count_hits(&pool).await);
+
+    // other visitors are not affected
+    let (status_code, _, _) = helper::root_with_headers(
+        &mut app,
+        Method::GET,
+        "limited",
+        &[("x-real-ip", "10.0.0.2")],
+    )
+    .await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+
+    // the API is not limited
+    let (status_code, _) = helper::list_destinations(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+}
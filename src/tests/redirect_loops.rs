@@ -0,0 +1,122 @@
+use axum::http::Method;
+use axum::http::StatusCode;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_redirect_loops(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let host = "sho.rt";
+
+    // directly to itself
+    let (status_code, _, error) = helper::maybe_create_destination_on_host(
+        &mut app,
+        &access_token,
+        host,
+        "self",
+        "https://sho.rt/self",
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(
+        Some(helper::Error {
+            error: "Redirect loop detected".to_string(),
+            code: "redirect_loop".to_string(),
+            description: Some("URL redirects back to this slug".to_string()),
+            details: None,
+        }),
+        error
+    );
+
+    // via a chain
+    let (status_code, _, _) = helper::maybe_create_destination_on_host(
+        &mut app,
+        &access_token,
+        host,
+        "first",
+        "https://sho.rt/second",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, _, error) = helper::maybe_create_destination_on_host(
+        &mut app,
+        &access_token,
+        host,
+        "second",
+        "https://sho.rt/first",
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert!(error.is_some());
+
+    // other hosts are not followed
+    let (status_code, _, _) = helper::maybe_create_destination_on_host(
+        &mut app,
+        &access_token,
+        "other.host",
+        "second",
+        "https://sho.rt/first",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    // but the loop is broken when redirecting
+    let (status_code, headers, _) =
+        helper::root_with_headers(&mut app, Method::GET, "first", &[("host", host)]).await;
+    assert_eq!(StatusCode::LOOP_DETECTED, status_code);
+    assert!(headers.get("location").is_none());
+
+    let (status_code, _, _) =
+        helper::root_with_headers(&mut app, Method::GET, "first", &[("host", "other.host")]).await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+}
+
+#[sqlx::test]
+async fn test_redirect_loops_cached(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let host = "sho.rt";
+
+    helper::maybe_create_destination_on_host(
+        &mut app,
+        &access_token,
+        host,
+        "first",
+        "https://sho.rt/second",
+    )
+    .await;
+    helper::maybe_create_destination_on_host(
+        &mut app,
+        &access_token,
+        host,
+        "second",
+        "https://www.example.com/",
+    )
+    .await;
+
+    let (status_code, _, _) =
+        helper::root_with_headers(&mut app, Method::GET, "first", &[("host", host)]).await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+
+    let (_, statistics) = helper::cache_statistics(&mut app, &access_token).await;
+    let statistics = statistics.unwrap();
+
+    // both slugs are cached, the chain is followed without the database
+    let (status_code, _, _) =
+        helper::root_with_headers(&mut app, Method::GET, "first", &[("host", host)]).await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+
+    let (_, cached) = helper::cache_statistics(&mut app, &access_token).await;
+    let cached = cached.unwrap();
+    assert_eq!(
+        statistics["lookups"].as_u64().unwrap() + 2,
+        cached["lookups"].as_u64().unwrap()
+    );
+    assert_eq!(statistics["misses"], cached["misses"]);
+}
@@ -0,0 +1,80 @@
+use axum::http::Method;
+use axum::http::StatusCode;
+
+use crate::rate_limit::RateLimit;
+use crate::slug_policy::SlugPolicy;
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_reload(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app_with_root_settings(pool, |settings| {
+        settings.rate_limit = RateLimit::new(0.001, 1.0);
+        settings.slug_policy = SlugPolicy::default().with_reserved(&["admin"]);
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, message) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "admin",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Slug is reserved".to_string()), message);
+
+    helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "cached",
+        "https://www.example.com/",
+    )
+    .await;
+
+    let scraper = [("x-real-ip", "10.0.0.1")];
+
+    let (status_code, _, _) =
+        helper::root_with_headers(&mut app, Method::GET, "cached", &scraper).await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+
+    let (status_code, _, _) =
+        helper::root_with_headers(&mut app, Method::GET, "cached", &scraper).await;
+    assert_eq!(StatusCode::TOO_MANY_REQUESTS, status_code);
+
+    // only admins reload
+    helper::maybe_create_user_with_password(
+        &mut app,
+        &access_token,
+        "manager",
+        "manager",
+        Some("verysecret"),
+    )
+    .await;
+    let manager_access_token = helper::login_as(&mut app, "manager", "verysecret").await;
+
+    let status_code = helper::reload_config(&mut app, &manager_access_token).await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    // without rate limit and reserved slugs in the environment
+    let status_code = helper::reload_config(&mut app, &access_token).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    let (status_code, _, _) =
+        helper::root_with_headers(&mut app, Method::GET, "cached", &scraper).await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "admin",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    // the cache is kept
+    let (_, statistics) = helper::cache_statistics(&mut app, &access_token).await;
+    assert_eq!(1, statistics.unwrap()["hits"]);
+}
@@ -0,0 +1,106 @@
+use axum::http::StatusCode;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_reservations(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    helper::maybe_create_user_with_password(
+        &mut app,
+        &access_token,
+        "manager",
+        "manager",
+        Some("verysecret"),
+    )
+    .await;
+    let manager_access_token = helper::login_as(&mut app, "manager", "verysecret").await;
+
+    helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "existing",
+        "https://www.example.com/",
+    )
+    .await;
+
+    let (status_code, reservations, _) = helper::maybe_reserve_slugs(
+        &mut app,
+        &manager_access_token,
+        r#"{ "slugs": ["summer-sale", "summer-sale-nl"] }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    assert_eq!(2, reservations.unwrap().as_array().unwrap().len());
+
+    // all or nothing
+    let (status_code, _, message) = helper::maybe_reserve_slugs(
+        &mut app,
+        &access_token,
+        r#"{ "slugs": ["winter-sale", "summer-sale"] }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(
+        Some("Slug is already reserved: summer-sale".to_string()),
+        message
+    );
+
+    let (status_code, _, message) =
+        helper::maybe_reserve_slugs(&mut app, &access_token, r#"{ "slugs": ["existing"] }"#).await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Slug already exists: existing".to_string()), message);
+
+    let (_, reservations) = helper::list_reservations(&mut app, &access_token).await;
+    assert_eq!(0, reservations.unwrap().as_array().unwrap().len());
+
+    // only the reserver creates the destination
+    let (status_code, _, message) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "summer-sale",
+        "https://www.example.com/summer",
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(
+        Some("Slug is reserved by another user".to_string()),
+        message
+    );
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &manager_access_token,
+        "summer-sale",
+        "https://www.example.com/summer",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, reservations) =
+        helper::list_reservations(&mut app, &manager_access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let reservations = reservations.unwrap();
+    assert_eq!(1, reservations.as_array().unwrap().len());
+    assert_eq!("summer-sale-nl", reservations[0]["slug"]);
+
+    // admins release the reservations of others
+    let status_code = helper::maybe_release_reservation(
+        &mut app,
+        &access_token,
+        reservations[0]["id"].as_str().unwrap(),
+    )
+    .await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "summer-sale-nl",
+        "https://www.example.com/summer/nl",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+}
@@ -1,3 +1,7 @@
+use axum::http::header::CACHE_CONTROL;
+use axum::http::header::CONTENT_TYPE;
+use axum::http::header::LOCATION;
+use axum::http::Method;
 use axum::http::StatusCode;
 
 use crate::tests::helper;
@@ -29,3 +33,298 @@ async fn test_root_with_invalid_utf8(pool: sqlx::PgPool) {
     assert_eq!(None, location);
     assert!(body.contains("URL contains invalid UTF-8 characters"));
 }
+
+#[sqlx::test]
+async fn test_root_cache_control(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    // setup
+    let temporary_slug = "temporary";
+    let permanent_slug = "permanent";
+    let url = "https://www.example.com/";
+
+    let (status_code, _, _) =
+        helper::maybe_create_destination(&mut app, &access_token, temporary_slug, url).await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, _, _) = helper::maybe_create_destination_with_is_permanent(
+        &mut app,
+        &access_token,
+        permanent_slug,
+        url,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    // temporary redirects are not cached
+    let (status_code, headers, _) =
+        helper::root_with_method(&mut app, Method::GET, temporary_slug).await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(
+        Some("no-store"),
+        headers.get(CACHE_CONTROL).map(|h| h.to_str().unwrap())
+    );
+
+    // permanent redirects are cached for a long time
+    let (status_code, headers, _) =
+        helper::root_with_method(&mut app, Method::GET, permanent_slug).await;
+    assert_eq!(StatusCode::PERMANENT_REDIRECT, status_code);
+    assert_eq!(
+        Some("public, max-age=31536000, immutable"),
+        headers.get(CACHE_CONTROL).map(|h| h.to_str().unwrap())
+    );
+}
+
+#[sqlx::test]
+async fn test_root_head_without_hit(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    // setup
+    let slug = "uptime";
+    let url = "https://www.example.com/";
+
+    let (status_code, _, _) =
+        helper::maybe_create_destination(&mut app, &access_token, slug, url).await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    // HEAD gets the redirect, without a hit
+    let (status_code, headers, _) = helper::root_with_method(&mut app, Method::HEAD, slug).await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(
+        Some(url),
+        headers.get(LOCATION).map(|h| h.to_str().unwrap())
+    );
+    assert_eq!(0, helper::count_hits(&pool).await);
+
+    // GET is counted
+    let (status_code, _, _) = helper::root_with_method(&mut app, Method::GET, slug).await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(1, helper::count_hits(&pool).await);
+}
+
+#[sqlx::test]
+async fn test_root_suggestions(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    // setup
+    let url = "https://www.example.com/";
+
+    for slug in ["hello-world", "hello-there", "something-else"] {
+        let (status_code, _, _) =
+            helper::maybe_create_destination(&mut app, &access_token, slug, url).await;
+        assert_eq!(StatusCode::CREATED, status_code);
+    }
+
+    let (status_code, headers, body) =
+        helper::root_with_method(&mut app, Method::GET, "hello-wrld").await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+    assert_eq!(
+        "hello-world,hello-there",
+        headers.get("x-shurly-suggestions").unwrap()
+    );
+    assert!(body.contains(r#"<a href="/hello-world">/hello-world</a>"#));
+    assert!(!body.contains("something-else"));
+
+    // nothing similar
+    let (status_code, headers, body) =
+        helper::root_with_method(&mut app, Method::GET, "zzzzzz").await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+    assert!(headers.get("x-shurly-suggestions").is_none());
+    assert!(!body.contains("Did you mean"));
+}
+
+#[sqlx::test]
+async fn test_root_robots_txt_and_favicon(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let (status_code, headers, body) =
+        helper::root_with_method(&mut app, Method::GET, "robots.txt").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(
+        "text/plain; charset=utf-8",
+        headers.get(CONTENT_TYPE).unwrap()
+    );
+    assert!(body.contains("Disallow: /"));
+
+    let (status_code, headers, _) =
+        helper::root_with_method(&mut app, Method::GET, "favicon.ico").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!("image/x-icon", headers.get(CONTENT_TYPE).unwrap());
+
+    assert_eq!(0, helper::count_hits(&pool).await);
+}
+
+#[sqlx::test]
+async fn test_root_request_id(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let (_, headers, _) = helper::root_with_method(&mut app, Method::GET, "some-slug").await;
+    assert_eq!(36, headers.get("x-request-id").unwrap().len());
+
+    // the ID of a request is kept, to follow it through multiple services
+    let (_, headers, _) = helper::root_with_headers(
+        &mut app,
+        Method::GET,
+        "some-slug",
+        &[("x-request-id", "some-request-id")],
+    )
+    .await;
+    assert_eq!("some-request-id", headers.get("x-request-id").unwrap());
+}
+
+#[sqlx::test]
+async fn test_root_do_not_track(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    // setup
+    let slug = "tracked";
+    let url = "https://www.example.com/";
+
+    let (status_code, _, _) =
+        helper::maybe_create_destination(&mut app, &access_token, slug, url).await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let user_agent = ("user-agent", "Test/1.0");
+
+    // regular hit
+    let (status_code, _, _) =
+        helper::root_with_headers(&mut app, Method::GET, slug, &[user_agent]).await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+
+    // anonymized hits, by default
+    for signal in ["dnt", "sec-gpc"] {
+        let (status_code, _, _) =
+            helper::root_with_headers(&mut app, Method::GET, slug, &[user_agent, (signal, "1")])
+                .await;
+        assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    }
+
+    assert_eq!(3, helper::count_hits(&pool).await);
+    assert_eq!(2, helper::count_anonymous_hits(&pool).await);
+}
+
+#[sqlx::test]
+async fn test_root_meta_refresh(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "meta-refresh", "url": "https://www.example.com/?a=1&b=2", "isMetaRefresh": true }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, headers, body) =
+        helper::root_with_method(&mut app, Method::GET, "meta-refresh").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert!(headers.get(LOCATION).is_none());
+    assert_eq!("no-store", headers.get(CACHE_CONTROL).unwrap());
+    assert!(body.contains(
+        r#"<meta http-equiv="refresh" content="0; url=https://www.example.com/?a=1&amp;b=2">"#
+    ));
+
+    // still a hit
+    assert_eq!(1, helper::count_hits(&pool).await);
+}
+
+#[sqlx::test]
+async fn test_root_open_graph(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{
+            "slug": "shared",
+            "url": "https://www.example.com/",
+            "ogTitle": "Example & co",
+            "ogImage": "https://www.example.com/image.png"
+        }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let crawler = ("user-agent", "Slackbot-LinkExpanding 1.0");
+
+    let (status_code, headers, body) =
+        helper::root_with_headers(&mut app, Method::GET, "shared", &[crawler]).await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert!(headers.get(LOCATION).is_none());
+    assert!(body.contains(r#"<meta property="og:title" content="Example &amp; co">"#));
+    assert!(
+        body.contains(r#"<meta property="og:image" content="https://www.example.com/image.png">"#)
+    );
+
+    // regular visitors are redirected
+    let (status_code, headers, _) = helper::root_with_headers(
+        &mut app,
+        Method::GET,
+        "shared",
+        &[("user-agent", "Mozilla/5.0")],
+    )
+    .await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert!(headers.get(LOCATION).is_some());
+
+    // crawlers are not visitors
+    assert_eq!(1, helper::count_hits(&pool).await);
+
+    // invalid image
+    let (status_code, _, error) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "invalid", "url": "https://www.example.com/", "ogImage": "image.png" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert!(error.is_some());
+}
+
+#[sqlx::test]
+async fn test_root_open_graph_web_url(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "shared", "url": "https://www.example.com/", "ogTitle": "Example" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    // stored before URLs were validated
+    sqlx::query("UPDATE destinations SET url = $1 WHERE slug = $2")
+        .bind("javascript:alert(document.cookie)")
+        .bind("shared")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let crawler = ("user-agent", "Slackbot-LinkExpanding 1.0");
+
+    let (status_code, _, body) =
+        helper::root_with_headers(&mut app, Method::GET, "shared", &[crawler]).await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert!(!body.contains("javascript:"));
+    assert!(body.contains(r#"<a href="about:blank">Example</a>"#));
+}
@@ -0,0 +1,76 @@
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::Router;
+
+use crate::tests::helper;
+use crate::Routes;
+
+#[sqlx::test]
+async fn test_routes_root(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app_with_routes(pool, Routes::Root).await;
+
+    let (status_code, _, _) = helper::root(&mut app, "healthz").await;
+    assert_eq!(StatusCode::OK, status_code);
+
+    let (status_code, _, _) = helper::root(&mut app, "robots.txt").await;
+    assert_eq!(StatusCode::OK, status_code);
+
+    // the API is just a slug that does not exist
+    let (status_code, _, body) = helper::root(&mut app, "api/users/me").await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+    assert!(body.contains("html"));
+}
+
+#[sqlx::test]
+async fn test_routes_api(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app_with_routes(pool, Routes::Api).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, _, _) = helper::root(&mut app, "healthz").await;
+    assert_eq!(StatusCode::OK, status_code);
+
+    // no redirects on the address of the API
+    let (status_code, location, body) = helper::root(&mut app, "some-slug").await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+    assert_eq!(None, location);
+    assert!(body.is_empty());
+}
+
+#[sqlx::test]
+async fn test_routes_embedded(pool: sqlx::PgPool) {
+    let shurly = helper::setup_test_app(pool).await;
+
+    // another axum app, with Shurly mounted in it
+    let mut app = Router::new()
+        .route("/hello", get(|| async { "Hello" }))
+        .merge(shurly);
+
+    let (status_code, _, body) = helper::root(&mut app, "hello").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!("Hello", body);
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, location, _) = helper::root(&mut app, "some-slug").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(Some("https://www.example.com/".to_string()), location);
+}
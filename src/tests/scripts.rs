@@ -0,0 +1,85 @@
+use axum::http::header::CACHE_CONTROL;
+use axum::http::Method;
+use axum::http::StatusCode;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_scripts(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{
+            "slug": "scripted",
+            "url": "https://www.example.com/",
+            "script": "if request.headers[\"accept-language\"]?.starts_with(\"nl\") ?? false { `${url}nl/` }"
+        }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    // without a result the URL of the destination is used
+    let (status_code, headers, _) =
+        helper::root_with_headers(&mut app, Method::GET, "scripted", &[]).await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!("https://www.example.com/", headers.get("location").unwrap());
+    assert_eq!("private, no-store", headers.get(CACHE_CONTROL).unwrap());
+
+    let (status_code, headers, _) = helper::root_with_headers(
+        &mut app,
+        Method::GET,
+        "scripted",
+        &[("accept-language", "nl-NL")],
+    )
+    .await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(
+        "https://www.example.com/nl/",
+        headers.get("location").unwrap()
+    );
+
+    assert_eq!(2, helper::count_hits(&pool).await);
+}
+
+#[sqlx::test]
+async fn test_scripts_failing(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "failing", "url": "https://www.example.com/", "script": "loop {}" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    // the link keeps working
+    let (status_code, location, _) = helper::root(&mut app, "failing").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(Some("https://www.example.com/".to_string()), location);
+}
+
+#[sqlx::test]
+async fn test_scripts_invalid(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, error) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "invalid", "url": "https://www.example.com/", "script": "if {" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert!(error.unwrap().error.starts_with("Invalid script: "));
+}
@@ -0,0 +1,106 @@
+use axum::http::StatusCode;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_search(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, message) = helper::search(&mut app, &access_token, " l ").await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Query is too short".to_string()), message);
+
+    let (status_code, launch, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "launch",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let launch = launch.unwrap();
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "launch-party",
+        "https://www.example.com/party",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "pricing",
+        "https://www.example.com/launch/pricing",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, gone, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "launch-gone",
+        "https://www.example.com/gone",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, _) =
+        helper::myabe_delete_destination(&mut app, &access_token, &gone.unwrap().id).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    let (status_code, _, _) = helper::maybe_create_note(
+        &mut app,
+        &access_token,
+        &launch.id,
+        "Printed on the flyers of the launch",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, results, _) = helper::search(&mut app, &access_token, "LAUNCH").await;
+    assert_eq!(StatusCode::OK, status_code);
+    let results = results.unwrap();
+
+    // the exact slug first, deleted destinations are never found
+    let found = results
+        .iter()
+        .map(|result| {
+            (
+                result["kind"].as_str().unwrap(),
+                result["slug"].as_str().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(4, found.len());
+    assert_eq!(("destination", "launch"), found[0]);
+    assert!(found.contains(&("destination", "launch-party")));
+    assert!(found.contains(&("destination", "pricing")));
+    assert!(found.contains(&("note", "launch")));
+
+    let scores = results
+        .iter()
+        .map(|result| result["score"].as_f64().unwrap())
+        .collect::<Vec<_>>();
+    assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+
+    let (status_code, results, _) = helper::search(&mut app, &access_token, "flyers").await;
+    assert_eq!(StatusCode::OK, status_code);
+    let results = results.unwrap();
+    assert_eq!(1, results.len());
+    assert_eq!("note", results[0]["kind"]);
+    assert_eq!(launch.id.to_string(), results[0]["destinationId"]);
+    assert_eq!(
+        "Printed on the flyers of the launch",
+        results[0]["noteContent"]
+    );
+
+    // wildcards are searched for literally
+    let (status_code, results, _) = helper::search(&mut app, &access_token, "%_").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(Some(vec![]), results);
+}
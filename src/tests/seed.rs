@@ -0,0 +1,110 @@
+use axum::http::StatusCode;
+
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::seed::Seed;
+use crate::seed::Summary;
+use crate::tests::helper;
+
+const SEED: &str = r#"{
+    "users": [
+        { "username": "jane", "password": "verysecret", "role": "admin" },
+        { "username": "john", "password": "verysecret" }
+    ],
+    "destinations": [
+        {
+            "slug": "seeded",
+            "url": "https://www.example.com/",
+            "notes": ["First note", "Second note"]
+        },
+        { "slug": "seeded-permanent", "url": "https://www.example.com/", "isPermanent": true }
+    ]
+}"#;
+
+#[sqlx::test]
+async fn test_seed(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool)).await;
+
+    let path = std::env::temp_dir().join(format!("shurly-{}.json", uuid::Uuid::new_v4()));
+    std::fs::write(&path, SEED).unwrap();
+
+    let seed = Seed::from_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let admin = database
+        .find_single_user_by_username("admin")
+        .await
+        .unwrap()
+        .unwrap();
+
+    let summary = seed.load(&database, &admin).await.unwrap();
+    assert_eq!(
+        Some(Summary {
+            users: 2,
+            destinations: 2,
+            notes: 2,
+        }),
+        summary
+    );
+
+    let (status_code, location, _) = helper::root(&mut app, "seeded").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(Some("https://www.example.com/".to_string()), location);
+
+    let (status_code, _, _) = helper::root(&mut app, "seeded-permanent").await;
+    assert_eq!(StatusCode::PERMANENT_REDIRECT, status_code);
+
+    let access_token = helper::login(&mut app).await;
+
+    let (_, users) = helper::list_users(&mut app, &access_token).await;
+    let users = users.unwrap();
+    assert!(users.iter().any(|user| user.username == "jane"));
+    assert!(users.iter().any(|user| user.username == "john"));
+
+    let (_, destinations) = helper::list_destinations(&mut app, &access_token).await;
+    let destination = destinations
+        .unwrap()
+        .into_iter()
+        .find(|destination| destination.slug == "seeded")
+        .unwrap();
+
+    let (_, notes) = helper::list_notes(&mut app, &access_token, &destination.id).await;
+    assert_eq!(2, notes.unwrap().len());
+
+    // the seed is on the audit trail, just like changes via the API
+    let (_, verification) = helper::verify_audit_trail(&mut app, &access_token).await;
+    let verification = verification.unwrap();
+    assert!(verification.is_valid);
+    assert_eq!(6, verification.verified_entries);
+
+    // the database is not empty anymore
+    assert!(seed.load(&database, &admin).await.unwrap().is_none());
+}
+
+#[sqlx::test]
+async fn test_seed_invalid(pool: sqlx::PgPool) {
+    let _app = helper::setup_test_app(pool.clone()).await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool)).await;
+
+    let path = std::env::temp_dir().join(format!("shurly-{}.json", uuid::Uuid::new_v4()));
+    std::fs::write(
+        &path,
+        r#"{ "destinations": [{ "slug": "robots.txt", "url": "https://www.example.com/" }] }"#,
+    )
+    .unwrap();
+
+    let seed = Seed::from_file(&path).unwrap();
+    let admin = database
+        .find_single_user_by_username("admin")
+        .await
+        .unwrap()
+        .unwrap();
+
+    let err = seed.load(&database, &admin).await.unwrap_err();
+    assert_eq!("Slug is reserved", err.to_string());
+
+    std::fs::write(&path, "not json").unwrap();
+    assert!(Seed::from_file(&path).is_err());
+
+    std::fs::remove_file(&path).unwrap();
+}
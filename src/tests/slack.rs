@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use axum::body::Body;
+use axum::http::Request;
+use axum::http::StatusCode;
+use tower::Service;
+
+use crate::slack::Slack;
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_slack(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app_with_root_settings(pool, |settings| {
+        settings.slack = Slack::new(
+            b"secret",
+            HashMap::from([("U012AB3CD".to_string(), "admin".to_string())]),
+        );
+    })
+    .await;
+
+    let (status_code, text) = helper::slack_command(
+        &mut app,
+        b"secret",
+        "U012AB3CD",
+        "create launch https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(
+        "Created `launch`, redirecting to https://www.example.com/",
+        text.unwrap()
+    );
+
+    let (status_code, _, _) = helper::root(&mut app, "launch").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+
+    let (_, text) = helper::slack_command(&mut app, b"secret", "U012AB3CD", "stats launch").await;
+    assert_eq!("`launch` has 1 hits, 1 in the last 7 days", text.unwrap());
+
+    // failures are replied to
+    let (status_code, text) = helper::slack_command(
+        &mut app,
+        b"secret",
+        "U012AB3CD",
+        "create launch https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!("Slug already exists", text.unwrap());
+
+    // the rules of the API apply, like for deleted slugs
+    let access_token = helper::login(&mut app).await;
+    let (_, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "gone",
+        "https://www.example.com/",
+    )
+    .await;
+    let (status_code, _) =
+        helper::myabe_delete_destination(&mut app, &access_token, &destination.unwrap().id).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    let (_, text) = helper::slack_command(
+        &mut app,
+        b"secret",
+        "U012AB3CD",
+        "create gone https://www.example.com/",
+    )
+    .await;
+    assert_eq!("Slug already exists and is deleted", text.unwrap());
+
+    let (_, text) = helper::slack_command(&mut app, b"secret", "U012AB3CD", "stats unknown").await;
+    assert_eq!("Destination not found", text.unwrap());
+
+    let (_, text) = helper::slack_command(&mut app, b"secret", "U012AB3CD", "help").await;
+    assert!(text.unwrap().starts_with("Usage:"));
+
+    // unmapped Slack users can not run commands
+    let (_, text) = helper::slack_command(&mut app, b"secret", "U045EF6GH", "stats launch").await;
+    assert_eq!(
+        "Your Slack user `U045EF6GH` is not linked to a Shurly user",
+        text.unwrap()
+    );
+
+    // only signed by Slack
+    let (status_code, _) =
+        helper::slack_command(&mut app, b"other", "U012AB3CD", "stats launch").await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+}
+
+#[sqlx::test]
+async fn test_slack_disabled(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let request = Request::post("/api/integrations/slack")
+        .body(Body::from("text=stats+launch"))
+        .unwrap();
+
+    let response = app.call(request).await.unwrap();
+    assert_eq!(StatusCode::NOT_FOUND, response.status());
+}
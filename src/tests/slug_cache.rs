@@ -0,0 +1,244 @@
+use axum::http::StatusCode;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_slug_cache(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    // missing slug is cached
+    let (status_code, _, _) = helper::root(&mut app, "cached").await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+
+    sqlx::query(
+        r"
+        INSERT INTO destinations (id, user_id, slug, url)
+        SELECT gen_random_uuid(), id, 'cached', 'https://www.example.com/'
+        FROM users
+        LIMIT 1
+        ",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let (status_code, _, _) = helper::root(&mut app, "cached").await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+
+    // creating a destination via the API invalidates the slug right away
+    let (status_code, _, _) = helper::root(&mut app, "created").await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+
+    let (status_code, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "created",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let destination = destination.unwrap();
+
+    let (status_code, location, _) = helper::root(&mut app, "created").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(Some("https://www.example.com/".to_string()), location);
+
+    // as do updates and deletes
+    let (status_code, _) = helper::maybe_update_destination(
+        &mut app,
+        &access_token,
+        &destination.id,
+        "https://www.example.com/updated",
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+
+    let (_, location, _) = helper::root(&mut app, "created").await;
+    assert_eq!(
+        Some("https://www.example.com/updated".to_string()),
+        location
+    );
+
+    let (status_code, _) =
+        helper::myabe_delete_destination(&mut app, &access_token, &destination.id).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    let (status_code, _, _) = helper::root(&mut app, "created").await;
+    assert_eq!(StatusCode::GONE, status_code);
+}
+
+#[sqlx::test]
+async fn test_slug_cache_management(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "managed",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    for _ in 0..3 {
+        let (status_code, _, _) = helper::root(&mut app, "managed").await;
+        assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    }
+
+    let (status_code, statistics) = helper::cache_statistics(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let statistics = statistics.unwrap();
+    assert_eq!(1, statistics["entries"]);
+    assert_eq!(3, statistics["lookups"]);
+    assert_eq!(2, statistics["hits"]);
+    assert_eq!(1, statistics["misses"]);
+
+    // stale destination, changed outside of Shurly
+    sqlx::query("UPDATE destinations SET url = 'https://www.example.com/stale'")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let (_, location, _) = helper::root(&mut app, "managed").await;
+    assert_eq!(Some("https://www.example.com/".to_string()), location);
+
+    let status_code =
+        helper::invalidate_cache(&mut app, &access_token, r#"{ "slug": "/managed" }"#).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    let (_, location, _) = helper::root(&mut app, "managed").await;
+    assert_eq!(Some("https://www.example.com/stale".to_string()), location);
+
+    // flush
+    let status_code = helper::flush_cache(&mut app, &access_token).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    let (_, statistics) = helper::cache_statistics(&mut app, &access_token).await;
+    assert_eq!(0, statistics.unwrap()["entries"]);
+}
+
+#[sqlx::test]
+async fn test_slug_cache_replicas(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+    let mut replica = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    // missing slug is cached by the replica
+    let (status_code, _, _) = helper::root(&mut replica, "replicated").await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "replicated",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    // the replica is notified in the background
+    let mut status_code = StatusCode::NOT_FOUND;
+    for _ in 0..50 {
+        (status_code, _, _) = helper::root(&mut replica, "replicated").await;
+
+        if status_code != StatusCode::NOT_FOUND {
+            break;
+        }
+
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+}
+
+#[sqlx::test]
+async fn test_slug_cache_warm_up(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    for slug in ["popular", "unpopular"] {
+        let (status_code, _, _) = helper::maybe_create_destination(
+            &mut app,
+            &access_token,
+            slug,
+            "https://www.example.com/",
+        )
+        .await;
+        assert_eq!(StatusCode::CREATED, status_code);
+    }
+
+    for _ in 0..3 {
+        let (status_code, _, _) = helper::root(&mut app, "popular").await;
+        assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    }
+
+    // a restarted instance starts with the popular destinations
+    let mut restarted = helper::setup_test_app(pool).await;
+
+    let (_, statistics) = helper::cache_statistics(&mut restarted, &access_token).await;
+    assert_eq!(1, statistics.unwrap()["entries"]);
+
+    let (status_code, location, _) = helper::root(&mut restarted, "popular").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(Some("https://www.example.com/".to_string()), location);
+
+    let (_, statistics) = helper::cache_statistics(&mut restarted, &access_token).await;
+    let statistics = statistics.unwrap();
+    assert_eq!(1, statistics["lookups"]);
+    assert_eq!(0, statistics["misses"]);
+}
+
+#[sqlx::test]
+async fn test_slug_cache_audit(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "audited",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let destination = destination.unwrap();
+
+    let (status_code, _, _) = helper::root(&mut app, "audited").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+
+    let (status_code, _, _) = helper::root(&mut app, "missing").await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+
+    let (status_code, audit, _) = helper::audit_cache(&mut app, &access_token, "").await;
+    assert_eq!(StatusCode::OK, status_code);
+    let audit = audit.unwrap();
+    assert_eq!(2, audit["checked"]);
+    assert_eq!(Some(&vec![]), audit["stale"].as_array());
+
+    // stale destination, changed outside of Shurly
+    sqlx::query("UPDATE destinations SET url = 'https://www.example.com/stale'")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let (status_code, audit, _) = helper::audit_cache(&mut app, &access_token, "").await;
+    assert_eq!(StatusCode::OK, status_code);
+    let stale = audit.unwrap()["stale"].as_array().unwrap().clone();
+    assert_eq!(1, stale.len());
+    assert_eq!("audited", stale[0]["slug"]);
+    assert_eq!(destination.id.to_string(), stale[0]["destinationId"]);
+
+    // repaired
+    let (_, location, _) = helper::root(&mut app, "audited").await;
+    assert_eq!(Some("https://www.example.com/stale".to_string()), location);
+
+    let (status_code, _, message) = helper::audit_cache(&mut app, &access_token, "sample=0").await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("Invalid sample".to_string()), message);
+}
@@ -0,0 +1,113 @@
+use axum::http::StatusCode;
+use chrono::TimeDelta;
+use chrono::Utc;
+use serde_json::json;
+use serde_json::Value;
+
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::hit_buffer::Hit;
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_sources(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool.clone()).await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool)).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, flyer, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "flyer", "url": "https://www.example.com/", "source": " print-flyer " }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let flyer = flyer.unwrap();
+
+    let (status_code, poster, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "poster", "url": "https://www.example.com/", "source": "print-flyer" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let poster = poster.unwrap();
+
+    let (status_code, footer, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "footer", "url": "https://www.example.com/", "source": "email-footer" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let footer = footer.unwrap();
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "plain",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    let (status_code, _, error) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "long", "url": "https://www.example.com/",
+            "source": "a-source-that-is-way-too-long-to-compare-channels-by-in-any-useful-way" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("Source is too long", error.unwrap().error);
+
+    let yesterday = (Utc::now() - TimeDelta::days(1)).naive_utc();
+    let hit = |destination_id| Hit {
+        destination_id,
+        ip_address: None,
+        user_agent: None,
+        referrer: None,
+        created_at: yesterday,
+    };
+    database
+        .save_hits(&[hit(flyer.id), hit(flyer.id), hit(poster.id), hit(footer.id)])
+        .await
+        .unwrap();
+
+    let (status_code, sources) = helper::stats_sources(&mut app, &access_token, "days=7").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(
+        vec![
+            json!({ "source": "print-flyer", "destinations": 2, "hits": 3 }),
+            json!({ "source": "email-footer", "destinations": 1, "hits": 1 }),
+            json!({ "source": null, "destinations": 1, "hits": 0 }),
+        ],
+        sources.unwrap()
+    );
+
+    let (status_code, heatmap, _) =
+        helper::destination_heatmap(&mut app, &access_token, &flyer.id, "").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!("print-flyer", heatmap.unwrap()["source"]);
+
+    let (status_code, destination, _) =
+        helper::maybe_patch_destination(&mut app, &access_token, &footer.id, r#"{ "source": "" }"#)
+            .await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(Value::Null, destination.unwrap()["source"]);
+
+    let (status_code, sources) = helper::stats_sources(&mut app, &access_token, "").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(
+        vec![
+            json!({ "source": "print-flyer", "destinations": 2, "hits": 3 }),
+            json!({ "source": null, "destinations": 2, "hits": 1 }),
+        ],
+        sources.unwrap()
+    );
+}
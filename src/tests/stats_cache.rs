@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use chrono::TimeDelta;
+use chrono::Utc;
+use serde_json::json;
+
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::hit_buffer::Hit;
+use crate::jobs::Jobs;
+use crate::stats_cache::StatsCache;
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_stats_cache(pool: sqlx::PgPool) {
+    let stats_cache = StatsCache::new(Duration::from_secs(3600));
+    let jobs = Jobs::new(None, None, None).with_stats_cache(stats_cache.clone());
+
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |settings| {
+        settings.stats_cache = stats_cache.clone();
+    })
+    .await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool)).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, flyer, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "flyer", "url": "https://www.example.com/", "source": "print-flyer" }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let flyer = flyer.unwrap();
+
+    let yesterday = (Utc::now() - TimeDelta::days(1)).naive_utc();
+    let hit = || Hit {
+        destination_id: flyer.id,
+        ip_address: None,
+        user_agent: None,
+        referrer: None,
+        created_at: yesterday,
+    };
+    database.save_hits(&[hit()]).await.unwrap();
+
+    let (status_code, sources) = helper::stats_sources(&mut app, &access_token, "").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(
+        vec![json!({ "source": "print-flyer", "destinations": 1, "hits": 1 })],
+        sources.unwrap()
+    );
+
+    // cached, the new hit is not counted yet
+    database.save_hits(&[hit()]).await.unwrap();
+
+    let (status_code, sources) = helper::stats_sources(&mut app, &access_token, "").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(
+        vec![json!({ "source": "print-flyer", "destinations": 1, "hits": 1 })],
+        sources.unwrap()
+    );
+
+    // other days are cached on their own
+    let (status_code, sources) = helper::stats_sources(&mut app, &access_token, "days=7").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(
+        vec![json!({ "source": "print-flyer", "destinations": 1, "hits": 2 })],
+        sources.unwrap()
+    );
+
+    // the rollup forgets the cached stats
+    jobs.run_all(&database).await;
+
+    let (status_code, sources) = helper::stats_sources(&mut app, &access_token, "").await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(
+        vec![json!({ "source": "print-flyer", "destinations": 1, "hits": 2 })],
+        sources.unwrap()
+    );
+}
@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use axum::http::header::CONTENT_TYPE;
+use axum::http::StatusCode;
+use http_body_util::BodyExt;
+use serde_json::Value;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_stream_events(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let (status_code, _, _) = helper::stream_events(&mut app, "").await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    let access_token = helper::login(&mut app).await;
+
+    helper::maybe_create_user_with_password(
+        &mut app,
+        &access_token,
+        "manager",
+        "manager",
+        Some("verysecret"),
+    )
+    .await;
+    let manager_access_token = helper::login_as(&mut app, "manager", "verysecret").await;
+    let (status_code, _, _) = helper::stream_events(&mut app, &manager_access_token).await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    let (status_code, headers, mut body) = helper::stream_events(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!("text/event-stream", headers[CONTENT_TYPE]);
+
+    let (_, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+    let destination = destination.unwrap();
+
+    let frame = tokio::time::timeout(Duration::from_secs(5), body.frame())
+        .await
+        .expect("Event within time")
+        .unwrap()
+        .unwrap();
+    let frame = String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap();
+
+    let field = |name: &str| {
+        frame
+            .lines()
+            .find_map(|line| line.strip_prefix(&format!("{name}: ")))
+            .unwrap()
+            .to_string()
+    };
+
+    assert_eq!("create-destination", field("event"));
+
+    let event = serde_json::from_str::<Value>(&field("data")).unwrap();
+    assert_eq!("some-slug", event["destination"]["slug"]);
+    assert_eq!(destination.id.to_string(), event["destination"]["id"]);
+    assert_eq!(event["id"], field("id"));
+}
@@ -0,0 +1,67 @@
+use axum::http::StatusCode;
+
+use crate::ingest::IngestKeys;
+use crate::tests::helper;
+
+const API_KEY: &str = "edge-key-edge-key-edge-key-edge-key";
+
+#[sqlx::test]
+async fn test_untracked(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |settings| {
+        settings.ingest_keys = IngestKeys::new(&[("cdn", API_KEY)]);
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, destination, _) = helper::maybe_create_destination_with_raw_body(
+        &mut app,
+        &access_token,
+        r#"{ "slug": "payroll", "url": "https://www.example.com/", "isUntracked": true }"#,
+        true,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let destination = destination.unwrap();
+
+    let (status_code, location, _) = helper::root(&mut app, "payroll").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(Some("https://www.example.com/".to_string()), location);
+    assert_eq!(0, helper::count_hits(&pool).await);
+
+    let (status_code, ingested, _) = helper::maybe_ingest_hits(
+        &mut app,
+        Some(API_KEY),
+        r#"{ "hits": [{ "slug": "payroll", "ipAddress": "192.0.2.1" }] }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    let ingested = ingested.unwrap();
+    assert_eq!(0, ingested["ingested"]);
+    assert_eq!(1, ingested["skipped"]);
+    assert_eq!(0, helper::count_hits(&pool).await);
+
+    let (status_code, _, message) = helper::maybe_patch_destination(
+        &mut app,
+        &access_token,
+        &destination.id,
+        r#"{ "isUntracked": null }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert!(message.unwrap().contains("isUntracked"));
+
+    let (status_code, updated, _) = helper::maybe_patch_destination(
+        &mut app,
+        &access_token,
+        &destination.id,
+        r#"{ "isUntracked": false }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(false, updated.unwrap()["isUntracked"]);
+
+    let (status_code, _, _) = helper::root(&mut app, "payroll").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(1, helper::count_hits(&pool).await);
+}
@@ -0,0 +1,111 @@
+use axum::http::StatusCode;
+use serde_json::json;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_upsert_destination(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let body = r#"{ "url": "https://www.example.com/docs", "tags": ["terraform"] }"#;
+
+    let (status_code, created, _) =
+        helper::maybe_upsert_destination(&mut app, &access_token, "docs/terraform", body).await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let created = created.unwrap();
+    assert_eq!("docs/terraform", created["slug"]);
+    assert_eq!(json!(["terraform"]), created["tags"]);
+
+    // declaring the same state again changes nothing
+    let (status_code, unchanged, _) =
+        helper::maybe_upsert_destination(&mut app, &access_token, "docs/terraform", body).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let unchanged = unchanged.unwrap();
+    assert_eq!(created["id"], unchanged["id"]);
+    assert_eq!(created["updatedAt"], unchanged["updatedAt"]);
+
+    // fields not provided get their default
+    let (status_code, updated, _) = helper::maybe_upsert_destination(
+        &mut app,
+        &access_token,
+        "docs/terraform",
+        r#"{ "url": "https://www.example.com/docs/v2", "ogTitle": "Docs" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    let updated = updated.unwrap();
+    assert_eq!(created["id"], updated["id"]);
+    assert_eq!("https://www.example.com/docs/v2", updated["url"]);
+    assert_eq!("Docs", updated["ogTitle"]);
+    assert_eq!(json!([]), updated["tags"]);
+
+    // the domain is its own namespace
+    let (status_code, on_domain, _) = helper::maybe_upsert_destination(
+        &mut app,
+        &access_token,
+        "docs/terraform",
+        r#"{ "url": "https://www.example.com/docs", "domain": "example.com" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    assert_ne!(created["id"], on_domain.unwrap()["id"]);
+
+    let permanent = r#"{ "url": "https://www.example.com/docs/v2", "isPermanent": true }"#;
+
+    let (status_code, updated, _) =
+        helper::maybe_upsert_destination(&mut app, &access_token, "docs/terraform", permanent)
+            .await;
+    assert_eq!(StatusCode::OK, status_code);
+    let updated = updated.unwrap();
+    assert_eq!(true, updated["isPermanent"]);
+    assert_eq!(json!(null), updated["ogTitle"]);
+
+    let (status_code, _, _) =
+        helper::maybe_upsert_destination(&mut app, &access_token, "docs/terraform", permanent)
+            .await;
+    assert_eq!(StatusCode::OK, status_code);
+
+    let (status_code, _, error) = helper::maybe_upsert_destination(
+        &mut app,
+        &access_token,
+        "docs/terraform",
+        r#"{ "url": "https://www.example.com/docs/v3", "isPermanent": true }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("permanent_immutable", error.unwrap().code);
+
+    let (_, gone, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "gone",
+        "https://www.example.com/gone",
+    )
+    .await;
+
+    let (status_code, _) =
+        helper::myabe_delete_destination(&mut app, &access_token, &gone.unwrap().id).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    let (status_code, _, error) = helper::maybe_upsert_destination(
+        &mut app,
+        &access_token,
+        "gone",
+        r#"{ "url": "https://www.example.com/gone" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("slug_deleted", error.unwrap().code);
+
+    let (status_code, _, error) = helper::maybe_upsert_destination(
+        &mut app,
+        &access_token,
+        "api/docs",
+        r#"{ "url": "https://www.example.com/docs" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("slug_reserved", error.unwrap().code);
+}
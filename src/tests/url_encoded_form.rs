@@ -0,0 +1,46 @@
+use axum::http::StatusCode;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_url_encoded_form(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, destination, _) = helper::maybe_create_destination_with_url_encoded_form(
+        &mut app,
+        &access_token,
+        "slug=launch&url=https%3A%2F%2Fwww.example.com%2F%3Fa%3D1%26b%3D2&isPermanent=true",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let destination = destination.unwrap();
+    assert_eq!("launch", destination.slug);
+    assert_eq!("https://www.example.com/?a=1&b=2", destination.url);
+
+    let (status_code, location, _) = helper::root(&mut app, "launch").await;
+    assert_eq!(StatusCode::PERMANENT_REDIRECT, status_code);
+    assert_eq!(
+        Some("https://www.example.com/?a=1&b=2".to_string()),
+        location
+    );
+
+    let (status_code, _, error) = helper::maybe_create_destination_with_url_encoded_form(
+        &mut app,
+        &access_token,
+        "slug=no-url",
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("Data error", error.unwrap().error);
+
+    let (status_code, _, error) = helper::maybe_create_destination_with_url_encoded_form(
+        &mut app,
+        &access_token,
+        "slug=not-permanent&url=https%3A%2F%2Fwww.example.com%2F&isPermanent=maybe",
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("Data error", error.unwrap().error);
+}
@@ -0,0 +1,207 @@
+use axum::extract::RawQuery;
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::Json;
+use axum::Router;
+use serde_json::json;
+use serde_json::Value;
+
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::jobs::Job;
+use crate::jobs::Jobs;
+use crate::jobs::Outcome;
+use crate::reputation::UrlReputation;
+use crate::tests::helper;
+
+/// Serve an API like Safe Browsing, flagging URLs with `malware` as malware; returns its endpoint
+fn serve_safe_browsing() -> String {
+    async fn find(
+        RawQuery(query): RawQuery,
+        Json(body): Json<Value>,
+    ) -> Result<Json<Value>, StatusCode> {
+        if query.as_deref() != Some("key=some-key") {
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        let matches = body["threatInfo"]["threatEntries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|entry| entry["url"].as_str().unwrap().contains("malware"))
+            .map(|entry| json!({ "threatType": "MALWARE", "threat": entry }))
+            .collect::<Vec<_>>();
+
+        // safe URLs get an empty object
+        Ok(Json(if matches.is_empty() {
+            json!({})
+        } else {
+            json!({ "matches": matches })
+        }))
+    }
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let app = Router::new().route("/v4/threatMatches:find", post(find));
+
+    tokio::spawn(axum_server::from_tcp(listener).serve(app.into_make_service()));
+
+    format!("http://{address}/v4/threatMatches:find")
+}
+
+#[sqlx::test]
+async fn test_url_reputation_blocklist(pool: sqlx::PgPool) {
+    let path = std::env::temp_dir().join(format!("shurly-{}.txt", uuid::Uuid::new_v4()));
+    std::fs::write(&path, "# phishing\nevil.example.com\n").unwrap();
+
+    let url_reputation = UrlReputation::default().with_blocklist(&path);
+    let jobs = Jobs::new(None, None, None).with_url_reputation(url_reputation.clone());
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |settings| {
+        settings.url_reputation = url_reputation.clone();
+        settings.jobs = jobs.clone();
+    })
+    .await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool)).await;
+
+    let access_token = helper::login(&mut app).await;
+
+    // listed hosts are refused
+    let (status_code, _, message) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "login",
+        "https://www.evil.example.com/login",
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("URL is flagged as unsafe".to_string()), message);
+
+    let (status_code, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "download",
+        "https://downloads.example.org/setup.exe",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let destination_id = destination.unwrap().id;
+
+    let outcomes = jobs.run_all(&database).await;
+    assert_eq!(
+        Some(&(
+            Job::CheckUrlReputation,
+            Outcome::Succeeded("Checked 1 destination(s), flagged 0".to_string())
+        )),
+        outcomes.last()
+    );
+
+    // the updated feed disables the existing destination
+    std::fs::write(&path, "evil.example.com\nhttps://downloads.example.org/\n").unwrap();
+
+    let outcomes = jobs.run_all(&database).await;
+    assert_eq!(
+        Some(&(
+            Job::CheckUrlReputation,
+            Outcome::Succeeded("Checked 1 destination(s), flagged 1".to_string())
+        )),
+        outcomes.last()
+    );
+
+    let (status_code, _, _) = helper::root(&mut app, "download").await;
+    assert_eq!(StatusCode::GONE, status_code);
+
+    let (status_code, response) = helper::graphql(
+        &mut app,
+        &access_token,
+        &format!(r#"{{ destination(id: "{destination_id}") {{ flaggedReason }} }}"#),
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(
+        "Listed on the blocklist",
+        response.unwrap()["data"]["destination"]["flaggedReason"]
+    );
+
+    // disabled destinations are not checked again
+    let outcomes = jobs.run_all(&database).await;
+    assert_eq!(
+        Some(&(
+            Job::CheckUrlReputation,
+            Outcome::Succeeded("Checked 0 destination(s), flagged 0".to_string())
+        )),
+        outcomes.last()
+    );
+
+    // a new URL enables the destination again
+    let (status_code, destination, _) = helper::maybe_patch_destination(
+        &mut app,
+        &access_token,
+        &destination_id,
+        r#"{ "url": "https://www.example.org/setup.exe" }"#,
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status_code);
+    assert_eq!(Value::Null, destination.unwrap()["flaggedReason"]);
+
+    let (status_code, location, _) = helper::root(&mut app, "download").await;
+    assert_eq!(StatusCode::TEMPORARY_REDIRECT, status_code);
+    assert_eq!(
+        Some("https://www.example.org/setup.exe".to_string()),
+        location
+    );
+
+    // the system flagged the destination on the audit trail
+    let (_, verification) = helper::verify_audit_trail(&mut app, &access_token).await;
+    assert!(verification.unwrap().is_valid);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[sqlx::test]
+async fn test_url_reputation_safe_browsing(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |settings| {
+        settings.url_reputation =
+            UrlReputation::default().with_safe_browsing(&serve_safe_browsing(), "some-key");
+    })
+    .await;
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, message) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "malware",
+        "https://www.example.com/malware.exe",
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!(Some("URL is flagged as unsafe".to_string()), message);
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "safe",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+
+    // an unreachable API does not refuse URLs
+    let mut app = helper::setup_test_app_with_root_settings(pool, |settings| {
+        settings.url_reputation =
+            UrlReputation::default().with_safe_browsing("http://127.0.0.1:1/", "some-key");
+    })
+    .await;
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "malware",
+        "https://www.example.com/malware.exe",
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+}
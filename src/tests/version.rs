@@ -0,0 +1,22 @@
+use axum::http::StatusCode;
+
+use crate::tests::helper;
+
+#[sqlx::test]
+async fn test_version(pool: sqlx::PgPool) {
+    let mut app = helper::setup_test_app(pool).await;
+
+    let (status_code, _) = helper::version(&mut app, "").await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, version) = helper::version(&mut app, &access_token).await;
+    assert_eq!(StatusCode::OK, status_code);
+
+    let version = version.unwrap();
+    assert_eq!(env!("CARGO_PKG_VERSION"), version["version"]);
+    assert!(!version["gitSha"].as_str().unwrap().is_empty());
+    assert!(version["buildTimestamp"].is_string());
+    assert!(version["features"].is_array());
+}
@@ -0,0 +1,274 @@
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use axum::http::HeaderMap;
+use axum::http::StatusCode;
+use axum::routing::post;
+use axum::Extension;
+use axum::Router;
+use serde_json::Value;
+
+use crate::database::Database;
+use crate::database::DatabaseConfig;
+use crate::tests::helper;
+use crate::webhooks::sign;
+use crate::webhooks::Webhooks;
+
+/// Requests received by the webhook, with their headers and body
+type Received = Arc<Mutex<Vec<(HeaderMap, String)>>>;
+
+/// Serve a webhook failing the first request, returns its URL
+fn serve_webhook(received: Received) -> String {
+    async fn receive(
+        Extension(received): Extension<Received>,
+        Extension(requests): Extension<Arc<AtomicUsize>>,
+        headers: HeaderMap,
+        body: String,
+    ) -> StatusCode {
+        received.lock().unwrap().push((headers, body));
+
+        if requests.fetch_add(1, Ordering::Relaxed) == 0 {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::OK
+        }
+    }
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let app = Router::new()
+        .route("/hook", post(receive))
+        .layer(Extension(received))
+        .layer(Extension(Arc::new(AtomicUsize::new(0))));
+
+    tokio::spawn(axum_server::from_tcp(listener).serve(app.into_make_service()));
+
+    format!("http://{address}/hook")
+}
+
+async fn count_deliveries(pool: &sqlx::PgPool) -> i64 {
+    sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM webhook_deliveries")
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test]
+async fn test_webhooks(pool: sqlx::PgPool) {
+    // without the deliveries in the background
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |_| {}).await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool.clone())).await;
+    let webhooks = Webhooks::default();
+
+    let received = Received::default();
+    let url = serve_webhook(Arc::clone(&received));
+
+    let (status_code, _) = helper::list_webhooks(&mut app, "").await;
+    assert_eq!(StatusCode::FORBIDDEN, status_code);
+
+    let access_token = helper::login(&mut app).await;
+
+    let (status_code, _, error) =
+        helper::maybe_create_webhook(&mut app, &access_token, "ftp://cms.acme.com/").await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("Invalid URL scheme", error.unwrap());
+
+    let (status_code, webhook, _) =
+        helper::maybe_create_webhook(&mut app, &access_token, &url).await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let webhook = webhook.unwrap();
+    let webhook_id = webhook["id"].as_str().unwrap();
+    let secret = webhook["secret"].as_str().unwrap();
+
+    // the secret is only shown once
+    let (_, list) = helper::list_webhooks(&mut app, &access_token).await;
+    let list = list.unwrap();
+    assert_eq!(1, list.as_array().unwrap().len());
+    assert!(list[0].get("secret").is_none());
+
+    helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+
+    // the first attempt fails
+    assert_eq!(1, webhooks.deliver(&database).await);
+    assert_eq!(0, webhooks.deliver(&database).await);
+
+    let (status_code, deliveries) =
+        helper::webhook_deliveries(&mut app, &access_token, webhook_id).await;
+    assert_eq!(StatusCode::OK, status_code);
+    let deliveries = deliveries.unwrap();
+    assert_eq!(1, deliveries.as_array().unwrap().len());
+    assert_eq!("create-destination", deliveries[0]["event"]);
+    assert_eq!("pending", deliveries[0]["status"]);
+    assert_eq!(1, deliveries[0]["attempts"]);
+    assert_eq!(500, deliveries[0]["statusCode"]);
+
+    // the retry is due right away
+    sqlx::query("UPDATE webhook_deliveries SET next_attempt_at = CURRENT_TIMESTAMP")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    assert_eq!(1, webhooks.deliver(&database).await);
+
+    let (_, deliveries) = helper::webhook_deliveries(&mut app, &access_token, webhook_id).await;
+    let deliveries = deliveries.unwrap();
+    assert_eq!("delivered", deliveries[0]["status"]);
+    assert_eq!(2, deliveries[0]["attempts"]);
+    assert_eq!(200, deliveries[0]["statusCode"]);
+    assert_eq!(Value::Null, deliveries[0]["error"]);
+
+    {
+        let received = received.lock().unwrap();
+        assert_eq!(2, received.len());
+
+        let (headers, body) = &received[1];
+        assert_eq!("create-destination", headers["x-shurly-event"]);
+        assert_eq!(
+            received[0].0["x-shurly-delivery"],
+            headers["x-shurly-delivery"]
+        );
+        assert_eq!(
+            sign(secret, body),
+            headers["x-shurly-signature"].to_str().unwrap()
+        );
+
+        let event = serde_json::from_str::<Value>(body).unwrap();
+        assert_eq!("some-slug", event["destination"]["slug"]);
+        assert_eq!(deliveries[0]["payload"], event);
+    }
+
+    // deleted webhooks get no more deliveries
+    let status_code = helper::maybe_delete_webhook(&mut app, &access_token, webhook_id).await;
+    assert_eq!(StatusCode::NO_CONTENT, status_code);
+
+    helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "other-slug",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(1, count_deliveries(&pool).await);
+
+    let (status_code, _) = helper::webhook_deliveries(&mut app, &access_token, webhook_id).await;
+    assert_eq!(StatusCode::NOT_FOUND, status_code);
+}
+
+#[sqlx::test]
+async fn test_hit_webhooks(pool: sqlx::PgPool) {
+    // without the deliveries in the background
+    let mut app = helper::setup_test_app_with_root_settings(pool.clone(), |_| {}).await;
+    let database = Database::from_config(DatabaseConfig::ExistingConnection(pool.clone())).await;
+    let webhooks = Webhooks::default();
+
+    let received = Received::default();
+    let url = serve_webhook(Arc::clone(&received));
+
+    let access_token = helper::login(&mut app).await;
+
+    let (_, destination, _) = helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "some-slug",
+        "https://www.example.com/",
+    )
+    .await;
+    let destination_id = destination.unwrap().id.to_string();
+    let destination_id = destination_id.as_str();
+
+    let (status_code, _, error) =
+        helper::maybe_create_destination_webhook(&mut app, &access_token, &url, None, true).await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("Only hits can be batched", error.unwrap());
+
+    let (status_code, _, error) = helper::maybe_create_destination_webhook(
+        &mut app,
+        &access_token,
+        &url,
+        Some("00000000-0000-0000-0000-000000000000"),
+        false,
+    )
+    .await;
+    assert_eq!(StatusCode::BAD_REQUEST, status_code);
+    assert_eq!("Destination not found", error.unwrap());
+
+    let (status_code, webhook, _) = helper::maybe_create_destination_webhook(
+        &mut app,
+        &access_token,
+        &url,
+        Some(destination_id),
+        false,
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status_code);
+    let webhook = webhook.unwrap();
+    assert_eq!(destination_id, webhook["destinationId"]);
+    assert_eq!(false, webhook["isBatched"]);
+    let webhook_id = webhook["id"].as_str().unwrap();
+
+    let (_, batched_webhook, _) = helper::maybe_create_destination_webhook(
+        &mut app,
+        &access_token,
+        &url,
+        Some(destination_id),
+        true,
+    )
+    .await;
+    let batched_webhook = batched_webhook.unwrap();
+    let batched_webhook_id = batched_webhook["id"].as_str().unwrap();
+
+    // the webhooks of a destination get no changes
+    helper::maybe_create_destination(
+        &mut app,
+        &access_token,
+        "other-slug",
+        "https://www.example.com/",
+    )
+    .await;
+    assert_eq!(0, count_deliveries(&pool).await);
+
+    helper::root(&mut app, "some-slug").await;
+    helper::root(&mut app, "some-slug").await;
+    helper::root(&mut app, "other-slug").await;
+
+    // a delivery per hit right away
+    assert_eq!(2, webhooks.deliver(&database).await);
+
+    let (_, deliveries) = helper::webhook_deliveries(&mut app, &access_token, webhook_id).await;
+    let deliveries = deliveries.unwrap();
+    assert_eq!(2, deliveries.as_array().unwrap().len());
+    assert_eq!("hit", deliveries[0]["event"]);
+    assert_eq!(
+        "some-slug",
+        deliveries[0]["payload"]["destination"]["slug"]
+    );
+    assert_eq!(1, deliveries[0]["payload"]["hits"].as_array().unwrap().len());
+
+    // the batched hits wait for the job
+    let (_, deliveries) =
+        helper::webhook_deliveries(&mut app, &access_token, batched_webhook_id).await;
+    assert!(deliveries.unwrap().as_array().unwrap().is_empty());
+
+    assert_eq!(1, database.queue_webhook_hits(true).await.unwrap());
+    assert_eq!(0, database.queue_webhook_hits(true).await.unwrap());
+
+    let (_, deliveries) =
+        helper::webhook_deliveries(&mut app, &access_token, batched_webhook_id).await;
+    let deliveries = deliveries.unwrap();
+    assert_eq!(1, deliveries.as_array().unwrap().len());
+    assert_eq!(2, deliveries[0]["payload"]["hits"].as_array().unwrap().len());
+    assert_eq!(
+        Value::Null,
+        deliveries[0]["payload"]["hits"][0]["ipAddress"]
+    );
+}
@@ -0,0 +1,289 @@
+//! Serving HTTPS directly, for deployments without a proxy in front of Shurly
+//!
+//! TLS is disabled by default, Shurly serves plain HTTP. With `TLS_CERTIFICATE` and
+//! `TLS_PRIVATE_KEY` (paths to PEM files) the given certificate is used. With `ACME_DOMAINS` the
+//! certificates are requested (and renewed) automatically via ACME, with the TLS-ALPN-01
+//! challenge on the address Shurly binds to, which should be reachable on port 443.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
+use futures_util::StreamExt;
+use rustls_acme::caches::DirCache;
+use rustls_acme::AcmeConfig;
+use tokio::net::TcpListener;
+
+use crate::graceful_shutdown;
+use crate::utils::env_var_optional;
+
+/// Directory of the ACME server, Let's Encrypt by default
+const DEFAULT_ACME_DIRECTORY: &str = rustls_acme::acme::LETS_ENCRYPT_PRODUCTION_DIRECTORY;
+
+/// How Shurly serves its connections
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Tls {
+    /// Plain HTTP
+    Disabled,
+
+    /// HTTPS with a certificate from disk
+    Certificate {
+        /// Path to the certificate (chain), PEM encoded
+        certificate: PathBuf,
+
+        /// Path to the private key, PEM encoded
+        private_key: PathBuf,
+    },
+
+    /// HTTPS with certificates requested via ACME
+    Acme {
+        /// Domains to request the certificate for
+        domains: Vec<String>,
+
+        /// Email addresses of the ACME account, for notices about the certificates
+        contacts: Vec<String>,
+
+        /// Directory to store the account and certificates in, between restarts
+        cache_directory: Option<PathBuf>,
+
+        /// URL of the directory of the ACME server
+        directory: String,
+    },
+}
+
+impl Tls {
+    /// Setup TLS based on the `TLS_CERTIFICATE`, `TLS_PRIVATE_KEY`, `ACME_DOMAINS`,
+    /// `ACME_CONTACT`, `ACME_CACHE_DIRECTORY` and `ACME_DIRECTORY` environment variables
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when only half of the certificate is configured, or when both a
+    /// certificate and ACME are configured
+    pub fn from_environment() -> anyhow::Result<Self> {
+        Self::parse(
+            env_var_optional("TLS_CERTIFICATE"),
+            env_var_optional("TLS_PRIVATE_KEY"),
+            env_var_optional("ACME_DOMAINS"),
+            env_var_optional("ACME_CONTACT"),
+            env_var_optional("ACME_CACHE_DIRECTORY"),
+            env_var_optional("ACME_DIRECTORY"),
+        )
+    }
+
+    /// Parse the TLS settings
+    fn parse(
+        certificate: Option<String>,
+        private_key: Option<String>,
+        domains: Option<String>,
+        contacts: Option<String>,
+        cache_directory: Option<String>,
+        directory: Option<String>,
+    ) -> anyhow::Result<Self> {
+        let domains = split_list(domains);
+
+        match (certificate, private_key) {
+            (Some(_), Some(_)) if !domains.is_empty() => Err(anyhow!(
+                "Invalid TLS: expected either `TLS_CERTIFICATE` or `ACME_DOMAINS`, not both"
+            )),
+            (Some(certificate), Some(private_key)) => Ok(Self::Certificate {
+                certificate: certificate.into(),
+                private_key: private_key.into(),
+            }),
+            (Some(_), None) | (None, Some(_)) => Err(anyhow!(
+                "Invalid TLS: expected both `TLS_CERTIFICATE` and `TLS_PRIVATE_KEY`"
+            )),
+            (None, None) if domains.is_empty() => Ok(Self::Disabled),
+            (None, None) => Ok(Self::Acme {
+                domains,
+                contacts: split_list(contacts),
+                cache_directory: cache_directory.map(PathBuf::from),
+                directory: directory.unwrap_or_else(|| DEFAULT_ACME_DIRECTORY.to_string()),
+            }),
+        }
+    }
+
+    /// TLS of the separate API address, the same certificate as the root
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` with ACME, its challenge is only answered on the address of the root
+    pub fn for_api(&self) -> anyhow::Result<Self> {
+        match self {
+            Self::Acme { .. } => Err(anyhow!(
+                "Invalid TLS: `ACME_DOMAINS` is not available with `API_ADDRESS`"
+            )),
+            tls => Ok(tls.clone()),
+        }
+    }
+
+    /// Serve the app on the address, until a terminate signal is received
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the address can not be bound to, or the certificate can not be
+    /// loaded
+    pub async fn serve(self, address: SocketAddr, app: Router) -> anyhow::Result<()> {
+        let make_service = app.into_make_service_with_connect_info::<SocketAddr>();
+
+        match self {
+            Self::Disabled => {
+                let listener = TcpListener::bind(address).await?;
+
+                axum::serve(listener, make_service)
+                    .with_graceful_shutdown(graceful_shutdown::handler())
+                    .await?;
+            }
+            Self::Certificate {
+                certificate,
+                private_key,
+            } => {
+                install_crypto_provider();
+
+                let config = RustlsConfig::from_pem_file(&certificate, &private_key)
+                    .await
+                    .map_err(|err| {
+                        anyhow!(
+                            "Invalid TLS_CERTIFICATE or TLS_PRIVATE_KEY: {}, {}: {err}",
+                            certificate.display(),
+                            private_key.display(),
+                        )
+                    })?;
+
+                axum_server::bind_rustls(address, config)
+                    .handle(shutdown_handle())
+                    .serve(make_service)
+                    .await?;
+            }
+            Self::Acme {
+                domains,
+                contacts,
+                cache_directory,
+                directory,
+            } => {
+                install_crypto_provider();
+
+                let mut state = AcmeConfig::new(domains)
+                    .contact(contacts.iter().map(|contact| format!("mailto:{contact}")))
+                    .cache_option(cache_directory.map(DirCache::new))
+                    .directory(directory)
+                    .state();
+
+                let acceptor = state.axum_acceptor(state.default_rustls_config());
+
+                // drives the ordering and renewal of the certificates
+                tokio::spawn(async move {
+                    while let Some(event) = state.next().await {
+                        match event {
+                            Ok(event) => tracing::info!("ACME: {event:?}"),
+                            Err(err) => tracing::error!("ACME failed: {err:?}"),
+                        }
+                    }
+                });
+
+                axum_server::bind(address)
+                    .acceptor(acceptor)
+                    .handle(shutdown_handle())
+                    .serve(make_service)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Split a comma separated list, without empty entries
+fn split_list(list: Option<String>) -> Vec<String> {
+    list.map(|list| {
+        list.split(',')
+            .map(|entry| entry.trim().to_string())
+            .filter(|entry| !entry.is_empty())
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Install the cryptography of rustls, ignored when already installed
+fn install_crypto_provider() {
+    rustls::crypto::ring::default_provider()
+        .install_default()
+        .ok();
+}
+
+/// Handle shutting down the server gracefully, when a terminate signal is received
+fn shutdown_handle() -> Handle {
+    let handle = Handle::new();
+
+    let shutdown = handle.clone();
+    tokio::spawn(async move {
+        graceful_shutdown::handler().await;
+
+        // wait for all connections to finish, like without TLS
+        shutdown.graceful_shutdown(None);
+    });
+
+    handle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse with the strings of the environment
+    fn parse(
+        certificate: Option<&str>,
+        private_key: Option<&str>,
+        domains: Option<&str>,
+    ) -> anyhow::Result<Tls> {
+        Tls::parse(
+            certificate.map(String::from),
+            private_key.map(String::from),
+            domains.map(String::from),
+            Some("admin@example.com".to_string()),
+            None,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Tls::Disabled, parse(None, None, None).unwrap());
+        assert_eq!(Tls::Disabled, parse(None, None, Some(" , ")).unwrap());
+
+        assert_eq!(
+            Tls::Certificate {
+                certificate: "cert.pem".into(),
+                private_key: "key.pem".into(),
+            },
+            parse(Some("cert.pem"), Some("key.pem"), None).unwrap()
+        );
+
+        assert_eq!(
+            Tls::Acme {
+                domains: vec!["example.com".to_string(), "www.example.com".to_string()],
+                contacts: vec!["admin@example.com".to_string()],
+                cache_directory: None,
+                directory: DEFAULT_ACME_DIRECTORY.to_string(),
+            },
+            parse(None, None, Some("example.com, www.example.com")).unwrap()
+        );
+
+        assert!(parse(Some("cert.pem"), None, None).is_err());
+        assert!(parse(None, Some("key.pem"), None).is_err());
+        assert!(parse(Some("cert.pem"), Some("key.pem"), Some("example.com")).is_err());
+    }
+
+    #[test]
+    fn test_for_api() {
+        assert_eq!(Tls::Disabled, Tls::Disabled.for_api().unwrap());
+
+        let certificate = parse(Some("cert.pem"), Some("key.pem"), None).unwrap();
+        assert_eq!(certificate, certificate.for_api().unwrap());
+
+        let acme = parse(None, None, Some("example.com")).unwrap();
+        assert!(acme.for_api().is_err());
+    }
+}
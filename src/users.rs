@@ -1,19 +1,27 @@
 //! Users
 
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::anyhow;
 use anyhow::Result;
 use chrono::naive::NaiveDateTime;
+use clap::ValueEnum;
 use serde::Deserialize;
 use serde::Serialize;
 use uuid::Uuid;
 
 use crate::database::CreateUserValues;
 use crate::database::Database;
+use crate::notifier::is_valid_address;
 use crate::password::generate;
 use crate::password::hash;
+use crate::password::is_valid_hash;
+use crate::utils::env_var_optional;
 use crate::utils::env_var_or_else;
 
 /// User roles
-#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, ValueEnum)]
 #[serde(rename_all = "kebab-case")]
 pub enum Role {
     /// Manage users/destinations/notes
@@ -40,6 +48,9 @@ pub struct User {
     /// Role of the user
     pub role: Role,
 
+    /// Email address, for the notifications
+    pub email: Option<String>,
+
     /// Creation date
     #[allow(dead_code)] // used by sqlx
     pub created_at: NaiveDateTime,
@@ -54,17 +65,189 @@ pub struct User {
 
 impl User {
     /// Is the user soft-deleted?
+    #[must_use]
     pub fn is_deleted(&self) -> bool {
         self.deleted_at.is_some()
     }
 }
 
+/// Attempts to use the token of a deleted user, from a single IP address
+#[derive(Clone, Debug)]
+pub struct DeletedUserToken {
+    /// ID of the deleted user
+    pub user_id: Uuid,
+
+    /// Username of the deleted user
+    pub username: String,
+
+    /// IP address of the client presenting the token, when known
+    pub ip_address: Option<String>,
+
+    /// Number of attempts registered on the audit trail
+    pub attempts: i64,
+
+    /// Moment of the first registered attempt
+    pub first_attempt_at: NaiveDateTime,
+
+    /// Moment of the last registered attempt
+    pub last_attempt_at: NaiveDateTime,
+}
+
+/// Key of the advisory lock of the creation of the initial users, unique within the database
+const INITIAL_USERS_LOCK: i64 = 0x5348_5552_4c59_0002;
+
+/// Users created on the first run, from a JSON or TOML file, instead of the single initial user
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InitialUsers {
+    /// The users, at least a single admin
+    users: Vec<InitialUser>,
+}
+
+/// A single initial user
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct InitialUser {
+    /// Username
+    username: String,
+
+    /// Role, a manager by default
+    #[serde(default = "default_role")]
+    role: Role,
+
+    /// Argon2 hash of the password, in the PHC string format; the password itself is not in the
+    /// file
+    hashed_password: String,
+
+    /// Email address, for the notifications
+    #[serde(default)]
+    email: Option<String>,
+}
+
+/// Users get the least privileges by default
+fn default_role() -> Role {
+    Role::Manager
+}
+
+impl InitialUsers {
+    /// Read the initial users from a file, TOML when the extension is `.toml` and JSON otherwise
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when the file can not be read or is invalid
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|err| anyhow!("Invalid initial users: {}, {err}", path.display()))?;
+
+        let initial_users: Self = if path
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+        {
+            toml::from_str(&content).map_err(|err| anyhow!("{err}"))
+        } else {
+            serde_json::from_str(&content).map_err(|err| anyhow!("{err}"))
+        }
+        .and_then(Self::validated)
+        .map_err(|err| anyhow!("Invalid initial users: {}, {err}", path.display()))?;
+
+        Ok(initial_users)
+    }
+
+    /// Validate the users: unique usernames, valid hashes and email addresses, and at least a
+    /// single admin to manage the rest
+    fn validated(self) -> Result<Self> {
+        let mut usernames = HashSet::new();
+
+        for user in &self.users {
+            if user.username.trim().is_empty() {
+                return Err(anyhow!("Username is empty"));
+            }
+
+            if !usernames.insert(user.username.as_str()) {
+                return Err(anyhow!("Username `{}` is not unique", user.username));
+            }
+
+            if !is_valid_hash(&user.hashed_password) {
+                return Err(anyhow!(
+                    "Hashed password of `{}` is not an Argon2 hash",
+                    user.username
+                ));
+            }
+
+            if user
+                .email
+                .as_deref()
+                .is_some_and(|email| !is_valid_address(email))
+            {
+                return Err(anyhow!("Email address of `{}` is invalid", user.username));
+            }
+        }
+
+        if !self
+            .users
+            .iter()
+            .any(|user| matches!(user.role, Role::Admin))
+        {
+            return Err(anyhow!("At least a single admin is needed"));
+        }
+
+        Ok(self)
+    }
+
+    /// Create the users, all or nothing, when there are no users yet
+    ///
+    /// Instances starting at the same time create the users only once. Returns the number of
+    /// created users, `None` when there already are users.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` when a user could not be created, none of the users are created then
+    pub async fn create(&self, database: &Database) -> Result<Option<usize>> {
+        let session_ids = self
+            .users
+            .iter()
+            .map(|_| Uuid::new_v4())
+            .collect::<Vec<_>>();
+
+        let values = self
+            .users
+            .iter()
+            .zip(&session_ids)
+            .map(|(user, session_id)| CreateUserValues {
+                session_id,
+                role: user.role,
+                username: &user.username,
+                hashed_password: &user.hashed_password,
+                email: user.email.as_deref(),
+            })
+            .collect::<Vec<_>>();
+
+        Ok(database
+            .create_initial_users(INITIAL_USERS_LOCK, &values)
+            .await?)
+    }
+}
+
 /// On startup, ensure there is at least a single user
 ///
-/// This user will be created with the credentials from the `INITIAL_USERNAME` and
+/// When the `INITIAL_USER_FILE` environment variable is set, the users of that file are created
+/// instead, see [`InitialUsers`]. The file is read on every startup, an invalid file
+/// is found before it is needed.
+///
+/// Otherwise this user will be created with the credentials from the `INITIAL_USERNAME` and
 /// `INITIAL_PASSWORD` environment variables. If those are empty, randomly generated credentials
 /// will be user; these will be shown in the logs
 pub async fn ensure_initial_user(database: &Database) -> Result<()> {
+    if let Some(path) = env_var_optional("INITIAL_USER_FILE") {
+        let initial_users = InitialUsers::from_file(Path::new(&path))?;
+
+        if let Some(created) = initial_users.create(database).await? {
+            tracing::info!("Created {created} initial users from {path}");
+        }
+
+        return Ok(());
+    }
+
     let user = database.find_any_single_user().await?;
 
     if user.is_none() {
@@ -91,6 +274,7 @@ pub async fn ensure_initial_user(database: &Database) -> Result<()> {
             role: Role::Admin,
             username: &username,
             hashed_password: &hashed_password,
+            email: None,
         };
 
         database.create_user(&values).await?;
@@ -98,3 +282,76 @@ pub async fn ensure_initial_user(database: &Database) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_initial_users() {
+        let hashed_password = hash("verysecret");
+
+        let initial_users: InitialUsers = serde_json::from_str(&format!(
+            r#"{{
+                "users": [
+                    {{ "username": "ops", "role": "admin", "hashedPassword": "{hashed_password}" }},
+                    {{ "username": "editor", "hashedPassword": "{hashed_password}" }}
+                ]
+            }}"#
+        ))
+        .unwrap();
+        let initial_users = initial_users.validated().unwrap();
+        assert!(matches!(initial_users.users[1].role, Role::Manager));
+
+        let initial_users: InitialUsers = toml::from_str(&format!(
+            r#"
+                [[users]]
+                username = "ops"
+                role = "admin"
+                hashedPassword = "{hashed_password}"
+            "#
+        ))
+        .unwrap();
+        assert!(initial_users.validated().is_ok());
+
+        assert!(serde_json::from_str::<InitialUsers>(
+            r#"{ "users": [{ "username": "ops", "password": "verysecret" }] }"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_invalid_initial_users() {
+        let hashed_password = hash("verysecret");
+        let admin = format!(
+            r#"{{ "username": "ops", "role": "admin", "hashedPassword": "{hashed_password}" }}"#
+        );
+        let manager =
+            format!(r#"{{ "username": "editor", "hashedPassword": "{hashed_password}" }}"#);
+
+        for (users, error) in [
+            (format!("[{manager}]"), "At least a single admin is needed"),
+            (
+                format!("[{admin}, {admin}]"),
+                "Username `ops` is not unique",
+            ),
+            (
+                format!(
+                    r#"[{admin}, {{ "username": " ", "hashedPassword": "{hashed_password}" }}]"#
+                ),
+                "Username is empty",
+            ),
+            (
+                r#"[{ "username": "ops", "role": "admin", "hashedPassword": "verysecret" }]"#
+                    .to_string(),
+                "Hashed password of `ops` is not an Argon2 hash",
+            ),
+        ] {
+            let initial_users: InitialUsers =
+                serde_json::from_str(&format!(r#"{{ "users": {users} }}"#)).unwrap();
+
+            let err = initial_users.validated().unwrap_err();
+            assert_eq!(error, err.to_string());
+        }
+    }
+}
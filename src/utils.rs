@@ -2,17 +2,102 @@
 
 use std::env::var;
 
+use percent_encoding::utf8_percent_encode;
+use percent_encoding::AsciiSet;
+use percent_encoding::NON_ALPHANUMERIC;
+use url::Url;
+
+use crate::config;
+
+/// Characters of a slug that are kept as is when used in a URL path
+const SLUG: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'/')
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
 /// Get the value of ENV var, or a default
 ///
 /// Only when:
-/// - It is set
+/// - It is set, in the environment or the configuration file
 /// - It is not empty
 pub fn env_var_or_else(var_name: &'static str, or_else: fn() -> String) -> String {
-    if let Ok(value) = var(var_name) {
-        if !value.is_empty() {
-            return value;
-        }
+    env_var_optional(var_name).unwrap_or_else(or_else)
+}
+
+/// Get the value of ENV var, if any
+///
+/// Only when:
+/// - It is set, in the environment or the configuration file
+/// - It is not empty
+pub fn env_var_optional(var_name: &'static str) -> Option<String> {
+    var(var_name)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .or_else(|| config::value(var_name))
+        .filter(|value| !value.is_empty())
+}
+
+/// Parse a positive whole number of an environment variable
+///
+/// # Errors
+///
+/// Will return `Err` naming the variable when the value is not a positive whole number
+pub fn parse_positive(var_name: &str, value: &str) -> anyhow::Result<u64> {
+    match value.parse::<u64>() {
+        Ok(number) if number > 0 => Ok(number),
+        _ => Err(anyhow::anyhow!(
+            "Invalid {var_name}: {value}, expected a positive whole number"
+        )),
     }
+}
 
-    or_else()
+/// Parse a positive number of an environment variable, with a fraction or not
+///
+/// # Errors
+///
+/// Will return `Err` naming the variable when the value is not a positive number
+pub fn parse_positive_fraction(var_name: &str, value: &str) -> anyhow::Result<f64> {
+    match value.parse::<f64>() {
+        Ok(number) if number.is_finite() && number > 0.0 => Ok(number),
+        _ => Err(anyhow::anyhow!(
+            "Invalid {var_name}: {value}, expected a positive number"
+        )),
+    }
+}
+
+/// Is the URL a web URL, with the `http` or `https` scheme?
+///
+/// Other schemes, like `javascript:` and `data:`, could run scripts on the origin of Shurly
+pub fn is_web_url(url: &Url) -> bool {
+    matches!(url.scheme(), "http" | "https")
+}
+
+/// Percent-encode a slug to be used as the path of a URL
+pub fn encode_slug(slug: &str) -> String {
+    utf8_percent_encode(slug, SLUG).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_positive() {
+        assert_eq!(30, parse_positive("REQUEST_TIMEOUT", "30").unwrap());
+        assert!(parse_positive("REQUEST_TIMEOUT", "0").is_err());
+        assert!(parse_positive("BODY_LIMIT", "-1").is_err());
+        assert!(parse_positive("BODY_LIMIT", "64k").is_err());
+        assert!(parse_positive("CACHE_TIME_TO_LIVE", "1.5").is_err());
+    }
+
+    #[test]
+    fn test_parse_positive_fraction() {
+        assert!(parse_positive_fraction("RATE_LIMIT", "0.5").is_ok());
+        assert!(parse_positive_fraction("RATE_LIMIT", "0").is_err());
+        assert!(parse_positive_fraction("RATE_LIMIT", "-1").is_err());
+        assert!(parse_positive_fraction("RATE_LIMIT", "inf").is_err());
+        assert!(parse_positive_fraction("RATE_LIMIT", "many").is_err());
+    }
 }
@@ -0,0 +1,558 @@
+//! Outgoing webhooks, for other systems to react on changes
+//!
+//! Admins register the URLs of webhooks. Every entry on the audit trail, like a created
+//! destination or a deleted user, is an event; it is queued for every webhook as part of the
+//! entry itself. The queued deliveries are sent in the background as a JSON `POST`, signed with
+//! the secret of the webhook:
+//!
+//! - `X-Shurly-Event`, the type of the event, like `create-destination`
+//! - `X-Shurly-Delivery`, the ID of the delivery, the same for every attempt
+//! - `X-Shurly-Signature`, `sha256=` with the hex encoded HMAC-SHA256 of the body
+//!
+//! A delivery is successful with a `2xx` status code, it is retried with an exponential backoff
+//! otherwise, up to 8 attempts. Every instance sends deliveries, each delivery is claimed by a
+//! single instance at a time.
+//!
+//! A webhook can be attached to a single destination instead, it receives the `hit` events of
+//! the destination and no changes. Every hit is delivered on its own, or batched with the other
+//! hits of the last minute.
+
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+use hmac::Hmac;
+use hmac::Mac;
+use serde::Serialize;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::audit_trail::AuditTrailEntry;
+use crate::database::AuditEntry;
+use crate::database::Database;
+use crate::destinations::Destination;
+use crate::domains::Domain;
+use crate::notes::Note;
+use crate::users::Role;
+use crate::users::User;
+
+/// HMAC-SHA256, used for the signatures
+type HmacSha256 = Hmac<Sha256>;
+
+/// Time between the checks for deliveries to send
+const DELIVERY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Maximum number of deliveries to send at once
+const DELIVERY_BATCH_SIZE: i64 = 25;
+
+/// Maximum time to wait for the response of a webhook
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Number of attempts before a delivery is given up
+const MAX_ATTEMPTS: i32 = 8;
+
+/// Delay before the first retry, doubled for every next retry
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Maximum length of the stored error of a delivery
+const MAX_ERROR_LENGTH: usize = 500;
+
+/// A registered webhook
+#[derive(Clone, Debug)]
+pub struct Webhook {
+    /// Webhook ID
+    pub id: Uuid,
+
+    /// The ID of the user that registered it
+    #[allow(dead_code)] // used by sqlx
+    pub user_id: Uuid,
+
+    /// URL the events are posted to
+    pub url: String,
+
+    /// Secret of the signatures
+    pub secret: String,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+
+    /// Last updated at
+    pub updated_at: NaiveDateTime,
+
+    /// Soft-deleted at
+    #[allow(dead_code)] // used by sqlx
+    pub deleted_at: Option<NaiveDateTime>,
+
+    /// The destination of which the hits are posted, `None` for the changes
+    pub destination_id: Option<Uuid>,
+
+    /// Are the hits posted every minute, instead of right away
+    pub is_batched: bool,
+}
+
+/// A delivery of an event to a webhook
+#[derive(Clone, Debug)]
+pub struct WebhookDelivery {
+    /// Delivery ID
+    pub id: Uuid,
+
+    /// Webhook the event is delivered to
+    #[allow(dead_code)] // used by sqlx
+    pub webhook_id: Uuid,
+
+    /// Type of the event, like `create-destination`
+    pub event: String,
+
+    /// The JSON body that is posted
+    pub payload: String,
+
+    /// Number of attempts so far
+    pub attempts: i32,
+
+    /// Status code of the response of the last attempt
+    pub status_code: Option<i32>,
+
+    /// Why the last attempt failed
+    pub error: Option<String>,
+
+    /// Moment of the next attempt, `None` when delivered or given up
+    pub next_attempt_at: Option<NaiveDateTime>,
+
+    /// Moment of the successful attempt
+    pub delivered_at: Option<NaiveDateTime>,
+
+    /// Creation date
+    pub created_at: NaiveDateTime,
+
+    /// Last updated at
+    pub updated_at: NaiveDateTime,
+}
+
+/// Status of a delivery
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeliveryStatus {
+    /// Waiting for the next attempt
+    Pending,
+
+    /// Delivered successfully
+    Delivered,
+
+    /// Given up after the last attempt
+    Failed,
+}
+
+impl WebhookDelivery {
+    /// Status of the delivery
+    pub fn status(&self) -> DeliveryStatus {
+        if self.delivered_at.is_some() {
+            DeliveryStatus::Delivered
+        } else if self.next_attempt_at.is_some() {
+            DeliveryStatus::Pending
+        } else {
+            DeliveryStatus::Failed
+        }
+    }
+}
+
+/// A delivery claimed for an attempt, with its webhook
+#[derive(Debug)]
+pub struct ClaimedDelivery {
+    /// Delivery ID
+    pub id: Uuid,
+
+    /// URL of the webhook
+    pub url: String,
+
+    /// Secret of the webhook
+    pub secret: String,
+
+    /// Type of the event
+    pub event: String,
+
+    /// The JSON body that is posted
+    pub payload: String,
+
+    /// Number of attempts, including this one
+    pub attempts: i32,
+}
+
+/// Outcome of an attempt of a delivery
+#[derive(Debug, PartialEq, Eq)]
+pub struct Attempt {
+    /// Status code of the response, if any
+    pub status_code: Option<i32>,
+
+    /// Why the attempt failed, `None` when delivered
+    pub error: Option<String>,
+
+    /// Delay before the next attempt, `None` when delivered or given up
+    pub retry_in: Option<Duration>,
+}
+
+/// An event as posted to the webhooks
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event<'a> {
+    /// ID of the entry on the audit trail
+    id: Uuid,
+
+    /// Type of the event, like `create-destination`
+    #[allow(clippy::struct_field_names)] // `event` is the name of the field
+    event: &'a str,
+
+    /// The ID of the user that caused the event
+    created_by: Uuid,
+
+    /// Moment of the event
+    created_at: NaiveDateTime,
+
+    /// The user the event is about
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<EventUser<'a>>,
+
+    /// The destination the event is about, also for notes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    destination: Option<EventDestination<'a>>,
+
+    /// The note the event is about
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<EventNote<'a>>,
+
+    /// The domain the event is about
+    #[serde(skip_serializing_if = "Option::is_none")]
+    domain: Option<EventDomain<'a>>,
+}
+
+/// User of an event
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EventUser<'a> {
+    /// User ID
+    id: Uuid,
+
+    /// Username
+    username: &'a str,
+
+    /// Role of the user
+    role: Role,
+}
+
+/// Destination of an event
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EventDestination<'a> {
+    /// Destination ID
+    id: Uuid,
+
+    /// Slug of the destination
+    slug: &'a str,
+
+    /// Domain the slug is used on, all domains when empty
+    domain: Option<&'a str>,
+
+    /// URL the slug redirects to
+    url: &'a str,
+
+    /// Is the redirect permanent
+    is_permanent: bool,
+
+    /// Does the redirect need a signed link
+    is_private: bool,
+
+    /// Last updated at
+    updated_at: NaiveDateTime,
+}
+
+/// Note of an event
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EventNote<'a> {
+    /// Note ID
+    id: Uuid,
+
+    /// Content of the note
+    content: &'a str,
+}
+
+/// Domain of an event
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EventDomain<'a> {
+    /// Domain ID
+    id: Uuid,
+
+    /// Hostname of the domain
+    hostname: &'a str,
+
+    /// Redirect for slugs without a destination on the domain
+    fallback_url: Option<&'a str>,
+}
+
+impl<'a> Event<'a> {
+    /// Create the event of an entry on the audit trail
+    pub fn new(entry: &'a AuditTrailEntry, audit_entry: &'a AuditEntry<'a>) -> Self {
+        let mut event = Self {
+            id: entry.id,
+            event: &entry.entry_type,
+            created_by: entry.created_by,
+            created_at: entry.created_at,
+            user: None,
+            destination: None,
+            note: None,
+            domain: None,
+        };
+
+        match audit_entry {
+            AuditEntry::CreateUser(user)
+            | AuditEntry::ChangePassword(user)
+            | AuditEntry::DeleteUser(user)
+            | AuditEntry::ChangeQuota(user)
+            | AuditEntry::ChangeEmail(user)
+            | AuditEntry::DeletedUserToken(user) => event.user = Some(EventUser::new(user)),
+
+            AuditEntry::CreateDestination(destination)
+            | AuditEntry::UpdateDestination(destination)
+            | AuditEntry::DeleteDestination(destination)
+            | AuditEntry::FlagDestination(destination)
+            | AuditEntry::ApproveDestination(destination)
+            | AuditEntry::RejectDestination(destination) => {
+                event.destination = Some(EventDestination::new(destination));
+            }
+
+            AuditEntry::CreateNote(destination, note)
+            | AuditEntry::UpdateNote(destination, note)
+            | AuditEntry::DeleteNote(destination, note) => {
+                event.destination = Some(EventDestination::new(destination));
+                event.note = Some(EventNote::new(note));
+            }
+
+            AuditEntry::CreateDomain(domain)
+            | AuditEntry::UpdateDomain(domain)
+            | AuditEntry::DeleteDomain(domain) => event.domain = Some(EventDomain::new(domain)),
+        }
+
+        event
+    }
+
+    /// ID of the entry on the audit trail
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Type of the event
+    pub fn event(&self) -> &str {
+        self.event
+    }
+
+    /// The JSON body of the event
+    pub fn payload(&self) -> String {
+        serde_json::to_string(self).expect("Valid event")
+    }
+}
+
+impl<'a> EventUser<'a> {
+    /// Create the user of an event
+    fn new(user: &'a User) -> Self {
+        Self {
+            id: user.id,
+            username: &user.username,
+            role: user.role,
+        }
+    }
+}
+
+impl<'a> EventDestination<'a> {
+    /// Create the destination of an event
+    fn new(destination: &'a Destination) -> Self {
+        Self {
+            id: destination.id,
+            slug: &destination.slug,
+            domain: destination.domain.as_deref(),
+            url: &destination.url,
+            is_permanent: destination.is_permanent,
+            is_private: destination.is_private,
+            updated_at: destination.updated_at,
+        }
+    }
+}
+
+impl<'a> EventNote<'a> {
+    /// Create the note of an event
+    fn new(note: &'a Note) -> Self {
+        Self {
+            id: note.id,
+            content: &note.content,
+        }
+    }
+}
+
+impl<'a> EventDomain<'a> {
+    /// Create the domain of an event
+    fn new(domain: &'a Domain) -> Self {
+        Self {
+            id: domain.id,
+            hostname: &domain.hostname,
+            fallback_url: domain.fallback_url.as_deref(),
+        }
+    }
+}
+
+/// The signature of the payload, as sent in the `X-Shurly-Signature` header
+pub fn sign(secret: &str, payload: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key");
+    mac.update(payload.as_bytes());
+
+    format!("sha256={:x}", mac.finalize().into_bytes())
+}
+
+/// Sender of the deliveries of the webhooks
+#[derive(Clone, Debug)]
+pub struct Webhooks {
+    /// Client for the requests to the webhooks
+    client: reqwest::Client,
+}
+
+impl Default for Webhooks {
+    fn default() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .user_agent(concat!("Shurly/", env!("CARGO_PKG_VERSION")))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("Valid HTTP client");
+
+        Self { client }
+    }
+}
+
+impl Webhooks {
+    /// Send the deliveries that are due, once, after queueing the hits that are not batched
+    ///
+    /// Returns the number of attempted deliveries
+    pub async fn deliver(&self, database: &Database) -> usize {
+        if let Err(err) = database.queue_webhook_hits(false).await {
+            tracing::warn!("Could not queue webhook hits: {err}");
+        }
+
+        let deliveries = match database.claim_webhook_deliveries(DELIVERY_BATCH_SIZE).await {
+            Ok(deliveries) => deliveries,
+            Err(err) => {
+                tracing::warn!("Could not claim webhook deliveries: {err}");
+                return 0;
+            }
+        };
+
+        let attempts = futures_util::future::join_all(
+            deliveries.iter().map(|delivery| self.attempt(delivery)),
+        )
+        .await;
+
+        for (delivery, attempt) in deliveries.iter().zip(&attempts) {
+            if let Err(err) = database
+                .finish_webhook_delivery(&delivery.id, attempt)
+                .await
+            {
+                tracing::warn!("Could not save webhook delivery {}: {err}", delivery.id);
+            }
+        }
+
+        deliveries.len()
+    }
+
+    /// Send the deliveries in the background, until the database is closed
+    pub fn start(&self, database: &Database) {
+        let webhooks = self.clone();
+        let database = database.clone();
+
+        tokio::spawn(async move {
+            loop {
+                // keep going right away while there is a backlog
+                if webhooks.deliver(&database).await > 0 {
+                    continue;
+                }
+
+                tokio::select! {
+                    () = tokio::time::sleep(DELIVERY_INTERVAL) => {},
+                    () = database.closed() => break,
+                }
+            }
+        });
+    }
+
+    /// Make a single attempt of the delivery
+    async fn attempt(&self, delivery: &ClaimedDelivery) -> Attempt {
+        let response = self
+            .client
+            .post(&delivery.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header("X-Shurly-Event", &delivery.event)
+            .header("X-Shurly-Delivery", delivery.id.to_string())
+            .header(
+                "X-Shurly-Signature",
+                sign(&delivery.secret, &delivery.payload),
+            )
+            .body(delivery.payload.clone())
+            .send()
+            .await;
+
+        let (status_code, error) = match response {
+            Ok(response) if response.status().is_success() => {
+                (Some(response.status().as_u16()), None)
+            }
+            Ok(response) => (
+                Some(response.status().as_u16()),
+                Some(format!("Unexpected status code: {}", response.status())),
+            ),
+            Err(err) => (None, Some(err.to_string())),
+        };
+
+        if let Some(ref error) = error {
+            tracing::debug!(
+                "Webhook delivery {} failed, attempt {}: {error}",
+                delivery.id,
+                delivery.attempts
+            );
+        }
+
+        Attempt {
+            status_code: status_code.map(i32::from),
+            retry_in: error.as_ref().and_then(|_| retry_in(delivery.attempts)),
+            error: error.map(|error| error.chars().take(MAX_ERROR_LENGTH).collect()),
+        }
+    }
+}
+
+/// Delay before the next attempt, after the given number of attempts
+///
+/// Returns `None` when the delivery is given up
+fn retry_in(attempts: i32) -> Option<Duration> {
+    if attempts >= MAX_ATTEMPTS {
+        return None;
+    }
+
+    let exponent = u32::try_from(attempts.max(1) - 1).unwrap_or_default();
+
+    Some(RETRY_DELAY * 2_u32.pow(exponent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_in() {
+        assert_eq!(Some(Duration::from_secs(30)), retry_in(1));
+        assert_eq!(Some(Duration::from_secs(60)), retry_in(2));
+        assert_eq!(Some(Duration::from_secs(30 * 64)), retry_in(7));
+        assert_eq!(None, retry_in(8));
+    }
+
+    #[test]
+    fn test_sign() {
+        // the same as `echo -n '{}' | openssl dgst -sha256 -hmac verysecret`
+        assert_eq!(
+            "sha256=1c3079a8dc19a45c3a1b3e3bf69c880fbb838e1c8399b136a18e9955984dc30c",
+            sign("verysecret", "{}")
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Import of the links of YOURLS, from a SQL dump or a CSV export
+//!
+//! Every keyword becomes a destination with the same slug and creation date, the clicks are kept
+//! as hits. Links with a slug that is already taken are reported as conflicts, like the other
+//! imports.
+//!
+//! The SQL dump needs the `INSERT` statements of the `yourls_url` table (with any prefix), like
+//! `mysqldump yourls yourls_url`. The CSV export needs a header with at least the `keyword`, `url`
+//! and `timestamp` columns, `clicks` is optional and other columns are ignored.
+//!
+//! ```sh
+//! shurly import-yourls yourls.sql --as admin
+//! shurly import-yourls links.csv --as admin
+//! ```
+
+use std::io::Read;
+
+use anyhow::Result;
+
+use crate::import::inserts;
+use crate::import::Csv;
+use crate::import::ExportedLink;
+use crate::import::Table;
+
+/// Read the links of a SQL dump of YOURLS
+///
+/// # Errors
+///
+/// Will return `Err` when the SQL is invalid or misses one of the needed columns
+pub fn from_sql(sql: &str) -> Result<Vec<ExportedLink>> {
+    let table = inserts(sql, "url")?;
+
+    // the columns of `yourls_url`, for inserts without their names
+    let keyword = table.column("keyword", 0)?;
+    let url = table.column("url", 1)?;
+    let timestamp = table.column("timestamp", 3)?;
+    let clicks = table.column("clicks", 5)?;
+
+    let links = table
+        .rows
+        .iter()
+        .map(|row| ExportedLink {
+            short_link: Table::value(row, keyword).unwrap_or_default().to_string(),
+            url: Table::value(row, url).unwrap_or_default().to_string(),
+            created_at: Table::value(row, timestamp).unwrap_or_default().to_string(),
+            clicks: Table::value(row, clicks).map(ToString::to_string),
+        })
+        .collect();
+
+    Ok(links)
+}
+
+/// Read the links of a CSV export of YOURLS
+///
+/// # Errors
+///
+/// Will return `Err` when the CSV is invalid or misses one of the needed columns
+pub fn from_csv(reader: impl Read) -> Result<Vec<ExportedLink>> {
+    let csv = Csv::from_reader(reader)?;
+
+    let keyword = csv.column(&["keyword"])?;
+    let url = csv.column(&["url", "long_url"])?;
+    let timestamp = csv.column(&["timestamp", "date"])?;
+    let clicks = csv.optional_column(&["clicks"]);
+
+    let links = csv
+        .records()
+        .iter()
+        .map(|record| ExportedLink {
+            short_link: record[keyword].to_string(),
+            url: record[url].to_string(),
+            created_at: record[timestamp].to_string(),
+            clicks: clicks.map(|clicks| record[clicks].to_string()),
+        })
+        .collect();
+
+    Ok(links)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_sql() {
+        let sql = "INSERT INTO `yourls_url` VALUES \
+            ('ozh','https://ozh.org/','Ozh','2020-01-02 03:04:05','127.0.0.1',12);";
+
+        assert_eq!(
+            vec![ExportedLink {
+                short_link: "ozh".into(),
+                url: "https://ozh.org/".into(),
+                created_at: "2020-01-02 03:04:05".into(),
+                clicks: Some("12".into()),
+            }],
+            from_sql(sql).unwrap()
+        );
+
+        assert!(from_sql("INSERT INTO yourls_url (keyword) VALUES ('ozh');").is_err());
+    }
+
+    #[test]
+    fn test_from_csv() {
+        let csv = "keyword,url,title,timestamp,ip,clicks\n\
+            ozh,https://ozh.org/,Ozh,2020-01-02 03:04:05,127.0.0.1,12\n";
+
+        assert_eq!(
+            vec![ExportedLink {
+                short_link: "ozh".into(),
+                url: "https://ozh.org/".into(),
+                created_at: "2020-01-02 03:04:05".into(),
+                clicks: Some("12".into()),
+            }],
+            from_csv(csv.as_bytes()).unwrap()
+        );
+
+        assert!(from_csv("url,timestamp\n".as_bytes()).is_err());
+    }
+}